@@ -0,0 +1,111 @@
+use crate::format;
+use crate::renderer::GHOST_COPIES;
+use crate::simulation::SimulationMethod;
+
+/// Below this the advisor looks for something to change
+const TARGET_FPS: f32 = 30.0;
+/// Particle counts from which the CPU backend can't keep up
+const CPU_COMFORTABLE_COUNT: u32 = 200_000;
+/// Share of the frame spent in the simulation update above which it counts
+/// as the bottleneck
+const SIMULATION_BOUND: f32 = 0.6;
+
+/// What the advisor looks at, one frame's worth
+pub struct PerformanceSnapshot {
+    pub fps: f32,
+    /// Smoothed CPU time of the simulation update, in milliseconds
+    pub update_ms: f32,
+    pub particle_count: u32,
+    pub method: SimulationMethod,
+    pub compute_available: bool,
+    pub ghosts_shown: bool,
+    pub rdf_enabled: bool,
+    pub pair_forces: bool,
+    pub throttled: bool,
+}
+
+/// One-click fixes the app knows how to apply
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fix {
+    SwitchToCompute,
+    SetParticleCount(u32),
+    HideGhosts,
+    DisableRdf,
+}
+
+pub struct Advice {
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+/// Suggestions for the current settings and timings, most useful first
+pub fn advise(snapshot: &PerformanceSnapshot) -> Vec<Advice> {
+    let mut advice = Vec::new();
+    if snapshot.throttled {
+        advice.push(Advice {
+            message: "The frame rate is capped to save power, timings aren't representative"
+                .to_owned(),
+            fix: None,
+        });
+        return advice;
+    }
+
+    let count = format::si(snapshot.particle_count as f64);
+    if snapshot.method == SimulationMethod::Cpu
+        && snapshot.compute_available
+        && snapshot.particle_count >= CPU_COMFORTABLE_COUNT
+    {
+        advice.push(Advice {
+            message: format!(
+                "The CPU backend is the bottleneck at {count} particles, switch to Compute Shader"
+            ),
+            fix: Some(Fix::SwitchToCompute),
+        });
+    }
+
+    if snapshot.fps <= 0.0 || snapshot.fps >= TARGET_FPS {
+        return advice;
+    }
+
+    let frame_ms = 1000.0 / snapshot.fps;
+    let half_count = (snapshot.particle_count / 2).max(1);
+    if snapshot.rdf_enabled && snapshot.method == SimulationMethod::Cpu {
+        advice.push(Advice {
+            message:
+                "Measuring g(r) stalls a frame four times a second, turn it off when not reading it"
+                    .to_owned(),
+            fix: Some(Fix::DisableRdf),
+        });
+    }
+    if snapshot.update_ms > frame_ms * SIMULATION_BOUND {
+        let cause = if snapshot.pair_forces {
+            ", mostly pair interactions"
+        } else {
+            ""
+        };
+        advice.push(Advice {
+            message: format!(
+                "The simulation takes {:.1} of {frame_ms:.1} ms per frame{cause}, try {} particles",
+                snapshot.update_ms,
+                format::si(half_count as f64)
+            ),
+            fix: Some(Fix::SetParticleCount(half_count)),
+        });
+    } else if snapshot.ghosts_shown {
+        advice.push(Advice {
+            message: format!(
+                "Rendering is the bottleneck and periodic images draw every particle {GHOST_COPIES} times, hide them"
+            ),
+            fix: Some(Fix::HideGhosts),
+        });
+    } else {
+        advice.push(Advice {
+            message: format!(
+                "Rendering {count} points is the bottleneck, try {} particles",
+                format::si(half_count as f64)
+            ),
+            fix: Some(Fix::SetParticleCount(half_count)),
+        });
+    }
+    advice
+}
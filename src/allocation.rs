@@ -0,0 +1,108 @@
+use crate::settings::Settings;
+use crate::simulation::SimulationMethod;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+type ScopeResult = Pin<Box<dyn Future<Output = Option<wgpu::Error>>>>;
+
+/// Configuration to go back to if a change runs out of GPU resources
+#[derive(Clone)]
+pub struct Checkpoint {
+    /// What the change was trying to allocate, for the notice
+    pub what: String,
+    pub settings: Settings,
+    pub method: SimulationMethod,
+}
+
+pub struct AllocationFailure {
+    pub checkpoint: Checkpoint,
+    pub error: wgpu::Error,
+}
+
+/// Error scopes around changes that allocate large GPU buffers, so a failed
+/// allocation can be undone instead of taking the app down.
+///
+/// Scope results are futures that only resolve later on the web, they're
+/// polled once per frame until they do.
+#[derive(Default)]
+pub struct AllocationGuard {
+    /// Changes with scope results still to come, oldest first
+    pending: Vec<(Checkpoint, Vec<ScopeResult>)>,
+    /// Buffers that grow on the next simulation update, like neighbor grids
+    next_update: Option<Checkpoint>,
+}
+
+impl AllocationGuard {
+    pub fn begin(device: &wgpu::Device) {
+        device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+    }
+
+    /// Closes the scopes from [`Self::begin`], also watching the next
+    /// simulation update since some backends allocate lazily
+    pub fn end(&mut self, device: &wgpu::Device, checkpoint: Checkpoint) {
+        self.finish(device, checkpoint.clone());
+        self.watch_next_update(checkpoint);
+    }
+
+    /// Watches only the next simulation update, for changes that allocate
+    /// there
+    pub fn watch_next_update(&mut self, checkpoint: Checkpoint) {
+        // The oldest checkpoint is the last known good one
+        self.next_update.get_or_insert(checkpoint);
+    }
+
+    /// Opens the scopes around a simulation update if a change asked for it
+    pub fn begin_update(&self, device: &wgpu::Device) {
+        if self.next_update.is_some() {
+            Self::begin(device);
+        }
+    }
+
+    pub fn end_update(&mut self, device: &wgpu::Device) {
+        if let Some(checkpoint) = self.next_update.take() {
+            self.finish(device, checkpoint);
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// The first change whose scopes caught an error, dropping the ones after
+    /// it since they built on top of it
+    pub fn poll(&mut self) -> Option<AllocationFailure> {
+        let mut context = Context::from_waker(Waker::noop());
+        let mut failure = None;
+        for (index, (_, scopes)) in self.pending.iter_mut().enumerate() {
+            // Resolved scopes are dropped, they mustn't be polled again
+            scopes.retain_mut(|scope| match scope.as_mut().poll(&mut context) {
+                Poll::Ready(result) => {
+                    failure = failure.take().or(result.map(|error| (index, error)));
+                    false
+                }
+                Poll::Pending => true,
+            });
+            if failure.is_some() {
+                break;
+            }
+        }
+
+        if let Some((index, error)) = failure {
+            let (checkpoint, _) = self.pending.swap_remove(index);
+            self.pending.clear();
+            self.next_update = None;
+            return Some(AllocationFailure { checkpoint, error });
+        }
+        self.pending.retain(|(_, scopes)| !scopes.is_empty());
+        None
+    }
+
+    fn finish(&mut self, device: &wgpu::Device, checkpoint: Checkpoint) {
+        let validation: ScopeResult = Box::pin(device.pop_error_scope());
+        let out_of_memory: ScopeResult = Box::pin(device.pop_error_scope());
+        self.pending
+            .push((checkpoint, vec![out_of_memory, validation]));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::camera::Camera;
+use crate::simulation::forces::ForceStack;
+use crate::simulation::{Particle, SimParams};
+use glam::Vec3;
+
+pub const MAX_SAMPLES: u32 = 64;
+
+const VELOCITY_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 220, 120);
+const ACCELERATION_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 170, 60);
+const FORCE_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 90, 230);
+
+/// Sum of the field forces the integrator applies to `particle`: the force
+/// stack in proportion to its mass, but for the placed points, the mouse and
+/// the Lorentz force. Pair interactions and damping are left out, which is
+/// what the measured acceleration shows.
+// Keep in sync with the update loop in simulation/cpu.rs
+pub fn field_force(particle: &Particle, params: &SimParams, forces: &ForceStack) -> Vec3 {
+    let position = Vec3::from(particle.position);
+    let velocity = Vec3::from(particle.velocity);
+    let mut force = Vec3::ZERO;
+
+    for field in forces.active() {
+        force += field.acceleration(position, velocity, params, &[]) * particle.mass;
+    }
+
+    if params.is_mouse_dragging > 0 {
+        let dir = Vec3::from(params.mouse_position) - position;
+        let dist = dir.length();
+        if dist < params.mouse_radius * 2.0 {
+            let force_factor = (1.0 - dist / (params.mouse_radius * 2.0)).powi(2) * 2.0;
+            force += dir.normalize_or_zero() * params.mouse_force * force_factor;
+        }
+    }
+
+    if params.lorentz_enabled > 0 {
+        let electric_field = Vec3::from(params.electric_field);
+        let magnetic_field = Vec3::from(params.magnetic_field);
+        force += particle.charge * (electric_field + velocity.cross(magnetic_field));
+    }
+
+    force
+}
+
+struct Sample {
+    index: u32,
+    position: Vec3,
+    velocity: Vec3,
+    /// Measured from the change in velocity since the previous sample
+    acceleration: Vec3,
+    force: Vec3,
+}
+
+/// Velocity, acceleration and force arrows on a small, evenly spread subset
+/// of the particles, for explaining what the simulation does to them
+pub struct Annotations {
+    pub enabled: bool,
+    pub sample_count: u32,
+    pub show_velocity: bool,
+    pub show_acceleration: bool,
+    pub show_force: bool,
+    pub show_labels: bool,
+    /// World units of arrow per unit of the annotated quantity
+    pub arrow_scale: f32,
+    samples: Vec<Sample>,
+    /// Time since the last sample arrived
+    elapsed: f32,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_count: 12,
+            show_velocity: true,
+            show_acceleration: true,
+            show_force: false,
+            show_labels: true,
+            arrow_scale: 2.0,
+            samples: Vec::new(),
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Annotations {
+    /// Indices of the particles to annotate out of `particle_count`
+    pub fn sample_indices(&self, particle_count: u32) -> Vec<u32> {
+        let samples = self.sample_count.min(particle_count);
+        if samples == 0 {
+            return Vec::new();
+        }
+        let stride = (particle_count / samples).max(1);
+        (0..samples).map(|i| i * stride).collect()
+    }
+
+    /// Advances by one frame of `delta_time`, recording the state of the
+    /// particles at `indices` if the backend had a sample ready
+    pub fn update(
+        &mut self,
+        indices: &[u32],
+        sampled: Option<Vec<Particle>>,
+        params: &SimParams,
+        forces: &ForceStack,
+        delta_time: f32,
+    ) {
+        self.elapsed += delta_time;
+        let Some(particles) = sampled else {
+            return;
+        };
+        let delta_time = std::mem::take(&mut self.elapsed);
+
+        let samples = indices
+            .iter()
+            .zip(particles)
+            .map(|(&index, particle)| {
+                let velocity = Vec3::from(particle.velocity);
+                let acceleration = match self.samples.iter().find(|s| s.index == index) {
+                    Some(previous) if delta_time > 0.0 => {
+                        (velocity - previous.velocity) / delta_time
+                    }
+                    _ => Vec3::ZERO,
+                };
+                Sample {
+                    index,
+                    position: Vec3::from(particle.position),
+                    velocity,
+                    acceleration,
+                    force: field_force(&particle, params, forces),
+                }
+            })
+            .collect();
+        self.samples = samples;
+    }
+
+    pub fn show_overlay(&self, ui: &egui::Ui, rect: egui::Rect, camera: &Camera) {
+        let painter = ui.painter_at(rect);
+        let font = egui::FontId::proportional(12.0);
+
+        for sample in &self.samples {
+            let Some(start) = camera.world_to_screen(sample.position, rect) else {
+                continue;
+            };
+            painter.circle_filled(start, 3.0, egui::Color32::WHITE);
+            if self.show_labels {
+                painter.text(
+                    start + egui::vec2(-6.0, 6.0),
+                    egui::Align2::RIGHT_TOP,
+                    format!("#{}", sample.index),
+                    font.clone(),
+                    egui::Color32::WHITE,
+                );
+            }
+
+            let arrows = [
+                (self.show_velocity, sample.velocity, VELOCITY_COLOR, "v"),
+                (
+                    self.show_acceleration,
+                    sample.acceleration,
+                    ACCELERATION_COLOR,
+                    "a",
+                ),
+                (self.show_force, sample.force, FORCE_COLOR, "F"),
+            ];
+            for (shown, vector, color, name) in arrows {
+                if !shown || vector.length_squared() < 1e-8 {
+                    continue;
+                }
+                let Some(end) =
+                    camera.world_to_screen(sample.position + vector * self.arrow_scale, rect)
+                else {
+                    continue;
+                };
+                painter.arrow(start, end - start, egui::Stroke::new(1.5_f32, color));
+                if self.show_labels {
+                    painter.text(
+                        end + egui::vec2(4.0, -4.0),
+                        egui::Align2::LEFT_BOTTOM,
+                        format!("{name} {:.2}", vector.length()),
+                        font.clone(),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_nothing_without_particles() {
+        assert!(Annotations::default().sample_indices(0).is_empty());
+    }
+
+    #[test]
+    fn samples_within_the_particles() {
+        let annotations = Annotations::default();
+        for particle_count in [1, 5, 12, 13, 1000] {
+            let indices = annotations.sample_indices(particle_count);
+            assert_eq!(
+                indices.len() as u32,
+                particle_count.min(annotations.sample_count)
+            );
+            assert!(indices.iter().all(|&index| index < particle_count));
+        }
+    }
+}
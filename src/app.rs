@@ -1,53 +1,530 @@
-use crate::camera::Camera;
-use crate::custom_renderer::ClonedParticleCallback;
-use crate::renderer::ParticleRenderer;
+use crate::advisor::{self, Fix, PerformanceSnapshot};
+use crate::allocation::{AllocationGuard, Checkpoint};
+use crate::annotations::{Annotations, MAX_SAMPLES};
+use crate::benchmark::{self, Benchmark, BenchmarkResult};
+use crate::bindings::{self, Binding};
+use crate::camera::{Camera, CameraView};
+use crate::commands::{self, Command, Stats};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::contact_sheet::{
+    CELL_WIDTHS, ContactSheet, ContactSheetSettings, MAX_SWEEP_VALUES, SweepAxis,
+};
+use crate::custom_renderer::{ClonedParticleCallback, ParticleDraw, SceneCallbacks};
+use crate::device_profile::DeviceProfile;
+use crate::evolve::{self, Evolve, Track};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::export::{self, CameraPath, ExportFormat, ExportJob, ExportSettings};
+use crate::format;
+use crate::frame_copy::FrameCopy;
+use crate::inset::{Inset, InsetPose};
+use crate::layers::{Coupling, Layer, Parked};
+use crate::morph::{Easing, Morph, MorphSettings};
+use crate::node_editor;
+use crate::panels::{self, Panel, PanelBehavior};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::panorama::{self, Panorama, Projection};
+use crate::parameters::Parameter;
+use crate::power::{PowerSaver, THROTTLED_FRAME_TIME};
+use crate::presets::PRESETS;
+use crate::recording::{MEMORY_BUDGET, Playback, RECORD_INTERVALS, Recorder, Recording};
+use crate::renderer::{DrawOrder, GHOST_COPIES, ParticleRenderer};
+use crate::screensaver::{Restore, Screensaver, ScreensaverStep};
+use crate::settings::{SETTINGS_VERSION, Settings};
 
+use crate::simulation::analysis::GroupStats;
+use crate::simulation::attractors::{MAX_POINT_ATTRACTORS, PointAttractor};
+use crate::simulation::chemistry::{ReactionRule, species_name};
 use crate::simulation::compute::ComputeParticleSimulation;
 use crate::simulation::cpu::CpuParticleSimulation;
-use crate::simulation::{ParticleSimulation, SimParams, SimulationMethod, SphereGeneration};
+use crate::simulation::flip::{
+    MAX_LIQUID_RESOLUTION, MAX_PRESSURE_ITERATIONS, MIN_LIQUID_RESOLUTION,
+};
+use crate::simulation::flow_field::FlowField;
+use crate::simulation::forces::{Force, ForceLayer, MAX_FORCES};
+use crate::simulation::health::Health;
+use crate::simulation::mesh_sdf::MeshSdf;
+use crate::simulation::mpm::{MAX_MPM_RESOLUTION, MIN_MPM_RESOLUTION, MpmMaterial};
+use crate::simulation::obstacles::{MAX_OBSTACLES, Obstacle, ObstacleShape};
+use crate::simulation::particle_life::{self, InteractionMatrix};
+use crate::simulation::readback::{CopyState, ParticleCopy};
+use crate::simulation::springs::MAX_SPRING_ITERATIONS;
+use crate::simulation::strange::{STRANGE_ATTRACTORS, STRANGE_NONE, strange_attractor};
+use crate::simulation::surface::Surface;
+use crate::simulation::{
+    BOUNDARY_CONTAINER, BOUNDARY_OPEN, BOUNDARY_PERIODIC, CENTRAL_GRAVITY_RADIUS, COLOR_AGE,
+    COLOR_DENSITY, COLOR_DISPLACEMENT, Capability, EMIT_BURST, EMIT_FOUNTAIN, EMIT_SPAWN_SHAPE,
+    GRAVITY_CENTRAL_INVERSE_SQUARE, GRAVITY_CENTRAL_LINEAR, GRAVITY_UNIFORM, GenerationSettings,
+    Integrator, MAX_SPECIES, MassDistribution, Particle, ParticleSimulation, SPECIES_COLORS,
+    SimParams, SimulationMethod, SphereGeneration,
+};
+use crate::timestep::{self, FixedTimestep, MAX_SUBSTEPS};
+use crate::tutorial::OrbitTutorial;
+use crate::usage::{self, UsageStats};
+use crate::watchdog::{self, Divergence, Watchdog};
+use crate::wgsl;
 
 use egui::epaint::text::{FontInsert, InsertFontFamily};
 use glam::Vec3;
-use std::collections::HashSet;
+use rand::{Rng, SeedableRng};
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
 
+const RDF_BINS: usize = 64;
+/// Particles in a newly added layer, kept small so layers stay cheap to stack
+const LAYER_PARTICLES: u32 = 20_000;
+/// Particles of a source layer binned into the flow a coupled layer follows
+const COUPLING_SAMPLES: u32 = 4096;
+/// Playback speeds the transport bar and its shortcuts go through
+const TIME_SCALES: [f32; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 1.5, 2.0, 4.0, 8.0, 10.0];
+/// Time scale multiplier while the slow motion key is held
+const SLOW_MOTION: f32 = 0.1;
+
+type Param = Parameter<ParticleApp>;
+
+/// Every plain value the panels, the search, URL parameters and scripts edit
+#[rustfmt::skip]
+const PARAMETERS: &[Param] = &[
+    Param::slider("nbody_mass", "Total Mass (G·M)", "Barnes-Hut", Panel::Physics, |app| &mut app.settings.nbody_mass, 0.0..=5000.0)
+        .tooltip("Shared equally by all particles"),
+    Param::slider("nbody_theta", "Opening Angle θ", "Barnes-Hut", Panel::Physics, |app| &mut app.settings.nbody_theta, 0.2..=1.5)
+        .tooltip("Larger is faster but less accurate"),
+    Param::slider("nbody_softening", "Softening", "Barnes-Hut", Panel::Physics, |app| &mut app.settings.nbody_softening, 0.05..=5.0)
+        .logarithmic(),
+    Param::slider("boid_radius", "Perception Radius", "Boids", Panel::Physics, |app| &mut app.settings.boid_radius, 0.5..=20.0)
+        .tooltip("How far each boid sees its flockmates"),
+    Param::slider("boid_separation", "Separation", "Boids", Panel::Physics, |app| &mut app.settings.boid_separation, 0.0..=50.0)
+        .tooltip("Steer away from flockmates that are too close"),
+    Param::slider("boid_alignment", "Alignment", "Boids", Panel::Physics, |app| &mut app.settings.boid_alignment, 0.0..=5.0)
+        .tooltip("Match the heading of nearby flockmates"),
+    Param::slider("boid_cohesion", "Cohesion", "Boids", Panel::Physics, |app| &mut app.settings.boid_cohesion, 0.0..=5.0)
+        .tooltip("Steer towards the center of nearby flockmates"),
+    Param::slider("boid_max_speed", "Max Speed", "Boids", Panel::Physics, |app| &mut app.settings.boid_max_speed, 0.0..=50.0),
+    Param::slider("flip_ratio", "FLIP Ratio", "Liquid", Panel::Physics, |app| &mut app.settings.flip_ratio, 0.0..=1.0)
+        .tooltip("0 takes the grid's velocity (PIC), smooth but syrupy, 1 keeps the particles' own (FLIP), lively but noisy"),
+    Param::slider("hardening", "Hardening", "MPM", Panel::Physics, |app| &mut app.settings.hardening, 0.0..=20.0)
+        .tooltip("How much snow stiffens as it packs, low stays slushy, high clumps into chunks"),
+    Param::slider("friction_angle", "Friction Angle", "MPM", Panel::Physics, |app| &mut app.settings.friction_angle, 0.0..=60.0)
+        .suffix("°")
+        .tooltip("Steepest slope sand piles up to, 0 flows like a liquid"),
+    Param::slider("gravity", "Gravity", "Particle Settings", Panel::Physics, |app| &mut app.settings.gravity, 0.0..=5.0),
+    Param::slider("fixed_step_rate", "Step Rate", "Simulation", Panel::Physics, |app| &mut app.settings.fixed_step_rate, 15.0..=240.0)
+        .suffix(" Hz")
+        .tooltip("Physics steps per second, the same at any frame rate"),
+    Param::toggle("interpolation", "Interpolate Between Steps", "Simulation", Panel::Physics, |app| &mut app.settings.interpolation)
+        .tooltip("Draws particles blended from the previous step, smooth when the frame rate is higher than the step rate"),
+    Param::toggle("adaptive_timestep", "Adaptive Sub-steps", "Simulation", Panel::Physics, |app| &mut app.settings.adaptive_timestep)
+        .tooltip("Adds sub-steps while the fastest particle would move too far in one, so it can't pass through colliders"),
+    Param::slider("max_travel", "Max Travel per Sub-step", "Simulation", Panel::Physics, |app| &mut app.settings.max_travel, 0.05..=5.0)
+        .logarithmic()
+        .tooltip("Farthest the fastest particle may move in one sub-step, about a contact radius or grid cell"),
+    Param::toggle("deterministic", "Deterministic", "Simulation", Panel::Physics, |app| &mut app.settings.deterministic)
+        .tooltip("Fixed sub-steps and a step counter restarting on every reset, so the CPU backend repeats a run from the same seed bit for bit. Mouse input isn't recorded."),
+    Param::toggle("watchdog.enabled", "Watchdog", "Simulation", Panel::Physics, |app| &mut app.watchdog.enabled)
+        .tooltip("Pause when particles go NaN or fly off to infinity, and offer to roll back"),
+    Param::toggle("watchdog.expert_mode", "Expert Mode", "Simulation", Panel::Physics, |app| &mut app.watchdog.expert_mode)
+        .tooltip("No warnings or limits for settings known to be unstable at the current step"),
+    Param::toggle("watchdog.clamp_unstable", "Clamp Unstable Settings", "Simulation", Panel::Physics, |app| &mut app.watchdog.clamp_unstable)
+        .tooltip("Pull settings known to be unstable at the current step back to their limits"),
+    Param::slider("damping", "Damping", "Particle Settings", Panel::Physics, |app| &mut app.settings.damping, 0.9..=1.0)
+        .tooltip("Velocity kept per step, use 1.0 for molecular dynamics"),
+    Param::slider("variation", "Variation", "Particle Settings", Panel::Physics, |app| &mut app.settings.variation, 0.0..=100.0)
+        .suffix("%")
+        .tooltip("How far each particle's damping and gravity stray from the rest, so large clouds look less uniform"),
+    Param::toggle("attractor_enabled", "Central Attractor", "Particle Settings", Panel::Physics, |app| &mut app.settings.attractor_enabled),
+    Param::slider("attractor_mass", "Attractor G·M", "Particle Settings", Panel::Physics, |app| &mut app.settings.attractor_mass, 0.0..=5000.0)
+        .logarithmic(),
+    Param::slider("turbulence_amplitude", "Strength", "Turbulence", Panel::Physics, |app| &mut app.settings.turbulence_amplitude, 0.0..=50.0)
+        .tooltip("Swirls particles around like smoke without bunching them up"),
+    Param::slider("turbulence_frequency", "Frequency", "Turbulence", Panel::Physics, |app| &mut app.settings.turbulence_frequency, 0.005..=0.5)
+        .logarithmic()
+        .tooltip("Swirls per unit of distance, lower makes larger ones"),
+    Param::slider("turbulence_speed", "Scroll Speed", "Turbulence", Panel::Physics, |app| &mut app.settings.turbulence_speed, 0.0..=20.0)
+        .tooltip("How fast the swirls drift upwards"),
+    Param::slider("wind_strength", "Wind", "Wind", Panel::Physics, |app| &mut app.settings.wind_strength, 0.0..=20.0),
+    Param::slider("wind_gustiness", "Gustiness", "Wind", Panel::Physics, |app| &mut app.settings.wind_gustiness, 0.0..=2.0)
+        .tooltip("How much gusts drifting downwind vary the wind, 0 keeps it steady"),
+    Param::toggle("collisions_enabled", "Collisions", "Particle Settings", Panel::Physics, |app| &mut app.settings.collisions_enabled),
+    Param::slider("collision_radius", "Particle Radius", "Collisions", Panel::Physics, |app| &mut app.settings.collision_radius, 0.05..=2.0),
+    Param::toggle("springs_enabled", "Springs", "Particle Settings", Panel::Physics, |app| &mut app.settings.springs_enabled)
+        .tooltip("Keep particles tied together since generation at their starting distance"),
+    Param::slider("spring_stiffness", "Stiffness", "Springs", Panel::Physics, |app| &mut app.settings.spring_stiffness, 0.0..=1.0)
+        .tooltip("Fraction of the stretch undone per iteration"),
+    Param::slider("restitution", "Restitution", "Collisions", Panel::Physics, |app| &mut app.settings.restitution, 0.0..=1.0)
+        .tooltip("1 is perfectly elastic, 0 perfectly inelastic"),
+    Param::toggle("granular_contacts", "Granular Contacts", "Collisions", Panel::Physics, |app| &mut app.settings.granular_contacts)
+        .tooltip("Touching particles grip and roll over each other, so piles and avalanches behave like sand"),
+    Param::slider("contact_friction", "Friction", "Collisions", Panel::Physics, |app| &mut app.settings.contact_friction, 0.0..=1.5)
+        .tooltip("How hard touching particles grip before they slide, steeper piles hold with more"),
+    Param::slider("rolling_resistance", "Rolling Resistance", "Collisions", Panel::Physics, |app| &mut app.settings.rolling_resistance, 0.0..=0.5)
+        .tooltip("Brakes particles rolling over each other, round grains need some to pile up at all"),
+    Param::slider("surface_radius", "Radius", "Surface", Panel::Physics, |app| &mut app.settings.surface_radius, 1.0..=100.0)
+        .tooltip("Radius of the sphere shell, or of the ring the torus's tube goes around"),
+    Param::slider("surface_tube_radius", "Tube Radius", "Surface", Panel::Physics, |app| &mut app.settings.surface_tube_radius, 0.5..=50.0),
+    Param::slider("conduction", "Conduction", "Heat", Panel::Physics, |app| &mut app.settings.conduction, 0.0..=10.0)
+        .logarithmic()
+        .requires(Capability::HeatConduction)
+        .tooltip("How quickly temperature spreads between touching particles"),
+    Param::slider("buoyancy", "Buoyancy", "Heat", Panel::Physics, |app| &mut app.settings.buoyancy, 0.0..=20.0)
+        .tooltip("How hard particles warmer than the ambient rise, colder ones sink"),
+    Param::slider("ambient_temperature", "Ambient Temperature", "Heat", Panel::Physics, |app| &mut app.settings.ambient_temperature, 0.0..=1.0)
+        .tooltip("Temperature at which particles neither rise nor sink"),
+    Param::toggle("dipoles_enabled", "Magnetic dipoles", "Magnetism", Panel::Physics, |app| &mut app.settings.dipoles_enabled)
+        .requires(Capability::DipoleForces),
+    Param::slider("dipole_strength", "Dipole Strength", "Magnetism", Panel::Physics, |app| &mut app.settings.dipole_strength, 0.01..=20.0)
+        .logarithmic()
+        .requires(Capability::DipoleForces),
+    Param::slider("dipole_radius", "Dipole Range", "Magnetism", Panel::Physics, |app| &mut app.settings.dipole_radius, 0.5..=10.0)
+        .requires(Capability::DipoleForces),
+    Param::toggle("lorentz_enabled", "Lorentz force (E/B fields)", "Magnetism", Panel::Physics, |app| &mut app.settings.lorentz_enabled),
+    Param::toggle("coulomb_enabled", "Coulomb force", "Magnetism", Panel::Physics, |app| &mut app.settings.coulomb_enabled)
+        .tooltip("Charges push like ones away and pull unlike ones in"),
+    Param::slider("coulomb_strength", "Coulomb Strength", "Magnetism", Panel::Physics, |app| &mut app.settings.coulomb_strength, -20.0..=20.0)
+        .tooltip("Negative makes like charges attract"),
+    Param::slider("coulomb_cutoff", "Coulomb Cutoff", "Magnetism", Panel::Physics, |app| &mut app.settings.coulomb_cutoff, 0.5..=20.0)
+        .tooltip("Distance past which charges stop feeling each other, larger is slower"),
+    Param::toggle("particle_life_enabled", "Particle Life", "Particle Life", Panel::Physics, |app| &mut app.settings.particle_life_enabled)
+        .tooltip("Species chase and flee each other by the matrix below, growing into creatures"),
+    Param::slider("particle_life_strength", "Strength", "Particle Life", Panel::Physics, |app| &mut app.settings.particle_life_strength, 0.0..=20.0),
+    Param::slider("particle_life_radius", "Radius", "Particle Life", Panel::Physics, |app| &mut app.settings.particle_life_radius, 0.5..=20.0)
+        .tooltip("Distance past which species stop feeling each other, larger is slower"),
+    Param::slider("strange_follow", "Follow", "Strange Attractor", Panel::Physics, |app| &mut app.settings.strange_follow, 0.1..=100.0)
+        .logarithmic()
+        .suffix(" /s")
+        .tooltip("How quickly particles take on the flow's velocity, slow lets other forces in"),
+    Param::slider("strange_scale", "Scale", "Strange Attractor", Panel::Physics, |app| &mut app.settings.strange_scale, 0.1..=50.0)
+        .logarithmic()
+        .tooltip("World units per attractor unit"),
+    Param::slider("strange_speed", "Speed", "Strange Attractor", Panel::Physics, |app| &mut app.settings.strange_speed, 0.01..=10.0)
+        .logarithmic()
+        .tooltip("Attractor time per simulated second"),
+    Param::slider("electric_strength", "Electric Field", "Magnetism", Panel::Physics, |app| &mut app.settings.electric_strength, 0.0..=10.0),
+    Param::slider("magnetic_strength", "Magnetic Field", "Magnetism", Panel::Physics, |app| &mut app.settings.magnetic_strength, 0.0..=10.0),
+    Param::toggle("lj_enabled", "Lennard-Jones potential", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.lj_enabled)
+        .requires(Capability::MolecularDynamics),
+    Param::slider("lj_epsilon", "Epsilon", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.lj_epsilon, 0.01..=10.0)
+        .requires(Capability::MolecularDynamics),
+    Param::slider("lj_sigma", "Sigma", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.lj_sigma, 0.1..=5.0)
+        .requires(Capability::MolecularDynamics),
+    Param::slider("lj_cutoff", "Cutoff (σ)", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.lj_cutoff, 1.0..=4.0)
+        .requires(Capability::MolecularDynamics),
+    Param::toggle("thermostat_enabled", "Berendsen thermostat", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.thermostat_enabled)
+        .requires(Capability::MolecularDynamics),
+    Param::slider("thermostat_target", "Target Temperature", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.thermostat_target, 0.0..=5.0)
+        .requires(Capability::MolecularDynamics),
+    Param::slider("thermostat_tau", "Relaxation Time", "Molecular Dynamics", Panel::Physics, |app| &mut app.settings.thermostat_tau, 0.05..=10.0)
+        .logarithmic()
+        .requires(Capability::MolecularDynamics),
+    Param::toggle("show_ghosts", "Show periodic copies near the faces", "Boundaries", Panel::Physics, |app| &mut app.settings.show_ghosts),
+    Param::slider("ghost_margin", "Copy Margin", "Boundaries", Panel::Physics, |app| &mut app.settings.ghost_margin, 0.0..=50.0),
+    Param::slider("wall_restitution", "Wall Restitution", "Boundaries", Panel::Physics, |app| &mut app.settings.wall_restitution, 0.0..=1.0)
+        .tooltip("1 bounces without losing speed, 0 sticks to the walls"),
+    Param::toggle("ground_enabled", "Ground plane", "Boundaries", Panel::Physics, |app| &mut app.settings.ground_enabled),
+    Param::slider("ground_height", "Ground Height", "Boundaries", Panel::Physics, |app| &mut app.settings.ground_height, -200.0..=200.0),
+    Param::slider("ground_restitution", "Ground Restitution", "Boundaries", Panel::Physics, |app| &mut app.settings.ground_restitution, 0.0..=1.0),
+    Param::slider("ground_friction", "Friction", "Boundaries", Panel::Physics, |app| &mut app.settings.ground_friction, 0.0..=1.0)
+        .tooltip("Fraction of the sliding speed lost on every contact"),
+    Param::toggle("show_ground_grid", "Show ground grid", "Boundaries", Panel::Physics, |app| &mut app.settings.show_ground_grid),
+    Param::toggle("gizmo_xray", "X-ray boundary lines", "Boundaries", Panel::Physics, |app| &mut app.settings.gizmo_xray)
+        .tooltip("Draws the container and ground lines over the particles, instead of hidden behind them"),
+    Param::slider("obstacle_restitution", "Obstacle Restitution", "Obstacles", Panel::Physics, |app| &mut app.settings.obstacle_restitution, 0.0..=1.0),
+    Param::toggle("respawn_enabled", "Continuous respawn", "Generation", Panel::Generation, |app| &mut app.settings.respawn_enabled)
+        .tooltip("Keep re-seeding particles from the spawn shape"),
+    Param::slider("respawn_rate", "Respawn Rate (/s)", "Generation", Panel::Generation, |app| &mut app.settings.respawn_rate, 0.0..=2.0),
+    Param::toggle("lifetime_enabled", "Limited lifetime", "Lifetime", Panel::Generation, |app| &mut app.settings.lifetime_enabled)
+        .tooltip("Re-emit particles from the emitter once they are old enough"),
+    Param::slider("lifetime", "Lifetime", "Lifetime", Panel::Generation, |app| &mut app.settings.lifetime, 0.1..=60.0)
+        .logarithmic()
+        .suffix(" s"),
+    Param::slider("lifetime_variation", "Variation", "Lifetime", Panel::Generation, |app| &mut app.settings.lifetime_variation, 0.0..=0.9)
+        .tooltip("Fraction of the lifetime each particle's is varied by"),
+    Param::slider("emission_rate", "Emission Rate (/s)", "Lifetime", Panel::Generation, |app| &mut app.settings.emission_rate, 0.0..=1_000_000.0)
+        .logarithmic()
+        .tooltip("Particles that die wait to be re-emitted at this rate, 0 re-emits them right away"),
+    Param::slider("emitter_speed", "Launch Speed", "Lifetime", Panel::Generation, |app| &mut app.settings.emitter_speed, 0.0..=100.0),
+    Param::slider("emitter_spread", "Spread", "Lifetime", Panel::Generation, |app| &mut app.settings.emitter_spread, 0.0..=1.5)
+        .suffix(" rad")
+        .tooltip("How far off straight up fountains spray"),
+    Param::slider("contact_radius", "Contact Radius", "Display", Panel::Display, |app| &mut app.settings.contact_radius, 0.1..=5.0),
+    Param::slider("max_dist_for_color", "Color Distance", "Display", Panel::Display, |app| &mut app.settings.max_dist_for_color, 0.1..=500.0)
+        .logarithmic()
+        .tooltip("Distance at which the position and displacement colors saturate"),
+    Param::toggle("power_saver.enabled", "Save Power", "Display", Panel::Display, |app| &mut app.power_saver.enabled)
+        .tooltip("Cap the frame rate on battery or when the hardware throttles"),
+    Param::toggle("annotations.enabled", "Show vectors on sampled particles", "Annotations", Panel::Display, |app| &mut app.annotations.enabled),
+    Param::toggle("annotations.show_velocity", "Velocity", "Annotations", Panel::Display, |app| &mut app.annotations.show_velocity),
+    Param::toggle("annotations.show_acceleration", "Acceleration", "Annotations", Panel::Display, |app| &mut app.annotations.show_acceleration),
+    Param::toggle("annotations.show_force", "Force", "Annotations", Panel::Display, |app| &mut app.annotations.show_force)
+        .tooltip("Sum of the applied fields, without pair interactions or damping"),
+    Param::toggle("annotations.show_labels", "Labels", "Annotations", Panel::Display, |app| &mut app.annotations.show_labels),
+    Param::slider("annotations.arrow_scale", "Arrow Scale", "Annotations", Panel::Display, |app| &mut app.annotations.arrow_scale, 0.1..=20.0)
+        .logarithmic(),
+    Param::toggle("inset.enabled", "Show a second camera in the corner", "Picture-in-Picture", Panel::Display, |app| &mut app.inset.enabled),
+    Param::slider("inset.size", "Size", "Picture-in-Picture", Panel::Display, |app| &mut app.inset.size, 0.15..=0.5),
+    Param::toggle("rdf_enabled", "Radial distribution g(r)", "Analysis", Panel::Display, |app| &mut app.settings.rdf_enabled)
+        .requires(Capability::RadialDistribution),
+    Param::slider("rdf_max_radius", "Max Radius", "Analysis", Panel::Display, |app| &mut app.settings.rdf_max_radius, 1.0..=30.0)
+        .requires(Capability::RadialDistribution),
+    Param::toggle("group_stats_enabled", "Per-species statistics", "Analysis", Panel::Display, |app| &mut app.settings.group_stats_enabled)
+        .tooltip("Count, average speed, kinetic energy and extent of every species. GPU backends measure a sample of up to 4096 particles."),
+    Param::slider("screensaver.preset_interval", "Preset Duration", "Screensaver", Panel::Display, |app| &mut app.screensaver.preset_interval, 5.0..=300.0)
+        .logarithmic()
+        .suffix(" s"),
+    Param::slider("screensaver.idle_timeout", "Start When Idle", "Screensaver", Panel::Display, |app| &mut app.screensaver.idle_timeout, 0.0..=600.0)
+        .suffix(" s")
+        .tooltip("0 never starts it automatically"),
+    Param::toggle("evolve.enabled", "Evolve", "Evolve", Panel::Display, |app| &mut app.evolve.enabled)
+        .tooltip("Slowly drifts the picked parameters within their bounds"),
+    Param::slider("evolve.speed", "Drift Speed", "Evolve", Panel::Display, |app| &mut app.evolve.speed, 0.1..=30.0)
+        .logarithmic()
+        .suffix(" /min")
+        .tooltip("Roughly how often a drifting parameter turns around"),
+    Param::slider("mouse_radius", "Radius", "Mouse Interaction", Panel::Camera, |app| &mut app.settings.mouse_radius, 1.0..=50.0),
+    Param::slider("mouse_force", "Force", "Mouse Interaction", Panel::Camera, |app| &mut app.settings.mouse_force, 0.0..=100.0),
+    Param::slider("mouse_heat", "Heat", "Mouse Interaction", Panel::Camera, |app| &mut app.settings.mouse_heat, 0.0..=5.0)
+        .tooltip("Temperature injected per second near the cursor"),
+];
+
+/// The registered parameter under `key`
+fn parameter(key: &str) -> &'static Param {
+    PARAMETERS
+        .iter()
+        .find(|parameter| parameter.key == key)
+        .unwrap_or_else(|| panic!("no parameter registered as {key}"))
+}
+
+fn is_parameter(key: &str) -> bool {
+    PARAMETERS.iter().any(|parameter| parameter.key == key)
+}
+
+/// The bindings saved last time, for the parameters still registered
+fn load_bindings(storage: Option<&dyn eframe::Storage>) -> BTreeMap<&'static str, Binding> {
+    storage
+        .and_then(|storage| {
+            eframe::get_value::<BTreeMap<String, String>>(storage, bindings::STORAGE_KEY)
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(key, text)| {
+            let parameter = PARAMETERS.iter().find(|parameter| parameter.key == key)?;
+            Some((parameter.key, Binding::new(text, is_parameter)))
+        })
+        .collect()
+}
+
+/// Queues the registered parameters in the page URL, e.g.
+/// `?gravity=2&ground_enabled=1`, as a change on the first frame
+#[cfg(target_arch = "wasm32")]
+fn queue_url_parameters() {
+    let params: serde_json::Map<String, serde_json::Value> = PARAMETERS
+        .iter()
+        .filter_map(|parameter| {
+            let text = crate::web::query_param(parameter.key)?;
+            Some((parameter.key.to_owned(), parameter.parse(&text)?))
+        })
+        .collect();
+    if !params.is_empty() {
+        commands::push(Command::SetParams {
+            params: params.into(),
+        });
+    }
+}
+
+/// Lines at the top of the statistics panel, formatted once per FPS update
+/// rather than every frame
+struct StatisticsText {
+    particle_count: u32,
+    lines: [String; 3],
+    /// Alive and dead, while particles can die
+    alive: Option<String>,
+    #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+    allocations: String,
+}
+
+impl StatisticsText {
+    fn new(fps: f32, particle_count: u32, update_time: f32, alive_count: Option<u32>) -> Self {
+        Self {
+            particle_count,
+            lines: [
+                format!("FPS: {fps:.1}"),
+                format!("Particles: {}", format::count(particle_count as u64)),
+                format!("Particles update time: {update_time:.4} ms"),
+            ],
+            alive: alive_count.map(|alive| {
+                let alive = alive.min(particle_count);
+                format!(
+                    "Alive: {}, dead: {}",
+                    format::count(alive as u64),
+                    format::count((particle_count - alive) as u64)
+                )
+            }),
+            #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+            allocations: String::new(),
+        }
+    }
+}
+
+/// A compute backend that failed, its particles on their way back to seed
+/// the CPU backend
+struct GpuFallback {
+    copy: ParticleCopy,
+    error: String,
+    was_paused: bool,
+}
+
 pub struct ParticleApp {
     simulation: Box<dyn ParticleSimulation>,
     surface_format: wgpu::TextureFormat,
     renderer: ParticleRenderer,
+    /// Particle draws of the last frames, handed out again while their
+    /// buffers and camera stay the same
+    particle_draws: RefCell<Vec<Arc<ParticleDraw>>>,
     camera: Camera,
 
-    // Simulation parameters
-    gravity: f32,
-    color_mode: u32,
-    mouse_force: f32,
-    mouse_radius: f32,
+    /// Every parameter of the active layer, the panels and the registry
+    /// edit it in place
+    settings: Settings,
+    /// Sub-steps the fastest particle last called for, when adaptive
+    adaptive_substeps: u32,
+    /// Last health reading that came back, until the watchdog takes it
+    latest_health: Option<Health>,
+    timestep: FixedTimestep,
     mouse_position: [f32; 3],
-    max_dist_for_color: f32,
+    /// Seed of the last randomized matrix
+    particle_life_seed: u64,
+    /// Shape of the mesh obstacles and the file it came from
+    obstacle_mesh: Arc<MeshSdf>,
+    obstacle_mesh_name: Option<String>,
+    show_field_editor: bool,
+    step: u32,
+
+    // Tutorials
+    orbit_tutorial: OrbitTutorial,
+    annotations: Annotations,
+    inset: Inset,
+    #[cfg(not(target_arch = "wasm32"))]
+    panorama: Panorama,
+    #[cfg(not(target_arch = "wasm32"))]
+    export: Option<ExportJob>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export_settings: ExportSettings,
+    #[cfg(not(target_arch = "wasm32"))]
+    show_export: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    contact_sheet: Option<ContactSheet>,
+    #[cfg(not(target_arch = "wasm32"))]
+    contact_sheet_settings: ContactSheetSettings,
+    #[cfg(not(target_arch = "wasm32"))]
+    show_contact_sheet: bool,
+    /// A frame being read back for the clipboard
+    frame_copy: Option<FrameCopy>,
+    copy_frame_requested: bool,
+    /// The central panel's size in physical pixels
+    viewport_pixels: [u32; 2],
+    /// Snapshots of the running simulation being taken
+    recorder: Option<Recorder>,
+    record_interval: u32,
+    /// The last recording, kept to play back
+    recording: Option<Recording>,
+    /// A recording being scrubbed through in place of the physics
+    playback: Option<Playback>,
+    benchmark: Option<Benchmark>,
+    benchmark_result: Option<BenchmarkResult>,
+    screensaver: Screensaver,
+    power_saver: PowerSaver,
+    /// Pauses a simulation that blew up
+    watchdog: Watchdog,
+    usage: UsageStats,
+    panel_layout: egui_tiles::Tree<Panel>,
+    /// Parameters set from an expression every frame, by key
+    bindings: BTreeMap<&'static str, Binding>,
+    evolve: Evolve,
+    /// Carrying the parameters from one preset to another, if under way
+    morph: Option<Morph>,
+    morph_settings: MorphSettings,
+
+    // Analysis
+    rdf_timer: f32,
+    rdf: Option<Vec<f32>>,
+    group_stats_timer: f32,
+    group_stats: Option<Vec<GroupStats>>,
+    /// Particles not waiting on the emitter at the last count, only counted
+    /// while there's an emission rate
+    alive_count: Option<u32>,
+    /// Column the table is sorted by, and whether it's descending
+    group_sort: (GroupColumn, bool),
+
+    // Layers, the active one's state is the rest of the app
+    layers: Vec<Layer>,
+    active_layer: usize,
+    /// Layer drawn alone, hiding all the others
+    solo_layer: Option<usize>,
+    /// Records the background layers on the rayon pool instead of one after
+    /// another
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    parallel_layers: bool,
+    /// Main thread milliseconds spent on the background layers
+    layer_update_time: f32,
+
+    /// Last GPU error nobody captured, filled in by wgpu's error handler
+    gpu_error: Arc<Mutex<Option<String>>>,
+    gpu_fallback: Option<GpuFallback>,
+    allocations: AllocationGuard,
 
     // UI state
     show_ui: bool,
+    /// Message shown until dismissed, for things that happened on their own
+    notice: Option<String>,
+    /// Transparent desktop overlay, only the particles are drawn
+    overlay: bool,
+    paste_pending: bool,
+    settings_status: Option<String>,
+    /// Filters the panels down to matching parameters when not empty
+    settings_query: String,
+    current_preset: Option<usize>,
+    /// Particle count, FPS and pause state the window title was last set for
+    window_title: Option<(u32, f32, bool)>,
+    #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+    tray: crate::tray::Tray,
+    #[cfg(all(feature = "update-check", not(target_arch = "wasm32")))]
+    update_checker: crate::update_check::UpdateChecker,
+    /// Simulated seconds per real second
+    time_scale: f32,
+    /// Held down key slowing the time scale further by [`SLOW_MOTION`]
+    slow_motion: bool,
+    /// Advance paused layers by one step on the next frame
+    step_requested: bool,
+    /// Simulated seconds since the last reset
+    sim_time: f32,
     fps: f32,
     fps_counter: u32,
     fps_timer: f32,
+    /// Heap allocations when the FPS was last updated and per frame since
+    /// the update before
+    #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+    heap_allocations: (u64, f32),
+    /// Dropped on every FPS update and particle count change
+    statistics_text: Option<StatisticsText>,
     last_update: Instant,
     simulation_update_time: f32,
 
     current_method: SimulationMethod,
     available_methods: Vec<SimulationMethod>,
+    device_profile: DeviceProfile,
     ui_particle_count: u32,
     // TODO: see if its possible to  remove the ui specific variable
-    generation_mode: SphereGeneration,
-    ui_generation_mode: SphereGeneration,
+    ui_generation: GenerationSettings,
 
     // Input tracking
     mouse_pos: (f32, f32),
     mouse_prev_pos: (f32, f32),
     mouse_dragging: bool,
-    right_mouse_down: bool,
     keys_down: HashSet<egui::Key>,
     shift_down: bool,
 }
@@ -78,18 +555,34 @@ impl ParticleApp {
 
         let device = &wgpu_render_state.device;
 
+        // Keep running after validation or out of memory errors instead of
+        // panicking, the app falls back to the CPU backend on the next frame
+        let gpu_error = Arc::new(Mutex::new(None));
+        let handler_error = gpu_error.clone();
+        device.on_uncaptured_error(Arc::new(move |error: wgpu::Error| {
+            if let Ok(mut slot) = handler_error.lock() {
+                slot.get_or_insert(error.to_string());
+            }
+        }));
+
         // Initialize camera
         let size = cc.egui_ctx.content_rect().size();
         let aspect_ratio = size.x / size.y;
         let camera = Camera::new(device, aspect_ratio);
 
         // Determine available simulation methods based on capabilities
-        let mut available_methods = vec![SimulationMethod::Cpu]; // CPU always available
+        // CPU always available
+        let mut available_methods = vec![
+            SimulationMethod::Cpu,
+            SimulationMethod::BarnesHut,
+            SimulationMethod::Boids,
+        ];
 
-        // Check if we can use compute shaders (not available in WebGL)
-        let has_compute = device.limits().max_compute_workgroup_storage_size > 0;
+        let has_compute = supports_compute(device);
         if has_compute {
             available_methods.push(SimulationMethod::ComputeShader);
+            available_methods.push(SimulationMethod::Liquid);
+            available_methods.push(SimulationMethod::Mpm);
         }
 
         // Default to best available method
@@ -100,437 +593,4741 @@ impl ParticleApp {
         };
 
         let surface_format = wgpu_render_state.target_format;
-        let initial_generation_mode = SphereGeneration::Hollow;
+        let initial_generation = GenerationSettings::default();
 
-        let initial_particles;
-        let simulation: Box<dyn ParticleSimulation> = match default_method {
-            SimulationMethod::Cpu => {
-                initial_particles = 100_000;
-                Box::new(CpuParticleSimulation::new(
-                    device,
-                    initial_particles,
-                    surface_format,
-                    initial_generation_mode,
-                ))
-            }
-            SimulationMethod::ComputeShader => {
-                initial_particles = 1_000_000;
-                Box::new(ComputeParticleSimulation::new(
-                    device,
-                    initial_particles,
-                    surface_format,
-                    initial_generation_mode,
-                ))
-            }
-        };
+        let device_profile = DeviceProfile::detect(
+            &wgpu_render_state.adapter.get_info(),
+            device.limits(),
+            default_method,
+        );
+        let initial_particles = device_profile.particle_count;
+        let simulation = Self::create_simulation(
+            default_method,
+            device,
+            initial_particles,
+            surface_format,
+            initial_generation,
+        );
 
         let particle_shader = unsafe {
             device.create_shader_module_trusted(
-                wgpu::include_wgsl!("shaders/particle.wgsl"),
+                wgpu::ShaderModuleDescriptor {
+                    label: Some("Particle Shader"),
+                    source: wgpu::ShaderSource::Wgsl(
+                        wgsl::compose(&[wgsl::CAMERA, include_str!("shaders/particle.wgsl")])
+                            .into(),
+                    ),
+                },
                 wgpu::ShaderRuntimeChecks::unchecked(),
             )
         };
 
         let surface_format = wgpu_render_state.target_format;
         let renderer = ParticleRenderer::new(device, &camera, &surface_format, &particle_shader);
+        // The window's particle callbacks find the pipeline here, which
+        // leaves them only the per-layer buffers to carry
+        wgpu_render_state
+            .renderer
+            .write()
+            .callback_resources
+            .insert(renderer.pipeline.clone());
+
+        #[cfg(target_arch = "wasm32")]
+        queue_url_parameters();
 
         Self {
             simulation,
             surface_format,
             renderer,
+            particle_draws: RefCell::new(Vec::new()),
             camera,
+            gpu_error,
+            gpu_fallback: None,
+            allocations: AllocationGuard::default(),
 
-            gravity: 0.0,
-            color_mode: 0,
-            mouse_force: 5.0,
-            mouse_radius: 10.0,
+            settings: Settings::default(),
+            adaptive_substeps: 1,
+            latest_health: None,
+            timestep: FixedTimestep::default(),
             mouse_position: [0.0, 0.0, 48.0],
-            max_dist_for_color: 50.0,
+            particle_life_seed: 0,
+            obstacle_mesh: Arc::new(MeshSdf::empty()),
+            obstacle_mesh_name: None,
+            show_field_editor: false,
+            step: 0,
+
+            orbit_tutorial: OrbitTutorial::default(),
+            annotations: Annotations::default(),
+            inset: Inset::new(device),
+            #[cfg(not(target_arch = "wasm32"))]
+            panorama: Panorama::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            export: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            export_settings: ExportSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            show_export: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            contact_sheet: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            contact_sheet_settings: ContactSheetSettings {
+                columns: sweep_axis("damping"),
+                rows: Some(sweep_axis("gravity")),
+                warm_up: 3.0,
+                cell_width: CELL_WIDTHS[1],
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            show_contact_sheet: false,
+            frame_copy: None,
+            copy_frame_requested: false,
+            viewport_pixels: [1, 1],
+            recorder: None,
+            record_interval: RECORD_INTERVALS[0],
+            recording: None,
+            playback: None,
+            benchmark: None,
+            benchmark_result: None,
+            screensaver: Screensaver::default(),
+            power_saver: PowerSaver::new(),
+            watchdog: Watchdog::new(),
+            usage: UsageStats::load(cc.storage),
+            panel_layout: panels::load_layout(cc.storage),
+            bindings: load_bindings(cc.storage),
+            evolve: Evolve::load(cc.storage),
+            morph: None,
+            morph_settings: MorphSettings::default(),
+
+            rdf_timer: 0.0,
+            rdf: None,
+            group_stats_timer: 0.0,
+            group_stats: None,
+            alive_count: None,
+            group_sort: (GroupColumn::Species, false),
+
+            layers: vec![Layer::active("Layer 1".to_owned())],
+            active_layer: 0,
+            solo_layer: None,
+            // A single thread only pays the pool's overhead
+            parallel_layers: rayon::current_num_threads() > 1,
+            layer_update_time: 0.0,
 
             show_ui: true,
+            notice: None,
+            overlay: false,
+            paste_pending: false,
+            settings_status: None,
+            settings_query: String::new(),
+            current_preset: None,
+            window_title: None,
+            #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+            tray: crate::tray::Tray::new(),
+            #[cfg(all(feature = "update-check", not(target_arch = "wasm32")))]
+            update_checker: Default::default(),
+            time_scale: 1.0,
+            slow_motion: false,
+            step_requested: false,
+            sim_time: 0.0,
             fps: 0.0,
             fps_counter: 0,
             fps_timer: 0.0,
+            #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+            heap_allocations: (crate::alloc_count::allocations(), 0.0),
+            statistics_text: None,
             last_update: Instant::now(),
             simulation_update_time: 0.0,
 
             current_method: default_method,
             available_methods,
+            device_profile,
             ui_particle_count: initial_particles,
-            generation_mode: initial_generation_mode,
-            ui_generation_mode: initial_generation_mode,
+            ui_generation: initial_generation,
 
             mouse_pos: (0.0, 0.0),
             mouse_prev_pos: (0.0, 0.0),
             mouse_dragging: false,
-            right_mouse_down: false,
             keys_down: HashSet::new(),
             shift_down: false,
         }
     }
 
-    fn change_simulation_method(&mut self, new_method: SimulationMethod, device: &wgpu::Device) {
-        if self.current_method == new_method {
-            return;
-        }
+    /// Draws only the particles over a transparent background, for windows
+    /// created transparent and click-through
+    pub fn with_overlay(mut self) -> Self {
+        self.overlay = true;
+        self.show_ui = false;
+        self
+    }
 
-        // Get current count to preserve when switching
-        let current_count = self.simulation.get_particle_count();
-        let was_paused = self.simulation.is_paused();
+    /// Hides the UI and takes commands from the host page, for `?embed=1`
+    #[cfg(target_arch = "wasm32")]
+    pub fn with_embed(mut self) -> Self {
+        self.show_ui = false;
+        crate::web::listen_for_messages();
+        self
+    }
 
-        // Create new simulation with the same particle count
-        self.simulation = match new_method {
-            SimulationMethod::Cpu => Box::new(CpuParticleSimulation::new(
-                device,
-                current_count,
-                self.surface_format,
-                self.generation_mode,
-            )),
-            SimulationMethod::ComputeShader => Box::new(ComputeParticleSimulation::new(
-                device,
-                current_count,
-                self.surface_format,
-                self.generation_mode,
-            )),
-        };
+    /// Starts straight into the screensaver and quits on the first input
+    pub fn with_screensaver(mut self) -> Self {
+        self.screensaver.launch();
+        self
+    }
 
-        self.simulation.set_paused(was_paused);
-        self.current_method = new_method;
-        self.ui_particle_count = current_count;
+    /// Format of the textures [`Self::render_to_texture`] can render into
+    pub fn target_format(&self) -> wgpu::TextureFormat {
+        self.surface_format
     }
 
-    fn update_simulation(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        // Calculate delta time
-        let now = Instant::now();
-        let delta_time = now.duration_since(self.last_update).as_secs_f32();
+    /// Where the window's camera sees from
+    pub fn camera_view(&self) -> CameraView {
+        self.camera.view()
+    }
 
-        self.last_update = now;
+    /// Renders the particles as they are now into `target` as seen from
+    /// `view`, without going through egui, for compositors, offline renders
+    /// and hosts embedding the simulation. `target` has to be in
+    /// [`Self::target_format`] and is cleared to transparent first.
+    pub fn render_to_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        view: &CameraView,
+    ) {
+        self.renderer.update_offscreen_camera(queue, view);
+        let scene = self.scene_callbacks(queue, &self.renderer.offscreen_camera_bind_group);
 
-        // Update FPS counter
-        self.fps_counter += 1;
-        self.fps_timer += delta_time;
-        if self.fps_timer >= 1.0 {
-            self.fps = self.fps_counter as f32 / self.fps_timer;
-            self.fps_counter = 0;
-            self.fps_timer = 0.0;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Render Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            scene.draw(&mut pass, &self.renderer.pipeline);
         }
+        queue.submit(Some(encoder.finish()));
+    }
 
-        // Handle keyboard input for camera movement
-        for key in [
-            egui::Key::W,
-            egui::Key::S,
-            egui::Key::A,
-            egui::Key::D,
-            egui::Key::Space,
-        ] {
-            if self.keys_down.contains(&key) {
-                self.camera
-                    .process_keyboard(Some(key), self.shift_down, delta_time);
-            }
+    /// Saves a panorama from the camera's position if one is due
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_panorama(&mut self, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+        // Taken out so the scene can be drawn from `self` meanwhile
+        let mut panorama = std::mem::take(&mut self.panorama);
+        let notice = panorama.update(
+            device,
+            queue,
+            self.surface_format,
+            self.camera.position,
+            self.camera.get_forward(),
+            |target, view| self.render_to_texture(device, queue, target, view),
+        );
+        self.panorama = panorama;
+        if notice.is_some() {
+            self.notice = notice;
         }
+    }
 
-        if self.shift_down {
-            self.camera.process_keyboard(None, true, delta_time);
+    /// Draws and writes out the next frame of the running export
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_export(&mut self, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let Some(mut export) = self.export.take() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+        self.render_to_texture(device, queue, export.target(), &export.camera_view());
+        match export.write_frame(device, queue) {
+            Ok(false) => self.export = Some(export),
+            Ok(true) => {
+                self.notice = Some(match export.finish() {
+                    Ok(path) => format!("Exported {}", path.display()),
+                    Err(error) => format!("Export failed: {error}"),
+                });
+            }
+            Err(error) => {
+                export.cancel();
+                self.notice = Some(format!("Export failed: {error}"));
+            }
         }
+    }
 
-        // Get wgpu render state for queue access
-        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
-            let queue = &wgpu_render_state.queue;
-            let device = &wgpu_render_state.device;
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_export_window(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let mut open = self.show_export;
+        egui::Window::new("Export Animation")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(export) = &self.export {
+                    let (written, total) = export.frames();
+                    ui.add(
+                        egui::ProgressBar::new(written as f32 / total as f32)
+                            .text(format!("Frame {written} / {total}")),
+                    );
+                    if ui.button("Cancel").clicked()
+                        && let Some(export) = self.export.take()
+                    {
+                        export.cancel();
+                        self.notice = Some("Export cancelled".to_owned());
+                    }
+                    return;
+                }
 
-            // Update camera uniform buffer
-            self.camera.update_buffer(queue);
+                let settings = &mut self.export_settings;
+                ui.add(
+                    egui::Slider::new(&mut settings.duration, 1.0..=120.0)
+                        .suffix(" s")
+                        .text("Duration"),
+                )
+                .on_hover_text("Simulated seconds at the current time scale");
+                egui::ComboBox::from_label("Frame Rate")
+                    .selected_text(format!("{} fps", settings.fps))
+                    .show_ui(ui, |ui| {
+                        for fps in export::FRAME_RATES {
+                            ui.selectable_value(&mut settings.fps, fps, format!("{fps} fps"));
+                        }
+                    });
+                let (width, height) = settings.resolution;
+                egui::ComboBox::from_label("Resolution")
+                    .selected_text(format!("{width} × {height}"))
+                    .show_ui(ui, |ui| {
+                        for (width, height) in export::RESOLUTIONS {
+                            ui.selectable_value(
+                                &mut settings.resolution,
+                                (width, height),
+                                format!("{width} × {height}"),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Format")
+                    .selected_text(settings.format.name())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut settings.format, format, format.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Videos are encoded by ffmpeg, which has to be on the PATH");
+                egui::ComboBox::from_label("Camera")
+                    .selected_text(settings.camera_path.name())
+                    .show_ui(ui, |ui| {
+                        for path in CameraPath::ALL {
+                            ui.selectable_value(&mut settings.camera_path, path, path.name());
+                        }
+                    });
+                ui.label(format!("{} frames", settings.total_frames()));
 
-            // Handle mouse position for particle interaction
-            if self.mouse_dragging {
-                let screen_rect = ctx.content_rect();
-                let (x, y) = self.mouse_pos;
+                if ui
+                    .button("Start Export")
+                    .on_hover_text("Renders every frame in full, however long that takes")
+                    .clicked()
+                    && let Some(wgpu_render_state) = frame.wgpu_render_state()
+                {
+                    match ExportJob::start(
+                        &wgpu_render_state.device,
+                        self.surface_format,
+                        self.export_settings,
+                        self.camera.position,
+                        self.camera.get_forward(),
+                        self.camera.fov,
+                    ) {
+                        Ok(export) => self.export = Some(export),
+                        Err(error) => self.notice = Some(format!("Couldn't start export: {error}")),
+                    }
+                }
+            });
+        self.show_export = open;
+    }
 
-                // Convert screen coordinates to normalized device coordinates
-                let ndc_x = (2.0 * x / screen_rect.width()) - 1.0;
-                let ndc_y = 1.0 - (2.0 * y / screen_rect.height());
+    /// Regenerates the scene with the next cell's values, runs it through its
+    /// warm-up and draws it into the sheet, putting the parameters and a fresh
+    /// scene back once the sheet is done
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_contact_sheet(&mut self, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let Some(mut sheet) = self.contact_sheet.take() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
 
-                // Calculate world position using camera
-                let camera_forward = self.camera.get_forward();
-                let camera_right = self.camera.get_right();
-                let camera_up = self.camera.get_up();
+        for (key, value) in sheet.values() {
+            parameter(key).set_value(self, value);
+        }
+        self.reset(device, queue);
+        self.step = sheet.first_step;
+        let mut sim_params = self.step_params(self.step_delta());
+        let steps = sheet.warm_up_steps(self.settings.fixed_step_rate);
+        self.run_steps(device, queue, &mut sim_params, steps, false);
 
-                let current_pos = glam::Vec3::new(
-                    self.mouse_position[0],
-                    self.mouse_position[1],
-                    self.mouse_position[2],
-                );
+        // Drawn where the warm-up left them
+        self.renderer.update_interpolation(queue, 1.0);
+        self.render_to_texture(device, queue, sheet.target(), sheet.camera_view());
+        let written = sheet.write_cell(device, queue);
+        if let Ok(false) = written {
+            self.contact_sheet = Some(sheet);
+            return;
+        }
 
-                let camera_pos = self.camera.position;
-                let to_cursor = current_pos - camera_pos;
-                let distance = to_cursor.dot(camera_forward);
+        self.set_parameters(sheet.restore.clone());
+        self.reset(device, queue);
+        self.notice = Some(match written.and_then(|_| sheet.finish()) {
+            Ok(path) => format!("Saved {}", path.display()),
+            Err(error) => format!("Contact sheet failed: {error}"),
+        });
+    }
 
-                // Calculate the plane at the specified distance from camera
-                let plane_center = camera_pos + camera_forward * distance;
+    /// Runs the standard scene once a benchmark was asked for, putting the
+    /// settings from before back when the GPU is done with it
+    fn update_benchmark(&mut self, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let Some(mut benchmark) = self.benchmark.take() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
 
-                // Scale the NDC coordinates based on the field of view and distance
-                let height = 2.0 * distance * (self.camera.fov / 2.0).tan();
-                let width = height * self.camera.aspect;
+        if benchmark.pending() {
+            self.apply_settings(benchmark::scene(), device, queue);
+            let mut sim_params = self.step_params(1.0 / self.settings.fixed_step_rate);
+            benchmark.start();
+            self.run_steps(device, queue, &mut sim_params, benchmark::STEPS, false);
+            benchmark.finish_after_submitted(device, queue);
+        }
 
-                let world_pos = plane_center
-                    + camera_right * (ndc_x * width / 2.0)
-                    + camera_up * (ndc_y * height / 2.0);
+        let Some(seconds) = benchmark.poll(device) else {
+            self.benchmark = Some(benchmark);
+            return;
+        };
+        let info = wgpu_render_state.adapter.get_info();
+        let result = BenchmarkResult {
+            method: self.current_method,
+            adapter: format!("{} ({:?})", info.name, info.backend),
+            particles: self.simulation.get_particle_count(),
+            seconds,
+        };
+        self.notice = Some(format!(
+            "Benchmark: {:.1} M particle-steps/s",
+            result.score()
+        ));
+        self.benchmark_result = Some(result);
+        self.apply_settings(benchmark.restore, device, queue);
+    }
 
-                self.mouse_position = [world_pos.x, world_pos.y, world_pos.z];
+    fn render_benchmark_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "Steps the same {} particles {} times on the current backend and scores how many particle steps per second it managed",
+            format::si(benchmark::PARTICLES as f64),
+            benchmark::STEPS
+        ));
+        let running = self.benchmark.is_some();
+        if ui
+            .add_enabled(
+                !running,
+                egui::Button::new(if running {
+                    "Running…"
+                } else {
+                    "Run Benchmark"
+                }),
+            )
+            .on_hover_text("Replaces the scene for a moment, the settings come back afterwards")
+            .clicked()
+        {
+            self.benchmark = Some(Benchmark::new(self.settings()));
+        }
+        if let Some(result) = &self.benchmark_result {
+            ui.label(
+                egui::RichText::new(format!("{:.1} M particle-steps/s", result.score()))
+                    .size(20.0)
+                    .strong(),
+            );
+            ui.label(format!(
+                "{} backend, {} in {:.2} s",
+                result.method.name(),
+                result.adapter,
+                result.seconds
+            ));
+            if ui
+                .button("Copy Result")
+                .on_hover_text("The score with the GPU and backend, to compare with other devices")
+                .clicked()
+            {
+                ui.ctx().copy_text(result.share_text());
             }
+        }
+    }
 
-            // Update particle simulation if not paused
-            if !self.simulation.is_paused() {
-                // Create a command encoder for this frame
-                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Particle Update Encoder"),
-                });
-
-                // Build simulation parameters
-                let sim_params = SimParams {
-                    delta_time,
-                    gravity: self.gravity,
-                    color_mode: self.color_mode,
-                    mouse_force: self.mouse_force,
-                    mouse_radius: self.mouse_radius,
-                    mouse_position: self.mouse_position,
-                    is_mouse_dragging: if self.mouse_dragging { 1 } else { 0 },
-                    damping: 0.99, // Add damping factor
-                    max_dist_for_color: self.max_dist_for_color,
-                    _padding2: 0,
-                };
-
-                let update_start = Instant::now();
-
-                // Run the particle simulation using current method
-                self.simulation
-                    .update(device, queue, &mut encoder, &sim_params);
+    /// Draws the particle layer offscreen at the viewport's size when a copy
+    /// was asked for, and puts it on the clipboard once it's read back
+    fn update_frame_copy(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
 
-                let update_time_ms = update_start.elapsed().as_secs_f32() * 1000.0;
-                const ALPHA: f32 = 0.1;
+        if std::mem::take(&mut self.copy_frame_requested) && self.frame_copy.is_none() {
+            let [width, height] = self.viewport_pixels;
+            let mut copy = FrameCopy::new(device, self.surface_format, width, height);
+            self.render_to_texture(device, queue, copy.target(), &self.camera_view());
+            copy.start(device, queue);
+            self.frame_copy = Some(copy);
+        }
 
-                // Submit the work
-                queue.submit(Some(encoder.finish()));
-                self.simulation_update_time =
-                    (1.0 - ALPHA) * self.simulation_update_time + ALPHA * update_time_ms;
+        let Some(result) = self.frame_copy.as_mut().and_then(|copy| copy.poll(device)) else {
+            // Keep repainting until the copy arrives, even when idle
+            if self.frame_copy.is_some() {
+                ctx.request_repaint();
             }
-        }
+            return;
+        };
+        self.frame_copy = None;
+        self.notice = Some(match result {
+            Ok(image) => {
+                let [width, height] = image.size;
+                ctx.copy_image(image);
+                format!("Copied a {width}×{height} frame to the clipboard")
+            }
+            Err(error) => format!("Copying the frame failed: {error}"),
+        });
     }
 
-    fn render_ui(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        egui::Window::new("Particle Simulator")
-            .resizable(true)
-            .default_width(300.0)
+    fn render_field_editor_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_field_editor;
+        let mut changed = false;
+        egui::Window::new("Force Field Editor")
+            .open(&mut open)
+            .default_width(640.0)
             .show(ctx, |ui| {
-                ui.heading("Statistics");
-                ui.label(format!("FPS: {:.1}", self.fps));
-                ui.label(format!(
-                    "Particles update time: {:.4} ms",
-                    self.simulation_update_time
-                ));
-
-                ui.separator();
-                ui.heading("Simulation");
+                ui.weak("Wire nodes into a Force node to push every particle by the result, on top of the force stack");
+                changed = node_editor::show(ui, &mut self.settings.field_graph);
+            });
+        self.show_field_editor = open;
+        if changed {
+            self.sync_field_graph();
+        }
+    }
 
-                ui.horizontal(|ui| {
-                    if ui.button("Reset").clicked()
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_contact_sheet_window(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let mut open = self.show_contact_sheet;
+        egui::Window::new("Contact Sheet")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(sheet) = &self.contact_sheet {
+                    let (drawn, total) = sheet.cells();
+                    ui.add(
+                        egui::ProgressBar::new(drawn as f32 / total as f32)
+                            .text(format!("Cell {drawn} / {total}")),
+                    );
+                    if ui.button("Cancel").clicked()
+                        && let Some(sheet) = self.contact_sheet.take()
                         && let Some(wgpu_render_state) = frame.wgpu_render_state()
                     {
-                        self.simulation.reset(
-                            &wgpu_render_state.device,
-                            &wgpu_render_state.queue,
-                            self.generation_mode,
-                        );
+                        self.set_parameters(sheet.restore);
+                        self.reset(&wgpu_render_state.device, &wgpu_render_state.queue);
+                        self.notice = Some("Contact sheet cancelled".to_owned());
                     }
+                    return;
+                }
 
-                    let paused = self.simulation.is_paused();
-                    if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
-                        self.simulation.set_paused(!paused);
-                    }
-                });
-
-                let mut clicked_method = None;
-                egui::ComboBox::from_label("Method")
-                    .selected_text(format!("{:?}", self.current_method))
+                let settings = &mut self.contact_sheet_settings;
+                ui.label("Columns");
+                sweep_axis_ui(ui, "contact_sheet_columns", &mut settings.columns);
+                let mut has_rows = settings.rows.is_some();
+                if ui.checkbox(&mut has_rows, "Rows").changed() {
+                    settings.rows = has_rows.then(|| sweep_axis("gravity"));
+                }
+                if let Some(rows) = &mut settings.rows {
+                    sweep_axis_ui(ui, "contact_sheet_rows", rows);
+                }
+                ui.separator();
+                ui.add(
+                    egui::Slider::new(&mut settings.warm_up, 0.0..=30.0)
+                        .suffix(" s")
+                        .text("Warm-up"),
+                )
+                .on_hover_text(
+                    "Simulated seconds every cell runs from a fresh scene before it's drawn",
+                );
+                egui::ComboBox::from_label("Cell Width")
+                    .selected_text(format!("{} px", settings.cell_width))
                     .show_ui(ui, |ui| {
-                        for method in &self.available_methods {
-                            let text = match method {
-                                SimulationMethod::Cpu => "CPU (Compatible Everywhere)",
-                                SimulationMethod::ComputeShader => "Compute Shader (Fastest)",
-                            };
-                            if ui
-                                .selectable_label(self.current_method == *method, text)
-                                .clicked()
-                                && self.current_method != *method
-                            {
-                                clicked_method = Some(*method);
-                            }
+                        for width in CELL_WIDTHS {
+                            ui.selectable_value(
+                                &mut settings.cell_width,
+                                width,
+                                format!("{width} px"),
+                            );
                         }
                     });
+                ui.label(format!("{} cells", settings.cell_count()));
 
-                if let Some(method) = clicked_method
+                if ui
+                    .button("Render Sheet")
+                    .on_hover_text("Restarts the scene for every cell and once more at the end")
+                    .clicked()
                     && let Some(wgpu_render_state) = frame.wgpu_render_state()
                 {
-                    self.change_simulation_method(method, &wgpu_render_state.device);
+                    self.contact_sheet = Some(ContactSheet::start(
+                        &wgpu_render_state.device,
+                        self.surface_format,
+                        self.contact_sheet_settings,
+                        self.settings(),
+                        self.step,
+                        self.camera.view(),
+                        self.camera.aspect,
+                    ));
                 }
+            });
+        self.show_contact_sheet = open;
+    }
 
-                ui.separator();
-                ui.heading("Generation");
-                let mut generation_mode_changed = false;
-                ui.horizontal(|ui| {
-                    generation_mode_changed |= ui
-                        .radio_value(
-                            &mut self.ui_generation_mode,
-                            SphereGeneration::Hollow,
-                            "Hollow Sphere",
-                        )
-                        .changed();
-                    generation_mode_changed |= ui
-                        .radio_value(
-                            &mut self.ui_generation_mode,
-                            SphereGeneration::Filled,
-                            "Filled Sphere",
-                        )
-                        .changed();
-                });
+    /// What the 3D view draws with the camera in `camera_bind_group`: the
+    /// boundary lines, and one particle draw per shown layer with periodic
+    /// images only for the active one
+    fn scene_callbacks(
+        &self,
+        queue: &wgpu::Queue,
+        camera_bind_group: &wgpu::BindGroup,
+    ) -> SceneCallbacks {
+        let mut lines = Vec::new();
+        if self.settings.boundary_mode == BOUNDARY_CONTAINER {
+            self.renderer
+                .update_container(queue, self.settings.box_half_extents);
+            lines.push(self.renderer.container.callback(camera_bind_group));
+        }
+        if self.settings.ground_enabled && self.settings.show_ground_grid {
+            self.renderer
+                .update_ground(queue, self.settings.ground_height);
+            lines.push(self.renderer.ground.callback(camera_bind_group));
+        }
 
-                ui.separator();
-                ui.heading("Mouse Interaction");
-                ui.label(format!(
-                    "Position: ({:.2}, {:.2}, {:.2})",
-                    self.mouse_position[0], self.mouse_position[1], self.mouse_position[2]
-                ));
+        let mut ghost_copies = 1;
+        if self.settings.boundary_mode == BOUNDARY_PERIODIC && self.settings.show_ghosts {
+            self.renderer.update_ghosts(
+                queue,
+                self.settings.box_half_extents,
+                self.settings.ghost_margin,
+            );
+            ghost_copies = GHOST_COPIES;
+        }
 
-                ui.label(format!("Dragging: {}", self.mouse_dragging));
-                ui.label(format!("Depth: {:.2}", self.mouse_position[2]));
+        let mut particles = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !layer.shown(index, self.solo_layer) {
+                continue;
+            }
+            let (simulation, ghost_copies) = match &layer.parked {
+                Some(parked) => (&parked.simulation, 1),
+                None => (&self.simulation, ghost_copies),
+            };
+
+            let (particle_buffer, previous_buffer, indices) = match &layer.parked {
+                Some(_) => {
+                    let particles = simulation.get_particle_buffer().clone();
+                    (particles.clone(), particles, None)
+                }
+                None => self.renderer.draw_buffers(simulation.get_particle_buffer()),
+            };
+            particles.push(ClonedParticleCallback {
+                draw: self.particle_draw(ParticleDraw {
+                    camera_bind_group: camera_bind_group.clone(),
+                    particle_buffer,
+                    previous_buffer,
+                    indices,
+                }),
+                num_particles: simulation.get_particle_count(),
+                ghost_copies,
+            });
+        }
+        SceneCallbacks {
+            lines,
+            particles,
+            xray: self.settings.gizmo_xray,
+        }
+    }
+
+    /// The cached draw for the same buffers and camera as `draw`, cached
+    /// first if there isn't one. Draws of replaced buffers are dropped once
+    /// more pile up than the views and layers could be using.
+    fn particle_draw(&self, draw: ParticleDraw) -> Arc<ParticleDraw> {
+        let mut draws = self.particle_draws.borrow_mut();
+        if let Some(cached) = draws.iter().find(|cached| ***cached == draw) {
+            return cached.clone();
+        }
+        if draws.len() >= 4 * (self.layers.len() + 1) {
+            draws.retain(|cached| Arc::strong_count(cached) > 1);
+        }
+        let draw = Arc::new(draw);
+        draws.push(draw.clone());
+        draw
+    }
+
+    fn create_simulation(
+        method: SimulationMethod,
+        device: &wgpu::Device,
+        particle_count: u32,
+        surface_format: wgpu::TextureFormat,
+        generation: GenerationSettings,
+    ) -> Box<dyn ParticleSimulation> {
+        match method {
+            SimulationMethod::Cpu => Box::new(CpuParticleSimulation::new(
+                device,
+                particle_count,
+                surface_format,
+                generation,
+            )),
+            SimulationMethod::ComputeShader => Box::new(ComputeParticleSimulation::new(
+                device,
+                particle_count,
+                surface_format,
+                generation,
+            )),
+            SimulationMethod::BarnesHut => Box::new(
+                CpuParticleSimulation::new(device, particle_count, surface_format, generation)
+                    .with_mutual_gravity(),
+            ),
+            SimulationMethod::Boids if supports_compute(device) => Box::new(
+                ComputeParticleSimulation::new(device, particle_count, surface_format, generation)
+                    .with_flocking(device),
+            ),
+            SimulationMethod::Boids => Box::new(
+                CpuParticleSimulation::new(device, particle_count, surface_format, generation)
+                    .with_flocking(),
+            ),
+            SimulationMethod::Liquid => Box::new(
+                ComputeParticleSimulation::new(device, particle_count, surface_format, generation)
+                    .with_liquid(device),
+            ),
+            SimulationMethod::Mpm => Box::new(
+                ComputeParticleSimulation::new(device, particle_count, surface_format, generation)
+                    .with_mpm(device),
+            ),
+        }
+    }
+
+    /// The current configuration, to return to if `what` can't be allocated
+    fn checkpoint(&self, what: String) -> Checkpoint {
+        Checkpoint {
+            what,
+            settings: self.settings(),
+            method: self.current_method,
+        }
+    }
+
+    fn change_simulation_method(&mut self, new_method: SimulationMethod, device: &wgpu::Device) {
+        if self.simulation.get_method() == new_method {
+            return;
+        }
+        let checkpoint = self.checkpoint(format!("the {} backend", new_method.name()));
+        AllocationGuard::begin(device);
+
+        // Get current count to preserve when switching, as far as the new backend can hold it
+        let current_count = self
+            .simulation
+            .get_particle_count()
+            .min(self.device_profile.particle_limit(new_method).max);
+        let was_paused = self.simulation.is_paused();
+
+        // Create new simulation with the same particle count
+        self.simulation = Self::create_simulation(
+            new_method,
+            device,
+            current_count,
+            self.surface_format,
+            self.settings.generation,
+        );
+
+        self.simulation.set_paused(was_paused);
+        self.current_method = new_method;
+        self.ui_particle_count = current_count;
+        self.sync_reaction_rules();
+        self.sync_obstacles();
+        self.sync_point_attractors();
+        self.sync_forces();
+        self.sync_field_graph();
+        self.sync_species_interactions();
+        self.allocations.end(device, checkpoint);
+    }
+
+    /// Puts back the configuration from before a change that ran out of GPU
+    /// resources, on a fresh backend since the old one holds invalid buffers
+    fn handle_allocation_failures(&mut self, frame: &eframe::Frame) {
+        let Some(failure) = self.allocations.poll() else {
+            return;
+        };
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let Checkpoint {
+            what,
+            settings,
+            method,
+        } = failure.checkpoint;
+
+        let was_paused = self.simulation.is_paused();
+        self.settings.generation = settings.generation;
+        self.ui_generation = settings.generation;
+        self.ui_particle_count = settings.particle_count;
+        self.simulation = Self::create_simulation(
+            method,
+            &wgpu_render_state.device,
+            settings.particle_count,
+            self.surface_format,
+            settings.generation,
+        );
+        self.simulation.set_paused(was_paused);
+        self.current_method = method;
+        self.set_parameters(settings);
+        self.sync_reaction_rules();
+        self.sync_obstacles();
+        self.sync_point_attractors();
+        self.sync_forces();
+        self.sync_field_graph();
+        self.sync_species_interactions();
+
+        // Errors from drawing with the failed buffers don't call for a fallback
+        if let Ok(mut slot) = self.gpu_error.lock() {
+            *slot = None;
+        }
+        self.notice = Some(format!(
+            "Couldn't allocate {what}, went back to the previous configuration.\n{}",
+            failure.error
+        ));
+    }
+
+    /// Hover text explaining why a feature is disabled, `None` if the active
+    /// backend supports it
+    fn unsupported_reason(&self, capability: Capability) -> Option<String> {
+        (!self.simulation.supports(capability)).then(|| {
+            format!(
+                "{} not available on the {} backend",
+                capability.name(),
+                self.current_method.name()
+            )
+        })
+    }
+
+    /// Switches to the orbit scene: no gravity or damping, the attractor on and
+    /// a ring of particles launched around it
+    fn start_orbit_tutorial(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.orbit_tutorial.active = true;
+        self.settings.gravity = 0.0;
+        self.settings.damping = 1.0;
+        self.settings.attractor_enabled = true;
+        self.settings.boundary_mode = BOUNDARY_OPEN;
+        self.settings.lj_enabled = false;
+        self.settings.lorentz_enabled = false;
+        self.ui_particle_count = 2_000;
+        self.relaunch_orbit(device, queue);
+    }
+
+    fn relaunch_orbit(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.ui_generation = self
+            .orbit_tutorial
+            .generation(self.ui_generation.species_count);
+        self.settings.generation = self.ui_generation;
+        self.simulation.resize_buffer(
+            device,
+            queue,
+            self.ui_particle_count,
+            self.settings.generation,
+        );
+        self.simulation
+            .reset(device, queue, self.settings.generation);
+    }
+
+    /// The parameters with the particle count the simulation is running
+    fn settings(&self) -> Settings {
+        Settings {
+            version: SETTINGS_VERSION,
+            particle_count: self.simulation.get_particle_count(),
+            ..self.settings.clone()
+        }
+    }
+
+    /// Takes over every parameter from `settings` and regenerates the
+    /// particles to match
+    fn apply_settings(&mut self, settings: Settings, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let checkpoint = self.checkpoint(format!(
+            "{} particles",
+            format::si(settings.particle_count as f64)
+        ));
+        AllocationGuard::begin(device);
+        let particle_count = settings
+            .particle_count
+            .min(self.device_profile.particle_limit(self.current_method).max);
+        let generation = settings.generation;
+        self.set_parameters(settings);
+
+        // A new scene replaces whatever the morph was heading for
+        self.morph = None;
+        self.orbit_tutorial.active = false;
+        self.ui_particle_count = particle_count;
+        self.ui_generation = generation;
+        self.settings.generation = generation;
+        self.simulation.resize_buffer(
+            device,
+            queue,
+            self.ui_particle_count,
+            self.settings.generation,
+        );
+        self.reset(device, queue);
+        self.allocations.end(device, checkpoint);
+    }
+
+    /// Takes over the parameters from `settings` that apply on the fly, all
+    /// but the particle count and generation
+    fn set_parameters(&mut self, settings: Settings) {
+        self.settings = Settings {
+            generation: self.settings.generation,
+            substeps: settings.substeps.clamp(1, MAX_SUBSTEPS),
+            liquid_resolution: settings
+                .liquid_resolution
+                .clamp(MIN_LIQUID_RESOLUTION, MAX_LIQUID_RESOLUTION),
+            pressure_iterations: settings
+                .pressure_iterations
+                .clamp(1, MAX_PRESSURE_ITERATIONS),
+            mpm_resolution: settings
+                .mpm_resolution
+                .clamp(MIN_MPM_RESOLUTION, MAX_MPM_RESOLUTION),
+            spring_iterations: settings.spring_iterations.clamp(1, MAX_SPRING_ITERATIONS),
+            ..settings
+        };
+        self.rdf = None;
+        self.group_stats = None;
+        self.sync_reaction_rules();
+        self.sync_obstacles();
+        self.sync_point_attractors();
+        self.sync_forces();
+        self.sync_field_graph();
+        self.sync_species_interactions();
+    }
+
+    /// Parks the active layer and takes over the state of the one at `index`
+    fn select_layer(&mut self, index: usize) {
+        if index == self.active_layer {
+            return;
+        }
+        let Some(target) = self
+            .layers
+            .get_mut(index)
+            .and_then(|layer| layer.parked.take())
+        else {
+            return;
+        };
+        let settings = self.settings();
+        let simulation = std::mem::replace(&mut self.simulation, target.simulation);
+        self.layers[self.active_layer].parked = Some(Parked {
+            simulation,
+            method: self.current_method,
+            settings,
+        });
+        self.active_layer = index;
+
+        self.current_method = target.method;
+        self.ui_particle_count = self.simulation.get_particle_count();
+        self.settings.generation = target.settings.generation;
+        self.ui_generation = target.settings.generation;
+        self.current_preset = None;
+        self.orbit_tutorial.active = false;
+        self.set_parameters(target.settings);
+    }
+
+    /// Adds a small layer with default parameters and makes it the active one
+    fn add_layer(&mut self, device: &wgpu::Device) {
+        let settings = Settings {
+            particle_count: LAYER_PARTICLES
+                .min(self.device_profile.particle_limit(self.current_method).max),
+            ..Settings::default()
+        };
+        let simulation = Self::create_simulation(
+            self.current_method,
+            device,
+            settings.particle_count,
+            self.surface_format,
+            settings.generation,
+        );
+        self.layers.push(Layer {
+            name: format!("Layer {}", self.layers.len() + 1),
+            visible: true,
+            parked: Some(Parked {
+                simulation,
+                method: self.current_method,
+                settings,
+            }),
+            coupling: None,
+        });
+        self.select_layer(self.layers.len() - 1);
+    }
+
+    /// Removes the layer at `index`, there's always at least one left
+    fn remove_layer(&mut self, index: usize) {
+        if self.layers.len() < 2 {
+            return;
+        }
+        if index == self.active_layer {
+            self.select_layer(if index == 0 { 1 } else { index - 1 });
+        }
+        self.layers.remove(index);
+        if self.active_layer > index {
+            self.active_layer -= 1;
+        }
+        self.solo_layer = match self.solo_layer {
+            Some(solo) if solo == index => None,
+            Some(solo) if solo > index => Some(solo - 1),
+            solo => solo,
+        };
+        for layer in &mut self.layers {
+            match &mut layer.coupling {
+                Some(coupling) if coupling.source == index => layer.coupling = None,
+                Some(coupling) if coupling.source > index => coupling.source -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// The simulation of the layer at `index`, wherever it lives
+    fn layer_simulation(&mut self, index: usize) -> Option<&mut Box<dyn ParticleSimulation>> {
+        match self.layers.get_mut(index)?.parked.as_mut() {
+            Some(parked) => Some(&mut parked.simulation),
+            None => Some(&mut self.simulation),
+        }
+    }
+
+    /// Measures the flow of every layer others are coupled to and hands it to
+    /// them. GPU layers answer a frame or so late, until then the last flow
+    /// is kept.
+    fn couple_layers(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut sources: Vec<usize> = self
+            .layers
+            .iter()
+            .filter_map(|layer| Some(layer.coupling.as_ref()?.source))
+            .collect();
+        sources.sort_unstable();
+        sources.dedup();
+
+        // Sampled once per source, readbacks only hand out each copy once
+        for source in sources {
+            let Some(simulation) = self.layer_simulation(source) else {
+                continue;
+            };
+            let count = simulation.get_particle_count();
+            let stride = (count / COUPLING_SAMPLES).max(1);
+            let indices: Vec<u32> = (0..count.min(COUPLING_SAMPLES))
+                .map(|i| i * stride)
+                .collect();
+            let Some(sampled) = simulation.sample_particles(device, queue, &indices) else {
+                continue;
+            };
+            let field = Arc::new(FlowField::from_particles(&sampled));
+            for layer in &mut self.layers {
+                if let Some(coupling) = &mut layer.coupling
+                    && coupling.source == source
+                {
+                    coupling.field = Some(field.clone());
+                }
+            }
+        }
+
+        for index in 0..self.layers.len() {
+            let (field, strength) = match &self.layers[index].coupling {
+                Some(coupling) => (coupling.field.clone(), coupling.strength),
+                None => (None, 0.0),
+            };
+            if let Some(simulation) = self.layer_simulation(index) {
+                simulation.set_flow_field(field, strength);
+            }
+        }
+    }
+
+    /// Steps the layers running in the background, each with its own
+    /// parameters. Each layer is recorded into its own encoder, on the rayon
+    /// pool if [`Self::parallel_layers`] is on, and they're submitted
+    /// together.
+    fn update_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        due_steps: u32,
+        stepping: bool,
+    ) {
+        let update_start = Instant::now();
+        let step_delta = self.step_delta();
+        let (step, sim_time) = (self.step, self.sim_time);
+        let encode = |parked: &mut Parked| {
+            let steps = if parked.simulation.is_paused() {
+                stepping as u32
+            } else {
+                due_steps
+            };
+            if steps == 0 {
+                return None;
+            }
+            let mut sim_params = parked.settings.sim_params(
+                step_delta,
+                parked.settings.substeps,
+                step,
+                parked.simulation.get_particle_count(),
+            );
+            sim_params.turbulence_scroll = sim_time * parked.settings.turbulence_speed;
+            sim_params.time = sim_time;
+            // Unlike the active layer's, the sub-steps all write the same
+            // parameters, so they can share an encoder
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Layer Update Encoder"),
+            });
+            for _ in 0..steps * parked.settings.substeps.clamp(1, MAX_SUBSTEPS) {
+                parked
+                    .simulation
+                    .update(device, queue, &mut encoder, &sim_params);
+            }
+            Some(encoder.finish())
+        };
+
+        let parked = self
+            .layers
+            .iter_mut()
+            .filter_map(|layer| layer.parked.as_mut());
+        #[cfg(not(target_arch = "wasm32"))]
+        let command_buffers: Vec<wgpu::CommandBuffer> = if self.parallel_layers {
+            parked
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .filter_map(encode)
+                .collect()
+        } else {
+            parked.filter_map(encode).collect()
+        };
+        #[cfg(target_arch = "wasm32")]
+        let command_buffers: Vec<wgpu::CommandBuffer> = parked.filter_map(encode).collect();
+        if command_buffers.is_empty() {
+            return;
+        }
+        queue.submit(command_buffers);
+
+        let update_time_ms = update_start.elapsed().as_secs_f32() * 1000.0;
+        const ALPHA: f32 = 0.1;
+        self.layer_update_time = (1.0 - ALPHA) * self.layer_update_time + ALPHA * update_time_ms;
+    }
+
+    fn apply_preset(&mut self, index: usize, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(preset) = PRESETS.get(index) else {
+            return;
+        };
+        self.apply_settings((preset.settings)(), device, queue);
+        self.current_preset = Some(index);
+    }
+
+    fn render_settings_ui(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let mut selected = None;
+        egui::ComboBox::from_label("Preset")
+            .selected_text(self.current_preset.map_or("Custom", |i| PRESETS[i].name))
+            .show_ui(ui, |ui| {
+                for (index, preset) in PRESETS.iter().enumerate() {
+                    if ui
+                        .selectable_label(self.current_preset == Some(index), preset.name)
+                        .clicked()
+                    {
+                        selected = Some(index);
+                    }
+                }
+            });
+        if let Some(index) = selected
+            && let Some(wgpu_render_state) = frame.wgpu_render_state()
+        {
+            self.apply_preset(index, &wgpu_render_state.device, &wgpu_render_state.queue);
+        }
+
+        egui::CollapsingHeader::new("Morph").show(ui, |ui| {
+            self.render_morph_ui(ui);
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Copy Settings").clicked() {
+                ui.ctx().copy_text(self.settings().to_json());
+                self.settings_status = Some("Copied to the clipboard".to_owned());
+            }
+            if ui.button("Paste Settings").clicked() {
+                self.paste_pending = !self.paste_pending;
+                self.settings_status = None;
+            }
+        });
+
+        if self.paste_pending {
+            ui.label("Press Ctrl+V (Cmd+V on macOS) to paste");
+            let pasted = ui.input(|input| {
+                input.events.iter().find_map(|event| match event {
+                    egui::Event::Paste(text) => Some(text.clone()),
+                    _ => None,
+                })
+            });
+            if let Some(text) = pasted
+                && let Some(wgpu_render_state) = frame.wgpu_render_state()
+            {
+                self.paste_pending = false;
+                self.settings_status = Some(match Settings::from_json(&text) {
+                    Ok(settings) => {
+                        self.current_preset = None;
+                        self.apply_settings(
+                            settings,
+                            &wgpu_render_state.device,
+                            &wgpu_render_state.queue,
+                        );
+                        "Settings applied".to_owned()
+                    }
+                    Err(error) => error,
+                });
+            }
+        }
+
+        if let Some(status) = &self.settings_status {
+            ui.label(status);
+        }
+    }
+
+    /// Picks the presets, duration and easing of a morph and runs it
+    fn render_morph_ui(&mut self, ui: &mut egui::Ui) {
+        let preset_name = |index: Option<usize>| index.map_or("Current", |i| PRESETS[i].name);
+        let morphing = self.morph.is_some();
+        ui.add_enabled_ui(!morphing, |ui| {
+            let settings = &mut self.morph_settings;
+            egui::ComboBox::from_label("From")
+                .selected_text(preset_name(settings.from))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.from, None, "Current");
+                    for (index, preset) in PRESETS.iter().enumerate() {
+                        ui.selectable_value(&mut settings.from, Some(index), preset.name);
+                    }
+                });
+            egui::ComboBox::from_label("To")
+                .selected_text(PRESETS[settings.to].name)
+                .show_ui(ui, |ui| {
+                    for (index, preset) in PRESETS.iter().enumerate() {
+                        ui.selectable_value(&mut settings.to, index, preset.name);
+                    }
+                });
+            ui.add(
+                egui::Slider::new(&mut settings.duration, 1.0..=120.0)
+                    .logarithmic(true)
+                    .suffix(" s")
+                    .text("Duration"),
+            );
+            egui::ComboBox::from_label("Easing")
+                .selected_text(settings.easing.name())
+                .show_ui(ui, |ui| {
+                    for easing in Easing::ALL {
+                        ui.selectable_value(&mut settings.easing, easing, easing.name());
+                    }
+                });
+        })
+        .response
+        .on_disabled_hover_text("Stop the morph to change it");
+
+        if let Some(morph) = &self.morph {
+            ui.add(egui::ProgressBar::new(morph.progress()).show_percentage());
+            if ui.button("Stop").clicked() {
+                self.morph = None;
+            }
+        } else if ui
+            .button("Morph")
+            .on_hover_text(
+                "Carry every numeric parameter over to the target preset, \
+                 keeping the particles",
+            )
+            .clicked()
+        {
+            self.start_morph();
+        }
+    }
+
+    #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+    fn handle_tray(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        use crate::tray::TrayAction;
+
+        while let Some(action) = self.tray.poll() {
+            match action {
+                TrayAction::TogglePause => {
+                    let paused = self.simulation.is_paused();
+                    self.set_paused(!paused);
+                }
+                TrayAction::Preset(index) => {
+                    if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+                        self.apply_preset(
+                            index,
+                            &wgpu_render_state.device,
+                            &wgpu_render_state.queue,
+                        );
+                    }
+                }
+                TrayAction::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            }
+        }
+    }
+
+    /// Switches from the compute shader to the CPU backend after a GPU error,
+    /// keeping the parameters, count and pause state. The particles are
+    /// regenerated, the GPU copy can't be trusted after a failed submission.
+    fn handle_gpu_errors(&mut self, frame: &eframe::Frame) {
+        // Wait for guarded allocations to resolve, their fallout is undone there
+        if self.allocations.is_pending() {
+            return;
+        }
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+        if let Some(fallback) = &self.gpu_fallback {
+            match fallback.copy.poll(device) {
+                CopyState::Pending => {}
+                CopyState::Done(particles) => self.fall_back_to_cpu(Some(particles), device, queue),
+                CopyState::Failed => self.fall_back_to_cpu(None, device, queue),
+            }
+            return;
+        }
+        let Some(error) = self.gpu_error.lock().ok().and_then(|mut slot| slot.take()) else {
+            return;
+        };
+        if matches!(
+            self.current_method,
+            SimulationMethod::ComputeShader | SimulationMethod::Liquid | SimulationMethod::Mpm
+        ) {
+            // Stopped until the particles are back, they move to the CPU
+            // backend as they are now
+            let was_paused = self.simulation.is_paused();
+            self.set_paused(true);
+            let copy = ParticleCopy::new(
+                device,
+                queue,
+                self.simulation.get_particle_buffer(),
+                self.simulation.get_particle_count(),
+            );
+            self.gpu_fallback = Some(GpuFallback {
+                copy,
+                error,
+                was_paused,
+            });
+        } else {
+            self.notice = Some(format!("GPU error: {error}"));
+        }
+    }
+
+    /// Switches a failed compute backend to the CPU one, seeded with the
+    /// `particles` read back from it. Without them, as when the device was
+    /// lost, the last healthy configuration is respawned instead.
+    fn fall_back_to_cpu(
+        &mut self,
+        particles: Option<Vec<Particle>>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let Some(fallback) = self.gpu_fallback.take() else {
+            return;
+        };
+        let error = fallback.error;
+        self.change_simulation_method(SimulationMethod::Cpu, device);
+        self.notice = Some(match particles {
+            Some(particles) => {
+                self.simulation.set_particles(device, queue, &particles);
+                format!(
+                    "The compute shader failed, switched to the CPU backend with the \
+                     particles where they were.\n{error}"
+                )
+            }
+            None => match self.watchdog.checkpoint().cloned() {
+                Some(checkpoint) => {
+                    self.apply_settings(checkpoint.settings, device, queue);
+                    self.sim_time = 0.0;
+                    format!(
+                        "The compute shader failed and its particles couldn't be read \
+                         back, switched to the CPU backend and respawned {}.\n{error}",
+                        checkpoint.what
+                    )
+                }
+                None => format!(
+                    "The compute shader failed and its particles couldn't be read back, \
+                     switched to the CPU backend and respawned them.\n{error}"
+                ),
+            },
+        });
+        self.set_paused(fallback.was_paused);
+    }
+
+    fn show_notice(&mut self, ctx: &egui::Context) {
+        let Some(notice) = &self.notice else {
+            return;
+        };
+        let mut dismissed = false;
+        egui::Area::new(egui::Id::new("notice"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 12.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(480.0);
+                    ui.label(notice);
+                    dismissed = ui.button("Dismiss").clicked();
+                });
+            });
+        if dismissed {
+            self.notice = None;
+        }
+    }
+
+    /// What blew up and what probably did it, with a way back to the last
+    /// healthy configuration
+    fn show_watchdog(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let Some(divergence) = &self.watchdog.tripped else {
+            return;
+        };
+        let message = self.describe_divergence(divergence);
+        let can_roll_back = divergence.checkpoint.is_some();
+        let (mut roll_back, mut dismissed) = (false, false);
+        egui::Area::new(egui::Id::new("watchdog"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(480.0);
+                    ui.heading("Simulation paused");
+                    ui.label(message);
+                    ui.horizontal(|ui| {
+                        roll_back = ui
+                            .add_enabled(can_roll_back, egui::Button::new("Roll Back"))
+                            .on_hover_text("Restore the last settings that ran healthy and respawn the particles")
+                            .on_disabled_hover_text("It blew up before any settings ran healthy for long")
+                            .clicked();
+                        dismissed = ui.button("Dismiss").clicked();
+                    });
+                });
+            });
+
+        if roll_back
+            && let Some(checkpoint) = self
+                .watchdog
+                .tripped
+                .take()
+                .and_then(|divergence| divergence.checkpoint)
+            && let Some(wgpu_render_state) = frame.wgpu_render_state()
+        {
+            let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+            self.change_simulation_method(checkpoint.method, device);
+            self.apply_settings(checkpoint.settings, device, queue);
+            self.sim_time = 0.0;
+            self.watchdog.dismiss();
+            self.set_paused(false);
+        } else if dismissed {
+            self.watchdog.dismiss();
+        }
+    }
+
+    /// Settings known to blow up at the current step, each with a button
+    /// pulling it back to where it's stable
+    fn stability_warnings_ui(&mut self, ui: &mut egui::Ui) {
+        let step_delta = self.step_delta();
+        for instability in watchdog::instabilities(&self.settings, step_delta) {
+            let parameter = parameter(instability.key);
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("⚠ {}", instability.reason),
+                )
+                .on_hover_text("More sub-steps keep it stable too");
+                if ui
+                    .small_button("Clamp")
+                    .on_hover_text(format!(
+                        "Set {} to {:.3}",
+                        parameter.name, instability.limit
+                    ))
+                    .clicked()
+                {
+                    parameter.set_value(self, instability.limit);
+                }
+            });
+        }
+    }
+
+    fn describe_divergence(&self, divergence: &Divergence) -> String {
+        let health = divergence.health;
+        let mut message = if health.non_finite > 0 {
+            format!(
+                "{} particles went NaN or infinite.",
+                format::count(health.non_finite as u64)
+            )
+        } else {
+            format!(
+                "Particles ran away, {} units from the origin at up to {} units/s.",
+                format::si(health.max_distance as f64),
+                format::si(health.max_speed as f64)
+            )
+        };
+
+        if !divergence.changed.is_empty() {
+            let changed: Vec<&str> = divergence
+                .changed
+                .iter()
+                .map(|key| {
+                    if is_parameter(key) {
+                        parameter(key).name
+                    } else {
+                        key.as_str()
+                    }
+                })
+                .collect();
+            message += &format!(
+                "\nChanged since it last ran healthy: {}.",
+                changed.join(", ")
+            );
+        }
+        let step_delta = self.step_delta();
+        for instability in watchdog::instabilities(&self.settings, step_delta) {
+            message += &format!("\n{}.", instability.reason);
+        }
+        if self.settings.integrator == Integrator::Euler {
+            message += "\nStrong forces can outrun the Euler integrator, Velocity Verlet or more sub-steps keep them stable.";
+        } else if self.settings.substeps < MAX_SUBSTEPS {
+            message += "\nMore sub-steps keep strong forces stable.";
+        }
+        message
+    }
+
+    /// Runs the commands queued from outside the app since the last frame
+    fn handle_commands(&mut self, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+
+        for command in commands::drain() {
+            match command {
+                Command::SetParams { params } => {
+                    self.set_unsaved_parameters(&params);
+                    match self.settings().merged(&params) {
+                        Ok(settings) => {
+                            self.current_preset = None;
+                            if settings.particle_count == self.simulation.get_particle_count()
+                                && settings.generation == self.settings.generation
+                            {
+                                self.set_parameters(settings);
+                            } else {
+                                self.apply_settings(settings, device, queue);
+                            }
+                        }
+                        Err(error) => self.settings_status = Some(error),
+                    }
+                }
+                Command::Pause => self.set_paused(true),
+                Command::Resume => self.set_paused(false),
+                Command::Reset => self.reset(device, queue),
+                Command::ApplyPreset { name } => {
+                    match PRESETS
+                        .iter()
+                        .position(|preset| preset.name.eq_ignore_ascii_case(&name))
+                    {
+                        Some(index) => self.apply_preset(index, device, queue),
+                        None => self.settings_status = Some(format!("No preset named \"{name}\"")),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts carrying every parameter the target preset sets from the start
+    /// preset, or from where it is, to the target's value
+    fn start_morph(&mut self) {
+        let settings = self.morph_settings;
+        let Some(target) = PRESETS.get(settings.to) else {
+            return;
+        };
+        let to_value =
+            |settings: Settings| serde_json::to_value(settings).expect("settings always serialize");
+        let end = to_value((target.settings)());
+        let start = settings
+            .from
+            .and_then(|index| PRESETS.get(index))
+            .map(|preset| to_value((preset.settings)()));
+        // Toggles as 0 and 1, the way the registry sets them from numbers
+        let number = |value: &serde_json::Value| {
+            value
+                .as_f64()
+                .map(|number| number as f32)
+                .or_else(|| value.as_bool().map(|on| on as u32 as f32))
+        };
+
+        let mut tracks = BTreeMap::new();
+        for parameter in PARAMETERS {
+            let Some(end) = number(&end[parameter.key]) else {
+                continue;
+            };
+            let start = match &start {
+                Some(start) => number(&start[parameter.key]).unwrap_or(end),
+                None => parameter.value(self),
+            };
+            tracks.insert(parameter.key, (start, end));
+        }
+        self.morph = Some(Morph::new(
+            settings.to,
+            tracks,
+            settings.duration,
+            settings.easing,
+        ));
+        self.current_preset = None;
+    }
+
+    /// Moves the morph on, except for the bound parameters which their
+    /// expression drives. Once done the target preset's other settings are
+    /// applied, keeping the particles.
+    fn update_morph(&mut self, ctx: &egui::Context) {
+        let Some(morph) = &mut self.morph else {
+            return;
+        };
+        morph.advance(ctx.input(|input| input.stable_dt));
+        let values: Vec<(&str, f32)> = morph.values().collect();
+        let finished = morph.is_finished().then_some(morph.target);
+        for (key, value) in values {
+            if !self.bindings.contains_key(key) {
+                parameter(key).set_value(self, value);
+            }
+        }
+
+        if let Some(target) = finished {
+            self.morph = None;
+            self.set_parameters(Settings {
+                particle_count: self.simulation.get_particle_count(),
+                generation: self.settings.generation,
+                ..(PRESETS[target].settings)()
+            });
+        }
+    }
+
+    /// Drifts the parameters picked for evolving, except the bound ones which
+    /// their expression drives
+    fn update_evolve(&mut self, ctx: &egui::Context) {
+        if !self.evolve.enabled {
+            return;
+        }
+        self.evolve.advance(ctx.input(|input| input.stable_dt));
+        for parameter in PARAMETERS {
+            if self.bindings.contains_key(parameter.key) {
+                continue;
+            }
+            if let Some(value) = self.evolve.value(parameter.key) {
+                parameter.set_value(self, value);
+            }
+        }
+    }
+
+    /// Sets the bound parameters from their expressions, which all see the
+    /// values from before this frame's and `t` as the simulated seconds
+    fn update_bindings(&mut self) {
+        if self.bindings.is_empty() {
+            return;
+        }
+        let values: HashMap<&str, f32> = PARAMETERS
+            .iter()
+            .map(|parameter| (parameter.key, parameter.value(self)))
+            .collect();
+        let time = self.sim_time;
+        let variable = |name: &str| match name {
+            bindings::TIME => time,
+            _ => values.get(name).copied().unwrap_or_default(),
+        };
+        let updates: Vec<(&Param, f32)> = self
+            .bindings
+            .iter()
+            .filter_map(|(key, binding)| Some((parameter(key), binding.evaluate(variable)?)))
+            .collect();
+        for (parameter, value) in updates {
+            parameter.set_value(self, value);
+        }
+    }
+
+    /// The registered parameters in `params` that aren't part of [`Settings`],
+    /// like `"annotations.enabled"`, which merging the settings would drop
+    fn set_unsaved_parameters(&mut self, params: &serde_json::Value) {
+        let Some(params) = params.as_object() else {
+            return;
+        };
+        for (key, value) in params {
+            if let Some(parameter) = PARAMETERS
+                .iter()
+                .find(|parameter| parameter.key == key && key.contains('.'))
+            {
+                parameter.set(self, value);
+            }
+        }
+    }
+
+    /// Starts the screensaver when asked to or after the idle timeout, and
+    /// drives the camera and preset cycle while it runs
+    fn update_screensaver(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+        let input = Screensaver::had_input(ctx);
+        let delta_time = ctx.input(|input| input.stable_dt);
+
+        if !self.screensaver.active {
+            if !self.screensaver.should_start(input, delta_time) {
+                return;
+            }
+            let first = self.screensaver.start(Restore {
+                settings: self.settings(),
+                preset: self.current_preset,
+                camera_position: self.camera.position,
+                camera_yaw: self.camera.yaw,
+                camera_pitch: self.camera.pitch,
+                show_ui: self.show_ui,
+            });
+            self.show_ui = false;
+            self.apply_preset(first, device, queue);
+        }
+
+        match self.screensaver.update(input, delta_time) {
+            ScreensaverStep::Continue => {}
+            ScreensaverStep::Preset(index) => self.apply_preset(index, device, queue),
+            ScreensaverStep::Exit if self.screensaver.exit_on_input => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            ScreensaverStep::Exit => {
+                if let Some(restore) = self.screensaver.stop() {
+                    self.apply_settings(restore.settings, device, queue);
+                    self.current_preset = restore.preset;
+                    self.camera.position = restore.camera_position;
+                    self.camera.yaw = restore.camera_yaw;
+                    self.camera.pitch = restore.camera_pitch;
+                    self.camera.update_view_proj();
+                    self.show_ui = restore.show_ui;
+                }
+                return;
+            }
+        }
+
+        let (position, yaw, pitch) = self.screensaver.camera_pose();
+        self.camera.position = position;
+        self.camera.yaw = yaw;
+        self.camera.pitch = pitch;
+        self.camera.update_view_proj();
+        ctx.set_cursor_icon(egui::CursorIcon::None);
+    }
+
+    /// Mirrors particle count, FPS and pause state in the window title. The FPS
+    /// only changes once a second, so the title is only formatted and sent
+    /// when something in it did.
+    fn update_window_title(&mut self, ctx: &egui::Context) {
+        let shown = (
+            self.simulation.get_particle_count(),
+            self.fps,
+            self.simulation.is_paused(),
+        );
+        if self.window_title == Some(shown) {
+            return;
+        }
+        self.window_title = Some(shown);
+
+        let (particle_count, fps, paused) = shown;
+        let mut title = format!(
+            "Particle Simulation 3D - {} particles - {:.0} FPS",
+            format::si(particle_count as f64),
+            fps
+        );
+        if paused {
+            title.push_str(" - Paused");
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+    }
+
+    fn render_advisor_ui(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let advice = advisor::advise(&PerformanceSnapshot {
+            fps: self.fps,
+            update_ms: self.simulation_update_time,
+            particle_count: self.simulation.get_particle_count(),
+            method: self.current_method,
+            compute_available: self
+                .available_methods
+                .contains(&SimulationMethod::ComputeShader),
+            ghosts_shown: self.settings.boundary_mode == BOUNDARY_PERIODIC
+                && self.settings.show_ghosts,
+            rdf_enabled: self.settings.rdf_enabled,
+            pair_forces: self.settings.reactions_enabled
+                || self.settings.conduction > 0.0
+                || self.settings.dipoles_enabled
+                || self.settings.coulomb_enabled
+                || self.settings.particle_life_enabled
+                || self.settings.lj_enabled,
+            throttled: self.power_saver.throttled(),
+        });
+        if advice.is_empty() {
+            ui.label("Nothing to improve");
+            return;
+        }
+
+        let mut apply = None;
+        for item in advice {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(item.message);
+                if let Some(fix) = item.fix
+                    && ui.button("Apply").clicked()
+                {
+                    apply = Some(fix);
+                }
+            });
+        }
+        let (Some(fix), Some(wgpu_render_state)) = (apply, frame.wgpu_render_state()) else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+        match fix {
+            Fix::SwitchToCompute => {
+                self.change_simulation_method(SimulationMethod::ComputeShader, device);
+            }
+            Fix::SetParticleCount(count) => {
+                self.ui_particle_count = count;
+                self.simulation
+                    .resize_buffer(device, queue, count, self.settings.generation);
+            }
+            Fix::HideGhosts => self.settings.show_ghosts = false,
+            Fix::DisableRdf => {
+                self.settings.rdf_enabled = false;
+                self.rdf = None;
+            }
+        }
+    }
+
+    fn record_usage(&mut self, delta_time: f32) {
+        let particle_count = self.simulation.get_particle_count();
+        let throughput =
+            (!self.simulation.is_paused()).then_some(self.fps as f64 * particle_count as f64);
+        self.usage.record_frame(
+            delta_time,
+            particle_count,
+            self.current_method.name(),
+            throughput,
+        );
+        self.usage.record_features(&[
+            ("Reactions", self.settings.reactions_enabled),
+            ("Heat conduction", self.settings.conduction > 0.0),
+            ("Dipole forces", self.settings.dipoles_enabled),
+            ("Lorentz force", self.settings.lorentz_enabled),
+            ("Coulomb force", self.settings.coulomb_enabled),
+            ("Particle life", self.settings.particle_life_enabled),
+            (
+                "Strange attractor",
+                self.settings.strange_mode != STRANGE_NONE,
+            ),
+            ("Lennard-Jones", self.settings.lj_enabled),
+            ("Thermostat", self.settings.thermostat_enabled),
+            (
+                "Periodic boundaries",
+                self.settings.boundary_mode == BOUNDARY_PERIODIC,
+            ),
+            ("g(r) measurement", self.settings.rdf_enabled),
+            ("Group statistics", self.settings.group_stats_enabled),
+            ("Central attractor", self.settings.attractor_enabled),
+            ("Continuous respawn", self.settings.respawn_enabled),
+            ("Orbit tutorial", self.orbit_tutorial.active),
+            ("Annotations", self.annotations.enabled),
+            ("Screensaver", self.screensaver.active),
+        ]);
+    }
+
+    fn publish_stats(&self) {
+        commands::publish_stats(Stats {
+            particles: self.simulation.get_particle_count(),
+            fps: self.fps,
+            paused: self.simulation.is_paused(),
+            method: self.current_method.name(),
+            preset: self.current_preset.map(|index| PRESETS[index].name),
+        });
+    }
+
+    fn sync_reaction_rules(&mut self) {
+        let rules: &[ReactionRule] = if self.settings.reactions_enabled {
+            &self.settings.reaction_rules
+        } else {
+            &[]
+        };
+        self.simulation.set_reaction_rules(rules);
+    }
+
+    fn sync_obstacles(&mut self) {
+        self.simulation.set_obstacles(&self.settings.obstacles);
+        self.simulation
+            .set_obstacle_mesh(self.obstacle_mesh.clone());
+    }
+
+    fn sync_point_attractors(&mut self) {
+        self.simulation
+            .set_point_attractors(&self.settings.point_attractors);
+    }
+
+    fn sync_forces(&mut self) {
+        self.simulation.set_forces(&self.settings.forces);
+    }
+
+    fn sync_field_graph(&mut self) {
+        self.simulation.set_field_graph(&self.settings.field_graph);
+    }
+
+    fn sync_species_interactions(&mut self) {
+        self.simulation
+            .set_species_interactions(&self.settings.particle_life_matrix);
+    }
+
+    /// Voxelizes .obj files dropped on the window into the mesh obstacle
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            let name = file
+                .path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map_or(file.name.clone(), |name| {
+                    name.to_string_lossy().into_owned()
+                });
+            let lowercase = name.to_lowercase();
+            if !lowercase.ends_with(".obj") && !lowercase.ends_with(".psrec") {
+                continue;
+            }
+            let bytes = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Ok(bytes.to_vec()),
+                (None, Some(path)) => std::fs::read(path).map_err(|e| e.to_string()),
+                (None, None) => Err("no contents".to_owned()),
+            };
+            if lowercase.ends_with(".psrec") {
+                match bytes.and_then(|bytes| Recording::from_bytes(&bytes)) {
+                    Ok(recording) => {
+                        self.stop_playback();
+                        self.recording = Some(recording);
+                        self.start_playback();
+                    }
+                    Err(error) => self.notice = Some(format!("Couldn't load {name}: {error}")),
+                }
+                continue;
+            }
+            let mesh = bytes.and_then(|bytes| MeshSdf::from_obj(&String::from_utf8_lossy(&bytes)));
+            match mesh {
+                Ok(mesh) => {
+                    self.obstacle_mesh = Arc::new(mesh);
+                    self.obstacle_mesh_name = Some(name);
+                    if self.settings.obstacles.len() < MAX_OBSTACLES
+                        && !self
+                            .settings
+                            .obstacles
+                            .iter()
+                            .any(|obstacle| obstacle.shape == ObstacleShape::Mesh)
+                    {
+                        self.settings.obstacles.push(Obstacle {
+                            shape: ObstacleShape::Mesh,
+                            ..Default::default()
+                        });
+                    }
+                    self.sync_obstacles();
+                }
+                Err(error) => self.notice = Some(format!("Couldn't load {name}: {error}")),
+            }
+        }
+    }
+
+    fn update_simulation(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Calculate delta time
+        let now = Instant::now();
+        let delta_time = now.duration_since(self.last_update).as_secs_f32();
+
+        self.last_update = now;
+
+        // Update FPS counter
+        self.fps_counter += 1;
+        self.fps_timer += delta_time;
+        if self.fps_timer >= 1.0 {
+            self.fps = self.fps_counter as f32 / self.fps_timer;
+            #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+            {
+                let allocations = crate::alloc_count::allocations();
+                let per_frame =
+                    (allocations - self.heap_allocations.0) as f32 / self.fps_counter as f32;
+                self.heap_allocations = (allocations, per_frame);
+            }
+            self.fps_counter = 0;
+            self.fps_timer = 0.0;
+            self.statistics_text = None;
+        }
+        self.power_saver.update(
+            self.fps,
+            self.simulation.get_particle_count(),
+            self.simulation.is_paused(),
+            delta_time,
+        );
+        self.record_usage(delta_time);
+
+        // Handle keyboard input for camera movement
+        for key in [
+            egui::Key::W,
+            egui::Key::S,
+            egui::Key::A,
+            egui::Key::D,
+            egui::Key::Space,
+        ] {
+            if self.keys_down.contains(&key) {
+                self.camera
+                    .process_keyboard(Some(key), self.shift_down, delta_time);
+            }
+        }
+
+        if self.shift_down {
+            self.camera.process_keyboard(None, true, delta_time);
+        }
+
+        self.sync_orbit_mass();
+
+        // Get wgpu render state for queue access
+        if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+            let queue = &wgpu_render_state.queue;
+            let device = &wgpu_render_state.device;
+
+            // Update camera uniform buffer
+            self.camera.update_buffer(queue);
+
+            // Handle mouse position for particle interaction
+            if self.mouse_dragging {
+                let screen_rect = ctx.content_rect();
+                let (x, y) = self.mouse_pos;
+
+                // Convert screen coordinates to normalized device coordinates
+                let ndc_x = (2.0 * x / screen_rect.width()) - 1.0;
+                let ndc_y = 1.0 - (2.0 * y / screen_rect.height());
+
+                // Calculate world position using camera
+                let camera_forward = self.camera.get_forward();
+                let camera_right = self.camera.get_right();
+                let camera_up = self.camera.get_up();
+
+                let current_pos = glam::Vec3::new(
+                    self.mouse_position[0],
+                    self.mouse_position[1],
+                    self.mouse_position[2],
+                );
+
+                let camera_pos = self.camera.position;
+                let to_cursor = current_pos - camera_pos;
+                let distance = to_cursor.dot(camera_forward);
+
+                // Calculate the plane at the specified distance from camera
+                let plane_center = camera_pos + camera_forward * distance;
+
+                // Scale the NDC coordinates based on the field of view and distance
+                let height = 2.0 * distance * (self.camera.fov / 2.0).tan();
+                let width = height * self.camera.aspect;
+
+                let world_pos = plane_center
+                    + camera_right * (ndc_x * width / 2.0)
+                    + camera_up * (ndc_y * height / 2.0);
+
+                self.mouse_position = [world_pos.x, world_pos.y, world_pos.z];
+            }
+
+            self.couple_layers(device, queue);
+
+            // Update particle simulation in fixed steps if not paused, or by one.
+            // Exports take exactly the steps their next frame is due.
+            #[cfg(not(target_arch = "wasm32"))]
+            let exporting = self
+                .export
+                .as_ref()
+                .map(|export| export.due_steps(self.settings.fixed_step_rate));
+            #[cfg(target_arch = "wasm32")]
+            let exporting: Option<u32> = None;
+            let stepping = std::mem::take(&mut self.step_requested);
+            let paused = self.simulation.is_paused();
+            let due_steps = self
+                .timestep
+                .advance(delta_time, self.settings.fixed_step_rate);
+            let steps = match exporting {
+                Some(steps) => steps,
+                None if self.playback.is_some() || self.benchmark.is_some() => 0,
+                None if paused => stepping as u32,
+                None => due_steps,
+            };
+            if steps > 0 {
+                let step_delta = self.step_delta();
+                if self.watchdog.clamp_unstable && !self.watchdog.expert_mode {
+                    for instability in watchdog::instabilities(&self.settings, step_delta) {
+                        parameter(instability.key).set_value(self, instability.limit);
+                    }
+                }
+                let update_start = Instant::now();
+
+                // Build simulation parameters
+                let mut sim_params = self.step_params(step_delta);
+                sim_params.mouse_position = self.mouse_position;
+                sim_params.is_mouse_dragging = (self.mouse_dragging && exporting.is_none()) as u32;
+
+                self.run_steps(
+                    device,
+                    queue,
+                    &mut sim_params,
+                    steps,
+                    self.settings.interpolation,
+                );
+
+                if let Some(recorder) = &mut self.recorder
+                    && let Err(error) = recorder.update(
+                        device,
+                        queue,
+                        self.simulation.get_particle_buffer(),
+                        self.simulation.get_particle_count(),
+                        step_delta * steps as f32,
+                    )
+                {
+                    self.stop_recording();
+                    // Keeps where it was saved
+                    let saved = self.notice.take().unwrap_or_default();
+                    self.notice = Some(format!("Recording stopped, {error}. {saved}"));
+                }
+
+                let update_time_ms = update_start.elapsed().as_secs_f32() * 1000.0;
+                const ALPHA: f32 = 0.1;
+                self.simulation_update_time =
+                    (1.0 - ALPHA) * self.simulation_update_time + ALPHA * update_time_ms;
+
+                if self.annotations.enabled {
+                    let indices = self
+                        .annotations
+                        .sample_indices(self.simulation.get_particle_count());
+                    let sampled = self.simulation.sample_particles(device, queue, &indices);
+                    self.annotations.update(
+                        &indices,
+                        sampled,
+                        &sim_params,
+                        &self.settings.forces,
+                        step_delta * steps as f32,
+                    );
+                }
+            }
+
+            self.update_playback(device, queue, delta_time);
+
+            // Paused, exported and played back particles are drawn where
+            // they are
+            let alpha = if self.settings.interpolation
+                && !paused
+                && exporting.is_none()
+                && self.playback.is_none()
+            {
+                self.timestep.alpha(self.settings.fixed_step_rate)
+            } else {
+                1.0
+            };
+            self.renderer.update_interpolation(queue, alpha);
+            // Every frame, the camera may have moved even when paused
+            self.renderer.sort_particles(
+                device,
+                queue,
+                self.simulation.get_particle_buffer(),
+                self.simulation.get_particle_count(),
+                self.settings.draw_order,
+                &self.camera,
+            );
+
+            self.update_layers(device, queue, due_steps, stepping);
+
+            if !paused {
+                // Adaptive sub-steps follow the fastest particle every frame,
+                // the watchdog only looks now and then
+                let watchdog_due = self.watchdog.due(delta_time);
+                if (watchdog_due || self.settings.adaptive_timestep)
+                    && let Some(health) = self.simulation.check_health(device, queue)
+                {
+                    self.adaptive_substeps = timestep::adaptive_substeps(
+                        self.settings.substeps,
+                        health.max_speed,
+                        self.step_delta(),
+                        self.settings.max_travel,
+                    );
+                    self.latest_health = Some(health);
+                }
+                if watchdog_due
+                    && let Some(health) = self.latest_health.take()
+                    && self
+                        .watchdog
+                        .report(health, self.checkpoint("the last healthy state".to_owned()))
+                {
+                    self.set_paused(true);
+                }
+            }
+
+            // Refreshed a few times per second like g(r), GPU backends hand
+            // out the sample asked for on the previous refresh
+            self.group_stats_timer += delta_time;
+            if self.settings.group_stats_enabled && self.group_stats_timer >= 0.25 {
+                self.group_stats_timer = 0.0;
+                if let Some(mut stats) = self.simulation.group_statistics(device, queue) {
+                    sort_groups(&mut stats, self.group_sort);
+                    self.group_stats = Some(stats);
+                }
+            }
+
+            // GPU backends hand out the count asked for on the previous call
+            if self.settings.lifetime_enabled && self.settings.emission_rate > 0.0 {
+                if let Some(alive) = self.simulation.alive_count(device, queue) {
+                    self.alive_count = Some(alive);
+                }
+            } else {
+                self.alive_count = None;
+            }
+        }
+
+        // Refresh g(r) a few times per second, it's too expensive for every frame
+        self.rdf_timer += delta_time;
+        if self.settings.rdf_enabled && self.rdf_timer >= 0.25 {
+            self.rdf_timer = 0.0;
+            let periodic_box = (self.settings.boundary_mode == BOUNDARY_PERIODIC)
+                .then_some(self.settings.box_half_extents);
+            self.rdf = self.simulation.radial_distribution(
+                self.settings.rdf_max_radius,
+                RDF_BINS,
+                periodic_box,
+            );
+        }
+    }
+
+    /// Simulated seconds per step, the time scale's share of the step rate.
+    /// Sub-steps split it further, so they see the scaled step too.
+    fn step_delta(&self) -> f32 {
+        let scale = if self.slow_motion {
+            self.time_scale * SLOW_MOTION
+        } else {
+            self.time_scale
+        };
+        scale / self.settings.fixed_step_rate
+    }
+
+    /// Sub-steps each step is split into, more than set while adaptive
+    /// sub-steps are catching up with a fast particle
+    fn current_substeps(&self) -> u32 {
+        if self.settings.adaptive_timestep && !self.settings.deterministic {
+            self.settings.substeps.max(self.adaptive_substeps)
+        } else {
+            self.settings.substeps
+        }
+    }
+
+    /// Uniforms for one sub-step of a step of `delta_time`, split into the
+    /// sub-steps currently taken
+    fn step_params(&self, delta_time: f32) -> SimParams {
+        self.settings.sim_params(
+            delta_time,
+            self.current_substeps(),
+            self.step,
+            self.simulation.get_particle_count(),
+        )
+    }
+
+    /// Advances the simulation by `steps` steps of `sim_params.delta_time`,
+    /// split into the sub-steps, remembering the particles before the last
+    /// one if `remember_last` for interpolation
+    fn run_steps(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sim_params: &mut SimParams,
+        steps: u32,
+        remember_last: bool,
+    ) {
+        let step_delta = self.step_delta();
+        for step in 0..steps {
+            if step + 1 == steps && remember_last {
+                self.renderer.remember_particles(
+                    device,
+                    queue,
+                    self.simulation.get_particle_buffer(),
+                );
+            }
+            self.sim_time += step_delta;
+            sim_params.turbulence_scroll = self.sim_time * self.settings.turbulence_speed;
+            sim_params.time = self.sim_time;
+
+            for _ in 0..self.current_substeps() {
+                // One submit per sub-step, each writes its own parameters
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Particle Update Encoder"),
+                });
+                sim_params.step = self.step;
+                self.step = self.step.wrapping_add(1);
+
+                // Run the particle simulation using current method
+                self.allocations.begin_update(device);
+                self.simulation
+                    .update(device, queue, &mut encoder, sim_params);
+                self.allocations.end_update(device);
+                queue.submit(Some(encoder.finish()));
+            }
+        }
+    }
+
+    fn render_ui(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // An empty tree doesn't allocate, unlike building the default layout
+        let mut layout =
+            std::mem::replace(&mut self.panel_layout, egui_tiles::Tree::empty("panels"));
+        egui::Window::new("Particle Simulator")
+            .resizable(true)
+            .default_size([340.0, 640.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings_query)
+                            .hint_text("Search settings")
+                            .desired_width(160.0),
+                    );
+                    if ui
+                        .small_button("Reset Layout")
+                        .on_hover_text("Put every panel back where it started")
+                        .clicked()
+                    {
+                        layout = panels::default_layout();
+                    }
+                });
+                if self.settings_query.trim().is_empty() {
+                    let mut behavior = PanelBehavior {
+                        show: |ui: &mut egui::Ui, panel| self.render_panel(ui, frame, panel),
+                    };
+                    layout.ui(&mut behavior, ui);
+                } else {
+                    egui::ScrollArea::vertical()
+                        .auto_shrink(false)
+                        .show(ui, |ui| self.render_search_results(ui));
+                }
+            });
+        self.panel_layout = layout;
+    }
+
+    /// The parameters matching the search, grouped by the panel they're in
+    fn render_search_results(&mut self, ui: &mut egui::Ui) {
+        let query = self.settings_query.trim().to_lowercase();
+        let mut found = false;
+        for panel in Panel::ALL {
+            let mut heading = Some(panel.name());
+            for parameter in PARAMETERS.iter().filter(|p| p.panel == panel) {
+                let Some(highlight) = parameter.find(&query) else {
+                    continue;
+                };
+                if let Some(name) = heading.take() {
+                    ui.heading(name);
+                }
+                found = true;
+                ui.weak(parameter.section);
+                let reason = parameter
+                    .capability
+                    .and_then(|capability| self.unsupported_reason(capability));
+                capability_scope(ui, reason, |ui| {
+                    self.show_parameter(ui, parameter, highlight)
+                });
+            }
+        }
+        if !found {
+            ui.weak("No settings match");
+        }
+    }
+
+    /// The registered parameter `key`, as the panels show it
+    fn parameter_ui(&mut self, ui: &mut egui::Ui, key: &str) -> egui::Response {
+        self.show_parameter(ui, parameter(key), 0..0)
+    }
+
+    /// Draws `parameter` with a context menu to put back its default
+    fn show_parameter(
+        &mut self,
+        ui: &mut egui::Ui,
+        parameter: &Param,
+        highlight: std::ops::Range<usize>,
+    ) -> egui::Response {
+        let mut response = parameter.show(ui, self, highlight);
+        if let Some(binding) = self.bindings.get_mut(parameter.key) {
+            // A manual edit wins until the binding is resumed
+            if response.changed() {
+                binding.paused = true;
+            }
+            let state = if binding.paused { "paused" } else { "active" };
+            response = response.on_hover_text(format!("= {} ({state})", binding.text));
+        }
+        response.context_menu(|ui| {
+            let default = serde_json::to_value(Settings::default())
+                .ok()
+                .and_then(|settings| settings.get(parameter.key).cloned());
+            let clicked = ui
+                .add_enabled(default.is_some(), egui::Button::new("Reset to Default"))
+                .on_disabled_hover_text("Not part of the saved settings")
+                .clicked();
+            if clicked && let Some(default) = default {
+                parameter.set(self, &default);
+                if let Some(binding) = self.bindings.get_mut(parameter.key) {
+                    binding.paused = true;
+                }
+                ui.close();
+            }
+            if self.bindings.contains_key(parameter.key) {
+                if ui.button("Remove Binding").clicked() {
+                    self.bindings.remove(parameter.key);
+                    ui.close();
+                }
+            } else if ui.button("Bind to Expression").clicked() {
+                // Starts out constant, edited under Bindings
+                let text = parameter.value(self).to_string();
+                self.bindings
+                    .insert(parameter.key, Binding::new(text, is_parameter));
+                ui.close();
+            }
+        });
+        response
+    }
+
+    fn render_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame, panel: Panel) {
+        match panel {
+            Panel::Statistics => self.render_statistics_panel(ui, frame),
+            Panel::Physics => self.render_physics_panel(ui, frame),
+            Panel::Generation => self.render_generation_panel(ui, frame),
+            Panel::Display => self.render_display_panel(ui, frame),
+            Panel::Camera => self.render_camera_panel(ui, frame),
+            Panel::Layers => self.render_layers_ui(ui, frame),
+        }
+    }
+
+    fn render_statistics_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let particle_count = self.simulation.get_particle_count();
+        let text = match &mut self.statistics_text {
+            Some(text) if text.particle_count == particle_count => text,
+            text => text.insert(StatisticsText::new(
+                self.fps,
+                particle_count,
+                self.simulation_update_time,
+                self.alive_count,
+            )),
+        };
+        for line in &text.lines {
+            ui.label(line.as_str());
+        }
+        if let Some(alive) = &text.alive {
+            ui.label(alive.as_str());
+        }
+        #[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+        {
+            if text.allocations.is_empty() {
+                text.allocations = format!("Allocations per frame: {:.1}", self.heap_allocations.1);
+            }
+            ui.label(text.allocations.as_str());
+        }
+        if let Some(reason) = self.power_saver.reason() {
+            ui.label(format!("Capped at 30 FPS: {reason}"));
+        }
+        egui::CollapsingHeader::new("Performance Advisor")
+            .show(ui, |ui| self.render_advisor_ui(ui, frame));
+        egui::CollapsingHeader::new("Benchmark").show(ui, |ui| self.render_benchmark_ui(ui));
+        if let Some(plan) = self.simulation.frame_plan() {
+            egui::CollapsingHeader::new("Frame Graph").show(ui, |ui| {
+                ui.monospace(plan.to_string());
+                if ui
+                    .button("Copy as Graphviz")
+                    .on_hover_text("Paste into any DOT viewer to see the passes as a graph")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(plan.to_dot());
+                }
+            });
+        }
+
+        #[cfg(all(feature = "update-check", not(target_arch = "wasm32")))]
+        {
+            ui.separator();
+            ui.heading("Updates");
+            ui.checkbox(&mut self.update_checker.enabled, "Check for Updates")
+                .on_hover_text("Asks GitHub for the latest release, nothing else is sent");
+            ui.label(self.update_checker.status());
+        }
+
+        ui.separator();
+        ui.heading("About");
+        ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
+        self.usage.show(ui);
+        if ui
+            .button("Wipe Statistics")
+            .on_hover_text("These are only stored on this device")
+            .clicked()
+        {
+            self.usage = UsageStats::default();
+        }
+
+        ui.separator();
+        ui.heading("Controls");
+        ui.label("WASD - Move camera");
+        ui.label("Mouse Right - Rotate camera");
+        ui.label("Space/Shift - Move up/down");
+        ui.label("Mouse Left - Drag particles");
+        ui.label("Mouse Scroll - Cursor Distance");
+        ui.label("U - Toggle UI");
+        ui.label("K - Play/Pause");
+        ui.label(". - Step while paused");
+        ui.label("[ / ] - Slower/Faster");
+        ui.label("M (hold) - Slow motion");
+    }
+
+    fn render_physics_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        ui.heading("Simulation");
+
+        let mut clicked_method = None;
+        egui::ComboBox::from_label("Method")
+            .selected_text(format!("{:?}", self.current_method))
+            .show_ui(ui, |ui| {
+                for method in &self.available_methods {
+                    let text = match method {
+                        SimulationMethod::Cpu => "CPU (Compatible Everywhere)",
+                        SimulationMethod::ComputeShader => "Compute Shader (Fastest)",
+                        SimulationMethod::BarnesHut => "Barnes-Hut N-body (Mutual Gravity)",
+                        SimulationMethod::Boids => "Boids (Flocking)",
+                        SimulationMethod::Liquid => "FLIP Liquid (Sloshing in the Box)",
+                        SimulationMethod::Mpm => "MPM Snow & Sand (Piles and Clumps)",
+                    };
+                    if ui
+                        .selectable_label(self.current_method == *method, text)
+                        .clicked()
+                        && self.current_method != *method
+                    {
+                        clicked_method = Some(*method);
+                    }
+                }
+            });
+
+        if let Some(method) = clicked_method
+            && let Some(wgpu_render_state) = frame.wgpu_render_state()
+        {
+            self.change_simulation_method(method, &wgpu_render_state.device);
+        }
+        self.parameter_ui(ui, "fixed_step_rate");
+        ui.add(egui::Slider::new(&mut self.settings.substeps, 1..=MAX_SUBSTEPS).text("Sub-steps"))
+            .on_hover_text("Splits every step into shorter ones, keeping strong mouse forces and stiff interactions from blowing up");
+        ui.add_enabled_ui(!self.settings.deterministic, |ui| {
+            self.parameter_ui(ui, "adaptive_timestep")
+                .on_disabled_hover_text("Deterministic runs keep the sub-steps fixed");
+        });
+        if self.settings.adaptive_timestep && !self.settings.deterministic {
+            self.parameter_ui(ui, "max_travel");
+            ui.weak(format!("Taking {} sub-steps", self.current_substeps()));
+        }
+        self.parameter_ui(ui, "deterministic");
+        if self.settings.deterministic && self.current_method != SimulationMethod::Cpu {
+            ui.weak("Only the CPU backend repeats runs bit for bit, GPUs may reorder floating point math");
+        }
+        self.parameter_ui(ui, "interpolation");
+        self.parameter_ui(ui, "watchdog.enabled");
+        self.parameter_ui(ui, "watchdog.expert_mode");
+        if !self.watchdog.expert_mode {
+            self.parameter_ui(ui, "watchdog.clamp_unstable");
+            self.stability_warnings_ui(ui);
+        }
+
+        if self.current_method == SimulationMethod::BarnesHut {
+            for key in ["nbody_mass", "nbody_theta", "nbody_softening"] {
+                self.parameter_ui(ui, key);
+            }
+        }
+        if self.current_method == SimulationMethod::Boids {
+            for key in [
+                "boid_radius",
+                "boid_separation",
+                "boid_alignment",
+                "boid_cohesion",
+                "boid_max_speed",
+            ] {
+                self.parameter_ui(ui, key);
+            }
+        }
+        if self.current_method == SimulationMethod::Liquid {
+            self.parameter_ui(ui, "flip_ratio");
+            ui.add(
+                egui::Slider::new(
+                    &mut self.settings.liquid_resolution,
+                    MIN_LIQUID_RESOLUTION..=MAX_LIQUID_RESOLUTION,
+                )
+                .text("Grid Resolution"),
+            )
+            .on_hover_text("Cells along the longest side of the box, more keep finer splashes");
+            ui.add(
+                egui::Slider::new(
+                    &mut self.settings.pressure_iterations,
+                    1..=MAX_PRESSURE_ITERATIONS,
+                )
+                .text("Pressure Iterations"),
+            )
+            .on_hover_text("More keep the liquid from compressing under its own weight");
+            if self.settings.boundary_mode != BOUNDARY_CONTAINER {
+                ui.weak("The liquid fills the box, a container boundary keeps it in");
+            }
+        }
+        if self.current_method == SimulationMethod::Mpm {
+            egui::ComboBox::from_label("Material")
+                .selected_text(self.settings.mpm_material.name())
+                .show_ui(ui, |ui| {
+                    for material in MpmMaterial::ALL {
+                        ui.selectable_value(
+                            &mut self.settings.mpm_material,
+                            material,
+                            material.name(),
+                        );
+                    }
+                });
+            let key = match self.settings.mpm_material {
+                MpmMaterial::Snow => "hardening",
+                MpmMaterial::Sand => "friction_angle",
+            };
+            self.parameter_ui(ui, key);
+            ui.add(
+                egui::Slider::new(
+                    &mut self.settings.mpm_resolution,
+                    MIN_MPM_RESOLUTION..=MAX_MPM_RESOLUTION,
+                )
+                .text("Grid Resolution"),
+            )
+            .on_hover_text(
+                "Cells along the longest side of the box, finer grids want more particles per cell",
+            );
+            if self.settings.boundary_mode != BOUNDARY_CONTAINER {
+                ui.weak("The material fills the box, a container boundary keeps it in");
+            }
+        }
+
+        egui::CollapsingHeader::new("Backend Features").show(ui, |ui| {
+            for capability in Capability::ALL {
+                let mark = if self.simulation.supports(capability) {
+                    "✔"
+                } else {
+                    "✖"
+                };
+                ui.label(format!("{mark} {}", capability.name()));
+            }
+        });
+
+        ui.separator();
+        ui.heading("Particle Settings");
+
+        let direction_controls = |ui: &mut egui::Ui, direction: &mut Vec3| {
+            ui.horizontal(|ui| {
+                ui.label("Direction:");
+                ui.add(
+                    egui::DragValue::new(&mut direction.x)
+                        .speed(0.01)
+                        .prefix("x "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut direction.y)
+                        .speed(0.01)
+                        .prefix("y "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut direction.z)
+                        .speed(0.01)
+                        .prefix("z "),
+                );
+            });
+        };
+
+        egui::ComboBox::from_label("Gravity Mode")
+            .selected_text(match self.settings.gravity_mode {
+                GRAVITY_UNIFORM => "Uniform",
+                GRAVITY_CENTRAL_LINEAR => "Central (1/r)",
+                GRAVITY_CENTRAL_INVERSE_SQUARE => "Central (1/r²)",
+                _ => "Unknown",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.settings.gravity_mode, GRAVITY_UNIFORM, "Uniform");
+                ui.selectable_value(
+                    &mut self.settings.gravity_mode,
+                    GRAVITY_CENTRAL_LINEAR,
+                    "Central (1/r)",
+                );
+                ui.selectable_value(
+                    &mut self.settings.gravity_mode,
+                    GRAVITY_CENTRAL_INVERSE_SQUARE,
+                    "Central (1/r²)",
+                );
+            })
+            .response
+            .on_hover_text(format!(
+                "Central pulls towards a point, fully within {CENTRAL_GRAVITY_RADIUS} units of it"
+            ));
+        self.parameter_ui(ui, "gravity");
+        if self.settings.gravity_mode == GRAVITY_UNIFORM {
+            ui.horizontal(|ui| {
+                direction_controls(ui, &mut self.settings.gravity_direction);
+                if ui
+                    .add_enabled(
+                        self.settings.gravity_direction != Vec3::NEG_Y,
+                        egui::Button::new("Down").small(),
+                    )
+                    .on_hover_text("Point gravity straight down again")
+                    .clicked()
+                {
+                    self.settings.gravity_direction = Vec3::NEG_Y;
+                }
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Center:");
+                for axis in self.settings.gravity_center.as_mut() {
+                    ui.add(egui::DragValue::new(axis).speed(0.5));
+                }
+                if ui
+                    .add_enabled(
+                        self.settings.gravity_center != Vec3::ZERO,
+                        egui::Button::new("Origin").small(),
+                    )
+                    .on_hover_text("Pull towards the origin again")
+                    .clicked()
+                {
+                    self.settings.gravity_center = Vec3::ZERO;
+                }
+            });
+        }
+        self.parameter_ui(ui, "damping");
+        self.parameter_ui(ui, "variation");
+        egui::ComboBox::from_label("Integrator")
+            .selected_text(self.settings.integrator.name())
+            .show_ui(ui, |ui| {
+                for integrator in Integrator::ALL {
+                    ui.selectable_value(
+                        &mut self.settings.integrator,
+                        integrator,
+                        integrator.name(),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Velocity Verlet stays stable under strong mouse forces");
+        self.parameter_ui(ui, "attractor_enabled");
+        ui.add_enabled_ui(self.settings.attractor_enabled, |ui| {
+            self.parameter_ui(ui, "attractor_mass");
+        });
+        self.parameter_ui(ui, "collisions_enabled");
+        ui.add_enabled_ui(self.settings.collisions_enabled, |ui| {
+            self.parameter_ui(ui, "collision_radius");
+            self.parameter_ui(ui, "restitution");
+            self.parameter_ui(ui, "granular_contacts");
+            ui.add_enabled_ui(self.settings.granular_contacts, |ui| {
+                self.parameter_ui(ui, "contact_friction");
+                self.parameter_ui(ui, "rolling_resistance");
+            });
+        });
+        self.parameter_ui(ui, "springs_enabled");
+        ui.add_enabled_ui(self.settings.springs_enabled, |ui| {
+            self.parameter_ui(ui, "spring_stiffness");
+            ui.add(
+                egui::Slider::new(
+                    &mut self.settings.spring_iterations,
+                    1..=MAX_SPRING_ITERATIONS,
+                )
+                .text("Iterations"),
+            )
+            .on_hover_text("Relaxation passes per step, more make stiffer cloth");
+        })
+        .response
+        .on_hover_text("Springs come from the cloth grid or the spring radius in Generation");
+
+        ui.separator();
+        ui.heading("Surface");
+        egui::ComboBox::from_label("Constrain To")
+            .selected_text(self.settings.surface.name())
+            .show_ui(ui, |ui| {
+                for surface in Surface::ALL {
+                    ui.selectable_value(&mut self.settings.surface, surface, surface.name());
+                }
+            })
+            .response
+            .on_hover_text(
+                "Puts the particles back on the shape after every step, they slide along it",
+            );
+        if matches!(self.settings.surface, Surface::Sphere | Surface::Torus) {
+            self.parameter_ui(ui, "surface_radius");
+        }
+        if self.settings.surface == Surface::Torus {
+            self.parameter_ui(ui, "surface_tube_radius");
+        }
+
+        ui.separator();
+        ui.heading("Turbulence");
+        for key in [
+            "turbulence_amplitude",
+            "turbulence_frequency",
+            "turbulence_speed",
+        ] {
+            self.parameter_ui(ui, key);
+        }
+
+        ui.separator();
+        ui.heading("Wind");
+        self.parameter_ui(ui, "wind_strength");
+        direction_controls(ui, &mut self.settings.wind_direction);
+        self.parameter_ui(ui, "wind_gustiness");
+
+        ui.separator();
+        ui.heading("Chemistry");
+        self.render_chemistry_ui(ui);
+
+        ui.separator();
+        ui.heading("Heat");
+        let reason = self.unsupported_reason(Capability::HeatConduction);
+        capability_scope(ui, reason, |ui| self.parameter_ui(ui, "conduction"));
+        self.parameter_ui(ui, "buoyancy");
+        self.parameter_ui(ui, "ambient_temperature");
+
+        ui.separator();
+        ui.heading("Magnetism");
+        let reason = self.unsupported_reason(Capability::DipoleForces);
+        capability_scope(ui, reason, |ui| {
+            for key in ["dipoles_enabled", "dipole_strength", "dipole_radius"] {
+                self.parameter_ui(ui, key);
+            }
+        });
+
+        self.parameter_ui(ui, "lorentz_enabled");
+        self.parameter_ui(ui, "electric_strength");
+        direction_controls(ui, &mut self.settings.electric_direction);
+        self.parameter_ui(ui, "magnetic_strength");
+        direction_controls(ui, &mut self.settings.magnetic_direction);
+        for key in ["coulomb_enabled", "coulomb_strength", "coulomb_cutoff"] {
+            self.parameter_ui(ui, key);
+        }
+
+        ui.separator();
+        ui.heading("Particle Life");
+        for key in [
+            "particle_life_enabled",
+            "particle_life_strength",
+            "particle_life_radius",
+        ] {
+            self.parameter_ui(ui, key);
+        }
+        ui.add_enabled_ui(self.settings.particle_life_enabled, |ui| {
+            self.render_species_interactions_ui(ui);
+        });
+
+        ui.separator();
+        ui.heading("Strange Attractor");
+        self.render_strange_attractor_ui(ui);
+
+        ui.separator();
+        ui.heading("Molecular Dynamics");
+        let reason = self.unsupported_reason(Capability::MolecularDynamics);
+        capability_scope(ui, reason, |ui| {
+            for key in [
+                "lj_enabled",
+                "lj_epsilon",
+                "lj_sigma",
+                "lj_cutoff",
+                "thermostat_enabled",
+                "thermostat_target",
+                "thermostat_tau",
+            ] {
+                self.parameter_ui(ui, key);
+            }
+        });
+
+        ui.separator();
+        ui.heading("Boundaries");
+        egui::ComboBox::from_label("Boundary")
+            .selected_text(match self.settings.boundary_mode {
+                BOUNDARY_OPEN => "Open",
+                BOUNDARY_PERIODIC => "Periodic",
+                BOUNDARY_CONTAINER => "Container",
+                _ => "Unknown",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.settings.boundary_mode, BOUNDARY_OPEN, "Open");
+                ui.selectable_value(
+                    &mut self.settings.boundary_mode,
+                    BOUNDARY_PERIODIC,
+                    "Periodic",
+                );
+                ui.selectable_value(
+                    &mut self.settings.boundary_mode,
+                    BOUNDARY_CONTAINER,
+                    "Container",
+                );
+            });
+        ui.horizontal(|ui| {
+            ui.label("Box Half Size:");
+            for axis in self.settings.box_half_extents.as_mut() {
+                ui.add(egui::DragValue::new(axis).speed(0.5).range(1.0..=500.0));
+            }
+        });
+        if self.settings.boundary_mode == BOUNDARY_PERIODIC {
+            self.parameter_ui(ui, "show_ghosts");
+            self.parameter_ui(ui, "ghost_margin");
+        }
+        if self.settings.boundary_mode == BOUNDARY_CONTAINER {
+            self.parameter_ui(ui, "wall_restitution");
+        }
+        self.parameter_ui(ui, "ground_enabled");
+        ui.add_enabled_ui(self.settings.ground_enabled, |ui| {
+            for key in [
+                "ground_height",
+                "ground_restitution",
+                "ground_friction",
+                "show_ground_grid",
+            ] {
+                self.parameter_ui(ui, key);
+            }
+        });
+        if self.settings.boundary_mode == BOUNDARY_CONTAINER || self.settings.ground_enabled {
+            self.parameter_ui(ui, "gizmo_xray");
+        }
+
+        ui.separator();
+        ui.heading("Obstacles");
+        self.render_obstacles_ui(ui);
+
+        ui.separator();
+        ui.heading("Attractors, Repellers & Black Holes");
+        self.render_point_attractors_ui(ui);
+
+        ui.separator();
+        ui.heading("Force Stack");
+        self.render_force_stack_ui(ui);
+        ui.horizontal(|ui| {
+            if ui.button("Force Field Editor...").clicked() {
+                self.show_field_editor = true;
+            }
+            if self.settings.field_graph.is_active() {
+                ui.weak("Field active");
+            }
+        });
+
+        ui.separator();
+        ui.heading("Orbital Mechanics");
+        self.render_orbit_tutorial_ui(ui, frame);
+    }
+
+    fn render_generation_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let mut generation_mode_changed = false;
+        ui.horizontal(|ui| {
+            generation_mode_changed |= ui
+                .radio_value(
+                    &mut self.ui_generation.mode,
+                    SphereGeneration::Hollow,
+                    "Hollow Sphere",
+                )
+                .changed();
+            generation_mode_changed |= ui
+                .radio_value(
+                    &mut self.ui_generation.mode,
+                    SphereGeneration::Filled,
+                    "Filled Sphere",
+                )
+                .changed();
+            generation_mode_changed |= ui
+                .radio_value(
+                    &mut self.ui_generation.mode,
+                    SphereGeneration::ClothGrid,
+                    "Cloth",
+                )
+                .on_hover_text("A sheet held together by springs")
+                .changed();
+        });
+        ui.add_enabled_ui(
+            self.ui_generation.mode != SphereGeneration::ClothGrid,
+            |ui| {
+                generation_mode_changed |= ui
+                    .add(
+                        egui::Slider::new(&mut self.ui_generation.spring_radius, 0.0..=5.0)
+                            .text("Spring Radius"),
+                    )
+                    .on_hover_text(
+                        "Tie particles to their nearest neighbors within it, for soft bodies",
+                    )
+                    .changed();
+            },
+        );
+        generation_mode_changed |= ui
+            .add(
+                egui::Slider::new(&mut self.ui_generation.species_count, 1..=MAX_SPECIES)
+                    .text("Species"),
+            )
+            .changed();
+        egui::ComboBox::from_label("Masses")
+            .selected_text(self.ui_generation.mass_distribution.name())
+            .show_ui(ui, |ui| {
+                for distribution in MassDistribution::ALL {
+                    generation_mode_changed |= ui
+                        .selectable_value(
+                            &mut self.ui_generation.mass_distribution,
+                            distribution,
+                            distribution.name(),
+                        )
+                        .changed();
+                }
+            })
+            .response
+            .on_hover_text("Heavier particles give way less to the mouse, fields and collisions");
+        if self.ui_generation.mass_distribution != MassDistribution::Equal {
+            generation_mode_changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.ui_generation.mass_ratio, 1.0..=100.0)
+                        .logarithmic(true)
+                        .text("Mass Ratio"),
+                )
+                .on_hover_text("Heaviest over lightest mass")
+                .changed();
+        }
+        ui.horizontal(|ui| {
+            ui.label("Seed");
+            generation_mode_changed |= ui
+                .add(egui::DragValue::new(&mut self.ui_generation.seed))
+                .on_hover_text("The same seed generates the same particles")
+                .changed();
+            if ui.button("🎲").on_hover_text("New random seed").clicked() {
+                // Mixed from the current seed, the step and the time so
+                // every click gives a different one
+                let time = ui.input(|input| input.time).to_bits();
+                self.ui_generation.seed = rand::rngs::SmallRng::seed_from_u64(
+                    self.ui_generation.seed ^ u64::from(self.step) ^ time,
+                )
+                .random();
+                generation_mode_changed = true;
+            }
+            if ui
+                .button("Copy Seed & Config")
+                .on_hover_text(
+                    "The settings with the seed, Paste Settings restarts the run from them",
+                )
+                .clicked()
+            {
+                let mut settings = self.settings();
+                settings.generation.seed = self.ui_generation.seed;
+                ui.ctx().copy_text(settings.to_json());
+                self.settings_status = Some("Copied to the clipboard".to_owned());
+            }
+        });
+        let sphere = matches!(
+            self.ui_generation.mode,
+            SphereGeneration::Hollow | SphereGeneration::Filled
+        );
+        generation_mode_changed |= ui
+            .add_enabled(
+                sphere,
+                egui::Checkbox::new(&mut self.ui_generation.orbit_insertion, "Orbit Insertion"),
+            )
+            .on_hover_text(
+                "Launch the particles on circular orbits around the central attractor instead of letting them fall in",
+            )
+            .on_disabled_hover_text("Only for the spheres")
+            .changed();
+        if sphere && self.ui_generation.orbit_insertion {
+            generation_mode_changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.ui_generation.orbit_jitter, 0.0..=0.5)
+                        .text("Eccentricity Jitter"),
+                )
+                .on_hover_text("Spread of the launch speeds around the circular one")
+                .changed();
+            if !self.settings.attractor_enabled {
+                ui.weak("Enable the central attractor for something to orbit");
+            }
+        }
+        ui.add_enabled_ui(self.settings.generation.mode.spawn_mode().is_some(), |ui| {
+            self.parameter_ui(ui, "respawn_enabled")
+                .on_disabled_hover_text("Not available for rings, cloths and galaxies");
+        });
+        ui.add_enabled_ui(self.settings.respawn_enabled, |ui| {
+            self.parameter_ui(ui, "respawn_rate");
+        });
+
+        ui.separator();
+        ui.heading("Lifetime");
+        self.parameter_ui(ui, "lifetime_enabled");
+        ui.add_enabled_ui(self.settings.lifetime_enabled, |ui| {
+            self.parameter_ui(ui, "lifetime");
+            self.parameter_ui(ui, "lifetime_variation");
+            self.parameter_ui(ui, "emission_rate");
+            egui::ComboBox::from_label("Emitter")
+                .selected_text(match self.settings.emitter_mode {
+                    EMIT_SPAWN_SHAPE => "Spawn Shape",
+                    EMIT_FOUNTAIN => "Fountain",
+                    EMIT_BURST => "Burst",
+                    _ => "Unknown",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.settings.emitter_mode,
+                        EMIT_SPAWN_SHAPE,
+                        "Spawn Shape",
+                    )
+                    .on_hover_text("Back where they were generated, at rest");
+                    ui.selectable_value(&mut self.settings.emitter_mode, EMIT_FOUNTAIN, "Fountain")
+                        .on_hover_text("Sprayed upwards from the emitter");
+                    ui.selectable_value(&mut self.settings.emitter_mode, EMIT_BURST, "Burst")
+                        .on_hover_text("Flung in every direction from the emitter, all at once");
+                });
+            if self.settings.emitter_mode != EMIT_SPAWN_SHAPE {
+                ui.horizontal(|ui| {
+                    ui.label("Emitter Position:");
+                    for axis in self.settings.emitter_position.as_mut() {
+                        ui.add(egui::DragValue::new(axis).speed(0.5));
+                    }
+                });
+                self.parameter_ui(ui, "emitter_speed");
+            }
+            if self.settings.emitter_mode == EMIT_FOUNTAIN {
+                self.parameter_ui(ui, "emitter_spread");
+            }
+        });
+
+        ui.separator();
+        ui.heading("Particle Count");
+        ui.label(self.device_profile.summary());
+
+        let mut particle_count_changed = false; // Flag to trigger resize later
+        let limit = self.device_profile.particle_limit(self.current_method);
+
+        ui.horizontal(|ui| {
+            ui.label("Count:");
+            // Use DragValue bound to the u32 field
+            let drag_response = ui
+                .add(
+                    egui::DragValue::new(&mut self.ui_particle_count)
+                        .range(0..=limit.max)
+                        .speed(100.0) // Adjust speed as needed (particles per point dragged)
+                        .custom_formatter(|n, _| format::grouped(n as u64))
+                        .custom_parser(|text| format::parse_count(text).map(|n| n as f64)),
+                )
+                .on_hover_text("Accepts short forms like 500k or 1.5m");
+
+            // Check if the DragValue was changed by the user
+            if drag_response.changed() {
+                particle_count_changed = true;
+            }
+            if self.simulation.get_particle_count() == 0 {
+                ui.weak("(empty scene)");
+            }
+        });
+
+        // Quick selection buttons
+        ui.horizontal(|ui| {
+            let mut set_count = |count: u32| {
+                if self.ui_particle_count != count {
+                    self.ui_particle_count = count;
+                    particle_count_changed = true; // Signal that resize is needed
+                }
+            };
+
+            for count in [10_000, 100_000, 1_000_000] {
+                let button = egui::Button::new(format::si(count as f64));
+                if ui.add_enabled(count <= limit.max, button).clicked() {
+                    set_count(count);
+                }
+            }
+        });
+        ui.weak(format!(
+            "Limit: {} particles ({})",
+            format::si(limit.max as f64),
+            limit.reason
+        ))
+        .on_hover_text(
+            "The most the device can allocate for this backend, more would fail with a GPU validation error",
+        );
+
+        // Apply resize if the count changed via DragValue or buttons
+        if particle_count_changed || generation_mode_changed {
+            let count_to_set = self.ui_particle_count;
+            let checkpoint =
+                self.checkpoint(format!("{} particles", format::si(count_to_set as f64)));
+            let generation_changed = self.settings.generation != self.ui_generation;
+            self.settings.generation = self.ui_generation;
+
+            if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+                AllocationGuard::begin(&wgpu_render_state.device);
+                self.simulation.resize_buffer(
+                    &wgpu_render_state.device,
+                    &wgpu_render_state.queue,
+                    count_to_set,
+                    self.settings.generation,
+                );
+
+                // Resizing to the same count keeps the old particles around
+                if generation_changed {
+                    self.simulation.reset(
+                        &wgpu_render_state.device,
+                        &wgpu_render_state.queue,
+                        self.settings.generation,
+                    );
+                }
+                self.allocations.end(&wgpu_render_state.device, checkpoint);
+            }
+        }
+
+        ui.separator();
+        ui.heading("Bindings");
+        self.render_bindings_ui(ui);
+
+        ui.separator();
+        ui.heading("Settings");
+        self.render_settings_ui(ui, frame);
+    }
+
+    /// The bound parameters, their expressions editable
+    fn render_bindings_ui(&mut self, ui: &mut egui::Ui) {
+        if self.bindings.is_empty() {
+            ui.weak("Right-click a parameter to drive it by an expression of the time t and other parameters, e.g. 2*sin(t*0.5)");
+            return;
+        }
+        let mut removed = None;
+        for (key, binding) in &mut self.bindings {
+            let parameter = parameter(key);
+            ui.horizontal(|ui| {
+                ui.label(format!("{} =", parameter.name))
+                    .on_hover_text(format!("{} in {}", parameter.key, parameter.section));
+                let mut text = binding.text.clone();
+                if ui
+                    .add(egui::TextEdit::singleline(&mut text).code_editor())
+                    .changed()
+                {
+                    *binding = Binding::new(text, is_parameter);
+                }
+                if binding.paused {
+                    if ui
+                        .button("Resume")
+                        .on_hover_text("Paused by a manual edit")
+                        .clicked()
+                    {
+                        binding.paused = false;
+                    }
+                } else if ui.button("Pause").clicked() {
+                    binding.paused = true;
+                }
+                if ui.button("Remove").clicked() {
+                    removed = Some(*key);
+                }
+            });
+            if let Some(error) = binding.error() {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        }
+        if let Some(key) = removed {
+            self.bindings.remove(key);
+        }
+    }
+
+    fn render_display_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let previous_color_mode = self.settings.color_mode;
+        egui::ComboBox::from_label("Color Mode")
+            .selected_text(match self.settings.color_mode {
+                0 => "Original",
+                1 => "Velocity",
+                2 => "Position",
+                3 => "Species",
+                4 => "Temperature",
+                5 => "Dipole",
+                6 => "Charge",
+                COLOR_DENSITY => "Density",
+                COLOR_AGE => "Age",
+                COLOR_DISPLACEMENT => "Displacement",
+                _ => "Unknown",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.settings.color_mode, 0, "Original");
+                ui.selectable_value(&mut self.settings.color_mode, 1, "Velocity");
+                ui.selectable_value(&mut self.settings.color_mode, 2, "Position");
+                ui.selectable_value(&mut self.settings.color_mode, 3, "Species");
+                ui.selectable_value(&mut self.settings.color_mode, 4, "Temperature");
+                ui.selectable_value(&mut self.settings.color_mode, 5, "Dipole");
+                ui.selectable_value(&mut self.settings.color_mode, 6, "Charge");
+                ui.selectable_value(&mut self.settings.color_mode, COLOR_DENSITY, "Density")
+                    .on_hover_text("Neighbors within the contact radius");
+                ui.selectable_value(&mut self.settings.color_mode, COLOR_AGE, "Age")
+                    .on_hover_text("How far through their lifetime, or 10 s without one");
+                ui.selectable_value(
+                    &mut self.settings.color_mode,
+                    COLOR_DISPLACEMENT,
+                    "Displacement",
+                )
+                .on_hover_text("How far they moved since the reference, dark where nothing moves");
+            });
+        // The density grid is allocated on the next update
+        if self.settings.color_mode == COLOR_DENSITY && previous_color_mode != COLOR_DENSITY {
+            let mut checkpoint = self.checkpoint("the density grid".to_owned());
+            checkpoint.settings.color_mode = previous_color_mode;
+            self.allocations.watch_next_update(checkpoint);
+        }
+        if self.settings.color_mode == COLOR_DENSITY {
+            self.parameter_ui(ui, "contact_radius");
+        }
+        if self.settings.color_mode == 2 || self.settings.color_mode == COLOR_DISPLACEMENT {
+            self.parameter_ui(ui, "max_dist_for_color");
+        }
+        if self.settings.color_mode == COLOR_DISPLACEMENT
+            && ui
+                .button("Measure From Now")
+                .on_hover_text(
+                    "Take where the particles are now as the reference, resets take one too",
+                )
+                .clicked()
+            && let Some(wgpu_render_state) = frame.wgpu_render_state()
+        {
+            self.simulation
+                .mark_reference(&wgpu_render_state.device, &wgpu_render_state.queue);
+        }
+        egui::ComboBox::from_label("Draw Order")
+            .selected_text(self.settings.draw_order.name())
+            .show_ui(ui, |ui| {
+                for order in DrawOrder::ALL {
+                    ui.selectable_value(&mut self.settings.draw_order, order, order.name());
+                }
+            })
+            .response
+            .on_hover_text("Later particles cover earlier ones, sorted on the GPU every frame");
+        self.parameter_ui(ui, "power_saver.enabled");
+
+        ui.separator();
+        ui.heading("Annotations");
+        self.parameter_ui(ui, "annotations.enabled");
+        ui.add_enabled_ui(self.annotations.enabled, |ui| {
+            ui.add(
+                egui::Slider::new(&mut self.annotations.sample_count, 1..=MAX_SAMPLES)
+                    .text("Samples"),
+            );
+            ui.horizontal(|ui| {
+                self.parameter_ui(ui, "annotations.show_velocity");
+                self.parameter_ui(ui, "annotations.show_acceleration");
+                self.parameter_ui(ui, "annotations.show_force");
+            });
+            self.parameter_ui(ui, "annotations.show_labels");
+            self.parameter_ui(ui, "annotations.arrow_scale");
+        });
+
+        ui.separator();
+        ui.heading("Picture-in-Picture");
+        self.parameter_ui(ui, "inset.enabled");
+        ui.add_enabled_ui(self.inset.enabled, |ui| {
+            self.parameter_ui(ui, "inset.size");
+            ui.horizontal(|ui| {
+                for pose in InsetPose::ALL {
+                    if ui.button(pose.name()).clicked() {
+                        self.inset.set_pose(pose);
+                    }
+                }
+                if ui
+                    .button("From Camera")
+                    .on_hover_text("Freeze the inset where the main camera is now")
+                    .clicked()
+                {
+                    self.inset.copy_pose(&self.camera);
+                }
+            });
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.separator();
+            ui.heading("360° Capture");
+            self.render_panorama_ui(ui);
+        }
+
+        ui.separator();
+        ui.heading("Export");
+        if ui
+            .add_enabled(self.frame_copy.is_none(), egui::Button::new("Copy Image"))
+            .on_hover_text(
+                "Put the particles as the camera sees them on the clipboard (Ctrl+Shift+C)",
+            )
+            .clicked()
+        {
+            self.copy_frame_requested = true;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if ui
+                .button("Export Animation…")
+                .on_hover_text("Render a fixed number of frames to images or a video")
+                .clicked()
+            {
+                self.show_export = true;
+            }
+            if ui
+                .button("Contact Sheet…")
+                .on_hover_text("Render the scene once per combination of swept parameters")
+                .clicked()
+            {
+                self.show_contact_sheet = true;
+            }
+        }
+
+        ui.separator();
+        ui.heading("Analysis");
+        let reason = self.unsupported_reason(Capability::RadialDistribution);
+        capability_scope(ui, reason, |ui| {
+            self.parameter_ui(ui, "rdf_enabled");
+            if self.settings.rdf_enabled {
+                self.parameter_ui(ui, "rdf_max_radius");
+                if let Some(rdf) = &self.rdf {
+                    plot_curve(ui, rdf, self.settings.rdf_max_radius);
+                }
+            }
+        });
+        self.parameter_ui(ui, "group_stats_enabled");
+        if self.settings.group_stats_enabled
+            && let Some(stats) = &mut self.group_stats
+            && group_stats_table(ui, stats, &mut self.group_sort)
+        {
+            sort_groups(stats, self.group_sort);
+        }
+
+        ui.separator();
+        ui.heading("Screensaver");
+        self.parameter_ui(ui, "screensaver.preset_interval");
+        self.parameter_ui(ui, "screensaver.idle_timeout");
+        if ui.button("Start Screensaver").clicked() {
+            self.screensaver.request_start();
+        }
+        ui.label("Any input exits");
+
+        ui.separator();
+        ui.heading("Evolve");
+        self.parameter_ui(ui, "evolve.enabled");
+        self.parameter_ui(ui, "evolve.speed");
+        egui::CollapsingHeader::new("Drifting Parameters").show(ui, |ui| {
+            self.render_evolve_tracks(ui);
+        });
+    }
+
+    /// Picks the flow, resetting its coefficients, scale and speed to the
+    /// classic shape, and tunes its coefficients
+    fn render_strange_attractor_ui(&mut self, ui: &mut egui::Ui) {
+        let current = strange_attractor(self.settings.strange_mode);
+        egui::ComboBox::from_label("Flow")
+            .selected_text(current.map_or("None", |attractor| attractor.name))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.settings.strange_mode, STRANGE_NONE, "None");
+                for attractor in &STRANGE_ATTRACTORS {
+                    if ui
+                        .selectable_label(
+                            self.settings.strange_mode == attractor.mode,
+                            attractor.name,
+                        )
+                        .clicked()
+                    {
+                        self.settings.strange_mode = attractor.mode;
+                        self.settings.strange_coefficients = attractor.defaults;
+                        self.settings.strange_scale = attractor.scale;
+                        self.settings.strange_speed = attractor.speed;
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Carries the particles along a chaotic flow, replacing their velocity");
+        let Some(attractor) = strange_attractor(self.settings.strange_mode) else {
+            return;
+        };
+        for (value, coefficient) in self
+            .settings
+            .strange_coefficients
+            .iter_mut()
+            .zip(&attractor.coefficients)
+        {
+            if let Some((name, range)) = coefficient {
+                ui.add(egui::Slider::new(value, range.clone()).text(*name));
+            }
+        }
+        for key in ["strange_follow", "strange_scale", "strange_speed"] {
+            self.parameter_ui(ui, key);
+        }
+    }
+
+    /// A checkbox per slider to let it drift, and its bounds once checked
+    fn render_evolve_tracks(&mut self, ui: &mut egui::Ui) {
+        for panel in Panel::ALL {
+            let mut heading = Some(panel.name());
+            for parameter in PARAMETERS {
+                let Some(range) = parameter.range() else {
+                    continue;
+                };
+                if parameter.panel != panel || parameter.key.starts_with("evolve.") {
+                    continue;
+                }
+                if let Some(heading) = heading.take() {
+                    ui.strong(heading);
+                }
+
+                let full = Track {
+                    enabled: false,
+                    min: *range.start(),
+                    max: *range.end(),
+                };
+                let previous = self.evolve.tracks.get(parameter.key).copied();
+                let mut track = previous.unwrap_or(full);
+                let speed = (range.end() - range.start()) / 200.0;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut track.enabled, parameter.name)
+                        .on_hover_text(parameter.section);
+                    if track.enabled {
+                        ui.add(
+                            egui::DragValue::new(&mut track.min)
+                                .range(range.clone())
+                                .speed(speed),
+                        )
+                        .on_hover_text("Lowest it drifts to");
+                        ui.add(
+                            egui::DragValue::new(&mut track.max)
+                                .range(range.clone())
+                                .speed(speed),
+                        )
+                        .on_hover_text("Highest it drifts to");
+                    }
+                });
+                if previous.unwrap_or(full) != track {
+                    self.evolve.tracks.insert(parameter.key.to_owned(), track);
+                }
+            }
+        }
+    }
+
+    fn render_camera_panel(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        ui.label(format!(
+            "Position: ({:.2}, {:.2}, {:.2})",
+            self.camera.position.x, self.camera.position.y, self.camera.position.z
+        ));
+
+        let mut fov_degrees = self.camera.fov * 180.0 / std::f32::consts::PI;
+        ui.add(egui::Slider::new(&mut fov_degrees, 10.0..=120.0).text("Field of View (degrees)"));
+
+        // Convert to radians and update camera if changed
+        if (fov_degrees * std::f32::consts::PI / 180.0 - self.camera.fov).abs() > 0.001 {
+            self.camera.fov = fov_degrees * std::f32::consts::PI / 180.0;
+            self.camera.update_view_proj();
+
+            if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+                self.camera.update_buffer(&wgpu_render_state.queue);
+            }
+        }
+
+        ui.separator();
+        ui.heading("Mouse Interaction");
+        ui.label(format!(
+            "Position: ({:.2}, {:.2}, {:.2})",
+            self.mouse_position[0], self.mouse_position[1], self.mouse_position[2]
+        ));
+
+        ui.label(format!("Dragging: {}", self.mouse_dragging));
+        ui.label(format!("Depth: {:.2}", self.mouse_position[2]));
+
+        for key in ["mouse_radius", "mouse_force", "mouse_heat"] {
+            self.parameter_ui(ui, key);
+        }
+    }
+
+    /// Play/pause, stepping and playback speed, docked at the bottom
+    fn render_transport_bar(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+        egui::TopBottomPanel::bottom("transport").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("⏮").on_hover_text("Reset").clicked()
+                    && let Some(wgpu_render_state) = frame.wgpu_render_state()
+                {
+                    self.reset(&wgpu_render_state.device, &wgpu_render_state.queue);
+                }
+
+                let paused = self.simulation.is_paused();
+                if ui
+                    .button(if paused { "▶" } else { "⏸" })
+                    .on_hover_text(if paused { "Play (K)" } else { "Pause (K)" })
+                    .clicked()
+                {
+                    self.set_paused(!paused);
+                }
+                if ui
+                    .add_enabled(paused, egui::Button::new("⏭"))
+                    .on_hover_text("Step (.)")
+                    .clicked()
+                {
+                    self.step_requested = true;
+                }
+
+                ui.separator();
+                egui::ComboBox::from_id_salt("time_scale")
+                    .width(60.0)
+                    .selected_text(format!("{}×", self.time_scale))
+                    .show_ui(ui, |ui| {
+                        for scale in TIME_SCALES {
+                            ui.selectable_value(&mut self.time_scale, scale, format!("{scale}×"));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Playback speed ([ and ]), hold M for slow motion");
+                if self.slow_motion {
+                    ui.label(format!("{}×", self.time_scale * SLOW_MOTION))
+                        .on_hover_text("Slow motion while M is held");
+                }
+
+                ui.separator();
+                ui.label(format!("t = {:.2} s", self.sim_time))
+                    .on_hover_text("Simulated time since the last reset");
+
+                ui.separator();
+                self.render_recording_controls(ui);
+            });
+        });
+    }
+
+    /// Shortcuts for the transport bar, unless a text field has the keyboard
+    fn handle_transport_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            self.slow_motion = false;
+            return;
+        }
+        self.slow_motion = ctx.input(|i| i.key_down(egui::Key::M));
+        let (toggle, step, slower, faster) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::K),
+                i.key_pressed(egui::Key::Period),
+                i.key_pressed(egui::Key::OpenBracket),
+                i.key_pressed(egui::Key::CloseBracket),
+            )
+        });
+        if toggle {
+            let paused = self.simulation.is_paused();
+            self.set_paused(!paused);
+        }
+        if step && self.simulation.is_paused() {
+            self.step_requested = true;
+        }
+        let current = TIME_SCALES
+            .iter()
+            .position(|&scale| scale >= self.time_scale)
+            .unwrap_or(TIME_SCALES.len() - 1);
+        if slower {
+            self.time_scale = TIME_SCALES[current.saturating_sub(1)];
+        }
+        if faster {
+            self.time_scale = TIME_SCALES[(current + 1).min(TIME_SCALES.len() - 1)];
+        }
+    }
+
+    /// Pauses or resumes every layer
+    fn set_paused(&mut self, paused: bool) {
+        self.simulation.set_paused(paused);
+        for parked in self
+            .layers
+            .iter_mut()
+            .filter_map(|layer| layer.parked.as_mut())
+        {
+            parked.simulation.set_paused(paused);
+        }
+    }
+
+    /// Orbit insertion launches particles around the attractor as it is
+    /// when they're generated
+    fn sync_orbit_mass(&mut self) {
+        let mass = if self.settings.attractor_enabled {
+            self.settings.attractor_mass
+        } else {
+            0.0
+        };
+        self.settings.generation.orbit_mass = mass;
+        self.ui_generation.orbit_mass = mass;
+    }
+
+    /// Respawns the active layer's particles and restarts the clock
+    fn reset(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.stop_playback();
+        self.simulation
+            .reset(device, queue, self.settings.generation);
+        self.sim_time = 0.0;
+        // Replays the same random numbers from the start
+        if self.settings.deterministic {
+            self.step = 0;
+        }
+    }
+
+    fn start_recording(&mut self) {
+        self.recorder = Some(Recorder::new(
+            self.simulation.get_particle_count(),
+            self.record_interval,
+        ));
+    }
+
+    /// Keeps what was recorded to play back, saving it next to the other
+    /// captures on native
+    fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        let recording = recorder.finish();
+        if recording.is_empty() {
+            self.notice = Some("Nothing was recorded".to_owned());
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.notice = Some(match recording.save() {
+                Ok(path) => format!("Saved {}", path.display()),
+                Err(error) => format!("Couldn't save the recording: {error}"),
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.notice = Some(format!("Recorded {} frames", recording.len()));
+        }
+        self.recording = Some(recording);
+    }
+
+    fn start_playback(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        let count = self.simulation.get_particle_count();
+        if recording.particle_count() != count {
+            self.notice = Some(format!(
+                "The recording has {} particles, set the count to match to play it back",
+                format::si(recording.particle_count() as f64)
+            ));
+            self.recording = Some(recording);
+            return;
+        }
+        self.stop_recording();
+        self.playback = Some(Playback::new(recording));
+    }
+
+    /// Leaves the particles at the frame shown, the simulation carrying on
+    /// from there
+    fn stop_playback(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            self.recording = Some(playback.recording);
+        }
+    }
+
+    /// Shows the playback's current frame, moving it on while it plays
+    fn update_playback(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, delta_time: f32) {
+        // Played at the speed the simulation would run at
+        let elapsed = delta_time * self.step_delta() * self.settings.fixed_step_rate;
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        playback.advance(elapsed);
+        match playback.take_frame() {
+            Some(Ok(particles)) => self.simulation.set_particles(device, queue, &particles),
+            Some(Err(error)) => {
+                self.stop_playback();
+                self.notice = Some(format!("Playback stopped, {error}"));
+            }
+            None => {}
+        }
+    }
+
+    /// The record button, or the timeline while a recording plays back
+    fn render_recording_controls(&mut self, ui: &mut egui::Ui) {
+        if let Some(playback) = &mut self.playback {
+            if ui
+                .button(if playback.playing { "⏸" } else { "▶" })
+                .on_hover_text(if playback.playing {
+                    "Pause the playback"
+                } else {
+                    "Play the recording"
+                })
+                .clicked()
+            {
+                playback.playing = !playback.playing;
+            }
+            let mut frame = playback.frame;
+            let last = playback.recording.len().saturating_sub(1);
+            if ui
+                .add(egui::Slider::new(&mut frame, 0..=last).text(format!("/ {last}")))
+                .on_hover_text("Drag to scrub through the recording")
+                .changed()
+            {
+                playback.seek(frame);
+                playback.playing = false;
+            }
+            if ui
+                .button("Exit Playback")
+                .on_hover_text("Carry on simulating from the frame shown")
+                .clicked()
+            {
+                self.stop_playback();
+            }
+            return;
+        }
+
+        if let Some(recorder) = &self.recorder {
+            let recording = recorder.recording();
+            if ui.button("⏹").on_hover_text("Stop recording").clicked() {
+                self.stop_recording();
+                return;
+            }
+            ui.label(format!(
+                "● {} frames, {}",
+                recording.len(),
+                format::bytes(recording.size_bytes() as u64)
+            ))
+            .on_hover_text(format!(
+                "Recording stops on its own at {}",
+                format::bytes(MEMORY_BUDGET as u64)
+            ));
+            return;
+        }
+
+        if ui
+            .button("⏺")
+            .on_hover_text("Record every particle to play back later, compressed")
+            .clicked()
+        {
+            self.start_recording();
+        }
+        egui::ComboBox::from_id_salt("record_interval")
+            .width(70.0)
+            .selected_text(format!("every {}", self.record_interval))
+            .show_ui(ui, |ui| {
+                for interval in RECORD_INTERVALS {
+                    ui.selectable_value(
+                        &mut self.record_interval,
+                        interval,
+                        format!("every {interval}"),
+                    );
+                }
+            })
+            .response
+            .on_hover_text("Frames between snapshots");
+        if let Some(recording) = &self.recording
+            && ui
+                .button("Play Back")
+                .on_hover_text(format!(
+                    "{} frames over {:.1} s of simulated time",
+                    recording.len(),
+                    recording.duration()
+                ))
+                .clicked()
+        {
+            self.start_playback();
+        }
+    }
+
+    fn render_orbit_tutorial_ui(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let Some(wgpu_render_state) = frame.wgpu_render_state() else {
+            return;
+        };
+        let (device, queue) = (&wgpu_render_state.device, &wgpu_render_state.queue);
+
+        if !self.orbit_tutorial.active {
+            ui.label("A ring of particles around a central mass, drag the arrow to set its launch speed.");
+            if ui.button("Start Tutorial").clicked() {
+                self.start_orbit_tutorial(device, queue);
+            }
+            return;
+        }
+
+        let mass = self.settings.attractor_mass;
+        let circular = self.orbit_tutorial.circular_speed(mass);
+        let escape = self.orbit_tutorial.escape_speed(mass);
+
+        let mut relaunch = false;
+        relaunch |= ui
+            .add(
+                egui::Slider::new(&mut self.orbit_tutorial.radius, 5.0..=80.0)
+                    .text("Launch Radius"),
+            )
+            .drag_stopped();
+        relaunch |= ui
+            .add(
+                egui::Slider::new(&mut self.orbit_tutorial.speed, 0.0..=escape * 1.5)
+                    .text("Launch Speed"),
+            )
+            .drag_stopped();
+        ui.horizontal(|ui| {
+            if ui.button(format!("Circular ({circular:.2})")).clicked() {
+                self.orbit_tutorial.speed = circular;
+                relaunch = true;
+            }
+            if ui.button(format!("Escape ({escape:.2})")).clicked() {
+                self.orbit_tutorial.speed = escape;
+                relaunch = true;
+            }
+        });
+
+        match self.orbit_tutorial.prediction(mass) {
+            Some(prediction) => {
+                ui.label(format!("Eccentricity: {:.3}", prediction.eccentricity));
+                match prediction.period {
+                    Some(period) => ui.label(format!("Period: {period:.1} s")),
+                    None => ui.label("Unbound: the ring escapes"),
+                };
+            }
+            None => {
+                ui.label("No angular momentum: straight fall into the attractor");
+            }
+        }
 
-                ui.add(egui::Slider::new(&mut self.mouse_radius, 1.0..=50.0).text("Radius"));
+        ui.horizontal(|ui| {
+            relaunch |= ui.button("Relaunch").clicked();
+            if ui.button("Stop Tutorial").clicked() {
+                self.orbit_tutorial.active = false;
+            }
+        });
+        if relaunch {
+            self.relaunch_orbit(device, queue);
+        }
+    }
 
-                ui.add(egui::Slider::new(&mut self.mouse_force, 0.0..=100.0).text("Force"));
+    /// The interaction matrix over the species in use, each cell how much
+    /// the row's species is drawn to the column's
+    fn render_species_interactions_ui(&mut self, ui: &mut egui::Ui) {
+        let species_count = self.settings.generation.species_count.clamp(1, MAX_SPECIES) as usize;
+        let species_label = |ui: &mut egui::Ui, species: usize| {
+            let [r, g, b, _] = SPECIES_COLORS[species].map(|c| (c * 255.0) as u8);
+            ui.colored_label(
+                egui::Color32::from_rgb(r, g, b),
+                species_name(species as u32),
+            );
+        };
 
-                ui.separator();
-                ui.heading("Camera");
-                ui.label(format!(
-                    "Position: ({:.2}, {:.2}, {:.2})",
-                    self.camera.position.x, self.camera.position.y, self.camera.position.z
+        let mut changed = false;
+        egui::Grid::new("species_interactions").show(ui, |ui| {
+            ui.label("");
+            for column in 0..species_count {
+                species_label(ui, column);
+            }
+            ui.end_row();
+            for (row, attractions) in self.settings.particle_life_matrix[..species_count]
+                .iter_mut()
+                .enumerate()
+            {
+                species_label(ui, row);
+                for attraction in &mut attractions[..species_count] {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(attraction)
+                                .range(-1.0..=1.0)
+                                .speed(0.01)
+                                .fixed_decimals(2),
+                        )
+                        .changed();
+                }
+                ui.end_row();
+            }
+        });
+        if species_count < 2 {
+            ui.label("Set more species in the generation settings for them to interact");
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Randomize")
+                .on_hover_text("A new random matrix, most of them grow some kind of creature")
+                .clicked()
+            {
+                self.particle_life_seed = self.particle_life_seed.wrapping_add(1);
+                self.settings.particle_life_matrix =
+                    particle_life::random_matrix(self.particle_life_seed);
+                changed = true;
+            }
+            if ui.button("Clear").clicked() {
+                self.settings.particle_life_matrix = InteractionMatrix::default();
+                changed = true;
+            }
+        });
+        if changed {
+            self.sync_species_interactions();
+        }
+    }
+
+    fn render_chemistry_ui(&mut self, ui: &mut egui::Ui) {
+        let reason = self.unsupported_reason(Capability::Reactions);
+        capability_scope(ui, reason, |ui| self.render_reaction_rules(ui));
+    }
+
+    fn render_layers_ui(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+        let can_remove = self.layers.len() > 1;
+        let names: Vec<String> = self.layers.iter().map(|layer| layer.name.clone()).collect();
+        let mut select = None;
+        let mut remove = None;
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let simulation = match &layer.parked {
+                Some(parked) => &parked.simulation,
+                None => &self.simulation,
+            };
+            let (count, method) = (simulation.get_particle_count(), simulation.get_method());
+            let unsupported = (!simulation.supports(Capability::LayerCoupling)).then(|| {
+                format!(
+                    "{} not available on the {} backend",
+                    Capability::LayerCoupling.name(),
+                    method.name()
+                )
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut layer.visible, "")
+                    .on_hover_text("Draw this layer");
+                if ui
+                    .selectable_label(i == self.active_layer, layer.name.as_str())
+                    .on_hover_text("Edit this layer")
+                    .clicked()
+                {
+                    select = Some(i);
+                }
+                ui.weak(format!(
+                    "{} · {}",
+                    format::count(count as u64),
+                    method.name()
                 ));
 
-                let mut fov_degrees = self.camera.fov * 180.0 / std::f32::consts::PI;
-                ui.add(
-                    egui::Slider::new(&mut fov_degrees, 10.0..=120.0)
-                        .text("Field of View (degrees)"),
-                );
+                let mut solo = self.solo_layer == Some(i);
+                if ui.toggle_value(&mut solo, "Solo").changed() {
+                    self.solo_layer = solo.then_some(i);
+                }
+                if ui.add_enabled(can_remove, egui::Button::new("✖")).clicked() {
+                    remove = Some(i);
+                }
+            });
 
-                // Convert to radians and update camera if changed
-                if (fov_degrees * std::f32::consts::PI / 180.0 - self.camera.fov).abs() > 0.001 {
-                    self.camera.fov = fov_degrees * std::f32::consts::PI / 180.0;
-                    self.camera.update_view_proj();
+            if names.len() > 1 {
+                capability_scope(ui, unsupported, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut source = layer.coupling.as_ref().map(|c| c.source);
+                        egui::ComboBox::from_id_salt(("coupling", i))
+                            .selected_text(match source {
+                                Some(source) => format!("Follows {}", names[source]),
+                                None => "Not coupled".to_owned(),
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut source, None, "Not coupled");
+                                for (j, name) in names.iter().enumerate().filter(|&(j, _)| j != i) {
+                                    ui.selectable_value(
+                                        &mut source,
+                                        Some(j),
+                                        format!("Follows {name}"),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Drag this layer along the flow of another one");
+                        if source != layer.coupling.as_ref().map(|c| c.source) {
+                            layer.coupling = source.map(Coupling::new);
+                        }
+                        if let Some(coupling) = &mut layer.coupling {
+                            ui.add(
+                                egui::DragValue::new(&mut coupling.strength)
+                                    .range(0.0..=20.0)
+                                    .speed(0.05)
+                                    .prefix("Strength: "),
+                            );
+                        }
+                    });
+                });
+            }
+        }
 
-                    if let Some(wgpu_render_state) = frame.wgpu_render_state() {
-                        self.camera.update_buffer(&wgpu_render_state.queue);
+        if let Some(i) = select {
+            self.select_layer(i);
+        }
+        if let Some(i) = remove {
+            self.remove_layer(i);
+        }
+
+        if ui.button("Add Layer").clicked()
+            && let Some(wgpu_render_state) = frame.wgpu_render_state()
+        {
+            self.add_layer(&wgpu_render_state.device);
+        }
+        if self.layers.len() > 1 {
+            ui.horizontal(|ui| {
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.checkbox(&mut self.parallel_layers, "Encode in Parallel")
+                    .on_hover_text("Record the background layers on all cores, compare the time to see what it saves");
+                ui.weak(format!("{:.3} ms", self.layer_update_time))
+                    .on_hover_text("Main thread time spent stepping the background layers");
+            });
+        }
+        ui.label("The rest of the window edits the selected layer");
+    }
+
+    fn render_reaction_rules(&mut self, ui: &mut egui::Ui) {
+        let mut rules_changed = ui
+            .checkbox(&mut self.settings.reactions_enabled, "Enable reactions")
+            .changed();
+
+        self.parameter_ui(ui, "contact_radius");
+
+        let species_count = self.settings.generation.species_count.max(1);
+        let species_combo = |ui: &mut egui::Ui, id: (usize, &str), species: &mut u32| {
+            egui::ComboBox::from_id_salt(id)
+                .width(40.0)
+                .selected_text(species_name(*species))
+                .show_ui(ui, |ui| {
+                    let mut changed = false;
+                    for s in 0..species_count {
+                        changed |= ui.selectable_value(species, s, species_name(s)).changed();
                     }
+                    changed
+                })
+                .inner
+                .unwrap_or(false)
+        };
+
+        let mut remove = None;
+        for (i, rule) in self.settings.reaction_rules.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                rules_changed |= species_combo(ui, (i, "a"), &mut rule.reactant_a);
+                ui.label("+");
+                rules_changed |= species_combo(ui, (i, "b"), &mut rule.reactant_b);
+                ui.label("→");
+                rules_changed |= species_combo(ui, (i, "product"), &mut rule.product);
+                rules_changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut rule.probability)
+                            .range(0.0..=1.0)
+                            .speed(0.005)
+                            .prefix("p = "),
+                    )
+                    .changed();
+                if ui.button("✖").clicked() {
+                    remove = Some(i);
                 }
+            });
+        }
 
-                ui.separator();
-                ui.heading("Particle Settings");
+        if let Some(i) = remove {
+            self.settings.reaction_rules.remove(i);
+            rules_changed = true;
+        }
+
+        if ui.button("Add Rule").clicked() {
+            self.settings.reaction_rules.push(ReactionRule::default());
+            rules_changed = true;
+        }
 
-                ui.add(egui::Slider::new(&mut self.gravity, 0.0..=5.0).text("Gravity"));
+        if rules_changed {
+            self.sync_reaction_rules();
+        }
+    }
 
-                ui.separator();
-                ui.heading("Particle Count");
+    fn render_obstacles_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        for (i, obstacle) in self.settings.obstacles.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("obstacle shape", i))
+                    .width(70.0)
+                    .selected_text(obstacle.shape.name())
+                    .show_ui(ui, |ui| {
+                        for shape in ObstacleShape::ALL {
+                            changed |= ui
+                                .selectable_value(&mut obstacle.shape, shape, shape.name())
+                                .changed();
+                        }
+                    });
+                if ui.button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Center:");
+                for axis in obstacle.center.as_mut() {
+                    changed |= ui.add(egui::DragValue::new(axis).speed(0.5)).changed();
+                }
+            });
+            ui.horizontal(|ui| {
+                let labels = obstacle.shape.size_labels();
+                for (axis, label) in obstacle.size.as_mut().iter_mut().zip(labels) {
+                    let Some(label) = label else {
+                        continue;
+                    };
+                    ui.label(label);
+                    changed |= ui
+                        .add(egui::DragValue::new(axis).speed(0.2).range(0.1..=500.0))
+                        .changed();
+                }
+            });
+        }
 
-                let mut particle_count_changed = false; // Flag to trigger resize later
+        if let Some(i) = remove {
+            self.settings.obstacles.remove(i);
+            changed = true;
+        }
 
-                ui.horizontal(|ui| {
-                    ui.label("Count:");
-                    // Use DragValue bound to the u32 field
-                    let drag_response = ui.add(
-                        egui::DragValue::new(&mut self.ui_particle_count).speed(100.0), // Adjust speed as needed (particles per point dragged)
-                                                                                        // .suffix(" particles") // Optional suffix
+        ui.add_enabled_ui(self.settings.obstacles.len() < MAX_OBSTACLES, |ui| {
+            if ui.button("Add Obstacle").clicked() {
+                self.settings.obstacles.push(Obstacle::default());
+                changed = true;
+            }
+        });
+        self.parameter_ui(ui, "obstacle_restitution");
+        match &self.obstacle_mesh_name {
+            Some(name) => ui.label(format!("Mesh: {name}")),
+            None => ui.weak("Drop an .obj file on the window to load a mesh"),
+        }
+        .on_hover_text("Closed meshes only, the inside is found by counting crossings");
+
+        if changed {
+            self.sync_obstacles();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_panorama_ui(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Projection")
+            .selected_text(self.panorama.projection.name())
+            .show_ui(ui, |ui| {
+                for projection in Projection::ALL {
+                    ui.selectable_value(
+                        &mut self.panorama.projection,
+                        projection,
+                        projection.name(),
+                    );
+                }
+            });
+        let projection = self.panorama.projection;
+        egui::ComboBox::from_label("Resolution")
+            .selected_text(format!(
+                "{} × {}",
+                self.panorama.width,
+                projection.height(self.panorama.width)
+            ))
+            .show_ui(ui, |ui| {
+                for width in panorama::WIDTHS {
+                    ui.selectable_value(
+                        &mut self.panorama.width,
+                        width,
+                        format!("{width} × {}", projection.height(width)),
                     );
+                }
+            });
+        ui.horizontal(|ui| {
+            let hover = match projection {
+                Projection::Equirectangular => {
+                    "Equirectangular PNG from the camera's position, for 360° viewers"
+                }
+                Projection::Fisheye => {
+                    "Domemaster PNG of the sky above the camera, for planetarium domes"
+                }
+            };
+            if ui.button("Save Panorama").on_hover_text(hover).clicked() {
+                self.panorama.request_still();
+            }
+            let label = if self.panorama.is_recording() {
+                "Stop Recording"
+            } else {
+                "Record Frames"
+            };
+            if ui
+                .button(label)
+                .on_hover_text("Saves a panorama every frame into a new folder")
+                .clicked()
+                && let Err(error) = self.panorama.toggle_recording()
+            {
+                self.notice = Some(format!("Couldn't start recording: {error}"));
+            }
+        });
+    }
 
-                    // Check if the DragValue was changed by the user
-                    if drag_response.changed() {
-                        particle_count_changed = true;
-                    }
-                });
+    /// The field forces in the order they're summed, each with a switch and
+    /// buttons to move or remove it, and the settings of drag and vortices
+    fn render_force_stack_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        let mut moved = None;
+        let count = self.settings.forces.layers.len();
+        for (i, layer) in self.settings.forces.layers.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .checkbox(&mut layer.enabled, layer.force.name())
+                    .changed();
+                if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                    moved = Some((i, i - 1));
+                }
+                if ui
+                    .add_enabled(i + 1 < count, egui::Button::new("⏷"))
+                    .clicked()
+                {
+                    moved = Some((i, i + 1));
+                }
+                if ui.button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+            match &mut layer.force {
+                Force::Drag { coefficient } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Coefficient");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(coefficient)
+                                    .speed(0.01)
+                                    .range(0.0..=10.0),
+                            )
+                            .on_hover_text("Fraction of the velocity lost per second")
+                            .changed();
+                    });
+                }
+                Force::Vortex {
+                    center,
+                    axis,
+                    strength,
+                    radius,
+                } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Center:");
+                        for component in center.as_mut() {
+                            changed |= ui.add(egui::DragValue::new(component).speed(0.5)).changed();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Axis:");
+                        for component in axis.as_mut() {
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(component)
+                                        .speed(0.01)
+                                        .range(-1.0..=1.0),
+                                )
+                                .changed();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Strength");
+                        changed |= ui
+                            .add(egui::DragValue::new(strength).speed(0.1))
+                            .on_hover_text("Negative swirls the other way")
+                            .changed();
+                        ui.label("Radius");
+                        changed |= ui
+                            .add(egui::DragValue::new(radius).speed(0.2).range(0.1..=500.0))
+                            .changed();
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((from, to)) = moved {
+            self.settings.forces.move_layer(from, to);
+            changed = true;
+        }
+        if let Some(i) = remove {
+            self.settings.forces.layers.remove(i);
+            changed = true;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for force in Force::BUILT_IN {
+                if !self.settings.forces.contains(force)
+                    && ui.button(format!("Add {}", force.name())).clicked()
+                {
+                    self.settings.forces.layers.push(ForceLayer {
+                        force,
+                        enabled: true,
+                    });
+                    changed = true;
+                }
+            }
+            for force in [Force::drag(), Force::vortex()] {
+                if ui.button(format!("Add {}", force.name())).clicked() {
+                    self.settings.forces.layers.push(ForceLayer {
+                        force,
+                        enabled: true,
+                    });
+                    changed = true;
+                }
+            }
+        });
+        if self.settings.forces.enabled_count() > MAX_FORCES {
+            ui.weak(format!("Only the first {MAX_FORCES} enabled forces apply"));
+        }
 
-                // Quick selection buttons
+        if changed {
+            self.sync_forces();
+        }
+    }
+
+    fn render_point_attractors_ui(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        for (i, point) in self.settings.point_attractors.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label("Position:");
+                for axis in point.position.as_mut() {
+                    changed |= ui.add(egui::DragValue::new(axis).speed(0.5)).changed();
+                }
+                if ui.button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+            if point.is_black_hole() {
                 ui.horizontal(|ui| {
-                    let mut set_count = |count: u32| {
-                        if self.ui_particle_count != count {
-                            self.ui_particle_count = count;
-                            particle_count_changed = true; // Signal that resize is needed
-                        }
-                    };
+                    ui.label("G·M");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut point.strength)
+                                .speed(1.0)
+                                .range(0.0..=100_000.0),
+                        )
+                        .changed();
+                    ui.label("Horizon");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut point.horizon)
+                                .speed(0.05)
+                                .range(0.1..=50.0),
+                        )
+                        .on_hover_text("Particles crossing it are re-emitted")
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(&mut point.accretion_glow, "Accretion glow")
+                        .changed();
+                    ui.add_enabled_ui(point.accretion_glow, |ui| {
+                        ui.label("Disc Radius");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut point.radius)
+                                    .speed(0.2)
+                                    .range(0.1..=500.0),
+                            )
+                            .changed();
+                    });
+                });
+            } else {
+                ui.horizontal(|ui| {
+                    ui.label("Strength");
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut point.strength).speed(0.2))
+                        .on_hover_text("Negative pushes particles away")
+                        .changed();
+                    ui.label("Radius");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut point.radius)
+                                .speed(0.2)
+                                .range(0.1..=500.0),
+                        )
+                        .changed();
+                });
+            }
+        }
 
-                    if ui.button("10,000").clicked() {
-                        set_count(10_000);
+        if let Some(i) = remove {
+            self.settings.point_attractors.remove(i);
+            changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(
+                self.settings.point_attractors.len() < MAX_POINT_ATTRACTORS,
+                |ui| {
+                    if ui.button("Add Attractor").clicked() {
+                        self.settings
+                            .point_attractors
+                            .push(PointAttractor::default());
+                        changed = true;
                     }
-                    if ui.button("100,000").clicked() {
-                        set_count(100_000);
+                    if ui.button("Add Repeller").clicked() {
+                        self.settings.point_attractors.push(PointAttractor {
+                            strength: -PointAttractor::default().strength,
+                            ..Default::default()
+                        });
+                        changed = true;
                     }
-                    if ui.button("1,000,000").clicked() {
-                        set_count(1_000_000);
+                    if ui
+                        .button("Add Black Hole")
+                        .on_hover_text("Pulls everything in and swallows what crosses its horizon")
+                        .clicked()
+                    {
+                        self.settings
+                            .point_attractors
+                            .push(PointAttractor::black_hole());
+                        changed = true;
                     }
-                });
+                },
+            );
+        });
+
+        if changed {
+            self.sync_point_attractors();
+        }
+    }
+}
 
-                // Apply resize if the count changed via DragValue or buttons
-                if particle_count_changed || generation_mode_changed {
-                    let count_to_set = self.ui_particle_count.max(1);
-                    self.ui_particle_count = count_to_set;
-                    self.generation_mode = self.ui_generation_mode;
+/// Greys out `add_contents` and explains why on hover when the active backend
+/// doesn't support the feature
+/// Sweep over the whole range of the slider under `key`
+#[cfg(not(target_arch = "wasm32"))]
+fn sweep_axis(key: &str) -> SweepAxis {
+    let parameter = parameter(key);
+    let range = parameter.range().unwrap_or(0.0..=1.0);
+    SweepAxis {
+        key: parameter.key,
+        name: parameter.name,
+        start: *range.start(),
+        end: *range.end(),
+        count: 4,
+    }
+}
 
-                    if let Some(wgpu_render_state) = frame.wgpu_render_state() {
-                        self.simulation.resize_buffer(
-                            &wgpu_render_state.device,
-                            &wgpu_render_state.queue,
-                            count_to_set,
-                            self.generation_mode,
-                        );
-                    }
+/// Picks the slider an axis sweeps, its bounds and how many values it takes
+#[cfg(not(target_arch = "wasm32"))]
+fn sweep_axis_ui(ui: &mut egui::Ui, id: &str, axis: &mut SweepAxis) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(axis.name)
+        .show_ui(ui, |ui| {
+            // Only the simulation's sliders, the dotted ones aren't settings
+            for candidate in PARAMETERS
+                .iter()
+                .filter(|p| p.range().is_some() && !p.key.contains('.'))
+            {
+                let label = format!("{} ({})", candidate.name, candidate.section);
+                if ui
+                    .selectable_label(axis.key == candidate.key, label)
+                    .clicked()
+                {
+                    *axis = SweepAxis {
+                        count: axis.count,
+                        ..sweep_axis(candidate.key)
+                    };
                 }
-                ui.separator();
-                ui.heading("Display");
-
-                egui::ComboBox::from_label("Color Mode")
-                    .selected_text(match self.color_mode {
-                        0 => "Original",
-                        1 => "Velocity",
-                        2 => "Position",
-                        _ => "Unknown",
-                    })
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.color_mode, 0, "Original");
-                        ui.selectable_value(&mut self.color_mode, 1, "Velocity");
-                        ui.selectable_value(&mut self.color_mode, 2, "Position");
-                    });
+            }
+        });
+    let range = parameter(axis.key).range().unwrap_or(0.0..=1.0);
+    let speed = (range.end() - range.start()) / 200.0;
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut axis.start)
+                .range(range.clone())
+                .speed(speed),
+        );
+        ui.label("to");
+        ui.add(
+            egui::DragValue::new(&mut axis.end)
+                .range(range)
+                .speed(speed),
+        );
+    });
+    ui.add(egui::Slider::new(&mut axis.count, 1..=MAX_SWEEP_VALUES).text("Values"));
+}
 
-                ui.separator();
-                ui.heading("Controls");
-                ui.label("WASD - Move camera");
-                ui.label("Mouse Right - Rotate camera");
-                ui.label("Space/Shift - Move up/down");
-                ui.label("Mouse Left - Drag particles");
-                ui.label("Mouse Scroll - Cursor Distance");
-                ui.label("U - Toggle UI");
-            });
+fn capability_scope<R>(
+    ui: &mut egui::Ui,
+    unsupported: Option<String>,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> R {
+    let inner = ui.add_enabled_ui(unsupported.is_none(), add_contents);
+    if let Some(reason) = unsupported {
+        inner.response.on_hover_text(reason);
+    }
+    inner.inner
+}
+
+/// Minimal line plot of `values` sampled evenly over `0..x_max`, with a
+/// reference line at y = 1
+fn plot_curve(ui: &mut egui::Ui, values: &[f32], x_max: f32) {
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), 120.0),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let y_max = values.iter().copied().fold(1.5_f32, f32::max) * 1.1;
+    let to_screen = |x: f32, y: f32| {
+        egui::pos2(
+            rect.left() + x * rect.width(),
+            rect.bottom() - (y / y_max) * rect.height(),
+        )
+    };
+
+    painter.line_segment(
+        [to_screen(0.0, 1.0), to_screen(1.0, 1.0)],
+        egui::Stroke::new(1.0_f32, ui.visuals().weak_text_color()),
+    );
+
+    let points: Vec<egui::Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &y)| to_screen((i as f32 + 0.5) / values.len() as f32, y))
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5_f32, ui.visuals().selection.bg_fill),
+    ));
+
+    painter.text(
+        rect.right_bottom() - egui::vec2(4.0, 4.0),
+        egui::Align2::RIGHT_BOTTOM,
+        format!("r = {x_max:.1}"),
+        egui::FontId::monospace(10.0),
+        ui.visuals().text_color(),
+    );
+    painter.text(
+        rect.left_top() + egui::vec2(4.0, 4.0),
+        egui::Align2::LEFT_TOP,
+        format!("g = {y_max:.2}"),
+        egui::FontId::monospace(10.0),
+        ui.visuals().text_color(),
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupColumn {
+    Species,
+    Count,
+    Speed,
+    Energy,
+    Radius,
+}
+
+impl GroupColumn {
+    const ALL: [GroupColumn; 5] = [
+        GroupColumn::Species,
+        GroupColumn::Count,
+        GroupColumn::Speed,
+        GroupColumn::Energy,
+        GroupColumn::Radius,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            GroupColumn::Species => "Species",
+            GroupColumn::Count => "Count",
+            GroupColumn::Speed => "Avg Speed",
+            GroupColumn::Energy => "Kinetic Energy",
+            GroupColumn::Radius => "Radius",
+        }
+    }
+
+    fn key(self, stats: &GroupStats) -> f32 {
+        match self {
+            GroupColumn::Species => stats.species as f32,
+            GroupColumn::Count => stats.count as f32,
+            GroupColumn::Speed => stats.average_speed,
+            GroupColumn::Energy => stats.kinetic_energy,
+            GroupColumn::Radius => stats.bounding_radius,
+        }
     }
 }
 
+fn sort_groups(stats: &mut [GroupStats], (column, descending): (GroupColumn, bool)) {
+    stats.sort_by(|a, b| {
+        let order = column.key(a).total_cmp(&column.key(b));
+        if descending { order.reverse() } else { order }
+    });
+}
+
+/// One row per species, sorted by clicking a header, clicking it again
+/// flips the order. Returns whether the sort changed.
+fn group_stats_table(
+    ui: &mut egui::Ui,
+    stats: &[GroupStats],
+    sort: &mut (GroupColumn, bool),
+) -> bool {
+    let mut changed = false;
+    egui::Grid::new("group_stats")
+        .striped(true)
+        .num_columns(GroupColumn::ALL.len())
+        .show(ui, |ui| {
+            for column in GroupColumn::ALL {
+                let arrow = match *sort {
+                    (sorted, false) if sorted == column => " ⏶",
+                    (sorted, true) if sorted == column => " ⏷",
+                    _ => "",
+                };
+                if ui
+                    .selectable_label(sort.0 == column, format!("{}{arrow}", column.name()))
+                    .clicked()
+                {
+                    *sort = (column, sort.0 == column && !sort.1);
+                    changed = true;
+                }
+            }
+            ui.end_row();
+
+            for group in stats {
+                ui.label(species_name(group.species));
+                ui.label(format::count(group.count as u64));
+                ui.label(format!("{:.2}", group.average_speed));
+                ui.label(format::si(group.kinetic_energy as f64));
+                ui.label(format!("{:.1}", group.bounding_radius));
+                ui.end_row();
+            }
+        });
+    changed
+}
+
 impl eframe::App for ParticleApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, usage::STORAGE_KEY, &self.usage);
+        eframe::set_value(storage, panels::STORAGE_KEY, &self.panel_layout);
+        eframe::set_value(
+            storage,
+            bindings::STORAGE_KEY,
+            &bindings::texts(&self.bindings),
+        );
+        eframe::set_value(storage, evolve::STORAGE_KEY, &self.evolve);
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        if self.overlay {
+            [0.0; 4]
+        } else {
+            // eframe's default
+            egui::Color32::from_rgba_unmultiplied(12, 12, 12, 180).to_normalized_gamma_f32()
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        if ctx.input(|i| i.key_pressed(egui::Key::U)) {
-            self.show_ui = !self.show_ui;
+        if self.overlay {
+            // The window ignores the mouse, the keyboard is the only way out
+            if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        } else {
+            if ctx.input(|i| i.key_pressed(egui::Key::U)) {
+                self.show_ui = !self.show_ui;
+            }
+            if ctx.input_mut(|i| {
+                i.consume_key(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::C,
+                )
+            }) {
+                self.copy_frame_requested = true;
+            }
+            self.handle_transport_keys(ctx);
         }
 
-        // TODO: rethink keyboard input handling
         ctx.input(|input| {
-            // Clear and rebuild the set of keys that are currently down
-            self.keys_down.clear();
-            for key in egui::Key::ALL {
-                if input.key_down(*key) {
-                    self.keys_down.insert(*key);
+            // Follow the presses and releases of this frame rather than
+            // polling every key, forgetting them all when focus goes away
+            // since the releases then never arrive
+            for event in &input.events {
+                match event {
+                    egui::Event::Key { key, pressed, .. } => {
+                        if *pressed {
+                            self.keys_down.insert(*key);
+                        } else {
+                            self.keys_down.remove(key);
+                        }
+                    }
+                    egui::Event::WindowFocused(false) => self.keys_down.clear(),
+                    _ => {}
                 }
             }
 
@@ -544,7 +5341,7 @@ impl eframe::App for ParticleApp {
             }
 
             // Track mouse dragging for particle interaction
-            self.mouse_dragging = input.pointer.primary_down();
+            self.mouse_dragging = input.pointer.primary_down() && !self.orbit_tutorial.dragging;
             if input.pointer.secondary_down() {
                 // Get the actual pointer delta from egui (this is more reliable)
                 // TODO: Check this
@@ -575,44 +5372,134 @@ impl eframe::App for ParticleApp {
             }
         });
 
+        #[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+        self.handle_tray(ctx, frame);
+
+        self.handle_allocation_failures(frame);
+        self.handle_gpu_errors(frame);
+        self.handle_dropped_files(ctx);
+        self.handle_commands(frame);
+        self.update_screensaver(ctx, frame);
+
         // Update simulation state
+        self.update_morph(ctx);
+        self.update_evolve(ctx);
+        self.update_bindings();
         self.update_simulation(ctx, frame);
+        self.update_window_title(ctx);
+        self.publish_stats();
+
+        if self.show_ui {
+            self.render_transport_bar(ctx, frame);
+        }
 
         // Create a central panel to render our 3D content
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Get the available space for rendering
-            let rect = ui.max_rect();
+        let panel_frame = if self.overlay {
+            egui::Frame::NONE
+        } else {
+            egui::Frame::central_panel(&ctx.style())
+        };
+        egui::CentralPanel::default()
+            .frame(panel_frame)
+            .show(ctx, |ui| {
+                // Get the available space for rendering
+                let rect = ui.max_rect();
+
+                // Capture rect size for aspect ratio updates
+                let size = rect.size();
+                let pixels = size * ctx.pixels_per_point();
+                self.viewport_pixels = [pixels.x.round() as u32, pixels.y.round() as u32];
+                let aspect_ratio = size.x / size.y;
+                if (aspect_ratio - self.camera.aspect).abs() > 0.001 {
+                    self.camera.aspect = aspect_ratio;
+                    self.camera.update_view_proj();
 
-            // Capture rect size for aspect ratio updates
-            let size = rect.size();
-            let aspect_ratio = size.x / size.y;
-            if (aspect_ratio - self.camera.aspect).abs() > 0.001 {
-                self.camera.aspect = aspect_ratio;
-                self.camera.update_view_proj();
+                    if let Some(wgpu_render_state) = frame.wgpu_render_state() {
+                        self.camera.update_buffer(&wgpu_render_state.queue);
+                    }
+                }
 
                 if let Some(wgpu_render_state) = frame.wgpu_render_state() {
-                    self.camera.update_buffer(&wgpu_render_state.queue);
+                    self.scene_callbacks(&wgpu_render_state.queue, &self.camera.bind_group)
+                        .paint(ui.painter(), rect);
+
+                    // The same scene again from the inset camera, on a
+                    // backdrop so it reads as its own view
+                    if self.inset.enabled {
+                        let inset_rect = self.inset.rect(rect);
+                        self.inset
+                            .update_buffer(&wgpu_render_state.queue, inset_rect);
+                        let painter = ui.painter();
+                        painter.rect_filled(inset_rect, 4.0, ctx.style().visuals.extreme_bg_color);
+                        self.scene_callbacks(
+                            &wgpu_render_state.queue,
+                            &self.inset.camera.bind_group,
+                        )
+                        .paint(painter, inset_rect);
+                        painter.rect_stroke(
+                            inset_rect,
+                            4.0,
+                            ctx.style().visuals.window_stroke,
+                            egui::StrokeKind::Outside,
+                        );
+                    }
                 }
-            }
 
-            // TODO: See about making this reference counted
-            let callback_obj = ClonedParticleCallback {
-                render_pipeline: self.renderer.render_pipeline.clone(),
-                camera_bind_group: self.camera.bind_group.clone(),
-                particle_buffer: self.simulation.get_particle_buffer().clone(),
-                num_particles: self.simulation.get_particle_count(),
-            };
+                if self.annotations.enabled {
+                    self.annotations.show_overlay(ui, rect, &self.camera);
+                }
 
-            let callback = egui_wgpu::Callback::new_paint_callback(rect, callback_obj);
-            ui.painter().add(callback);
-        });
+                if self.orbit_tutorial.active
+                    && self.orbit_tutorial.show_overlay(
+                        ui,
+                        rect,
+                        &self.camera,
+                        self.settings.attractor_mass,
+                    )
+                    && let Some(wgpu_render_state) = frame.wgpu_render_state()
+                {
+                    self.relaunch_orbit(&wgpu_render_state.device, &wgpu_render_state.queue);
+                }
+            });
+
+        self.update_benchmark(frame);
+        self.update_frame_copy(ctx, frame);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_panorama(frame);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_export(frame);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_contact_sheet(frame);
 
         // Show UI if enabled
         if self.show_ui {
             self.render_ui(ctx, frame);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.render_export_window(ctx, frame);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.render_contact_sheet_window(ctx, frame);
+            self.render_field_editor_window(ctx);
+        }
+
+        self.show_notice(ctx);
+        self.show_watchdog(ctx, frame);
+
+        #[cfg(all(feature = "update-check", not(target_arch = "wasm32")))]
+        {
+            self.update_checker.update();
+            self.update_checker.show(ctx);
         }
 
         // Request continuous repaints for smooth animation
-        ctx.request_repaint();
+        if self.power_saver.throttled() {
+            ctx.request_repaint_after(THROTTLED_FRAME_TIME);
+        } else {
+            ctx.request_repaint();
+        }
     }
 }
+
+/// Compute shaders aren't available on WebGL
+fn supports_compute(device: &wgpu::Device) -> bool {
+    device.limits().max_compute_workgroup_storage_size > 0
+}
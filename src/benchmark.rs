@@ -0,0 +1,111 @@
+use crate::settings::Settings;
+use crate::simulation::{GenerationSettings, SimulationMethod};
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Particles, steps and seed of the standard scene. Changing any of them
+/// makes scores incomparable with ones shared before.
+pub const PARTICLES: u32 = 200_000;
+pub const STEPS: u32 = 200;
+const SEED: u64 = 0x5eed;
+
+/// The standard scene, the default settings with a fixed count and seed
+pub fn scene() -> Settings {
+    Settings {
+        particle_count: PARTICLES,
+        generation: GenerationSettings {
+            seed: SEED,
+            ..GenerationSettings::default()
+        },
+        deterministic: true,
+        ..Settings::default()
+    }
+}
+
+pub struct BenchmarkResult {
+    pub method: SimulationMethod,
+    /// Name of the GPU and the graphics API it was driven through
+    pub adapter: String,
+    /// Particles actually stepped, fewer than [`PARTICLES`] when the device
+    /// can't hold them
+    pub particles: u32,
+    pub seconds: f64,
+}
+
+impl BenchmarkResult {
+    /// Millions of particle steps per second
+    pub fn score(&self) -> f64 {
+        self.particles as f64 * STEPS as f64 / self.seconds.max(1e-9) / 1e6
+    }
+
+    /// One line to paste wherever results get compared
+    pub fn share_text(&self) -> String {
+        let mut text = format!(
+            "Particle Simulation 3D v{} benchmark: {:.1} M particle-steps/s, {} backend, {}",
+            env!("CARGO_PKG_VERSION"),
+            self.score(),
+            self.method.name(),
+            self.adapter,
+        );
+        if self.particles != PARTICLES {
+            text += &format!(" ({} particles)", self.particles);
+        }
+        text
+    }
+}
+
+/// A run of the standard scene. The steps are recorded in one go, the clock
+/// stopping once the GPU reports them done.
+pub struct Benchmark {
+    /// Settings to put back once it's done
+    pub restore: Settings,
+    started: Option<Instant>,
+    /// Seconds the steps took, filled in when the GPU reports them done
+    seconds: Arc<Mutex<Option<f64>>>,
+}
+
+impl Benchmark {
+    pub fn new(restore: Settings) -> Self {
+        Self {
+            restore,
+            started: None,
+            seconds: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Whether the steps still have to be run
+    pub fn pending(&self) -> bool {
+        self.started.is_none()
+    }
+
+    /// Starts the clock, to be called right before the steps are recorded
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Stops the clock once everything submitted so far has run. Native
+    /// waits for that right away, the web can't block and hears back on a
+    /// later frame.
+    pub fn finish_after_submitted(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(started) = self.started else {
+            return;
+        };
+        let seconds = self.seconds.clone();
+        queue.on_submitted_work_done(move || {
+            *seconds.lock().unwrap() = Some(started.elapsed().as_secs_f64());
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = device.poll(wgpu::PollType::wait_indefinitely());
+        #[cfg(target_arch = "wasm32")]
+        let _ = device;
+    }
+
+    /// Seconds the steps took, once the GPU is done with them
+    pub fn poll(&self, device: &wgpu::Device) -> Option<f64> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        *self.seconds.lock().unwrap()
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+
+/// Key in eframe's storage
+pub const STORAGE_KEY: &str = "parameter_bindings";
+
+/// Name of the seconds since the app started in expressions
+pub const TIME: &str = "t";
+
+/// A parameter driven by an expression, e.g. `2*sin(t*0.5)` for the gravity
+pub struct Binding {
+    pub text: String,
+    expression: Result<Expression, String>,
+    /// Set when the parameter was changed by hand, until resumed
+    pub paused: bool,
+}
+
+impl Binding {
+    /// `text` parsed with the variables `is_variable` accepts besides [`TIME`]
+    pub fn new(text: String, is_variable: impl Fn(&str) -> bool) -> Self {
+        let expression = Expression::parse(&text, |name| name == TIME || is_variable(name));
+        Self {
+            text,
+            expression,
+            paused: false,
+        }
+    }
+
+    /// Why the text doesn't parse, if it doesn't
+    pub fn error(&self) -> Option<&str> {
+        self.expression.as_ref().err().map(String::as_str)
+    }
+
+    /// The value for this frame, `None` while paused, broken or off the
+    /// number line (e.g. dividing by zero)
+    pub fn evaluate(&self, variable: impl Fn(&str) -> f32) -> Option<f32> {
+        let expression = self.expression.as_ref().ok().filter(|_| !self.paused)?;
+        Some(expression.evaluate(&variable)).filter(|value| value.is_finite())
+    }
+}
+
+/// The texts of `bindings`, as saved
+pub fn texts(bindings: &BTreeMap<&'static str, Binding>) -> BTreeMap<String, String> {
+    bindings
+        .iter()
+        .map(|(key, binding)| (key.to_string(), binding.text.clone()))
+        .collect()
+}
+
+/// Deepest nesting of parentheses, operators and calls an expression may
+/// have, so a pasted `((((…` can't overflow the stack parsing, evaluating or
+/// dropping it
+const MAX_DEPTH: usize = 64;
+
+/// Most arguments any [`Function`] takes
+const MAX_ARGUMENTS: usize = 3;
+
+/// Arithmetic on numbers and variables with `+ - * / % ^`, parentheses and
+/// a few functions of one or more arguments
+#[derive(Debug, Clone)]
+pub struct Expression(Node);
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f32),
+    Variable(String),
+    Negate(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Abs,
+    Sqrt,
+    Exp,
+    Floor,
+    Fract,
+    Min,
+    Max,
+    Clamp,
+}
+
+impl Function {
+    /// The function called `name` and how many arguments it takes
+    fn named(name: &str) -> Option<(Function, usize)> {
+        Some(match name {
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "abs" => (Function::Abs, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "exp" => (Function::Exp, 1),
+            "floor" => (Function::Floor, 1),
+            "fract" => (Function::Fract, 1),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            "clamp" => (Function::Clamp, 3),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, arguments: &[f32]) -> f32 {
+        match (self, arguments) {
+            (Function::Sin, [x]) => x.sin(),
+            (Function::Cos, [x]) => x.cos(),
+            (Function::Tan, [x]) => x.tan(),
+            (Function::Abs, [x]) => x.abs(),
+            (Function::Sqrt, [x]) => x.sqrt(),
+            (Function::Exp, [x]) => x.exp(),
+            (Function::Floor, [x]) => x.floor(),
+            (Function::Fract, [x]) => x - x.floor(),
+            (Function::Min, [a, b]) => a.min(*b),
+            (Function::Max, [a, b]) => a.max(*b),
+            (Function::Clamp, [x, low, high]) => x.max(*low).min(*high),
+            _ => f32::NAN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(char),
+}
+
+impl Expression {
+    /// Parses `text`, rejecting names `is_variable` doesn't know. `pi` is
+    /// always there.
+    pub fn parse(text: &str, is_variable: impl Fn(&str) -> bool) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+            depth: 0,
+            is_variable: &is_variable,
+        };
+        let node = parser.sum()?;
+        match parser.next() {
+            None => Ok(Expression(node)),
+            Some(token) => Err(format!("Unexpected {}", describe(&token))),
+        }
+    }
+
+    pub fn evaluate(&self, variable: &impl Fn(&str) -> f32) -> f32 {
+        evaluate(&self.0, variable)
+    }
+}
+
+fn evaluate(node: &Node, variable: &impl Fn(&str) -> f32) -> f32 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Variable(name) => variable(name),
+        Node::Negate(node) => -evaluate(node, variable),
+        Node::Binary(operator, left, right) => {
+            let (left, right) = (evaluate(left, variable), evaluate(right, variable));
+            match operator {
+                '+' => left + right,
+                '-' => left - right,
+                '*' => left * right,
+                '/' => left / right,
+                '%' => left.rem_euclid(right),
+                _ => left.powf(right),
+            }
+        }
+        Node::Call(function, arguments) => {
+            let mut values = [0.0; MAX_ARGUMENTS];
+            for (value, argument) in values.iter_mut().zip(arguments) {
+                *value = evaluate(argument, variable);
+            }
+            function.apply(&values[..arguments.len()])
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek()
+                && (c.is_ascii_digit() || c == '.')
+            {
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            let number = &text[start..end];
+            let value = number
+                .parse()
+                .map_err(|_| format!("\"{number}\" isn't a number"))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            // Dotted, like the registry's keys
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek()
+                && (c.is_alphanumeric() || c == '_' || c == '.')
+            {
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Name(text[start..end].to_owned()));
+        } else if "+-*/%^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected \"{c}\""));
+        }
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {value}"),
+        Token::Name(name) => format!("\"{name}\""),
+        Token::Symbol(symbol) => format!("\"{symbol}\""),
+    }
+}
+
+/// Recursive descent, loosest binding first
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    is_variable: &'a dyn Fn(&str) -> bool,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.tokens.get(self.position) == Some(&Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected \"{symbol}\""))
+        }
+    }
+
+    /// One level deeper, failing past [`MAX_DEPTH`]
+    fn descend(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("Nested deeper than {MAX_DEPTH} levels"));
+        }
+        Ok(())
+    }
+
+    // Every operator of a chain nests the tree one level deeper
+    fn sum(&mut self) -> Result<Node, String> {
+        let depth = self.depth;
+        let mut node = self.product()?;
+        loop {
+            let operator = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                self.depth = depth;
+                return Ok(node);
+            };
+            self.descend()?;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let depth = self.depth;
+        let mut node = self.unary()?;
+        loop {
+            let operator = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else if self.eat('%') {
+                '%'
+            } else {
+                self.depth = depth;
+                return Ok(node);
+            };
+            self.descend()?;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        let depth = self.depth;
+        self.descend()?;
+        let node = if self.eat('-') {
+            Node::Negate(Box::new(self.unary()?))
+        } else {
+            let base = self.atom()?;
+            if self.eat('^') {
+                // Right associative, and -2^2 is -(2^2)
+                Node::Binary('^', Box::new(base), Box::new(self.unary()?))
+            } else {
+                base
+            }
+        };
+        self.depth = depth;
+        Ok(node)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Node::Number(value)),
+            Some(Token::Symbol('(')) => {
+                let node = self.sum()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(Token::Name(name)) if self.eat('(') => {
+                let (function, arity) =
+                    Function::named(&name).ok_or_else(|| format!("No function \"{name}\""))?;
+                let mut arguments = vec![self.sum()?];
+                while self.eat(',') {
+                    arguments.push(self.sum()?);
+                }
+                self.expect(')')?;
+                if arguments.len() != arity {
+                    return Err(format!("\"{name}\" takes {arity} argument(s)"));
+                }
+                Ok(Node::Call(function, arguments))
+            }
+            Some(Token::Name(name)) if name == "pi" => Ok(Node::Number(std::f32::consts::PI)),
+            Some(Token::Name(name)) if (self.is_variable)(&name) => Ok(Node::Variable(name)),
+            Some(Token::Name(name)) => Err(format!("No parameter \"{name}\"")),
+            Some(token) => Err(format!("Unexpected {}", describe(&token))),
+            None => Err("Unexpected end".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` evaluated with `x` = 2
+    fn value(text: &str) -> Result<f32, String> {
+        let expression = Expression::parse(text, |name| name == "x")?;
+        Ok(expression.evaluate(&|_| 2.0))
+    }
+
+    #[test]
+    fn follows_precedence() {
+        assert_eq!(value("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(value("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(value("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(value("12 / 3 / 2"), Ok(2.0));
+        assert_eq!(value("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(value("1 + 7 % 4 * 2"), Ok(7.0));
+        assert_eq!(value("-7 % 4"), Ok(1.0));
+        assert_eq!(value("3 * x ^ 2 - x"), Ok(10.0));
+    }
+
+    #[test]
+    fn negates_after_powers() {
+        assert_eq!(value("-2^2"), Ok(-4.0));
+        assert_eq!(value("(-2)^2"), Ok(4.0));
+        assert_eq!(value("2^-1"), Ok(0.5));
+        assert_eq!(value("--x"), Ok(2.0));
+    }
+
+    #[test]
+    fn calls_functions() {
+        assert_eq!(value("abs(-x)"), Ok(2.0));
+        assert_eq!(value("min(x, 1) + max(x, 1)"), Ok(3.0));
+        assert_eq!(value("clamp(5, 0, x)"), Ok(2.0));
+        assert_eq!(value("floor(2.5) + fract(2.5)"), Ok(2.5));
+        assert_eq!(value("sin(pi / 2)"), Ok(1.0));
+    }
+
+    #[test]
+    fn checks_arity() {
+        assert_eq!(
+            value("min(1)"),
+            Err("\"min\" takes 2 argument(s)".to_owned())
+        );
+        assert_eq!(
+            value("sin(1, 2)"),
+            Err("\"sin\" takes 1 argument(s)".to_owned())
+        );
+        assert_eq!(
+            value("clamp(1, 2)"),
+            Err("\"clamp\" takes 3 argument(s)".to_owned())
+        );
+        assert!(value("max()").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(value("y + 1"), Err("No parameter \"y\"".to_owned()));
+        assert_eq!(value("log(2)"), Err("No function \"log\"".to_owned()));
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        assert!(value("").is_err());
+        assert!(value("1 +").is_err());
+        assert!(value("(1").is_err());
+        assert!(value("1)").is_err());
+        assert!(value("1 $ 2").is_err());
+        assert!(value("1..2").is_err());
+    }
+
+    #[test]
+    fn limits_the_nesting() {
+        let within = format!("{}1{}", "(".repeat(20), ")".repeat(20));
+        assert_eq!(value(&within), Ok(1.0));
+        assert_eq!(value(&"-".repeat(40)), Err("Unexpected end".to_owned()));
+
+        let error = Err(format!("Nested deeper than {MAX_DEPTH} levels"));
+        let parentheses = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        assert_eq!(value(&parentheses), error);
+        assert_eq!(value(&format!("{}1", "-".repeat(10_000))), error);
+        assert_eq!(value(&format!("{}1", "2^".repeat(10_000))), error);
+        assert_eq!(value(&format!("{}1", "1+".repeat(10_000))), error);
+        assert_eq!(value(&format!("{}1", "sin(".repeat(10_000))), error);
+    }
+}
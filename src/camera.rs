@@ -3,6 +3,7 @@ use glam::{Mat4, Vec3};
 use std::f32::consts::PI;
 use wgpu::util::DeviceExt;
 
+// Keep in sync with `Camera` in camera.wgsl
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct CameraUniform {
@@ -19,11 +20,37 @@ impl Default for CameraUniform {
     }
 }
 
+/// Where a frame is seen from, all the particle shaders need of a camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+    pub position: Vec3,
+    pub view_proj: Mat4,
+}
+
+impl CameraView {
+    /// Looking from `position` at `target` with a vertical `fov` in radians,
+    /// seeing as far as the app's camera does
+    pub fn look_at(position: Vec3, target: Vec3, fov: f32, aspect: f32) -> Self {
+        let view = Mat4::look_at_rh(position, target, Vec3::Y);
+        let proj = Mat4::perspective_rh(fov, aspect, 0.1, 1000.0);
+        Self {
+            position,
+            view_proj: proj * view,
+        }
+    }
+
+    pub fn uniform(&self) -> CameraUniform {
+        CameraUniform {
+            view_proj: self.view_proj.to_cols_array(),
+            position: self.position.extend(1.0).into(),
+        }
+    }
+}
+
 pub struct Camera {
     pub position: Vec3,
     pub yaw: f32,
     pub pitch: f32,
-    pub up: Vec3,
     pub fov: f32,
     pub aspect: f32,
     pub near: f32,
@@ -73,7 +100,6 @@ impl Camera {
             position: Vec3::new(0.0, 0.0, 100.0),
             yaw: -PI / 2.0,
             pitch: 0.0,
-            up: Vec3::Y,
             fov: PI / 3.0,
             aspect,
             near: 0.1,
@@ -103,6 +129,13 @@ impl Camera {
         self.uniform.position = [self.position.x, self.position.y, self.position.z, 1.0];
     }
 
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            position: self.position,
+            view_proj: Mat4::from_cols_array(&self.uniform.view_proj),
+        }
+    }
+
     pub fn get_forward(&self) -> Vec3 {
         Vec3::new(
             self.yaw.cos() * self.pitch.cos(),
@@ -173,6 +206,19 @@ impl Camera {
         self.update_view_proj();
     }
 
+    /// Where `point` lands inside `rect` on screen, `None` if it's behind the camera
+    pub fn world_to_screen(&self, point: Vec3, rect: egui::Rect) -> Option<egui::Pos2> {
+        let clip = Mat4::from_cols_array(&self.uniform.view_proj) * point.extend(1.0);
+        if clip.w <= self.near {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(egui::pos2(
+            rect.left() + (ndc.x + 1.0) * 0.5 * rect.width(),
+            rect.top() + (1.0 - ndc.y) * 0.5 * rect.height(),
+        ))
+    }
+
     pub fn update_buffer(&self, queue: &wgpu::Queue) {
         queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
     }
@@ -0,0 +1,149 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+/// Copies the pixels of a texture with 4 bytes per pixel and `COPY_SRC`
+/// back from the GPU, waiting for them, as tightly packed rows
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, String> {
+    let staging = copy_texture(device, queue, texture);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    staging
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |status| {
+            let _ = sender.send(status);
+        });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .map_err(|error| error.to_string())?;
+    receiver
+        .recv()
+        .map_err(|error| error.to_string())?
+        .map_err(|error| error.to_string())?;
+    Ok(packed_rows(&staging, texture.width()))
+}
+
+/// Queues a copy of a texture with 4 bytes per pixel and `COPY_SRC` into a
+/// buffer that can be mapped once the copy is done, rows padded to the copy
+/// alignment
+pub fn copy_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> wgpu::Buffer {
+    let padded_row_bytes = padded_row_bytes(texture.width());
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Capture Readback Buffer"),
+        size: (padded_row_bytes * texture.height()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Capture Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &staging,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    staging
+}
+
+/// Copies have to start every row on a 256 byte boundary
+fn padded_row_bytes(width: u32) -> u32 {
+    (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// The pixels of a mapped [`copy_texture`] buffer as tightly packed rows,
+/// unmapping it
+pub fn packed_rows(staging: &wgpu::Buffer, width: u32) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+    let pixels = {
+        let view = staging.slice(..).get_mapped_range();
+        view.chunks_exact(padded_row_bytes(width) as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect()
+    };
+    staging.unmap();
+    pixels
+}
+
+/// A texture to render into in `format` and read back with [`read_texture`].
+/// It's stored without sRGB encoding, so the bytes read back are the ones
+/// drawn.
+pub fn render_target(
+    device: &wgpu::Device,
+    label: &str,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: format.remove_srgb_suffix(),
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[format],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some(&format!("{label} View")),
+        format: Some(format),
+        ..Default::default()
+    });
+    (texture, view)
+}
+
+/// Turns pixels read back from a [`render_target`] in `format` into opaque
+/// RGBA, the background becoming black
+pub fn opaque_rgba(pixels: &mut [u8], format: wgpu::TextureFormat) {
+    let swap_red_blue = matches!(format.remove_srgb_suffix(), wgpu::TextureFormat::Bgra8Unorm);
+    for pixel in pixels.as_chunks_mut::<4>().0 {
+        if swap_red_blue {
+            pixel.swap(0, 2);
+        }
+        pixel[3] = 255;
+    }
+}
+
+/// Writes RGBA pixels with 8 bits per channel to `path` as a PNG
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|error| error.to_string())?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|error| error.to_string())
+}
+
+/// A name in the working directory that sorts by when it was taken, like
+/// `panorama-1760000000.png`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn timestamped_path(prefix: &str, extension: &str) -> PathBuf {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    PathBuf::from(format!("{prefix}-{seconds}.{extension}"))
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Requests from outside the app, such as the page embedding the web build,
+/// as JSON like `{"type": "apply_preset", "name": "Plasma"}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    /// Settings fields to change, e.g. `{"gravity": 2.0, "color_mode": 1}`.
+    /// Changing the particle count or generation regenerates the particles.
+    SetParams {
+        params: serde_json::Value,
+    },
+    Pause,
+    Resume,
+    Reset,
+    ApplyPreset {
+        name: String,
+    },
+}
+
+impl Command {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Not a valid command: {e}"))
+    }
+}
+
+/// What the app reports back, refreshed every frame
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    pub particles: u32,
+    pub fps: f32,
+    pub paused: bool,
+    pub method: &'static str,
+    pub preset: Option<&'static str>,
+}
+
+thread_local! {
+    static QUEUE: RefCell<VecDeque<Command>> = const { RefCell::new(VecDeque::new()) };
+    static STATS: RefCell<Stats> = RefCell::new(Stats::default());
+}
+
+/// Queues `command` for the app to run on its next frame
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn push(command: Command) {
+    QUEUE.with(|queue| queue.borrow_mut().push_back(command));
+}
+
+/// Everything queued since the last call
+pub fn drain() -> Vec<Command> {
+    QUEUE.with(|queue| queue.borrow_mut().drain(..).collect())
+}
+
+pub fn publish_stats(stats: Stats) {
+    STATS.with(|current| *current.borrow_mut() = stats);
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn stats() -> Stats {
+    STATS.with(|stats| stats.borrow().clone())
+}
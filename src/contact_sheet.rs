@@ -0,0 +1,357 @@
+use crate::camera::CameraView;
+use crate::capture;
+use crate::settings::Settings;
+use std::path::PathBuf;
+
+/// Widths offered for the cells of a sheet
+pub const CELL_WIDTHS: [u32; 4] = [240, 320, 480, 640];
+/// Most values along one side of a sheet
+pub const MAX_SWEEP_VALUES: u32 = 8;
+
+/// Pixels of one dot of the label font
+const LABEL_SCALE: u32 = 2;
+/// Strip under every cell its label is written in
+const LABEL_HEIGHT: u32 = (GLYPH_HEIGHT + 4) * LABEL_SCALE;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const LABEL_COLOR: [u8; 4] = [220, 220, 220, 255];
+
+/// One parameter stepped evenly from `start` to `end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepAxis {
+    /// Registry key of the parameter
+    pub key: &'static str,
+    /// What the labels call it
+    pub name: &'static str,
+    pub start: f32,
+    pub end: f32,
+    pub count: u32,
+}
+
+impl SweepAxis {
+    fn value(&self, index: u32) -> f32 {
+        if self.count < 2 {
+            return self.start;
+        }
+        self.start + (self.end - self.start) * index as f32 / (self.count - 1) as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactSheetSettings {
+    /// Swept along every row
+    pub columns: SweepAxis,
+    /// Swept down every column, `None` for a single row
+    pub rows: Option<SweepAxis>,
+    /// Simulated seconds every cell runs from the freshly generated scene
+    /// before it is drawn
+    pub warm_up: f32,
+    pub cell_width: u32,
+}
+
+impl ContactSheetSettings {
+    pub fn cell_count(&self) -> u32 {
+        self.columns.count * self.rows.map_or(1, |rows| rows.count)
+    }
+}
+
+/// A sheet in progress, one cell per app frame. Every cell starts from the
+/// same regenerated scene and step, so they only differ by the swept values.
+pub struct ContactSheet {
+    settings: ContactSheetSettings,
+    cell: u32,
+    cell_height: u32,
+    /// Parameters from before the sweep, put back once it's done
+    pub restore: Settings,
+    /// Step counter every cell starts at, which seeds the random rolls
+    pub first_step: u32,
+    /// Where the window's camera was when the sheet started
+    view: CameraView,
+    texture: wgpu::Texture,
+    target: wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    /// The whole sheet as RGBA
+    pixels: Vec<u8>,
+}
+
+impl ContactSheet {
+    /// Allocates the sheet and the target the cells are drawn into in
+    /// `format`, seen from `view` with its `aspect`
+    pub fn start(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        settings: ContactSheetSettings,
+        restore: Settings,
+        first_step: u32,
+        view: CameraView,
+        aspect: f32,
+    ) -> Self {
+        let cell_height = (settings.cell_width as f32 / aspect).round().max(1.0) as u32;
+        let (texture, target) = capture::render_target(
+            device,
+            "Contact Sheet Target",
+            format,
+            settings.cell_width,
+            cell_height,
+        );
+        let (width, height) = Self::sheet_size(&settings, cell_height);
+        Self {
+            settings,
+            cell: 0,
+            cell_height,
+            restore,
+            first_step,
+            view,
+            texture,
+            target,
+            format,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    fn sheet_size(settings: &ContactSheetSettings, cell_height: u32) -> (u32, u32) {
+        let rows = settings.rows.map_or(1, |rows| rows.count);
+        (
+            settings.cell_width * settings.columns.count,
+            (cell_height + LABEL_HEIGHT) * rows,
+        )
+    }
+
+    /// Cells drawn so far and in total
+    pub fn cells(&self) -> (u32, u32) {
+        (self.cell, self.settings.cell_count())
+    }
+
+    /// Column and row of the next cell
+    fn position_of(&self, cell: u32) -> (u32, u32) {
+        (
+            cell % self.settings.columns.count,
+            cell / self.settings.columns.count,
+        )
+    }
+
+    /// The swept parameters and their values for the next cell
+    pub fn values(&self) -> Vec<(&'static str, f32)> {
+        let (column, row) = self.position_of(self.cell);
+        let columns = &self.settings.columns;
+        let mut values = vec![(columns.key, columns.value(column))];
+        if let Some(rows) = &self.settings.rows {
+            values.push((rows.key, rows.value(row)));
+        }
+        values
+    }
+
+    /// Fixed steps of `1 / step_rate` every cell runs before it's drawn
+    pub fn warm_up_steps(&self, step_rate: f32) -> u32 {
+        (self.settings.warm_up * step_rate).round() as u32
+    }
+
+    /// Texture view the next cell is drawn into
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.target
+    }
+
+    pub fn camera_view(&self) -> &CameraView {
+        &self.view
+    }
+
+    /// Reads back the cell drawn into the target and copies it into the
+    /// sheet with its label, returning whether that was the last one
+    pub fn write_cell(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<bool, String> {
+        let mut cell_pixels = capture::read_texture(device, queue, &self.texture)?;
+        capture::opaque_rgba(&mut cell_pixels, self.format);
+
+        let (sheet_width, _) = Self::sheet_size(&self.settings, self.cell_height);
+        let (column, row) = self.position_of(self.cell);
+        let cell_width = self.settings.cell_width;
+        let left = column * cell_width;
+        let top = row * (self.cell_height + LABEL_HEIGHT);
+        let row_bytes = (cell_width * 4) as usize;
+        for (y, source) in cell_pixels.chunks_exact(row_bytes).enumerate() {
+            let start = (((top + y as u32) * sheet_width + left) * 4) as usize;
+            self.pixels[start..start + row_bytes].copy_from_slice(source);
+        }
+
+        let columns = &self.settings.columns;
+        let mut label = format!("{} {:.3}", columns.name, columns.value(column));
+        if let Some(rows) = &self.settings.rows {
+            label += &format!("  {} {:.3}", rows.name, rows.value(row));
+        }
+        self.draw_label(
+            &label,
+            left + LABEL_SCALE * 2,
+            top + self.cell_height + LABEL_SCALE * 2,
+            left + cell_width,
+        );
+
+        self.cell += 1;
+        Ok(self.cell >= self.settings.cell_count())
+    }
+
+    /// Writes `text` in capitals from (`x`, `y`), at half size if it
+    /// doesn't fit otherwise and cut off at `right` if it still doesn't
+    fn draw_label(&mut self, text: &str, x: u32, y: u32, right: u32) {
+        let (sheet_width, _) = Self::sheet_size(&self.settings, self.cell_height);
+        let width = text.chars().count() as u32 * (GLYPH_WIDTH + 1) * LABEL_SCALE;
+        let scale = if x + width <= right { LABEL_SCALE } else { 1 };
+        let advance = (GLYPH_WIDTH + 1) * scale;
+        for (index, character) in text.chars().enumerate() {
+            let glyph_left = x + index as u32 * advance;
+            if glyph_left + GLYPH_WIDTH * scale > right {
+                break;
+            }
+            let rows = glyph(character);
+            for (dot_y, bits) in rows.iter().enumerate() {
+                for dot_x in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - dot_x)) == 0 {
+                        continue;
+                    }
+                    for offset_y in 0..scale {
+                        for offset_x in 0..scale {
+                            let px = glyph_left + dot_x * scale + offset_x;
+                            let py = y + dot_y as u32 * scale + offset_y;
+                            let start = ((py * sheet_width + px) * 4) as usize;
+                            self.pixels[start..start + 4].copy_from_slice(&LABEL_COLOR);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Saves the sheet as a PNG, returning where it is
+    pub fn finish(self) -> Result<PathBuf, String> {
+        let (width, height) = Self::sheet_size(&self.settings, self.cell_height);
+        let path = capture::timestamped_path("contact-sheet", "png");
+        capture::save_png(&path, width, height, &self.pixels)?;
+        Ok(path)
+    }
+}
+
+/// Rows of a 5×7 dot glyph, the leftmost dot in the highest bit. Letters come
+/// out as capitals, anything without a glyph as a space.
+fn glyph(character: char) -> [u8; 7] {
+    match character.to_ascii_uppercase() {
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        _ => [0; 7],
+    }
+}
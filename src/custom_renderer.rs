@@ -1,17 +1,68 @@
 use egui::PaintCallbackInfo;
 use egui_wgpu::{CallbackResources, CallbackTrait};
+use std::sync::Arc;
 
-pub struct ClonedParticleCallback {
+/// What every particle draw shares. Put once into egui's
+/// [`CallbackResources`] so the callbacks don't carry copies of it.
+pub struct ParticlePipeline {
     pub render_pipeline: wgpu::RenderPipeline,
+    pub ghost_bind_group: wgpu::BindGroup,
+    pub ghost_stride: u32,
+}
+
+/// The buffers a layer is drawn from with one camera, kept from frame to
+/// frame while they stay the same
+#[derive(PartialEq)]
+pub struct ParticleDraw {
     pub camera_bind_group: wgpu::BindGroup,
     pub particle_buffer: wgpu::Buffer,
+    /// The particles a step earlier, blended from
+    pub previous_buffer: wgpu::Buffer,
+    /// Particle indices in the order to draw them, `None` for as they are
+    pub indices: Option<wgpu::Buffer>,
+}
+
+pub struct ClonedParticleCallback {
+    pub draw: Arc<ParticleDraw>,
     pub num_particles: u32,
+    /// 1 draws only the particles, more also draws their periodic images
+    pub ghost_copies: u32,
+}
+
+/// Lines of a [`crate::renderer::LineOverlay`], like the container box
+pub struct LineCallback {
+    pub pipeline: wgpu::RenderPipeline,
+    pub camera_bind_group: wgpu::BindGroup,
+    pub bind_group: wgpu::BindGroup,
+    pub vertex_count: u32,
+}
+
+/// Everything one view of the scene draws. Without a depth buffer the lines
+/// can't be occluded by the particles in front of them only, so they're
+/// either all hidden under the particles or, x-rayed, all drawn over them.
+pub struct SceneCallbacks {
+    pub lines: Vec<LineCallback>,
+    pub particles: Vec<ClonedParticleCallback>,
+    /// Draws the lines last, over the particles
+    pub xray: bool,
 }
 
 #[cfg(target_arch = "wasm32")]
 unsafe impl Send for ClonedParticleCallback {}
 #[cfg(target_arch = "wasm32")]
 unsafe impl Sync for ClonedParticleCallback {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Send for ParticleDraw {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for ParticleDraw {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Send for ParticlePipeline {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for ParticlePipeline {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Send for LineCallback {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for LineCallback {}
 
 impl CallbackTrait for ClonedParticleCallback {
     fn prepare(
@@ -25,16 +76,97 @@ impl CallbackTrait for ClonedParticleCallback {
         Vec::new()
     }
 
+    fn paint(
+        &self,
+        _info: PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'static>,
+        callback_resources: &CallbackResources,
+    ) {
+        if let Some(pipeline) = callback_resources.get::<Arc<ParticlePipeline>>() {
+            self.draw(render_pass, pipeline);
+        }
+    }
+}
+
+impl ClonedParticleCallback {
+    /// Records the draws into `render_pass`, in the window or offscreen
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>, pipeline: &ParticlePipeline) {
+        if self.num_particles == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&pipeline.render_pipeline);
+        render_pass.set_bind_group(0, &self.draw.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.draw.particle_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.draw.previous_buffer.slice(..));
+        if let Some(indices) = &self.draw.indices {
+            render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+        }
+        for copy in 0..self.ghost_copies {
+            let offset = copy * pipeline.ghost_stride;
+            render_pass.set_bind_group(1, &pipeline.ghost_bind_group, &[offset]);
+            if self.draw.indices.is_some() {
+                render_pass.draw_indexed(0..self.num_particles, 0, 0..1);
+            } else {
+                render_pass.draw(0..self.num_particles, 0..1);
+            }
+        }
+    }
+}
+
+impl CallbackTrait for LineCallback {
     fn paint(
         &self,
         _info: PaintCallbackInfo,
         render_pass: &mut wgpu::RenderPass<'static>,
         _callback_resources: &CallbackResources,
     ) {
-        render_pass.set_pipeline(&self.render_pipeline);
+        self.draw(render_pass);
+    }
+}
+
+impl LineCallback {
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.particle_buffer.slice(..));
-        // TODO: See this
-        render_pass.draw(0..1, 0..self.num_particles);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+impl SceneCallbacks {
+    /// Adds the draws to egui, in `rect` of the window
+    pub fn paint(self, painter: &egui::Painter, rect: egui::Rect) {
+        let add_lines = |lines: Vec<LineCallback>| {
+            for line in lines {
+                painter.add(egui_wgpu::Callback::new_paint_callback(rect, line));
+            }
+        };
+        let (under, over) = if self.xray {
+            (Vec::new(), self.lines)
+        } else {
+            (self.lines, Vec::new())
+        };
+        add_lines(under);
+        for particles in self.particles {
+            painter.add(egui_wgpu::Callback::new_paint_callback(rect, particles));
+        }
+        add_lines(over);
+    }
+
+    /// Records the draws into `render_pass`, outside of egui
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>, pipeline: &ParticlePipeline) {
+        if !self.xray {
+            for line in &self.lines {
+                line.draw(render_pass);
+            }
+        }
+        for particles in &self.particles {
+            particles.draw(render_pass, pipeline);
+        }
+        if self.xray {
+            for line in &self.lines {
+                line.draw(render_pass);
+            }
+        }
     }
 }
@@ -0,0 +1,180 @@
+use crate::format;
+use crate::simulation::{Particle, SimulationMethod};
+
+/// Bytes per particle the neighbor grids take on top of the particle itself
+const GRID_BYTES_PER_PARTICLE: u64 = 24;
+/// Share of the available memory the particles may fill
+const MEMORY_SHARE: f64 = 0.5;
+/// Threads per workgroup of the compute passes
+const WORKGROUP_SIZE: u64 = 256;
+
+/// Largest particle count a backend can allocate on this device
+pub struct ParticleLimit {
+    pub max: u32,
+    /// The limit that caps it, shown in the UI
+    pub reason: String,
+}
+
+/// What the app could find out about the machine at startup, used to pick a
+/// particle count it can run smoothly instead of a fixed one
+pub struct DeviceProfile {
+    pub particle_count: u32,
+    /// Why that count was picked, shown in the UI
+    pub reasons: Vec<String>,
+    limits: wgpu::Limits,
+    /// Bytes of memory free at startup, if the platform tells
+    available_memory: Option<u64>,
+}
+
+impl DeviceProfile {
+    pub fn detect(
+        adapter: &wgpu::AdapterInfo,
+        limits: wgpu::Limits,
+        method: SimulationMethod,
+    ) -> Self {
+        let mut reasons = Vec::new();
+        let mut particle_count: u32 = match method {
+            SimulationMethod::Cpu | SimulationMethod::BarnesHut => 100_000,
+            SimulationMethod::ComputeShader => 1_000_000,
+            SimulationMethod::Boids => 200_000,
+            SimulationMethod::Liquid => 300_000,
+            SimulationMethod::Mpm => 150_000,
+        };
+        reasons.push(format!("{} backend", method.name()));
+
+        match adapter.device_type {
+            wgpu::DeviceType::Cpu => {
+                particle_count /= 10;
+                reasons.push("software renderer".to_owned());
+            }
+            wgpu::DeviceType::IntegratedGpu => {
+                particle_count /= 2;
+                reasons.push("integrated GPU".to_owned());
+            }
+            _ => {}
+        }
+
+        let (mobile, memory_gb) = platform_hints();
+        if mobile {
+            particle_count /= 4;
+            reasons.push("mobile device".to_owned());
+        }
+        if let Some(memory_gb) = memory_gb
+            && memory_gb <= 2.0
+        {
+            particle_count /= 2;
+            reasons.push(format!("{memory_gb} GB of memory"));
+        }
+
+        let mut profile = Self {
+            particle_count,
+            reasons,
+            limits,
+            available_memory: available_memory(),
+        };
+        let limit = profile.particle_limit(method);
+        if limit.max < profile.particle_count {
+            profile.particle_count = limit.max;
+            profile.reasons.push(limit.reason);
+        }
+        profile
+    }
+
+    /// The most particles `method` can hold before an allocation fails, from
+    /// the buffer limits of the device and the memory left
+    pub fn particle_limit(&self, method: SimulationMethod) -> ParticleLimit {
+        let particle_size = std::mem::size_of::<Particle>() as u64;
+        let mut limits = vec![(
+            self.limits.max_buffer_size / particle_size,
+            format!(
+                "{} max buffer size",
+                format::bytes(self.limits.max_buffer_size)
+            ),
+        )];
+        // Boids run on the compute shader backend where there is one
+        let on_gpu = match method {
+            SimulationMethod::ComputeShader | SimulationMethod::Liquid | SimulationMethod::Mpm => {
+                true
+            }
+            SimulationMethod::Boids => self.limits.max_compute_workgroup_storage_size > 0,
+            SimulationMethod::Cpu | SimulationMethod::BarnesHut => false,
+        };
+        if on_gpu {
+            let binding_size = self.limits.max_storage_buffer_binding_size as u64;
+            limits.push((
+                binding_size / particle_size,
+                format!("{} max storage binding", format::bytes(binding_size)),
+            ));
+            // Dispatches wrap onto a second dimension once the first is full
+            let workgroups = self.limits.max_compute_workgroups_per_dimension as u64;
+            limits.push((
+                workgroups * workgroups * WORKGROUP_SIZE,
+                format!(
+                    "{0} × {0} workgroups per dispatch",
+                    format::grouped(workgroups)
+                ),
+            ));
+        }
+        // The CPU backends keep a copy of the particles in main memory, and
+        // boids a snapshot of the flock
+        let copies = if method == SimulationMethod::ComputeShader {
+            1
+        } else {
+            2
+        };
+        if let Some(memory) = self.available_memory {
+            let budget = (memory as f64 * MEMORY_SHARE) as u64;
+            limits.push((
+                budget / (particle_size * copies + GRID_BYTES_PER_PARTICLE),
+                format!("{} of free memory", format::bytes(memory)),
+            ));
+        }
+
+        let (max, reason) = limits
+            .into_iter()
+            .min_by_key(|(max, _)| *max)
+            .unwrap_or_default();
+        ParticleLimit {
+            max: max.min(u32::MAX as u64) as u32,
+            reason,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Started with {} particles: {}",
+            format::si(self.particle_count as f64),
+            self.reasons.join(", ")
+        )
+    }
+}
+
+/// Whether this is a phone or tablet, and the memory size if it's known
+fn platform_hints() -> (bool, Option<f32>) {
+    #[cfg(target_arch = "wasm32")]
+    return crate::web::device_hints();
+    #[cfg(not(target_arch = "wasm32"))]
+    (cfg!(any(target_os = "android", target_os = "ios")), None)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn available_memory() -> Option<u64> {
+    // Browsers only tell the rounded total, in GiB
+    let (_, memory_gb) = crate::web::device_hints();
+    memory_gb.map(|gb| (gb as f64 * (1u64 << 30) as f64) as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "linux")))]
+fn available_memory() -> Option<u64> {
+    None
+}
@@ -0,0 +1,88 @@
+use crate::simulation::random_unit;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Key in eframe's storage
+pub const STORAGE_KEY: &str = "evolve";
+
+/// Salt for the noise lattice, apart from the simulation's own rolls
+const NOISE_SALT: u32 = 0xE701;
+
+/// Slowly drifts the chosen parameters on smooth noise within their bounds,
+/// so the scene keeps changing on its own
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Evolve {
+    pub enabled: bool,
+    /// Noise lattice points passed per minute, roughly how often each
+    /// parameter turns around
+    pub speed: f32,
+    /// Bounds of every parameter ever picked, by key
+    pub tracks: BTreeMap<String, Track>,
+    /// Position along the noise
+    #[serde(skip)]
+    time: f32,
+}
+
+/// Where one parameter may drift
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub enabled: bool,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for Evolve {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 2.0,
+            tracks: BTreeMap::new(),
+            time: 0.0,
+        }
+    }
+}
+
+impl Evolve {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Moves along the noise by `delta_time` real seconds
+    pub fn advance(&mut self, delta_time: f32) {
+        if self.enabled {
+            self.time += delta_time * self.speed / 60.0;
+        }
+    }
+
+    /// Where `key` has drifted to, `None` unless it's drifting
+    pub fn value(&self, key: &str) -> Option<f32> {
+        let track = self
+            .tracks
+            .get(key)
+            .filter(|track| self.enabled && track.enabled)?;
+        let noise = smooth_noise(self.time, seed(key));
+        Some(track.min + (track.max - track.min) * noise)
+    }
+}
+
+/// FNV-1a of `key`, so each parameter follows its own curve
+fn seed(key: &str) -> u32 {
+    key.bytes().fold(0x811C_9DC5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Value noise in [0, 1) through random points one unit apart, eased so it
+/// has no corners
+fn smooth_noise(x: f32, seed: u32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let cell = cell as i32;
+    let start = random_unit(seed, cell as u32, NOISE_SALT);
+    let end = random_unit(seed, cell.wrapping_add(1) as u32, NOISE_SALT);
+    start + (end - start) * t
+}
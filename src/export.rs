@@ -0,0 +1,276 @@
+use crate::camera::CameraView;
+use crate::capture;
+use glam::{Quat, Vec3};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+/// Frame rates offered for exports
+pub const FRAME_RATES: [u32; 3] = [24, 30, 60];
+/// Resolutions offered for exports
+pub const RESOLUTIONS: [(u32, u32); 3] = [(1280, 720), (1920, 1080), (3840, 2160)];
+
+/// What the frames are written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Numbered PNGs in a new folder
+    PngSequence,
+    /// H.264 through ffmpeg
+    Mp4,
+    /// VP9 through ffmpeg
+    WebM,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::PngSequence,
+        ExportFormat::Mp4,
+        ExportFormat::WebM,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence => "PNG Sequence",
+            ExportFormat::Mp4 => "MP4 (H.264)",
+            ExportFormat::WebM => "WebM (VP9)",
+        }
+    }
+
+    /// Encoder arguments for ffmpeg, none for image sequences
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            ExportFormat::PngSequence => &[],
+            ExportFormat::Mp4 => &["-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf", "18"],
+            ExportFormat::WebM => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-pix_fmt",
+                "yuv420p",
+                "-b:v",
+                "0",
+                "-crf",
+                "30",
+            ],
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence => "frames",
+            ExportFormat::Mp4 => "mp4",
+            ExportFormat::WebM => "webm",
+        }
+    }
+}
+
+/// How the camera moves over the export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraPath {
+    /// Holds the view the export started from
+    Fixed,
+    /// One turn around the vertical axis through the origin, looking at it
+    Orbit,
+}
+
+impl CameraPath {
+    pub const ALL: [CameraPath; 2] = [CameraPath::Fixed, CameraPath::Orbit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraPath::Fixed => "Fixed",
+            CameraPath::Orbit => "Orbit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportSettings {
+    /// Simulated seconds to export at the current time scale
+    pub duration: f32,
+    pub fps: u32,
+    pub resolution: (u32, u32),
+    pub format: ExportFormat,
+    pub camera_path: CameraPath,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            duration: 10.0,
+            fps: FRAME_RATES[1],
+            resolution: RESOLUTIONS[1],
+            format: ExportFormat::PngSequence,
+            camera_path: CameraPath::Fixed,
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn total_frames(&self) -> u32 {
+        ((self.duration * self.fps as f32).round() as u32).max(1)
+    }
+}
+
+/// Where finished frames go
+enum FrameSink {
+    Png(PathBuf),
+    Ffmpeg(Child, PathBuf),
+}
+
+/// An export in progress, taking one video frame per app frame. Every video
+/// frame advances the simulation by the same whole number of fixed steps, so
+/// the result doesn't depend on how fast the machine renders.
+pub struct ExportJob {
+    settings: ExportSettings,
+    frame: u32,
+    total_frames: u32,
+    /// Pose of the camera when the export started
+    start_position: Vec3,
+    start_forward: Vec3,
+    fov: f32,
+    texture: wgpu::Texture,
+    target: wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    sink: FrameSink,
+}
+
+impl ExportJob {
+    /// Opens the output and the target the frames are drawn into in `format`,
+    /// starting from the camera at `position` looking along `forward`
+    pub fn start(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        settings: ExportSettings,
+        position: Vec3,
+        forward: Vec3,
+        fov: f32,
+    ) -> Result<Self, String> {
+        let (width, height) = settings.resolution;
+        let path = capture::timestamped_path("export", settings.format.extension());
+        let sink = if settings.format == ExportFormat::PngSequence {
+            std::fs::create_dir_all(&path).map_err(|error| error.to_string())?;
+            FrameSink::Png(path)
+        } else {
+            let child = Command::new("ffmpeg")
+                .args(["-y", "-f", "rawvideo", "-pix_fmt", "rgba", "-s"])
+                .arg(format!("{width}x{height}"))
+                .arg("-r")
+                .arg(settings.fps.to_string())
+                .args(["-i", "-"])
+                .args(settings.format.codec_args())
+                .arg(&path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|error| format!("couldn't start ffmpeg: {error}"))?;
+            FrameSink::Ffmpeg(child, path)
+        };
+
+        let (texture, target) =
+            capture::render_target(device, "Export Target", format, width, height);
+
+        Ok(Self {
+            settings,
+            frame: 0,
+            total_frames: settings.total_frames(),
+            start_position: position,
+            start_forward: forward,
+            fov,
+            texture,
+            target,
+            format,
+            sink,
+        })
+    }
+
+    /// Frames written so far and in total
+    pub fn frames(&self) -> (u32, u32) {
+        (self.frame, self.total_frames)
+    }
+
+    /// Fixed steps of `1 / step_rate` to take before drawing the next frame,
+    /// so frame n shows the simulation n / fps seconds in
+    pub fn due_steps(&self, step_rate: f32) -> u32 {
+        let fps = self.settings.fps as f64;
+        let steps_at = |frame: u32| (frame as f64 * step_rate as f64 / fps).floor() as u32;
+        steps_at(self.frame) - steps_at(self.frame.saturating_sub(1))
+    }
+
+    /// Texture view the next frame is drawn into
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.target
+    }
+
+    /// Where the camera path has got to for the next frame
+    pub fn camera_view(&self) -> CameraView {
+        let (width, height) = self.settings.resolution;
+        let aspect = width as f32 / height as f32;
+        match self.settings.camera_path {
+            CameraPath::Fixed => CameraView::look_at(
+                self.start_position,
+                self.start_position + self.start_forward,
+                self.fov,
+                aspect,
+            ),
+            CameraPath::Orbit => {
+                let turn = std::f32::consts::TAU * self.frame as f32 / self.total_frames as f32;
+                let position = Quat::from_rotation_y(turn) * self.start_position;
+                CameraView::look_at(position, Vec3::ZERO, self.fov, aspect)
+            }
+        }
+    }
+
+    /// Reads back the frame drawn into the target and writes it out,
+    /// returning whether that was the last one
+    pub fn write_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<bool, String> {
+        let mut pixels = capture::read_texture(device, queue, &self.texture)?;
+        // Videos have no transparency
+        capture::opaque_rgba(&mut pixels, self.format);
+
+        let (width, height) = self.settings.resolution;
+        match &mut self.sink {
+            FrameSink::Png(folder) => {
+                let path = folder.join(format!("frame-{:05}.png", self.frame));
+                capture::save_png(&path, width, height, &pixels)?;
+            }
+            FrameSink::Ffmpeg(child, _) => {
+                let stdin = child.stdin.as_mut().ok_or("ffmpeg closed its input")?;
+                stdin
+                    .write_all(&pixels)
+                    .map_err(|error| format!("couldn't write to ffmpeg: {error}"))?;
+            }
+        }
+        self.frame += 1;
+        Ok(self.frame >= self.total_frames)
+    }
+
+    /// Closes the output once every frame is written, returning where it is
+    pub fn finish(self) -> Result<PathBuf, String> {
+        match self.sink {
+            FrameSink::Png(folder) => Ok(folder),
+            FrameSink::Ffmpeg(mut child, path) => {
+                // Closing its input lets ffmpeg finish the file
+                drop(child.stdin.take());
+                let status = child.wait().map_err(|error| error.to_string())?;
+                if status.success() {
+                    Ok(path)
+                } else {
+                    Err(format!("ffmpeg failed with {status}"))
+                }
+            }
+        }
+    }
+
+    /// Stops early, keeping the frames written so far
+    pub fn cancel(self) {
+        if let FrameSink::Ffmpeg(mut child, _) = self.sink {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::OnceLock;
+
+/// Digit grouping and decimal mark of the user's locale
+#[derive(Debug, Clone, Copy)]
+struct NumberLocale {
+    group_separator: char,
+    decimal_separator: char,
+}
+
+const ENGLISH: NumberLocale = NumberLocale {
+    group_separator: ',',
+    decimal_separator: '.',
+};
+
+fn locale() -> NumberLocale {
+    static LOCALE: OnceLock<NumberLocale> = OnceLock::new();
+    *LOCALE.get_or_init(|| detect_locale().unwrap_or(ENGLISH))
+}
+
+/// Guesses the number format from the POSIX locale variables. The web build
+/// has no access to them and keeps the English format.
+fn detect_locale() -> Option<NumberLocale> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    let name = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    let language = name
+        .split(['_', '.', '-', '@'])
+        .next()?
+        .to_ascii_lowercase();
+
+    let (group_separator, decimal_separator) = match language.as_str() {
+        "de" | "es" | "it" | "pt" | "nl" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" => {
+            ('.', ',')
+        }
+        // Narrow no-break space
+        "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "uk" | "hu" | "bg" => {
+            ('\u{202F}', ',')
+        }
+        _ => return None,
+    };
+    Some(NumberLocale {
+        group_separator,
+        decimal_separator,
+    })
+}
+
+/// `1234567` → `"1,234,567"`, using the locale's digit grouping
+pub fn grouped(value: u64) -> String {
+    let digits = value.to_string();
+    let separator = locale().group_separator;
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 * 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Short form with an SI suffix, `1_200_000` → `"1.2M"`, `500_000` → `"500k"`
+pub fn si(value: f64) -> String {
+    const UNITS: [(f64, &str); 4] = [(1.0, ""), (1e3, "k"), (1e6, "M"), (1e9, "G")];
+    let mut unit = UNITS
+        .iter()
+        .rposition(|(scale, _)| value.abs() >= *scale)
+        .unwrap_or(0);
+    // 999_950 would round to "1000k"
+    if unit + 1 < UNITS.len() && (value / UNITS[unit].0).abs().round() >= 1000.0 {
+        unit += 1;
+    }
+    let (scale, suffix) = UNITS[unit];
+    let scaled = value / scale;
+
+    // Up to three significant digits, without trailing zeros
+    let decimals = if scaled.abs() >= 100.0 {
+        0
+    } else if scaled.abs() >= 10.0 {
+        1
+    } else {
+        2
+    };
+    let mut text = format!("{scaled:.decimals$}");
+    if text.contains('.') {
+        text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+    }
+    format!(
+        "{}{suffix}",
+        text.replace('.', &locale().decimal_separator.to_string())
+    )
+}
+
+/// Count as shown next to sliders and statistics, e.g. `"1,200,000 (1.2M)"`
+pub fn count(value: u64) -> String {
+    if value < 1000 {
+        value.to_string()
+    } else {
+        format!("{} ({})", grouped(value), si(value as f64))
+    }
+}
+
+/// Byte size with an SI suffix, `268_435_456` → `"268MB"`
+pub fn bytes(value: u64) -> String {
+    format!("{}B", si(value as f64))
+}
+
+/// Parses counts typed by the user: plain or grouped digits (`"1,000,000"`,
+/// `"1 000 000"`, `"1_000"`) and SI suffixes (`"500k"`, `"1.5m"`, `"2M"`)
+pub fn parse_count(text: &str) -> Option<u64> {
+    let text: String = text
+        .trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '\'')
+        .collect();
+    let (number, multiplier) = match text.chars().last()?.to_ascii_lowercase() {
+        'k' => (&text[..text.len() - 1], 1e3),
+        'm' => (&text[..text.len() - 1], 1e6),
+        'g' | 'b' => (&text[..text.len() - 1], 1e9),
+        _ => (text.as_str(), 1.0),
+    };
+
+    let locale = locale();
+    let number = if multiplier > 1.0 {
+        // With a suffix a single separator is the decimal mark, "1,5k" or "1.5k"
+        number.replace([locale.decimal_separator, '.', ','], ".")
+    } else {
+        // Without a suffix every separator groups thousands, "2.5" is a
+        // fractional count rather than 25
+        let mut groups = number.split([locale.group_separator, ',', '.']);
+        let mut digits = groups.next()?.to_string();
+        for group in groups {
+            if group.len() != 3 {
+                return None;
+            }
+            digits.push_str(group);
+        }
+        digits
+    };
+    let value = number.parse::<f64>().ok()? * multiplier;
+    (value.is_finite() && value >= 0.0).then(|| value.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` with the English separators swapped for the locale's
+    fn localized(text: &str) -> String {
+        let locale = locale();
+        text.chars()
+            .map(|c| match c {
+                ',' => locale.group_separator,
+                '.' => locale.decimal_separator,
+                c => c,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(grouped(0), "0");
+        assert_eq!(grouped(999), "999");
+        assert_eq!(grouped(1000), localized("1,000"));
+        assert_eq!(grouped(1_234_567), localized("1,234,567"));
+        assert_eq!(grouped(u64::MAX), localized("18,446,744,073,709,551,615"));
+    }
+
+    #[test]
+    fn shortens_with_si_suffixes() {
+        assert_eq!(si(0.0), "0");
+        assert_eq!(si(999.0), "999");
+        assert_eq!(si(1000.0), "1k");
+        assert_eq!(si(1234.0), localized("1.23k"));
+        assert_eq!(si(12_345.0), localized("12.3k"));
+        assert_eq!(si(500_000.0), "500k");
+        assert_eq!(si(1_200_000.0), localized("1.2M"));
+        assert_eq!(si(-2_500_000_000.0), localized("-2.5G"));
+    }
+
+    #[test]
+    fn moves_to_the_next_suffix_after_rounding() {
+        assert_eq!(si(999.6), "1k");
+        assert_eq!(si(999_950.0), "1M");
+        assert_eq!(si(999_999_999.0), "1G");
+        assert_eq!(si(999_950_000_000.0), "1000G");
+    }
+
+    #[test]
+    fn formats_bytes() {
+        assert_eq!(bytes(512), "512B");
+        assert_eq!(bytes(268_435_456), "268MB");
+        assert_eq!(bytes(1 << 30), localized("1.07GB"));
+    }
+
+    #[test]
+    fn parses_plain_and_grouped_counts() {
+        assert_eq!(parse_count("0"), Some(0));
+        assert_eq!(parse_count(" 1000 "), Some(1000));
+        assert_eq!(parse_count("1,000,000"), Some(1_000_000));
+        assert_eq!(parse_count("1.000.000"), Some(1_000_000));
+        assert_eq!(parse_count("1 000 000"), Some(1_000_000));
+        assert_eq!(parse_count("1_000"), Some(1000));
+        assert_eq!(parse_count("1'000"), Some(1000));
+    }
+
+    #[test]
+    fn parses_si_suffixes() {
+        assert_eq!(parse_count("500k"), Some(500_000));
+        assert_eq!(parse_count("1.5m"), Some(1_500_000));
+        assert_eq!(parse_count("1,5M"), Some(1_500_000));
+        assert_eq!(parse_count("2G"), Some(2_000_000_000));
+        assert_eq!(parse_count("1b"), Some(1_000_000_000));
+    }
+
+    #[test]
+    fn rejects_fractional_and_invalid_counts() {
+        assert_eq!(parse_count("2.5"), None);
+        assert_eq!(parse_count("1,00"), None);
+        assert_eq!(parse_count("1,0000"), None);
+        assert_eq!(parse_count(""), None);
+        assert_eq!(parse_count("k"), None);
+        assert_eq!(parse_count("-5"), None);
+        assert_eq!(parse_count("lots"), None);
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        for value in [0, 7, 999, 1000, 65_536, 1_234_567, 4_294_967_295] {
+            assert_eq!(parse_count(&grouped(value)), Some(value));
+        }
+        for value in [0, 999, 1000, 1500, 500_000, 1_200_000, 2_000_000_000] {
+            assert_eq!(parse_count(&si(value as f64)), Some(value));
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::capture;
+use crate::simulation::readback::{MapState, ReadMapping};
+
+/// One frame of the particle layer on its way to the clipboard. It's read
+/// back without waiting, since nothing can block on the web, and
+/// [`FrameCopy::poll`] hands it out once it arrives.
+pub struct FrameCopy {
+    texture: wgpu::Texture,
+    target: wgpu::TextureView,
+    format: wgpu::TextureFormat,
+    staging: Option<wgpu::Buffer>,
+    mapping: ReadMapping,
+}
+
+impl FrameCopy {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let (texture, target) = capture::render_target(
+            device,
+            "Frame Copy Target",
+            format,
+            width.max(1),
+            height.max(1),
+        );
+        Self {
+            texture,
+            target,
+            format,
+            staging: None,
+            mapping: ReadMapping::new(),
+        }
+    }
+
+    /// What to draw the frame into before [`FrameCopy::start`]
+    pub fn target(&self) -> &wgpu::TextureView {
+        &self.target
+    }
+
+    /// Queues reading back what was drawn into [`FrameCopy::target`]
+    pub fn start(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let staging = capture::copy_texture(device, queue, &self.texture);
+        self.mapping.start(staging.slice(..));
+        self.staging = Some(staging);
+    }
+
+    /// The frame as an opaque image once it's back, `None` while it's still
+    /// on its way
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Result<egui::ColorImage, String>> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        let staging = self.staging.as_ref()?;
+        match self.mapping.state() {
+            MapState::Pending => None,
+            MapState::Done => {
+                let (width, height) = (self.texture.width(), self.texture.height());
+                let mut pixels = capture::packed_rows(staging, width);
+                capture::opaque_rgba(&mut pixels, self.format);
+                Some(Ok(egui::ColorImage::from_rgba_unmultiplied(
+                    [width as usize, height as usize],
+                    &pixels,
+                )))
+            }
+            MapState::Failed => Some(Err("the frame couldn't be read back".to_owned())),
+        }
+    }
+}
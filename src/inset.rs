@@ -0,0 +1,83 @@
+use crate::camera::Camera;
+use glam::Vec3;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// How far the overview poses sit from the origin
+const OVERVIEW_DISTANCE: f32 = 150.0;
+/// Gap between the inset and the edges of the view, in points
+const MARGIN: f32 = 12.0;
+
+/// Fixed poses for the inset camera, looking at the origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsetPose {
+    TopDown,
+    Front,
+    Side,
+}
+
+impl InsetPose {
+    pub const ALL: [InsetPose; 3] = [InsetPose::TopDown, InsetPose::Front, InsetPose::Side];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InsetPose::TopDown => "Top Down",
+            InsetPose::Front => "Front",
+            InsetPose::Side => "Side",
+        }
+    }
+}
+
+/// A second camera drawn into the bottom right corner of the 3D view with
+/// its own uniform buffer, e.g. a fixed overview while flying the main one
+pub struct Inset {
+    pub enabled: bool,
+    /// Fraction of the view's width the inset takes
+    pub size: f32,
+    pub camera: Camera,
+}
+
+impl Inset {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let mut inset = Self {
+            enabled: false,
+            size: 0.3,
+            camera: Camera::new(device, 1.0),
+        };
+        inset.set_pose(InsetPose::TopDown);
+        inset
+    }
+
+    pub fn set_pose(&mut self, pose: InsetPose) {
+        let (position, yaw, pitch) = match pose {
+            // Pitch stays short of straight down, where the view has no up
+            InsetPose::TopDown => (Vec3::Y, -FRAC_PI_2, -FRAC_PI_2 + 0.01),
+            InsetPose::Front => (Vec3::Z, -FRAC_PI_2, 0.0),
+            InsetPose::Side => (Vec3::X, PI, 0.0),
+        };
+        self.camera.position = position * OVERVIEW_DISTANCE;
+        self.camera.yaw = yaw;
+        self.camera.pitch = pitch;
+        self.camera.update_view_proj();
+    }
+
+    /// Takes over where `camera` is looking from
+    pub fn copy_pose(&mut self, camera: &Camera) {
+        self.camera.position = camera.position;
+        self.camera.yaw = camera.yaw;
+        self.camera.pitch = camera.pitch;
+        self.camera.update_view_proj();
+    }
+
+    /// Where the inset goes inside `view`, keeping the view's aspect ratio
+    pub fn rect(&self, view: egui::Rect) -> egui::Rect {
+        let size = view.size() * self.size;
+        egui::Rect::from_min_size(view.max - size - egui::vec2(MARGIN, MARGIN), size)
+    }
+
+    /// Fits the camera to `rect` and uploads it
+    pub fn update_buffer(&mut self, queue: &wgpu::Queue, rect: egui::Rect) {
+        self.camera.aspect = rect.width() / rect.height();
+        self.camera.update_view_proj();
+        self.camera.update_buffer(queue);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::settings::Settings;
+use crate::simulation::flow_field::FlowField;
+use crate::simulation::{ParticleSimulation, SimulationMethod};
+use std::sync::Arc;
+
+/// One of several independent simulations composited in the same view
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    /// Everything the layer runs on while another one is being edited,
+    /// `None` for the active layer since the app holds its state
+    pub parked: Option<Parked>,
+    pub coupling: Option<Coupling>,
+}
+
+/// One-way coupling, the layer gets dragged along by the flow of another
+pub struct Coupling {
+    /// Index of the layer whose flow is followed
+    pub source: usize,
+    /// Rate at which velocities relax towards the flow, per second
+    pub strength: f32,
+    /// Flow last measured from the source, refreshed as samples come back
+    pub field: Option<Arc<FlowField>>,
+}
+
+impl Coupling {
+    pub fn new(source: usize) -> Self {
+        Self {
+            source,
+            strength: 2.0,
+            field: None,
+        }
+    }
+}
+
+/// A layer's simulation and configuration while it runs in the background
+pub struct Parked {
+    pub simulation: Box<dyn ParticleSimulation>,
+    pub method: SimulationMethod,
+    pub settings: Settings,
+}
+
+impl Layer {
+    pub fn active(name: String) -> Self {
+        Self {
+            name,
+            visible: true,
+            parked: None,
+            coupling: None,
+        }
+    }
+
+    /// Whether the layer is drawn, only the soloed one is if any
+    pub fn shown(&self, index: usize, solo: Option<usize>) -> bool {
+        match solo {
+            Some(solo) => solo == index,
+            None => self.visible,
+        }
+    }
+}
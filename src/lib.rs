@@ -1,7 +1,55 @@
+#![recursion_limit = "256"]
+
+mod advisor;
+#[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+mod alloc_count;
+mod allocation;
+mod annotations;
 mod app;
+mod benchmark;
+mod bindings;
 mod camera;
+mod capture;
+mod commands;
+#[cfg(not(target_arch = "wasm32"))]
+mod contact_sheet;
 mod custom_renderer;
+mod device_profile;
+mod evolve;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+mod format;
+mod frame_copy;
+mod inset;
+mod layers;
+mod morph;
+mod node_editor;
+mod panels;
+#[cfg(not(target_arch = "wasm32"))]
+mod panorama;
+mod parameters;
+mod power;
+mod presets;
+mod recording;
 mod renderer;
+mod screensaver;
+mod settings;
 mod simulation;
+mod timestep;
+#[cfg(all(feature = "tray", not(target_arch = "wasm32")))]
+mod tray;
+mod tutorial;
+#[cfg(all(feature = "update-check", not(target_arch = "wasm32")))]
+mod update_check;
+mod usage;
+mod watchdog;
+#[cfg(target_arch = "wasm32")]
+mod web;
+mod wgsl;
 
+#[cfg(all(feature = "alloc-count", not(target_arch = "wasm32")))]
+pub use alloc_count::CountingAllocator;
 pub use app::ParticleApp;
+pub use camera::CameraView;
+#[cfg(target_arch = "wasm32")]
+pub use web::{embed_requested, install_api};
@@ -1,9 +1,14 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "alloc-count")))]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
+#[cfg(all(not(target_arch = "wasm32"), feature = "alloc-count"))]
+#[global_allocator]
+static GLOBAL: particle_simulation_3d::CountingAllocator<mimalloc::MiMalloc> =
+    particle_simulation_3d::CountingAllocator(mimalloc::MiMalloc);
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
     use std::sync::Arc;
@@ -11,15 +16,34 @@ fn main() -> eframe::Result {
     #[cfg(feature = "logs")]
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("debug"));
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // "/s" is how Windows starts screensavers
+    let screensaver = args
+        .iter()
+        .any(|arg| arg == "--screensaver" || arg.eq_ignore_ascii_case("/s"));
+    let overlay = args.iter().any(|arg| arg == "--overlay");
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([1360.0, 768.0])
+        .with_fullscreen(screensaver)
+        .with_min_inner_size([800.0, 600.0])
+        .with_icon(
+            // NOTE: Adding an icon is optional
+            eframe::icon_data::from_png_bytes(&include_bytes!("../assets/icon-256.png")[..])
+                .expect("Failed to load icon"),
+        );
+    if overlay {
+        // Desktop overlay: particles on top of everything, clicks go through
+        viewport = viewport
+            .with_transparent(true)
+            .with_decorations(false)
+            .with_maximized(true)
+            .with_always_on_top()
+            .with_mouse_passthrough(true);
+    }
+
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1360.0, 768.0])
-            .with_min_inner_size([800.0, 600.0])
-            .with_icon(
-                // NOTE: Adding an icon is optional
-                eframe::icon_data::from_png_bytes(&include_bytes!("../assets/icon-256.png")[..])
-                    .expect("Failed to load icon"),
-            ),
+        viewport,
         renderer: eframe::Renderer::Wgpu,
         // TODO: Check this
         wgpu_options: egui_wgpu::WgpuConfiguration {
@@ -71,7 +95,16 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Particle Simulation 3D",
         native_options,
-        Box::new(|cc| Ok(Box::new(particle_simulation_3d::ParticleApp::new(cc)))),
+        Box::new(move |cc| {
+            let mut app = particle_simulation_3d::ParticleApp::new(cc);
+            if overlay {
+                app = app.with_overlay();
+            }
+            if screensaver {
+                app = app.with_screensaver();
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
 
@@ -85,6 +118,8 @@ fn main() {
 
     let web_options = eframe::WebOptions::default();
 
+    particle_simulation_3d::install_api();
+
     wasm_bindgen_futures::spawn_local(async {
         let document = web_sys::window()
             .expect("No window")
@@ -101,7 +136,14 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Box::new(particle_simulation_3d::ParticleApp::new(cc)))),
+                Box::new(|cc| {
+                    let app = particle_simulation_3d::ParticleApp::new(cc);
+                    Ok(Box::new(if particle_simulation_3d::embed_requested() {
+                        app.with_embed()
+                    } else {
+                        app
+                    }))
+                }),
             )
             .await;
 
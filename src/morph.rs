@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+/// How a morph speeds up and slows down over its duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::EaseIn => "Ease In",
+            Easing::EaseOut => "Ease Out",
+            Easing::EaseInOut => "Ease In-Out",
+        }
+    }
+
+    /// Progress through the values at `t` in [0, 1] of the duration
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// What the next morph goes between, as picked in the UI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MorphSettings {
+    /// Preset to start from, `None` for the parameters as they are
+    pub from: Option<usize>,
+    pub to: usize,
+    /// Real seconds the morph takes
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+impl Default for MorphSettings {
+    fn default() -> Self {
+        Self {
+            from: None,
+            to: 0,
+            duration: 10.0,
+            easing: Easing::EaseInOut,
+        }
+    }
+}
+
+/// A morph in progress, carrying every parameter from its start to its end
+/// value. Toggles are carried as 0 and 1, so they flip halfway through.
+pub struct Morph {
+    /// Preset the morph ends on, whose other settings are applied once done
+    pub target: usize,
+    /// Start and end value by parameter key
+    tracks: BTreeMap<&'static str, (f32, f32)>,
+    duration: f32,
+    easing: Easing,
+    elapsed: f32,
+}
+
+impl Morph {
+    pub fn new(
+        target: usize,
+        tracks: BTreeMap<&'static str, (f32, f32)>,
+        duration: f32,
+        easing: Easing,
+    ) -> Self {
+        Self {
+            target,
+            tracks,
+            duration,
+            easing,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Moves on by `delta_time` real seconds
+    pub fn advance(&mut self, delta_time: f32) {
+        self.elapsed = (self.elapsed + delta_time).min(self.duration);
+    }
+
+    /// Fraction of the duration passed
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Where every parameter has got to, by key
+    pub fn values(&self) -> impl Iterator<Item = (&'static str, f32)> + '_ {
+        let t = self.easing.apply(self.progress());
+        self.tracks
+            .iter()
+            .map(move |(&key, &(start, end))| (key, start + (end - start) * t))
+    }
+}
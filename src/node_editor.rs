@@ -0,0 +1,259 @@
+use crate::simulation::field_graph::{FieldGraph, MAX_FIELD_NODES, NodeKind};
+
+const CANVAS_HEIGHT: f32 = 360.0;
+const NODE_WIDTH: f32 = 150.0;
+const HEADER_HEIGHT: f32 = 22.0;
+const ROW_HEIGHT: f32 = 22.0;
+const PORT_RADIUS: f32 = 5.0;
+/// How close to an input a wire has to be dropped to connect
+const SNAP_DISTANCE: f32 = 12.0;
+
+fn node_size(kind: NodeKind) -> egui::Vec2 {
+    let rows = kind.arity() + usize::from(kind.value_label().is_some());
+    egui::vec2(NODE_WIDTH, HEADER_HEIGHT + rows as f32 * ROW_HEIGHT + 4.0)
+}
+
+fn input_port(rect: egui::Rect, slot: usize) -> egui::Pos2 {
+    egui::pos2(
+        rect.left(),
+        rect.top() + HEADER_HEIGHT + ROW_HEIGHT * (slot as f32 + 0.5),
+    )
+}
+
+fn output_port(rect: egui::Rect) -> egui::Pos2 {
+    egui::pos2(rect.right(), rect.top() + HEADER_HEIGHT * 0.5)
+}
+
+fn draw_wire(painter: &egui::Painter, from: egui::Pos2, to: egui::Pos2, stroke: egui::Stroke) {
+    let bend = ((to.x - from.x).abs() * 0.5).max(30.0);
+    painter.add(egui::epaint::CubicBezierShape::from_points_stroke(
+        [
+            from,
+            from + egui::vec2(bend, 0.0),
+            to - egui::vec2(bend, 0.0),
+            to,
+        ],
+        false,
+        egui::Color32::TRANSPARENT,
+        stroke,
+    ));
+}
+
+/// Canvas of the field graph's nodes. Drag a node by its title, wire an
+/// output into an input by dragging between their ports, right-click an input
+/// to unplug it and a title to remove the node. Returns whether the graph
+/// changed.
+pub fn show(ui: &mut egui::Ui, graph: &mut FieldGraph) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut graph.enabled, "Enabled").changed();
+        let full = graph.nodes.len() >= MAX_FIELD_NODES;
+        ui.add_enabled_ui(!full, |ui| {
+            ui.menu_button("Add Node", |ui| {
+                for kind in NodeKind::ALL {
+                    if ui.button(kind.name()).clicked() {
+                        // Cascade new nodes so they don't hide each other
+                        let offset = (graph.nodes.len() % 8) as f32 * 16.0;
+                        changed |= graph.add(kind, [20.0 + offset, 20.0 + offset]);
+                        ui.close();
+                    }
+                }
+            });
+        });
+        if full {
+            ui.weak(format!("Up to {MAX_FIELD_NODES} nodes"));
+        }
+    });
+
+    let (canvas, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), CANVAS_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let canvas = canvas.rect;
+    let visuals = ui.visuals().clone();
+    painter.rect_filled(canvas, 2.0, visuals.extreme_bg_color);
+    let id = ui.id().with("field_graph_canvas");
+
+    // Move nodes first so the wires follow them this frame
+    let mut remove = None;
+    for (i, node) in graph.nodes.iter_mut().enumerate() {
+        let size = node_size(node.kind);
+        let rect = egui::Rect::from_min_size(
+            canvas.min + egui::Vec2::from(node.position),
+            egui::vec2(size.x, HEADER_HEIGHT),
+        );
+        let header = ui.interact(rect, id.with(("node", i)), egui::Sense::click_and_drag());
+        if header.dragged() {
+            let max = (canvas.size() - size).max(egui::Vec2::ZERO);
+            let position = (egui::Vec2::from(node.position) + header.drag_delta())
+                .clamp(egui::Vec2::ZERO, max);
+            node.position = position.into();
+            changed = true;
+        }
+        header.context_menu(|ui| {
+            if ui.button("Remove").clicked() {
+                remove = Some(i);
+                ui.close();
+            }
+        });
+    }
+    if let Some(i) = remove {
+        graph.remove(i);
+        changed = true;
+    }
+
+    let rects: Vec<egui::Rect> = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            egui::Rect::from_min_size(
+                canvas.min + egui::Vec2::from(node.position),
+                node_size(node.kind),
+            )
+        })
+        .collect();
+
+    let wire = egui::Stroke::new(2.0_f32, visuals.widgets.active.fg_stroke.color);
+    for (i, node) in graph.nodes.iter().enumerate() {
+        for (slot, input) in node.inputs.iter().enumerate().take(node.kind.arity()) {
+            if let Some(&source) = input.and_then(|source| rects.get(source)) {
+                draw_wire(
+                    &painter,
+                    output_port(source),
+                    input_port(rects[i], slot),
+                    wire,
+                );
+            }
+        }
+    }
+
+    let mut connection = None;
+    let mut disconnect = None;
+    for (i, node) in graph.nodes.iter_mut().enumerate() {
+        let rect = rects[i];
+        let header = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), HEADER_HEIGHT));
+        let header_fill = if node.kind == NodeKind::Force {
+            visuals.selection.bg_fill
+        } else {
+            visuals.widgets.inactive.bg_fill
+        };
+        painter.rect_filled(rect, 4.0, visuals.widgets.noninteractive.bg_fill);
+        painter.rect_filled(header, 4.0, header_fill);
+        painter.rect_stroke(
+            rect,
+            4.0,
+            visuals.widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
+        painter.text(
+            header.left_center() + egui::vec2(8.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            node.kind.name(),
+            egui::FontId::proportional(13.0),
+            visuals.strong_text_color(),
+        );
+
+        let arity = node.kind.arity();
+        for slot in 0..arity {
+            let port = input_port(rect, slot);
+            let label = match (arity, slot) {
+                (1, _) => "In",
+                (_, 0) => "A",
+                _ => "B",
+            };
+            painter.text(
+                port + egui::vec2(10.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                label,
+                egui::FontId::proportional(12.0),
+                visuals.text_color(),
+            );
+            painter.circle_filled(port, PORT_RADIUS, visuals.widgets.active.fg_stroke.color);
+            let response = ui
+                .interact(
+                    egui::Rect::from_center_size(port, egui::Vec2::splat(PORT_RADIUS * 3.0)),
+                    id.with(("input", i, slot)),
+                    egui::Sense::click(),
+                )
+                .on_hover_text("Right-click to disconnect");
+            if response.secondary_clicked() {
+                disconnect = Some((i, slot));
+            }
+        }
+
+        if node.kind.has_output() {
+            let port = output_port(rect);
+            painter.circle_filled(port, PORT_RADIUS, visuals.widgets.active.fg_stroke.color);
+            let response = ui.interact(
+                egui::Rect::from_center_size(port, egui::Vec2::splat(PORT_RADIUS * 3.0)),
+                id.with(("output", i)),
+                egui::Sense::drag(),
+            );
+            let pointer = ui.ctx().pointer_latest_pos();
+            if response.dragged()
+                && let Some(pointer) = pointer
+            {
+                draw_wire(&painter, port, pointer, wire);
+            }
+            if response.drag_stopped()
+                && let Some(pointer) = pointer
+            {
+                connection = Some((i, pointer));
+            }
+        }
+
+        if let Some(label) = node.kind.value_label() {
+            let row = egui::Rect::from_min_max(
+                egui::pos2(
+                    rect.left() + 8.0,
+                    rect.top() + HEADER_HEIGHT + arity as f32 * ROW_HEIGHT + 1.0,
+                ),
+                egui::pos2(
+                    rect.right() - 8.0,
+                    rect.top() + HEADER_HEIGHT + (arity + 1) as f32 * ROW_HEIGHT - 1.0,
+                ),
+            );
+            if node.kind == NodeKind::Constant {
+                let width = row.width() / 3.0;
+                for (axis, value) in node.value.iter_mut().enumerate() {
+                    let cell = egui::Rect::from_min_size(
+                        row.min + egui::vec2(axis as f32 * width, 0.0),
+                        egui::vec2(width - 2.0, row.height()),
+                    );
+                    changed |= ui
+                        .put(cell, egui::DragValue::new(value).speed(0.05))
+                        .changed();
+                }
+            } else {
+                painter.text(
+                    row.left_center(),
+                    egui::Align2::LEFT_CENTER,
+                    label,
+                    egui::FontId::proportional(12.0),
+                    visuals.text_color(),
+                );
+                let cell = egui::Rect::from_min_max(row.center_top(), row.max);
+                changed |= ui
+                    .put(cell, egui::DragValue::new(&mut node.value[0]).speed(0.01))
+                    .changed();
+            }
+        }
+    }
+
+    if let Some((from, pointer)) = connection {
+        let target = rects.iter().enumerate().find_map(|(to, &rect)| {
+            (0..graph.nodes[to].kind.arity())
+                .find(|&slot| input_port(rect, slot).distance(pointer) <= SNAP_DISTANCE)
+                .map(|slot| (to, slot))
+        });
+        if let Some((to, slot)) = target {
+            changed |= graph.connect(from, to, slot);
+        }
+    }
+    if let Some((node, slot)) = disconnect {
+        changed |= graph.nodes[node].inputs[slot].take().is_some();
+    }
+
+    changed
+}
@@ -0,0 +1,96 @@
+use egui_tiles::{Behavior, SimplificationOptions, TileId, Tree, UiResponse};
+use serde::{Deserialize, Serialize};
+
+/// Key in eframe's storage
+pub const STORAGE_KEY: &str = "panel_layout";
+
+/// One dockable group of controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Panel {
+    Statistics,
+    Physics,
+    Generation,
+    Display,
+    Camera,
+    Layers,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 6] = [
+        Panel::Statistics,
+        Panel::Physics,
+        Panel::Generation,
+        Panel::Display,
+        Panel::Camera,
+        Panel::Layers,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Statistics => "Statistics",
+            Panel::Physics => "Physics",
+            Panel::Generation => "Generation",
+            Panel::Display => "Display",
+            Panel::Camera => "Camera",
+            Panel::Layers => "Layers",
+        }
+    }
+}
+
+/// Statistics on top of the rest as tabs
+pub fn default_layout() -> Tree<Panel> {
+    let mut tiles = egui_tiles::Tiles::default();
+    let statistics = tiles.insert_pane(Panel::Statistics);
+    let tabs = Panel::ALL[1..]
+        .iter()
+        .map(|&panel| tiles.insert_pane(panel))
+        .collect();
+    let tabs = tiles.insert_tab_tile(tabs);
+    let root = tiles.insert_vertical_tile(vec![statistics, tabs]);
+    if let Some(egui_tiles::Tile::Container(egui_tiles::Container::Linear(linear))) =
+        tiles.get_mut(root)
+    {
+        linear.shares.set_share(statistics, 1.0);
+        linear.shares.set_share(tabs, 3.0);
+    }
+    Tree::new("panels", root, tiles)
+}
+
+/// The saved layout, unless it lost a panel (e.g. one added since)
+pub fn load_layout(storage: Option<&dyn eframe::Storage>) -> Tree<Panel> {
+    storage
+        .and_then(|storage| eframe::get_value::<Tree<Panel>>(storage, STORAGE_KEY))
+        .filter(|tree| {
+            Panel::ALL
+                .iter()
+                .all(|panel| tree.tiles.find_pane(panel).is_some())
+        })
+        .unwrap_or_else(default_layout)
+}
+
+/// Draws each panel with `show`, scrolling when it doesn't fit
+pub struct PanelBehavior<F: FnMut(&mut egui::Ui, Panel)> {
+    pub show: F,
+}
+
+impl<F: FnMut(&mut egui::Ui, Panel)> Behavior<Panel> for PanelBehavior<F> {
+    fn pane_ui(&mut self, ui: &mut egui::Ui, _tile_id: TileId, panel: &mut Panel) -> UiResponse {
+        egui::ScrollArea::vertical()
+            .id_salt(*panel)
+            .auto_shrink(false)
+            .show(ui, |ui| (self.show)(ui, *panel));
+        UiResponse::None
+    }
+
+    fn tab_title_for_pane(&mut self, panel: &Panel) -> egui::WidgetText {
+        panel.name().into()
+    }
+
+    fn simplification_options(&self) -> SimplificationOptions {
+        // Lone panels keep a tab to drag them by
+        SimplificationOptions {
+            all_panes_must_have_tabs: true,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::camera::CameraView;
+use crate::capture;
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use std::f32::consts::FRAC_PI_2;
+use std::path::PathBuf;
+
+/// Widths of the images offered
+pub const WIDTHS: [u32; 3] = [2048, 4096, 8192];
+
+/// How the sphere of directions around the camera is laid out in the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// Longitude across, latitude down, for 360° viewers
+    Equirectangular,
+    /// Upper hemisphere as a domemaster for planetarium domes: zenith in the
+    /// middle, horizon on the rim, the camera's heading at the bottom
+    Fisheye,
+}
+
+impl Projection {
+    pub const ALL: [Projection; 2] = [Projection::Equirectangular, Projection::Fisheye];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Projection::Equirectangular => "Equirectangular",
+            Projection::Fisheye => "Dome (Fisheye)",
+        }
+    }
+
+    /// Height of an image `width` wide
+    pub fn height(self, width: u32) -> u32 {
+        match self {
+            Projection::Equirectangular => width / 2,
+            Projection::Fisheye => width,
+        }
+    }
+
+    /// Cubemap face size matching the image's pixels per radian at its
+    /// center
+    fn face_size(self, width: u32) -> u32 {
+        match self {
+            Projection::Equirectangular => width / 4,
+            Projection::Fisheye => width / 2,
+        }
+    }
+
+    fn entry_point(self) -> &'static str {
+        match self {
+            Projection::Equirectangular => "equirect",
+            Projection::Fisheye => "fisheye",
+        }
+    }
+
+    fn file_prefix(self) -> &'static str {
+        match self {
+            Projection::Equirectangular => "panorama",
+            Projection::Fisheye => "dome",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PanoramaUniform {
+    forward: [f32; 4],
+    right: [f32; 4],
+}
+
+/// Direction and up of every cubemap face, in wgpu's layer order
+const FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::Y),
+    (Vec3::NEG_X, Vec3::Y),
+    (Vec3::Y, Vec3::NEG_Z),
+    (Vec3::NEG_Y, Vec3::Z),
+    (Vec3::Z, Vec3::Y),
+    (Vec3::NEG_Z, Vec3::Y),
+];
+
+/// The view from `position` onto cubemap face `face`. Cubemap faces are
+/// sampled as seen from inside, so the image is mirrored horizontally.
+pub fn face_view(position: Vec3, face: usize) -> CameraView {
+    let (direction, up) = FACES[face];
+    let view = Mat4::look_at_rh(position, position + direction, up);
+    let projection = Mat4::perspective_rh(FRAC_PI_2, 1.0, 0.1, 1000.0);
+    CameraView {
+        position,
+        view_proj: Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)) * projection * view,
+    }
+}
+
+/// Cubemap the scene is rendered into one face at a time, and the compute
+/// pass resolving it into the projected image
+pub struct PanoramaCapture {
+    width: u32,
+    projection: Projection,
+    face_views: Vec<wgpu::TextureView>,
+    output: wgpu::Texture,
+    uniform: wgpu::Buffer,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PanoramaCapture {
+    /// Resources for `projection` images `width` wide of scenes drawn in
+    /// `format`
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        projection: Projection,
+    ) -> Self {
+        let face_size = projection.face_size(width);
+        // Stored without sRGB encoding on sampling, so the resolve copies the
+        // bytes the faces were drawn with
+        let storage_format = format.remove_srgb_suffix();
+        let faces = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Panorama Faces"),
+            size: wgpu::Extent3d {
+                width: face_size,
+                height: face_size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: storage_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[format],
+        });
+        let face_views = (0..6)
+            .map(|layer| {
+                faces.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Panorama Face"),
+                    format: Some(format),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let cube_view = faces.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Panorama Cube"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let output = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Panorama Output"),
+            size: wgpu::Extent3d {
+                width,
+                height: projection.height(width),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Panorama Uniform"),
+            size: std::mem::size_of::<PanoramaUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Panorama Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Panorama Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Panorama Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cube_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/panorama.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Panorama Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Panorama Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some(projection.entry_point()),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            width,
+            projection,
+            face_views,
+            output,
+            uniform,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Draws the six faces around `position` with `render`, then resolves
+    /// them into an image heading along `forward` and returns its RGBA pixels
+    pub fn capture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        position: Vec3,
+        forward: Vec3,
+        render: impl Fn(&wgpu::TextureView, &CameraView),
+    ) -> Result<Vec<u8>, String> {
+        for (face, target) in self.face_views.iter().enumerate() {
+            render(target, &face_view(position, face));
+        }
+
+        let forward = Vec3::new(forward.x, 0.0, forward.z)
+            .try_normalize()
+            .unwrap_or(Vec3::NEG_Z);
+        let right = forward.cross(Vec3::Y);
+        let uniform = PanoramaUniform {
+            forward: forward.extend(0.0).into(),
+            right: right.extend(0.0).into(),
+        };
+        queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Panorama Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Panorama Resolve Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            let height = self.projection.height(self.width);
+            pass.dispatch_workgroups(self.width.div_ceil(8), height.div_ceil(8), 1);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        capture::read_texture(device, queue, &self.output)
+    }
+}
+
+/// 360° and dome stills and frame sequences of the scene, saved as PNGs in
+/// the working directory
+pub struct Panorama {
+    pub width: u32,
+    pub projection: Projection,
+    capture: Option<PanoramaCapture>,
+    still_requested: bool,
+    /// Folder and number of the next frame while recording
+    recording: Option<(PathBuf, u32)>,
+}
+
+impl Default for Panorama {
+    fn default() -> Self {
+        Self {
+            width: WIDTHS[1],
+            projection: Projection::Equirectangular,
+            capture: None,
+            still_requested: false,
+            recording: None,
+        }
+    }
+}
+
+impl Panorama {
+    pub fn request_still(&mut self) {
+        self.still_requested = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts saving a panorama every frame into a new folder, or stops
+    pub fn toggle_recording(&mut self) -> Result<(), String> {
+        if self.recording.take().is_some() {
+            return Ok(());
+        }
+        let folder = capture::timestamped_path(self.projection.file_prefix(), "frames");
+        std::fs::create_dir_all(&folder).map_err(|error| error.to_string())?;
+        self.recording = Some((folder, 0));
+        Ok(())
+    }
+
+    /// Captures from `position` looking along `forward` if a still was asked
+    /// for or a recording is running, returning what to tell the user.
+    /// `render` draws the scene into a texture in `format`.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        position: Vec3,
+        forward: Vec3,
+        render: impl Fn(&wgpu::TextureView, &CameraView),
+    ) -> Option<String> {
+        if !self.still_requested && self.recording.is_none() {
+            return None;
+        }
+        let (width, projection) = (self.width, self.projection);
+        let capture = match self.capture.take() {
+            Some(capture) if capture.width() == width && capture.projection() == projection => {
+                capture
+            }
+            _ => PanoramaCapture::new(device, format, width, projection),
+        };
+        let result = capture.capture(device, queue, position, forward, render);
+        self.capture = Some(capture);
+
+        let still = std::mem::take(&mut self.still_requested);
+        let path = match &mut self.recording {
+            Some((folder, frame)) if !still => {
+                *frame += 1;
+                folder.join(format!("frame-{:05}.png", *frame - 1))
+            }
+            _ => capture::timestamped_path(projection.file_prefix(), "png"),
+        };
+        let height = projection.height(width);
+        match result.and_then(|pixels| capture::save_png(&path, width, height, &pixels)) {
+            Ok(()) if still => Some(format!("Saved {}", path.display())),
+            Ok(()) => None,
+            Err(error) => {
+                self.recording = None;
+                Some(format!("Couldn't save {}: {error}", path.display()))
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::panels::Panel;
+use crate::simulation::Capability;
+use std::ops::{Range, RangeInclusive};
+
+/// How a [`Parameter`] is edited, with accessors into the owning `A`
+pub enum Widget<A> {
+    Slider {
+        value: fn(&mut A) -> &mut f32,
+        range: RangeInclusive<f32>,
+        logarithmic: bool,
+        suffix: &'static str,
+    },
+    Toggle {
+        value: fn(&mut A) -> &mut bool,
+    },
+}
+
+/// One setting in the central registry. The panels and the search draw their
+/// widgets from it, URL parameters and scripts find it by its key, and its
+/// default is the one in [`Settings::default`](crate::settings::Settings).
+pub struct Parameter<A> {
+    /// Field in [`Settings`](crate::settings::Settings) and in `SetParams`
+    /// commands, dotted for the few that aren't saved with the settings
+    pub key: &'static str,
+    /// The label the panels show it with
+    pub name: &'static str,
+    /// Heading it's under, so "Radius" of the mouse and of collisions differ
+    pub section: &'static str,
+    pub panel: Panel,
+    /// Greyed out when the active backend lacks it
+    pub capability: Option<Capability>,
+    pub tooltip: Option<&'static str>,
+    pub widget: Widget<A>,
+}
+
+impl<A> Parameter<A> {
+    pub const fn slider(
+        key: &'static str,
+        name: &'static str,
+        section: &'static str,
+        panel: Panel,
+        value: fn(&mut A) -> &mut f32,
+        range: RangeInclusive<f32>,
+    ) -> Self {
+        Self {
+            key,
+            name,
+            section,
+            panel,
+            capability: None,
+            tooltip: None,
+            widget: Widget::Slider {
+                value,
+                range,
+                logarithmic: false,
+                suffix: "",
+            },
+        }
+    }
+
+    pub const fn toggle(
+        key: &'static str,
+        name: &'static str,
+        section: &'static str,
+        panel: Panel,
+        value: fn(&mut A) -> &mut bool,
+    ) -> Self {
+        Self {
+            key,
+            name,
+            section,
+            panel,
+            capability: None,
+            tooltip: None,
+            widget: Widget::Toggle { value },
+        }
+    }
+
+    pub const fn logarithmic(mut self) -> Self {
+        if let Widget::Slider { logarithmic, .. } = &mut self.widget {
+            *logarithmic = true;
+        }
+        self
+    }
+
+    pub const fn suffix(mut self, text: &'static str) -> Self {
+        if let Widget::Slider { suffix, .. } = &mut self.widget {
+            *suffix = text;
+        }
+        self
+    }
+
+    pub const fn requires(mut self, capability: Capability) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+
+    pub const fn tooltip(mut self, text: &'static str) -> Self {
+        self.tooltip = Some(text);
+        self
+    }
+
+    /// `text` as a value for this parameter, e.g. from a URL, `None` if it
+    /// doesn't fit. Slider values are clamped to the range.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn parse(&self, text: &str) -> Option<serde_json::Value> {
+        match &self.widget {
+            Widget::Slider { range, .. } => {
+                let value: f32 = text.trim().parse().ok()?;
+                let value = value.clamp(*range.start(), *range.end());
+                Some(value.into())
+            }
+            Widget::Toggle { .. } => match text.trim() {
+                "1" | "true" | "on" => Some(true.into()),
+                "0" | "false" | "off" => Some(false.into()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Puts `value`, as found in the settings JSON, into the target
+    pub fn set(&self, target: &mut A, value: &serde_json::Value) {
+        match &self.widget {
+            Widget::Slider { value: field, .. } => {
+                if let Some(value) = value.as_f64() {
+                    *field(target) = value as f32;
+                }
+            }
+            Widget::Toggle { value: field } => {
+                if let Some(value) = value.as_bool() {
+                    *field(target) = value;
+                }
+            }
+        }
+    }
+
+    /// Bounds of a slider, `None` for toggles
+    pub fn range(&self) -> Option<RangeInclusive<f32>> {
+        match &self.widget {
+            Widget::Slider { range, .. } => Some(range.clone()),
+            Widget::Toggle { .. } => None,
+        }
+    }
+
+    /// The current value as a number, toggles being 0 or 1
+    pub fn value(&self, target: &mut A) -> f32 {
+        match &self.widget {
+            Widget::Slider { value, .. } => *value(target),
+            Widget::Toggle { value } => *value(target) as u32 as f32,
+        }
+    }
+
+    /// Sets the value from a number, clamped to the slider's range or on for
+    /// anything above a half
+    pub fn set_value(&self, target: &mut A, number: f32) {
+        match &self.widget {
+            Widget::Slider { value, range, .. } => {
+                *value(target) = number.clamp(*range.start(), *range.end());
+            }
+            Widget::Toggle { value } => *value(target) = number > 0.5,
+        }
+    }
+
+    /// Where `query` (lowercase) occurs in the name, or an empty match at the
+    /// start if only the section or panel has it
+    pub fn find(&self, query: &str) -> Option<Range<usize>> {
+        if let Some(start) = self.name.to_lowercase().find(query) {
+            return Some(start..start + query.len());
+        }
+        let context = format!("{} {}", self.section, self.panel.name()).to_lowercase();
+        context.contains(query).then_some(0..0)
+    }
+
+    /// Draws the widget labelled with its name, `highlight` marked up
+    pub fn show(
+        &self,
+        ui: &mut egui::Ui,
+        target: &mut A,
+        highlight: Range<usize>,
+    ) -> egui::Response {
+        let label = highlighted(ui, self.name, highlight);
+        let response = match &self.widget {
+            Widget::Slider {
+                value,
+                range,
+                logarithmic,
+                suffix,
+            } => ui.add(
+                egui::Slider::new(value(target), range.clone())
+                    .logarithmic(*logarithmic)
+                    .suffix(*suffix)
+                    .text(label),
+            ),
+            Widget::Toggle { value } => ui.checkbox(value(target), label),
+        };
+        match self.tooltip {
+            Some(tooltip) => response.on_hover_text(tooltip),
+            None => response,
+        }
+    }
+}
+
+/// `text` with the bytes in `range` on the selection color, the whole text
+/// plain if the range is empty or off a char boundary
+fn highlighted(ui: &egui::Ui, text: &str, range: Range<usize>) -> egui::text::LayoutJob {
+    let plain = egui::TextFormat {
+        font_id: egui::TextStyle::Body.resolve(ui.style()),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let marked = egui::TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        color: ui.visuals().strong_text_color(),
+        ..plain.clone()
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    match (
+        text.get(..range.start),
+        text.get(range.clone()),
+        text.get(range.end..),
+    ) {
+        (Some(before), Some(matched), Some(after)) if !range.is_empty() => {
+            job.append(before, 0.0, plain.clone());
+            job.append(matched, 0.0, marked);
+            job.append(after, 0.0, plain);
+        }
+        _ => job.append(text, 0.0, plain),
+    }
+    job
+}
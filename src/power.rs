@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+/// Frame interval while saving power, 30 FPS
+pub const THROTTLED_FRAME_TIME: Duration = Duration::from_millis(33);
+const BATTERY_POLL_INTERVAL: f32 = 10.0;
+/// Ignore the first seconds, shader compilation and warm-up are slow
+const WARM_UP: f32 = 5.0;
+/// Frame rate below this fraction of the best one seen counts as degraded
+const SLOWDOWN_RATIO: f32 = 0.6;
+/// How long the frame rate has to stay degraded to count as throttling
+const SLOWDOWN_DURATION: f32 = 15.0;
+
+/// Caps the frame rate when running on battery, or when the frame rate sinks
+/// and stays down without the workload changing, which on laptops and phones
+/// usually means the hardware is throttling because of heat
+pub struct PowerSaver {
+    pub enabled: bool,
+    on_battery: Option<bool>,
+    battery_poll_timer: f32,
+    /// Particle count the baseline was measured at
+    workload: u32,
+    running_time: f32,
+    baseline_fps: f32,
+    slow_time: f32,
+    overheating: bool,
+}
+
+impl PowerSaver {
+    pub fn new() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        crate::web::watch_battery();
+
+        Self {
+            enabled: true,
+            on_battery: None,
+            battery_poll_timer: BATTERY_POLL_INTERVAL,
+            workload: 0,
+            running_time: 0.0,
+            baseline_fps: 0.0,
+            slow_time: 0.0,
+            overheating: false,
+        }
+    }
+
+    /// Advances by one frame of `delta_time` running `particle_count`
+    /// particles at `fps`, paused frames tell nothing about throttling
+    pub fn update(&mut self, fps: f32, particle_count: u32, paused: bool, delta_time: f32) {
+        self.battery_poll_timer += delta_time;
+        if self.battery_poll_timer >= BATTERY_POLL_INTERVAL {
+            self.battery_poll_timer = 0.0;
+            self.on_battery = on_battery();
+        }
+
+        if particle_count != self.workload {
+            // A different workload has a different frame rate, start over
+            self.workload = particle_count;
+            self.running_time = 0.0;
+            self.baseline_fps = 0.0;
+            self.slow_time = 0.0;
+            self.overheating = false;
+        }
+        // Capped frames can't be compared with the baseline
+        if paused || self.throttled() {
+            return;
+        }
+
+        self.running_time += delta_time;
+        if self.running_time < WARM_UP {
+            return;
+        }
+        self.baseline_fps = self.baseline_fps.max(fps);
+        if fps < self.baseline_fps * SLOWDOWN_RATIO {
+            self.slow_time += delta_time;
+            self.overheating = self.slow_time >= SLOWDOWN_DURATION;
+        } else {
+            self.slow_time = 0.0;
+        }
+    }
+
+    pub fn throttled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    pub fn reason(&self) -> Option<&'static str> {
+        if !self.enabled {
+            None
+        } else if self.on_battery == Some(true) {
+            Some("on battery")
+        } else if self.overheating {
+            Some("sustained slowdown, likely thermal throttling")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn on_battery() -> Option<bool> {
+    crate::web::on_battery()
+}
+
+/// Discharging when there is a system battery and no external supply is online
+#[cfg(target_os = "linux")]
+fn on_battery() -> Option<bool> {
+    let mut has_battery = false;
+    for entry in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = entry.path();
+        let read = |name: &str| std::fs::read_to_string(path.join(name)).unwrap_or_default();
+        match read("type").trim() {
+            "Mains" | "USB" if read("online").trim() == "1" => return Some(false),
+            // Mice and headsets report their batteries with a "Device" scope
+            "Battery" if read("scope").trim() != "Device" => has_battery = true,
+            _ => {}
+        }
+    }
+    has_battery.then_some(true)
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "linux")))]
+fn on_battery() -> Option<bool> {
+    None
+}
@@ -0,0 +1,144 @@
+use crate::settings::Settings;
+use crate::simulation::chemistry::ReactionRule;
+use crate::simulation::{BOUNDARY_PERIODIC, GenerationSettings, SphereGeneration};
+use glam::Vec3;
+
+/// Built-in parameter sets, selectable from the UI and the tray
+pub struct Preset {
+    pub name: &'static str,
+    pub settings: fn() -> Settings,
+}
+
+pub const PRESETS: [Preset; 7] = [
+    Preset {
+        name: "Default",
+        settings: Settings::default,
+    },
+    Preset {
+        name: "Orbiting Ring",
+        settings: orbiting_ring,
+    },
+    Preset {
+        name: "Galaxy",
+        settings: galaxy,
+    },
+    Preset {
+        name: "Fountain",
+        settings: fountain,
+    },
+    Preset {
+        name: "Plasma",
+        settings: plasma,
+    },
+    Preset {
+        name: "Reaction Soup",
+        settings: reaction_soup,
+    },
+    Preset {
+        name: "Lennard-Jones Fluid",
+        settings: lennard_jones_fluid,
+    },
+];
+
+fn orbiting_ring() -> Settings {
+    Settings {
+        particle_count: 20_000,
+        generation: GenerationSettings {
+            mode: SphereGeneration::OrbitRing,
+            orbit_radius: 30.0,
+            orbit_speed: 3.5,
+            ..GenerationSettings::default()
+        },
+        damping: 1.0,
+        attractor_enabled: true,
+        attractor_mass: 500.0,
+        color_mode: 1,
+        ..Settings::default()
+    }
+}
+
+fn galaxy() -> Settings {
+    let radius = 40.0;
+    let mass = 2000.0;
+    Settings {
+        particle_count: 100_000,
+        generation: GenerationSettings {
+            mode: SphereGeneration::Galaxy,
+            orbit_radius: radius,
+            // Circular at the rim
+            orbit_speed: (mass / radius).sqrt(),
+            ..GenerationSettings::default()
+        },
+        damping: 1.0,
+        attractor_enabled: true,
+        attractor_mass: mass,
+        color_mode: 0,
+        ..Settings::default()
+    }
+}
+
+fn fountain() -> Settings {
+    Settings {
+        generation: GenerationSettings {
+            mode: SphereGeneration::Filled,
+            ..GenerationSettings::default()
+        },
+        respawn_enabled: true,
+        respawn_rate: 0.3,
+        gravity: 2.0,
+        color_mode: 1,
+        ..Settings::default()
+    }
+}
+
+fn plasma() -> Settings {
+    Settings {
+        particle_count: 50_000,
+        generation: GenerationSettings {
+            mode: SphereGeneration::Filled,
+            ..GenerationSettings::default()
+        },
+        damping: 1.0,
+        color_mode: 6,
+        lorentz_enabled: true,
+        electric_strength: 0.5,
+        electric_direction: Vec3::X,
+        magnetic_strength: 2.0,
+        magnetic_direction: Vec3::Y,
+        ..Settings::default()
+    }
+}
+
+fn reaction_soup() -> Settings {
+    Settings {
+        particle_count: 20_000,
+        generation: GenerationSettings {
+            mode: SphereGeneration::Filled,
+            species_count: 2,
+            ..GenerationSettings::default()
+        },
+        color_mode: 3,
+        contact_radius: 1.5,
+        reactions_enabled: true,
+        reaction_rules: vec![ReactionRule::default()],
+        ..Settings::default()
+    }
+}
+
+fn lennard_jones_fluid() -> Settings {
+    Settings {
+        particle_count: 5_000,
+        generation: GenerationSettings {
+            mode: SphereGeneration::Filled,
+            ..GenerationSettings::default()
+        },
+        damping: 1.0,
+        color_mode: 1,
+        lj_enabled: true,
+        thermostat_enabled: true,
+        boundary_mode: BOUNDARY_PERIODIC,
+        box_half_extents: Vec3::splat(20.0),
+        rdf_enabled: true,
+        ..Settings::default()
+    }
+}
@@ -0,0 +1,398 @@
+use crate::simulation::Particle;
+use crate::simulation::readback::{MapState, ReadMapping};
+use bytemuck::Zeroable;
+
+/// Rendered frames between snapshots offered for recordings
+pub const RECORD_INTERVALS: [u32; 4] = [1, 2, 5, 10];
+/// Every this many frames one is stored whole so seeking doesn't have to
+/// replay the recording from the start
+const KEYFRAME_INTERVAL: usize = 30;
+/// Recordings stop on their own once their frames take this much memory
+pub const MEMORY_BUDGET: usize = 1 << 30;
+/// Zero bytes shorter than this stay in the literal around them, a run of
+/// its own would cost more than it saves
+const MIN_ZERO_RUN: usize = 4;
+/// Start of recording files, with the format's version in the last byte
+const MAGIC: [u8; 8] = *b"PSREC\0\0\x01";
+
+const PARTICLE_SIZE: usize = std::mem::size_of::<Particle>();
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> Result<usize, String> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = *data.get(*position).ok_or("the frame is cut short")?;
+        *position += 1;
+        value |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("the frame is corrupt".to_owned())
+}
+
+/// XORs `frame` with `previous` when there is one and squeezes the runs of
+/// zero bytes that leaves wherever nothing changed, like the colors, masses
+/// and species of most particles
+fn encode(frame: &[u8], previous: Option<&[u8]>) -> Vec<u8> {
+    let delta: Vec<u8> = match previous {
+        Some(previous) => frame.iter().zip(previous).map(|(a, b)| a ^ b).collect(),
+        None => frame.to_vec(),
+    };
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < delta.len() {
+        let zeros = delta[i..].iter().take_while(|&&byte| byte == 0).count();
+        i += zeros;
+        let start = i;
+        while i < delta.len() && !delta[i..].starts_with(&[0; MIN_ZERO_RUN]) {
+            i += 1;
+        }
+        write_varint(&mut out, zeros);
+        write_varint(&mut out, i - start);
+        out.extend_from_slice(&delta[start..i]);
+    }
+    out
+}
+
+/// Undoes [`encode`], `previous` being the frame the delta was taken from
+fn decode(data: &[u8], previous: Option<&[u8]>, len: usize) -> Result<Vec<u8>, String> {
+    let mut frame = Vec::with_capacity(len);
+    let mut position = 0;
+    while position < data.len() {
+        let zeros = read_varint(data, &mut position)?;
+        let literal = read_varint(data, &mut position)?;
+        let bytes = data
+            .get(position..position + literal)
+            .ok_or("the frame is cut short")?;
+        position += literal;
+        frame.resize(frame.len() + zeros, 0);
+        frame.extend_from_slice(bytes);
+    }
+    if frame.len() != len {
+        return Err("the frame has the wrong size".to_owned());
+    }
+    if let Some(previous) = previous {
+        for (byte, previous) in frame.iter_mut().zip(previous) {
+            *byte ^= previous;
+        }
+    }
+    Ok(frame)
+}
+
+fn take<'a>(bytes: &'a [u8], position: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    let taken = bytes
+        .get(*position..*position + len)
+        .ok_or("the file is cut short")?;
+    *position += len;
+    Ok(taken)
+}
+
+fn take_u32(bytes: &[u8], position: &mut usize) -> Result<u32, String> {
+    let taken = take(bytes, position, 4)?;
+    Ok(u32::from_le_bytes([taken[0], taken[1], taken[2], taken[3]]))
+}
+
+struct Frame {
+    /// Simulated seconds since the recording started
+    time: f32,
+    data: Vec<u8>,
+}
+
+/// Compressed snapshots of every particle. Each frame is stored as its
+/// difference to the one before, apart from keyframes.
+pub struct Recording {
+    particle_count: u32,
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    fn new(particle_count: u32) -> Self {
+        Self {
+            particle_count,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn particle_count(&self) -> u32 {
+        self.particle_count
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Simulated seconds from the first frame to the last
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map_or(0.0, |frame| frame.time)
+    }
+
+    /// Memory the compressed frames take
+    pub fn size_bytes(&self) -> usize {
+        self.frames.iter().map(|frame| frame.data.len()).sum()
+    }
+
+    fn frame_len(&self) -> usize {
+        self.particle_count as usize * PARTICLE_SIZE
+    }
+
+    fn push(&mut self, time: f32, frame: &[u8], previous: Option<&[u8]>) {
+        let previous = previous.filter(|_| !self.frames.len().is_multiple_of(KEYFRAME_INTERVAL));
+        self.frames.push(Frame {
+            time,
+            data: encode(frame, previous),
+        });
+    }
+
+    /// Uncompressed bytes of frame `index`, carrying on from `known`, an
+    /// earlier frame's, when it's past the last keyframe
+    fn frame_bytes(&self, index: usize, known: Option<(usize, &[u8])>) -> Result<Vec<u8>, String> {
+        let keyframe = index - index % KEYFRAME_INTERVAL;
+        let (mut at, mut bytes) = match known {
+            Some((known, bytes)) if (keyframe..=index).contains(&known) => (known, bytes.to_vec()),
+            _ => (
+                keyframe,
+                decode(&self.frames[keyframe].data, None, self.frame_len())?,
+            ),
+        };
+        while at < index {
+            at += 1;
+            bytes = decode(&self.frames[at].data, Some(&bytes), self.frame_len())?;
+        }
+        Ok(bytes)
+    }
+
+    /// The recording as a file, see [`Recording::from_bytes`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&self.particle_count.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.time.to_le_bytes());
+            bytes.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&frame.data);
+        }
+        bytes
+    }
+
+    /// Reads a file written from [`Recording::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut position = 0;
+        if take(bytes, &mut position, MAGIC.len())? != MAGIC {
+            return Err("not a recording of this version".to_owned());
+        }
+        let particle_count = take_u32(bytes, &mut position)?;
+        let frame_count = take_u32(bytes, &mut position)?;
+        let mut recording = Self::new(particle_count);
+        for _ in 0..frame_count {
+            let time = f32::from_bits(take_u32(bytes, &mut position)?);
+            let len = take_u32(bytes, &mut position)? as usize;
+            recording.frames.push(Frame {
+                time,
+                data: take(bytes, &mut position, len)?.to_vec(),
+            });
+        }
+        Ok(recording)
+    }
+
+    /// Writes the recording to a new file in the working directory
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> Result<std::path::PathBuf, String> {
+        let path = crate::capture::timestamped_path("recording", "psrec");
+        std::fs::write(&path, self.to_bytes()).map_err(|error| error.to_string())?;
+        Ok(path)
+    }
+}
+
+/// Snapshots the particles every few rendered frames into a [`Recording`].
+/// The copies come back from the GPU without waiting, a snapshot being
+/// skipped while the one before is still on its way.
+pub struct Recorder {
+    recording: Recording,
+    /// Rendered frames between snapshots
+    interval: u32,
+    countdown: u32,
+    /// Uncompressed bytes of the last snapshot, what the next is diffed with
+    last: Option<Vec<u8>>,
+    /// Simulated seconds since recording started
+    clock: f32,
+    /// Staging buffer and simulated time of the snapshot in flight
+    in_flight: Option<(wgpu::Buffer, f32)>,
+    mapping: ReadMapping,
+}
+
+impl Recorder {
+    pub fn new(particle_count: u32, interval: u32) -> Self {
+        Self {
+            recording: Recording::new(particle_count),
+            interval: interval.max(1),
+            countdown: 0,
+            last: None,
+            clock: 0.0,
+            in_flight: None,
+            mapping: ReadMapping::new(),
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// The frames so far, a snapshot still on its way is dropped
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+
+    /// Stores a snapshot that came back and asks for the next one when it's
+    /// due, to be called on frames the simulation stepped by `elapsed`
+    /// simulated seconds. Fails once the recording can't go on.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::Buffer,
+        particle_count: u32,
+        elapsed: f32,
+    ) -> Result<(), String> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        self.clock += elapsed;
+
+        if let Some((staging, taken_at)) = &self.in_flight {
+            match self.mapping.state() {
+                MapState::Pending => {}
+                MapState::Done => {
+                    let frame = staging.slice(..).get_mapped_range().to_vec();
+                    staging.unmap();
+                    self.recording.push(*taken_at, &frame, self.last.as_deref());
+                    self.last = Some(frame);
+                    self.in_flight = None;
+                }
+                MapState::Failed => return Err("a snapshot couldn't be read back".to_owned()),
+            }
+        }
+
+        if particle_count != self.recording.particle_count {
+            return Err("the particle count changed".to_owned());
+        }
+        if self.recording.size_bytes() >= MEMORY_BUDGET {
+            return Err(format!(
+                "it reached {}",
+                crate::format::bytes(MEMORY_BUDGET as u64)
+            ));
+        }
+
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return Ok(());
+        }
+        if self.in_flight.is_some() || particle_count == 0 {
+            return Ok(());
+        }
+        self.countdown = self.interval - 1;
+
+        let size = self.recording.frame_len() as wgpu::BufferAddress;
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Recording Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Recording Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(source, 0, &staging, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        self.mapping.start(staging.slice(..));
+        self.in_flight = Some((staging, self.clock));
+        Ok(())
+    }
+}
+
+/// Scrubbing through a [`Recording`] in place of running the physics
+pub struct Playback {
+    pub recording: Recording,
+    /// Frame the clock is at
+    pub frame: usize,
+    pub playing: bool,
+    /// Simulated seconds since the first frame
+    clock: f32,
+    /// Index and bytes of the frame last handed out
+    shown: Option<(usize, Vec<u8>)>,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            frame: 0,
+            playing: true,
+            clock: 0.0,
+            shown: None,
+        }
+    }
+
+    /// Runs the clock `delta` simulated seconds on while playing, starting
+    /// over after the last frame
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing || self.recording.is_empty() {
+            return;
+        }
+        self.clock += delta;
+        if self.clock > self.recording.duration() {
+            self.clock = 0.0;
+            self.frame = 0;
+        }
+        let frames = &self.recording.frames;
+        while self.frame + 1 < frames.len() && frames[self.frame + 1].time <= self.clock {
+            self.frame += 1;
+        }
+    }
+
+    /// Jumps to `frame`, the clock following
+    pub fn seek(&mut self, frame: usize) {
+        self.frame = frame.min(self.recording.len().saturating_sub(1));
+        self.clock = self
+            .recording
+            .frames
+            .get(self.frame)
+            .map_or(0.0, |frame| frame.time);
+    }
+
+    /// The particles of the current frame, `None` if it's the one handed out
+    /// last time
+    pub fn take_frame(&mut self) -> Option<Result<Vec<Particle>, String>> {
+        if self.recording.is_empty()
+            || self
+                .shown
+                .as_ref()
+                .is_some_and(|(shown, _)| *shown == self.frame)
+        {
+            return None;
+        }
+        let known = self
+            .shown
+            .as_ref()
+            .map(|(index, bytes)| (*index, bytes.as_slice()));
+        let bytes = match self.recording.frame_bytes(self.frame, known) {
+            Ok(bytes) => bytes,
+            Err(error) => return Some(Err(error)),
+        };
+        let mut particles = vec![Particle::zeroed(); self.recording.particle_count as usize];
+        bytemuck::cast_slice_mut::<Particle, u8>(&mut particles).copy_from_slice(&bytes);
+        self.shown = Some((self.frame, bytes));
+        Some(Ok(particles))
+    }
+}
@@ -1,7 +1,228 @@
-use crate::{camera::Camera, simulation::Particle};
+use crate::camera::{Camera, CameraUniform, CameraView};
+use crate::custom_renderer::{LineCallback, ParticlePipeline};
+use crate::simulation::Particle;
+use crate::simulation::gpu_sort::{ParticleSorter, SortKey, SortParams};
+use crate::wgsl;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use std::cell::Cell;
+use std::sync::Arc;
+
+/// The particles themselves plus their 26 periodic images
+pub const GHOST_COPIES: u32 = 27;
+
+/// One entry per draw of the particle buffer, selected with a dynamic offset
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GhostUniform {
+    /// xyz = image shift, w = 1 for periodic copies
+    offset: [f32; 4],
+    /// xyz = box half extents, w = how far outside the box copies are drawn
+    box_half_extents: [f32; 4],
+}
+
+/// How far the drawn positions are from the previous step to the current one
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct InterpolationUniform {
+    alpha: f32,
+    _padding: [f32; 3],
+}
+
+/// Size and color of the container box wireframe
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct ContainerUniform {
+    /// xyz = box half extents
+    half_extents: [f32; 4],
+    color: [f32; 4],
+}
+
+/// Lines of the ground grid along each axis
+const GROUND_LINES: u32 = 41;
+const GROUND_SPACING: f32 = 5.0;
+
+/// Height and look of the ground grid
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GroundUniform {
+    height: f32,
+    /// Distance between grid lines
+    spacing: f32,
+    _padding: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Order the particles are drawn in, later ones covering earlier ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum DrawOrder {
+    /// As they are in the buffer
+    #[default]
+    Unsorted,
+    /// Farthest first, so blended particles composite correctly
+    BackToFront,
+    /// Fastest first, so the slow ones stay on top
+    SlowestLast,
+    /// Grouped by species, lowest first
+    BySpecies,
+}
+
+impl DrawOrder {
+    pub const ALL: [DrawOrder; 4] = [
+        DrawOrder::Unsorted,
+        DrawOrder::BackToFront,
+        DrawOrder::SlowestLast,
+        DrawOrder::BySpecies,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DrawOrder::Unsorted => "Unsorted",
+            DrawOrder::BackToFront => "Back to Front",
+            DrawOrder::SlowestLast => "Slowest Last",
+            DrawOrder::BySpecies => "By Species",
+        }
+    }
+
+    /// What to sort by and whether largest first, `None` to draw unsorted
+    fn sort_key(self) -> Option<(SortKey, bool)> {
+        match self {
+            DrawOrder::Unsorted => None,
+            DrawOrder::BackToFront => Some((SortKey::Depth, true)),
+            DrawOrder::SlowestLast => Some((SortKey::Speed, true)),
+            DrawOrder::BySpecies => Some((SortKey::Species, false)),
+        }
+    }
+}
 
 pub struct ParticleRenderer {
-    pub render_pipeline: wgpu::RenderPipeline,
+    /// Also handed to egui's callback resources for the window's draws
+    pub pipeline: Arc<ParticlePipeline>,
+    ghost_buffer: wgpu::Buffer,
+    /// Box and margin the ghost offsets were last written for
+    ghosts_written: Cell<Option<(Vec3, f32)>>,
+    interpolation_buffer: wgpu::Buffer,
+    /// The particles as of the step before the last, blended from when drawn
+    previous_particles: Option<wgpu::Buffer>,
+    sorter: ParticleSorter,
+    /// Set while the active particles are drawn through the sorter's indices
+    sorted: bool,
+    /// Wireframe of the container box, 12 line segments
+    pub container: LineOverlay,
+    /// Grid on the ground plane
+    pub ground: LineOverlay,
+    /// Camera of offscreen renders, kept apart from the window's
+    offscreen_camera: wgpu::Buffer,
+    pub offscreen_camera_bind_group: wgpu::BindGroup,
+}
+
+/// Lines generated in the vertex shader from a single uniform, drawn on top
+/// of the scene to show where boundaries are
+pub struct LineOverlay {
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group: wgpu::BindGroup,
+    pub vertex_count: u32,
+    buffer: wgpu::Buffer,
+}
+
+impl LineOverlay {
+    /// `shader` reads the camera at group 0 and a uniform of `uniform_size`
+    /// bytes at group 1
+    fn new(
+        device: &wgpu::Device,
+        camera: &Camera,
+        surface_format: &wgpu::TextureFormat,
+        shader: wgpu::ShaderModuleDescriptor,
+        uniform_size: usize,
+        vertex_count: u32,
+    ) -> Self {
+        let label = shader.label;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: uniform_size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let shader = device.create_shader_module(shader);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label,
+            bind_group_layouts: &[&camera.bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: *surface_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            vertex_count,
+            buffer,
+        }
+    }
+
+    pub fn callback(&self, camera_bind_group: &wgpu::BindGroup) -> LineCallback {
+        LineCallback {
+            pipeline: self.pipeline.clone(),
+            camera_bind_group: camera_bind_group.clone(),
+            bind_group: self.bind_group.clone(),
+            vertex_count: self.vertex_count,
+        }
+    }
+
+    fn write(&self, queue: &wgpu::Queue, uniform: &impl Pod) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(uniform));
+    }
 }
 
 impl ParticleRenderer {
@@ -11,11 +232,75 @@ impl ParticleRenderer {
         surface_format: &wgpu::TextureFormat,
         shader: &wgpu::ShaderModule,
     ) -> Self {
+        let ghost_stride = (std::mem::size_of::<GhostUniform>() as u32)
+            .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment);
+        let ghost_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ghost Buffer"),
+            size: (ghost_stride * GHOST_COPIES) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let ghost_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Ghost Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<GhostUniform>() as u64,
+                            ),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let interpolation_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Interpolation Buffer"),
+            size: std::mem::size_of::<InterpolationUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let ghost_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ghost Bind Group"),
+            layout: &ghost_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &ghost_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<GhostUniform>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: interpolation_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
         // Create render pipeline layout
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Particle Render Pipeline Layout"),
-                bind_group_layouts: &[&camera.bind_group_layout],
+                bind_group_layouts: &[&camera.bind_group_layout, &ghost_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
@@ -31,7 +316,7 @@ impl ParticleRenderer {
                     // Particle buffer
                     wgpu::VertexBufferLayout {
                         array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Instance,
+                        step_mode: wgpu::VertexStepMode::Vertex,
                         attributes: &[
                             // position
                             wgpu::VertexAttribute {
@@ -39,11 +324,11 @@ impl ParticleRenderer {
                                 shader_location: 0,
                                 format: wgpu::VertexFormat::Float32x3,
                             },
-                            // padding1
+                            // species
                             wgpu::VertexAttribute {
                                 offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                                 shader_location: 1,
-                                format: wgpu::VertexFormat::Float32,
+                                format: wgpu::VertexFormat::Uint32,
                             },
                             // velocity
                             wgpu::VertexAttribute {
@@ -51,7 +336,7 @@ impl ParticleRenderer {
                                 shader_location: 2,
                                 format: wgpu::VertexFormat::Float32x3,
                             },
-                            // padding2
+                            // temperature
                             wgpu::VertexAttribute {
                                 offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
                                 shader_location: 3,
@@ -63,8 +348,24 @@ impl ParticleRenderer {
                                 shader_location: 4,
                                 format: wgpu::VertexFormat::Float32x4,
                             },
+                            // age and lifetime
+                            wgpu::VertexAttribute {
+                                offset: std::mem::size_of::<[f32; 21]>() as wgpu::BufferAddress,
+                                shader_location: 6,
+                                format: wgpu::VertexFormat::Float32x2,
+                            },
                         ],
                     },
+                    // The same particles a step earlier, only their position
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Particle>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[wgpu::VertexAttribute {
+                            offset: 0,
+                            shader_location: 5,
+                            format: wgpu::VertexFormat::Float32x3,
+                        }],
+                    },
                 ],
                 compilation_options: Default::default(),
             },
@@ -97,6 +398,216 @@ impl ParticleRenderer {
             cache: None,
         });
 
-        Self { render_pipeline }
+        let container = LineOverlay::new(
+            device,
+            camera,
+            surface_format,
+            wgpu::ShaderModuleDescriptor {
+                label: Some("Container Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    wgsl::compose(&[wgsl::CAMERA, include_str!("shaders/container.wgsl")]).into(),
+                ),
+            },
+            std::mem::size_of::<ContainerUniform>(),
+            24,
+        );
+        let ground = LineOverlay::new(
+            device,
+            camera,
+            surface_format,
+            wgpu::ShaderModuleDescriptor {
+                label: Some("Ground Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    wgsl::compose(&[wgsl::CAMERA, include_str!("shaders/ground.wgsl")]).into(),
+                ),
+            },
+            std::mem::size_of::<GroundUniform>(),
+            GROUND_LINES * 4,
+        );
+
+        let offscreen_camera = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Camera Buffer"),
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let offscreen_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Offscreen Camera Bind Group"),
+            layout: &camera.bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: offscreen_camera.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            pipeline: Arc::new(ParticlePipeline {
+                render_pipeline,
+                ghost_bind_group,
+                ghost_stride,
+            }),
+            ghost_buffer,
+            ghosts_written: Cell::new(None),
+            interpolation_buffer,
+            previous_particles: None,
+            sorter: ParticleSorter::new(device),
+            sorted: false,
+            container,
+            ground,
+            offscreen_camera,
+            offscreen_camera_bind_group,
+        }
+    }
+
+    /// Writes the camera [`Self::offscreen_camera_bind_group`] renders from
+    pub fn update_offscreen_camera(&self, queue: &wgpu::Queue, view: &CameraView) {
+        queue.write_buffer(
+            &self.offscreen_camera,
+            0,
+            bytemuck::bytes_of(&view.uniform()),
+        );
+    }
+
+    /// Copies `particles` aside before a step, so the next frames can draw
+    /// them blended from where they were
+    pub fn remember_particles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &wgpu::Buffer,
+    ) {
+        let previous = match self.previous_particles.take() {
+            Some(buffer) if buffer.size() == particles.size() => buffer,
+            _ => device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Previous Particle Buffer"),
+                size: particles.size(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        };
+        // Submitted on its own, ahead of the step's buffer writes
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Previous Particles Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(particles, 0, &previous, 0, particles.size());
+        queue.submit(Some(encoder.finish()));
+        self.previous_particles = Some(previous);
+    }
+
+    /// What to blend `particles` from, themselves if nothing fitting was
+    /// remembered (e.g. for parked layers or right after a resize)
+    fn previous_particles(&self, particles: &wgpu::Buffer) -> wgpu::Buffer {
+        match &self.previous_particles {
+            Some(previous) if previous.size() == particles.size() => previous.clone(),
+            _ => particles.clone(),
+        }
+    }
+
+    /// Sorts the first `particle_count` of `particles` into `order` as seen
+    /// from `camera`, for [`Self::draw_buffers`]
+    pub fn sort_particles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &wgpu::Buffer,
+        particle_count: u32,
+        order: DrawOrder,
+        camera: &Camera,
+    ) {
+        let Some((key, descending)) = order.sort_key() else {
+            self.sorted = false;
+            return;
+        };
+        let params = SortParams::new(
+            particle_count,
+            key,
+            descending,
+            camera.position,
+            camera.get_forward(),
+        );
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Particle Sort Encoder"),
+        });
+        self.sorter
+            .sort(device, queue, &mut encoder, particles, params);
+        queue.submit(Some(encoder.finish()));
+        self.sorted = true;
+    }
+
+    /// The buffers to draw the active `particles` from and blend them from,
+    /// and the indices to draw them in when they're sorted
+    pub fn draw_buffers(
+        &self,
+        particles: &wgpu::Buffer,
+    ) -> (wgpu::Buffer, wgpu::Buffer, Option<wgpu::Buffer>) {
+        let indices = self.sorted.then(|| self.sorter.sorted_indices().clone());
+        (
+            particles.clone(),
+            self.previous_particles(particles),
+            indices,
+        )
+    }
+
+    /// Writes how far from the previous step to the current one the particles
+    /// are drawn, 1 for exactly where they are
+    pub fn update_interpolation(&self, queue: &wgpu::Queue, alpha: f32) {
+        let interpolation = InterpolationUniform {
+            alpha,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(
+            &self.interpolation_buffer,
+            0,
+            bytemuck::bytes_of(&interpolation),
+        );
+    }
+
+    /// Writes the size of the container box wireframe
+    pub fn update_container(&self, queue: &wgpu::Queue, half_extents: Vec3) {
+        let container = ContainerUniform {
+            half_extents: [half_extents.x, half_extents.y, half_extents.z, 0.0],
+            color: [0.6, 0.6, 0.7, 0.5],
+        };
+        self.container.write(queue, &container);
+    }
+
+    /// Writes the height of the ground grid
+    pub fn update_ground(&self, queue: &wgpu::Queue, height: f32) {
+        let ground = GroundUniform {
+            height,
+            spacing: GROUND_SPACING,
+            _padding: [0.0; 2],
+            color: [0.5, 0.5, 0.55, 0.35],
+        };
+        self.ground.write(queue, &ground);
+    }
+
+    /// Writes the draw offsets for the particles and their periodic images.
+    /// The first entry is always the untouched original. Skipped while the
+    /// box and margin stay the same.
+    pub fn update_ghosts(&self, queue: &wgpu::Queue, half_extents: Vec3, margin: f32) {
+        let written = Some((half_extents, margin));
+        if self.ghosts_written.replace(written) == written {
+            return;
+        }
+        let ghost_stride = self.pipeline.ghost_stride;
+        let mut data = vec![0u8; (ghost_stride * GHOST_COPIES) as usize];
+        let images = std::iter::once((0, 0, 0)).chain(
+            (-1..=1)
+                .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| (x, y, z))))
+                .filter(|&cell| cell != (0, 0, 0)),
+        );
+        for (copy, (dx, dy, dz)) in images.enumerate() {
+            let shift = Vec3::new(dx as f32, dy as f32, dz as f32) * half_extents * 2.0;
+            let ghost = GhostUniform {
+                offset: [shift.x, shift.y, shift.z, if copy == 0 { 0.0 } else { 1.0 }],
+                box_half_extents: [half_extents.x, half_extents.y, half_extents.z, margin],
+            };
+            let start = copy * ghost_stride as usize;
+            data[start..start + std::mem::size_of::<GhostUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&ghost));
+        }
+
+        queue.write_buffer(&self.ghost_buffer, 0, &data);
     }
 }
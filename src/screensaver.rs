@@ -0,0 +1,154 @@
+use crate::presets::PRESETS;
+use crate::settings::Settings;
+use glam::Vec3;
+
+const ORBIT_RADIUS: f32 = 120.0;
+const ORBIT_HEIGHT: f32 = 35.0;
+/// Radians per second around the vertical axis
+const ORBIT_SPEED: f32 = 0.08;
+/// Input right after starting is ignored, so the click or key press that
+/// started it (or a jittery pointer) doesn't end it straight away
+const GRACE_PERIOD: f32 = 1.0;
+/// Pointer movement in points per frame that counts as input
+const POINTER_THRESHOLD: f32 = 2.0;
+
+/// What the app looked like before the screensaver took over
+pub struct Restore {
+    pub settings: Settings,
+    pub preset: Option<usize>,
+    pub camera_position: Vec3,
+    pub camera_yaw: f32,
+    pub camera_pitch: f32,
+    pub show_ui: bool,
+}
+
+pub enum ScreensaverStep {
+    Continue,
+    /// Time to switch to the preset at this index
+    Preset(usize),
+    Exit,
+}
+
+/// Attract loop: hidden UI, a slowly orbiting camera and a new preset every
+/// `preset_interval` seconds, until any input
+pub struct Screensaver {
+    pub active: bool,
+    /// Seconds without input before it starts on its own, 0 disables it
+    pub idle_timeout: f32,
+    pub preset_interval: f32,
+    /// Quit on input instead of returning to the app, when launched as a screensaver
+    pub exit_on_input: bool,
+    start_requested: bool,
+    idle_time: f32,
+    running_time: f32,
+    preset_timer: f32,
+    preset: usize,
+    orbit_angle: f32,
+    restore: Option<Restore>,
+}
+
+impl Default for Screensaver {
+    fn default() -> Self {
+        Self {
+            active: false,
+            idle_timeout: 0.0,
+            preset_interval: 30.0,
+            exit_on_input: false,
+            start_requested: false,
+            idle_time: 0.0,
+            running_time: 0.0,
+            preset_timer: 0.0,
+            preset: 0,
+            orbit_angle: 0.0,
+            restore: None,
+        }
+    }
+}
+
+impl Screensaver {
+    /// Starts on the first frame and quits on input, for `--screensaver`
+    pub fn launch(&mut self) {
+        self.start_requested = true;
+        self.exit_on_input = true;
+    }
+
+    pub fn request_start(&mut self) {
+        self.start_requested = true;
+    }
+
+    /// Whether the user touched the mouse, keyboard or screen this frame
+    pub fn had_input(ctx: &egui::Context) -> bool {
+        ctx.input(|input| {
+            input.pointer.delta().length() > POINTER_THRESHOLD
+                || input.pointer.any_pressed()
+                || input.raw_scroll_delta != egui::Vec2::ZERO
+                || input.events.iter().any(|event| {
+                    matches!(
+                        event,
+                        egui::Event::Key { pressed: true, .. }
+                            | egui::Event::Text(_)
+                            | egui::Event::Touch { .. }
+                    )
+                })
+        })
+    }
+
+    /// Tracks idle time while inactive, true when it should start
+    pub fn should_start(&mut self, input: bool, delta_time: f32) -> bool {
+        self.idle_time = if input {
+            0.0
+        } else {
+            self.idle_time + delta_time
+        };
+        let idle = self.idle_timeout > 0.0 && self.idle_time >= self.idle_timeout;
+        std::mem::take(&mut self.start_requested) || idle
+    }
+
+    /// Begins the loop, returning the first preset to show
+    pub fn start(&mut self, restore: Restore) -> usize {
+        self.active = true;
+        self.running_time = 0.0;
+        self.preset_timer = 0.0;
+        self.preset = restore
+            .preset
+            .map_or(0, |index| (index + 1) % PRESETS.len());
+        self.restore = Some(restore);
+        self.preset
+    }
+
+    /// Ends the loop, handing back the state to return to
+    pub fn stop(&mut self) -> Option<Restore> {
+        self.active = false;
+        self.idle_time = 0.0;
+        self.restore.take()
+    }
+
+    pub fn update(&mut self, input: bool, delta_time: f32) -> ScreensaverStep {
+        self.running_time += delta_time;
+        if input && self.running_time > GRACE_PERIOD {
+            return ScreensaverStep::Exit;
+        }
+
+        self.orbit_angle = (self.orbit_angle + ORBIT_SPEED * delta_time) % std::f32::consts::TAU;
+        self.preset_timer += delta_time;
+        if self.preset_timer >= self.preset_interval {
+            self.preset_timer = 0.0;
+            self.preset = (self.preset + 1) % PRESETS.len();
+            return ScreensaverStep::Preset(self.preset);
+        }
+        ScreensaverStep::Continue
+    }
+
+    /// Camera position, yaw and pitch looking at the origin from the orbit
+    pub fn camera_pose(&self) -> (Vec3, f32, f32) {
+        let angle = self.orbit_angle;
+        let position = Vec3::new(
+            ORBIT_RADIUS * angle.cos(),
+            ORBIT_HEIGHT * (angle * 2.0).sin(),
+            ORBIT_RADIUS * angle.sin(),
+        );
+        // Inverse of Camera::get_forward
+        let forward = -position.normalize();
+        (position, forward.z.atan2(forward.x), forward.y.asin())
+    }
+}
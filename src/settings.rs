@@ -0,0 +1,615 @@
+use crate::renderer::DrawOrder;
+use crate::simulation::attractors::{MAX_POINT_ATTRACTORS, PointAttractor};
+use crate::simulation::chemistry::ReactionRule;
+use crate::simulation::collisions::{CONTACT_GRANULAR, CONTACT_IMPULSE};
+use crate::simulation::field_graph::FieldGraph;
+use crate::simulation::flip::{
+    MAX_LIQUID_RESOLUTION, MAX_PRESSURE_ITERATIONS, MIN_LIQUID_RESOLUTION,
+};
+use crate::simulation::forces::ForceStack;
+use crate::simulation::mpm::{MAX_MPM_RESOLUTION, MIN_MPM_RESOLUTION, MpmMaterial};
+use crate::simulation::obstacles::{MAX_OBSTACLES, Obstacle};
+use crate::simulation::particle_life::{self, InteractionMatrix};
+use crate::simulation::springs::MAX_SPRING_ITERATIONS;
+use crate::simulation::strange::{STRANGE_ATTRACTORS, STRANGE_NONE};
+use crate::simulation::surface::Surface;
+use crate::simulation::{
+    BOUNDARY_OPEN, EMIT_SPAWN_SHAPE, GRAVITY_UNIFORM, GenerationSettings, Integrator, SimParams,
+};
+use crate::timestep::MAX_SUBSTEPS;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Bumped when fields change meaning. Missing fields fall back to their
+/// defaults, so adding new ones doesn't need a bump.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Every user-tunable parameter, in the form shared through the clipboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+
+    pub particle_count: u32,
+    pub generation: GenerationSettings,
+    pub respawn_enabled: bool,
+    pub respawn_rate: f32,
+    pub lifetime_enabled: bool,
+    /// Mean seconds particles live before being re-emitted
+    pub lifetime: f32,
+    pub lifetime_variation: f32,
+    /// One of the `EMIT_*` constants
+    pub emitter_mode: u32,
+    pub emitter_position: Vec3,
+    pub emitter_speed: f32,
+    /// Radians off straight up fountains spray within
+    pub emitter_spread: f32,
+    /// Dead particles revived per second, 0 re-emits them right away
+    pub emission_rate: f32,
+
+    /// Strength of gravity along `gravity_direction`, or towards
+    /// `gravity_center` in the central modes
+    pub gravity: f32,
+    pub gravity_direction: Vec3,
+    /// One of the `GRAVITY_*` modes
+    pub gravity_mode: u32,
+    pub gravity_center: Vec3,
+    pub damping: f32,
+    /// Percent each particle's damping and gravity are varied by
+    pub variation: f32,
+    pub integrator: Integrator,
+    /// Physics steps per second, whatever the frame rate
+    pub fixed_step_rate: f32,
+    /// Passes each step is split into, for stiff forces that blow up at the
+    /// full step
+    pub substeps: u32,
+    /// Add sub-steps while the fastest particle would move farther than
+    /// `max_travel` in one
+    pub adaptive_timestep: bool,
+    pub max_travel: f32,
+    /// Keep the sub-steps fixed and restart the step counter on resets, so
+    /// the CPU backend repeats a run bit for bit
+    pub deterministic: bool,
+    /// Draw particles blended between steps instead of where the last left them
+    pub interpolation: bool,
+    pub attractor_enabled: bool,
+    pub attractor_mass: f32,
+    /// Curl noise acceleration, 0 disables it
+    pub turbulence_amplitude: f32,
+    pub turbulence_frequency: f32,
+    /// How fast the noise drifts upwards, in units per second
+    pub turbulence_speed: f32,
+    pub wind_strength: f32,
+    pub wind_direction: Vec3,
+    pub wind_gustiness: f32,
+    pub nbody_mass: f32,
+    pub nbody_theta: f32,
+    pub nbody_softening: f32,
+    pub boid_radius: f32,
+    pub boid_separation: f32,
+    pub boid_alignment: f32,
+    pub boid_cohesion: f32,
+    pub boid_max_speed: f32,
+    /// Share of the liquid's velocity change particles keep, 0 is PIC and
+    /// 1 is FLIP
+    pub flip_ratio: f32,
+    pub liquid_resolution: u32,
+    pub pressure_iterations: u32,
+    pub mpm_material: MpmMaterial,
+    /// How much snow stiffens as it packs
+    pub hardening: f32,
+    /// Steepest slope sand piles up to, in degrees
+    pub friction_angle: f32,
+    pub mpm_resolution: u32,
+    pub collisions_enabled: bool,
+    pub collision_radius: f32,
+    pub restitution: f32,
+    /// Collisions grip and roll like sand instead of bouncing
+    pub granular_contacts: bool,
+    pub contact_friction: f32,
+    pub rolling_resistance: f32,
+    /// Shape the particles are held on after every step
+    pub surface: Surface,
+    pub surface_radius: f32,
+    pub surface_tube_radius: f32,
+    pub springs_enabled: bool,
+    /// Fraction of their error springs are relaxed by per iteration
+    pub spring_stiffness: f32,
+    pub spring_iterations: u32,
+    pub color_mode: u32,
+    pub max_dist_for_color: f32,
+    pub draw_order: DrawOrder,
+
+    pub mouse_force: f32,
+    pub mouse_radius: f32,
+    pub mouse_heat: f32,
+
+    pub contact_radius: f32,
+    pub conduction: f32,
+    pub buoyancy: f32,
+    pub ambient_temperature: f32,
+    pub reactions_enabled: bool,
+    pub reaction_rules: Vec<ReactionRule>,
+
+    pub dipoles_enabled: bool,
+    pub dipole_strength: f32,
+    pub dipole_radius: f32,
+    pub lorentz_enabled: bool,
+    pub electric_strength: f32,
+    pub electric_direction: Vec3,
+    pub magnetic_strength: f32,
+    pub magnetic_direction: Vec3,
+    pub coulomb_enabled: bool,
+    /// Coulomb constant, negative makes like charges attract
+    pub coulomb_strength: f32,
+    pub coulomb_cutoff: f32,
+    pub particle_life_enabled: bool,
+    pub particle_life_strength: f32,
+    pub particle_life_radius: f32,
+    pub particle_life_matrix: InteractionMatrix,
+    /// One of the `STRANGE_*` flows, `STRANGE_NONE` for none
+    pub strange_mode: u32,
+    pub strange_coefficients: [f32; 3],
+    pub strange_follow: f32,
+    pub strange_scale: f32,
+    pub strange_speed: f32,
+
+    pub lj_enabled: bool,
+    pub lj_epsilon: f32,
+    pub lj_sigma: f32,
+    pub lj_cutoff: f32,
+    pub thermostat_enabled: bool,
+    pub thermostat_target: f32,
+    pub thermostat_tau: f32,
+
+    pub boundary_mode: u32,
+    pub box_half_extents: Vec3,
+    pub wall_restitution: f32,
+    pub ground_enabled: bool,
+    pub ground_height: f32,
+    pub ground_restitution: f32,
+    pub ground_friction: f32,
+    pub show_ground_grid: bool,
+    /// Draw the container and ground lines over the particles instead of
+    /// under them
+    pub gizmo_xray: bool,
+    pub obstacles: Vec<Obstacle>,
+    pub obstacle_restitution: f32,
+    pub point_attractors: Vec<PointAttractor>,
+    /// Which field forces apply, in what order
+    pub forces: ForceStack,
+    /// Force field wired in the node editor
+    pub field_graph: FieldGraph,
+    pub show_ghosts: bool,
+    pub ghost_margin: f32,
+
+    pub rdf_enabled: bool,
+    pub rdf_max_radius: f32,
+    pub group_stats_enabled: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+
+            particle_count: 100_000,
+            generation: GenerationSettings::default(),
+            respawn_enabled: false,
+            respawn_rate: 0.2,
+            lifetime_enabled: false,
+            lifetime: 4.0,
+            lifetime_variation: 0.3,
+            emitter_mode: EMIT_SPAWN_SHAPE,
+            emitter_position: Vec3::ZERO,
+            emitter_speed: 20.0,
+            emitter_spread: 0.3,
+            emission_rate: 0.0,
+
+            gravity: 0.0,
+            gravity_direction: Vec3::NEG_Y,
+            gravity_mode: GRAVITY_UNIFORM,
+            gravity_center: Vec3::ZERO,
+            damping: 0.99,
+            variation: 0.0,
+            integrator: Integrator::Euler,
+            fixed_step_rate: 60.0,
+            substeps: 1,
+            adaptive_timestep: false,
+            max_travel: 0.5,
+            deterministic: false,
+            interpolation: true,
+            attractor_enabled: false,
+            attractor_mass: 500.0,
+            turbulence_amplitude: 0.0,
+            turbulence_frequency: 0.05,
+            turbulence_speed: 2.0,
+            wind_strength: 0.0,
+            wind_direction: Vec3::X,
+            wind_gustiness: 0.5,
+            nbody_mass: 500.0,
+            nbody_theta: 0.7,
+            nbody_softening: 1.0,
+            boid_radius: 4.0,
+            boid_separation: 8.0,
+            boid_alignment: 1.0,
+            boid_cohesion: 0.5,
+            boid_max_speed: 8.0,
+            flip_ratio: 0.95,
+            liquid_resolution: 32,
+            pressure_iterations: 40,
+            mpm_material: MpmMaterial::Snow,
+            hardening: 10.0,
+            friction_angle: 30.0,
+            mpm_resolution: 48,
+            collisions_enabled: false,
+            collision_radius: 0.5,
+            restitution: 0.8,
+            granular_contacts: false,
+            contact_friction: 0.6,
+            rolling_resistance: 0.1,
+            surface: Surface::None,
+            surface_radius: 30.0,
+            surface_tube_radius: 10.0,
+            springs_enabled: false,
+            spring_stiffness: 0.5,
+            spring_iterations: 8,
+            color_mode: 0,
+            max_dist_for_color: 50.0,
+            draw_order: DrawOrder::Unsorted,
+
+            mouse_force: 5.0,
+            mouse_radius: 10.0,
+            mouse_heat: 0.0,
+
+            contact_radius: 1.0,
+            conduction: 0.0,
+            buoyancy: 0.0,
+            ambient_temperature: 0.0,
+            reactions_enabled: false,
+            reaction_rules: vec![ReactionRule::default()],
+
+            dipoles_enabled: false,
+            dipole_strength: 1.0,
+            dipole_radius: 3.0,
+            lorentz_enabled: false,
+            electric_strength: 0.0,
+            electric_direction: Vec3::X,
+            magnetic_strength: 2.0,
+            magnetic_direction: Vec3::Y,
+            coulomb_enabled: false,
+            coulomb_strength: 1.0,
+            coulomb_cutoff: 5.0,
+            particle_life_enabled: false,
+            particle_life_strength: 2.0,
+            particle_life_radius: 5.0,
+            particle_life_matrix: particle_life::random_matrix(0),
+            strange_mode: STRANGE_NONE,
+            strange_coefficients: STRANGE_ATTRACTORS[0].defaults,
+            strange_follow: 10.0,
+            strange_scale: STRANGE_ATTRACTORS[0].scale,
+            strange_speed: STRANGE_ATTRACTORS[0].speed,
+
+            lj_enabled: false,
+            lj_epsilon: 1.0,
+            lj_sigma: 1.5,
+            lj_cutoff: 2.5,
+            thermostat_enabled: false,
+            thermostat_target: 0.5,
+            thermostat_tau: 0.5,
+
+            boundary_mode: BOUNDARY_OPEN,
+            box_half_extents: Vec3::splat(50.0),
+            wall_restitution: 0.8,
+            ground_enabled: false,
+            ground_height: -30.0,
+            ground_restitution: 0.3,
+            ground_friction: 0.2,
+            show_ground_grid: true,
+            gizmo_xray: false,
+            obstacles: Vec::new(),
+            obstacle_restitution: 0.5,
+            point_attractors: Vec::new(),
+            forces: ForceStack::default(),
+            field_graph: FieldGraph::default(),
+            show_ghosts: false,
+            ghost_margin: 5.0,
+
+            rdf_enabled: false,
+            rdf_max_radius: 6.0,
+            group_stats_enabled: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("settings always serialize")
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let settings: Settings =
+            serde_json::from_str(text.trim()).map_err(|e| format!("Not valid settings: {e}"))?;
+        if settings.version > SETTINGS_VERSION {
+            return Err(format!(
+                "Settings are from a newer version ({} > {SETTINGS_VERSION})",
+                settings.version
+            ));
+        }
+        Ok(settings)
+    }
+
+    /// Copy with the fields named in `patch`, a JSON object such as
+    /// `{"gravity": 2.0}`, replaced
+    pub fn merged(&self, patch: &serde_json::Value) -> Result<Self, String> {
+        let mut value = serde_json::to_value(self).expect("settings always serialize");
+        let (Some(fields), Some(patch)) = (value.as_object_mut(), patch.as_object()) else {
+            return Err("Parameters must be an object".to_owned());
+        };
+        for (name, field) in patch {
+            fields.insert(name.clone(), field.clone());
+        }
+        serde_json::from_value(value).map_err(|e| format!("Not valid parameters: {e}"))
+    }
+
+    /// Uniforms for one of `substeps` sub-steps of a simulation step of
+    /// `delta_time`, without mouse interaction
+    pub fn sim_params(
+        &self,
+        delta_time: f32,
+        substeps: u32,
+        step: u32,
+        particle_count: u32,
+    ) -> SimParams {
+        let substeps = substeps.clamp(1, MAX_SUBSTEPS) as f32;
+        SimParams {
+            delta_time: delta_time / substeps,
+            force_count: self.forces.active().count() as u32,
+            color_mode: self.color_mode,
+            mouse_force: self.mouse_force,
+            mouse_radius: self.mouse_radius,
+            mouse_position: [0.0, 0.0, 0.0],
+            point_attractor_count: self.point_attractors.len().min(MAX_POINT_ATTRACTORS) as u32,
+            is_mouse_dragging: 0,
+            // Per step, so spread over the sub-steps
+            damping: self.damping.powf(1.0 / substeps),
+            max_dist_for_color: self.max_dist_for_color,
+            contact_radius: self.contact_radius,
+            conduction: self.conduction,
+            buoyancy: self.buoyancy,
+            ambient_temperature: self.ambient_temperature,
+            mouse_heat: self.mouse_heat,
+            emitter_spread: self.emitter_spread,
+            dipole_strength: if self.dipoles_enabled {
+                self.dipole_strength
+            } else {
+                0.0
+            },
+            dipole_radius: self.dipole_radius,
+            lifetime: if self.lifetime_enabled {
+                self.lifetime
+            } else {
+                0.0
+            },
+            lifetime_variation: self.lifetime_variation,
+            electric_field: (self.electric_direction.normalize_or_zero() * self.electric_strength)
+                .into(),
+            lorentz_enabled: self.lorentz_enabled as u32,
+            magnetic_field: (self.magnetic_direction.normalize_or_zero() * self.magnetic_strength)
+                .into(),
+            emitter_mode: self.emitter_mode,
+            strange_mode: self.strange_mode,
+            strange_follow: self.strange_follow,
+            strange_scale: self.strange_scale,
+            strange_speed: self.strange_speed,
+            strange_coefficients: self.strange_coefficients,
+            coulomb_cutoff: self.coulomb_cutoff,
+            particle_life_strength: if self.particle_life_enabled {
+                self.particle_life_strength
+            } else {
+                0.0
+            },
+            particle_life_radius: self.particle_life_radius,
+            gravity_mode: self.gravity_mode,
+            central_gravity: if self.gravity_mode == GRAVITY_UNIFORM {
+                0.0
+            } else {
+                self.gravity
+            },
+            gravity: if self.gravity_mode == GRAVITY_UNIFORM {
+                (self.gravity_direction.normalize_or_zero() * self.gravity).into()
+            } else {
+                [0.0; 3]
+            },
+            _padding20: 0,
+            gravity_center: self.gravity_center.into(),
+            variation: self.variation / 100.0,
+            flip_ratio: self.flip_ratio,
+            liquid_resolution: self
+                .liquid_resolution
+                .clamp(MIN_LIQUID_RESOLUTION, MAX_LIQUID_RESOLUTION),
+            pressure_iterations: self.pressure_iterations.clamp(1, MAX_PRESSURE_ITERATIONS),
+            mpm_material: self.mpm_material.mode(),
+            hardening: self.hardening,
+            friction_angle: self.friction_angle.to_radians(),
+            mpm_resolution: self
+                .mpm_resolution
+                .clamp(MIN_MPM_RESOLUTION, MAX_MPM_RESOLUTION),
+            emission_rate: if self.lifetime_enabled {
+                self.emission_rate
+            } else {
+                0.0
+            },
+            _padding22: 0,
+            _padding23: 0,
+            lj_epsilon: if self.lj_enabled {
+                self.lj_epsilon
+            } else {
+                0.0
+            },
+            lj_sigma: self.lj_sigma,
+            lj_cutoff: self.lj_cutoff,
+            thermostat_target: self.thermostat_target,
+            thermostat_tau: if self.thermostat_enabled {
+                self.thermostat_tau
+            } else {
+                0.0
+            },
+            turbulence_amplitude: self.turbulence_amplitude,
+            turbulence_frequency: self.turbulence_frequency,
+            // Set every step from the simulated time
+            turbulence_scroll: 0.0,
+            wind: (self.wind_direction.normalize_or_zero() * self.wind_strength).into(),
+            wind_gustiness: self.wind_gustiness,
+            box_half_extents: self.box_half_extents.into(),
+            boundary_mode: self.boundary_mode,
+            respawn_rate: match self.generation.mode.spawn_mode() {
+                Some(_) if self.respawn_enabled => self.respawn_rate,
+                _ => 0.0,
+            },
+            spawn_mode: self.generation.mode.spawn_mode().unwrap_or_default(),
+            step,
+            particle_count,
+            attractor_position: [0.0, 0.0, 0.0],
+            attractor_mass: if self.attractor_enabled {
+                self.attractor_mass
+            } else {
+                0.0
+            },
+            nbody_mass: self.nbody_mass,
+            nbody_theta: self.nbody_theta,
+            nbody_softening: self.nbody_softening,
+            emitter_speed: self.emitter_speed,
+            emitter_position: self.emitter_position.into(),
+            coulomb_strength: if self.coulomb_enabled {
+                self.coulomb_strength
+            } else {
+                0.0
+            },
+            boid_radius: self.boid_radius,
+            boid_separation: self.boid_separation,
+            boid_alignment: self.boid_alignment,
+            boid_cohesion: self.boid_cohesion,
+            boid_max_speed: self.boid_max_speed,
+            collision_radius: if self.collisions_enabled {
+                self.collision_radius
+            } else {
+                0.0
+            },
+            restitution: self.restitution,
+            contact_model: if self.granular_contacts {
+                CONTACT_GRANULAR
+            } else {
+                CONTACT_IMPULSE
+            },
+            contact_friction: self.contact_friction,
+            rolling_resistance: self.rolling_resistance,
+            surface_mode: self.surface.mode(),
+            surface_radius: self.surface_radius,
+            surface_tube_radius: self.surface_tube_radius,
+            wall_restitution: self.wall_restitution,
+            ground_enabled: self.ground_enabled as u32,
+            ground_height: self.ground_height,
+            ground_restitution: self.ground_restitution,
+            ground_friction: self.ground_friction,
+            obstacle_count: self.obstacles.len().min(MAX_OBSTACLES) as u32,
+            obstacle_restitution: self.obstacle_restitution,
+            integrator: self.integrator.mode(),
+            // Set every step like the turbulence scroll
+            time: 0.0,
+            spring_stiffness: if self.springs_enabled {
+                self.spring_stiffness
+            } else {
+                0.0
+            },
+            spring_iterations: self.spring_iterations.clamp(1, MAX_SPRING_ITERATIONS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut settings = Settings {
+            particle_count: 12_345,
+            gravity: -3.5,
+            gravity_direction: Vec3::new(1.0, 0.0, 0.0),
+            lifetime_enabled: true,
+            ..Settings::default()
+        };
+        settings.generation.seed = 42;
+        settings.point_attractors.push(PointAttractor {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            horizon: 0.5,
+            ..PointAttractor::default()
+        });
+
+        let json = settings.to_json();
+        let loaded = Settings::from_json(&json).unwrap();
+        assert_eq!(loaded.particle_count, 12_345);
+        assert_eq!(loaded.generation.seed, 42);
+        assert_eq!(loaded.point_attractors, settings.point_attractors);
+        assert_eq!(loaded.to_json(), json);
+    }
+
+    #[test]
+    fn fills_missing_fields_with_defaults() {
+        let defaults = Settings::default();
+        assert_eq!(
+            Settings::from_json("{}").unwrap().to_json(),
+            defaults.to_json()
+        );
+
+        let loaded = Settings::from_json(r#" {"gravity": 2.5} "#).unwrap();
+        assert_eq!(loaded.gravity, 2.5);
+        assert_eq!(loaded.version, SETTINGS_VERSION);
+        assert_eq!(loaded.particle_count, defaults.particle_count);
+        assert_eq!(loaded.damping, defaults.damping);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let newer = format!(r#"{{"version": {}}}"#, SETTINGS_VERSION + 1);
+        assert_eq!(
+            Settings::from_json(&newer).unwrap_err(),
+            format!(
+                "Settings are from a newer version ({} > {SETTINGS_VERSION})",
+                SETTINGS_VERSION + 1
+            )
+        );
+        let current = format!(r#"{{"version": {SETTINGS_VERSION}}}"#);
+        assert!(Settings::from_json(&current).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        assert!(Settings::from_json("").is_err());
+        assert!(Settings::from_json("gravity = 2").is_err());
+        assert!(Settings::from_json(r#"{"gravity": "strong"}"#).is_err());
+        assert!(Settings::from_json(r#"{"particle_count": -1}"#).is_err());
+    }
+
+    #[test]
+    fn merges_only_the_named_fields() {
+        let settings = Settings {
+            damping: 0.5,
+            ..Settings::default()
+        };
+        let merged = settings
+            .merged(&serde_json::json!({"gravity": 2.0, "particle_count": 10}))
+            .unwrap();
+        assert_eq!(merged.gravity, 2.0);
+        assert_eq!(merged.particle_count, 10);
+        assert_eq!(merged.damping, 0.5);
+
+        assert!(settings.merged(&serde_json::json!([1, 2])).is_err());
+        assert!(
+            settings
+                .merged(&serde_json::json!({"gravity": "up"}))
+                .is_err()
+        );
+    }
+}
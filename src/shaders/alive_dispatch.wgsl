@@ -0,0 +1,20 @@
+// The index helpers in dispatch.wgsl are prepended when the shader module is
+// created
+
+// The living `compact` in compute.wgsl listed, after their count
+@group(0) @binding(0)
+var<storage, read> alive: array<u32>;
+
+// Workgroups along x, y and z the integration is dispatched with
+@group(0) @binding(1)
+var<storage, read_write> dispatch_args: array<u32, 3>;
+
+// Sizes the integration by the living, wrapped like `dispatch_linear` in
+// simulation/dispatch.rs
+@compute @workgroup_size(1)
+fn main() {
+    let grid = workgroup_grid((alive[0] + DISPATCH_WORKGROUP_SIZE - 1u) / DISPATCH_WORKGROUP_SIZE);
+    dispatch_args[0] = grid.x;
+    dispatch_args[1] = grid.y;
+    dispatch_args[2] = 1u;
+}
@@ -0,0 +1,48 @@
+// Background grid indexing, `BackgroundGrid` is generated from its Rust
+// declaration in simulation/background_grid.rs and the shader binds it as
+// `grid`
+
+// Particles scatter onto the grid with integer atomics, as fixed point
+// values with this many steps per unit
+const FIXED_POINT_SCALE: f32 = 4096.0;
+
+fn to_fixed(value: f32) -> i32 {
+    return i32(round(value * FIXED_POINT_SCALE));
+}
+
+fn from_fixed(value: i32) -> f32 {
+    return f32(value) / FIXED_POINT_SCALE;
+}
+
+fn node_index(node: vec3<u32>) -> u32 {
+    let nodes = grid.cells + 1u;
+    return (node.z * nodes.y + node.y) * nodes.x + node.x;
+}
+
+fn node_of(index: u32) -> vec3<u32> {
+    let nodes = grid.cells + 1u;
+    return vec3<u32>(index % nodes.x, (index / nodes.x) % nodes.y, index / (nodes.x * nodes.y));
+}
+
+fn cell_index(cell: vec3<u32>) -> u32 {
+    return (cell.z * grid.cells.y + cell.y) * grid.cells.x + cell.x;
+}
+
+fn cell_of(index: u32) -> vec3<u32> {
+    return vec3<u32>(
+        index % grid.cells.x,
+        (index / grid.cells.x) % grid.cells.y,
+        index / (grid.cells.x * grid.cells.y),
+    );
+}
+
+// `position` in cells from the grid's corner
+fn grid_position(position: vec3<f32>) -> vec3<f32> {
+    return (position - grid.origin) / grid.cell_size;
+}
+
+// The cell `position` is in, the nearest one for positions outside the grid
+fn clamped_cell(position: vec3<f32>) -> vec3<u32> {
+    let cell = floor(grid_position(position));
+    return vec3<u32>(clamp(cell, vec3<f32>(0.0), vec3<f32>(grid.cells - 1u)));
+}
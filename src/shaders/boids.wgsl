@@ -0,0 +1,77 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so every boid sees the same
+// flock regardless of which ones have already steered
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Keep in sync with `apply_flocking` in simulation/flocking.rs. The grid
+// cells are at least `boid_radius` large so the surrounding 27 cover it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    var velocity = snapshot[index].velocity;
+    let center_cell = grid_cell_of(position);
+    let radius_sq = params.boid_radius * params.boid_radius;
+
+    var separation = vec3<f32>(0.0);
+    var heading = vec3<f32>(0.0);
+    var center = vec3<f32>(0.0);
+    var flockmates = 0u;
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(position, snapshot[other].position);
+            let dist_sq = dot(offset, offset);
+            if other == index || dist_sq >= radius_sq || dist_sq == 0.0 {
+                continue;
+            }
+            separation += offset / dist_sq;
+            heading += snapshot[other].velocity;
+            center -= offset;
+            flockmates++;
+        }
+    }
+
+    if flockmates > 0u {
+        let count = f32(flockmates);
+        let steering = separation * params.boid_separation
+            + (heading / count - velocity) * params.boid_alignment
+            + center / count * params.boid_cohesion;
+        velocity += steering * params.delta_time;
+    }
+    let speed = length(velocity);
+    if params.boid_max_speed > 0.0 && speed > params.boid_max_speed {
+        velocity *= params.boid_max_speed / speed;
+    }
+    particles[index].velocity = velocity;
+}
@@ -0,0 +1,9 @@
+// Camera every render shader draws from, `CameraUniform` in camera.rs
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
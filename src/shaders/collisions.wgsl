@@ -0,0 +1,72 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so both sides of a collision
+// see the same pair
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Keep in sync with `resolve_collisions` in simulation/collisions.rs. The grid
+// cells are at least a diameter large so the surrounding 27 cover it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let velocity = snapshot[index].velocity;
+    let mass = snapshot[index].mass;
+    let center_cell = grid_cell_of(position);
+    let diameter = 2.0 * params.collision_radius;
+    let diameter_sq = diameter * diameter;
+
+    var displacement = vec3<f32>(0.0);
+    var impulse = vec3<f32>(0.0);
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(position, snapshot[other].position);
+            let dist_sq = dot(offset, offset);
+            if other == index || dist_sq >= diameter_sq || dist_sq == 0.0 {
+                continue;
+            }
+            let dist = sqrt(dist_sq);
+            let normal = offset / dist;
+            let share = snapshot[other].mass / (mass + snapshot[other].mass);
+            displacement += normal * ((diameter - dist) * share);
+
+            let approach = dot(velocity - snapshot[other].velocity, normal);
+            if approach < 0.0 {
+                impulse -= normal * (approach * (1.0 + params.restitution) * share);
+            }
+        }
+    }
+
+    particles[index].position = position + displacement;
+    particles[index].velocity = velocity + impulse;
+}
@@ -0,0 +1,50 @@
+// Color ramps particles are tinted with, shared by the shaders coloring them
+
+// Keep in sync with `SPECIES_COLORS` in simulation/mod.rs
+fn species_color(species: u32) -> vec4<f32> {
+    var colors = array<vec4<f32>, 8>(
+        vec4<f32>(0.95, 0.30, 0.25, 1.0),
+        vec4<f32>(0.25, 0.60, 0.95, 1.0),
+        vec4<f32>(0.35, 0.90, 0.40, 1.0),
+        vec4<f32>(0.95, 0.85, 0.25, 1.0),
+        vec4<f32>(0.75, 0.40, 0.95, 1.0),
+        vec4<f32>(0.25, 0.90, 0.85, 1.0),
+        vec4<f32>(0.95, 0.55, 0.15, 1.0),
+        vec4<f32>(0.90, 0.90, 0.90, 1.0),
+    );
+    return colors[species % 8u];
+}
+
+// Keep in sync with `temperature_color` in simulation/mod.rs
+fn temperature_color(temperature: f32) -> vec4<f32> {
+    let t = clamp(temperature, 0.0, 1.0) * 3.0;
+    return vec4<f32>(min(t, 1.0), clamp(t - 1.0, 0.0, 1.0), clamp(t - 2.0, 0.0, 1.0), 1.0);
+}
+
+// Keep in sync with `AGE_FULL` and `age_color` in simulation/mod.rs
+const AGE_FULL: f32 = 10.0;
+
+fn age_color(age: f32, lifetime: f32) -> vec4<f32> {
+    let span = select(AGE_FULL, lifetime, lifetime > 0.0);
+    let t = clamp(age / span, 0.0, 1.0);
+    return vec4<f32>(
+        1.0 - 0.5 * t,
+        max(1.0 - t * 1.5, 0.0) * 0.9 + 0.1 * (1.0 - t),
+        max(1.0 - t * 3.0, 0.0) * 0.8,
+        1.0,
+    );
+}
+
+// Keep in sync with `displacement_color` in simulation/mod.rs
+fn displacement_color(distance: f32, full: f32) -> vec4<f32> {
+    let t = clamp(distance / max(full, 0.01), 0.0, 1.0);
+    return vec4<f32>(0.25 * (1.0 - t) + t * t, 0.05 + 0.85 * t, 0.35 + 0.3 * t - 0.55 * t * t, 1.0);
+}
+
+// Keep in sync with `DENSITY_FULL` and `density_color` in simulation/mod.rs
+const DENSITY_FULL: f32 = 24.0;
+
+fn density_color(neighbors: u32) -> vec4<f32> {
+    let d = min(f32(neighbors) / DENSITY_FULL, 1.0);
+    return vec4<f32>(d, 0.15 + 0.75 * d * d, 0.45 * (1.0 - d) + 0.6 * d * d * d, 1.0);
+}
@@ -1,26 +1,9 @@
-struct Particle {
-  position: vec3<f32>,
-  padding1: f32,
-  velocity: vec3<f32>,
-  padding2: f32,
-  color: vec4<f32>,
-  initial_color: vec4<f32>,
-};
-
-struct SimParams {
-  delta_time: f32,
-  gravity: f32,
-  color_mode: u32,
-  mouse_force: f32,
-
-  mouse_radius: f32,
-  is_mouse_dragging: u32,
-  damping: f32,
-  max_dist_for_color: f32,
-
-  mouse_position: vec3<f32>,
-  _padding2: u32,
-};
+// `Particle`, `SimParams`, `GpuPointAttractor` and `GpuForce` are generated
+// from their Rust declarations in simulation/mod.rs, simulation/attractors.rs
+// and simulation/forces.rs and prepended along with the shared colors.wgsl, falloffs.wgsl, random.wgsl,
+// noise.wgsl and dispatch.wgsl when the shader module is created. The
+// `field_graph` function is generated from the node editor's graph by
+// `FieldGraph::wgsl` in simulation/field_graph.rs and appended.
 
 @group(0) @binding(0)
 var<storage, read_write> particles: array<Particle>;
@@ -28,9 +11,398 @@ var<storage, read_write> particles: array<Particle>;
 @group(0) @binding(1)
 var<uniform> params: SimParams;
 
+// The first `params.point_attractor_count` entries are in use
+@group(0) @binding(2)
+var<storage, read> point_attractors: array<GpuPointAttractor>;
+
+// The enabled forces of the stack in order, the first `params.force_count`
+// entries are in use
+@group(0) @binding(3)
+var<storage, read> forces: array<GpuForce>;
+
+// The value of every node of the field graph, by index
+@group(0) @binding(4)
+var<storage, read> field_nodes: array<vec4<f32>>;
+
+// The living `compact` lists while there's an emission rate
+// Keep in sync with `ALIVE_HEADER` in simulation/compute.rs
+struct AliveList {
+    // Listed so far, all of them once `compact` is done
+    count: atomic<u32>,
+    // Dead revived so far this step
+    revived: atomic<u32>,
+    indices: array<u32>,
+}
+
+@group(0) @binding(5)
+var<storage, read_write> alive: AliveList;
+
+// Keep in sync with `strange_velocity` in simulation/strange.rs
+fn strange_velocity(position: vec3<f32>) -> vec3<f32> {
+    let a = params.strange_coefficients.x;
+    let b = params.strange_coefficients.y;
+    let c = params.strange_coefficients.z;
+    // The attractor's z axis points up the world's y
+    var p = position.xzy / params.strange_scale;
+    var d = vec3<f32>(0.0);
+    switch params.strange_mode {
+        case 1u: {
+            // Its wings circle z = ρ - 1
+            p.z += b - 1.0;
+            d = vec3<f32>(a * (p.y - p.x), p.x * (b - p.z) - p.y, p.x * p.y - c * p.z);
+        }
+        case 2u: {
+            p.z += 0.7;
+            let spin = p.z - b;
+            d = vec3<f32>(
+                spin * p.x - 3.5 * p.y,
+                3.5 * p.x + spin * p.y,
+                c + a * p.z - p.z * p.z * p.z / 3.0 - (p.x * p.x + p.y * p.y) * (1.0 + 0.25 * p.z)
+                    + 0.1 * p.z * p.x * p.x * p.x,
+            );
+        }
+        case 3u: {
+            d = sin(p.yzx) - a * p;
+        }
+        default: {}
+    }
+    return d.xzy * params.strange_scale * params.strange_speed;
+}
+
+// Keep in sync with `lorentz_push` in simulation/mod.rs
+fn lorentz_push(velocity: vec3<f32>, charge_per_mass: f32, delta_time: f32) -> vec3<f32> {
+    let half_kick = params.electric_field * (charge_per_mass * delta_time * 0.5);
+    let v_minus = velocity + half_kick;
+    let t = params.magnetic_field * (charge_per_mass * delta_time * 0.5);
+    let s = 2.0 * t / (1.0 + dot(t, t));
+    let v_prime = v_minus + cross(v_minus, t);
+    let v_plus = v_minus + cross(v_prime, s);
+    return v_plus + half_kick;
+}
+
+// Keep in sync with `wrap_periodic` in simulation/mod.rs
+fn wrap_periodic(position: vec3<f32>) -> vec3<f32> {
+    let size = params.box_half_extents * 2.0;
+    let shifted = position + params.box_half_extents;
+    return shifted - size * floor(shifted / size) - params.box_half_extents;
+}
+
+// Keep in sync with `attractor_acceleration` in simulation/mod.rs
+const ATTRACTOR_SOFTENING: f32 = 0.5;
+
+fn attractor_acceleration(position: vec3<f32>) -> vec3<f32> {
+    let offset = params.attractor_position - position;
+    return softened_inverse_square(offset, params.attractor_mass, ATTRACTOR_SOFTENING);
+}
+
+// Keep in sync with the `GRAVITY_*` constants and `central_gravity` in
+// simulation/mod.rs
+const GRAVITY_UNIFORM: u32 = 0u;
+const GRAVITY_CENTRAL_INVERSE_SQUARE: u32 = 2u;
+const CENTRAL_GRAVITY_RADIUS: f32 = 10.0;
+
+fn central_gravity_acceleration(position: vec3<f32>) -> vec3<f32> {
+    let offset = params.gravity_center - position;
+    let dist = length(offset);
+    if dist == 0.0 {
+        return vec3<f32>(0.0);
+    }
+    let squared = params.gravity_mode == GRAVITY_CENTRAL_INVERSE_SQUARE;
+    let falloff = capped_falloff(dist, CENTRAL_GRAVITY_RADIUS, squared);
+    return offset * (params.central_gravity * falloff / dist);
+}
+
+// Keep in sync with `PointAttractor::acceleration` in simulation/attractors.rs
+fn point_attractor_acceleration(point: GpuPointAttractor, position: vec3<f32>) -> vec3<f32> {
+    let offset = point.position - position;
+    let dist = length(offset);
+    if point.horizon > 0.0 {
+        // Nothing gets closer than the horizon before it's swallowed
+        let clamped = max(dist, point.horizon);
+        return offset * (point.strength / (clamped * clamped * clamped));
+    }
+    if dist >= point.radius || dist <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    return offset / dist * point.strength * quadratic_falloff(dist, point.radius);
+}
+
+// Keep in sync with `PointAttractor::swallows` in simulation/attractors.rs
+fn swallowed(point: GpuPointAttractor, position: vec3<f32>) -> bool {
+    let offset = point.position - position;
+    return point.horizon > 0.0 && dot(offset, offset) < point.horizon * point.horizon;
+}
+
+// Keep in sync with `PointAttractor::accretion_color` in simulation/attractors.rs
+fn accretion_color(point: GpuPointAttractor, position: vec3<f32>, color: vec4<f32>) -> vec4<f32> {
+    if point.horizon <= 0.0 || point.accretion_glow == 0u || point.radius <= point.horizon {
+        return color;
+    }
+    let dist = distance(point.position, position);
+    let heat = clamp(1.0 - (dist - point.horizon) / (point.radius - point.horizon), 0.0, 1.0);
+    let glow = vec4<f32>(1.0, 0.3 + 0.6 * heat, 0.1 + 0.8 * heat * heat, 1.0);
+    return mix(color, glow, heat);
+}
+
+const SPAWN_RADIUS: f32 = 50.0;
+const RESPAWN_SALT: u32 = 4u;
+
+// Keep in sync with `spawn_position` in simulation/mod.rs
+fn spawn_position(index: u32) -> vec3<f32> {
+    let step = params.step;
+    if params.spawn_mode == 1u {
+        let r = SPAWN_RADIUS * pow(random_unit(index, step, 1u), 1.0 / 3.0);
+        let theta = random_unit(index, step, 2u) * 6.28318530718;
+        let phi = acos(random_unit(index, step, 3u) * 2.0 - 1.0);
+        return vec3<f32>(r * sin(phi) * cos(theta), r * cos(phi), r * sin(phi) * sin(theta));
+    }
+
+    let golden_angle = 3.14159265359 * (3.0 - sqrt(5.0));
+    let y = 1.0 - (f32(index) / f32(max(params.particle_count, 2u) - 1u)) * 2.0;
+    let radius_at_y = sqrt(max(1.0 - y * y, 0.0));
+    let theta = golden_angle * f32(index);
+    return vec3<f32>(cos(theta) * radius_at_y, y, sin(theta) * radius_at_y) * SPAWN_RADIUS;
+}
+
+// Keep in sync with `spawn_color` in simulation/mod.rs
+fn spawn_color(position: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>((position / SPAWN_RADIUS + vec3<f32>(1.0)) * 0.5, 1.0);
+}
+
+// Keep in sync with the `*_SALT` and `EMIT_*` constants in simulation/mod.rs
+const LIFETIME_SALT: u32 = 5u;
+const AGE_SALT: u32 = 6u;
+const EMIT_SALT: u32 = 7u;
+const EMIT_SPAWN_SHAPE: u32 = 0u;
+const EMIT_BURST: u32 = 2u;
+const EMISSION_SALT: u32 = 10u;
+const MIN_LIFETIME: f32 = 0.05;
+// Keep in sync with `DEAD` in simulation/mod.rs
+const DEAD: f32 = -1.0;
+
+// Keep in sync with `roll_lifetime` in simulation/mod.rs
+fn roll_lifetime(index: u32) -> f32 {
+    let variation = params.lifetime_variation * (random_unit(index, params.step, LIFETIME_SALT) * 2.0 - 1.0);
+    return max(params.lifetime * (1.0 + variation), MIN_LIFETIME);
+}
+
+struct Emission {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    color: vec4<f32>,
+}
+
+// Keep in sync with `emit` in simulation/mod.rs
+fn emit(index: u32) -> Emission {
+    let step = params.step;
+    if params.emitter_mode == EMIT_SPAWN_SHAPE {
+        let position = spawn_position(index);
+        return Emission(position, vec3<f32>(0.0), spawn_color(position));
+    }
+
+    let min_cos = select(cos(params.emitter_spread), -1.0, params.emitter_mode == EMIT_BURST);
+    let cos_theta = 1.0 - random_unit(index, step, EMIT_SALT) * (1.0 - min_cos);
+    let sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    let phi = random_unit(index, step, EMIT_SALT + 1u) * 6.28318530718;
+    let direction = vec3<f32>(sin_theta * cos(phi), cos_theta, sin_theta * sin(phi));
+    let speed = params.emitter_speed * (0.8 + 0.4 * random_unit(index, step, EMIT_SALT + 2u));
+    return Emission(params.emitter_position, direction * speed, spawn_color(direction * SPAWN_RADIUS));
+}
+
+// Keep in sync with `reemit` in simulation/mod.rs
+fn reemit(index: u32) {
+    let emission = emit(index);
+    particles[index].position = emission.position;
+    particles[index].velocity = emission.velocity;
+    particles[index].temperature = 0.0;
+    particles[index].initial_color = emission.color;
+    particles[index].age = 0.0;
+    particles[index].lifetime = select(0.0, roll_lifetime(index), params.lifetime > 0.0);
+}
+
+// Keep in sync with `emission_budget` in simulation/mod.rs
+fn emission_budget() -> u32 {
+    let expected = params.emission_rate * params.delta_time;
+    return u32(expected + random_unit(0u, params.step, EMISSION_SALT));
+}
+
+const NOT_LISTED: u32 = 0xffffffffu;
+
+var<workgroup> group_alive: atomic<u32>;
+var<workgroup> group_start: u32;
+
+// Revives the dead within the step's budget and lists the living for
+// `main`. Every workgroup counts its living in shared memory first so the
+// list's count sees one write per workgroup instead of one per particle.
 @compute @workgroup_size(256)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
+fn compact(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    var slot = NOT_LISTED;
+    if index < params.particle_count {
+        // Nested so only the dead count towards the budget
+        if particles[index].lifetime < 0.0 {
+            if atomicAdd(&alive.revived, 1u) < emission_budget() {
+                reemit(index);
+            }
+        }
+        if particles[index].lifetime >= 0.0 {
+            slot = atomicAdd(&group_alive, 1u);
+        }
+    }
+    workgroupBarrier();
+
+    if local_id.x == 0u {
+        group_start = atomicAdd(&alive.count, atomicLoad(&group_alive));
+    }
+    workgroupBarrier();
+
+    if slot != NOT_LISTED {
+        alive.indices[group_start + slot] = index;
+    }
+}
+
+// Keep in sync with `turbulence` in simulation/noise.rs
+fn turbulence_acceleration(position: vec3<f32>) -> vec3<f32> {
+    let scrolled = position - vec3<f32>(0.0, params.turbulence_scroll, 0.0);
+    return curl_noise(scrolled * params.turbulence_frequency) * params.turbulence_amplitude;
+}
+
+// Keep in sync with `GUST_FREQUENCY` in simulation/noise.rs
+const GUST_FREQUENCY: f32 = 0.02;
+
+// Keep in sync with `buoyancy` in simulation/mod.rs
+fn buoyancy(temperature: f32) -> vec3<f32> {
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if dot(params.gravity, params.gravity) > 0.0 {
+        up = -normalize(params.gravity);
+    }
+    return up * (params.buoyancy * (temperature - params.ambient_temperature));
+}
+
+// Keep in sync with `gust_factor` in simulation/noise.rs
+fn gust_factor(position: vec3<f32>) -> f32 {
+    let drifted = position - params.wind * params.time;
+    let noise = simplex_noise(drifted * GUST_FREQUENCY).w;
+    return max(1.0 + params.wind_gustiness * noise, 0.0);
+}
+
+// Keep in sync with `vortex_acceleration` in simulation/forces.rs
+fn vortex_acceleration(force: GpuForce, position: vec3<f32>) -> vec3<f32> {
+    let offset = position - force.center;
+    let radial = offset - force.axis * dot(offset, force.axis);
+    let dist = length(radial);
+    if dist >= force.radius || dist <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    return cross(force.axis, radial) / dist * force.strength * quadratic_falloff(dist, force.radius);
+}
+
+// Keep in sync with the `FORCE_*` constants in simulation/forces.rs
+const FORCE_GRAVITY: u32 = 0u;
+const FORCE_ATTRACTOR: u32 = 1u;
+const FORCE_POINT_ATTRACTORS: u32 = 2u;
+const FORCE_TURBULENCE: u32 = 3u;
+const FORCE_WIND: u32 = 4u;
+const FORCE_DRAG: u32 = 5u;
+const FORCE_VORTEX: u32 = 6u;
+
+// Keep in sync with `Force::acceleration` in simulation/forces.rs
+fn force_acceleration(force: GpuForce, position: vec3<f32>, velocity: vec3<f32>) -> vec3<f32> {
+    var acceleration = vec3<f32>(0.0);
+    switch force.kind {
+        case FORCE_GRAVITY: {
+            acceleration = params.gravity;
+            // Fall towards the center of gravity instead of down
+            if params.gravity_mode != GRAVITY_UNIFORM {
+                acceleration += central_gravity_acceleration(position);
+            }
+        }
+        case FORCE_ATTRACTOR: {
+            if params.attractor_mass > 0.0 {
+                acceleration = attractor_acceleration(position);
+            }
+        }
+        case FORCE_POINT_ATTRACTORS: {
+            for (var i = 0u; i < params.point_attractor_count; i++) {
+                acceleration += point_attractor_acceleration(point_attractors[i], position);
+            }
+        }
+        case FORCE_TURBULENCE: {
+            if params.turbulence_amplitude > 0.0 {
+                acceleration = turbulence_acceleration(position);
+            }
+        }
+        case FORCE_WIND: {
+            if any(params.wind != vec3<f32>(0.0)) {
+                acceleration = params.wind * gust_factor(position);
+            }
+        }
+        case FORCE_DRAG: {
+            acceleration = -velocity * force.strength;
+        }
+        case FORCE_VORTEX: {
+            acceleration = vortex_acceleration(force, position);
+        }
+        default: {}
+    }
+    return acceleration;
+}
+
+// Factors a particle scales damping's loss and gravity by
+// Keep in sync with `variation_scales` in simulation/mod.rs
+fn variation_scales(variation: u32) -> vec2<f32> {
+    return 1.0 + params.variation * (unpack2x16unorm(variation) * 2.0 - 1.0);
+}
+
+// Acceleration from the force stack, gravity scaled by `gravity_scale`, and
+// the mouse's force divided by the mass in xyz, how strongly the mouse heats
+// a particle there in w
+fn field_acceleration(
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    inverse_mass: f32,
+    gravity_scale: f32,
+) -> vec4<f32> {
+    var acceleration = vec3<f32>(0.0);
+    for (var i = 0u; i < params.force_count; i++) {
+        let scale = select(1.0, gravity_scale, forces[i].kind == FORCE_GRAVITY);
+        acceleration += force_acceleration(forces[i], position, velocity) * scale;
+    }
+    acceleration += field_graph(position, velocity);
+
+    // Apply mouse force - only if needed
+    var heating = 0.0;
+    if params.is_mouse_dragging > 0u {
+        let dir = params.mouse_position - position;
+        let dist = length(dir);
+
+        if dist < params.mouse_radius * 2.0 {
+            heating = quadratic_falloff(dist, params.mouse_radius * 2.0) * 2.0;
+            acceleration += normalize(dir) * (params.mouse_force * heating * inverse_mass);
+        }
+    }
+    return vec4<f32>(acceleration, heating);
+}
+
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    var index = invocation_index(global_id, num_workgroups);
+
+    // Only the living are dispatched at an emission rate, through the list
+    let rate_limited = params.emission_rate > 0.0;
+    if rate_limited {
+        if index >= atomicLoad(&alive.count) {
+            return;
+        }
+        index = alive.indices[index];
+    }
 
     // Early return if we're out of bounds
     if index >= arrayLength(&particles) {
@@ -39,37 +411,122 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 
     // Cache frequently used values for better performance
     let delta_time = params.delta_time;
-    let gravity = params.gravity;
     let damping = params.damping;
     let max_dist = params.max_dist_for_color;
 
 
+    // Without an emission rate the dead are re-emitted right away
+    if particles[index].lifetime < 0.0 {
+        reemit(index);
+    }
+
+    // Continuously re-seed a fraction of the particles from the spawn shape
+    let respawn_chance = params.respawn_rate * delta_time;
+    if respawn_chance > 0.0 && random_unit(index, params.step, RESPAWN_SALT) < respawn_chance {
+        let spawn = spawn_position(index);
+        particles[index].position = spawn;
+        particles[index].velocity = vec3<f32>(0.0);
+        particles[index].temperature = 0.0;
+        particles[index].initial_color = spawn_color(spawn);
+        particles[index].age = 0.0;
+    }
+
+    // Age, and re-emit particles that outlived their lifetime or let them die
+    // at an emission rate
+    particles[index].age += delta_time;
+    if params.lifetime > 0.0 {
+        if particles[index].lifetime <= 0.0 {
+            // First roll, staggered so they don't all expire together
+            particles[index].lifetime = roll_lifetime(index);
+            if params.emitter_mode != EMIT_BURST {
+                particles[index].age = random_unit(index, params.step, AGE_SALT) * particles[index].lifetime;
+            }
+        }
+        if particles[index].age >= particles[index].lifetime {
+            if rate_limited {
+                particles[index].lifetime = DEAD;
+                return;
+            }
+            reemit(index);
+        }
+    } else {
+        particles[index].lifetime = 0.0;
+    }
+
     var position = particles[index].position;
     var velocity = particles[index].velocity;
-    let initial_color = particles[index].initial_color;
+    var initial_color = particles[index].initial_color;
     var current_color = particles[index].color;
+    var temperature = particles[index].temperature;
 
-    // Apply gravity
-    velocity.y -= gravity * delta_time;
+    let inverse_mass = 1.0 / particles[index].mass;
+    let scales = variation_scales(particles[index].variation);
+    let field = field_acceleration(position, velocity, inverse_mass, scales.y);
+    temperature += params.mouse_heat * field.w * delta_time;
+    let lift = buoyancy(temperature);
 
-    // Apply mouse force - only if needed
-    if params.is_mouse_dragging > 0u {
-        let dir = params.mouse_position - position;
-        let dist = length(dir);
+    // Keep in sync with the integrators in simulation/cpu.rs
+    let verlet = params.integrator == 1u;
+    let kick = select(delta_time, delta_time * 0.5, verlet);
+    velocity += (field.xyz + lift) * kick;
 
-        if dist < params.mouse_radius * 2.0 {
-        // More efficient force calculation
-            let normalized_dist = clamp(dist / (params.mouse_radius * 2.0), 0.0, 1.0);
-            let force_factor = (1.0 - normalized_dist) * (1.0 - normalized_dist) * 2.0;
-            velocity += normalize(dir) * params.mouse_force * force_factor * delta_time;
-        }
+    // Apply electric and magnetic fields to charged particles
+    if params.lorentz_enabled > 0u {
+        velocity = lorentz_push(velocity, particles[index].charge * inverse_mass, delta_time);
+    }
+
+    // Get carried along a strange attractor's flow
+    // Keep in sync with `follow_strange_flow` in simulation/strange.rs
+    if params.strange_mode != 0u {
+        let blend = 1.0 - exp(-params.strange_follow * delta_time);
+        velocity = mix(velocity, strange_velocity(position), blend);
     }
 
     // Update position
     position += velocity * delta_time;
+    if verlet {
+        velocity += (field_acceleration(position, velocity, inverse_mass, scales.y).xyz + lift) * kick;
+    }
+    if params.boundary_mode == 1u {
+        position = wrap_periodic(position);
+    } else if params.boundary_mode == 2u {
+        // Keep in sync with `reflect_walls` in simulation/mod.rs
+        let outside = abs(position) > params.box_half_extents;
+        let outward = outside & (velocity * position > vec3<f32>(0.0));
+        velocity = select(velocity, -velocity * params.wall_restitution, outward);
+        position = clamp(position, -params.box_half_extents, params.box_half_extents);
+    }
+    if params.ground_enabled == 1u && position.y < params.ground_height {
+        // Keep in sync with `land_on_ground` in simulation/mod.rs
+        position.y = params.ground_height;
+        if velocity.y < 0.0 {
+            velocity.y *= -params.ground_restitution;
+        }
+        velocity.x *= 1.0 - params.ground_friction;
+        velocity.z *= 1.0 - params.ground_friction;
+    }
+
+    // Re-emit whatever fell into a black hole, or let it die at an emission
+    // rate
+    for (var i = 0u; i < params.point_attractor_count; i++) {
+        if swallowed(point_attractors[i], position) {
+            if rate_limited {
+                particles[index].lifetime = DEAD;
+                return;
+            }
+            let emission = emit(index);
+            position = emission.position;
+            velocity = emission.velocity;
+            temperature = 0.0;
+            initial_color = emission.color;
+            particles[index].initial_color = emission.color;
+            particles[index].age = 0.0;
+            break;
+        }
+    }
 
-    // Apply damping
-    velocity *= damping;
+    // Apply damping, this particle losing more or less than the rest
+    velocity *= max(1.0 - (1.0 - damping) * scales.x, 0.0);
 
     switch params.color_mode {
         case 0u: {
@@ -87,13 +544,35 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
             // Example coloring: blue near origin, red far away
             current_color = vec4<f32>(norm_dist, 0.0, 1.0 - norm_dist, 1.0);
         }
+        case 3u: {
+            current_color = species_color(particles[index].species);
+        }
+        case 4u: {
+            current_color = temperature_color(temperature);
+        }
+        case 5u: {
+            current_color = vec4<f32>(abs(particles[index].dipole), 1.0);
+        }
+        case 6u: {
+            let c = clamp(particles[index].charge, -1.0, 1.0);
+            current_color = vec4<f32>(max(c, 0.0), 0.2, max(-c, 0.0), 1.0);
+        }
+        case 8u: {
+            current_color = age_color(particles[index].age, particles[index].lifetime);
+        }
         default: {
             current_color = initial_color;
         }
     }
 
+    // Light up the accretion discs
+    for (var i = 0u; i < params.point_attractor_count; i++) {
+        current_color = accretion_color(point_attractors[i], position, current_color);
+    }
+
     // Write back particle data once
     particles[index].position = position;
     particles[index].velocity = velocity;
     particles[index].color = current_color;
+    particles[index].temperature = temperature;
 }
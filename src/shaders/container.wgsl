@@ -0,0 +1,36 @@
+// `Camera` is bound from camera.wgsl, prepended when the shader module is
+// created
+
+struct Container {
+    // xyz = box half extents
+    half_extents: vec4<f32>,
+    color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> container: Container;
+
+// Two vertices for each of the 12 edges, corners have bit 0 set for +x, bit 1
+// for +y and bit 2 for +z
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let edge = vertex_index / 2u;
+    let axis = edge / 4u;
+    // The other two axes of the edge's four parallel copies, spread around
+    // the bit of its own axis
+    let others = edge % 4u;
+    let below = (1u << axis) - 1u;
+    let corner = (others & below) | ((others & ~below) << 1u) | ((vertex_index % 2u) << axis);
+
+    let side = vec3<f32>(
+        f32(corner & 1u),
+        f32((corner >> 1u) & 1u),
+        f32((corner >> 2u) & 1u),
+    ) * 2.0 - 1.0;
+    return camera.view_proj * vec4<f32>(side * container.half_extents.xyz, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return container.color;
+}
@@ -0,0 +1,50 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared colors.wgsl, falloffs.wgsl and dispatch.wgsl when the shader
+// module is created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Colors every particle by the neighbors within the contact radius, the grid
+// cells are at least that large so the surrounding 27 cover it
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = particles[index].position;
+    let center = grid_cell_of(position);
+    let radius_sq = params.contact_radius * params.contact_radius;
+
+    var neighbors = 0u;
+    var neighborhood = grid_neighborhood(center);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(particles[other].position, position);
+            if other != index && dot(offset, offset) < radius_sq {
+                neighbors++;
+            }
+        }
+    }
+    particles[index].color = density_color(neighbors);
+}
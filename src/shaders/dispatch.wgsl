@@ -0,0 +1,26 @@
+// Dispatches with more workgroups than fit along x wrap onto more rows along
+// y, see `dispatch_linear` in simulation/dispatch.rs
+
+// Keep in sync with `WORKGROUP_SIZE` in simulation/dispatch.rs
+const DISPATCH_WORKGROUP_SIZE: u32 = 256u;
+
+// Position of the workgroup in the line the dispatch was wrapped from
+fn workgroup_index(workgroup_id: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+    return workgroup_id.y * num_workgroups.x + workgroup_id.x;
+}
+
+// Position of the invocation in the line the dispatch was wrapped from
+fn invocation_index(global_id: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+    return global_id.y * num_workgroups.x * DISPATCH_WORKGROUP_SIZE + global_id.x;
+}
+
+// Keep in sync with `MAX_WORKGROUPS_PER_DIMENSION` in simulation/dispatch.rs
+const DISPATCH_MAX_WORKGROUPS: u32 = 65535u;
+
+// Workgroups along x and y covering at least `workgroup_count`, for
+// dispatches sized on the GPU
+// Keep in sync with `workgroup_grid` in simulation/dispatch.rs
+fn workgroup_grid(workgroup_count: u32) -> vec2<u32> {
+    let rows = max((workgroup_count + DISPATCH_MAX_WORKGROUPS - 1u) / DISPATCH_MAX_WORKGROUPS, 1u);
+    return vec2<u32>((workgroup_count + rows - 1u) / rows, rows);
+}
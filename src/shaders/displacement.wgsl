@@ -0,0 +1,42 @@
+// `Particle` and `SimParams` are generated from their Rust declarations and
+// prepended along with the shared colors.wgsl and dispatch.wgsl when the
+// shader module is created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were when the reference was taken
+@group(0) @binding(2)
+var<storage, read> reference: array<Particle>;
+
+// Keep in sync with `BOUNDARY_PERIODIC` in simulation/mod.rs
+const BOUNDARY_PERIODIC: u32 = 1u;
+
+// Keep in sync with `nearest_image` in simulation/mod.rs
+fn nearest_image(offset: vec3<f32>) -> vec3<f32> {
+    let box_size = params.box_half_extents * 2.0;
+    return offset - box_size * round(offset / box_size);
+}
+
+// Colors every particle by how far it is from where it was when the reference
+// was taken, the shortest way round in a periodic box
+// Keep in sync with the `COLOR_DISPLACEMENT` coloring in simulation/cpu.rs
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= params.particle_count {
+        return;
+    }
+
+    var offset = particles[index].position - reference[index].position;
+    if params.boundary_mode == BOUNDARY_PERIODIC {
+        offset = nearest_image(offset);
+    }
+    particles[index].color = displacement_color(length(offset), params.max_dist_for_color);
+}
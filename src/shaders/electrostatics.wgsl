@@ -0,0 +1,67 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so both charges of a pair
+// feel the same force
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Keep in sync with `COULOMB_SOFTENING` in simulation/electrostatics.rs
+const COULOMB_SOFTENING: f32 = 0.5;
+
+// Keep in sync with `apply_coulomb_forces` in simulation/electrostatics.rs.
+// The grid cells are at least the cutoff large so the surrounding 27 cover it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+    let charge = snapshot[index].charge;
+    if charge == 0.0 {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let center_cell = grid_cell_of(position);
+    let cutoff_sq = params.coulomb_cutoff * params.coulomb_cutoff;
+    var field = vec3<f32>(0.0);
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let other_charge = snapshot[other].charge;
+            let offset = grid_separation(position, snapshot[other].position);
+            let dist_sq = dot(offset, offset);
+            if other == index || other_charge == 0.0 || dist_sq >= cutoff_sq {
+                continue;
+            }
+            field += softened_inverse_square(offset, other_charge, COULOMB_SOFTENING);
+        }
+    }
+
+    let scale = params.coulomb_strength * charge / snapshot[index].mass * params.delta_time;
+    particles[index].velocity = snapshot[index].velocity + field * scale;
+}
@@ -0,0 +1,26 @@
+// How the forces pulling particles towards points weaken with distance,
+// shared so every force of the same shape agrees on it
+
+// `strength / (d² + softening²)` along `offset`, an inverse square law that
+// stays finite as `offset` shrinks to nothing
+// Keep in sync with `attractor_acceleration` in simulation/mod.rs and
+// `apply_coulomb_forces` in simulation/electrostatics.rs
+fn softened_inverse_square(offset: vec3<f32>, strength: f32, softening: f32) -> vec3<f32> {
+    let soft_sq = dot(offset, offset) + softening * softening;
+    return offset * (strength / (soft_sq * sqrt(soft_sq)));
+}
+
+// 1 at the center, easing down to 0 at `radius` and beyond
+// Keep in sync with the mouse force in simulation/cpu.rs and
+// `PointAttractor::acceleration` in simulation/attractors.rs
+fn quadratic_falloff(dist: f32, radius: f32) -> f32 {
+    let t = clamp(1.0 - dist / radius, 0.0, 1.0);
+    return t * t;
+}
+
+// 1 within `radius`, then `radius / dist` past it, squared if asked to
+// Keep in sync with `central_gravity` in simulation/mod.rs
+fn capped_falloff(dist: f32, radius: f32, squared: bool) -> f32 {
+    let ratio = radius / max(dist, radius);
+    return select(ratio, ratio * ratio, squared);
+}
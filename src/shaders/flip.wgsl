@@ -0,0 +1,265 @@
+// `Particle`, `SimParams` and `BackgroundGrid` are generated from their Rust
+// declarations and prepended along with the helpers in background_grid.wgsl
+// and dispatch.wgsl when the shader module is created
+
+// Over-relaxation of the pressure solve, between 1 for plain Gauss-Seidel
+// and 2 where it stops converging
+const OVER_RELAXATION: f32 = 1.6;
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+@group(0) @binding(2)
+var<uniform> grid: BackgroundGrid;
+
+// Velocity times weight of the x, y and z faces of every node, then their
+// weights, as fixed point
+@group(0) @binding(3)
+var<storage, read_write> accumulators: array<atomic<i32>>;
+
+// Velocities of the faces on the low x, y and z sides of every node's cell
+@group(0) @binding(4)
+var<storage, read_write> velocities: array<vec4<f32>>;
+
+// The face velocities before the projection, w the divergence of the cell
+@group(0) @binding(5)
+var<storage, read_write> saved: array<vec4<f32>>;
+
+// Particles in every cell, cells without any are air
+@group(0) @binding(6)
+var<storage, read_write> cell_fill: array<atomic<u32>>;
+
+// Kept from step to step as the starting guess, only fluid cells are read
+@group(0) @binding(7)
+var<storage, read_write> pressure: array<f32>;
+
+// Corner of a cell and how far into it, for trilinear weights
+struct Stencil {
+    base: vec3<u32>,
+    frac: vec3<f32>,
+};
+
+// The 8 faces of `component` around `position`, clamped to the grid. Faces
+// of x sit at the middle of the cells' low x sides and likewise for y and z.
+fn face_stencil(position: vec3<f32>, component: u32) -> Stencil {
+    var offset = vec3<f32>(0.5);
+    offset[component] = 0.0;
+    var last = vec3<f32>(grid.cells - 1u);
+    last[component] = f32(grid.cells[component]);
+    let scaled = clamp(grid_position(position) - offset, vec3<f32>(0.0), last);
+    let base = min(floor(scaled), max(last - 1.0, vec3<f32>(0.0)));
+    return Stencil(vec3<u32>(base), scaled - base);
+}
+
+fn corner_offset(corner: u32) -> vec3<u32> {
+    return vec3<u32>(corner & 1u, (corner >> 1u) & 1u, corner >> 2u);
+}
+
+fn corner_weight(frac: vec3<f32>, offset: vec3<u32>) -> f32 {
+    let weights = select(1.0 - frac, frac, offset == vec3<u32>(1u));
+    return weights.x * weights.y * weights.z;
+}
+
+// Faces between two cells of the grid, the ones on its sides are walls
+fn open_face(node: vec3<u32>, component: u32) -> bool {
+    return all(node < grid.cells) && node[component] > 0u;
+}
+
+fn is_fluid(cell: u32) -> bool {
+    return atomicLoad(&cell_fill[cell]) > 0u;
+}
+
+@compute @workgroup_size(256)
+fn particle_to_grid(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+    let particle = particles[index];
+    for (var component = 0u; component < 3u; component++) {
+        let stencil = face_stencil(particle.position, component);
+        for (var corner = 0u; corner < 8u; corner++) {
+            let offset = corner_offset(corner);
+            let weight = corner_weight(stencil.frac, offset);
+            if weight <= 0.0 {
+                continue;
+            }
+            let node = node_index(stencil.base + offset);
+            let velocity = particle.velocity[component];
+            atomicAdd(&accumulators[node * 6u + component], to_fixed(weight * velocity));
+            atomicAdd(&accumulators[node * 6u + 3u + component], to_fixed(weight));
+        }
+    }
+    atomicAdd(&cell_fill[cell_index(clamped_cell(particle.position))], 1u);
+}
+
+// Weighted average of the particle velocities at every face, walls and faces
+// no particle reached stay still
+@compute @workgroup_size(256)
+fn normalize_faces(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.node_count {
+        return;
+    }
+    let node = node_of(index);
+    var velocity = vec3<f32>(0.0);
+    for (var component = 0u; component < 3u; component++) {
+        let weight = from_fixed(atomicLoad(&accumulators[index * 6u + 3u + component]));
+        if weight > 0.0 && open_face(node, component) {
+            let momentum = from_fixed(atomicLoad(&accumulators[index * 6u + component]));
+            velocity[component] = momentum / weight;
+        }
+    }
+    velocities[index] = vec4<f32>(velocity, 0.0);
+    saved[index] = vec4<f32>(velocity, 0.0);
+}
+
+@compute @workgroup_size(256)
+fn compute_divergence(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.cell_count {
+        return;
+    }
+    let cell = cell_of(index);
+    let node = node_index(cell);
+    var divergence = 0.0;
+    if is_fluid(index) {
+        let low = velocities[node].xyz;
+        let high = vec3<f32>(
+            velocities[node_index(cell + vec3<u32>(1u, 0u, 0u))].x,
+            velocities[node_index(cell + vec3<u32>(0u, 1u, 0u))].y,
+            velocities[node_index(cell + vec3<u32>(0u, 0u, 1u))].z,
+        );
+        let flux = high - low;
+        divergence = (flux.x + flux.y + flux.z) / grid.cell_size;
+    }
+    saved[node].w = divergence;
+}
+
+// One Gauss-Seidel update of a fluid cell's pressure, whose gradient takes
+// the divergence out of its faces. Walls are solid and keep no gradient
+// through them, air cells hold zero pressure.
+fn relax(index: u32) {
+    let cell = vec3<i32>(cell_of(index));
+    var sum = 0.0;
+    var open = 0.0;
+    for (var axis = 0u; axis < 3u; axis++) {
+        for (var side = -1; side <= 1; side += 2) {
+            var neighbor = cell;
+            neighbor[axis] += side;
+            if neighbor[axis] < 0 || neighbor[axis] >= i32(grid.cells[axis]) {
+                continue;
+            }
+            open += 1.0;
+            let neighbor_index = cell_index(vec3<u32>(neighbor));
+            if is_fluid(neighbor_index) {
+                sum += pressure[neighbor_index];
+            }
+        }
+    }
+    let divergence = saved[node_index(vec3<u32>(cell))].w;
+    let target_pressure = (sum - grid.cell_size * grid.cell_size * divergence) / max(open, 1.0);
+    pressure[index] = mix(pressure[index], target_pressure, OVER_RELAXATION);
+}
+
+// The fluid cells of one color of the checkerboard, their neighbors all
+// being of the other
+fn relax_color(index: u32, color: u32) {
+    if index >= grid.cell_count || !is_fluid(index) {
+        return;
+    }
+    let cell = cell_of(index);
+    if (cell.x + cell.y + cell.z) % 2u == color {
+        relax(index);
+    }
+}
+
+@compute @workgroup_size(256)
+fn relax_red(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    relax_color(invocation_index(global_id, num_workgroups), 0u);
+}
+
+@compute @workgroup_size(256)
+fn relax_black(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    relax_color(invocation_index(global_id, num_workgroups), 1u);
+}
+
+@compute @workgroup_size(256)
+fn subtract_gradient(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.node_count {
+        return;
+    }
+    let node = node_of(index);
+    var velocity = velocities[index];
+    for (var component = 0u; component < 3u; component++) {
+        if !open_face(node, component) {
+            continue;
+        }
+        var low = node;
+        low[component] -= 1u;
+        let high_cell = cell_index(node);
+        let low_cell = cell_index(low);
+        let high_fluid = is_fluid(high_cell);
+        let low_fluid = is_fluid(low_cell);
+        if high_fluid || low_fluid {
+            let high_pressure = select(0.0, pressure[high_cell], high_fluid);
+            let low_pressure = select(0.0, pressure[low_cell], low_fluid);
+            velocity[component] -= (high_pressure - low_pressure) / grid.cell_size;
+        }
+    }
+    velocities[index] = velocity;
+}
+
+// Blends the grid's velocity (PIC) with the particle's own changed by as much
+// as the grid's changed (FLIP) by `flip_ratio`
+@compute @workgroup_size(256)
+fn grid_to_particle(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+    let position = particles[index].position;
+    var pic = vec3<f32>(0.0);
+    var change = vec3<f32>(0.0);
+    for (var component = 0u; component < 3u; component++) {
+        let stencil = face_stencil(position, component);
+        for (var corner = 0u; corner < 8u; corner++) {
+            let offset = corner_offset(corner);
+            let weight = corner_weight(stencil.frac, offset);
+            if weight <= 0.0 {
+                continue;
+            }
+            let node = node_index(stencil.base + offset);
+            let velocity = velocities[node][component];
+            pic[component] += weight * velocity;
+            change[component] += weight * (velocity - saved[node][component]);
+        }
+    }
+    let flip = particles[index].velocity + change;
+    particles[index].velocity = mix(pic, flip, params.flip_ratio);
+}
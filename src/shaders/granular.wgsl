@@ -0,0 +1,147 @@
+// `Particle`, `SimParams`, `GridParams` and `Contact` are generated from their
+// Rust declarations and prepended along with the lookups in grid_common.wgsl
+// and the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+// Keep in sync with simulation/collisions.rs
+const MAX_CONTACTS: u32 = 8u;
+const TANGENTIAL_SHARE: f32 = 2.0 / 7.0;
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so both sides of a contact
+// see the same pair
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+// `MAX_CONTACTS` slots of every particle, slots past its last contact zeroed
+@group(0) @binding(3)
+var<storage, read_write> contacts: array<Contact>;
+
+// Angular velocity of every particle
+@group(0) @binding(4)
+var<storage, read_write> spins: array<vec4<f32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> spin_snapshot: array<vec4<f32>>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+fn clamp_length(value: vec3<f32>, most: f32) -> vec3<f32> {
+    let size = length(value);
+    if size > most {
+        return value * (most / size);
+    }
+    return value;
+}
+
+// Keep in sync with `GranularContacts::resolve` in simulation/collisions.rs.
+// The grid cells are at least a diameter large so the surrounding 27 cover
+// it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let velocity = snapshot[index].velocity;
+    let mass = snapshot[index].mass;
+    let spin = spin_snapshot[index].xyz;
+    let center_cell = grid_cell_of(position);
+    let radius = params.collision_radius;
+    let diameter = 2.0 * radius;
+    let diameter_sq = diameter * diameter;
+    let delta_time = max(params.delta_time, 1e-6);
+    let first_contact = index * MAX_CONTACTS;
+
+    var displacement = vec3<f32>(0.0);
+    var impulse = vec3<f32>(0.0);
+    var spin_change = vec3<f32>(0.0);
+    var touching: array<Contact, MAX_CONTACTS>;
+    var touching_count = 0u;
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(position, snapshot[other].position);
+            let dist_sq = dot(offset, offset);
+            if other == index || dist_sq >= diameter_sq || dist_sq == 0.0 {
+                continue;
+            }
+            let dist = sqrt(dist_sq);
+            let normal = offset / dist;
+            let share = snapshot[other].mass / (mass + snapshot[other].mass);
+            let overlap = diameter - dist;
+            displacement += normal * (overlap * share);
+
+            // The push apart as a speed, so resting contacts that
+            // don't approach still grip
+            let relative = velocity - snapshot[other].velocity;
+            let approach = dot(relative, normal);
+            var push = overlap * share / delta_time;
+            if approach < 0.0 {
+                let bounce = -approach * (1.0 + params.restitution) * share;
+                impulse += normal * bounce;
+                push += bounce;
+            }
+
+            // Sliding of the surfaces where they touch, spins included
+            let other_spin = spin_snapshot[other].xyz;
+            let surface = relative - radius * cross(spin + other_spin, normal);
+            let slip = surface - normal * dot(surface, normal);
+            var stored = vec3<f32>(0.0);
+            for (var c = 0u; c < MAX_CONTACTS; c++) {
+                let contact = contacts[first_contact + c];
+                if contact.other == other {
+                    stored = contact.spring;
+                    break;
+                }
+            }
+            var spring = stored - normal * dot(stored, normal) + slip * delta_time;
+            let stiffness = share * TANGENTIAL_SHARE / delta_time;
+            var grip = -spring * stiffness;
+            let limit = params.contact_friction * push;
+            let grip_length = length(grip);
+            if grip_length > limit {
+                grip *= limit / grip_length;
+                spring = -grip / stiffness;
+            }
+            impulse += grip;
+            spin_change -= cross(normal, grip) * (2.5 / radius);
+
+            let rolling = (spin - other_spin) * share;
+            let most = 2.5 * params.rolling_resistance * push / radius;
+            spin_change -= clamp_length(rolling, most);
+
+            if touching_count < MAX_CONTACTS {
+                touching[touching_count] = Contact(spring, other);
+                touching_count++;
+            }
+        }
+    }
+
+    particles[index].position = position + displacement;
+    particles[index].velocity = velocity + impulse;
+    spins[index] = vec4<f32>(spin + spin_change, 0.0);
+    for (var c = 0u; c < MAX_CONTACTS; c++) {
+        contacts[first_contact + c] = touching[c];
+    }
+}
@@ -0,0 +1,57 @@
+// `Particle` and `GridParams` are generated from their Rust declarations and
+// prepended along with the lookups in grid_common.wgsl and the index helpers
+// in dispatch.wgsl when the shader module is created
+
+@group(0) @binding(0)
+var<storage, read> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> grid: GridParams;
+
+@group(0) @binding(2)
+var<storage, read_write> cell_counts: array<atomic<u32>>;
+
+// Exclusive prefix sum of the counts, `table_size + 1` entries
+@group(0) @binding(3)
+var<storage, read> cell_start: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> particle_cells: array<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> cell_entries: array<u32>;
+
+// The dead wait on the emitter out of every neighborhood, see `DEAD` in
+// simulation/mod.rs
+fn is_dead(index: u32) -> bool {
+    return particles[index].lifetime < 0.0;
+}
+
+@compute @workgroup_size(256)
+fn count_cells(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count || is_dead(index) {
+        return;
+    }
+    let hash = grid_hash_cell(grid_cell_of(particles[index].position));
+    particle_cells[index] = hash;
+    atomicAdd(&cell_counts[hash], 1u);
+}
+
+// Counts down each bucket's count to find free slots, leaving them at zero
+@compute @workgroup_size(256)
+fn reorder(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count || is_dead(index) {
+        return;
+    }
+    let hash = particle_cells[index];
+    let slot = cell_start[hash] + atomicSub(&cell_counts[hash], 1u) - 1u;
+    cell_entries[slot] = index;
+}
@@ -0,0 +1,63 @@
+// Spatial hash grid lookups, `GridParams` is generated from its Rust
+// declaration in simulation/gpu_grid.rs and the shader binds it as `grid`
+
+// Keep in sync with `SpatialGrid::cell_of` in simulation/grid.rs
+fn grid_cell_of(position: vec3<f32>) -> vec3<i32> {
+    if grid.periodic == 1u {
+        return vec3<i32>(floor((position + grid.half_extents) / grid.cell_size));
+    }
+    return vec3<i32>(floor(position / grid.cell_size));
+}
+
+// Keep in sync with `SpatialGrid::hash_cell` in simulation/grid.rs
+fn grid_hash_cell(cell: vec3<i32>) -> u32 {
+    var wrapped = cell;
+    if grid.periodic == 1u {
+        let cells = vec3<i32>(grid.cells_per_axis);
+        wrapped = ((cell % cells) + cells) % cells;
+    }
+    let h = (bitcast<u32>(wrapped.x) * 73856093u)
+        ^ (bitcast<u32>(wrapped.y) * 19349663u)
+        ^ (bitcast<u32>(wrapped.z) * 83492791u);
+    return h & (grid.table_size - 1u);
+}
+
+// Keep in sync with `SpatialGrid::separation` in simulation/grid.rs
+fn grid_separation(a: vec3<f32>, b: vec3<f32>) -> vec3<f32> {
+    let offset = a - b;
+    if grid.periodic == 1u {
+        let box_size = grid.half_extents * 2.0;
+        return offset - box_size * round(offset / box_size);
+    }
+    return offset;
+}
+
+// The distinct buckets of the 27 cells around a cell. Neighboring cells can
+// share a bucket, or wrap onto the same cell in a periodic box, and a walk
+// over the cells would see their particles twice.
+struct GridNeighborhood {
+    hashes: array<u32, 27>,
+    count: u32,
+};
+
+// Keep in sync with `SpatialGrid::for_each_neighbor` in simulation/grid.rs
+fn grid_neighborhood(cell: vec3<i32>) -> GridNeighborhood {
+    var neighborhood: GridNeighborhood;
+    neighborhood.count = 0u;
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let hash = grid_hash_cell(cell + vec3<i32>(x, y, z));
+                var seen = false;
+                for (var i = 0u; i < neighborhood.count; i++) {
+                    seen = seen || neighborhood.hashes[i] == hash;
+                }
+                if !seen {
+                    neighborhood.hashes[neighborhood.count] = hash;
+                    neighborhood.count++;
+                }
+            }
+        }
+    }
+    return neighborhood;
+}
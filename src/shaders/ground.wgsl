@@ -0,0 +1,36 @@
+// `Camera` is bound from camera.wgsl, prepended when the shader module is
+// created
+
+struct Ground {
+    height: f32,
+    // Distance between grid lines
+    spacing: f32,
+    color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> ground: Ground;
+
+// Keep in sync with `GROUND_LINES` in renderer.rs
+const LINES: u32 = 41u;
+
+// Two vertices per line, the first `LINES` lines run along x and the rest
+// along z, centered on the origin
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let line = vertex_index / 2u;
+    let half_size = f32(LINES - 1u) * 0.5 * ground.spacing;
+    let across = f32(line % LINES) * ground.spacing - half_size;
+    let along = (f32(vertex_index % 2u) * 2.0 - 1.0) * half_size;
+
+    var position = vec3<f32>(along, ground.height, across);
+    if line >= LINES {
+        position = vec3<f32>(across, ground.height, along);
+    }
+    return camera.view_proj * vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return ground.color;
+}
@@ -0,0 +1,57 @@
+// `Particle` and `SimParams` are generated from their Rust declarations and
+// prepended along with the index helpers in dispatch.wgsl when the shader
+// module is created
+
+@group(0) @binding(0)
+var<storage, read> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// Largest distance and speed of the finite particles as float bits, which
+// order like the floats since they're positive, then the number of
+// particles that aren't finite
+@group(0) @binding(2)
+var<storage, read_write> stats: array<atomic<u32>, 3>;
+
+var<workgroup> group_distance: atomic<u32>;
+var<workgroup> group_speed: atomic<u32>;
+var<workgroup> group_non_finite: atomic<u32>;
+
+const EXPONENT_MASK: u32 = 0x7f800000u;
+
+// NaN and infinity have every exponent bit set, unlike any finite float.
+// Comparing the value with itself could be optimized away.
+fn is_finite(v: vec3<f32>) -> bool {
+    let exponent = bitcast<vec3<u32>>(v) & vec3<u32>(EXPONENT_MASK);
+    return all(exponent != vec3<u32>(EXPONENT_MASK));
+}
+
+// Keep in sync with `measure` in simulation/health.rs. Every workgroup
+// reduces into shared memory first so the global atomics see one write per
+// workgroup instead of one per particle.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index < params.particle_count {
+        let position = particles[index].position;
+        let velocity = particles[index].velocity;
+        if is_finite(position) && is_finite(velocity) {
+            atomicMax(&group_distance, bitcast<u32>(length(position)));
+            atomicMax(&group_speed, bitcast<u32>(length(velocity)));
+        } else {
+            atomicAdd(&group_non_finite, 1u);
+        }
+    }
+    workgroupBarrier();
+
+    if local_id.x == 0u {
+        atomicMax(&stats[0], atomicLoad(&group_distance));
+        atomicMax(&stats[1], atomicLoad(&group_speed));
+        atomicAdd(&stats[2], atomicLoad(&group_non_finite));
+    }
+}
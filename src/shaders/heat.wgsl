@@ -0,0 +1,64 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so heat flows both ways
+// between a pair
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Keep in sync with `conduct_heat` in simulation/heat.rs. The grid cells are
+// at least the contact radius large so the surrounding 27 cover it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let temperature = snapshot[index].temperature;
+    let center_cell = grid_cell_of(position);
+    let radius_sq = params.contact_radius * params.contact_radius;
+    let rate = clamp(params.conduction * params.delta_time, 0.0, 1.0);
+
+    var sum = 0.0;
+    var contacts = 0u;
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(position, snapshot[other].position);
+            if other != index && dot(offset, offset) < radius_sq {
+                sum += snapshot[other].temperature;
+                contacts++;
+            }
+        }
+    }
+
+    if contacts > 0u {
+        let mean = sum / f32(contacts);
+        particles[index].temperature = temperature + (mean - temperature) * rate;
+    }
+}
@@ -0,0 +1,312 @@
+// `Particle`, `SimParams`, `BackgroundGrid` and `MaterialPoint` are generated
+// from their Rust declarations and prepended along with the helpers in
+// background_grid.wgsl and dispatch.wgsl when the shader module is created
+
+// Keep in sync with simulation/mpm.rs
+const MATERIAL_SNOW: u32 = 0u;
+const MATERIAL_SAND: u32 = 1u;
+
+// Share of a cell a stress wave crosses per step. The explicit step holds no
+// stiffer material, so Young's modulus follows from it.
+const WAVE_COURANT: f32 = 0.25;
+const POISSON_RATIO: f32 = 0.2;
+// Squash and stretch snow takes elastically before it yields
+const CRITICAL_COMPRESSION: f32 = 2.5e-2;
+const CRITICAL_STRETCH: f32 = 7.5e-3;
+// Bounds on how much packing stiffens or loosening softens snow, the stiffest
+// still fitting the step
+const MIN_HARDENING: f32 = 0.1;
+const MAX_HARDENING: f32 = 4.0;
+// Sweeps of the singular value decomposition, plenty for deformations that
+// change a little per step
+const JACOBI_SWEEPS: u32 = 6u;
+
+const IDENTITY: mat3x3<f32> = mat3x3<f32>(
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+);
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+@group(0) @binding(2)
+var<uniform> grid: BackgroundGrid;
+
+@group(0) @binding(3)
+var<storage, read_write> points: array<MaterialPoint>;
+
+// Momentum and mass of every node, as fixed point
+@group(0) @binding(4)
+var<storage, read_write> accumulators: array<atomic<i32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> velocities: array<vec4<f32>>;
+
+// First of the 3x3x3 nodes around a particle and how far past it the
+// particle is, in cells
+struct Stencil {
+    base: vec3<u32>,
+    frac: vec3<f32>,
+};
+
+// The nodes sit at whole grid positions, the stencil spans the three nearest
+// along every axis. Particles are kept a cell in from the sides so it stays
+// on the grid.
+fn stencil_of(position: vec3<f32>) -> Stencil {
+    let scaled = clamp(grid_position(position), vec3<f32>(1.0), vec3<f32>(grid.cells) - 1.0);
+    let base = floor(scaled - 0.5);
+    return Stencil(vec3<u32>(base), scaled - base);
+}
+
+// Quadratic B-spline weights of the three nodes along every axis
+fn quadratic_weights(frac: vec3<f32>) -> array<vec3<f32>, 3> {
+    return array<vec3<f32>, 3>(
+        0.5 * (1.5 - frac) * (1.5 - frac),
+        0.75 - (frac - 1.0) * (frac - 1.0),
+        0.5 * (frac - 0.5) * (frac - 0.5),
+    );
+}
+
+fn stencil_offset(node: u32) -> vec3<u32> {
+    return vec3<u32>(node % 3u, (node / 3u) % 3u, node / 9u);
+}
+
+fn outer(a: vec3<f32>, b: vec3<f32>) -> mat3x3<f32> {
+    return mat3x3<f32>(a * b.x, a * b.y, a * b.z);
+}
+
+fn diagonal(values: vec3<f32>) -> mat3x3<f32> {
+    return mat3x3<f32>(
+        vec3<f32>(values.x, 0.0, 0.0),
+        vec3<f32>(0.0, values.y, 0.0),
+        vec3<f32>(0.0, 0.0, values.z),
+    );
+}
+
+// APIC's scale of the velocity gradient for quadratic weights
+fn inverse_inertia() -> f32 {
+    return 4.0 / (grid.cell_size * grid.cell_size);
+}
+
+// Lamé parameters of the stiffest material the step holds, scaled. Particles
+// weigh 1 and their stress is kept times their volume, so only the ratio of
+// stiffness to density matters.
+fn lame(scale: f32) -> vec2<f32> {
+    let wave_speed = WAVE_COURANT * grid.cell_size / max(params.delta_time, 1e-6);
+    let young = wave_speed * wave_speed * scale;
+    let shear = young / (2.0 * (1.0 + POISSON_RATIO));
+    let bulk = young * POISSON_RATIO / ((1.0 + POISSON_RATIO) * (1.0 - 2.0 * POISSON_RATIO));
+    return vec2<f32>(shear, bulk);
+}
+
+struct Svd {
+    u: mat3x3<f32>,
+    sigma: vec3<f32>,
+    v: mat3x3<f32>,
+};
+
+// Zeroes the `p`, `q` entry of the symmetric `a` with a Jacobi rotation,
+// gathering the rotations in `v`
+fn jacobi_rotate(a: ptr<function, mat3x3<f32>>, v: ptr<function, mat3x3<f32>>, p: u32, q: u32) {
+    let off_diagonal = (*a)[q][p];
+    if abs(off_diagonal) < 1e-12 {
+        return;
+    }
+    let theta = ((*a)[q][q] - (*a)[p][p]) / (2.0 * off_diagonal);
+    let t = select(-1.0, 1.0, theta >= 0.0) / (abs(theta) + sqrt(theta * theta + 1.0));
+    let c = inverseSqrt(t * t + 1.0);
+    let s = t * c;
+    var rotation = IDENTITY;
+    rotation[p][p] = c;
+    rotation[q][q] = c;
+    rotation[q][p] = s;
+    rotation[p][q] = -s;
+    *a = transpose(rotation) * *a * rotation;
+    *v = *v * rotation;
+}
+
+fn any_orthogonal(direction: vec3<f32>) -> vec3<f32> {
+    let other = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(direction.x) > 0.9);
+    return normalize(cross(direction, other));
+}
+
+// `f` as rotation, scale and rotation. The smallest singular value comes last
+// and is negative when `f` turns the particle inside out.
+fn svd3(f: mat3x3<f32>) -> Svd {
+    var a = transpose(f) * f;
+    var v = IDENTITY;
+    for (var sweep = 0u; sweep < JACOBI_SWEEPS; sweep++) {
+        jacobi_rotate(&a, &v, 0u, 1u);
+        jacobi_rotate(&a, &v, 0u, 2u);
+        jacobi_rotate(&a, &v, 1u, 2u);
+    }
+    var eigen = vec3<f32>(a[0][0], a[1][1], a[2][2]);
+    if eigen.x < eigen.z {
+        eigen = eigen.zyx;
+        v = mat3x3<f32>(v[2], v[1], v[0]);
+    }
+    if eigen.y < eigen.z {
+        eigen = eigen.xzy;
+        v = mat3x3<f32>(v[0], v[2], v[1]);
+    }
+    if determinant(v) < 0.0 {
+        v[2] = -v[2];
+    }
+
+    var first = f * v[0];
+    let sigma_x = length(first);
+    first = select(vec3<f32>(1.0, 0.0, 0.0), first / sigma_x, sigma_x > 1e-6);
+    var second = f * v[1];
+    second -= dot(second, first) * first;
+    let sigma_y = length(second);
+    second = select(any_orthogonal(first), second / sigma_y, sigma_y > 1e-6);
+    let third = cross(first, second);
+    let sigma_z = dot(third, f * v[2]);
+    return Svd(mat3x3<f32>(first, second, third), vec3<f32>(sigma_x, sigma_y, sigma_z), v);
+}
+
+// Clamps the stretch to what snow holds elastically, the rest changing its
+// volume for good, and finds the fixed corotated stress of what's left
+fn yield_snow(svd: Svd, point: ptr<function, MaterialPoint>) {
+    let sigma = clamp(
+        svd.sigma,
+        vec3<f32>(1.0 - CRITICAL_COMPRESSION),
+        vec3<f32>(1.0 + CRITICAL_STRETCH),
+    );
+    let volume = svd.sigma.x * svd.sigma.y * svd.sigma.z;
+    let elastic_volume = sigma.x * sigma.y * sigma.z;
+    (*point).plastic_volume = clamp((*point).plastic_volume * volume / elastic_volume, 0.05, 20.0);
+    let deformation = svd.u * diagonal(sigma) * transpose(svd.v);
+    let hardening = clamp(
+        exp(params.hardening * (1.0 - (*point).plastic_volume)),
+        MIN_HARDENING,
+        MAX_HARDENING,
+    );
+    let moduli = lame(hardening);
+    let rotation = svd.u * transpose(svd.v);
+    (*point).deformation = deformation;
+    (*point).stress = 2.0 * moduli.x * (deformation - rotation) * transpose(deformation)
+        + moduli.y * elastic_volume * (elastic_volume - 1.0) * IDENTITY;
+}
+
+// Projects the logarithmic strain onto the Drucker-Prager cone: pulled apart
+// the sand lets go entirely, squeezed it shears freely past what its friction
+// holds. The Hencky stress of what's left follows.
+fn yield_sand(svd: Svd, point: ptr<function, MaterialPoint>) {
+    let moduli = lame(1.0);
+    var strain = log(max(svd.sigma, vec3<f32>(1e-4)));
+    let trace = strain.x + strain.y + strain.z;
+    let deviatoric = strain - trace / 3.0;
+    let shear = length(deviatoric);
+    if trace >= 0.0 {
+        strain = vec3<f32>(0.0);
+    } else {
+        let sin_friction = sin(params.friction_angle);
+        let friction = sqrt(2.0 / 3.0) * 2.0 * sin_friction / (3.0 - sin_friction);
+        let excess = shear + (3.0 * moduli.y + 2.0 * moduli.x) / (2.0 * moduli.x) * trace * friction;
+        if excess > 0.0 && shear > 0.0 {
+            strain -= excess * deviatoric / shear;
+        }
+    }
+    let principal = 2.0 * moduli.x * strain + moduli.y * (strain.x + strain.y + strain.z);
+    (*point).deformation = svd.u * diagonal(exp(strain)) * transpose(svd.v);
+    (*point).stress = svd.u * diagonal(principal) * transpose(svd.u);
+}
+
+// Scatters the particles' momentum onto the nodes, the stress of their
+// deformation folded into their affine velocity (MLS-MPM)
+@compute @workgroup_size(256)
+fn particle_to_grid(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+    let particle = particles[index];
+    let point = points[index];
+    let stencil = stencil_of(particle.position);
+    var weights = quadratic_weights(stencil.frac);
+    let affine = point.affine - params.delta_time * inverse_inertia() * point.stress;
+    for (var node = 0u; node < 27u; node++) {
+        let offset = stencil_offset(node);
+        let weight = weights[offset.x].x * weights[offset.y].y * weights[offset.z].z;
+        let delta = (vec3<f32>(offset) - stencil.frac) * grid.cell_size;
+        let momentum = weight * (particle.velocity + affine * delta);
+        let slot = node_index(stencil.base + offset) * 4u;
+        atomicAdd(&accumulators[slot], to_fixed(momentum.x));
+        atomicAdd(&accumulators[slot + 1u], to_fixed(momentum.y));
+        atomicAdd(&accumulators[slot + 2u], to_fixed(momentum.z));
+        atomicAdd(&accumulators[slot + 3u], to_fixed(weight));
+    }
+}
+
+// Velocities of the nodes, the ones along the sides of the grid free to
+// slide along the walls or leave them but not to push into them
+@compute @workgroup_size(256)
+fn update_grid(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.node_count {
+        return;
+    }
+    let slot = index * 4u;
+    let mass = from_fixed(atomicLoad(&accumulators[slot + 3u]));
+    var velocity = vec3<f32>(0.0);
+    if mass > 0.0 {
+        let momentum = vec3<f32>(
+            from_fixed(atomicLoad(&accumulators[slot])),
+            from_fixed(atomicLoad(&accumulators[slot + 1u])),
+            from_fixed(atomicLoad(&accumulators[slot + 2u])),
+        );
+        velocity = momentum / mass;
+        let node = node_of(index);
+        velocity = select(velocity, max(velocity, vec3<f32>(0.0)), node < vec3<u32>(2u));
+        velocity = select(velocity, min(velocity, vec3<f32>(0.0)), node + 2u > grid.cells);
+    }
+    velocities[index] = vec4<f32>(velocity, 0.0);
+}
+
+// Gathers the particles' velocities and velocity gradients from the nodes,
+// deforms them by the gradient and lets what the material can't hold yield
+@compute @workgroup_size(256)
+fn grid_to_particle(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+    let stencil = stencil_of(particles[index].position);
+    var weights = quadratic_weights(stencil.frac);
+    var velocity = vec3<f32>(0.0);
+    var gradient = mat3x3<f32>(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+    for (var node = 0u; node < 27u; node++) {
+        let offset = stencil_offset(node);
+        let weight = weights[offset.x].x * weights[offset.y].y * weights[offset.z].z;
+        let delta = (vec3<f32>(offset) - stencil.frac) * grid.cell_size;
+        let node_velocity = velocities[node_index(stencil.base + offset)].xyz;
+        velocity += weight * node_velocity;
+        gradient += (weight * inverse_inertia()) * outer(node_velocity, delta);
+    }
+    particles[index].velocity = velocity;
+
+    var point = points[index];
+    point.affine = gradient;
+    let deformation = (IDENTITY + params.delta_time * gradient) * point.deformation;
+    let svd = svd3(deformation);
+    if params.mpm_material == MATERIAL_SAND {
+        yield_sand(svd, &point);
+    } else {
+        yield_snow(svd, &point);
+    }
+    points[index] = point;
+}
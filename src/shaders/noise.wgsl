@@ -0,0 +1,79 @@
+// Simplex noise and the curl noise built on it, prepended to the shaders
+// that use them
+
+// Keep in sync with `SIMPLEX_SCALE` in simulation/noise.rs
+const SIMPLEX_SCALE: f32 = 70.0;
+
+// Keep in sync with `lattice_hash` in simulation/noise.rs
+fn noise_lattice_hash(cell: vec3<i32>) -> u32 {
+    let c = bitcast<vec3<u32>>(cell);
+    var h = c.x * 0x9E3779B9u + c.y * 0x85EBCA6Bu + c.z * 0xC2B2AE35u;
+    h ^= h >> 16u;
+    h *= 0x7FEB352Du;
+    h ^= h >> 15u;
+    h *= 0x846CA68Bu;
+    h ^= h >> 16u;
+    return h;
+}
+
+// Keep in sync with `lattice_gradient` in simulation/noise.rs
+fn noise_lattice_gradient(cell: vec3<i32>) -> vec3<f32> {
+    // The 12 edge midpoints of a cube
+    switch noise_lattice_hash(cell) % 12u {
+        case 0u: { return vec3<f32>(1.0, 1.0, 0.0); }
+        case 1u: { return vec3<f32>(-1.0, 1.0, 0.0); }
+        case 2u: { return vec3<f32>(1.0, -1.0, 0.0); }
+        case 3u: { return vec3<f32>(-1.0, -1.0, 0.0); }
+        case 4u: { return vec3<f32>(1.0, 0.0, 1.0); }
+        case 5u: { return vec3<f32>(-1.0, 0.0, 1.0); }
+        case 6u: { return vec3<f32>(1.0, 0.0, -1.0); }
+        case 7u: { return vec3<f32>(-1.0, 0.0, -1.0); }
+        case 8u: { return vec3<f32>(0.0, 1.0, 1.0); }
+        case 9u: { return vec3<f32>(0.0, -1.0, 1.0); }
+        case 10u: { return vec3<f32>(0.0, 1.0, -1.0); }
+        default: { return vec3<f32>(0.0, -1.0, -1.0); }
+    }
+}
+
+// 3D simplex noise, xyz = its gradient and w = its value
+// Keep in sync with `simplex_noise` in simulation/noise.rs
+fn simplex_noise(position: vec3<f32>) -> vec4<f32> {
+    let skew = (position.x + position.y + position.z) / 3.0;
+    let cell = floor(position + skew);
+    let unskew = (cell.x + cell.y + cell.z) / 6.0;
+    let x0 = position - cell + unskew;
+
+    // The simplex is picked by the order of the offset's components
+    let g = step(x0.yzx, x0.xyz);
+    let l = 1.0 - g;
+    let i1 = min(g, l.zxy);
+    let i2 = max(g, l.zxy);
+
+    let corners = array<vec3<f32>, 4>(vec3<f32>(0.0), i1, i2, vec3<f32>(1.0));
+    var result = vec4<f32>(0.0);
+    for (var corner = 0u; corner < 4u; corner++) {
+        let offset = x0 - corners[corner] + f32(corner) / 6.0;
+        let falloff = 0.5 - dot(offset, offset);
+        if falloff <= 0.0 {
+            continue;
+        }
+        let gradient = noise_lattice_gradient(vec3<i32>(cell + corners[corner]));
+        let ramp = dot(gradient, offset);
+        let f2 = falloff * falloff;
+        let f4 = f2 * f2;
+        result += vec4<f32>(
+            f4 * gradient - 8.0 * f2 * falloff * ramp * offset,
+            f4 * ramp,
+        );
+    }
+    return result * SIMPLEX_SCALE;
+}
+
+// Curl of three decorrelated noises, a divergence-free field
+// Keep in sync with `curl_noise` in simulation/noise.rs
+fn curl_noise(position: vec3<f32>) -> vec3<f32> {
+    let x = simplex_noise(position).xyz;
+    let y = simplex_noise(position + vec3<f32>(31.4, -17.2, 5.9)).xyz;
+    let z = simplex_noise(position + vec3<f32>(-8.3, 23.7, -41.1)).xyz;
+    return vec3<f32>(z.y - y.z, x.z - z.x, y.x - x.y);
+}
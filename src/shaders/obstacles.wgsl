@@ -0,0 +1,110 @@
+// `Particle`, `SimParams` and `GpuObstacle` are generated from their Rust
+// declarations and prepended along with the index helpers in dispatch.wgsl
+// when the shader module is created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+@group(0) @binding(2)
+var<storage, read> obstacles: array<GpuObstacle>;
+
+// Signed distance to the loaded mesh over [-1, 1]³, x fastest
+@group(0) @binding(3)
+var<storage, read> mesh_sdf: array<f32>;
+
+// Keep in sync with `RESOLUTION` in simulation/mesh_sdf.rs
+const MESH_SDF_RESOLUTION: u32 = 48u;
+
+fn mesh_sample(cell: vec3<u32>) -> f32 {
+    return mesh_sdf[cell.x + MESH_SDF_RESOLUTION * (cell.y + MESH_SDF_RESOLUTION * cell.z)];
+}
+
+// Keep in sync with `MeshSdf::sample` in simulation/mesh_sdf.rs
+fn mesh_distance(position: vec3<f32>) -> f32 {
+    let inside = clamp(position, vec3<f32>(-1.0), vec3<f32>(1.0));
+    let last = f32(MESH_SDF_RESOLUTION - 1u);
+    let cell_size = 2.0 / last;
+    let grid = clamp((inside + 1.0) / cell_size, vec3<f32>(0.0), vec3<f32>(last));
+    let base = min(floor(grid), vec3<f32>(last - 1.0));
+    let t = grid - base;
+    let b = vec3<u32>(base);
+
+    let x00 = mix(mesh_sample(b), mesh_sample(b + vec3<u32>(1u, 0u, 0u)), t.x);
+    let x10 = mix(mesh_sample(b + vec3<u32>(0u, 1u, 0u)), mesh_sample(b + vec3<u32>(1u, 1u, 0u)), t.x);
+    let x01 = mix(mesh_sample(b + vec3<u32>(0u, 0u, 1u)), mesh_sample(b + vec3<u32>(1u, 0u, 1u)), t.x);
+    let x11 = mix(mesh_sample(b + vec3<u32>(0u, 1u, 1u)), mesh_sample(b + vec3<u32>(1u, 1u, 1u)), t.x);
+    let distance = mix(mix(x00, x10, t.y), mix(x01, x11, t.y), t.z);
+    return distance + length(position - inside);
+}
+
+// Keep in sync with `Obstacle::distance` in simulation/obstacles.rs
+fn obstacle_distance(obstacle: GpuObstacle, position: vec3<f32>) -> f32 {
+    let p = position - obstacle.center;
+    let size = obstacle.size;
+    switch obstacle.shape {
+        case 0u: {
+            return length(p) - size.x;
+        }
+        case 1u: {
+            let q = abs(p) - size;
+            return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+        }
+        case 2u: {
+            return length(vec2<f32>(length(p.xz) - size.x, p.y)) - size.y;
+        }
+        case 3u: {
+            return length(p - vec3<f32>(0.0, clamp(p.y, -size.y, size.y), 0.0)) - size.x;
+        }
+        default: {
+            return mesh_distance(p / size.x) * size.x;
+        }
+    }
+}
+
+// Keep in sync with `Obstacle::normal` in simulation/obstacles.rs
+fn obstacle_normal(obstacle: GpuObstacle, position: vec3<f32>) -> vec3<f32> {
+    let e = vec2<f32>(1e-3, 0.0);
+    let gradient = vec3<f32>(
+        obstacle_distance(obstacle, position + e.xyy) - obstacle_distance(obstacle, position - e.xyy),
+        obstacle_distance(obstacle, position + e.yxy) - obstacle_distance(obstacle, position - e.yxy),
+        obstacle_distance(obstacle, position + e.yyx) - obstacle_distance(obstacle, position - e.yyx),
+    );
+    let length_sq = dot(gradient, gradient);
+    if length_sq == 0.0 {
+        return vec3<f32>(0.0, 1.0, 0.0);
+    }
+    return gradient * inverseSqrt(length_sq);
+}
+
+// Keep in sync with `resolve_obstacles` in simulation/obstacles.rs
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= params.particle_count {
+        return;
+    }
+
+    var position = particles[index].position;
+    var velocity = particles[index].velocity;
+    for (var i = 0u; i < params.obstacle_count; i++) {
+        let obstacle = obstacles[i];
+        let distance = obstacle_distance(obstacle, position);
+        if distance >= 0.0 {
+            continue;
+        }
+        let normal = obstacle_normal(obstacle, position);
+        position -= normal * distance;
+        let into = dot(velocity, normal);
+        if into < 0.0 {
+            velocity -= normal * (into * (1.0 + params.obstacle_restitution));
+        }
+    }
+    particles[index].position = position;
+    particles[index].velocity = velocity;
+}
@@ -0,0 +1,66 @@
+// Resolves the six faces of a cubemap capture into an equirectangular or a
+// fisheye dome image
+
+struct PanoramaUniform {
+    // Horizontal direction the middle of the image looks in
+    forward: vec4<f32>,
+    right: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> panorama: PanoramaUniform;
+
+@group(0) @binding(1)
+var faces: texture_cube<f32>;
+
+@group(0) @binding(2)
+var face_sampler: sampler;
+
+@group(0) @binding(3)
+var output: texture_storage_2d<rgba8unorm, write>;
+
+const PI: f32 = 3.14159265;
+
+// Longitude across the width from -π to π, latitude down the height from
+// straight up to straight down
+fn equirect_direction(uv: vec2<f32>) -> vec3<f32> {
+    let longitude = (uv.x * 2.0 - 1.0) * PI;
+    let latitude = (0.5 - uv.y) * PI;
+    let horizontal = sin(longitude) * panorama.right.xyz + cos(longitude) * panorama.forward.xyz;
+    return cos(latitude) * horizontal + vec3<f32>(0.0, sin(latitude), 0.0);
+}
+
+@compute @workgroup_size(8, 8)
+fn equirect(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(size);
+    let color = textureSampleLevel(faces, face_sampler, equirect_direction(uv), 0.0);
+    textureStore(output, global_id.xy, vec4<f32>(color.rgb, 1.0));
+}
+
+// Azimuthal equidistant over the upper hemisphere: the zenith in the middle,
+// the horizon on the inscribed circle, heading along forward at the bottom
+@compute @workgroup_size(8, 8)
+fn fisheye(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let point = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+    let radius = length(point);
+    if radius > 1.0 {
+        textureStore(output, global_id.xy, vec4<f32>(0.0, 0.0, 0.0, 1.0));
+        return;
+    }
+    let from_zenith = radius * PI * 0.5;
+    var horizontal = panorama.forward.xyz;
+    if radius > 0.0 {
+        horizontal = (point.x * panorama.right.xyz + point.y * panorama.forward.xyz) / radius;
+    }
+    let direction = cos(from_zenith) * vec3<f32>(0.0, 1.0, 0.0) + sin(from_zenith) * horizontal;
+    let color = textureSampleLevel(faces, face_sampler, direction, 0.0);
+    textureStore(output, global_id.xy, vec4<f32>(color.rgb, 1.0));
+}
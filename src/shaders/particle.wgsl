@@ -1,17 +1,40 @@
-struct Camera {
-    view_proj: mat4x4<f32>,
-    position: vec4<f32>,
+// `Camera` is bound from camera.wgsl, prepended when the shader module is
+// created
+
+struct Ghost {
+    // xyz = image shift, w = 1 for periodic copies
+    offset: vec4<f32>,
+    // xyz = box half extents, w = how far outside the box copies are drawn
+    box_half_extents: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> ghost: Ghost;
+
+struct Interpolation {
+    // From the previous step at 0 to the current one at 1
+    alpha: f32,
 };
 
-@group(0) @binding(0)
-var<uniform> camera: Camera;
+@group(1) @binding(1)
+var<uniform> interpolation: Interpolation;
+
+// Particles that moved further in one step jumped, e.g. wrapped around the
+// box or respawned, and are drawn where they landed
+const MAX_BLEND_DISTANCE: f32 = 5.0;
+
+// Fraction of their lifetime over which particles fade out at the end
+const FADE_FRACTION: f32 = 0.2;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) padding1: f32,
+    @location(1) species: u32,
     @location(2) velocity: vec3<f32>,
-    @location(3) padding2: f32,
+    @location(3) temperature: f32,
     @location(4) color: vec4<f32>,
+    @location(5) previous_position: vec3<f32>,
+    // Age and lifetime, 0 lifetime lives forever and a negative one is dead
+    @location(6) age: vec2<f32>,
 };
 
 struct VertexOutput {
@@ -26,11 +49,33 @@ fn vs_main(
     @builtin(vertex_index) vertex_index: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * vec4<f32>(vertex.position, 1.0);
+    var position = vertex.position;
+    if distance(vertex.previous_position, position) < MAX_BLEND_DISTANCE {
+        position = mix(vertex.previous_position, position, interpolation.alpha);
+    }
+    let world_position = position + ghost.offset.xyz;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
 
     // Color based on color mode (handled in compute shader)
     out.color = vertex.color;
     out.velocity = vertex.velocity;
+    if vertex.age.y > 0.0 {
+        let remaining = 1.0 - vertex.age.x / vertex.age.y;
+        out.color.a *= clamp(remaining / FADE_FRACTION, 0.0, 1.0);
+    }
+    // The dead wait on the emitter unseen
+    if vertex.age.y < 0.0 {
+        out.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+    }
+
+    // Periodic copies are only kept in a thin shell around the box
+    if ghost.offset.w > 0.0 {
+        let outside = abs(world_position) - ghost.box_half_extents.xyz;
+        if any(outside > vec3<f32>(ghost.box_half_extents.w)) {
+            out.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        }
+        out.color.a *= 0.35;
+    }
 
     return out;
 }
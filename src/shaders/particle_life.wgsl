@@ -0,0 +1,85 @@
+// `Particle`, `SimParams` and `GridParams` are generated from their Rust
+// declarations and prepended along with the lookups in grid_common.wgsl and
+// the shared falloffs.wgsl and dispatch.wgsl when the shader module is
+// created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this pass, so every particle sees the
+// same scene
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+// Attraction of each species (row) to each other one (column), rows one
+// after another
+@group(0) @binding(3)
+var<storage, read> interactions: array<f32>;
+
+@group(1) @binding(0)
+var<uniform> grid: GridParams;
+
+@group(1) @binding(1)
+var<storage, read> cell_start: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> cell_entries: array<u32>;
+
+// Keep in sync with `MAX_SPECIES` in simulation/mod.rs
+const MAX_SPECIES: u32 = 8u;
+// Keep in sync with `PARTICLE_LIFE_REPULSION` in simulation/particle_life.rs
+const PARTICLE_LIFE_REPULSION: f32 = 0.3;
+
+// Keep in sync with `life_force` in simulation/particle_life.rs
+fn life_force(ratio: f32, attraction: f32) -> f32 {
+    if ratio < PARTICLE_LIFE_REPULSION {
+        return ratio / PARTICLE_LIFE_REPULSION - 1.0;
+    }
+    if ratio < 1.0 {
+        let tent = abs(2.0 * ratio - 1.0 - PARTICLE_LIFE_REPULSION) / (1.0 - PARTICLE_LIFE_REPULSION);
+        return attraction * (1.0 - tent);
+    }
+    return 0.0;
+}
+
+// Keep in sync with `apply_particle_life` in simulation/particle_life.rs.
+// The grid cells are at least the radius large so the surrounding 27 cover it.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= grid.particle_count {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let row = (snapshot[index].species % MAX_SPECIES) * MAX_SPECIES;
+    let center_cell = grid_cell_of(position);
+    let radius = params.particle_life_radius;
+    let radius_sq = radius * radius;
+    var acceleration = vec3<f32>(0.0);
+
+    var neighborhood = grid_neighborhood(center_cell);
+    for (var bucket = 0u; bucket < neighborhood.count; bucket++) {
+        let hash = neighborhood.hashes[bucket];
+        for (var slot = cell_start[hash]; slot < cell_start[hash + 1u]; slot++) {
+            let other = cell_entries[slot];
+            let offset = grid_separation(snapshot[other].position, position);
+            let dist_sq = dot(offset, offset);
+            if other == index || dist_sq >= radius_sq || dist_sq == 0.0 {
+                continue;
+            }
+            let distance = sqrt(dist_sq);
+            let attraction = interactions[row + snapshot[other].species % MAX_SPECIES];
+            acceleration += offset / distance * life_force(distance / radius, attraction);
+        }
+    }
+
+    let scale = params.particle_life_strength * radius * params.delta_time;
+    particles[index].velocity = snapshot[index].velocity + acceleration * scale;
+}
@@ -0,0 +1,12 @@
+// Stateless random numbers, the same for the same particle, step and salt
+
+// Keep in sync with `random_unit` in simulation/mod.rs
+fn random_unit(index: u32, step: u32, salt: u32) -> f32 {
+    var h = index * 0x9E3779B9u + step * 0x85EBCA6Bu + salt * 0xC2B2AE35u;
+    h ^= h >> 16u;
+    h *= 0x7FEB352Du;
+    h ^= h >> 15u;
+    h *= 0x846CA68Bu;
+    h ^= h >> 16u;
+    return f32(h >> 8u) / 16777216.0;
+}
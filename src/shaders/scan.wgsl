@@ -0,0 +1,97 @@
+// `ScanParams` is generated from its Rust declaration in
+// simulation/gpu_scan.rs and prepended along with the index helpers in
+// dispatch.wgsl when the shader module is created
+
+const BLOCK_SIZE: u32 = 256u;
+
+@group(0) @binding(0)
+var<uniform> params: ScanParams;
+
+// `params.count` values scanned in place, their total written after them
+@group(0) @binding(1)
+var<storage, read_write> values: array<u32>;
+
+// Total of every block of `BLOCK_SIZE` values, then their exclusive prefix sum
+@group(0) @binding(2)
+var<storage, read_write> block_sums: array<u32>;
+
+var<workgroup> scratch: array<u32, BLOCK_SIZE>;
+
+// Inclusive Hillis-Steele scan of `scratch`, every thread has to call it
+fn scan_scratch(local: u32) {
+    for (var offset = 1u; offset < BLOCK_SIZE; offset *= 2u) {
+        var addend = 0u;
+        if local >= offset {
+            addend = scratch[local - offset];
+        }
+        workgroupBarrier();
+        scratch[local] += addend;
+        workgroupBarrier();
+    }
+}
+
+@compute @workgroup_size(256)
+fn scan_blocks(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    let block = workgroup_index(workgroup_id, num_workgroups);
+    let local = local_id.x;
+    var value = 0u;
+    if index < params.count {
+        value = values[index];
+    }
+    scratch[local] = value;
+    workgroupBarrier();
+    scan_scratch(local);
+
+    if index < params.count {
+        values[index] = scratch[local] - value;
+    }
+    // Wrapped dispatches run a few blocks past the end
+    if local == BLOCK_SIZE - 1u && block * BLOCK_SIZE < params.count {
+        block_sums[block] = scratch[local];
+    }
+}
+
+// Runs as a single workgroup, every thread scanning a run of blocks
+@compute @workgroup_size(256)
+fn scan_block_sums(@builtin(local_invocation_id) local_id: vec3<u32>) {
+    let local = local_id.x;
+    let block_count = (params.count + BLOCK_SIZE - 1u) / BLOCK_SIZE;
+    let run = (block_count + BLOCK_SIZE - 1u) / BLOCK_SIZE;
+    let start = min(local * run, block_count);
+    let end = min(start + run, block_count);
+
+    var total = 0u;
+    for (var i = start; i < end; i++) {
+        let sum = block_sums[i];
+        block_sums[i] = total;
+        total += sum;
+    }
+    scratch[local] = total;
+    workgroupBarrier();
+    scan_scratch(local);
+
+    let offset = scratch[local] - total;
+    for (var i = start; i < end; i++) {
+        block_sums[i] += offset;
+    }
+    if local == BLOCK_SIZE - 1u {
+        values[params.count] = scratch[local];
+    }
+}
+
+@compute @workgroup_size(256)
+fn add_block_offsets(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index < params.count {
+        values[index] += block_sums[index / BLOCK_SIZE];
+    }
+}
@@ -0,0 +1,173 @@
+// `Particle` and `SortParams` are generated from their Rust declarations and
+// prepended along with the index helpers in dispatch.wgsl when the shader
+// module is created
+
+const BLOCK_SIZE: u32 = 256u;
+const RADIX: u32 = 16u;
+const DIGIT_BITS: u32 = 4u;
+
+// Keep in sync with the `SORT_KEY_*` constants in simulation/gpu_sort.rs
+const SORT_KEY_DEPTH: u32 = 0u;
+const SORT_KEY_SPEED: u32 = 1u;
+
+@group(0) @binding(0)
+var<uniform> params: SortParams;
+
+@group(0) @binding(1)
+var<storage, read> source: array<Particle>;
+
+// Key and particle index pairs, even passes read A and scatter to B
+@group(0) @binding(2)
+var<storage, read_write> entries_a: array<vec2<u32>>;
+
+@group(0) @binding(3)
+var<storage, read_write> entries_b: array<vec2<u32>>;
+
+// Count of every digit in every block, digit-major so their exclusive prefix
+// sum is where each block's run of each digit starts
+@group(0) @binding(4)
+var<storage, read_write> offsets: array<u32>;
+
+// Bit offset of the digit being sorted by
+@group(0) @binding(5)
+var<storage, read_write> shift: u32;
+
+// Particle indices in key order, drawn through as an index buffer
+@group(0) @binding(6)
+var<storage, read_write> indices: array<u32>;
+
+var<workgroup> scratch: array<u32, BLOCK_SIZE>;
+var<workgroup> digit_counts: array<atomic<u32>, RADIX>;
+
+fn block_count() -> u32 {
+    return (params.particle_count + BLOCK_SIZE - 1u) / BLOCK_SIZE;
+}
+
+fn even_pass() -> bool {
+    return (shift / DIGIT_BITS) % 2u == 0u;
+}
+
+fn read_entry(index: u32) -> vec2<u32> {
+    if even_pass() {
+        return entries_a[index];
+    }
+    return entries_b[index];
+}
+
+fn digit_of(key: u32) -> u32 {
+    return (key >> shift) & (RADIX - 1u);
+}
+
+// Bits of `value` that compare as unsigned integers like the floats do
+fn float_key(value: f32) -> u32 {
+    let bits = bitcast<u32>(value);
+    if (bits & 0x80000000u) != 0u {
+        return ~bits;
+    }
+    return bits | 0x80000000u;
+}
+
+@compute @workgroup_size(256)
+fn write_keys(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= params.particle_count {
+        return;
+    }
+    let particle = source[index];
+    var key: u32;
+    switch params.key {
+        case SORT_KEY_DEPTH: {
+            key = float_key(dot(particle.position - params.eye, params.forward));
+        }
+        case SORT_KEY_SPEED: {
+            key = float_key(length(particle.velocity));
+        }
+        default: {
+            key = particle.species;
+        }
+    }
+    if params.descending == 1u {
+        key = ~key;
+    }
+    entries_a[index] = vec2<u32>(key, index);
+}
+
+@compute @workgroup_size(256)
+fn count_digits(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    let block = workgroup_index(workgroup_id, num_workgroups);
+    let local = local_id.x;
+    if local < RADIX {
+        atomicStore(&digit_counts[local], 0u);
+    }
+    workgroupBarrier();
+    if index < params.particle_count {
+        atomicAdd(&digit_counts[digit_of(read_entry(index).x)], 1u);
+    }
+    workgroupBarrier();
+    // Wrapped dispatches run a few blocks past the end
+    if local < RADIX && block < block_count() {
+        offsets[local * block_count() + block] = atomicLoad(&digit_counts[local]);
+    }
+}
+
+// Every entry goes after the ones with the same digit earlier in its block,
+// which keeps the sort stable
+@compute @workgroup_size(256)
+fn scatter(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    let block = workgroup_index(workgroup_id, num_workgroups);
+    let local = local_id.x;
+    var entry = vec2<u32>(0u);
+    // Past the end matches no digit
+    var digit = RADIX;
+    if index < params.particle_count {
+        entry = read_entry(index);
+        digit = digit_of(entry.x);
+    }
+    scratch[local] = digit;
+    workgroupBarrier();
+
+    if digit == RADIX {
+        return;
+    }
+    var rank = 0u;
+    for (var i = 0u; i < local; i++) {
+        rank += u32(scratch[i] == digit);
+    }
+    let slot = offsets[digit * block_count() + block] + rank;
+    if even_pass() {
+        entries_b[slot] = entry;
+    } else {
+        entries_a[slot] = entry;
+    }
+}
+
+@compute @workgroup_size(1)
+fn next_digit() {
+    shift += DIGIT_BITS;
+}
+
+@compute @workgroup_size(256)
+fn gather(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index < params.particle_count {
+        indices[index] = entries_a[index].y;
+    }
+}
@@ -0,0 +1,70 @@
+// `Particle`, `SimParams` and `SpringEnd` are generated from their Rust
+// declarations and prepended along with the index helpers in dispatch.wgsl
+// when the shader module is created
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+// The particles as they were before this iteration, so both ends of a spring
+// see the same pair
+@group(0) @binding(2)
+var<storage, read> snapshot: array<Particle>;
+
+// Where the springs of each particle start in `spring_ends`, with the end of
+// the last particle's after them
+@group(0) @binding(3)
+var<storage, read> spring_offsets: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read> spring_ends: array<SpringEnd>;
+
+// Keep in sync with `BOUNDARY_PERIODIC` in simulation/mod.rs
+const BOUNDARY_PERIODIC: u32 = 1u;
+
+// Keep in sync with `nearest_image` in simulation/mod.rs
+fn nearest_image(offset: vec3<f32>) -> vec3<f32> {
+    let box_size = params.box_half_extents * 2.0;
+    return offset - box_size * round(offset / box_size);
+}
+
+// Keep in sync with `relax` in simulation/springs.rs
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= params.particle_count {
+        return;
+    }
+    let start = spring_offsets[index];
+    let end = spring_offsets[index + 1u];
+    if start == end {
+        return;
+    }
+
+    let position = snapshot[index].position;
+    let mass = snapshot[index].mass;
+    var correction = vec3<f32>(0.0);
+    for (var i = start; i < end; i++) {
+        let spring = spring_ends[i];
+        var offset = snapshot[spring.other].position - position;
+        if params.boundary_mode == BOUNDARY_PERIODIC {
+            offset = nearest_image(offset);
+        }
+        let dist = length(offset);
+        if dist <= 0.0 {
+            continue;
+        }
+        let other_mass = snapshot[spring.other].mass;
+        let share = other_mass / (mass + other_mass);
+        correction += offset / dist * ((dist - spring.rest_length) * share);
+    }
+    correction *= params.spring_stiffness / f32(end - start);
+
+    particles[index].position = position + correction;
+    particles[index].velocity += correction / params.delta_time;
+}
@@ -0,0 +1,61 @@
+// `Particle` and `SimParams` are generated from their Rust declarations and
+// prepended along with dispatch.wgsl when the shader module is created
+
+// Keep in sync with simulation/surface.rs
+const SURFACE_SPHERE: u32 = 1u;
+const SURFACE_PLANE: u32 = 2u;
+const SURFACE_TORUS: u32 = 3u;
+
+@group(0) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+@group(0) @binding(1)
+var<uniform> params: SimParams;
+
+struct SurfacePoint {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+};
+
+fn normalize_or(value: vec3<f32>, fallback: vec3<f32>) -> vec3<f32> {
+    let size = length(value);
+    return select(fallback, value / size, size > 0.0);
+}
+
+// Keep in sync with `closest_on_surface` in simulation/surface.rs
+fn closest_on_surface(position: vec3<f32>) -> SurfacePoint {
+    switch params.surface_mode {
+        case SURFACE_SPHERE: {
+            let normal = normalize_or(position, vec3<f32>(0.0, 1.0, 0.0));
+            return SurfacePoint(normal * params.surface_radius, normal);
+        }
+        case SURFACE_PLANE: {
+            return SurfacePoint(vec3<f32>(position.x, 0.0, position.z), vec3<f32>(0.0, 1.0, 0.0));
+        }
+        case SURFACE_TORUS: {
+            let around = normalize_or(vec3<f32>(position.x, 0.0, position.z), vec3<f32>(1.0, 0.0, 0.0));
+            let ring = around * params.surface_radius;
+            let normal = normalize_or(position - ring, vec3<f32>(0.0, 1.0, 0.0));
+            return SurfacePoint(ring + normal * params.surface_tube_radius, normal);
+        }
+        default: {
+            return SurfacePoint(position, vec3<f32>(0.0));
+        }
+    }
+}
+
+// Keep in sync with `constrain_to_surface` in simulation/surface.rs
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let index = invocation_index(global_id, num_workgroups);
+    if index >= params.particle_count {
+        return;
+    }
+    let point = closest_on_surface(particles[index].position);
+    let velocity = particles[index].velocity;
+    particles[index].position = point.position;
+    particles[index].velocity = velocity - point.normal * dot(velocity, point.normal);
+}
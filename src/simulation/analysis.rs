@@ -0,0 +1,149 @@
+use super::grid::SpatialGrid;
+use super::{MAX_SPECIES, Particle};
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Number of particles the radial distribution function is averaged over
+const RDF_SAMPLES: usize = 1000;
+/// Particles GPU backends read back to measure the groups
+pub const GROUP_SAMPLES: u32 = 4096;
+
+/// Metrics of the particles of one species
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupStats {
+    pub species: u32,
+    pub count: u32,
+    pub average_speed: f32,
+    /// Summed over the group, ½·m·v²
+    pub kinetic_energy: f32,
+    /// Farthest particle from the group's center of mass
+    pub bounding_radius: f32,
+}
+
+/// [`GroupStats`] of every species present in `particles`, a sample of
+/// `total` particles. Counts and energies are scaled up to the whole, the
+/// rest is taken from the sample as is.
+pub fn group_statistics(particles: &[Particle], total: u32) -> Vec<GroupStats> {
+    if particles.is_empty() {
+        return Vec::new();
+    }
+    let scale = total as f32 / particles.len() as f32;
+    let group = |particle: &Particle| (particle.species % MAX_SPECIES) as usize;
+
+    let mut counts = [0u32; MAX_SPECIES as usize];
+    let mut speeds = [0.0f32; MAX_SPECIES as usize];
+    let mut energies = [0.0f32; MAX_SPECIES as usize];
+    let mut masses = [0.0f32; MAX_SPECIES as usize];
+    let mut moments = [Vec3::ZERO; MAX_SPECIES as usize];
+    for particle in particles {
+        let g = group(particle);
+        let speed = Vec3::from(particle.velocity).length();
+        counts[g] += 1;
+        speeds[g] += speed;
+        energies[g] += 0.5 * particle.mass * speed * speed;
+        masses[g] += particle.mass;
+        moments[g] += Vec3::from(particle.position) * particle.mass;
+    }
+
+    let centers: Vec<Vec3> = (0..MAX_SPECIES as usize)
+        .map(|g| {
+            if masses[g] > 0.0 {
+                moments[g] / masses[g]
+            } else {
+                Vec3::ZERO
+            }
+        })
+        .collect();
+    let mut radii = [0.0f32; MAX_SPECIES as usize];
+    for particle in particles {
+        let g = group(particle);
+        radii[g] = radii[g].max(Vec3::from(particle.position).distance(centers[g]));
+    }
+
+    (0..MAX_SPECIES as usize)
+        .filter(|&g| counts[g] > 0)
+        .map(|g| GroupStats {
+            species: g as u32,
+            count: (counts[g] as f32 * scale).round() as u32,
+            average_speed: speeds[g] / counts[g] as f32,
+            kinetic_energy: energies[g] * scale,
+            bounding_radius: radii[g],
+        })
+        .collect()
+}
+
+/// Radial distribution function g(r) over `bins` shells up to `max_radius`,
+/// averaged over an evenly spaced sample of particles. The density comes
+/// from the periodic box when there is one, otherwise from the bounding box
+/// of the particles.
+pub fn radial_distribution(
+    particles: &[Particle],
+    grid: &mut SpatialGrid,
+    max_radius: f32,
+    bins: usize,
+    periodic_box: Option<Vec3>,
+) -> Vec<f32> {
+    if particles.len() < 2 || bins == 0 || max_radius <= 0.0 {
+        return vec![0.0; bins];
+    }
+
+    grid.build(particles, max_radius, periodic_box);
+
+    let volume = match periodic_box {
+        Some(half_extents) => (half_extents * 2.0).element_product(),
+        None => {
+            let (min, max) = particles.iter().fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), p| {
+                    let position = Vec3::from(p.position);
+                    (min.min(position), max.max(position))
+                },
+            );
+            (max - min).max(Vec3::splat(max_radius)).element_product()
+        }
+    };
+    let density = (particles.len() - 1) as f32 / volume;
+
+    let stride = (particles.len() / RDF_SAMPLES).max(1);
+    let samples: Vec<usize> = (0..particles.len()).step_by(stride).collect();
+    let bin_width = max_radius / bins as f32;
+
+    let grid = &*grid;
+    let histogram = samples
+        .par_iter()
+        .map(|&i| {
+            let position = Vec3::from(particles[i].position);
+            let mut histogram = vec![0u32; bins];
+            grid.for_each_neighbor(position, max_radius, |j| {
+                if j == i {
+                    return;
+                }
+                let dist = grid
+                    .separation(position, Vec3::from(particles[j].position))
+                    .length();
+                let bin = (dist / bin_width) as usize;
+                if bin < bins {
+                    histogram[bin] += 1;
+                }
+            });
+            histogram
+        })
+        .reduce(
+            || vec![0u32; bins],
+            |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                a
+            },
+        );
+
+    histogram
+        .iter()
+        .enumerate()
+        .map(|(bin, &count)| {
+            let inner = bin as f32 * bin_width;
+            let outer = inner + bin_width;
+            let shell = 4.0 / 3.0 * std::f32::consts::PI * (outer.powi(3) - inner.powi(3));
+            count as f32 / (samples.len() as f32 * density * shell)
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+use super::layout;
+use glam::{Vec3, Vec4};
+
+/// Point attractors uploaded to the GPU at most, the rest are ignored
+pub const MAX_POINT_ATTRACTORS: usize = 16;
+
+/// User-placed point pulling particles within `radius` towards it, or
+/// pushing them away when `strength` is negative. With a horizon it's a
+/// black hole instead, pulling everything in by the inverse square of the
+/// distance and re-emitting whatever crosses the horizon.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PointAttractor {
+    pub position: Vec3,
+    /// Acceleration right at the point, negative repels
+    pub strength: f32,
+    /// Distance the pull fades out over, or a black hole's accretion disc
+    /// reaches out to
+    pub radius: f32,
+    /// Radius of the event horizon, 0 for a plain attractor
+    pub horizon: f32,
+    /// Colors the particles around a black hole hotter the closer they are
+    pub accretion_glow: bool,
+}
+
+impl Default for PointAttractor {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            strength: 10.0,
+            radius: 20.0,
+            horizon: 0.0,
+            accretion_glow: false,
+        }
+    }
+}
+
+layout::gpu_struct! {
+    pub struct GpuPointAttractor (version 2) {
+        pub position: [f32; 3] => "vec3<f32>",
+        pub strength: f32 => "f32",
+        pub radius: f32 => "f32",
+        /// 0 for a plain attractor
+        pub horizon: f32 => "f32",
+        pub accretion_glow: u32 => "u32",
+        pub _padding0: u32 => "u32",
+    }
+}
+
+impl PointAttractor {
+    /// A black hole with its accretion disc lit up
+    pub fn black_hole() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            strength: 500.0,
+            radius: 15.0,
+            horizon: 2.0,
+            accretion_glow: true,
+        }
+    }
+
+    pub fn is_black_hole(&self) -> bool {
+        self.horizon > 0.0
+    }
+
+    pub fn to_gpu(self) -> GpuPointAttractor {
+        GpuPointAttractor {
+            position: self.position.into(),
+            strength: self.strength,
+            radius: self.radius,
+            horizon: self.horizon.max(0.0),
+            accretion_glow: self.accretion_glow as u32,
+            _padding0: 0,
+        }
+    }
+
+    /// Acceleration of a particle at `position`, fading out quadratically
+    /// like the mouse force, or falling off with the inverse square of the
+    /// distance for black holes, `strength` being their G·M
+    // Keep in sync with `point_attractor_acceleration` in the compute shader
+    pub fn acceleration(&self, position: Vec3) -> Vec3 {
+        let offset = self.position - position;
+        let dist = offset.length();
+        if self.is_black_hole() {
+            // Nothing gets closer than the horizon before it's swallowed
+            let dist = dist.max(self.horizon);
+            return offset * (self.strength / (dist * dist * dist));
+        }
+        if dist >= self.radius || dist <= 0.0 {
+            return Vec3::ZERO;
+        }
+        let falloff = 1.0 - dist / self.radius;
+        offset / dist * self.strength * falloff * falloff
+    }
+
+    /// Whether a particle at `position` crossed the horizon and has to be
+    /// re-emitted
+    // Keep in sync with `swallowed` in the compute shader
+    pub fn swallows(&self, position: Vec3) -> bool {
+        self.is_black_hole()
+            && self.position.distance_squared(position) < self.horizon * self.horizon
+    }
+
+    /// `color` of a particle at `position` blended towards the white-hot
+    /// inner edge of the accretion disc the closer it is to the horizon
+    // Keep in sync with `accretion_color` in the compute shader
+    pub fn accretion_color(&self, position: Vec3, color: Vec4) -> Vec4 {
+        if !self.is_black_hole() || !self.accretion_glow || self.radius <= self.horizon {
+            return color;
+        }
+        let dist = self.position.distance(position);
+        let heat = (1.0 - (dist - self.horizon) / (self.radius - self.horizon)).clamp(0.0, 1.0);
+        let glow = Vec4::new(1.0, 0.3 + 0.6 * heat, 0.1 + 0.8 * heat * heat, 1.0);
+        color.lerp(glow, heat)
+    }
+}
@@ -0,0 +1,52 @@
+use super::layout;
+use glam::Vec3;
+
+layout::gpu_struct! {
+    /// Fixed cubic grid over the container box that grid-based solvers transfer
+    /// the particles' velocities to and back from.
+    ///
+    /// Shaders prepend [`BackgroundGrid::HELPERS_WGSL`] for the node and cell
+    /// indexing and the fixed point values particles scatter through with
+    /// atomics, which expect the grid bound as `grid`.
+    pub struct BackgroundGrid (version 1) {
+        /// Corner of the first cell
+        pub origin: [f32; 3] => "vec3<f32>",
+        pub cell_size: f32 => "f32",
+
+        /// Cells along every axis
+        pub cells: [u32; 3] => "vec3<u32>",
+        pub particle_count: u32 => "u32",
+
+        /// Corners of the cells, one more than the cells along every axis
+        pub node_count: u32 => "u32",
+        pub cell_count: u32 => "u32",
+        pub _padding0: u32 => "u32",
+        pub _padding1: u32 => "u32",
+    }
+}
+
+impl BackgroundGrid {
+    /// The indexing helpers, for shaders reading the grid
+    pub const HELPERS_WGSL: &'static str =
+        concat!(include_str!("../shaders/background_grid.wgsl"), "\n");
+
+    /// Cubic cells filling the box of `half_extents`, `resolution` of them
+    /// along its longest side. Other sides round to whole cells, at least two
+    /// for the quadratic stencils, the grid staying centered on the box.
+    pub fn new(half_extents: Vec3, resolution: u32, particle_count: u32) -> Self {
+        let size = half_extents.max(Vec3::splat(0.001)) * 2.0;
+        let cell_size = size.max_element() / resolution.max(1) as f32;
+        let cells = (size / cell_size).round().max(Vec3::splat(2.0)).as_uvec3();
+        let nodes = cells + 1;
+        Self {
+            origin: (-cells.as_vec3() * cell_size / 2.0).into(),
+            cell_size,
+            cells: cells.into(),
+            particle_count,
+            node_count: nodes.element_product(),
+            cell_count: cells.element_product(),
+            _padding0: 0,
+            _padding1: 0,
+        }
+    }
+}
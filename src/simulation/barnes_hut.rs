@@ -0,0 +1,224 @@
+use super::Particle;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Nodes with at most this many particles aren't split further
+const LEAF_SIZE: usize = 8;
+/// Stops splitting piles of particles at (nearly) the same position
+const MAX_DEPTH: u32 = 24;
+/// Enough for `MAX_DEPTH` levels of 7 deferred siblings each
+const STACK_SIZE: usize = 8 * MAX_DEPTH as usize;
+const NO_CHILD: u32 = u32::MAX;
+
+#[derive(Clone, Copy)]
+struct Node {
+    center_of_mass: Vec3,
+    /// Total mass of the particles below this node
+    mass: f32,
+    /// Edge length of the node's cube
+    size: f32,
+    /// Range of `Octree::order` holding this node's particles
+    start: u32,
+    end: u32,
+    children: [u32; 8],
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children == [NO_CHILD; 8]
+    }
+}
+
+/// Barnes–Hut octree for mutual gravity between the particles, weighted by
+/// their masses.
+///
+/// Distant nodes whose size over distance is below the opening angle θ act
+/// as a single mass at their center of mass, which takes the cost from
+/// O(n²) to O(n log n). The periodic box is ignored, gravity is long range
+/// and has no minimum image.
+pub struct Octree {
+    nodes: Vec<Node>,
+    /// Particle indices, grouped so every node covers a contiguous range
+    order: Vec<u32>,
+    positions: Vec<Vec3>,
+    masses: Vec<f32>,
+    accelerations: Vec<Vec3>,
+}
+
+impl Octree {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            order: Vec::new(),
+            positions: Vec::new(),
+            masses: Vec::new(),
+            accelerations: Vec::new(),
+        }
+    }
+
+    pub fn build(&mut self, particles: &[Particle]) {
+        self.nodes.clear();
+        particles
+            .par_iter()
+            .map(|p| Vec3::from(p.position))
+            .collect_into_vec(&mut self.positions);
+        particles
+            .par_iter()
+            .map(|p| p.mass)
+            .collect_into_vec(&mut self.masses);
+        self.order.clear();
+        self.order.extend(0..particles.len() as u32);
+        if particles.is_empty() {
+            return;
+        }
+
+        let (min, max) = self
+            .positions
+            .par_iter()
+            .fold(
+                || (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), &p| (min.min(p), max.max(p)),
+            )
+            .reduce(
+                || (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |a, b| (a.0.min(b.0), a.1.max(b.1)),
+            );
+        let size = (max - min).max_element().max(1e-3);
+        let mut order = std::mem::take(&mut self.order);
+        self.build_node(&mut order, 0, (min + max) * 0.5, size, 0);
+        self.order = order;
+    }
+
+    /// Adds the node for `indices`, which start at `offset` in `order`, and
+    /// returns its index
+    fn build_node(
+        &mut self,
+        indices: &mut [u32],
+        offset: u32,
+        center: Vec3,
+        size: f32,
+        depth: u32,
+    ) -> u32 {
+        let node_index = self.nodes.len() as u32;
+        let mass = indices
+            .iter()
+            .map(|&i| self.masses[i as usize])
+            .sum::<f32>();
+        let center_of_mass = indices
+            .iter()
+            .map(|&i| self.positions[i as usize] * self.masses[i as usize])
+            .sum::<Vec3>()
+            / mass;
+        self.nodes.push(Node {
+            center_of_mass,
+            mass,
+            size,
+            start: offset,
+            end: offset + indices.len() as u32,
+            children: [NO_CHILD; 8],
+        });
+        if indices.len() <= LEAF_SIZE || depth >= MAX_DEPTH {
+            return node_index;
+        }
+
+        // Group into octants, bit 0 set for +x, bit 1 for +y, bit 2 for +z
+        let positions = &self.positions;
+        let mut bounds = [0; 9];
+        bounds[8] = indices.len();
+        bounds[4] = partition(indices, |i| positions[i as usize].z < center.z);
+        for (lo, hi) in [(0, 4), (4, 8)] {
+            let half = &mut indices[bounds[lo]..bounds[hi]];
+            bounds[lo + 2] = bounds[lo] + partition(half, |i| positions[i as usize].y < center.y);
+        }
+        for (lo, hi) in [(0, 2), (2, 4), (4, 6), (6, 8)] {
+            let quarter = &mut indices[bounds[lo]..bounds[hi]];
+            bounds[lo + 1] =
+                bounds[lo] + partition(quarter, |i| positions[i as usize].x < center.x);
+        }
+
+        let child_size = size * 0.5;
+        for octant in 0..8 {
+            let (start, end) = (bounds[octant], bounds[octant + 1]);
+            if start == end {
+                continue;
+            }
+            let sign = Vec3::new(
+                if octant & 1 != 0 { 1.0 } else { -1.0 },
+                if octant & 2 != 0 { 1.0 } else { -1.0 },
+                if octant & 4 != 0 { 1.0 } else { -1.0 },
+            );
+            let child = self.build_node(
+                &mut indices[start..end],
+                offset + start as u32,
+                center + sign * (child_size * 0.5),
+                child_size,
+                depth + 1,
+            );
+            self.nodes[node_index as usize].children[octant] = child;
+        }
+        node_index
+    }
+
+    /// Gravitational acceleration on every particle from all the others, all
+    /// together having G·M = `total_mass` shared out by their masses, with
+    /// Plummer `softening`
+    pub fn accelerations(&mut self, total_mass: f32, theta: f32, softening: f32) -> &[Vec3] {
+        let theta_sq = theta * theta;
+        let softening_sq = softening * softening;
+        let (nodes, order, positions, masses) =
+            (&self.nodes, &self.order, &self.positions, &self.masses);
+        // G per unit of particle mass
+        let scale = nodes.first().map_or(0.0, |root| total_mass / root.mass);
+
+        positions
+            .par_iter()
+            .map(|&position| {
+                let mut acceleration = Vec3::ZERO;
+                if nodes.is_empty() {
+                    return acceleration;
+                }
+                let mut stack = [0u32; STACK_SIZE];
+                let mut top = 1;
+                while top > 0 {
+                    top -= 1;
+                    let node = &nodes[stack[top] as usize];
+                    let offset = node.center_of_mass - position;
+                    let dist_sq = offset.length_squared();
+
+                    if node.is_leaf() {
+                        // The particle's own term has a zero offset and drops out
+                        for &other in &order[node.start as usize..node.end as usize] {
+                            let offset = positions[other as usize] - position;
+                            let dist_sq = offset.length_squared() + softening_sq;
+                            let mass = scale * masses[other as usize];
+                            acceleration += offset * (mass / (dist_sq * dist_sq.sqrt()));
+                        }
+                    } else if node.size * node.size < theta_sq * dist_sq {
+                        let mass = scale * node.mass;
+                        let dist_sq = dist_sq + softening_sq;
+                        acceleration += offset * (mass / (dist_sq * dist_sq.sqrt()));
+                    } else {
+                        for &child in node.children.iter().filter(|&&c| c != NO_CHILD) {
+                            stack[top] = child;
+                            top += 1;
+                        }
+                    }
+                }
+                acceleration
+            })
+            .collect_into_vec(&mut self.accelerations);
+        &self.accelerations
+    }
+}
+
+/// Moves the entries matching `predicate` to the front, returning how many
+fn partition(slice: &mut [u32], predicate: impl Fn(u32) -> bool) -> usize {
+    let mut split = 0;
+    for i in 0..slice.len() {
+        if predicate(slice[i]) {
+            slice.swap(i, split);
+            split += 1;
+        }
+    }
+    split
+}
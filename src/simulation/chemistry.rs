@@ -0,0 +1,86 @@
+use super::grid::SpatialGrid;
+use super::{Particle, random_unit};
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// `A + B -> C`: when a particle of species `reactant_a` touches one of species
+/// `reactant_b` (or the other way around), each of them turns into `product`
+/// with the given probability per step.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ReactionRule {
+    pub reactant_a: u32,
+    pub reactant_b: u32,
+    pub product: u32,
+    pub probability: f32,
+}
+
+impl Default for ReactionRule {
+    fn default() -> Self {
+        Self {
+            reactant_a: 0,
+            reactant_b: 1,
+            product: 2,
+            probability: 0.1,
+        }
+    }
+}
+
+pub fn species_name(species: u32) -> String {
+    char::from(b'A' + species as u8).to_string()
+}
+
+pub fn apply_reactions(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    rules: &[ReactionRule],
+    contact_radius: f32,
+    step: u32,
+) {
+    let radius_sq = contact_radius * contact_radius;
+
+    // Decide every reaction against a snapshot of the current species so the
+    // outcome doesn't depend on the order rayon visits particles in
+    let new_species: Vec<u32> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+
+            for (rule_index, rule) in rules.iter().enumerate() {
+                let partner = if particle.species == rule.reactant_a {
+                    rule.reactant_b
+                } else if particle.species == rule.reactant_b {
+                    rule.reactant_a
+                } else {
+                    continue;
+                };
+
+                let mut in_contact = false;
+                grid.for_each_neighbor(position, contact_radius, |j| {
+                    if !in_contact
+                        && j != i
+                        && particles[j].species == partner
+                        && grid
+                            .separation(position, Vec3::from(particles[j].position))
+                            .length_squared()
+                            < radius_sq
+                    {
+                        in_contact = true;
+                    }
+                });
+
+                if in_contact && random_unit(i as u32, step, rule_index as u32) < rule.probability {
+                    return rule.product;
+                }
+            }
+
+            particle.species
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(new_species)
+        .for_each(|(particle, species)| particle.species = species);
+}
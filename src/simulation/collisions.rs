@@ -0,0 +1,220 @@
+use super::grid::SpatialGrid;
+use super::{Particle, SimParams, layout};
+use glam::Vec3;
+use rayon::prelude::*;
+
+// Keep in sync with granular.wgsl
+pub const CONTACT_IMPULSE: u32 = 0;
+pub const CONTACT_GRANULAR: u32 = 1;
+
+/// Neighbors every particle remembers the friction of, contacts past these
+/// grip afresh every step
+pub const MAX_CONTACTS: usize = 8;
+
+/// Solid spheres spin up 5/2 as easily as they slide, so a tangential
+/// impulse changes the sliding at the contact 7/2 times over
+const TANGENTIAL_SHARE: f32 = 2.0 / 7.0;
+
+layout::gpu_struct! {
+    /// How far a touching pair has slid past each other while stuck, the
+    /// tangential spring static friction pulls back on
+    pub struct Contact (version 1) {
+        pub spring: [f32; 3] => "vec3<f32>",
+        /// The neighbor, slots past the last contact are zeroed
+        pub other: u32 => "u32",
+    }
+}
+
+impl Contact {
+    pub const EMPTY: Self = Self {
+        spring: [0.0; 3],
+        other: 0,
+    };
+}
+
+/// Impulse-based collisions between equal spheres of `radius`.
+///
+/// Every overlapping pair that's still approaching exchanges an impulse along
+/// the line between the centers, keeping `restitution` of the approach speed,
+/// and both are pushed apart by the overlap. The lighter of the pair takes
+/// the larger share of both, in proportion to the other's mass. All pairs are resolved
+/// against the positions from before the pass, so piles settle over a few
+/// steps rather than at once.
+// Keep in sync with collisions.wgsl
+pub fn resolve_collisions(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    radius: f32,
+    restitution: f32,
+) {
+    let diameter = 2.0 * radius;
+    let diameter_sq = diameter * diameter;
+
+    let corrections: Vec<(Vec3, Vec3)> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let velocity = Vec3::from(particle.velocity);
+            let mass = particle.mass;
+            let mut displacement = Vec3::ZERO;
+            let mut impulse = Vec3::ZERO;
+
+            grid.for_each_neighbor(position, diameter, |j| {
+                if j == i {
+                    return;
+                }
+                let offset = grid.separation(position, Vec3::from(particles[j].position));
+                let dist_sq = offset.length_squared();
+                if dist_sq >= diameter_sq || dist_sq == 0.0 {
+                    return;
+                }
+                let dist = dist_sq.sqrt();
+                let normal = offset / dist;
+                let share = particles[j].mass / (mass + particles[j].mass);
+                displacement += normal * ((diameter - dist) * share);
+
+                let approach = (velocity - Vec3::from(particles[j].velocity)).dot(normal);
+                if approach < 0.0 {
+                    impulse -= normal * (approach * (1.0 + restitution) * share);
+                }
+            });
+
+            (displacement, impulse)
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(corrections)
+        .for_each(|(particle, (displacement, impulse))| {
+            particle.position = (Vec3::from(particle.position) + displacement).into();
+            particle.velocity = (Vec3::from(particle.velocity) + impulse).into();
+        });
+}
+
+/// What granular contacts keep of every particle from step to step on the
+/// CPU
+#[derive(Default)]
+pub struct GranularContacts {
+    /// Angular velocity of every particle
+    spins: Vec<Vec3>,
+    contacts: Vec<[Contact; MAX_CONTACTS]>,
+}
+
+impl GranularContacts {
+    /// Forgets the contacts and spins, for when the particles were replaced
+    pub fn clear(&mut self) {
+        self.spins.clear();
+        self.contacts.clear();
+    }
+
+    /// [`resolve_collisions`] for spheres that grip, the discrete element
+    /// method.
+    ///
+    /// Every touching pair keeps a tangential spring of how far its surfaces
+    /// slid while stuck, which static friction pulls back on up to
+    /// `contact_friction` times the pair's normal push, sliding past that.
+    /// Friction spins the particles up, and rolling resistance brakes the
+    /// pair's relative spin the same way, so piles hold their slope.
+    // Keep in sync with granular.wgsl
+    pub fn resolve(&mut self, particles: &mut [Particle], grid: &SpatialGrid, params: &SimParams) {
+        self.spins.resize(particles.len(), Vec3::ZERO);
+        self.contacts
+            .resize(particles.len(), [Contact::EMPTY; MAX_CONTACTS]);
+        let radius = params.collision_radius;
+        let diameter = 2.0 * radius;
+        let diameter_sq = diameter * diameter;
+        let delta_time = params.delta_time.max(1e-6);
+        let (spins, contacts) = (&self.spins, &self.contacts);
+
+        let corrections: Vec<(Vec3, Vec3, Vec3, [Contact; MAX_CONTACTS])> = particles
+            .par_iter()
+            .enumerate()
+            .map(|(i, particle)| {
+                let position = Vec3::from(particle.position);
+                let velocity = Vec3::from(particle.velocity);
+                let mass = particle.mass;
+                let spin = spins[i];
+                let mut displacement = Vec3::ZERO;
+                let mut impulse = Vec3::ZERO;
+                let mut spin_change = Vec3::ZERO;
+                let mut touching = [Contact::EMPTY; MAX_CONTACTS];
+                let mut touching_count = 0;
+
+                grid.for_each_neighbor(position, diameter, |j| {
+                    if j == i {
+                        return;
+                    }
+                    let offset = grid.separation(position, Vec3::from(particles[j].position));
+                    let dist_sq = offset.length_squared();
+                    if dist_sq >= diameter_sq || dist_sq == 0.0 {
+                        return;
+                    }
+                    let dist = dist_sq.sqrt();
+                    let normal = offset / dist;
+                    let share = particles[j].mass / (mass + particles[j].mass);
+                    let overlap = diameter - dist;
+                    displacement += normal * (overlap * share);
+
+                    // The push apart as a speed, so resting contacts that
+                    // don't approach still grip
+                    let relative = velocity - Vec3::from(particles[j].velocity);
+                    let approach = relative.dot(normal);
+                    let mut push = overlap * share / delta_time;
+                    if approach < 0.0 {
+                        let bounce = -approach * (1.0 + params.restitution) * share;
+                        impulse += normal * bounce;
+                        push += bounce;
+                    }
+
+                    // Sliding of the surfaces where they touch, spins included
+                    let surface = relative - radius * (spin + spins[j]).cross(normal);
+                    let slip = surface - normal * surface.dot(normal);
+                    let stored = contacts[i]
+                        .iter()
+                        .find(|contact| contact.other == j as u32)
+                        .map_or(Vec3::ZERO, |contact| Vec3::from(contact.spring));
+                    let mut spring = stored - normal * stored.dot(normal) + slip * delta_time;
+                    let stiffness = share * TANGENTIAL_SHARE / delta_time;
+                    let mut grip = -spring * stiffness;
+                    let limit = params.contact_friction * push;
+                    if grip.length() > limit {
+                        grip *= limit / grip.length();
+                        spring = -grip / stiffness;
+                    }
+                    impulse += grip;
+                    spin_change -= normal.cross(grip) * (2.5 / radius);
+
+                    let rolling = (spin - spins[j]) * share;
+                    let most = 2.5 * params.rolling_resistance * push / radius;
+                    spin_change -= rolling.clamp_length_max(most);
+
+                    if touching_count < MAX_CONTACTS {
+                        touching[touching_count] = Contact {
+                            spring: spring.into(),
+                            other: j as u32,
+                        };
+                        touching_count += 1;
+                    }
+                });
+
+                (displacement, impulse, spin + spin_change, touching)
+            })
+            .collect();
+
+        particles
+            .par_iter_mut()
+            .zip(self.spins.par_iter_mut())
+            .zip(self.contacts.par_iter_mut())
+            .zip(corrections)
+            .for_each(
+                |(((particle, spin), contacts), (displacement, impulse, new_spin, touching))| {
+                    particle.position = (Vec3::from(particle.position) + displacement).into();
+                    particle.velocity = (Vec3::from(particle.velocity) + impulse).into();
+                    *spin = new_spin;
+                    *contacts = touching;
+                },
+            );
+    }
+}
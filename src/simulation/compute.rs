@@ -1,17 +1,570 @@
-use super::{SphereGeneration, generate_initial_particles};
+use super::analysis::{self, GROUP_SAMPLES, GroupStats};
+use super::attractors::{GpuPointAttractor, MAX_POINT_ATTRACTORS, PointAttractor};
+use super::background_grid::BackgroundGrid;
+use super::collisions::{CONTACT_GRANULAR, Contact, MAX_CONTACTS};
+use super::dispatch::{self, WORKGROUP_SIZE, dispatch_linear};
+use super::field_graph::{FieldGraph, MAX_FIELD_NODES};
+use super::flip::FlipSolver;
+use super::forces::{ForceStack, GpuForce, MAX_FORCES};
+use super::frame_graph::{FrameGraph, FramePlan};
+use super::gpu_buffer::{
+    GpuBuffer, TrackedBindGroup, TrackedResource, storage_entry, uniform_entry,
+};
+use super::gpu_grid::{GpuSpatialGrid, GridParams};
+use super::health::{GpuHealthCheck, Health};
+use super::mesh_sdf::MeshSdf;
+use super::mpm::MpmSolver;
+use super::noise;
+use super::obstacles::{GpuObstacle, MAX_OBSTACLES, Obstacle};
+use super::particle_life::InteractionMatrix;
+use super::readback::{CountReadback, ParticleReadback};
+use super::springs::{SpringEnd, SpringNetwork};
+use super::surface::SURFACE_NONE;
+use super::{
+    COLOR_DENSITY, COLOR_DISPLACEMENT, GenerationSettings, MAX_SPECIES, generate_initial_particles,
+};
 
-use super::{ParticleSimulation, SimParams, SimulationMethod};
-use wgpu::util::DeviceExt;
+use super::{Capability, Particle, ParticleSimulation, SimParams, SimulationMethod};
+use crate::wgsl;
+use std::sync::Arc;
+
+/// Resources the frame graph tracks between the passes of a step
+const PARTICLES: &str = "particles";
+const GRID: &str = "grid";
+const ALIVE: &str = "alive";
+
+/// Count of the living and of the dead revived so far, ahead of the list of
+/// the living the compaction leaves for the integration
+// Keep in sync with `AliveList` in compute.wgsl
+const ALIVE_HEADER: usize = 2;
 
 pub struct ComputeParticleSimulation {
-    particle_buffer: wgpu::Buffer,
-    sim_param_buffer: wgpu::Buffer,
-    compute_pipeline: wgpu::ComputePipeline,
-    compute_bind_group: wgpu::BindGroup,
-    bind_group_layout: wgpu::BindGroupLayout,
+    particle_buffer: GpuBuffer<Particle>,
+    sim_param_buffer: GpuBuffer<SimParams>,
+    compute_pipelines: IntegratePipelines,
+    /// Kept to rebuild the pipelines when the field graph is rewired
+    compute_pipeline_layout: wgpu::PipelineLayout,
+    compute_bind_group: TrackedBindGroup,
+    /// The living listed by the compaction while there's an emission rate,
+    /// after `ALIVE_HEADER`
+    alive_buffer: GpuBuffer<u32>,
+    /// Workgroups the integration is dispatched with through the list
+    alive_dispatch_buffer: GpuBuffer<u32>,
+    alive_dispatch_pipeline: wgpu::ComputePipeline,
+    alive_dispatch_bind_group: TrackedBindGroup,
+    alive_readback: CountReadback,
+    /// Neighbor lookups for the passes that need them, built on demand
+    grid: GpuSpatialGrid,
+    density_pipeline: wgpu::ComputePipeline,
+    density_bind_group: TrackedBindGroup,
+    displacement_pipeline: wgpu::ComputePipeline,
+    displacement_bind_group: TrackedBindGroup,
+    /// The particles when the reference was last taken
+    reference_buffer: GpuBuffer<Particle>,
+    /// Steers the particles as boids before they are integrated, only in the
+    /// boids mode
+    flocking: Option<NeighborPass>,
+    /// Keeps the particles' flow incompressible before they are integrated,
+    /// only in the liquid mode
+    liquid: Option<FlipSolver>,
+    /// Deforms the particles as snow or sand before they are integrated, only
+    /// in the MPM mode
+    mpm: Option<MpmSolver>,
+    /// Resolves overlaps after they are integrated
+    collisions: NeighborPass,
+    /// Resolves them with friction instead, for granular contacts
+    granular: GranularPass,
+    /// Spreads temperature between touching particles before they are
+    /// integrated
+    heat: NeighborPass,
+    /// Kicks the charges by each other's Coulomb force before they are
+    /// integrated
+    electrostatics: NeighborPass,
+    /// Pulls and pushes the species by the interaction matrix before they
+    /// are integrated
+    particle_life: NeighborPass,
+    species_interaction_buffer: GpuBuffer<[f32; MAX_SPECIES as usize]>,
+    species_interactions: InteractionMatrix,
+    springs: SpringPass,
+    obstacle_pipeline: wgpu::ComputePipeline,
+    obstacle_bind_group: TrackedBindGroup,
+    obstacle_buffer: GpuBuffer<GpuObstacle>,
+    obstacles: Vec<GpuObstacle>,
+    obstacle_mesh_buffer: GpuBuffer<f32>,
+    /// Holds the particles on a surface after everything else moved them
+    surface_pipeline: wgpu::ComputePipeline,
+    surface_bind_group: TrackedBindGroup,
+    point_attractor_buffer: GpuBuffer<GpuPointAttractor>,
+    point_attractors: Vec<GpuPointAttractor>,
+    force_buffer: GpuBuffer<GpuForce>,
+    /// The enabled forces of the stack, in order
+    forces: Vec<GpuForce>,
+    field_node_buffer: GpuBuffer<[f32; 4]>,
+    /// Values of the field graph's nodes, empty while it's inactive
+    field_nodes: Vec<[f32; 4]>,
+    /// The generated `field_graph` the pipeline was built with
+    field_graph_source: String,
+    /// Rebuilt into the pipeline on the next update
+    pending_field_graph: Option<String>,
+    /// Uploaded on the next update
+    pending_mesh: Option<Arc<MeshSdf>>,
+    readback: ParticleReadback,
+    /// Separate from `readback` so the statistics don't keep replacing the
+    /// overlays' samples
+    group_readback: ParticleReadback,
+    health: GpuHealthCheck,
+    /// The passes of the last step
+    frame_plan: FramePlan,
     particle_count: u32,
     paused: bool,
-    generation_mode: SphereGeneration,
+    generation: GenerationSettings,
+}
+
+/// A pass over the particles that reads their neighbors through the grid,
+/// from a copy taken right before it so every particle sees the same scene
+struct NeighborPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+    snapshot: GpuBuffer<Particle>,
+}
+
+impl NeighborPass {
+    /// `shader` binds the particles, params and snapshot at group 0, followed
+    /// by `extra` read-only storage buffers, and the grid lookup at group 1
+    fn new(
+        device: &wgpu::Device,
+        label: &str,
+        shader: &str,
+        grid: &GpuSpatialGrid,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        extra: &[&dyn TrackedResource],
+    ) -> Self {
+        Self::with_access(
+            device, label, shader, grid, particles, sim_params, extra, true,
+        )
+    }
+
+    /// Like [`NeighborPass::new`], the `extra` buffers read-write unless
+    /// `read_only`, for passes that keep state of their own per particle
+    #[allow(clippy::too_many_arguments)]
+    fn with_access(
+        device: &wgpu::Device,
+        label: &str,
+        shader: &str,
+        grid: &GpuSpatialGrid,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        extra: &[&dyn TrackedResource],
+        read_only: bool,
+    ) -> Self {
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            GridParams::WGSL,
+            GpuSpatialGrid::LOOKUP_WGSL,
+            wgsl::FALLOFFS,
+            dispatch::WGSL,
+            shader,
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{label} Shader")),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let mut entries = vec![
+            storage_entry(0, false),
+            uniform_entry(1),
+            storage_entry(2, true),
+        ];
+        entries.extend((0..extra.len()).map(|i| storage_entry(3 + i as u32, read_only)));
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(&format!("{label} Bind Group Layout")),
+            entries: &entries,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{label} Pipeline Layout")),
+            bind_group_layouts: &[&layout, grid.lookup_layout()],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&format!("{label} Pipeline")),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let snapshot = GpuBuffer::with_capacity(
+            device,
+            "Neighbor Snapshot Buffer",
+            wgpu::BufferUsages::STORAGE,
+            1,
+        );
+        let mut resources: Vec<&dyn TrackedResource> = vec![particles, sim_params, &snapshot];
+        resources.extend_from_slice(extra);
+        let bind_group = TrackedBindGroup::new(device, "Neighbor Bind Group", layout, &resources);
+        Self {
+            pipeline,
+            bind_group,
+            snapshot,
+        }
+    }
+
+    /// Records the pass over the first `particle_count` particles, the grid
+    /// must have been built over them already. `extra` are the same buffers
+    /// it was created with.
+    #[allow(clippy::too_many_arguments)]
+    fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        grid: &mut GpuSpatialGrid,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        extra: &[&dyn TrackedResource],
+        particle_count: u32,
+    ) {
+        self.snapshot.reserve(device, particle_count as usize);
+        encoder.copy_buffer_to_buffer(
+            particles.buffer(),
+            0,
+            self.snapshot.buffer(),
+            0,
+            particle_count as u64 * std::mem::size_of::<Particle>() as u64,
+        );
+        let mut resources: Vec<&dyn TrackedResource> = vec![particles, sim_params, &self.snapshot];
+        resources.extend_from_slice(extra);
+        let bind_group = self.bind_group.get(device, &resources);
+        let grid_bind_group = grid.lookup_bind_group(device);
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Neighbor Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_bind_group(1, grid_bind_group, &[]);
+        dispatch_linear(&mut pass, particle_count.div_ceil(WORKGROUP_SIZE));
+    }
+}
+
+/// Collisions that grip and roll, with the friction springs of every
+/// particle's contacts and its spin kept from step to step
+struct GranularPass {
+    pass: NeighborPass,
+    contacts: GpuBuffer<Contact>,
+    spins: GpuBuffer<[f32; 4]>,
+    /// The spins as they were before the pass, for both sides of a contact
+    spin_snapshot: GpuBuffer<[f32; 4]>,
+    /// Whether the contacts and spins have to start over
+    stale: bool,
+}
+
+impl GranularPass {
+    fn new(
+        device: &wgpu::Device,
+        grid: &GpuSpatialGrid,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+    ) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let contacts = GpuBuffer::with_capacity(device, "Granular Contacts", storage, 1);
+        let spins = GpuBuffer::with_capacity(
+            device,
+            "Granular Spins",
+            storage | wgpu::BufferUsages::COPY_SRC,
+            1,
+        );
+        let spin_snapshot = GpuBuffer::with_capacity(device, "Granular Spin Snapshot", storage, 1);
+        let shader = wgsl::compose(&[Contact::WGSL, include_str!("../shaders/granular.wgsl")]);
+        let pass = NeighborPass::with_access(
+            device,
+            "Granular",
+            &shader,
+            grid,
+            particles,
+            sim_params,
+            &[&contacts, &spins, &spin_snapshot],
+            false,
+        );
+        Self {
+            pass,
+            contacts,
+            spins,
+            spin_snapshot,
+            stale: true,
+        }
+    }
+
+    fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        grid: &mut GpuSpatialGrid,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        particle_count: u32,
+    ) {
+        let count = particle_count as usize;
+        self.contacts.reserve(device, count * MAX_CONTACTS);
+        self.spins.reserve(device, count);
+        if self.stale {
+            encoder.clear_buffer(self.contacts.buffer(), 0, None);
+            encoder.clear_buffer(self.spins.buffer(), 0, None);
+            self.stale = false;
+        }
+        self.spin_snapshot
+            .copy_from(device, encoder, &self.spins, count);
+        self.pass.record(
+            device,
+            encoder,
+            grid,
+            particles,
+            sim_params,
+            &[&self.contacts, &self.spins, &self.spin_snapshot],
+            particle_count,
+        );
+    }
+}
+
+/// Jacobi relaxation of the springs, one dispatch per iteration against a
+/// copy of the particles taken right before it
+struct SpringPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+    snapshot: GpuBuffer<Particle>,
+    offsets: GpuBuffer<u32>,
+    ends: GpuBuffer<SpringEnd>,
+    /// Whether the particles have any springs at all
+    active: bool,
+}
+
+impl SpringPass {
+    fn new(
+        device: &wgpu::Device,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        network: &SpringNetwork,
+    ) -> Self {
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            SpringEnd::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/springs.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Spring Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Spring Bind Group Layout"),
+            entries: &[
+                storage_entry(0, false),
+                uniform_entry(1),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, true),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spring Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Spring Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let snapshot = GpuBuffer::with_capacity(
+            device,
+            "Spring Snapshot Buffer",
+            wgpu::BufferUsages::STORAGE,
+            1,
+        );
+        // Bindings can't be empty, particles without springs get a placeholder
+        let offsets = if network.is_empty() {
+            GpuBuffer::with_capacity(
+                device,
+                "Spring Offset Buffer",
+                wgpu::BufferUsages::STORAGE,
+                1,
+            )
+        } else {
+            GpuBuffer::with_contents(
+                device,
+                "Spring Offset Buffer",
+                wgpu::BufferUsages::STORAGE,
+                &network.offsets,
+            )
+        };
+        let ends = if network.is_empty() {
+            GpuBuffer::with_capacity(device, "Spring End Buffer", wgpu::BufferUsages::STORAGE, 1)
+        } else {
+            GpuBuffer::with_contents(
+                device,
+                "Spring End Buffer",
+                wgpu::BufferUsages::STORAGE,
+                &network.ends,
+            )
+        };
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "Spring Bind Group",
+            layout,
+            &[particles, sim_params, &snapshot, &offsets, &ends],
+        );
+        Self {
+            pipeline,
+            bind_group,
+            snapshot,
+            offsets,
+            ends,
+            active: !network.is_empty(),
+        }
+    }
+
+    /// Uploads the springs of newly generated particles
+    fn set_network(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, network: &SpringNetwork) {
+        self.active = !network.is_empty();
+        self.offsets.write(device, queue, &network.offsets);
+        self.ends.write(device, queue, &network.ends);
+    }
+
+    fn record(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        particle_count: u32,
+        iterations: u32,
+    ) {
+        self.snapshot.reserve(device, particle_count as usize);
+        let bind_group = self.bind_group.get(
+            device,
+            &[
+                particles,
+                sim_params,
+                &self.snapshot,
+                &self.offsets,
+                &self.ends,
+            ],
+        );
+        for _ in 0..iterations {
+            encoder.copy_buffer_to_buffer(
+                particles.buffer(),
+                0,
+                self.snapshot.buffer(),
+                0,
+                particle_count as u64 * std::mem::size_of::<Particle>() as u64,
+            );
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Spring Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            dispatch_linear(&mut pass, particle_count.div_ceil(WORKGROUP_SIZE));
+        }
+    }
+}
+
+impl ComputeParticleSimulation {
+    /// Makes the particles flock, the [`SimulationMethod::Boids`] backend
+    pub fn with_flocking(mut self, device: &wgpu::Device) -> Self {
+        self.flocking = Some(NeighborPass::new(
+            device,
+            "Boids",
+            include_str!("../shaders/boids.wgsl"),
+            &self.grid,
+            &self.particle_buffer,
+            &self.sim_param_buffer,
+            &[],
+        ));
+        self
+    }
+
+    /// Makes the particles a liquid, the [`SimulationMethod::Liquid`] backend
+    pub fn with_liquid(mut self, device: &wgpu::Device) -> Self {
+        self.liquid = Some(FlipSolver::new(
+            device,
+            &self.particle_buffer,
+            &self.sim_param_buffer,
+        ));
+        self
+    }
+
+    /// Makes the particles snow or sand, the [`SimulationMethod::Mpm`]
+    /// backend
+    pub fn with_mpm(mut self, device: &wgpu::Device) -> Self {
+        self.mpm = Some(MpmSolver::new(
+            device,
+            &self.particle_buffer,
+            &self.sim_param_buffer,
+        ));
+        self
+    }
+}
+
+/// The entry points of the compute shader
+struct IntegratePipelines {
+    /// Revives the dead at the emission rate and lists the living
+    compact: wgpu::ComputePipeline,
+    integrate: wgpu::ComputePipeline,
+}
+
+/// The integration and compaction passes, with the node editor's
+/// `field_graph` function appended to the shader
+fn create_integrate_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    field_graph: &str,
+) -> IntegratePipelines {
+    // Create compute shader, prefixed with the generated struct declarations
+    let compute_source = wgsl::compose(&[
+        Particle::WGSL,
+        SimParams::WGSL,
+        GpuPointAttractor::WGSL,
+        GpuForce::WGSL,
+        wgsl::COLORS,
+        wgsl::FALLOFFS,
+        wgsl::RANDOM,
+        noise::WGSL,
+        dispatch::WGSL,
+        include_str!("../shaders/compute.wgsl"),
+        field_graph,
+    ]);
+    let compute_shader = unsafe {
+        device.create_shader_module_trusted(
+            wgpu::ShaderModuleDescriptor {
+                label: Some("Compute Shader"),
+                source: wgpu::ShaderSource::Wgsl(compute_source.into()),
+            },
+            wgpu::ShaderRuntimeChecks::unchecked(),
+        )
+    };
+
+    let pipeline = |label, entry_point| {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            module: &compute_shader,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: None,
+        })
+    };
+    IntegratePipelines {
+        compact: pipeline("Compact Pipeline", "compact"),
+        integrate: pipeline("Compute Pipeline", "main"),
+    }
 }
 
 impl ParticleSimulation for ComputeParticleSimulation {
@@ -19,35 +572,27 @@ impl ParticleSimulation for ComputeParticleSimulation {
         device: &wgpu::Device,
         initial_particle_count: u32,
         _surface_format: wgpu::TextureFormat,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) -> Self {
         // Create initial particles
-        let particles = generate_initial_particles(initial_particle_count, generation_mode);
+        let particles = generate_initial_particles(initial_particle_count, generation);
+        let network = SpringNetwork::for_generation(&particles, &generation);
 
         // Create particle buffer
-        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Compute Particle Buffer"),
-            contents: bytemuck::cast_slice(&particles),
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_DST
-                | wgpu::BufferUsages::VERTEX,
-        });
+        let particle_buffer = GpuBuffer::with_contents(
+            device,
+            "Compute Particle Buffer",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+            &particles,
+        );
 
         // Create simulation parameters buffer
-        let sim_param_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Compute Sim Params Buffer"),
-            size: std::mem::size_of::<SimParams>() as wgpu::BufferAddress,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // Create compute shader
-        let compute_shader = unsafe {
-            device.create_shader_module_trusted(
-                wgpu::include_wgsl!("../shaders/compute.wgsl"),
-                wgpu::ShaderRuntimeChecks::unchecked(),
-            )
-        };
+        let sim_param_buffer = GpuBuffer::with_capacity(
+            device,
+            "Compute Sim Params Buffer",
+            wgpu::BufferUsages::UNIFORM,
+            1,
+        );
 
         // Create bind group layout
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -73,22 +618,10 @@ impl ParticleSimulation for ComputeParticleSimulation {
                     },
                     count: None,
                 },
-            ],
-        });
-
-        // Create bind group
-        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Compute Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: particle_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: sim_param_buffer.as_entire_binding(),
-                },
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, true),
+                storage_entry(5, false),
             ],
         });
 
@@ -100,47 +633,820 @@ impl ParticleSimulation for ComputeParticleSimulation {
                 push_constant_ranges: &[],
             });
 
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
+        // Placed attractors, written whenever some are in use
+        let point_attractor_buffer = GpuBuffer::with_capacity(
+            device,
+            "Point Attractor Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MAX_POINT_ATTRACTORS,
+        );
+
+        // The force stack, written whenever a force is enabled
+        let force_buffer = GpuBuffer::with_capacity(
+            device,
+            "Force Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MAX_FORCES,
+        );
+
+        // The node editor's values, written whenever its field is active
+        let field_node_buffer = GpuBuffer::with_capacity(
+            device,
+            "Field Node Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MAX_FIELD_NODES,
+        );
+
+        // Room for the header and every particle, written by the compaction
+        let alive_buffer = GpuBuffer::with_capacity(
+            device,
+            "Alive Buffer",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            ALIVE_HEADER + initial_particle_count as usize,
+        );
+
+        // Create bind group, rebuilt whenever the particle buffer grows
+        let compute_bind_group = TrackedBindGroup::new(
+            device,
+            "Compute Bind Group",
+            bind_group_layout,
+            &[
+                &particle_buffer,
+                &sim_param_buffer,
+                &point_attractor_buffer,
+                &force_buffer,
+                &field_node_buffer,
+                &alive_buffer,
+            ],
+        );
+
+        // Sizing the integration by the living reads nothing but their count
+        let alive_dispatch_source = wgsl::compose(&[
+            dispatch::WGSL,
+            include_str!("../shaders/alive_dispatch.wgsl"),
+        ]);
+        let alive_dispatch_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Alive Dispatch Shader"),
+            source: wgpu::ShaderSource::Wgsl(alive_dispatch_source.into()),
+        });
+        let alive_dispatch_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Alive Dispatch Bind Group Layout"),
+                entries: &[storage_entry(0, true), storage_entry(1, false)],
+            });
+        let alive_dispatch_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Alive Dispatch Pipeline Layout"),
+                bind_group_layouts: &[&alive_dispatch_layout],
+                push_constant_ranges: &[],
+            });
+        let alive_dispatch_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Alive Dispatch Pipeline"),
+                layout: Some(&alive_dispatch_pipeline_layout),
+                module: &alive_dispatch_shader,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        let alive_dispatch_buffer = GpuBuffer::with_capacity(
+            device,
+            "Alive Dispatch Buffer",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+            3,
+        );
+        let alive_dispatch_bind_group = TrackedBindGroup::new(
+            device,
+            "Alive Dispatch Bind Group",
+            alive_dispatch_layout,
+            &[&alive_buffer, &alive_dispatch_buffer],
+        );
+
+        // Density coloring reads the grid on top of the particles and params
+        let grid = GpuSpatialGrid::new(device);
+        let density_source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            GridParams::WGSL,
+            GpuSpatialGrid::LOOKUP_WGSL,
+            wgsl::COLORS,
+            wgsl::FALLOFFS,
+            dispatch::WGSL,
+            include_str!("../shaders/density.wgsl"),
+        ]);
+        let density_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Density Shader"),
+            source: wgpu::ShaderSource::Wgsl(density_source.into()),
+        });
+        let density_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Density Bind Group Layout"),
+            entries: &[storage_entry(0, false), uniform_entry(1)],
+        });
+        let density_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Density Pipeline Layout"),
+                bind_group_layouts: &[&density_layout, grid.lookup_layout()],
+                push_constant_ranges: &[],
+            });
+        let density_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Density Pipeline"),
+            layout: Some(&density_pipeline_layout),
+            module: &density_shader,
             entry_point: Some("main"),
             compilation_options: Default::default(),
             cache: None,
         });
+        let density_bind_group = TrackedBindGroup::new(
+            device,
+            "Density Bind Group",
+            density_layout,
+            &[&particle_buffer, &sim_param_buffer],
+        );
+
+        // Displacement coloring compares the particles to a copy of them
+        let displacement_source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            wgsl::COLORS,
+            dispatch::WGSL,
+            include_str!("../shaders/displacement.wgsl"),
+        ]);
+        let displacement_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Displacement Shader"),
+            source: wgpu::ShaderSource::Wgsl(displacement_source.into()),
+        });
+        let displacement_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Displacement Bind Group Layout"),
+                entries: &[
+                    storage_entry(0, false),
+                    uniform_entry(1),
+                    storage_entry(2, true),
+                ],
+            });
+        let displacement_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Displacement Pipeline Layout"),
+                bind_group_layouts: &[&displacement_layout],
+                push_constant_ranges: &[],
+            });
+        let displacement_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Displacement Pipeline"),
+                layout: Some(&displacement_pipeline_layout),
+                module: &displacement_shader,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        let reference_buffer = GpuBuffer::with_contents(
+            device,
+            "Reference Particle Buffer",
+            wgpu::BufferUsages::STORAGE,
+            &particles,
+        );
+        let displacement_bind_group = TrackedBindGroup::new(
+            device,
+            "Displacement Bind Group",
+            displacement_layout,
+            &[&particle_buffer, &sim_param_buffer, &reference_buffer],
+        );
+
+        let collisions = NeighborPass::new(
+            device,
+            "Collisions",
+            include_str!("../shaders/collisions.wgsl"),
+            &grid,
+            &particle_buffer,
+            &sim_param_buffer,
+            &[],
+        );
+
+        let granular = GranularPass::new(device, &grid, &particle_buffer, &sim_param_buffer);
+
+        let heat = NeighborPass::new(
+            device,
+            "Heat",
+            include_str!("../shaders/heat.wgsl"),
+            &grid,
+            &particle_buffer,
+            &sim_param_buffer,
+            &[],
+        );
+
+        let electrostatics = NeighborPass::new(
+            device,
+            "Electrostatics",
+            include_str!("../shaders/electrostatics.wgsl"),
+            &grid,
+            &particle_buffer,
+            &sim_param_buffer,
+            &[],
+        );
+
+        // Particle life reads the interaction matrix on top of the snapshot
+        let species_interaction_buffer = GpuBuffer::with_capacity(
+            device,
+            "Species Interaction Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MAX_SPECIES as usize,
+        );
+        let particle_life = NeighborPass::new(
+            device,
+            "Particle Life",
+            include_str!("../shaders/particle_life.wgsl"),
+            &grid,
+            &particle_buffer,
+            &sim_param_buffer,
+            &[&species_interaction_buffer],
+        );
+
+        let springs = SpringPass::new(device, &particle_buffer, &sim_param_buffer, &network);
+        let health = GpuHealthCheck::new(device, &particle_buffer, &sim_param_buffer);
+
+        // Obstacles read their own buffer on top of the particles and params
+        let obstacle_source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            GpuObstacle::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/obstacles.wgsl"),
+        ]);
+        let obstacle_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Obstacle Shader"),
+            source: wgpu::ShaderSource::Wgsl(obstacle_source.into()),
+        });
+        let obstacle_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Obstacle Bind Group Layout"),
+            entries: &[
+                storage_entry(0, false),
+                uniform_entry(1),
+                storage_entry(2, true),
+                storage_entry(3, true),
+            ],
+        });
+        let obstacle_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Obstacle Pipeline Layout"),
+                bind_group_layouts: &[&obstacle_layout],
+                push_constant_ranges: &[],
+            });
+        let obstacle_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Obstacle Pipeline"),
+            layout: Some(&obstacle_pipeline_layout),
+            module: &obstacle_shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let obstacle_buffer = GpuBuffer::with_capacity(
+            device,
+            "Obstacle Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MAX_OBSTACLES,
+        );
+        let obstacle_mesh_buffer = GpuBuffer::with_contents(
+            device,
+            "Obstacle Mesh Buffer",
+            wgpu::BufferUsages::STORAGE,
+            MeshSdf::empty().distances(),
+        );
+        let obstacle_bind_group = TrackedBindGroup::new(
+            device,
+            "Obstacle Bind Group",
+            obstacle_layout,
+            &[
+                &particle_buffer,
+                &sim_param_buffer,
+                &obstacle_buffer,
+                &obstacle_mesh_buffer,
+            ],
+        );
+
+        // The surface constraint reads nothing past the particles and params
+        let surface_source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/surface.wgsl"),
+        ]);
+        let surface_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Surface Shader"),
+            source: wgpu::ShaderSource::Wgsl(surface_source.into()),
+        });
+        let surface_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Surface Bind Group Layout"),
+            entries: &[storage_entry(0, false), uniform_entry(1)],
+        });
+        let surface_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Surface Pipeline Layout"),
+                bind_group_layouts: &[&surface_layout],
+                push_constant_ranges: &[],
+            });
+        let surface_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Surface Pipeline"),
+            layout: Some(&surface_pipeline_layout),
+            module: &surface_shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let surface_bind_group = TrackedBindGroup::new(
+            device,
+            "Surface Bind Group",
+            surface_layout,
+            &[&particle_buffer, &sim_param_buffer],
+        );
+
+        let field_graph_source = FieldGraph::default().wgsl();
+        let compute_pipelines =
+            create_integrate_pipelines(device, &compute_pipeline_layout, &field_graph_source);
 
         Self {
             particle_buffer,
             sim_param_buffer,
-            compute_pipeline,
+            compute_pipelines,
+            compute_pipeline_layout,
             compute_bind_group,
-            bind_group_layout,
+            alive_buffer,
+            alive_dispatch_buffer,
+            alive_dispatch_pipeline,
+            alive_dispatch_bind_group,
+            alive_readback: CountReadback::new(device),
+            grid,
+            density_pipeline,
+            density_bind_group,
+            displacement_pipeline,
+            displacement_bind_group,
+            reference_buffer,
+            flocking: None,
+            liquid: None,
+            mpm: None,
+            collisions,
+            granular,
+            heat,
+            electrostatics,
+            particle_life,
+            species_interaction_buffer,
+            species_interactions: InteractionMatrix::default(),
+            springs,
+            obstacle_pipeline,
+            obstacle_bind_group,
+            obstacle_buffer,
+            obstacles: Vec::new(),
+            obstacle_mesh_buffer,
+            surface_pipeline,
+            surface_bind_group,
+            point_attractor_buffer,
+            point_attractors: Vec::new(),
+            force_buffer,
+            forces: ForceStack::default()
+                .active()
+                .map(|force| force.to_gpu())
+                .collect(),
+            field_node_buffer,
+            field_nodes: Vec::new(),
+            field_graph_source,
+            pending_field_graph: None,
+            pending_mesh: None,
+            readback: ParticleReadback::new(),
+            group_readback: ParticleReadback::new(),
+            health,
+            frame_plan: FramePlan::default(),
             particle_count: initial_particle_count,
             paused: false,
-            generation_mode,
+            generation,
         }
     }
 
     fn update(
         &mut self,
-        _device: &wgpu::Device,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
         params: &SimParams,
     ) {
-        queue.write_buffer(&self.sim_param_buffer, 0, bytemuck::cast_slice(&[*params]));
+        // An empty scene has nothing to integrate
+        if self.particle_count == 0 {
+            return;
+        }
 
-        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-            label: Some("Particle Compute Pass"),
-            timestamp_writes: None,
+        self.sim_param_buffer.write(device, queue, &[*params]);
+
+        let workgroup_count = self.particle_count.div_ceil(WORKGROUP_SIZE);
+        let particle_count = self.particle_count;
+        let periodic_box = params.periodic_box();
+        let mut graph = FrameGraph::new(&[PARTICLES]);
+
+        // Revive the dead first, so they take part in the whole step
+        let rate_limited = params.emission_rate > 0.0;
+        if rate_limited {
+            self.alive_buffer
+                .reserve(device, ALIVE_HEADER + particle_count as usize);
+            graph.pass(
+                "Emission",
+                &[PARTICLES],
+                &[PARTICLES, ALIVE],
+                move |sim: &mut Self, encoder| {
+                    let header = (ALIVE_HEADER * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+                    encoder.clear_buffer(sim.alive_buffer.buffer(), 0, Some(header));
+                    let bind_group = sim.compute_bind_group.get(
+                        device,
+                        &[
+                            &sim.particle_buffer,
+                            &sim.sim_param_buffer,
+                            &sim.point_attractor_buffer,
+                            &sim.force_buffer,
+                            &sim.field_node_buffer,
+                            &sim.alive_buffer,
+                        ],
+                    );
+                    let mut compute_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Emission Pass"),
+                            timestamp_writes: None,
+                        });
+                    compute_pass.set_pipeline(&sim.compute_pipelines.compact);
+                    compute_pass.set_bind_group(0, bind_group, &[]);
+                    dispatch_linear(&mut compute_pass, workgroup_count);
+
+                    let bind_group = sim
+                        .alive_dispatch_bind_group
+                        .get(device, &[&sim.alive_buffer, &sim.alive_dispatch_buffer]);
+                    compute_pass.set_pipeline(&sim.alive_dispatch_pipeline);
+                    compute_pass.set_bind_group(0, bind_group, &[]);
+                    compute_pass.dispatch_workgroups(1, 1, 1);
+                },
+            );
+        }
+
+        if params.conduction > 0.0 {
+            let grid_params = GridParams::new(particle_count, params.contact_radius, periodic_box);
+            graph.pass(
+                "Heat Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim: &mut Self, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            graph.pass("Heat", &[PARTICLES, GRID], &[PARTICLES], |sim, encoder| {
+                sim.heat.record(
+                    device,
+                    encoder,
+                    &mut sim.grid,
+                    &sim.particle_buffer,
+                    &sim.sim_param_buffer,
+                    &[],
+                    particle_count,
+                );
+            });
+        }
+
+        if params.coulomb_strength != 0.0 && params.coulomb_cutoff > 0.0 {
+            let grid_params = GridParams::new(particle_count, params.coulomb_cutoff, periodic_box);
+            graph.pass(
+                "Electrostatics Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim: &mut Self, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            graph.pass(
+                "Electrostatics",
+                &[PARTICLES, GRID],
+                &[PARTICLES],
+                |sim, encoder| {
+                    sim.electrostatics.record(
+                        device,
+                        encoder,
+                        &mut sim.grid,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        &[],
+                        particle_count,
+                    );
+                },
+            );
+        }
+
+        if params.particle_life_strength != 0.0 && params.particle_life_radius > 0.0 {
+            self.species_interaction_buffer
+                .write(device, queue, &self.species_interactions);
+            let grid_params =
+                GridParams::new(particle_count, params.particle_life_radius, periodic_box);
+            graph.pass(
+                "Particle Life Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim: &mut Self, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            graph.pass(
+                "Particle Life",
+                &[PARTICLES, GRID],
+                &[PARTICLES],
+                |sim, encoder| {
+                    sim.particle_life.record(
+                        device,
+                        encoder,
+                        &mut sim.grid,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        &[&sim.species_interaction_buffer],
+                        particle_count,
+                    );
+                },
+            );
+        }
+
+        if self.flocking.is_some() {
+            let grid_params = GridParams::new(particle_count, params.boid_radius, periodic_box);
+            graph.pass(
+                "Boids Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim: &mut Self, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            graph.pass("Boids", &[PARTICLES, GRID], &[PARTICLES], |sim, encoder| {
+                if let Some(flocking) = &mut sim.flocking {
+                    flocking.record(
+                        device,
+                        encoder,
+                        &mut sim.grid,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        &[],
+                        particle_count,
+                    );
+                }
+            });
+        }
+
+        if self.liquid.is_some() {
+            let grid = BackgroundGrid::new(
+                params.box_half_extents.into(),
+                params.liquid_resolution,
+                particle_count,
+            );
+            let iterations = params.pressure_iterations;
+            graph.pass("Liquid", &[PARTICLES], &[PARTICLES], move |sim, encoder| {
+                if let Some(liquid) = &mut sim.liquid {
+                    liquid.record(
+                        device,
+                        queue,
+                        encoder,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        grid,
+                        iterations,
+                    );
+                }
+            });
+        }
+
+        if self.mpm.is_some() {
+            let grid = BackgroundGrid::new(
+                params.box_half_extents.into(),
+                params.mpm_resolution,
+                particle_count,
+            );
+            graph.pass("MPM", &[PARTICLES], &[PARTICLES], move |sim, encoder| {
+                if let Some(mpm) = &mut sim.mpm {
+                    mpm.record(
+                        device,
+                        queue,
+                        encoder,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        grid,
+                    );
+                }
+            });
+        }
+
+        if params.point_attractor_count > 0 && !self.point_attractors.is_empty() {
+            self.point_attractor_buffer
+                .write(device, queue, &self.point_attractors);
+        }
+        if params.force_count > 0 && !self.forces.is_empty() {
+            self.force_buffer.write(device, queue, &self.forces);
+        }
+        if let Some(source) = self.pending_field_graph.take() {
+            self.compute_pipelines =
+                create_integrate_pipelines(device, &self.compute_pipeline_layout, &source);
+        }
+        if !self.field_nodes.is_empty() {
+            self.field_node_buffer
+                .write(device, queue, &self.field_nodes);
+        }
+        let reads: &[&str] = if rate_limited {
+            &[PARTICLES, ALIVE]
+        } else {
+            &[PARTICLES]
+        };
+        graph.pass("Integrate", reads, &[PARTICLES], move |sim, encoder| {
+            let bind_group = sim.compute_bind_group.get(
+                device,
+                &[
+                    &sim.particle_buffer,
+                    &sim.sim_param_buffer,
+                    &sim.point_attractor_buffer,
+                    &sim.force_buffer,
+                    &sim.field_node_buffer,
+                    &sim.alive_buffer,
+                ],
+            );
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Particle Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(&sim.compute_pipelines.integrate);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+
+            // dispatch one workgroup per 256 particles, or per 256 of the
+            // living so the dead cost nothing
+            if rate_limited {
+                compute_pass.dispatch_workgroups_indirect(sim.alive_dispatch_buffer.buffer(), 0);
+            } else {
+                dispatch_linear(&mut compute_pass, workgroup_count);
+            }
         });
 
-        compute_pass.set_pipeline(&self.compute_pipeline);
-        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        if let Some(mesh) = self.pending_mesh.take() {
+            self.obstacle_mesh_buffer
+                .write(device, queue, mesh.distances());
+        }
+        if params.obstacle_count > 0 && !self.obstacles.is_empty() {
+            self.obstacle_buffer.write(device, queue, &self.obstacles);
+            graph.pass("Obstacles", &[PARTICLES], &[PARTICLES], |sim, encoder| {
+                let bind_group = sim.obstacle_bind_group.get(
+                    device,
+                    &[
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        &sim.obstacle_buffer,
+                        &sim.obstacle_mesh_buffer,
+                    ],
+                );
+
+                let mut obstacle_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Obstacle Pass"),
+                    timestamp_writes: None,
+                });
+                obstacle_pass.set_pipeline(&sim.obstacle_pipeline);
+                obstacle_pass.set_bind_group(0, bind_group, &[]);
+                dispatch_linear(&mut obstacle_pass, workgroup_count);
+            });
+        }
+
+        if params.spring_stiffness > 0.0 && self.springs.active {
+            let iterations = params.spring_iterations;
+            graph.pass(
+                "Springs",
+                &[PARTICLES],
+                &[PARTICLES],
+                move |sim, encoder| {
+                    sim.springs.record(
+                        device,
+                        encoder,
+                        &sim.particle_buffer,
+                        &sim.sim_param_buffer,
+                        particle_count,
+                        iterations,
+                    );
+                },
+            );
+        }
+
+        if params.collision_radius > 0.0 {
+            let grid_params =
+                GridParams::new(particle_count, 2.0 * params.collision_radius, periodic_box);
+            graph.pass(
+                "Collision Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            let granular = params.contact_model == CONTACT_GRANULAR;
+            graph.pass(
+                "Collisions",
+                &[PARTICLES, GRID],
+                &[PARTICLES],
+                move |sim, encoder| {
+                    if granular {
+                        sim.granular.record(
+                            device,
+                            encoder,
+                            &mut sim.grid,
+                            &sim.particle_buffer,
+                            &sim.sim_param_buffer,
+                            particle_count,
+                        );
+                    } else {
+                        sim.collisions.record(
+                            device,
+                            encoder,
+                            &mut sim.grid,
+                            &sim.particle_buffer,
+                            &sim.sim_param_buffer,
+                            &[],
+                            particle_count,
+                        );
+                    }
+                },
+            );
+        }
+
+        if params.surface_mode != SURFACE_NONE {
+            graph.pass("Surface", &[PARTICLES], &[PARTICLES], |sim, encoder| {
+                let bind_group = sim
+                    .surface_bind_group
+                    .get(device, &[&sim.particle_buffer, &sim.sim_param_buffer]);
+                let mut surface_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Surface Pass"),
+                    timestamp_writes: None,
+                });
+                surface_pass.set_pipeline(&sim.surface_pipeline);
+                surface_pass.set_bind_group(0, bind_group, &[]);
+                dispatch_linear(&mut surface_pass, workgroup_count);
+            });
+        }
 
-        // dispatch one workgroup per 128 particles
-        let workgroup_count = self.particle_count.div_ceil(256);
-        compute_pass.dispatch_workgroups(workgroup_count, 1, 1);
+        if params.color_mode == COLOR_DENSITY {
+            let grid_params = GridParams::new(particle_count, params.contact_radius, periodic_box);
+            graph.pass(
+                "Density Grid",
+                &[PARTICLES],
+                &[GRID],
+                move |sim, encoder| {
+                    sim.grid
+                        .build(device, queue, encoder, &sim.particle_buffer, grid_params);
+                },
+            );
+            graph.pass(
+                "Density",
+                &[PARTICLES, GRID],
+                &[PARTICLES],
+                |sim, encoder| {
+                    let bind_group = sim
+                        .density_bind_group
+                        .get(device, &[&sim.particle_buffer, &sim.sim_param_buffer]);
+                    let grid_bind_group = sim.grid.lookup_bind_group(device);
+
+                    let mut density_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Density Pass"),
+                            timestamp_writes: None,
+                        });
+                    density_pass.set_pipeline(&sim.density_pipeline);
+                    density_pass.set_bind_group(0, bind_group, &[]);
+                    density_pass.set_bind_group(1, grid_bind_group, &[]);
+                    dispatch_linear(&mut density_pass, workgroup_count);
+                },
+            );
+        }
+
+        if params.color_mode == COLOR_DISPLACEMENT {
+            graph.pass(
+                "Displacement",
+                &[PARTICLES],
+                &[PARTICLES],
+                |sim, encoder| {
+                    let bind_group = sim.displacement_bind_group.get(
+                        device,
+                        &[
+                            &sim.particle_buffer,
+                            &sim.sim_param_buffer,
+                            &sim.reference_buffer,
+                        ],
+                    );
+                    let mut displacement_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("Displacement Pass"),
+                            timestamp_writes: None,
+                        });
+                    displacement_pass.set_pipeline(&sim.displacement_pipeline);
+                    displacement_pass.set_bind_group(0, bind_group, &[]);
+                    dispatch_linear(&mut displacement_pass, workgroup_count);
+                },
+            );
+        }
+
+        self.frame_plan = graph.execute(self, encoder);
     }
 
     fn resize_buffer(
@@ -148,71 +1454,148 @@ impl ParticleSimulation for ComputeParticleSimulation {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         new_count: u32,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) {
-        self.generation_mode = generation_mode;
+        self.generation = generation;
 
         if new_count == self.particle_count {
             return;
         }
 
-        // Generate particles for the new count
-        let particles = generate_initial_particles(new_count, generation_mode);
-
-        if new_count > self.particle_count {
-            // Create new buffer
-            self.particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Compute Particle Buffer"),
-                contents: bytemuck::cast_slice(&particles),
-                usage: wgpu::BufferUsages::STORAGE
-                    | wgpu::BufferUsages::COPY_DST
-                    | wgpu::BufferUsages::VERTEX,
-            });
-
-            // Create new bind group with the new buffer
-            self.compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Compute Bind Group"),
-                layout: &self.bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: self.particle_buffer.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: self.sim_param_buffer.as_entire_binding(),
-                    },
-                ],
-            });
-        } else {
-            queue.write_buffer(&self.particle_buffer, 0, bytemuck::cast_slice(&particles));
-        }
+        // Generate particles for the new count, the buffer only grows
+        let particles = generate_initial_particles(new_count, generation);
+        self.particle_buffer.write(device, queue, &particles);
+        self.reference_buffer.write(device, queue, &particles);
+        let network = SpringNetwork::for_generation(&particles, &generation);
+        self.springs.set_network(device, queue, &network);
+        self.granular.stale = true;
 
         // Update instance fields
         self.particle_count = new_count;
     }
 
     fn get_particle_buffer(&self) -> &wgpu::Buffer {
-        &self.particle_buffer
+        self.particle_buffer.buffer()
     }
 
     fn get_method(&self) -> SimulationMethod {
-        SimulationMethod::ComputeShader
+        if self.flocking.is_some() {
+            SimulationMethod::Boids
+        } else if self.liquid.is_some() {
+            SimulationMethod::Liquid
+        } else if self.mpm.is_some() {
+            SimulationMethod::Mpm
+        } else {
+            SimulationMethod::ComputeShader
+        }
+    }
+
+    // Everything else that needs neighbor queries is still CPU only
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::HeatConduction]
     }
 
     fn get_particle_count(&self) -> u32 {
         self.particle_count
     }
+
+    fn frame_plan(&self) -> Option<&FramePlan> {
+        Some(&self.frame_plan)
+    }
+
     fn reset(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) {
-        self.generation_mode = generation_mode;
-        let particles = generate_initial_particles(self.particle_count, generation_mode);
+        self.generation = generation;
+        let particles = generate_initial_particles(self.particle_count, generation);
+
+        self.particle_buffer.write(device, queue, &particles);
+        self.reference_buffer.write(device, queue, &particles);
+        let network = SpringNetwork::for_generation(&particles, &generation);
+        self.springs.set_network(device, queue, &network);
+        if let Some(mpm) = &mut self.mpm {
+            mpm.reset_points();
+        }
+        self.granular.stale = true;
+    }
+
+    fn set_particles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &[Particle],
+    ) {
+        let count = particles.len().min(self.particle_count as usize);
+        self.particle_buffer
+            .write(device, queue, &particles[..count]);
+        if let Some(mpm) = &mut self.mpm {
+            mpm.reset_points();
+        }
+        self.granular.stale = true;
+    }
+
+    fn mark_reference(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Reference Encoder"),
+        });
+        self.reference_buffer.copy_from(
+            device,
+            &mut encoder,
+            &self.particle_buffer,
+            self.particle_count as usize,
+        );
+        queue.submit(Some(encoder.finish()));
+    }
 
-        queue.write_buffer(&self.particle_buffer, 0, bytemuck::cast_slice(&particles));
+    fn sample_particles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        indices: &[u32],
+    ) -> Option<Vec<Particle>> {
+        self.readback.sample(
+            device,
+            queue,
+            self.particle_buffer.buffer(),
+            self.particle_count,
+            indices,
+        )
+    }
+
+    fn group_statistics(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Option<Vec<GroupStats>> {
+        let count = self.particle_count;
+        let stride = (count / GROUP_SAMPLES).max(1);
+        let indices: Vec<u32> = (0..count.min(GROUP_SAMPLES)).map(|i| i * stride).collect();
+        let sampled = self.group_readback.sample(
+            device,
+            queue,
+            self.particle_buffer.buffer(),
+            count,
+            &indices,
+        )?;
+        Some(analysis::group_statistics(&sampled, count))
+    }
+
+    fn check_health(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Health> {
+        self.health.check(
+            device,
+            queue,
+            &self.particle_buffer,
+            &self.sim_param_buffer,
+            self.particle_count,
+        )
+    }
+
+    fn alive_count(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Option<u32> {
+        self.alive_readback
+            .read(device, queue, self.alive_buffer.buffer(), 0)
     }
 
     fn is_paused(&self) -> bool {
@@ -222,4 +1605,103 @@ impl ParticleSimulation for ComputeParticleSimulation {
     fn set_paused(&mut self, paused: bool) {
         self.paused = paused;
     }
+
+    fn set_obstacles(&mut self, obstacles: &[Obstacle]) {
+        self.obstacles = obstacles
+            .iter()
+            .take(MAX_OBSTACLES)
+            .map(|obstacle| obstacle.to_gpu())
+            .collect();
+    }
+
+    fn set_obstacle_mesh(&mut self, mesh: Arc<MeshSdf>) {
+        self.pending_mesh = Some(mesh);
+    }
+
+    fn set_species_interactions(&mut self, matrix: &InteractionMatrix) {
+        self.species_interactions = *matrix;
+    }
+
+    fn set_point_attractors(&mut self, attractors: &[PointAttractor]) {
+        self.point_attractors = attractors
+            .iter()
+            .take(MAX_POINT_ATTRACTORS)
+            .map(|attractor| attractor.to_gpu())
+            .collect();
+    }
+
+    fn set_forces(&mut self, forces: &ForceStack) {
+        self.forces = forces.active().map(|force| force.to_gpu()).collect();
+    }
+
+    fn set_field_graph(&mut self, graph: &FieldGraph) {
+        self.field_nodes = if graph.is_active() {
+            graph.gpu_values()
+        } else {
+            Vec::new()
+        };
+        let source = graph.wgsl();
+        if source != self.field_graph_source {
+            self.field_graph_source = source.clone();
+            self.pending_field_graph = Some(source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{DEAD, gpu_test};
+
+    /// Like the CPU backend's, with the living listed and integrated through
+    /// the indirect dispatch
+    #[test]
+    fn revives_the_dead_at_the_emission_rate() {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let particle_count = 600;
+        let generation = GenerationSettings::default();
+        let mut simulation = ComputeParticleSimulation::new(
+            &device,
+            particle_count,
+            wgpu::TextureFormat::Bgra8Unorm,
+            generation,
+        );
+        let mut particles = generate_initial_particles(particle_count, generation);
+        for particle in &mut particles {
+            particle.lifetime = DEAD;
+        }
+        simulation.set_particles(&device, &queue, &particles);
+
+        // Exactly 300 a step, more than a workgroup
+        let params = SimParams {
+            particle_count,
+            delta_time: 0.25,
+            emission_rate: 1200.0,
+            lifetime: 100.0,
+            ..Default::default()
+        };
+        for step in 1..=2 {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            simulation.update(&device, &queue, &mut encoder, &SimParams { step, ..params });
+            queue.submit(Some(encoder.finish()));
+            let particles: Vec<Particle> = gpu_test::read_buffer(
+                &device,
+                &queue,
+                simulation.get_particle_buffer(),
+                particle_count as usize,
+            );
+            let alive: Vec<&Particle> = particles.iter().filter(|p| !p.is_dead()).collect();
+            assert_eq!(alive.len() as u32, 300 * step);
+            // Integrated since, having been revived at 0
+            assert!(alive.iter().all(|particle| particle.age > 0.0));
+
+            let _ = simulation.alive_count(&device, &queue);
+            device
+                .poll(wgpu::PollType::wait_indefinitely())
+                .expect("device lost");
+            assert_eq!(simulation.alive_count(&device, &queue), Some(300 * step));
+        }
+    }
 }
@@ -1,15 +1,94 @@
-use super::{Particle, SphereGeneration, generate_initial_particles};
-use super::{ParticleSimulation, SimParams, SimulationMethod};
-use glam::Vec3;
+use super::analysis::{self, GroupStats};
+use super::attractors::{MAX_POINT_ATTRACTORS, PointAttractor};
+use super::barnes_hut::Octree;
+use super::chemistry::{self, ReactionRule};
+use super::collisions::{self, CONTACT_GRANULAR, GranularContacts};
+use super::electrostatics;
+use super::field_graph::{FieldFn, FieldGraph, FieldSample};
+use super::flocking;
+use super::flow_field::{self, FlowField};
+use super::forces::{Force, ForceStack};
+use super::gpu_buffer::GpuBuffer;
+use super::grid::SpatialGrid;
+use super::health::{self, Health};
+use super::heat;
+use super::lennard_jones::{self, LennardJones};
+use super::magnetism;
+use super::mesh_sdf::MeshSdf;
+use super::obstacles::{self, MAX_OBSTACLES, Obstacle};
+use super::particle_life::{self, InteractionMatrix};
+use super::springs::{self, SpringNetwork};
+use super::strange::{STRANGE_NONE, follow_strange_flow};
+use super::surface::{self, SURFACE_NONE};
+use super::{
+    AGE_SALT, COLOR_AGE, COLOR_DENSITY, COLOR_DISPLACEMENT, DEAD, EMIT_BURST, GenerationSettings,
+    INTEGRATOR_VERLET, Particle, RESPAWN_SALT, SPECIES_COLORS, age_color, buoyancy, density_color,
+    displacement_color, emission_budget, emit, generate_initial_particles, land_on_ground,
+    lorentz_push, nearest_image, random_unit, reemit, reflect_walls, roll_lifetime, spawn_color,
+    spawn_position, temperature_color, variation_scales, wrap_periodic,
+};
+use super::{Capability, ParticleSimulation, SimParams, SimulationMethod};
+use glam::{Vec3, Vec4};
 use rayon::prelude::*;
-use wgpu::util::DeviceExt;
+use std::sync::Arc;
 
 pub struct CpuParticleSimulation {
     particles: Vec<Particle>,
-    particle_buffer: wgpu::Buffer,
+    particle_buffer: GpuBuffer<Particle>,
+    /// Where steps stage the particles, copied to the particle buffer in the
+    /// step's encoder
+    upload_buffer: GpuBuffer<Particle>,
     particle_count: u32,
     paused: bool,
-    generation_mode: SphereGeneration,
+    generation: GenerationSettings,
+    grid: SpatialGrid,
+    analysis_grid: SpatialGrid,
+    reaction_rules: Vec<ReactionRule>,
+    lennard_jones: LennardJones,
+    /// Mutual gravity, only in the Barnes–Hut mode
+    octree: Option<Octree>,
+    /// Only in the boids mode
+    flocking: bool,
+    obstacles: Vec<Obstacle>,
+    obstacle_mesh: Arc<MeshSdf>,
+    point_attractors: Vec<PointAttractor>,
+    /// The enabled forces of the stack, in order
+    forces: Vec<Force>,
+    /// The node editor's field, while it's active
+    field_graph: Option<FieldFn>,
+    species_interactions: InteractionMatrix,
+    /// Another layer's flow and how strongly it drags the particles along
+    flow: Option<(Arc<FlowField>, f32)>,
+    /// Tying the particles together since they were generated
+    springs: SpringNetwork,
+    /// Friction and spins of touching particles, with granular contacts
+    granular: GranularContacts,
+    /// Where the particles were when the reference was last taken
+    reference: Vec<Vec3>,
+    step: u32,
+}
+
+impl CpuParticleSimulation {
+    /// Adds mutual gravity between the particles, making this the
+    /// [`SimulationMethod::BarnesHut`] backend
+    pub fn with_mutual_gravity(mut self) -> Self {
+        self.octree = Some(Octree::new());
+        self
+    }
+
+    /// Makes the particles flock, the [`SimulationMethod::Boids`] backend on
+    /// devices without compute shaders
+    pub fn with_flocking(mut self) -> Self {
+        self.flocking = true;
+        self
+    }
+}
+
+fn positions(particles: &[Particle]) -> Vec<Vec3> {
+    particles
+        .iter()
+        .map(|particle| Vec3::from(particle.position))
+        .collect()
 }
 
 impl ParticleSimulation for CpuParticleSimulation {
@@ -17,30 +96,57 @@ impl ParticleSimulation for CpuParticleSimulation {
         device: &wgpu::Device,
         initial_particle_count: u32,
         _surface_format: wgpu::TextureFormat,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) -> Self {
-        let particles = generate_initial_particles(initial_particle_count, generation_mode);
+        let particles = generate_initial_particles(initial_particle_count, generation);
+        let springs = SpringNetwork::for_generation(&particles, &generation);
+        let reference = positions(&particles);
 
-        let particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("CPU Particle Buffer"),
-            contents: bytemuck::cast_slice(&particles),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-        });
+        let particle_buffer = GpuBuffer::with_contents(
+            device,
+            "CPU Particle Buffer",
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+            &particles,
+        );
+        let upload_buffer = GpuBuffer::with_capacity(
+            device,
+            "CPU Particle Upload Buffer",
+            wgpu::BufferUsages::COPY_SRC,
+            particles.len(),
+        );
 
         Self {
             particles,
             particle_buffer,
+            upload_buffer,
             particle_count: initial_particle_count,
             paused: false,
-            generation_mode,
+            generation,
+            grid: SpatialGrid::new(),
+            analysis_grid: SpatialGrid::new(),
+            reaction_rules: Vec::new(),
+            lennard_jones: LennardJones::new(),
+            octree: None,
+            flocking: false,
+            obstacles: Vec::new(),
+            obstacle_mesh: Arc::new(MeshSdf::empty()),
+            point_attractors: Vec::new(),
+            forces: ForceStack::default().active().collect(),
+            field_graph: None,
+            species_interactions: InteractionMatrix::default(),
+            flow: None,
+            springs,
+            granular: GranularContacts::default(),
+            reference,
+            step: 0,
         }
     }
 
     fn update(
         &mut self,
-        _device: &wgpu::Device,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
-        _encoder: &mut wgpu::CommandEncoder,
+        encoder: &mut wgpu::CommandEncoder,
         params: &SimParams,
     ) {
         // if self.paused {
@@ -49,74 +155,462 @@ impl ParticleSimulation for CpuParticleSimulation {
 
         // Create local references to simulation parameters for better cache locality
         let delta_time = params.delta_time;
-        let gravity = params.gravity;
+        let point_attractor_count =
+            (params.point_attractor_count as usize).min(self.point_attractors.len());
+        let point_attractors = &self.point_attractors[..point_attractor_count];
+        let forces = &self.forces[..(params.force_count as usize).min(self.forces.len())];
+        let field_graph = self.field_graph.as_deref();
         let mouse_force = params.mouse_force;
         let mouse_radius = params.mouse_radius;
         let mouse_dragging = params.is_mouse_dragging > 0;
+        let mouse_heat = params.mouse_heat;
+        let lorentz = params.lorentz_enabled > 0;
+        let strange = params.strange_mode != STRANGE_NONE;
+        let electric_field = Vec3::from(params.electric_field);
+        let magnetic_field = Vec3::from(params.magnetic_field);
         let damping = params.damping;
         let color_mode = params.color_mode;
         let mouse_pos = Vec3::from(params.mouse_position);
         let max_dist = params.max_dist_for_color;
+        let periodic_box = params.periodic_box();
+        let container_box = params.container_box();
+        let respawn_chance = params.respawn_rate * delta_time;
+        let verlet = params.integrator == INTEGRATOR_VERLET;
+
+        // Acceleration from the force stack with gravity scaled by `gravity`,
+        // the node editor's field and the mouse's force divided by the mass,
+        // and how strongly the mouse heats a particle there
+        let field_acceleration =
+            |position: Vec3, velocity: Vec3, inverse_mass: f32, gravity: f32| {
+                let mut acceleration = Vec3::ZERO;
+                for force in forces {
+                    let force_acceleration =
+                        force.acceleration(position, velocity, params, point_attractors);
+                    acceleration += if *force == Force::Gravity {
+                        force_acceleration * gravity
+                    } else {
+                        force_acceleration
+                    };
+                }
+                if let Some(field_graph) = field_graph {
+                    acceleration += field_graph(&FieldSample {
+                        position,
+                        velocity,
+                        time: params.time,
+                    });
+                }
+
+                // Apply mouse force - only calculate if dragging
+                let mut heating = 0.0;
+                if mouse_dragging {
+                    let dir = mouse_pos - position;
+                    let dist = dir.length();
+
+                    if dist < mouse_radius * 2.0 {
+                        let force_factor = (1.0 - dist / (mouse_radius * 2.0)).powi(2) * 2.0;
+                        acceleration +=
+                            dir.normalize() * (mouse_force * force_factor * inverse_mass);
+                        heating = force_factor;
+                    }
+                }
+                (acceleration, heating)
+            };
 
         // Use Rayon to parallelize particle updates
         // Only process up to particle_count
         let active_particles = &mut self.particles[0..self.particle_count as usize];
+        let reference = &self.reference;
+
+        // Revive the dead waiting on the emitter, in index order
+        let rate_limited = params.emission_rate > 0.0;
+        if rate_limited {
+            active_particles
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, particle)| particle.is_dead())
+                .take(emission_budget(params) as usize)
+                .for_each(|(index, particle)| reemit(particle, index as u32, params));
+        }
+
+        let lennard_jones = params.lj_epsilon > 0.0;
+        if lennard_jones {
+            self.lennard_jones.half_kick(active_particles, delta_time);
+        }
+
+        let contact_radius = params.contact_radius;
+        let reactions = !self.reaction_rules.is_empty();
+        let conduction = params.conduction > 0.0;
+        let dipoles = params.dipole_strength > 0.0;
+        let coulomb = params.coulomb_strength != 0.0 && params.coulomb_cutoff > 0.0;
+        let life = params.particle_life_strength != 0.0 && params.particle_life_radius > 0.0;
+        if reactions || conduction || dipoles || coulomb || life {
+            let mut cell_size = contact_radius;
+            if dipoles {
+                cell_size = cell_size.max(params.dipole_radius);
+            }
+            if coulomb {
+                cell_size = cell_size.max(params.coulomb_cutoff);
+            }
+            if life {
+                cell_size = cell_size.max(params.particle_life_radius);
+            }
+            self.grid.build(active_particles, cell_size, periodic_box);
+        }
+        if reactions {
+            chemistry::apply_reactions(
+                active_particles,
+                &self.grid,
+                &self.reaction_rules,
+                contact_radius,
+                self.step,
+            );
+        }
+        if conduction {
+            heat::conduct_heat(
+                active_particles,
+                &self.grid,
+                contact_radius,
+                params.conduction * delta_time,
+            );
+        }
+        if dipoles {
+            magnetism::apply_dipole_forces(
+                active_particles,
+                &self.grid,
+                params.dipole_strength,
+                params.dipole_radius,
+                delta_time,
+            );
+        }
+        if coulomb {
+            electrostatics::apply_coulomb_forces(
+                active_particles,
+                &self.grid,
+                params.coulomb_strength,
+                params.coulomb_cutoff,
+                delta_time,
+            );
+        }
+        if life {
+            particle_life::apply_particle_life(
+                active_particles,
+                &self.grid,
+                &self.species_interactions,
+                params.particle_life_strength,
+                params.particle_life_radius,
+                delta_time,
+            );
+        }
+        self.step = self.step.wrapping_add(1);
 
-        active_particles.par_iter_mut().for_each(|particle| {
-            // Extract position and velocity once to minimize conversions
-            let mut position = Vec3::from(particle.position);
-            let mut velocity = Vec3::from(particle.velocity);
-            let initial_color = particle.initial_color;
+        if self.flocking {
+            self.grid
+                .build(active_particles, params.boid_radius, periodic_box);
+            flocking::apply_flocking(active_particles, &self.grid, params);
+        }
 
-            // Apply gravity
-            velocity.y -= gravity * delta_time;
+        if let Some((field, strength)) = &self.flow {
+            flow_field::apply_flow(active_particles, field, *strength, delta_time);
+        }
 
-            // Apply mouse force - only calculate if dragging
-            if mouse_dragging {
-                let dir = mouse_pos - position;
-                let dist = dir.length();
+        let mut nbody_accelerations: &[Vec3] = &[];
+        if let Some(octree) = &mut self.octree
+            && params.nbody_mass > 0.0
+        {
+            octree.build(active_particles);
+            nbody_accelerations = octree.accelerations(
+                params.nbody_mass,
+                params.nbody_theta,
+                params.nbody_softening,
+            );
+        }
 
-                if dist < mouse_radius * 2.0 {
-                    let force_factor = (1.0 - dist / (mouse_radius * 2.0)).powi(2) * 2.0;
-                    let force = dir.normalize() * mouse_force * force_factor;
-                    velocity += force * delta_time;
+        active_particles
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, particle)| {
+                let index = index as u32;
+                // Only the living move, without an emission rate the dead
+                // are re-emitted right away
+                if particle.is_dead() {
+                    if rate_limited {
+                        return;
+                    }
+                    reemit(particle, index, params);
+                }
+
+                // Continuously re-seed a fraction of the particles from the spawn shape
+                if respawn_chance > 0.0
+                    && random_unit(index, params.step, RESPAWN_SALT) < respawn_chance
+                {
+                    let position = spawn_position(
+                        index,
+                        params.particle_count,
+                        params.spawn_mode,
+                        params.step,
+                    );
+                    particle.position = position.into();
+                    particle.velocity = [0.0; 3];
+                    particle.temperature = 0.0;
+                    particle.initial_color = spawn_color(position).into();
+                    particle.age = 0.0;
+                }
+
+                // Age, and re-emit particles that outlived their lifetime or
+                // let them die at an emission rate
+                particle.age += delta_time;
+                if params.lifetime > 0.0 {
+                    if particle.lifetime <= 0.0 {
+                        // First roll, staggered so they don't all expire together
+                        particle.lifetime = roll_lifetime(index, params.step, params);
+                        if params.emitter_mode != EMIT_BURST {
+                            particle.age =
+                                random_unit(index, params.step, AGE_SALT) * particle.lifetime;
+                        }
+                    }
+                    if particle.age >= particle.lifetime {
+                        if rate_limited {
+                            particle.lifetime = DEAD;
+                            return;
+                        }
+                        reemit(particle, index, params);
+                    }
+                } else {
+                    particle.lifetime = 0.0;
                 }
-            }
 
-            // Update position
-            position += velocity * delta_time;
+                // Extract position and velocity once to minimize conversions
+                let mut position = Vec3::from(particle.position);
+                let mut velocity = Vec3::from(particle.velocity);
 
-            // Apply damping
-            velocity *= damping;
+                // Pull towards all the other particles, held for the whole step
+                let nbody = nbody_accelerations
+                    .get(index as usize)
+                    .copied()
+                    .unwrap_or_default();
+                let inverse_mass = 1.0 / particle.mass;
+                let (damping_scale, gravity_scale) =
+                    variation_scales(particle.variation, params.variation);
+                let (acceleration, heating) =
+                    field_acceleration(position, velocity, inverse_mass, gravity_scale);
+                particle.temperature += mouse_heat * heating * delta_time;
+                let lift = buoyancy(particle.temperature, params);
 
-            // Update color based on mode - using match for better performance
-            let color = match color_mode {
-                1 => {
-                    // Velocity-based
-                    let speed = velocity.length();
-                    let norm_speed = (speed / 5.0).min(1.0);
-                    [norm_speed, 0.5 - norm_speed * 0.5, 1.0 - norm_speed, 1.0]
+                // Keep in sync with the integrators in the compute shader
+                let kick = if verlet { delta_time * 0.5 } else { delta_time };
+                velocity += (acceleration + nbody + lift) * kick;
+
+                // Apply electric and magnetic fields to charged particles
+                if lorentz {
+                    velocity = lorentz_push(
+                        velocity,
+                        particle.charge * inverse_mass,
+                        electric_field,
+                        magnetic_field,
+                        delta_time,
+                    );
                 }
-                2 => {
-                    // Position-based (distance from origin)
-                    let dist_from_origin = position.length();
-                    let norm_dist = (dist_from_origin / max_dist.max(0.01)).clamp(0.0, 1.0);
-                    [norm_dist, 0.0, 1.0 - norm_dist, 1.0] // Blue near, Red far
+
+                // Get carried along a strange attractor's flow
+                if strange {
+                    velocity = follow_strange_flow(velocity, position, params);
                 }
-                _ => particle.color, // Keep original
-            };
 
-            // Update the particle
-            particle.position = position.into();
-            particle.velocity = velocity.into();
-            particle.color = color;
-        });
-
-        // Upload updated data to GPU
-        queue.write_buffer(
-            &self.particle_buffer,
-            0,
-            bytemuck::cast_slice(&self.particles[0..self.particle_count as usize]),
+                // Update position
+                position += velocity * delta_time;
+                if verlet {
+                    let (acceleration, _) =
+                        field_acceleration(position, velocity, inverse_mass, gravity_scale);
+                    velocity += (acceleration + nbody + lift) * kick;
+                }
+                if let Some(half_extents) = periodic_box {
+                    position = wrap_periodic(position, half_extents);
+                } else if let Some(half_extents) = container_box {
+                    (position, velocity) =
+                        reflect_walls(position, velocity, half_extents, params.wall_restitution);
+                }
+                if params.ground_enabled > 0 {
+                    (position, velocity) = land_on_ground(
+                        position,
+                        velocity,
+                        params.ground_height,
+                        params.ground_restitution,
+                        params.ground_friction,
+                    );
+                }
+
+                // Re-emit whatever fell into a black hole, or let it die at
+                // an emission rate
+                if point_attractors
+                    .iter()
+                    .any(|point| point.swallows(position))
+                {
+                    if rate_limited {
+                        particle.lifetime = DEAD;
+                        return;
+                    }
+                    let (emitted_position, emitted_velocity, color) = emit(index, params);
+                    position = emitted_position;
+                    velocity = emitted_velocity;
+                    particle.temperature = 0.0;
+                    particle.initial_color = color.into();
+                    particle.age = 0.0;
+                }
+
+                // Apply damping, this particle losing more or less than the rest
+                velocity *= (1.0 - (1.0 - damping) * damping_scale).max(0.0);
+
+                // Update color based on mode - using match for better performance
+                let color = match color_mode {
+                    1 => {
+                        // Velocity-based
+                        let speed = velocity.length();
+                        let norm_speed = (speed / 5.0).min(1.0);
+                        [norm_speed, 0.5 - norm_speed * 0.5, 1.0 - norm_speed, 1.0]
+                    }
+                    2 => {
+                        // Position-based (distance from origin)
+                        let dist_from_origin = position.length();
+                        let norm_dist = (dist_from_origin / max_dist.max(0.01)).clamp(0.0, 1.0);
+                        [norm_dist, 0.0, 1.0 - norm_dist, 1.0] // Blue near, Red far
+                    }
+                    3 => SPECIES_COLORS[particle.species as usize % SPECIES_COLORS.len()],
+                    4 => temperature_color(particle.temperature),
+                    5 => {
+                        // Dipole orientation
+                        let [x, y, z] = particle.dipole;
+                        [x.abs(), y.abs(), z.abs(), 1.0]
+                    }
+                    6 => {
+                        // Charge: red positive, blue negative
+                        let c = particle.charge.clamp(-1.0, 1.0);
+                        [c.max(0.0), 0.2, (-c).max(0.0), 1.0]
+                    }
+                    COLOR_AGE => age_color(particle.age, particle.lifetime),
+                    COLOR_DISPLACEMENT => {
+                        let reference = reference.get(index as usize).copied().unwrap_or(position);
+                        let mut offset = position - reference;
+                        if let Some(half_extents) = periodic_box {
+                            offset = nearest_image(offset, half_extents);
+                        }
+                        displacement_color(offset.length(), max_dist)
+                    }
+                    _ => particle.initial_color, // Keep original
+                };
+
+                // Light up the accretion discs
+                let color: [f32; 4] = point_attractors
+                    .iter()
+                    .fold(Vec4::from(color), |color, point| {
+                        point.accretion_color(position, color)
+                    })
+                    .into();
+
+                // Update the particle
+                particle.position = position.into();
+                particle.velocity = velocity.into();
+                particle.color = color;
+            });
+
+        if params.obstacle_count > 0 {
+            obstacles::resolve_obstacles(
+                active_particles,
+                &self.obstacles,
+                &self.obstacle_mesh,
+                params.obstacle_restitution,
+            );
+        }
+        if params.spring_stiffness > 0.0 && !self.springs.is_empty() {
+            springs::relax(
+                active_particles,
+                &self.springs,
+                params.spring_stiffness,
+                params.spring_iterations,
+                delta_time,
+                periodic_box,
+            );
+        }
+        if lennard_jones {
+            self.grid.build(
+                active_particles,
+                params.lj_sigma * params.lj_cutoff,
+                periodic_box,
+            );
+            self.lennard_jones.update_accelerations(
+                active_particles,
+                &self.grid,
+                params.lj_epsilon,
+                params.lj_sigma,
+                params.lj_cutoff,
+            );
+            self.lennard_jones.half_kick(active_particles, delta_time);
+        }
+        if params.thermostat_tau > 0.0 {
+            lennard_jones::berendsen_thermostat(
+                active_particles,
+                params.thermostat_target,
+                params.thermostat_tau,
+                delta_time,
+            );
+        }
+        if params.collision_radius > 0.0 {
+            let diameter = 2.0 * params.collision_radius;
+            self.grid.build(active_particles, diameter, periodic_box);
+            if params.contact_model == CONTACT_GRANULAR {
+                self.granular.resolve(active_particles, &self.grid, params);
+            } else {
+                collisions::resolve_collisions(
+                    active_particles,
+                    &self.grid,
+                    params.collision_radius,
+                    params.restitution,
+                );
+            }
+        }
+        if params.surface_mode != SURFACE_NONE {
+            surface::constrain_to_surface(
+                active_particles,
+                params.surface_mode,
+                params.surface_radius,
+                params.surface_tube_radius,
+            );
+        }
+        if color_mode == COLOR_DENSITY {
+            self.grid
+                .build(active_particles, contact_radius, periodic_box);
+            let radius_sq = contact_radius * contact_radius;
+            let (grid, particles) = (&self.grid, &*active_particles);
+            let neighbors: Vec<u32> = particles
+                .par_iter()
+                .enumerate()
+                .map(|(index, particle)| {
+                    let position = Vec3::from(particle.position);
+                    let mut count = 0;
+                    grid.for_each_neighbor(position, contact_radius, |other| {
+                        let offset =
+                            grid.separation(Vec3::from(particles[other].position), position);
+                        if other != index && offset.length_squared() < radius_sq {
+                            count += 1;
+                        }
+                    });
+                    count
+                })
+                .collect();
+            active_particles
+                .par_iter_mut()
+                .zip(neighbors)
+                .for_each(|(particle, count)| particle.color = density_color(count));
+        }
+
+        // Upload updated data to GPU through the encoder, so the step lands
+        // in order with whatever is recorded around it
+        let active_particles = &self.particles[0..self.particle_count as usize];
+        self.upload_buffer.write(device, queue, active_particles);
+        self.particle_buffer.copy_from(
+            device,
+            encoder,
+            &self.upload_buffer,
+            active_particles.len(),
         );
     }
 
@@ -125,9 +619,9 @@ impl ParticleSimulation for CpuParticleSimulation {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         new_count: u32,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) {
-        self.generation_mode = generation_mode;
+        self.generation = generation;
 
         if new_count == self.particle_count {
             return;
@@ -136,33 +630,41 @@ impl ParticleSimulation for CpuParticleSimulation {
         if new_count > self.particles.len() as u32 {
             // Expand the particle vector
             let additional_count = new_count - self.particles.len() as u32;
-            let mut new_particles = generate_initial_particles(additional_count, generation_mode);
+            let mut new_particles = generate_initial_particles(additional_count, generation);
             self.particles.append(&mut new_particles);
-
-            // Create a new buffer with larger size
-            self.particle_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("CPU Particle Buffer"),
-                contents: bytemuck::cast_slice(&self.particles),
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-            });
         }
 
         self.particle_count = new_count;
+        self.springs = SpringNetwork::for_generation(
+            &self.particles[0..self.particle_count as usize],
+            &generation,
+        );
+        self.reference = positions(&self.particles[0..self.particle_count as usize]);
 
-        // Upload current data to buffer
-        queue.write_buffer(
-            &self.particle_buffer,
-            0,
-            bytemuck::cast_slice(&self.particles[0..self.particle_count as usize]),
+        // Upload current data to buffer, growing it if needed
+        self.particle_buffer.write(
+            device,
+            queue,
+            &self.particles[0..self.particle_count as usize],
         );
     }
 
     fn get_particle_buffer(&self) -> &wgpu::Buffer {
-        &self.particle_buffer
+        self.particle_buffer.buffer()
     }
 
     fn get_method(&self) -> SimulationMethod {
-        SimulationMethod::Cpu
+        if self.octree.is_some() {
+            SimulationMethod::BarnesHut
+        } else if self.flocking {
+            SimulationMethod::Boids
+        } else {
+            SimulationMethod::Cpu
+        }
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &Capability::ALL
     }
 
     fn get_particle_count(&self) -> u32 {
@@ -173,15 +675,34 @@ impl ParticleSimulation for CpuParticleSimulation {
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) {
-        self.generation_mode = generation_mode;
-        self.particles = generate_initial_particles(self.particle_count, generation_mode);
+        self.generation = generation;
+        self.particles = generate_initial_particles(self.particle_count, generation);
+        self.springs = SpringNetwork::for_generation(&self.particles, &generation);
+        self.reference = positions(&self.particles);
+        self.granular.clear();
+
+        self.particle_buffer.write(
+            device,
+            queue,
+            &self.particles[0..self.particle_count as usize],
+        );
+    }
 
-        queue.write_buffer(
-            &self.particle_buffer,
-            0,
-            bytemuck::cast_slice(&self.particles[0..self.particle_count as usize]),
+    fn set_particles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &[Particle],
+    ) {
+        let count = particles.len().min(self.particle_count as usize);
+        self.particles[..count].copy_from_slice(&particles[..count]);
+        self.granular.clear();
+        self.particle_buffer.write(
+            device,
+            queue,
+            &self.particles[0..self.particle_count as usize],
         );
     }
 
@@ -192,4 +713,179 @@ impl ParticleSimulation for CpuParticleSimulation {
     fn set_paused(&mut self, paused: bool) {
         self.paused = paused;
     }
+
+    fn sample_particles(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        indices: &[u32],
+    ) -> Option<Vec<Particle>> {
+        let active = &self.particles[0..self.particle_count as usize];
+        indices
+            .iter()
+            .map(|&index| active.get(index as usize).copied())
+            .collect()
+    }
+
+    fn check_health(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) -> Option<Health> {
+        Some(health::measure(
+            &self.particles[0..self.particle_count as usize],
+        ))
+    }
+
+    fn alive_count(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) -> Option<u32> {
+        let alive = self.particles[0..self.particle_count as usize]
+            .par_iter()
+            .filter(|particle| !particle.is_dead())
+            .count();
+        Some(alive as u32)
+    }
+
+    fn group_statistics(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Option<Vec<GroupStats>> {
+        Some(analysis::group_statistics(
+            &self.particles[0..self.particle_count as usize],
+            self.particle_count,
+        ))
+    }
+
+    fn set_reaction_rules(&mut self, rules: &[ReactionRule]) {
+        self.reaction_rules = rules.to_vec();
+    }
+
+    fn set_obstacles(&mut self, obstacles: &[Obstacle]) {
+        self.obstacles = obstacles[..obstacles.len().min(MAX_OBSTACLES)].to_vec();
+    }
+
+    fn set_obstacle_mesh(&mut self, mesh: Arc<MeshSdf>) {
+        self.obstacle_mesh = mesh;
+    }
+
+    fn set_point_attractors(&mut self, attractors: &[PointAttractor]) {
+        self.point_attractors = attractors[..attractors.len().min(MAX_POINT_ATTRACTORS)].to_vec();
+    }
+
+    fn mark_reference(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {
+        self.reference = positions(&self.particles[0..self.particle_count as usize]);
+    }
+
+    fn set_forces(&mut self, forces: &ForceStack) {
+        self.forces = forces.active().collect();
+    }
+
+    fn set_field_graph(&mut self, graph: &FieldGraph) {
+        self.field_graph = graph.compile();
+    }
+
+    fn set_species_interactions(&mut self, matrix: &InteractionMatrix) {
+        self.species_interactions = *matrix;
+    }
+
+    fn set_flow_field(&mut self, field: Option<Arc<FlowField>>, strength: f32) {
+        self.flow = field.map(|field| (field, strength));
+    }
+
+    fn radial_distribution(
+        &mut self,
+        max_radius: f32,
+        bins: usize,
+        periodic_box: Option<Vec3>,
+    ) -> Option<Vec<f32>> {
+        Some(analysis::radial_distribution(
+            &self.particles[0..self.particle_count as usize],
+            &mut self.analysis_grid,
+            max_radius,
+            bins,
+            periodic_box,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::gpu_test;
+
+    /// Steps a scene of `particle_count` particles a few times and reads the
+    /// particles the renderer would draw back
+    fn step(particle_count: u32) -> Vec<Particle> {
+        let Some((device, queue)) = gpu_test::device() else {
+            return Vec::new();
+        };
+        let mut simulation = CpuParticleSimulation::new(
+            &device,
+            particle_count,
+            wgpu::TextureFormat::Bgra8Unorm,
+            GenerationSettings::default(),
+        );
+        let params = SimParams {
+            particle_count,
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            simulation.update(&device, &queue, &mut encoder, &params);
+            queue.submit(Some(encoder.finish()));
+        }
+        assert_eq!(simulation.get_particle_count(), particle_count);
+        gpu_test::read_buffer(
+            &device,
+            &queue,
+            simulation.get_particle_buffer(),
+            particle_count as usize,
+        )
+    }
+
+    #[test]
+    fn steps_an_empty_scene() {
+        assert!(step(0).is_empty());
+    }
+
+    /// A step with every particle dead revives the emission rate's share of
+    /// them, and no more
+    #[test]
+    fn revives_the_dead_at_the_emission_rate() {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let particle_count = 64;
+        let generation = GenerationSettings::default();
+        let mut simulation = CpuParticleSimulation::new(
+            &device,
+            particle_count,
+            wgpu::TextureFormat::Bgra8Unorm,
+            generation,
+        );
+        let mut particles = generate_initial_particles(particle_count, generation);
+        for particle in &mut particles {
+            particle.lifetime = DEAD;
+        }
+        simulation.set_particles(&device, &queue, &particles);
+
+        // Exactly 3 a step, which the random rounding leaves alone
+        let params = SimParams {
+            particle_count,
+            delta_time: 0.25,
+            emission_rate: 12.0,
+            lifetime: 100.0,
+            ..Default::default()
+        };
+        for step in 1..=4 {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            simulation.update(&device, &queue, &mut encoder, &SimParams { step, ..params });
+            queue.submit(Some(encoder.finish()));
+            assert_eq!(simulation.alive_count(&device, &queue), Some(3 * step));
+        }
+    }
+
+    #[test]
+    fn steps_a_single_particle() {
+        for particle in step(1) {
+            assert!(Vec3::from(particle.position).is_finite());
+            assert!(Vec3::from(particle.velocity).is_finite());
+        }
+    }
 }
@@ -0,0 +1,247 @@
+/// The index helpers, for shaders dispatched through [`dispatch_linear`]
+pub const WGSL: &str = concat!(include_str!("../shaders/dispatch.wgsl"), "\n");
+
+/// Invocations in every workgroup of a shader dispatched through here
+// Keep in sync with `DISPATCH_WORKGROUP_SIZE` in dispatch.wgsl
+pub const WORKGROUP_SIZE: u32 = 256;
+
+/// Workgroups every device allows along one dimension of a dispatch, which
+/// a workgroup per 256 particles runs out of just past 16.7M particles
+pub const MAX_WORKGROUPS_PER_DIMENSION: u32 = 65535;
+
+/// Workgroups along x and y covering at least `workgroup_count`, in as few
+/// rows as fit. The last row may run past the count by up to a workgroup
+/// per row.
+pub fn workgroup_grid(workgroup_count: u32) -> (u32, u32) {
+    let rows = workgroup_count
+        .div_ceil(MAX_WORKGROUPS_PER_DIMENSION)
+        .max(1);
+    (workgroup_count.div_ceil(rows), rows)
+}
+
+/// Dispatches `workgroup_count` workgroups in a line, wrapped onto more rows
+/// when one dimension can't hold them. The shader has to find its indices
+/// with `invocation_index` and `workgroup_index` from [`WGSL`] and ignore
+/// the ones past the end.
+pub fn dispatch_linear(pass: &mut wgpu::ComputePass<'_>, workgroup_count: u32) {
+    let (x, y) = workgroup_grid(workgroup_count);
+    pass.dispatch_workgroups(x, y, 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Particle;
+    use crate::simulation::gpu_buffer::{GpuBuffer, storage_entry};
+    use crate::simulation::gpu_test;
+    use crate::wgsl;
+
+    /// Checks the grid covers `workgroup_count` within the per-dimension
+    /// limit, running past it by less than a workgroup per row
+    fn check_grid(workgroup_count: u32) -> (u32, u32) {
+        let (x, y) = workgroup_grid(workgroup_count);
+        assert!(x <= MAX_WORKGROUPS_PER_DIMENSION && y <= MAX_WORKGROUPS_PER_DIMENSION);
+        let covered = x as u64 * y as u64;
+        assert!(covered >= workgroup_count as u64);
+        assert!(covered - (workgroup_count as u64) < y as u64);
+        (x, y)
+    }
+
+    #[test]
+    fn no_workgroups_dispatch_nothing() {
+        let (x, y) = check_grid(0);
+        assert_eq!(x * y, 0);
+    }
+
+    #[test]
+    fn wraps_past_one_dimension() {
+        assert_eq!(check_grid(65535), (65535, 1));
+        assert_eq!(check_grid(65536), (32768, 2));
+        assert_eq!(check_grid(65537), (32769, 2));
+    }
+
+    /// Most particles one storage binding holds, its size limit being a
+    /// `u32`
+    #[test]
+    fn covers_the_most_particles() {
+        let particle_count = u32::MAX / std::mem::size_of::<Particle>() as u32;
+        check_grid(particle_count.div_ceil(WORKGROUP_SIZE));
+        check_grid(u32::MAX.div_ceil(WORKGROUP_SIZE));
+    }
+
+    /// Dispatches `workgroup_count` workgroups through [`dispatch_linear`]
+    /// and checks every one of them finds its indices exactly once, the
+    /// spare workgroups of the last row finding ones past the end
+    fn check_indices(workgroup_count: u32) {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let source = wgsl::compose(&[
+            WGSL,
+            &format!("const WORKGROUP_COUNT: u32 = {workgroup_count}u;\n"),
+            r"
+            @group(0) @binding(0)
+            var<storage, read_write> hits: array<atomic<u32>>;
+
+            @group(0) @binding(1)
+            var<storage, read_write> spare: atomic<u32>;
+
+            @compute @workgroup_size(256)
+            fn main(
+                @builtin(global_invocation_id) global_id: vec3<u32>,
+                @builtin(workgroup_id) workgroup_id: vec3<u32>,
+                @builtin(num_workgroups) num_workgroups: vec3<u32>,
+            ) {
+                let index = invocation_index(global_id, num_workgroups);
+                let workgroup = workgroup_index(workgroup_id, num_workgroups);
+                if workgroup >= WORKGROUP_COUNT {
+                    if index >= WORKGROUP_COUNT * DISPATCH_WORKGROUP_SIZE {
+                        atomicAdd(&spare, 1u);
+                    }
+                    return;
+                }
+                if index / DISPATCH_WORKGROUP_SIZE == workgroup {
+                    atomicAdd(&hits[workgroup], 1u);
+                }
+            }
+            ",
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Dispatch Test Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[storage_entry(0, false), storage_entry(1, false)],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let hits = GpuBuffer::<u32>::with_contents(
+            &device,
+            "Test Hits",
+            usage,
+            &vec![0; workgroup_count as usize],
+        );
+        let spare = GpuBuffer::<u32>::with_contents(&device, "Test Spare", usage, &[0]);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: hits.buffer().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: spare.buffer().as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            dispatch_linear(&mut pass, workgroup_count);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let hits: Vec<u32> =
+            gpu_test::read_buffer(&device, &queue, hits.buffer(), workgroup_count as usize);
+        let spare: Vec<u32> = gpu_test::read_buffer(&device, &queue, spare.buffer(), 1);
+        assert!(hits.iter().all(|&hit| hit == WORKGROUP_SIZE));
+        let (x, y) = workgroup_grid(workgroup_count);
+        assert_eq!(spare[0], (x * y - workgroup_count) * WORKGROUP_SIZE);
+    }
+
+    #[test]
+    fn finds_indices_around_the_wrap() {
+        for workgroup_count in [1, 65535, 65536, 65537] {
+            check_indices(workgroup_count);
+        }
+    }
+
+    /// Indirect dispatches sized by the shaders wrap like direct ones
+    #[test]
+    fn sizes_the_grid_on_the_gpu_alike() {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let source = wgsl::compose(&[
+            WGSL,
+            r"
+            @group(0) @binding(0)
+            var<storage, read_write> grids: array<vec2<u32>>;
+
+            @compute @workgroup_size(1)
+            fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+                grids[global_id.x] = workgroup_grid(grids[global_id.x].x);
+            }
+            ",
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Test Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[storage_entry(0, false)],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let workgroup_counts = [0, 1, 65535, 65536, 65537, u32::MAX.div_ceil(WORKGROUP_SIZE)];
+        let grids = GpuBuffer::<[u32; 2]>::with_contents(
+            &device,
+            "Test Grids",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            &workgroup_counts.map(|count| [count, 0]),
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: grids.buffer().as_entire_binding(),
+            }],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(workgroup_counts.len() as u32, 1, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let grids: Vec<[u32; 2]> =
+            gpu_test::read_buffer(&device, &queue, grids.buffer(), workgroup_counts.len());
+        for (count, [x, y]) in workgroup_counts.into_iter().zip(grids) {
+            assert_eq!((x, y), workgroup_grid(count), "{count} workgroups");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use super::Particle;
+use super::grid::SpatialGrid;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Distance Coulomb forces are softened over, so close passes don't fling
+/// particles apart
+pub const COULOMB_SOFTENING: f32 = 0.5;
+
+/// Coulomb forces between the charges within `cutoff`, like charges pushing
+/// apart and unlike ones pulling together, added to the velocity divided by
+/// the mass. Every particle reads the others from before the pass.
+// Keep in sync with electrostatics.wgsl
+pub fn apply_coulomb_forces(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    strength: f32,
+    cutoff: f32,
+    delta_time: f32,
+) {
+    let cutoff_sq = cutoff * cutoff;
+    let softening_sq = COULOMB_SOFTENING * COULOMB_SOFTENING;
+
+    let impulses: Vec<Vec3> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            if particle.charge == 0.0 {
+                return Vec3::ZERO;
+            }
+            let position = Vec3::from(particle.position);
+            let mut field = Vec3::ZERO;
+
+            grid.for_each_neighbor(position, cutoff, |j| {
+                let other = &particles[j];
+                if j == i || other.charge == 0.0 {
+                    return;
+                }
+                let offset = grid.separation(position, Vec3::from(other.position));
+                let dist_sq = offset.length_squared();
+                if dist_sq >= cutoff_sq {
+                    return;
+                }
+                let soft_sq = dist_sq + softening_sq;
+                field += offset * (other.charge / (soft_sq * soft_sq.sqrt()));
+            });
+
+            field * (strength * particle.charge / particle.mass * delta_time)
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(impulses)
+        .for_each(|(particle, impulse)| {
+            particle.velocity = (Vec3::from(particle.velocity) + impulse).into();
+        });
+}
@@ -0,0 +1,390 @@
+use super::noise;
+use glam::Vec3;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Nodes a graph holds at most, as many as are uploaded to the GPU
+pub const MAX_FIELD_NODES: usize = 32;
+
+/// What a node of the [`FieldGraph`] computes. Every value passed along is a
+/// vector, scalars are spread over all three components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NodeKind {
+    /// Where the particle is
+    Position,
+    /// How fast it goes
+    Velocity,
+    /// Seconds since the simulation started
+    Time,
+    /// The node's value
+    Constant,
+    Add,
+    Subtract,
+    /// Component-wise
+    Multiply,
+    /// The input times the value
+    Scale,
+    Cross,
+    /// Zero for a zero input
+    Normalize,
+    Length,
+    /// Component-wise
+    Sin,
+    /// Curl noise of the input times the value, swirling without bunching up
+    Noise,
+    /// Accelerates the particle by the input times the value, every force
+    /// node is added up
+    Force,
+}
+
+impl NodeKind {
+    /// In the order the editor offers them
+    pub const ALL: [NodeKind; 14] = [
+        NodeKind::Position,
+        NodeKind::Velocity,
+        NodeKind::Time,
+        NodeKind::Constant,
+        NodeKind::Add,
+        NodeKind::Subtract,
+        NodeKind::Multiply,
+        NodeKind::Scale,
+        NodeKind::Cross,
+        NodeKind::Normalize,
+        NodeKind::Length,
+        NodeKind::Sin,
+        NodeKind::Noise,
+        NodeKind::Force,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NodeKind::Position => "Position",
+            NodeKind::Velocity => "Velocity",
+            NodeKind::Time => "Time",
+            NodeKind::Constant => "Constant",
+            NodeKind::Add => "Add",
+            NodeKind::Subtract => "Subtract",
+            NodeKind::Multiply => "Multiply",
+            NodeKind::Scale => "Scale",
+            NodeKind::Cross => "Cross",
+            NodeKind::Normalize => "Normalize",
+            NodeKind::Length => "Length",
+            NodeKind::Sin => "Sin",
+            NodeKind::Noise => "Noise",
+            NodeKind::Force => "Force",
+        }
+    }
+
+    /// Number of inputs
+    pub fn arity(self) -> usize {
+        match self {
+            NodeKind::Position | NodeKind::Velocity | NodeKind::Time | NodeKind::Constant => 0,
+            NodeKind::Add | NodeKind::Subtract | NodeKind::Multiply | NodeKind::Cross => 2,
+            _ => 1,
+        }
+    }
+
+    /// Whether other nodes can read it, only forces can't
+    pub fn has_output(self) -> bool {
+        self != NodeKind::Force
+    }
+
+    /// What the node's value stands for, `None` if it has none. Only
+    /// constants use all three components, the rest just the first.
+    pub fn value_label(self) -> Option<&'static str> {
+        match self {
+            NodeKind::Constant => Some("Value"),
+            NodeKind::Scale => Some("Factor"),
+            NodeKind::Noise => Some("Frequency"),
+            NodeKind::Force => Some("Strength"),
+            _ => None,
+        }
+    }
+
+    fn default_value(self) -> [f32; 3] {
+        match self {
+            NodeKind::Constant => [0.0, 1.0, 0.0],
+            NodeKind::Noise => [0.05, 0.0, 0.0],
+            NodeKind::Scale | NodeKind::Force => [1.0, 0.0, 0.0],
+            _ => [0.0; 3],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FieldNode {
+    pub kind: NodeKind,
+    /// The node feeding each input, unconnected ones read zero
+    pub inputs: [Option<usize>; 2],
+    pub value: [f32; 3],
+    /// Top left corner on the editor's canvas
+    pub position: [f32; 2],
+}
+
+/// What a compiled graph reads about a particle
+pub struct FieldSample {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub time: f32,
+}
+
+/// A graph compiled for the CPU, the acceleration at a sample
+pub type FieldFn = Arc<dyn Fn(&FieldSample) -> Vec3 + Send + Sync>;
+
+/// A force field wired from nodes in the editor, evaluated per particle on
+/// top of the force stack. The CPU backend runs it as a chain of closures
+/// from [`FieldGraph::compile`], the compute backend splices the WGSL from
+/// [`FieldGraph::wgsl`] into its shader.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FieldGraph {
+    pub enabled: bool,
+    pub nodes: Vec<FieldNode>,
+}
+
+impl Default for FieldGraph {
+    /// Curl noise of the position, to start from
+    fn default() -> Self {
+        let mut graph = Self {
+            enabled: false,
+            nodes: Vec::new(),
+        };
+        graph.add(NodeKind::Position, [20.0, 40.0]);
+        graph.add(NodeKind::Noise, [180.0, 40.0]);
+        graph.add(NodeKind::Force, [340.0, 40.0]);
+        graph.nodes[2].value[0] = 2.0;
+        graph.connect(0, 1, 0);
+        graph.connect(1, 2, 0);
+        graph
+    }
+}
+
+impl FieldGraph {
+    /// Whether it changes anything, enabled with a force node
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.nodes.iter().any(|node| node.kind == NodeKind::Force)
+    }
+
+    /// Adds an unconnected node, unless the graph is full
+    pub fn add(&mut self, kind: NodeKind, position: [f32; 2]) -> bool {
+        if self.nodes.len() >= MAX_FIELD_NODES {
+            return false;
+        }
+        self.nodes.push(FieldNode {
+            kind,
+            inputs: [None; 2],
+            value: kind.default_value(),
+            position,
+        });
+        true
+    }
+
+    /// Removes the node at `index` along with the wires from it
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.nodes.len() {
+            return;
+        }
+        self.nodes.remove(index);
+        for node in &mut self.nodes {
+            for input in &mut node.inputs {
+                *input = match *input {
+                    Some(i) if i == index => None,
+                    Some(i) if i > index => Some(i - 1),
+                    other => other,
+                };
+            }
+        }
+    }
+
+    /// Feeds the output of `from` into `input` of `to`, unless the nodes
+    /// can't be wired that way or it would close a loop
+    pub fn connect(&mut self, from: usize, to: usize, input: usize) -> bool {
+        let len = self.nodes.len();
+        if from >= len
+            || to >= len
+            || input >= self.nodes[to].kind.arity()
+            || !self.nodes[from].kind.has_output()
+            || self.depends_on(from, to)
+        {
+            return false;
+        }
+        self.nodes[to].inputs[input] = Some(from);
+        true
+    }
+
+    /// The node wired into `slot` of `node`, if it's one that can be
+    fn input(&self, node: usize, slot: usize) -> Option<usize> {
+        let kind = self.nodes[node].kind;
+        self.nodes[node].inputs[slot].filter(|&i| {
+            slot < kind.arity() && i < self.nodes.len() && self.nodes[i].kind.has_output()
+        })
+    }
+
+    /// Whether `node` reads the output of `target`, directly or through
+    /// others, or is it
+    fn depends_on(&self, node: usize, target: usize) -> bool {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![node];
+        while let Some(i) = stack.pop() {
+            if i == target {
+                return true;
+            }
+            if std::mem::replace(&mut seen[i], true) {
+                continue;
+            }
+            stack.extend((0..2).filter_map(|slot| self.input(i, slot)));
+        }
+        false
+    }
+
+    /// The nodes the forces read, each after its inputs, with the inputs
+    /// that would close a loop in a hand-edited file left unconnected
+    fn plan(&self) -> Vec<(usize, [Option<usize>; 2])> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Visiting,
+            Done,
+        }
+
+        fn visit(
+            graph: &FieldGraph,
+            node: usize,
+            marks: &mut [Mark],
+            plan: &mut Vec<(usize, [Option<usize>; 2])>,
+        ) {
+            marks[node] = Mark::Visiting;
+            let mut inputs = [None; 2];
+            for (slot, resolved) in inputs.iter_mut().enumerate() {
+                let Some(i) = graph.input(node, slot) else {
+                    continue;
+                };
+                match marks[i] {
+                    Mark::New => visit(graph, i, marks, plan),
+                    Mark::Visiting => continue,
+                    Mark::Done => {}
+                }
+                *resolved = Some(i);
+            }
+            marks[node] = Mark::Done;
+            plan.push((node, inputs));
+        }
+
+        let mut marks = vec![Mark::New; self.nodes.len()];
+        let mut plan = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.kind == NodeKind::Force && marks[index] == Mark::New {
+                visit(self, index, &mut marks, &mut plan);
+            }
+        }
+        plan
+    }
+
+    /// The graph as a chain of closures, `None` if it isn't active
+    // Keep in sync with `wgsl`
+    pub fn compile(&self) -> Option<FieldFn> {
+        if !self.is_active() {
+            return None;
+        }
+
+        let zero: FieldFn = Arc::new(|_: &FieldSample| Vec3::ZERO);
+        let mut compiled: Vec<Option<FieldFn>> = vec![None; self.nodes.len()];
+        let mut forces = Vec::new();
+        for (index, inputs) in self.plan() {
+            let node = &self.nodes[index];
+            let [a, b] = inputs.map(|input| {
+                input
+                    .and_then(|i| compiled[i].clone())
+                    .unwrap_or_else(|| zero.clone())
+            });
+            let value = Vec3::from(node.value);
+            let x = node.value[0];
+            let evaluate: FieldFn = match node.kind {
+                NodeKind::Position => Arc::new(|sample: &FieldSample| sample.position),
+                NodeKind::Velocity => Arc::new(|sample: &FieldSample| sample.velocity),
+                NodeKind::Time => Arc::new(|sample: &FieldSample| Vec3::splat(sample.time)),
+                NodeKind::Constant => Arc::new(move |_: &FieldSample| value),
+                NodeKind::Add => Arc::new(move |sample: &FieldSample| a(sample) + b(sample)),
+                NodeKind::Subtract => Arc::new(move |sample: &FieldSample| a(sample) - b(sample)),
+                NodeKind::Multiply => Arc::new(move |sample: &FieldSample| a(sample) * b(sample)),
+                NodeKind::Scale => Arc::new(move |sample: &FieldSample| a(sample) * x),
+                NodeKind::Cross => Arc::new(move |sample: &FieldSample| a(sample).cross(b(sample))),
+                NodeKind::Normalize => {
+                    Arc::new(move |sample: &FieldSample| a(sample).normalize_or_zero())
+                }
+                NodeKind::Length => {
+                    Arc::new(move |sample: &FieldSample| Vec3::splat(a(sample).length()))
+                }
+                NodeKind::Sin => Arc::new(move |sample: &FieldSample| {
+                    let v = a(sample);
+                    Vec3::new(v.x.sin(), v.y.sin(), v.z.sin())
+                }),
+                NodeKind::Noise => {
+                    Arc::new(move |sample: &FieldSample| noise::curl_noise(a(sample) * x))
+                }
+                NodeKind::Force => Arc::new(move |sample: &FieldSample| a(sample) * x),
+            };
+            if node.kind == NodeKind::Force {
+                forces.push(evaluate.clone());
+            }
+            compiled[index] = Some(evaluate);
+        }
+
+        Some(Arc::new(move |sample: &FieldSample| {
+            forces.iter().map(|force| force(sample)).sum()
+        }))
+    }
+
+    /// `fn field_graph(position, velocity) -> vec3<f32>` for the compute
+    /// shader, returning zero if it isn't active. The node values are read
+    /// from `field_nodes` by index, so only rewiring changes the source.
+    // Keep in sync with `compile`
+    pub fn wgsl(&self) -> String {
+        let mut source = String::from(
+            "fn field_graph(position: vec3<f32>, velocity: vec3<f32>) -> vec3<f32> {\n    \
+             var force = vec3<f32>(0.0);\n",
+        );
+        if self.is_active() {
+            for (index, inputs) in self.plan() {
+                let [a, b] = inputs.map(|input| {
+                    input.map_or_else(|| "vec3<f32>(0.0)".to_string(), |i| format!("n{i}"))
+                });
+                let x = format!("field_nodes[{index}].x");
+                let expression = match self.nodes[index].kind {
+                    NodeKind::Position => "position".to_string(),
+                    NodeKind::Velocity => "velocity".to_string(),
+                    NodeKind::Time => "vec3<f32>(params.time)".to_string(),
+                    NodeKind::Constant => format!("field_nodes[{index}].xyz"),
+                    NodeKind::Add => format!("{a} + {b}"),
+                    NodeKind::Subtract => format!("{a} - {b}"),
+                    NodeKind::Multiply => format!("{a} * {b}"),
+                    NodeKind::Scale | NodeKind::Force => format!("{a} * {x}"),
+                    NodeKind::Cross => format!("cross({a}, {b})"),
+                    NodeKind::Normalize => {
+                        format!("select(vec3<f32>(0.0), normalize({a}), dot({a}, {a}) > 0.0)")
+                    }
+                    NodeKind::Length => format!("vec3<f32>(length({a}))"),
+                    NodeKind::Sin => format!("sin({a})"),
+                    NodeKind::Noise => format!("curl_noise({a} * {x})"),
+                };
+                if self.nodes[index].kind == NodeKind::Force {
+                    let _ = writeln!(source, "    force += {expression};");
+                } else {
+                    let _ = writeln!(source, "    let n{index} = {expression};");
+                }
+            }
+        }
+        source.push_str("    return force;\n}\n");
+        source
+    }
+
+    /// The node values the shader from [`FieldGraph::wgsl`] reads, one per
+    /// node
+    pub fn gpu_values(&self) -> Vec<[f32; 4]> {
+        self.nodes
+            .iter()
+            .take(MAX_FIELD_NODES)
+            .map(|node| [node.value[0], node.value[1], node.value[2], 0.0])
+            .collect()
+    }
+}
@@ -0,0 +1,201 @@
+use super::background_grid::BackgroundGrid;
+use super::dispatch::{self, WORKGROUP_SIZE, dispatch_linear};
+use super::gpu_buffer::{GpuBuffer, TrackedBindGroup, storage_entry, uniform_entry};
+use super::{Particle, SimParams};
+use crate::wgsl;
+
+/// Fewest and most cells along the longest side of the box the liquid grid
+/// is made of
+pub const MIN_LIQUID_RESOLUTION: u32 = 8;
+pub const MAX_LIQUID_RESOLUTION: u32 = 96;
+/// Most red-black sweeps of the pressure solve per step
+pub const MAX_PRESSURE_ITERATIONS: u32 = 200;
+
+/// FLIP/PIC liquid on a [`BackgroundGrid`] over the container box, the
+/// [`super::SimulationMethod::Liquid`] backend's part of every step.
+///
+/// The particles splat their velocities onto the faces of the cells with
+/// trilinear weights, cells holding any particle count as fluid and the rest
+/// as air. A red-black Gauss-Seidel solve finds the pressure whose gradient
+/// takes the divergence out of the faces, walls letting nothing through, and
+/// the particles take back the projected velocities blended by
+/// `flip_ratio`. Integration moves them afterwards, forces included.
+pub struct FlipSolver {
+    grid_buffer: GpuBuffer<BackgroundGrid>,
+    /// Velocity times weight and weight of the three faces of every node, as
+    /// fixed point
+    accumulators: GpuBuffer<i32>,
+    /// Velocities of the faces on the low sides of every node's cell
+    velocities: GpuBuffer<[f32; 4]>,
+    /// The face velocities before the projection, w the cell's divergence
+    saved: GpuBuffer<[f32; 4]>,
+    /// Particles in every cell
+    cell_fill: GpuBuffer<u32>,
+    /// Kept between steps, the last step's pressure is a good first guess
+    pressure: GpuBuffer<f32>,
+    particle_to_grid_pipeline: wgpu::ComputePipeline,
+    normalize_pipeline: wgpu::ComputePipeline,
+    divergence_pipeline: wgpu::ComputePipeline,
+    relax_red_pipeline: wgpu::ComputePipeline,
+    relax_black_pipeline: wgpu::ComputePipeline,
+    subtract_gradient_pipeline: wgpu::ComputePipeline,
+    grid_to_particle_pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+}
+
+impl FlipSolver {
+    pub fn new(
+        device: &wgpu::Device,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+    ) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let grid_buffer =
+            GpuBuffer::with_capacity(device, "Liquid Grid Buffer", wgpu::BufferUsages::UNIFORM, 1);
+        let accumulators = GpuBuffer::with_capacity(device, "Liquid Accumulators", storage, 1);
+        let velocities = GpuBuffer::with_capacity(device, "Liquid Face Velocities", storage, 1);
+        let saved = GpuBuffer::with_capacity(device, "Liquid Saved Velocities", storage, 1);
+        let cell_fill = GpuBuffer::with_capacity(device, "Liquid Cell Fill", storage, 1);
+        let pressure = GpuBuffer::with_capacity(device, "Liquid Pressure", storage, 1);
+
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            BackgroundGrid::WGSL,
+            BackgroundGrid::HELPERS_WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/flip.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Liquid Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // Particles, params, grid, accumulators, velocities, saved, fill,
+        // pressure
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Liquid Bind Group Layout"),
+            entries: &[
+                storage_entry(0, false),
+                uniform_entry(1),
+                uniform_entry(2),
+                storage_entry(3, false),
+                storage_entry(4, false),
+                storage_entry(5, false),
+                storage_entry(6, false),
+                storage_entry(7, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Liquid Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "Liquid Bind Group",
+            layout,
+            &[
+                particles,
+                sim_params,
+                &grid_buffer,
+                &accumulators,
+                &velocities,
+                &saved,
+                &cell_fill,
+                &pressure,
+            ],
+        );
+
+        Self {
+            grid_buffer,
+            accumulators,
+            velocities,
+            saved,
+            cell_fill,
+            pressure,
+            particle_to_grid_pipeline: pipeline("particle_to_grid"),
+            normalize_pipeline: pipeline("normalize_faces"),
+            divergence_pipeline: pipeline("compute_divergence"),
+            relax_red_pipeline: pipeline("relax_red"),
+            relax_black_pipeline: pipeline("relax_black"),
+            subtract_gradient_pipeline: pipeline("subtract_gradient"),
+            grid_to_particle_pipeline: pipeline("grid_to_particle"),
+            bind_group,
+        }
+    }
+
+    /// Records the passes carrying the first `grid.particle_count` particles'
+    /// velocities through the grid, with `iterations` sweeps of the pressure
+    /// solve. `particles` and `sim_params` are the buffers it was created
+    /// with.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        grid: BackgroundGrid,
+        iterations: u32,
+    ) {
+        let node_count = grid.node_count as usize;
+        let cell_count = grid.cell_count as usize;
+        self.grid_buffer.write(device, queue, &[grid]);
+        self.accumulators.reserve(device, node_count * 6);
+        self.velocities.reserve(device, node_count);
+        self.saved.reserve(device, node_count);
+        self.cell_fill.reserve(device, cell_count);
+        self.pressure.reserve(device, cell_count);
+        encoder.clear_buffer(self.accumulators.buffer(), 0, None);
+        encoder.clear_buffer(self.cell_fill.buffer(), 0, None);
+
+        let bind_group = self.bind_group.get(
+            device,
+            &[
+                particles,
+                sim_params,
+                &self.grid_buffer,
+                &self.accumulators,
+                &self.velocities,
+                &self.saved,
+                &self.cell_fill,
+                &self.pressure,
+            ],
+        );
+
+        let particle_groups = grid.particle_count.div_ceil(WORKGROUP_SIZE);
+        let node_groups = grid.node_count.div_ceil(WORKGROUP_SIZE);
+        let cell_groups = grid.cell_count.div_ceil(WORKGROUP_SIZE);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Liquid Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        let mut run = |pipeline: &wgpu::ComputePipeline, workgroups: u32| {
+            pass.set_pipeline(pipeline);
+            dispatch_linear(&mut pass, workgroups);
+        };
+        run(&self.particle_to_grid_pipeline, particle_groups);
+        run(&self.normalize_pipeline, node_groups);
+        run(&self.divergence_pipeline, cell_groups);
+        for _ in 0..iterations {
+            run(&self.relax_red_pipeline, cell_groups);
+            run(&self.relax_black_pipeline, cell_groups);
+        }
+        run(&self.subtract_gradient_pipeline, node_groups);
+        run(&self.grid_to_particle_pipeline, particle_groups);
+    }
+}
@@ -0,0 +1,60 @@
+use super::grid::SpatialGrid;
+use super::{Particle, SimParams};
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Reynolds boids: every particle steers away from flockmates that are too
+/// close (separation), towards their average heading (alignment) and towards
+/// their center (cohesion), seeing only those within `params.boid_radius`.
+/// The steering is added to the velocity (unit mass), which is then capped at
+/// `params.boid_max_speed`.
+// Keep in sync with boids.wgsl
+pub fn apply_flocking(particles: &mut [Particle], grid: &SpatialGrid, params: &SimParams) {
+    let radius = params.boid_radius;
+    let radius_sq = radius * radius;
+
+    let velocities: Vec<Vec3> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let mut velocity = Vec3::from(particle.velocity);
+            let mut separation = Vec3::ZERO;
+            let mut heading = Vec3::ZERO;
+            let mut center = Vec3::ZERO;
+            let mut flockmates = 0;
+
+            grid.for_each_neighbor(position, radius, |j| {
+                if j == i {
+                    return;
+                }
+                let offset = grid.separation(position, Vec3::from(particles[j].position));
+                let dist_sq = offset.length_squared();
+                if dist_sq >= radius_sq || dist_sq == 0.0 {
+                    return;
+                }
+                separation += offset / dist_sq;
+                heading += Vec3::from(particles[j].velocity);
+                center -= offset;
+                flockmates += 1;
+            });
+
+            if flockmates > 0 {
+                let count = flockmates as f32;
+                let steering = separation * params.boid_separation
+                    + (heading / count - velocity) * params.boid_alignment
+                    + center / count * params.boid_cohesion;
+                velocity += steering * params.delta_time;
+            }
+            if params.boid_max_speed > 0.0 {
+                velocity = velocity.clamp_length_max(params.boid_max_speed);
+            }
+            velocity
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(velocities)
+        .for_each(|(particle, velocity)| particle.velocity = velocity.into());
+}
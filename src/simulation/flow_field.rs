@@ -0,0 +1,95 @@
+use super::Particle;
+use glam::{UVec3, Vec3};
+use rayon::prelude::*;
+
+/// Cells along the longest side of the sampled particles
+const RESOLUTION: u32 = 16;
+
+/// Mean velocity and particle density of one layer binned on a coarse grid,
+/// which the particles of another layer get dragged along (one-way coupling)
+pub struct FlowField {
+    min: Vec3,
+    cell_size: f32,
+    cells_per_axis: UVec3,
+    velocities: Vec<Vec3>,
+    /// Particles per cell over the mean of the occupied cells
+    densities: Vec<f32>,
+}
+
+impl FlowField {
+    /// Bins `particles`, usually an evenly spread sample of the source layer
+    pub fn from_particles(particles: &[Particle]) -> Self {
+        let (min, max) = if particles.is_empty() {
+            (Vec3::ZERO, Vec3::ONE)
+        } else {
+            particles.iter().fold(
+                (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                |(min, max), p| (min.min(p.position.into()), max.max(p.position.into())),
+            )
+        };
+        let cell_size = ((max - min).max_element() / RESOLUTION as f32).max(1e-3);
+        let cells_per_axis = ((max - min) / cell_size).floor().as_uvec3() + UVec3::ONE;
+
+        let cell_count = cells_per_axis.element_product() as usize;
+        let mut velocities = vec![Vec3::ZERO; cell_count];
+        let mut counts = vec![0u32; cell_count];
+        let mut field = Self {
+            min,
+            cell_size,
+            cells_per_axis,
+            velocities: Vec::new(),
+            densities: Vec::new(),
+        };
+        for particle in particles {
+            if let Some(cell) = field.cell_index(particle.position.into()) {
+                velocities[cell] += Vec3::from(particle.velocity);
+                counts[cell] += 1;
+            }
+        }
+
+        let occupied = counts.iter().filter(|&&count| count > 0).count().max(1);
+        let mean_count = particles.len() as f32 / occupied as f32;
+        for (velocity, &count) in velocities.iter_mut().zip(&counts) {
+            *velocity /= count.max(1) as f32;
+        }
+        field.velocities = velocities;
+        field.densities = counts
+            .iter()
+            .map(|&count| count as f32 / mean_count.max(1.0))
+            .collect();
+        field
+    }
+
+    /// Flow velocity and relative density at `position`, nothing outside the
+    /// sampled region
+    pub fn sample(&self, position: Vec3) -> Option<(Vec3, f32)> {
+        let cell = self.cell_index(position)?;
+        Some((self.velocities[cell], self.densities[cell]))
+    }
+
+    fn cell_index(&self, position: Vec3) -> Option<usize> {
+        let cell = ((position - self.min) / self.cell_size).floor();
+        if cell.min_element() < 0.0 {
+            return None;
+        }
+        let cell = cell.as_uvec3();
+        if cell.cmpge(self.cells_per_axis).any() {
+            return None;
+        }
+        let size = self.cells_per_axis;
+        Some((cell.x + size.x * (cell.y + size.y * cell.z)) as usize)
+    }
+}
+
+/// Relaxes every particle's velocity towards the flow around it at a rate of
+/// `strength` per second, faster where the source layer is denser
+pub fn apply_flow(particles: &mut [Particle], field: &FlowField, strength: f32, delta_time: f32) {
+    particles.par_iter_mut().for_each(|particle| {
+        let Some((flow, density)) = field.sample(particle.position.into()) else {
+            return;
+        };
+        let velocity = Vec3::from(particle.velocity);
+        let blend = (strength * density.min(1.0) * delta_time).min(1.0);
+        particle.velocity = velocity.lerp(flow, blend).into();
+    });
+}
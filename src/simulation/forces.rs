@@ -0,0 +1,266 @@
+use super::attractors::PointAttractor;
+use super::layout;
+use super::noise;
+use super::{GRAVITY_UNIFORM, SimParams, attractor_acceleration, central_gravity};
+use glam::Vec3;
+
+/// Forces uploaded to the GPU at most, the rest are ignored
+pub const MAX_FORCES: usize = 16;
+
+// Keep in sync with the `FORCE_*` constants in the compute shader
+pub const FORCE_GRAVITY: u32 = 0;
+pub const FORCE_ATTRACTOR: u32 = 1;
+pub const FORCE_POINT_ATTRACTORS: u32 = 2;
+pub const FORCE_TURBULENCE: u32 = 3;
+pub const FORCE_WIND: u32 = 4;
+pub const FORCE_DRAG: u32 = 5;
+pub const FORCE_VORTEX: u32 = 6;
+
+/// One component of the [`ForceStack`]. The built-in fields read their
+/// settings from [`SimParams`] like before, drag and vortices carry their
+/// own so there can be any number of them.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Force {
+    /// Uniform or central, by the gravity settings
+    Gravity,
+    /// The central attractor
+    Attractor,
+    /// Every placed point attractor and black hole
+    PointAttractors,
+    Turbulence,
+    Wind,
+    /// Slows particles down by `coefficient` of their velocity per second
+    Drag {
+        coefficient: f32,
+    },
+    /// Swirls particles within `radius` of the line through `center` along
+    /// `axis` around it, counterclockwise looking down the axis
+    Vortex {
+        center: Vec3,
+        axis: Vec3,
+        /// Acceleration on the axis, fading out towards `radius`
+        strength: f32,
+        radius: f32,
+    },
+}
+
+impl Force {
+    /// The built-in forces, in the order they were applied before the stack
+    pub const BUILT_IN: [Force; 5] = [
+        Force::Gravity,
+        Force::Attractor,
+        Force::PointAttractors,
+        Force::Turbulence,
+        Force::Wind,
+    ];
+
+    pub fn drag() -> Self {
+        Force::Drag { coefficient: 0.5 }
+    }
+
+    pub fn vortex() -> Self {
+        Force::Vortex {
+            center: Vec3::ZERO,
+            axis: Vec3::Y,
+            strength: 5.0,
+            radius: 30.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Force::Gravity => "Gravity",
+            Force::Attractor => "Central Attractor",
+            Force::PointAttractors => "Point Attractors",
+            Force::Turbulence => "Turbulence",
+            Force::Wind => "Wind",
+            Force::Drag { .. } => "Drag",
+            Force::Vortex { .. } => "Vortex",
+        }
+    }
+
+    /// Acceleration of a particle at `position` moving at `velocity`
+    // Keep in sync with `force_acceleration` in the compute shader
+    pub fn acceleration(
+        &self,
+        position: Vec3,
+        velocity: Vec3,
+        params: &SimParams,
+        point_attractors: &[PointAttractor],
+    ) -> Vec3 {
+        match *self {
+            Force::Gravity => {
+                let mut acceleration = Vec3::from(params.gravity);
+                // Fall towards the center of gravity instead of down
+                if params.gravity_mode != GRAVITY_UNIFORM {
+                    acceleration += central_gravity(
+                        position,
+                        Vec3::from(params.gravity_center),
+                        params.central_gravity,
+                        params.gravity_mode,
+                    );
+                }
+                acceleration
+            }
+            Force::Attractor if params.attractor_mass > 0.0 => attractor_acceleration(
+                position,
+                Vec3::from(params.attractor_position),
+                params.attractor_mass,
+            ),
+            Force::PointAttractors => point_attractors
+                .iter()
+                .map(|point| point.acceleration(position))
+                .sum(),
+            Force::Turbulence if params.turbulence_amplitude > 0.0 => {
+                noise::turbulence(
+                    position,
+                    params.turbulence_frequency,
+                    params.turbulence_scroll,
+                ) * params.turbulence_amplitude
+            }
+            Force::Wind if params.wind != [0.0; 3] => {
+                let wind = Vec3::from(params.wind);
+                wind * noise::gust_factor(position, wind, params.wind_gustiness, params.time)
+            }
+            Force::Drag { coefficient } => -velocity * coefficient,
+            Force::Vortex {
+                center,
+                axis,
+                strength,
+                radius,
+            } => vortex_acceleration(position, center, axis, strength, radius),
+            _ => Vec3::ZERO,
+        }
+    }
+
+    pub fn to_gpu(self) -> GpuForce {
+        let (kind, strength, radius, center, axis) = match self {
+            Force::Gravity => (FORCE_GRAVITY, 0.0, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::Attractor => (FORCE_ATTRACTOR, 0.0, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::PointAttractors => (FORCE_POINT_ATTRACTORS, 0.0, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::Turbulence => (FORCE_TURBULENCE, 0.0, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::Wind => (FORCE_WIND, 0.0, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::Drag { coefficient } => (FORCE_DRAG, coefficient, 0.0, Vec3::ZERO, Vec3::ZERO),
+            Force::Vortex {
+                center,
+                axis,
+                strength,
+                radius,
+            } => (
+                FORCE_VORTEX,
+                strength,
+                radius,
+                center,
+                axis.normalize_or(Vec3::Y),
+            ),
+        };
+        GpuForce {
+            kind,
+            strength,
+            radius,
+            _padding0: 0,
+            center: center.into(),
+            _padding1: 0,
+            axis: axis.into(),
+            _padding2: 0,
+        }
+    }
+}
+
+/// Tangential pull around the axis, quadratically fading out like the point
+/// attractors
+// Keep in sync with `vortex_acceleration` in the compute shader
+fn vortex_acceleration(
+    position: Vec3,
+    center: Vec3,
+    axis: Vec3,
+    strength: f32,
+    radius: f32,
+) -> Vec3 {
+    let axis = axis.normalize_or(Vec3::Y);
+    let offset = position - center;
+    let radial = offset - axis * offset.dot(axis);
+    let dist = radial.length();
+    if dist >= radius || dist <= 0.0 {
+        return Vec3::ZERO;
+    }
+    let falloff = 1.0 - dist / radius;
+    axis.cross(radial) / dist * strength * falloff * falloff
+}
+
+layout::gpu_struct! {
+    pub struct GpuForce (version 1) {
+        /// One of the `FORCE_*` kinds
+        pub kind: u32 => "u32",
+        /// Drag coefficient or vortex strength
+        pub strength: f32 => "f32",
+        pub radius: f32 => "f32",
+        pub _padding0: u32 => "u32",
+        pub center: [f32; 3] => "vec3<f32>",
+        pub _padding1: u32 => "u32",
+        /// Unit length
+        pub axis: [f32; 3] => "vec3<f32>",
+        pub _padding2: u32 => "u32",
+    }
+}
+
+/// A force in the stack and whether it's applied
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ForceLayer {
+    pub force: Force,
+    pub enabled: bool,
+}
+
+/// The forces that depend only on where a particle is and how fast it goes,
+/// summed in order on top of each other. Both backends apply the enabled
+/// ones, the GPU one from a tagged array of [`GpuForce`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ForceStack {
+    pub layers: Vec<ForceLayer>,
+}
+
+impl Default for ForceStack {
+    fn default() -> Self {
+        Self {
+            layers: Force::BUILT_IN
+                .into_iter()
+                .map(|force| ForceLayer {
+                    force,
+                    enabled: true,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ForceStack {
+    /// The enabled forces, as many as fit on the GPU
+    pub fn active(&self) -> impl Iterator<Item = Force> + '_ {
+        self.layers
+            .iter()
+            .filter(|layer| layer.enabled)
+            .map(|layer| layer.force)
+            .take(MAX_FORCES)
+    }
+
+    /// Number of enabled forces, applied or not
+    pub fn enabled_count(&self) -> usize {
+        self.layers.iter().filter(|layer| layer.enabled).count()
+    }
+
+    /// Whether a force of the same kind is in the stack, enabled or not
+    pub fn contains(&self, force: Force) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| std::mem::discriminant(&layer.force) == std::mem::discriminant(&force))
+    }
+
+    /// Moves the force at `from` to `to`, shifting the ones between
+    pub fn move_layer(&mut self, from: usize, to: usize) {
+        if from < self.layers.len() && to < self.layers.len() {
+            let layer = self.layers.remove(from);
+            self.layers.insert(to, layer);
+        }
+    }
+}
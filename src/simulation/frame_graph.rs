@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// Recording of one pass, given what the graph runs on and the encoder
+type Record<'a, C> = Box<dyn FnOnce(&mut C, &mut wgpu::CommandEncoder) + 'a>;
+
+/// The passes of one step, declared with the resources they read and write
+/// instead of recorded straight into an encoder.
+///
+/// Passes run in the order they are declared and depend on the last earlier
+/// pass writing each resource they read. Passes whose writes never reach one
+/// of the graph's outputs are skipped, so a build nothing reads costs
+/// nothing. wgpu inserts the barriers between dependent passes itself.
+pub struct FrameGraph<'a, C> {
+    outputs: &'static [&'static str],
+    passes: Vec<(PassInfo, Record<'a, C>)>,
+}
+
+/// A declared pass, without its recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassInfo {
+    pub name: &'static str,
+    pub reads: &'static [&'static str],
+    pub writes: &'static [&'static str],
+    /// Indices of the passes whose writes it reads
+    pub inputs: Vec<usize>,
+    /// Set when nothing it writes is used
+    pub skipped: bool,
+}
+
+/// The passes of the last execution in order, to show what a step does
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FramePlan {
+    pub passes: Vec<PassInfo>,
+}
+
+impl<'a, C> FrameGraph<'a, C> {
+    /// A graph whose passes are only kept if they lead to `outputs`
+    pub fn new(outputs: &'static [&'static str]) -> Self {
+        Self {
+            outputs,
+            passes: Vec::new(),
+        }
+    }
+
+    pub fn pass(
+        &mut self,
+        name: &'static str,
+        reads: &'static [&'static str],
+        writes: &'static [&'static str],
+        record: impl FnOnce(&mut C, &mut wgpu::CommandEncoder) + 'a,
+    ) {
+        let mut inputs: Vec<usize> = reads
+            .iter()
+            .filter_map(|resource| {
+                self.passes
+                    .iter()
+                    .rposition(|(earlier, _)| earlier.writes.contains(resource))
+            })
+            .collect();
+        inputs.sort_unstable();
+        inputs.dedup();
+        self.passes.push((
+            PassInfo {
+                name,
+                reads,
+                writes,
+                inputs,
+                skipped: false,
+            },
+            Box::new(record),
+        ));
+    }
+
+    /// Records the passes that lead to an output into `encoder`, returning
+    /// the plan they ran by
+    pub fn execute(mut self, context: &mut C, encoder: &mut wgpu::CommandEncoder) -> FramePlan {
+        // Walk back from the outputs, a pass is needed if a needed one reads
+        // what it wrote
+        let mut needed: Vec<bool> = self
+            .passes
+            .iter()
+            .map(|(info, _)| info.writes.iter().any(|w| self.outputs.contains(w)))
+            .collect();
+        for index in (0..self.passes.len()).rev() {
+            if needed[index] {
+                for &dependency in &self.passes[index].0.inputs {
+                    needed[dependency] = true;
+                }
+            }
+        }
+
+        let mut plan = FramePlan::default();
+        for ((mut info, record), needed) in self.passes.drain(..).zip(needed) {
+            if needed {
+                record(context, encoder);
+            }
+            info.skipped = !needed;
+            plan.passes.push(info);
+        }
+        plan
+    }
+}
+
+impl FramePlan {
+    /// The graph in Graphviz's DOT language
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frame {\n    rankdir=LR;\n");
+        for (index, pass) in self.passes.iter().enumerate() {
+            let style = if pass.skipped { ", style=dashed" } else { "" };
+            dot += &format!("    p{index} [label=\"{}\"{style}];\n", pass.name);
+            for dependency in &pass.inputs {
+                dot += &format!("    p{dependency} -> p{index};\n");
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+impl fmt::Display for FramePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, pass) in self.passes.iter().enumerate() {
+            write!(f, "{}. {}", index + 1, pass.name)?;
+            if pass.skipped {
+                write!(f, " (skipped)")?;
+            }
+            writeln!(f)?;
+            writeln!(f, "   reads {}", pass.reads.join(", "))?;
+            writeln!(f, "   writes {}", pass.writes.join(", "))?;
+        }
+        Ok(())
+    }
+}
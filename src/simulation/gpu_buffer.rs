@@ -0,0 +1,251 @@
+use bytemuck::Pod;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use wgpu::util::DeviceExt;
+
+/// Every allocation gets a fresh id so bind groups can tell when a buffer they
+/// were built from has been replaced
+static NEXT_ALLOCATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_allocation() -> u64 {
+    NEXT_ALLOCATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Typed GPU buffer that remembers its label, usage and capacity, and grows
+/// itself when a write doesn't fit.
+pub struct GpuBuffer<T> {
+    buffer: wgpu::Buffer,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    capacity: usize,
+    allocation: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> GpuBuffer<T> {
+    pub fn with_contents(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        contents: &[T],
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        Self {
+            buffer: Self::create(device, label, usage, contents),
+            label,
+            usage,
+            capacity: contents.len(),
+            allocation: next_allocation(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_capacity(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: usize,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: Self::byte_size(capacity),
+            usage,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            label,
+            usage,
+            capacity,
+            allocation: next_allocation(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uploads `contents` to the start of the buffer, reallocating it if they
+    /// don't fit. Anything past `contents` is lost when that happens, and
+    /// [`TrackedBindGroup`]s using the buffer rebuild on their next use.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[T]) {
+        if contents.len() > self.capacity {
+            self.buffer = Self::create(device, self.label, self.usage, contents);
+            self.capacity = contents.len();
+            self.allocation = next_allocation();
+        } else if !contents.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(contents));
+        }
+    }
+
+    /// Makes room for `capacity` elements, dropping the contents if the buffer
+    /// has to be reallocated
+    pub fn reserve(&mut self, device: &wgpu::Device, capacity: usize) {
+        if capacity > self.capacity {
+            self.buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.label),
+                size: Self::byte_size(capacity),
+                usage: self.usage,
+                mapped_at_creation: false,
+            });
+            self.capacity = capacity;
+            self.allocation = next_allocation();
+        }
+    }
+
+    /// Records a copy of the first `len` elements of `source` to the start
+    /// of the buffer, reallocating it first if they don't fit
+    pub fn copy_from(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &GpuBuffer<T>,
+        len: usize,
+    ) {
+        self.reserve(device, len);
+        if len > 0 {
+            let size = (len * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            encoder.copy_buffer_to_buffer(&source.buffer, 0, &self.buffer, 0, size);
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        contents: &[T],
+    ) -> wgpu::Buffer {
+        if contents.is_empty() {
+            // Zero-sized buffers can't be bound, keep room for one element
+            return device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: Self::byte_size(0),
+                usage,
+                mapped_at_creation: false,
+            });
+        }
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(contents),
+            usage,
+        })
+    }
+
+    fn byte_size(capacity: usize) -> wgpu::BufferAddress {
+        (capacity.max(1) * std::mem::size_of::<T>()) as wgpu::BufferAddress
+    }
+}
+
+/// Anything that can back a bind group entry and may be reallocated
+pub trait TrackedResource {
+    /// Changes whenever the underlying resource is replaced
+    fn allocation(&self) -> u64;
+    fn binding(&self) -> wgpu::BindingResource<'_>;
+}
+
+impl<T> TrackedResource for GpuBuffer<T> {
+    fn allocation(&self) -> u64 {
+        self.allocation
+    }
+
+    fn binding(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+}
+
+/// Bind group over [`TrackedResource`]s, bound in order starting at binding 0,
+/// that rebuilds itself when any of them has been reallocated.
+pub struct TrackedBindGroup {
+    label: &'static str,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    built_from: Vec<u64>,
+}
+
+impl TrackedBindGroup {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        layout: wgpu::BindGroupLayout,
+        resources: &[&dyn TrackedResource],
+    ) -> Self {
+        let bind_group = Self::create(device, label, &layout, resources);
+        Self {
+            label,
+            layout,
+            bind_group,
+            built_from: resources.iter().map(|r| r.allocation()).collect(),
+        }
+    }
+
+    /// Returns the bind group, rebuilding it first if `resources` differ from
+    /// the ones it was built from
+    pub fn get(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &[&dyn TrackedResource],
+    ) -> &wgpu::BindGroup {
+        let stale = self.built_from.len() != resources.len()
+            || self
+                .built_from
+                .iter()
+                .zip(resources)
+                .any(|(&built, resource)| built != resource.allocation());
+        if stale {
+            self.bind_group = Self::create(device, self.label, &self.layout, resources);
+            self.built_from = resources.iter().map(|r| r.allocation()).collect();
+        }
+        &self.bind_group
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        label: &'static str,
+        layout: &wgpu::BindGroupLayout,
+        resources: &[&dyn TrackedResource],
+    ) -> wgpu::BindGroup {
+        let entries: Vec<_> = resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.binding(),
+            })
+            .collect();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &entries,
+        })
+    }
+}
+
+/// Storage buffer entry for compute passes
+pub fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Uniform buffer entry for compute passes
+pub fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,267 @@
+use super::Particle;
+use super::dispatch::{self, dispatch_linear};
+use super::gpu_buffer::{
+    GpuBuffer, TrackedBindGroup, TrackedResource, storage_entry, uniform_entry,
+};
+use super::gpu_scan::GpuPrefixSum;
+use super::layout;
+use crate::wgsl;
+use glam::Vec3;
+
+/// Threads per workgroup in every grid pass
+const WORKGROUP_SIZE: u32 = 256;
+/// Keeps the per-bucket dispatches under the 65535 workgroup limit, larger
+/// scenes just share buckets
+const MAX_TABLE_SIZE: u32 = 1 << 23;
+
+layout::gpu_struct! {
+    pub struct GridParams (version 1) {
+        pub cell_size: [f32; 3] => "vec3<f32>",
+        /// Number of hash buckets, a power of two
+        pub table_size: u32 => "u32",
+
+        /// Half extents of the periodic box, unused when open
+        pub half_extents: [f32; 3] => "vec3<f32>",
+        /// 0 = open, 1 = periodic
+        pub periodic: u32 => "u32",
+
+        /// Whole cells spanning the periodic box
+        pub cells_per_axis: [f32; 3] => "vec3<f32>",
+        pub particle_count: u32 => "u32",
+    }
+}
+
+impl GridParams {
+    /// Cells of `cell_size` over `particle_count` particles, wrapping around
+    /// the box of `periodic` half extents if set
+    pub fn new(particle_count: u32, cell_size: f32, periodic: Option<Vec3>) -> Self {
+        // Keep in sync with `SpatialGrid::build`
+        let cell_size = cell_size.max(0.001);
+        let (cell_size, cells_per_axis) = match periodic {
+            Some(half_extents) => {
+                let box_size = half_extents * 2.0;
+                let cells_per_axis = (box_size / cell_size).floor().max(Vec3::ONE);
+                (box_size / cells_per_axis, cells_per_axis)
+            }
+            None => (Vec3::splat(cell_size), Vec3::ONE),
+        };
+        Self {
+            cell_size: cell_size.into(),
+            table_size: (particle_count.max(1).next_power_of_two() * 2).min(MAX_TABLE_SIZE),
+            half_extents: periodic.unwrap_or(Vec3::ZERO).into(),
+            periodic: periodic.is_some() as u32,
+            cells_per_axis: cells_per_axis.into(),
+            particle_count,
+        }
+    }
+}
+
+/// Uniform spatial hash grid built on the GPU, the compute shader
+/// counterpart of [`super::grid::SpatialGrid`].
+///
+/// Every build runs a counting sort over the particle buffer: hash and count
+/// each particle's cell, prefix sum the counts with [`GpuPrefixSum`] and
+/// scatter the particle indices.
+/// Afterwards the particles in bucket `h` are
+/// `cell_entries[cell_start[h]..cell_start[h + 1]]`.
+///
+/// Other passes bind [`GpuSpatialGrid::lookup_bind_group`] (grid params,
+/// cell starts and entries at bindings 0 to 2) and prepend
+/// [`GpuSpatialGrid::LOOKUP_WGSL`] for `grid_cell_of`, `grid_hash_cell` and
+/// `grid_separation`, which expect the params as `grid`.
+pub struct GpuSpatialGrid {
+    param_buffer: GpuBuffer<GridParams>,
+    cell_counts: GpuBuffer<u32>,
+    cell_start: GpuBuffer<u32>,
+    particle_cells: GpuBuffer<u32>,
+    cell_entries: GpuBuffer<u32>,
+    scan: GpuPrefixSum,
+    count_pipeline: wgpu::ComputePipeline,
+    reorder_pipeline: wgpu::ComputePipeline,
+    build_bind_group: TrackedBindGroup,
+    lookup_layout: wgpu::BindGroupLayout,
+    lookup_bind_group: TrackedBindGroup,
+}
+
+impl GpuSpatialGrid {
+    /// `GridParams` and the lookup helpers, for shaders reading the grid
+    pub const LOOKUP_WGSL: &'static str =
+        concat!(include_str!("../shaders/grid_common.wgsl"), "\n");
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let param_buffer =
+            GpuBuffer::with_capacity(device, "Grid Params Buffer", wgpu::BufferUsages::UNIFORM, 1);
+        let cell_counts = GpuBuffer::with_capacity(
+            device,
+            "Grid Cell Counts",
+            storage | wgpu::BufferUsages::COPY_SRC,
+            1,
+        );
+        let cell_start = GpuBuffer::with_capacity(device, "Grid Cell Start", storage, 2);
+        let particle_cells = GpuBuffer::with_capacity(device, "Grid Particle Cells", storage, 1);
+        let cell_entries = GpuBuffer::with_capacity(device, "Grid Cell Entries", storage, 1);
+
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            GridParams::WGSL,
+            Self::LOOKUP_WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/grid.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // Particles, params, counts, starts, particle cells, entries
+        let build_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Grid Build Bind Group Layout"),
+            entries: &[
+                storage_entry(0, true),
+                uniform_entry(1),
+                storage_entry(2, false),
+                storage_entry(3, true),
+                storage_entry(4, false),
+                storage_entry(5, false),
+            ],
+        });
+        let lookup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Grid Lookup Bind Group Layout"),
+            entries: &[
+                uniform_entry(0),
+                storage_entry(1, true),
+                storage_entry(2, true),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Build Pipeline Layout"),
+            bind_group_layouts: &[&build_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        // The particle buffer is swapped in on every build
+        let placeholder =
+            GpuBuffer::<Particle>::with_capacity(device, "Grid Placeholder Particles", storage, 1);
+        let build_bind_group = TrackedBindGroup::new(
+            device,
+            "Grid Build Bind Group",
+            build_layout,
+            &[
+                &placeholder,
+                &param_buffer,
+                &cell_counts,
+                &cell_start,
+                &particle_cells,
+                &cell_entries,
+            ],
+        );
+        let lookup_bind_group = TrackedBindGroup::new(
+            device,
+            "Grid Lookup Bind Group",
+            lookup_layout.clone(),
+            &[&param_buffer, &cell_start, &cell_entries],
+        );
+
+        Self {
+            param_buffer,
+            cell_counts,
+            cell_start,
+            particle_cells,
+            cell_entries,
+            scan: GpuPrefixSum::new(device),
+            count_pipeline: pipeline("count_cells"),
+            reorder_pipeline: pipeline("reorder"),
+            build_bind_group,
+            lookup_layout,
+            lookup_bind_group,
+        }
+    }
+
+    /// Records the passes bucketing the first `params.particle_count`
+    /// particles of `particles`
+    pub fn build(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: &GpuBuffer<Particle>,
+        params: GridParams,
+    ) {
+        let particle_count = params.particle_count;
+        let table_size = params.table_size as usize;
+
+        self.param_buffer.write(device, queue, &[params]);
+        self.cell_counts.reserve(device, table_size);
+        self.cell_start.reserve(device, table_size + 1);
+        self.particle_cells.reserve(device, particle_count as usize);
+        self.cell_entries.reserve(device, particle_count as usize);
+
+        encoder.clear_buffer(self.cell_counts.buffer(), 0, None);
+        let bind_group = self.build_bind_group.get(
+            device,
+            &[
+                particles,
+                &self.param_buffer,
+                &self.cell_counts,
+                &self.cell_start,
+                &self.particle_cells,
+                &self.cell_entries,
+            ],
+        );
+        let particle_groups = particle_count.div_ceil(WORKGROUP_SIZE);
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Grid Count Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_pipeline(&self.count_pipeline);
+            dispatch_linear(&mut pass, particle_groups);
+        }
+
+        // The counts are kept for the reorder to count down
+        let size = (table_size * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+        encoder.copy_buffer_to_buffer(
+            self.cell_counts.buffer(),
+            0,
+            self.cell_start.buffer(),
+            0,
+            size,
+        );
+        self.scan
+            .scan(device, queue, encoder, &self.cell_start, params.table_size);
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Grid Reorder Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_pipeline(&self.reorder_pipeline);
+        dispatch_linear(&mut pass, particle_groups);
+    }
+
+    /// Layout of [`Self::lookup_bind_group`], for pipelines reading the grid
+    pub fn lookup_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.lookup_layout
+    }
+
+    /// Grid params, cell starts and entries from the last build
+    pub fn lookup_bind_group(&mut self, device: &wgpu::Device) -> &wgpu::BindGroup {
+        let resources: [&dyn TrackedResource; 3] =
+            [&self.param_buffer, &self.cell_start, &self.cell_entries];
+        self.lookup_bind_group.get(device, &resources)
+    }
+}
@@ -0,0 +1,201 @@
+use super::dispatch::{self, dispatch_linear};
+use super::gpu_buffer::{GpuBuffer, TrackedBindGroup, storage_entry, uniform_entry};
+use super::layout;
+use crate::wgsl;
+
+/// Threads per workgroup, also the number of values each one scans
+const WORKGROUP_SIZE: u32 = 256;
+
+layout::gpu_struct! {
+    pub struct ScanParams (version 1) {
+        /// Number of values scanned
+        pub count: u32 => "u32",
+        pub _padding0: u32 => "u32",
+        pub _padding1: u32 => "u32",
+        pub _padding2: u32 => "u32",
+    }
+}
+
+/// In-place exclusive prefix sum of a `u32` buffer on the GPU, the building
+/// block of counting and radix sorts.
+///
+/// A scan runs in three passes: every workgroup scans its block of 256
+/// values in shared memory and writes the block's total aside, a single
+/// workgroup scans the block totals, and every value gets the total of the
+/// blocks before its own added. The grand total is written right after the
+/// scanned values. The single workgroup scanning the block totals walks a
+/// run of them per thread, so a scan is only bounded by the largest storage
+/// buffer the device binds, 128 MiB or about 33 million values by default,
+/// and by the total fitting a `u32`.
+///
+/// The count goes through a uniform written when the scan is recorded, so
+/// every scan recorded into one submission has to cover the same count.
+pub struct GpuPrefixSum {
+    param_buffer: GpuBuffer<ScanParams>,
+    block_sums: GpuBuffer<u32>,
+    scan_blocks_pipeline: wgpu::ComputePipeline,
+    scan_block_sums_pipeline: wgpu::ComputePipeline,
+    add_block_offsets_pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+}
+
+impl GpuPrefixSum {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let param_buffer =
+            GpuBuffer::with_capacity(device, "Scan Params Buffer", wgpu::BufferUsages::UNIFORM, 1);
+        let block_sums = GpuBuffer::with_capacity(device, "Scan Block Sums", storage, 1);
+
+        let source = wgsl::compose(&[
+            ScanParams::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/scan.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Scan Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // Params, values, block sums
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Scan Bind Group Layout"),
+            entries: &[
+                uniform_entry(0),
+                storage_entry(1, false),
+                storage_entry(2, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scan Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        // The values are swapped in on every scan
+        let placeholder = GpuBuffer::<u32>::with_capacity(device, "Scan Placeholder", storage, 1);
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "Scan Bind Group",
+            layout,
+            &[&param_buffer, &placeholder, &block_sums],
+        );
+
+        Self {
+            param_buffer,
+            block_sums,
+            scan_blocks_pipeline: pipeline("scan_blocks"),
+            scan_block_sums_pipeline: pipeline("scan_block_sums"),
+            add_block_offsets_pipeline: pipeline("add_block_offsets"),
+            bind_group,
+        }
+    }
+
+    /// Records the passes replacing the first `count` entries of `values`
+    /// with their exclusive prefix sum and entry `count` with their total,
+    /// so `values` needs room for `count + 1`
+    pub fn scan(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        values: &GpuBuffer<u32>,
+        count: u32,
+    ) {
+        let block_count = count.div_ceil(WORKGROUP_SIZE);
+        let params = ScanParams {
+            count,
+            _padding0: 0,
+            _padding1: 0,
+            _padding2: 0,
+        };
+        self.param_buffer.write(device, queue, &[params]);
+        self.block_sums.reserve(device, block_count as usize);
+
+        let bind_group = self
+            .bind_group
+            .get(device, &[&self.param_buffer, values, &self.block_sums]);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Scan Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        for (pipeline, workgroups) in [
+            (&self.scan_blocks_pipeline, block_count),
+            (&self.scan_block_sums_pipeline, 1),
+            (&self.add_block_offsets_pipeline, block_count),
+        ] {
+            pass.set_pipeline(pipeline);
+            dispatch_linear(&mut pass, workgroups);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::gpu_test;
+
+    /// Scans `count` values on the GPU and compares them and the total
+    /// written after them with an exclusive scan on the CPU
+    fn check(count: usize) {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let values: Vec<u32> = (0..count as u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) >> 29)
+            .collect();
+        // Marks the slot the total goes in
+        let mut contents = values.clone();
+        contents.push(u32::MAX);
+        let buffer = GpuBuffer::with_contents(
+            &device,
+            "Test Scan Values",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            &contents,
+        );
+        let mut scan = GpuPrefixSum::new(&device);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        scan.scan(&device, &queue, &mut encoder, &buffer, count as u32);
+        queue.submit(Some(encoder.finish()));
+        let scanned: Vec<u32> = gpu_test::read_buffer(&device, &queue, buffer.buffer(), count + 1);
+
+        let mut expected = Vec::with_capacity(count + 1);
+        let mut total = 0;
+        for value in values {
+            expected.push(total);
+            total += value;
+        }
+        expected.push(total);
+        assert_eq!(scanned, expected, "{count} values");
+    }
+
+    #[test]
+    fn scans_around_one_block() {
+        for count in [0, 1, 255, 256, 257] {
+            check(count);
+        }
+    }
+
+    #[test]
+    fn scans_around_one_block_of_blocks() {
+        for count in [256 * 256 - 1, 256 * 256, 256 * 256 + 1] {
+            check(count);
+        }
+    }
+
+    /// Every thread scanning the block totals walks a run of five
+    #[test]
+    fn scans_runs_of_block_totals() {
+        check(5 * 256 * 256 - 3);
+    }
+}
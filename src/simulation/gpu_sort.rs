@@ -0,0 +1,408 @@
+use super::Particle;
+use super::dispatch::{self, dispatch_linear};
+use super::gpu_buffer::{GpuBuffer, TrackedBindGroup, storage_entry, uniform_entry};
+use super::gpu_scan::GpuPrefixSum;
+use super::layout;
+use crate::wgsl;
+use glam::Vec3;
+
+/// Threads per workgroup in every sort pass, also the block each workgroup
+/// ranks
+const WORKGROUP_SIZE: u32 = 256;
+/// Distinct values of the digit sorted by in one pass
+const RADIX: u32 = 16;
+/// Bits in one digit
+const DIGIT_BITS: u32 = 4;
+
+pub const SORT_KEY_DEPTH: u32 = 0;
+pub const SORT_KEY_SPEED: u32 = 1;
+pub const SORT_KEY_SPECIES: u32 = 2;
+
+/// What particles are ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Distance along the view direction
+    Depth,
+    Speed,
+    Species,
+}
+
+impl SortKey {
+    fn mode(self) -> u32 {
+        match self {
+            SortKey::Depth => SORT_KEY_DEPTH,
+            SortKey::Speed => SORT_KEY_SPEED,
+            SortKey::Species => SORT_KEY_SPECIES,
+        }
+    }
+}
+
+layout::gpu_struct! {
+    pub struct SortParams (version 1) {
+        /// Camera position, for depth keys
+        pub eye: [f32; 3] => "vec3<f32>",
+        /// One of the `SORT_KEY_*` constants
+        pub key: u32 => "u32",
+
+        /// Unit view direction, for depth keys
+        pub forward: [f32; 3] => "vec3<f32>",
+        pub particle_count: u32 => "u32",
+
+        /// 1 = largest key first
+        pub descending: u32 => "u32",
+        pub _padding0: u32 => "u32",
+        pub _padding1: u32 => "u32",
+        pub _padding2: u32 => "u32",
+    }
+}
+
+impl SortParams {
+    /// Sorts `particle_count` particles by `key`, depth measured from `eye`
+    /// along `forward`
+    pub fn new(
+        particle_count: u32,
+        key: SortKey,
+        descending: bool,
+        eye: Vec3,
+        forward: Vec3,
+    ) -> Self {
+        Self {
+            eye: eye.into(),
+            key: key.mode(),
+            forward: forward.normalize_or_zero().into(),
+            particle_count,
+            descending: descending as u32,
+            _padding0: 0,
+            _padding1: 0,
+            _padding2: 0,
+        }
+    }
+
+    /// Digit passes covering the key's bits, an even number so the result
+    /// ends up back in the first buffer
+    fn passes(&self) -> u32 {
+        match self.key {
+            // Fewer than 256 species
+            SORT_KEY_SPECIES => 2,
+            _ => 32 / DIGIT_BITS,
+        }
+    }
+}
+
+/// Stable GPU radix sort of a particle buffer's indices by one attribute, for
+/// effects that care about draw order like transparency or drawing the
+/// slowest particles last.
+///
+/// A sort copies the particles aside, pairs a 32-bit key with the index of
+/// every particle, then runs one pass per 4-bit digit from the lowest: count
+/// the digits of each block of 256, prefix sum the counts digit-major across
+/// blocks with [`GpuPrefixSum`], and scatter every key to its digit's offset
+/// plus its rank within the block.
+/// The passes alternate between two buffers of pairs, reading
+/// the digit's position from a counter the last dispatch of each moves on.
+/// Finally the particle indices are gathered in key order into
+/// [`ParticleSorter::sorted_indices`], which the particles are drawn through
+/// so they stay where they are and still line up with the previous step's.
+pub struct ParticleSorter {
+    param_buffer: GpuBuffer<SortParams>,
+    source: GpuBuffer<Particle>,
+    /// Key and particle index pairs, read from one and scattered to the other
+    entries: [GpuBuffer<[u32; 2]>; 2],
+    /// Digit-major counts of every digit in every block, then their
+    /// exclusive prefix sum
+    offsets: GpuBuffer<u32>,
+    /// Bit offset of the digit being sorted by
+    shift: GpuBuffer<u32>,
+    indices: GpuBuffer<u32>,
+    scan: GpuPrefixSum,
+    write_keys_pipeline: wgpu::ComputePipeline,
+    count_digits_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    next_digit_pipeline: wgpu::ComputePipeline,
+    gather_pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+}
+
+impl ParticleSorter {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let param_buffer =
+            GpuBuffer::with_capacity(device, "Sort Params Buffer", wgpu::BufferUsages::UNIFORM, 1);
+        let source = GpuBuffer::with_capacity(device, "Sort Source Particles", storage, 1);
+        let entries = [
+            GpuBuffer::with_capacity(device, "Sort Entries A", storage, 1),
+            GpuBuffer::with_capacity(device, "Sort Entries B", storage, 1),
+        ];
+        let offsets = GpuBuffer::with_capacity(device, "Sort Digit Offsets", storage, 1);
+        let shift = GpuBuffer::with_capacity(device, "Sort Shift", storage, 1);
+        let indices = GpuBuffer::with_capacity(
+            device,
+            "Sorted Particle Indices",
+            storage | wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC,
+            1,
+        );
+
+        let source_code = wgsl::compose(&[
+            Particle::WGSL,
+            SortParams::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/sort.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sort Shader"),
+            source: wgpu::ShaderSource::Wgsl(source_code.into()),
+        });
+
+        // Params, source, entries A/B, offsets, shift, indices
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sort Bind Group Layout"),
+            entries: &[
+                uniform_entry(0),
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+                storage_entry(4, false),
+                storage_entry(5, false),
+                storage_entry(6, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sort Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "Sort Bind Group",
+            layout,
+            &[
+                &param_buffer,
+                &source,
+                &entries[0],
+                &entries[1],
+                &offsets,
+                &shift,
+                &indices,
+            ],
+        );
+
+        Self {
+            param_buffer,
+            source,
+            entries,
+            offsets,
+            shift,
+            indices,
+            scan: GpuPrefixSum::new(device),
+            write_keys_pipeline: pipeline("write_keys"),
+            count_digits_pipeline: pipeline("count_digits"),
+            scatter_pipeline: pipeline("scatter"),
+            next_digit_pipeline: pipeline("next_digit"),
+            gather_pipeline: pipeline("gather"),
+            bind_group,
+        }
+    }
+
+    /// Records the passes sorting the first `params.particle_count`
+    /// particles of `particles`, ties keeping their order
+    pub fn sort(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: &wgpu::Buffer,
+        params: SortParams,
+    ) {
+        let particle_count = params.particle_count;
+        let count = particle_count as usize;
+        let block_count = particle_count.div_ceil(WORKGROUP_SIZE);
+        let offset_count = RADIX * block_count;
+
+        self.param_buffer.write(device, queue, &[params]);
+        self.source.reserve(device, count);
+        for buffer in &mut self.entries {
+            buffer.reserve(device, count);
+        }
+        // And the total after them
+        self.offsets.reserve(device, offset_count as usize + 1);
+        self.indices.reserve(device, count);
+
+        if particle_count == 0 {
+            return;
+        }
+        let size = (count * std::mem::size_of::<Particle>()) as wgpu::BufferAddress;
+        encoder.copy_buffer_to_buffer(particles, 0, self.source.buffer(), 0, size);
+        encoder.clear_buffer(self.shift.buffer(), 0, None);
+
+        let bind_group = self.bind_group.get(
+            device,
+            &[
+                &self.param_buffer,
+                &self.source,
+                &self.entries[0],
+                &self.entries[1],
+                &self.offsets,
+                &self.shift,
+                &self.indices,
+            ],
+        );
+
+        // Runs pipelines with their workgroup counts in one pass
+        let run = |encoder: &mut wgpu::CommandEncoder,
+                   label,
+                   pipelines: &[(&wgpu::ComputePipeline, u32)]| {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some(label),
+                timestamp_writes: None,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            for &(pipeline, workgroups) in pipelines {
+                pass.set_pipeline(pipeline);
+                dispatch_linear(&mut pass, workgroups);
+            }
+        };
+        run(
+            encoder,
+            "Sort Key Pass",
+            &[(&self.write_keys_pipeline, block_count)],
+        );
+        for _ in 0..params.passes() {
+            run(
+                encoder,
+                "Sort Count Pass",
+                &[(&self.count_digits_pipeline, block_count)],
+            );
+            self.scan
+                .scan(device, queue, encoder, &self.offsets, offset_count);
+            run(
+                encoder,
+                "Sort Scatter Pass",
+                &[
+                    (&self.scatter_pipeline, block_count),
+                    (&self.next_digit_pipeline, 1),
+                ],
+            );
+        }
+        run(
+            encoder,
+            "Sort Gather Pass",
+            &[(&self.gather_pipeline, block_count)],
+        );
+    }
+
+    /// Indices of the particles in key order as of the last sort, to draw
+    /// them through
+    pub fn sorted_indices(&self) -> &wgpu::Buffer {
+        self.indices.buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::gpu_test;
+    use glam::Vec4;
+    use std::cmp::Reverse;
+
+    const EYE: Vec3 = Vec3::new(1.0, 2.0, -5.0);
+
+    /// Particles whose depth from `EYE` along z, speed and species are
+    /// integers that repeat, so the GPU's rounding can't reorder distinct
+    /// keys and ties are plenty
+    fn particles(count: usize) -> Vec<Particle> {
+        (0..count)
+            .map(|i| {
+                let depth = (i * 7919 % 61) as f32 - 30.0;
+                let speed = (i * 104_729 % 37) as f32;
+                let species = (i * 31 % 200) as u32;
+                Particle::new(
+                    EYE + Vec3::new(0.5, -1.0, depth),
+                    Vec3::new(speed, 0.0, 0.0),
+                    Vec4::ONE,
+                    species,
+                )
+            })
+            .collect()
+    }
+
+    fn key_of(key: SortKey, particle: &Particle) -> i64 {
+        match key {
+            SortKey::Depth => (particle.position[2] - EYE.z) as i64,
+            SortKey::Speed => particle.velocity[0] as i64,
+            SortKey::Species => particle.species as i64,
+        }
+    }
+
+    /// Sorts `count` particles on the GPU and compares the order with a
+    /// stable sort on the CPU
+    fn check(key: SortKey, descending: bool, count: usize) {
+        let Some((device, queue)) = gpu_test::device() else {
+            return;
+        };
+        let particles = particles(count);
+        let buffer = GpuBuffer::with_contents(
+            &device,
+            "Test Particles",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            &particles,
+        );
+        let mut sorter = ParticleSorter::new(&device);
+        let params = SortParams::new(count as u32, key, descending, EYE, Vec3::Z);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        sorter.sort(&device, &queue, &mut encoder, buffer.buffer(), params);
+        queue.submit(Some(encoder.finish()));
+        let sorted: Vec<u32> =
+            gpu_test::read_buffer(&device, &queue, sorter.sorted_indices(), count);
+
+        let mut expected: Vec<u32> = (0..count as u32).collect();
+        let key_at = |index: &u32| key_of(key, &particles[*index as usize]);
+        if descending {
+            expected.sort_by_key(|index| Reverse(key_at(index)));
+        } else {
+            expected.sort_by_key(key_at);
+        }
+        assert_eq!(
+            sorted, expected,
+            "{key:?}, descending {descending}, {count} particles"
+        );
+    }
+
+    /// Empty, single, around one block and across several blocks and
+    /// scan runs
+    const COUNTS: [usize; 7] = [0, 1, 255, 256, 257, 5 * 256 + 3, 70_001];
+
+    #[test]
+    fn sorts_by_depth() {
+        for count in COUNTS {
+            check(SortKey::Depth, false, count);
+            check(SortKey::Depth, true, count);
+        }
+    }
+
+    #[test]
+    fn sorts_by_speed() {
+        for count in COUNTS {
+            check(SortKey::Speed, false, count);
+            check(SortKey::Speed, true, count);
+        }
+    }
+
+    #[test]
+    fn sorts_by_species() {
+        for count in COUNTS {
+            check(SortKey::Species, false, count);
+            check(SortKey::Species, true, count);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Headless device and blocking readbacks shared by the GPU tests
+
+use bytemuck::Pod;
+
+/// A device on the default adapter, `None` on machines without one so the
+/// tests needing it pass without running
+pub fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()));
+    let Ok(adapter) = adapter else {
+        eprintln!("No GPU adapter, skipping");
+        return None;
+    };
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()
+}
+
+/// Copies the first `count` elements of `buffer`, which needs `COPY_SRC`,
+/// back from the GPU once everything submitted before has run
+pub fn read_buffer<T: Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    count: usize,
+) -> Vec<T> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let size = (count * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Test Readback Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Test Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    staging
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |status| {
+            let _ = sender.send(status);
+        });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("device lost");
+    receiver.recv().unwrap().expect("mapping failed");
+    let values = bytemuck::cast_slice(&staging.slice(..).get_mapped_range()).to_vec();
+    staging.unmap();
+    values
+}
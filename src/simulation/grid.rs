@@ -0,0 +1,262 @@
+use super::{Particle, nearest_image};
+use glam::{IVec3, Vec3};
+use rayon::prelude::*;
+use std::cell::RefCell;
+
+/// Uniform spatial hash grid over particle positions, used by the CPU backend
+/// for neighbor queries (contacts, reactions, ...).
+///
+/// Particles are bucketed by hashing their cell coordinate into a table sized
+/// to the particle count, then counting-sorted so every bucket is a contiguous
+/// range of `entries`.
+///
+/// With a periodic box the cells tile the box exactly and wrap around its
+/// faces, and [`SpatialGrid::separation`] applies the minimum-image convention.
+pub struct SpatialGrid {
+    cell_size: Vec3,
+    /// Half extents of the periodic box, if any
+    periodic: Option<Vec3>,
+    cells_per_axis: IVec3,
+    table_size: u32,
+    cell_hashes: Vec<u32>,
+    cell_start: Vec<u32>,
+    entries: Vec<u32>,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self {
+            cell_size: Vec3::ONE,
+            periodic: None,
+            cells_per_axis: IVec3::ONE,
+            table_size: 1,
+            cell_hashes: Vec::new(),
+            cell_start: vec![0; 2],
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn build(&mut self, particles: &[Particle], cell_size: f32, periodic: Option<Vec3>) {
+        let cell_size = cell_size.max(0.001);
+        self.periodic = periodic;
+        match periodic {
+            Some(half_extents) => {
+                // Round cells up so a whole number of them spans the box
+                let box_size = half_extents * 2.0;
+                self.cells_per_axis = (box_size / cell_size).floor().as_ivec3().max(IVec3::ONE);
+                self.cell_size = box_size / self.cells_per_axis.as_vec3();
+            }
+            None => {
+                self.cells_per_axis = IVec3::ONE;
+                self.cell_size = Vec3::splat(cell_size);
+            }
+        }
+        self.table_size = (particles.len() as u32).max(1).next_power_of_two() * 2;
+
+        // The dead go in a bucket past the table that no cell hashes to
+        let mut cell_hashes = std::mem::take(&mut self.cell_hashes);
+        particles
+            .par_iter()
+            .map(|p| {
+                if p.is_dead() {
+                    self.table_size
+                } else {
+                    self.hash_cell(self.cell_of(Vec3::from(p.position)))
+                }
+            })
+            .collect_into_vec(&mut cell_hashes);
+        self.cell_hashes = cell_hashes;
+
+        // Counting sort: histogram, exclusive prefix sum, scatter
+        self.cell_start.clear();
+        self.cell_start.resize(self.table_size as usize + 2, 0);
+        for &hash in &self.cell_hashes {
+            self.cell_start[hash as usize + 1] += 1;
+        }
+        for i in 1..self.cell_start.len() {
+            self.cell_start[i] += self.cell_start[i - 1];
+        }
+
+        self.entries.clear();
+        self.entries.resize(particles.len(), 0);
+        let mut cursor = self.cell_start.clone();
+        for (index, &hash) in self.cell_hashes.iter().enumerate() {
+            let slot = &mut cursor[hash as usize];
+            self.entries[*slot as usize] = index as u32;
+            *slot += 1;
+        }
+    }
+
+    /// Calls `f` with the index of every particle that may lie within `radius`
+    /// of `position`. Candidates still have to be distance-checked by the
+    /// caller since hash buckets can be shared between distant cells.
+    pub fn for_each_neighbor(&self, position: Vec3, radius: f32, mut f: impl FnMut(usize)) {
+        let reach = (Vec3::splat(radius) / self.cell_size)
+            .ceil()
+            .max(Vec3::ONE)
+            .as_ivec3();
+        let center = self.cell_of(position);
+
+        // Different cells can hash to the same bucket (or wrap onto the same
+        // cell in a periodic box), visit each bucket once
+        if reach == IVec3::ONE {
+            // The usual query, few enough buckets to track on the stack
+            let mut visited = [0; 27];
+            let mut count = 0;
+            self.visit_buckets(center, reach, &mut f, |hash| {
+                let seen = visited[..count].contains(&hash);
+                if !seen {
+                    visited[count] = hash;
+                    count += 1;
+                }
+                !seen
+            });
+        } else {
+            thread_local! {
+                static VISITED: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+            }
+            // Taken rather than borrowed, so a query from within `f` gets a
+            // fresh buffer instead of panicking
+            let mut visited = VISITED.take();
+            visited.clear();
+            self.visit_buckets(center, reach, &mut f, |hash| {
+                let seen = visited.contains(&hash);
+                if !seen {
+                    visited.push(hash);
+                }
+                !seen
+            });
+            VISITED.set(visited);
+        }
+    }
+
+    /// Calls `f` with every particle in the buckets of the cells within
+    /// `reach` of `center` that `first_visit` accepts
+    fn visit_buckets(
+        &self,
+        center: IVec3,
+        reach: IVec3,
+        f: &mut impl FnMut(usize),
+        mut first_visit: impl FnMut(u32) -> bool,
+    ) {
+        for z in -reach.z..=reach.z {
+            for y in -reach.y..=reach.y {
+                for x in -reach.x..=reach.x {
+                    let hash = self.hash_cell(center + IVec3::new(x, y, z));
+                    if !first_visit(hash) {
+                        continue;
+                    }
+
+                    let start = self.cell_start[hash as usize] as usize;
+                    let end = self.cell_start[hash as usize + 1] as usize;
+                    for &index in &self.entries[start..end] {
+                        f(index as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `from - to`, using the nearest periodic image of `to` when the grid was
+    /// built for a periodic box
+    pub fn separation(&self, from: Vec3, to: Vec3) -> Vec3 {
+        let offset = from - to;
+        match self.periodic {
+            Some(half_extents) => nearest_image(offset, half_extents),
+            None => offset,
+        }
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        match self.periodic {
+            Some(half_extents) => ((position + half_extents) / self.cell_size)
+                .floor()
+                .as_ivec3(),
+            None => (position / self.cell_size).floor().as_ivec3(),
+        }
+    }
+
+    fn hash_cell(&self, cell: IVec3) -> u32 {
+        let cell = if self.periodic.is_some() {
+            cell.rem_euclid(self.cells_per_axis)
+        } else {
+            cell
+        };
+        let h = (cell.x as u32).wrapping_mul(73_856_093)
+            ^ (cell.y as u32).wrapping_mul(19_349_663)
+            ^ (cell.z as u32).wrapping_mul(83_492_791);
+        h & (self.table_size - 1)
+    }
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::DEAD;
+    use glam::Vec4;
+
+    fn particles(count: usize) -> Vec<Particle> {
+        (0..count)
+            .map(|i| {
+                let position = Vec3::new(
+                    (i * 7919 % 1000) as f32 / 100.0 - 5.0,
+                    (i * 104_729 % 1000) as f32 / 100.0 - 5.0,
+                    (i * 31 % 1000) as f32 / 100.0 - 5.0,
+                );
+                Particle::new(position, Vec3::ZERO, Vec4::ONE, 0)
+            })
+            .collect()
+    }
+
+    /// Every particle within `radius` of each particle is visited exactly once
+    fn check(periodic: Option<Vec3>, radius: f32) {
+        let particles = particles(500);
+        let mut grid = SpatialGrid::new();
+        grid.build(&particles, 1.0, periodic);
+        for particle in &particles {
+            let position = Vec3::from(particle.position);
+            let mut visits = vec![0; particles.len()];
+            grid.for_each_neighbor(position, radius, |j| visits[j] += 1);
+            for (j, other) in particles.iter().enumerate() {
+                assert!(visits[j] <= 1);
+                let distance = grid.separation(position, other.position.into()).length();
+                if distance <= radius {
+                    assert_eq!(visits[j], 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_neighbors_in_adjacent_cells() {
+        check(None, 1.0);
+        check(Some(Vec3::splat(5.0)), 1.0);
+    }
+
+    #[test]
+    fn finds_neighbors_further_out() {
+        check(None, 2.5);
+        check(Some(Vec3::splat(5.0)), 2.5);
+    }
+
+    #[test]
+    fn leaves_the_dead_out() {
+        let mut particles = particles(500);
+        for particle in particles.iter_mut().step_by(3) {
+            particle.lifetime = DEAD;
+        }
+        let mut grid = SpatialGrid::new();
+        grid.build(&particles, 1.0, None);
+        for particle in &particles {
+            grid.for_each_neighbor(particle.position.into(), 2.5, |j| {
+                assert!(!particles[j].is_dead());
+            });
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use super::dispatch::{self, WORKGROUP_SIZE, dispatch_linear};
+use super::gpu_buffer::{GpuBuffer, TrackedBindGroup, storage_entry, uniform_entry};
+use super::readback::{MapState, ReadMapping};
+use super::{Particle, SimParams};
+use crate::wgsl;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Distance from the origin no sane scene sends particles to
+pub const DIVERGED_DISTANCE: f32 = 1.0e6;
+/// Speed no sane scene reaches, well past any mouse pull or explosion
+pub const DIVERGED_SPEED: f32 = 1.0e5;
+
+/// Largest distance, largest speed and the non-finite count, as the shader
+/// leaves them
+const STAT_COUNT: usize = 3;
+
+/// Extremes of the particle state, telling a simulation that blew up from
+/// a merely lively one
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Health {
+    /// Largest distance of a finite particle from the origin
+    pub max_distance: f32,
+    /// Largest speed of a finite particle
+    pub max_speed: f32,
+    /// Particles with a NaN or infinite position or velocity
+    pub non_finite: u32,
+}
+
+impl Health {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            max_distance: self.max_distance.max(other.max_distance),
+            max_speed: self.max_speed.max(other.max_speed),
+            non_finite: self.non_finite + other.non_finite,
+        }
+    }
+
+    pub fn diverged(&self) -> bool {
+        self.non_finite > 0
+            || self.max_distance > DIVERGED_DISTANCE
+            || self.max_speed > DIVERGED_SPEED
+    }
+}
+
+/// Health of the particles on the CPU.
+// Keep in sync with health.wgsl
+pub fn measure(particles: &[Particle]) -> Health {
+    particles
+        .par_iter()
+        .map(|particle| {
+            let position = Vec3::from(particle.position);
+            let velocity = Vec3::from(particle.velocity);
+            if position.is_finite() && velocity.is_finite() {
+                Health {
+                    max_distance: position.length(),
+                    max_speed: velocity.length(),
+                    non_finite: 0,
+                }
+            } else {
+                Health {
+                    non_finite: 1,
+                    ..Health::default()
+                }
+            }
+        })
+        .reduce(Health::default, Health::merge)
+}
+
+/// Health of a GPU particle buffer, reduced on the GPU so only a few bytes
+/// come back. Like [`super::readback::ParticleReadback`], each call hands
+/// out the last finished reduction and queues the next one.
+pub struct GpuHealthCheck {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+    stats: GpuBuffer<u32>,
+    staging: wgpu::Buffer,
+    mapping: ReadMapping,
+    in_flight: bool,
+}
+
+impl GpuHealthCheck {
+    pub fn new(
+        device: &wgpu::Device,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+    ) -> Self {
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/health.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Health Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Health Bind Group Layout"),
+            entries: &[
+                storage_entry(0, true),
+                uniform_entry(1),
+                storage_entry(2, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Health Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Health Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let stats = GpuBuffer::with_capacity(
+            device,
+            "Health Stats Buffer",
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            STAT_COUNT,
+        );
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Health Readback Buffer"),
+            size: std::mem::size_of::<[u32; STAT_COUNT]>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "Health Bind Group",
+            layout,
+            &[particles, sim_params, &stats],
+        );
+
+        Self {
+            pipeline,
+            bind_group,
+            stats,
+            staging,
+            mapping: ReadMapping::new(),
+            in_flight: false,
+        }
+    }
+
+    /// The last finished reduction, if one came back since the previous
+    /// call, queueing one over the first `particle_count` particles. The
+    /// count is taken from the params last written to `sim_params`.
+    pub fn check(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        particle_count: u32,
+    ) -> Option<Health> {
+        let _ = device.poll(wgpu::PollType::Poll);
+
+        let mut result = None;
+        if self.in_flight {
+            match self.mapping.state() {
+                MapState::Pending => return None,
+                MapState::Done => {
+                    {
+                        let view = self.staging.slice(..).get_mapped_range();
+                        let stats: &[u32] = bytemuck::cast_slice(&view);
+                        result = Some(Health {
+                            max_distance: f32::from_bits(stats[0]),
+                            max_speed: f32::from_bits(stats[1]),
+                            non_finite: stats[2],
+                        });
+                    }
+                    self.staging.unmap();
+                }
+                MapState::Failed => {}
+            }
+            self.in_flight = false;
+        }
+
+        if particle_count == 0 {
+            return result;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Health Encoder"),
+        });
+        encoder.clear_buffer(self.stats.buffer(), 0, None);
+        {
+            let bind_group = self
+                .bind_group
+                .get(device, &[particles, sim_params, &self.stats]);
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Health Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            dispatch_linear(&mut pass, particle_count.div_ceil(WORKGROUP_SIZE));
+        }
+        encoder.copy_buffer_to_buffer(
+            self.stats.buffer(),
+            0,
+            &self.staging,
+            0,
+            self.staging.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.mapping.start(self.staging.slice(..));
+        self.in_flight = true;
+
+        result
+    }
+}
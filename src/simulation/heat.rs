@@ -0,0 +1,50 @@
+use super::Particle;
+use super::grid::SpatialGrid;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Relaxes every particle's temperature towards the mean of its contacts.
+/// `rate` is the conduction coefficient already scaled by the timestep.
+pub fn conduct_heat(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    contact_radius: f32,
+    rate: f32,
+) {
+    let radius_sq = contact_radius * contact_radius;
+    let rate = rate.clamp(0.0, 1.0);
+
+    let new_temperatures: Vec<f32> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let mut sum = 0.0;
+            let mut contacts = 0u32;
+
+            grid.for_each_neighbor(position, contact_radius, |j| {
+                if j != i
+                    && grid
+                        .separation(position, Vec3::from(particles[j].position))
+                        .length_squared()
+                        < radius_sq
+                {
+                    sum += particles[j].temperature;
+                    contacts += 1;
+                }
+            });
+
+            if contacts == 0 {
+                return particle.temperature;
+            }
+
+            let mean = sum / contacts as f32;
+            particle.temperature + (mean - particle.temperature) * rate
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(new_temperatures)
+        .for_each(|(particle, temperature)| particle.temperature = temperature);
+}
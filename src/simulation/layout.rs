@@ -0,0 +1,136 @@
+/// Rust types that can appear in structs shared with WGSL, with their WGSL
+/// name and their alignment/size under WGSL's host-shareable layout rules.
+pub trait WgslType {
+    const NAME: &'static str;
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+impl WgslType for f32 {
+    const NAME: &'static str = "f32";
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+impl WgslType for u32 {
+    const NAME: &'static str = "u32";
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+}
+
+impl WgslType for [f32; 2] {
+    const NAME: &'static str = "vec2<f32>";
+    const ALIGN: usize = 8;
+    const SIZE: usize = 8;
+}
+
+impl WgslType for [f32; 3] {
+    const NAME: &'static str = "vec3<f32>";
+    const ALIGN: usize = 16;
+    const SIZE: usize = 12;
+}
+
+impl WgslType for [u32; 3] {
+    const NAME: &'static str = "vec3<u32>";
+    const ALIGN: usize = 16;
+    const SIZE: usize = 12;
+}
+
+impl WgslType for [f32; 4] {
+    const NAME: &'static str = "vec4<f32>";
+    const ALIGN: usize = 16;
+    const SIZE: usize = 16;
+}
+
+/// Columns padded to four floats, the w of each unused
+impl WgslType for [[f32; 4]; 3] {
+    const NAME: &'static str = "mat3x3<f32>";
+    const ALIGN: usize = 16;
+    const SIZE: usize = 48;
+}
+
+pub const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Declares a `#[repr(C)]` struct shared with the shaders and generates its
+/// WGSL declaration (`Self::WGSL`) from the same field list, so the two can't
+/// drift apart. Every field is annotated with its WGSL type, and compile-time
+/// asserts check that the annotation matches the Rust type and that each
+/// field sits at the offset WGSL expects.
+///
+/// Bump `version` whenever fields are added, removed or reordered; it ends up
+/// in the header comment of the generated declaration.
+macro_rules! gpu_struct {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident (version $version:literal) {
+            $(
+                $(#[$field_meta:meta])*
+                pub $field:ident: $ty:ty => $wgsl:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// WGSL declaration of this struct, prepended to the shaders using it
+            pub const WGSL: &'static str = concat!(
+                "// ", stringify!($name), " layout v", $version,
+                ", generated from the Rust declaration\n",
+                "struct ", stringify!($name), " {\n",
+                $("  ", stringify!($field), ": ", $wgsl, ",\n",)*
+                "};\n",
+            );
+        }
+
+        const _: () = {
+            use $crate::simulation::layout::{WgslType, str_eq};
+
+            let mut offset = 0;
+            let mut align = 1;
+            $(
+                assert!(
+                    str_eq($wgsl, <$ty as WgslType>::NAME),
+                    concat!("WGSL type of ", stringify!($name), "::", stringify!($field),
+                        " doesn't match its Rust type"),
+                );
+                offset = usize::next_multiple_of(offset, <$ty as WgslType>::ALIGN);
+                assert!(
+                    std::mem::offset_of!($name, $field) == offset,
+                    concat!(stringify!($name), "::", stringify!($field),
+                        " isn't at its WGSL offset, add explicit padding"),
+                );
+                offset += <$ty as WgslType>::SIZE;
+                if <$ty as WgslType>::ALIGN > align {
+                    align = <$ty as WgslType>::ALIGN;
+                }
+            )*
+            assert!(
+                std::mem::size_of::<$name>() == usize::next_multiple_of(offset, align),
+                concat!("size of ", stringify!($name), " doesn't match its WGSL size"),
+            );
+        };
+    };
+}
+
+pub(crate) use gpu_struct;
@@ -0,0 +1,117 @@
+use super::Particle;
+use super::grid::SpatialGrid;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Particles summed together before the partial sums are added up in order
+const REDUCTION_CHUNK: usize = 4096;
+
+/// Lennard-Jones pair forces integrated with velocity Verlet (kick-drift-kick).
+///
+/// The first half kick uses the accelerations from the previous step, the
+/// regular position update in the backend is the drift, and the second half
+/// kick uses accelerations evaluated at the new positions.
+pub struct LennardJones {
+    accelerations: Vec<Vec3>,
+}
+
+impl LennardJones {
+    pub fn new() -> Self {
+        Self {
+            accelerations: Vec::new(),
+        }
+    }
+
+    pub fn half_kick(&self, particles: &mut [Particle], delta_time: f32) {
+        particles
+            .par_iter_mut()
+            .zip(self.accelerations.par_iter())
+            .for_each(|(particle, acceleration)| {
+                let velocity = Vec3::from(particle.velocity) + *acceleration * (0.5 * delta_time);
+                particle.velocity = velocity.into();
+            });
+    }
+
+    pub fn update_accelerations(
+        &mut self,
+        particles: &[Particle],
+        grid: &SpatialGrid,
+        epsilon: f32,
+        sigma: f32,
+        cutoff: f32,
+    ) {
+        let range = sigma * cutoff;
+        let range_sq = range * range;
+        let sigma_sq = sigma * sigma;
+        // Overlapping particles would otherwise get launched out of the scene
+        let min_dist_sq = (0.7 * sigma).powi(2);
+
+        particles
+            .par_iter()
+            .enumerate()
+            .map(|(i, particle)| {
+                let position = Vec3::from(particle.position);
+                let mut acceleration = Vec3::ZERO;
+
+                grid.for_each_neighbor(position, range, |j| {
+                    if j == i {
+                        return;
+                    }
+                    let offset = grid.separation(position, Vec3::from(particles[j].position));
+                    let dist_sq = offset.length_squared();
+                    if dist_sq >= range_sq {
+                        return;
+                    }
+
+                    let s2 = sigma_sq / dist_sq.max(min_dist_sq);
+                    let s6 = s2 * s2 * s2;
+                    // F(r) / r = 24ε (2(σ/r)^12 - (σ/r)^6) / r²
+                    let magnitude =
+                        24.0 * epsilon * (2.0 * s6 * s6 - s6) / dist_sq.max(min_dist_sq);
+                    acceleration += offset * magnitude;
+                });
+
+                acceleration
+            })
+            .collect_into_vec(&mut self.accelerations);
+    }
+}
+
+impl Default for LennardJones {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Berendsen thermostat: rescales velocities so the kinetic temperature
+/// relaxes towards `target` with time constant `tau` (unit mass, k_B = 1).
+pub fn berendsen_thermostat(particles: &mut [Particle], target: f32, tau: f32, delta_time: f32) {
+    if particles.is_empty() {
+        return;
+    }
+
+    // Summed in fixed chunks, then in order, so the result doesn't depend on
+    // how rayon splits the work and runs repeat bit for bit
+    let kinetic: f32 = particles
+        .par_chunks(REDUCTION_CHUNK)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|p| Vec3::from(p.velocity).length_squared())
+                .sum::<f32>()
+        })
+        .collect::<Vec<f32>>()
+        .iter()
+        .sum();
+    let current = kinetic / (3.0 * particles.len() as f32);
+    if current <= f32::EPSILON {
+        return;
+    }
+
+    let lambda = (1.0 + delta_time / tau * (target / current - 1.0))
+        .max(0.0)
+        .sqrt();
+    particles.par_iter_mut().for_each(|p| {
+        p.velocity = (Vec3::from(p.velocity) * lambda).into();
+    });
+}
@@ -0,0 +1,66 @@
+use super::Particle;
+use super::grid::SpatialGrid;
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Pairwise dipole-dipole forces and torques within `radius`. Forces are added
+/// to the velocity (unit mass) and dipoles are rotated towards the local field
+/// with an overdamped torque response, which is what lets chains and rings form.
+pub fn apply_dipole_forces(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    strength: f32,
+    radius: f32,
+    delta_time: f32,
+) {
+    let radius_sq = radius * radius;
+    // Keep close encounters from producing huge impulses
+    let min_dist = radius * 0.1;
+
+    let updates: Vec<(Vec3, Vec3)> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let m_i = Vec3::from(particle.dipole);
+            let mut force = Vec3::ZERO;
+            let mut field = Vec3::ZERO;
+
+            grid.for_each_neighbor(position, radius, |j| {
+                if j == i {
+                    return;
+                }
+                let offset = grid.separation(position, Vec3::from(particles[j].position));
+                let dist_sq = offset.length_squared();
+                if dist_sq >= radius_sq || dist_sq == 0.0 {
+                    return;
+                }
+
+                let actual_dist = dist_sq.sqrt();
+                let r = offset / actual_dist;
+                let dist = actual_dist.max(min_dist);
+                let m_j = Vec3::from(particles[j].dipole);
+                let mi_r = m_i.dot(r);
+                let mj_r = m_j.dot(r);
+
+                let inv_r3 = 1.0 / (dist * dist * dist);
+                force += 3.0 * inv_r3 / dist
+                    * (mj_r * m_i + mi_r * m_j + m_i.dot(m_j) * r - 5.0 * mi_r * mj_r * r);
+                field += inv_r3 * (3.0 * mj_r * r - m_j);
+            });
+
+            let torque = m_i.cross(field);
+            let dipole = (m_i + torque.cross(m_i) * strength * delta_time).normalize_or(m_i);
+
+            (force * strength * delta_time, dipole)
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(updates)
+        .for_each(|(particle, (impulse, dipole))| {
+            particle.velocity = (Vec3::from(particle.velocity) + impulse).into();
+            particle.dipole = dipole.into();
+        });
+}
@@ -0,0 +1,333 @@
+use glam::{UVec3, Vec3};
+use rayon::prelude::*;
+
+/// Samples along each axis of the grid
+// Keep in sync with `MESH_SDF_RESOLUTION` in obstacles.wgsl
+pub const RESOLUTION: u32 = 48;
+
+/// The mesh is scaled to this fraction of the grid so the surface doesn't
+/// touch its border
+const FIT: f32 = 0.8;
+
+/// Cells around each triangle whose distance is measured exactly, the rest is
+/// propagated from them
+const BAND: i32 = 2;
+
+/// Signed distance to a closed triangle mesh, sampled on a grid over
+/// [-1, 1]³ with the mesh scaled to fit inside. Obstacles place and scale it.
+pub struct MeshSdf {
+    /// Negative inside, x fastest
+    distances: Vec<f32>,
+}
+
+impl MeshSdf {
+    /// Far from everything, for when no mesh is loaded
+    pub fn empty() -> Self {
+        Self {
+            distances: vec![f32::MAX.sqrt(); Self::sample_count()],
+        }
+    }
+
+    pub fn from_obj(text: &str) -> Result<Self, String> {
+        let triangles = parse_obj(text)?;
+        if triangles.is_empty() {
+            return Err("no faces".to_owned());
+        }
+        Ok(Self::voxelize(&triangles))
+    }
+
+    pub fn distances(&self) -> &[f32] {
+        &self.distances
+    }
+
+    const fn sample_count() -> usize {
+        (RESOLUTION * RESOLUTION * RESOLUTION) as usize
+    }
+
+    fn cell_size() -> f32 {
+        2.0 / (RESOLUTION - 1) as f32
+    }
+
+    fn sample_position(x: u32, y: u32, z: u32) -> Vec3 {
+        Vec3::new(x as f32, y as f32, z as f32) * Self::cell_size() - Vec3::ONE
+    }
+
+    fn index(x: u32, y: u32, z: u32) -> usize {
+        (x + RESOLUTION * (y + RESOLUTION * z)) as usize
+    }
+
+    fn voxelize(triangles: &[[Vec3; 3]]) -> Self {
+        let (min, max) = triangles.iter().flatten().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), &v| (min.min(v), max.max(v)),
+        );
+        let center = (min + max) * 0.5;
+        let scale = FIT / ((max - min).max_element() * 0.5).max(1e-6);
+        let triangles: Vec<[Vec3; 3]> = triangles
+            .iter()
+            .map(|triangle| triangle.map(|v| (v - center) * scale))
+            .collect();
+
+        let mut distances = Self::band_distances(&triangles);
+        Self::propagate(&mut distances);
+        Self::apply_sign(&mut distances, &triangles);
+        Self { distances }
+    }
+
+    /// Exact unsigned distances near the surface, everything else unknown
+    fn band_distances(triangles: &[[Vec3; 3]]) -> Vec<f32> {
+        let cell_size = Self::cell_size();
+        let to_cell = |v: f32| ((v + 1.0) / cell_size).floor() as i32;
+        let last = RESOLUTION as i32 - 1;
+        let bounds: Vec<(UVec3, UVec3)> = triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                let min = a.min(b).min(c);
+                let max = a.max(b).max(c);
+                let low = Vec3::new(
+                    to_cell(min.x) as f32,
+                    to_cell(min.y) as f32,
+                    to_cell(min.z) as f32,
+                );
+                let high = Vec3::new(
+                    to_cell(max.x) as f32,
+                    to_cell(max.y) as f32,
+                    to_cell(max.z) as f32,
+                );
+                let band = BAND as f32;
+                (
+                    (low - band)
+                        .clamp(Vec3::ZERO, Vec3::splat(last as f32))
+                        .as_uvec3(),
+                    (high + band + 1.0)
+                        .clamp(Vec3::ZERO, Vec3::splat(last as f32))
+                        .as_uvec3(),
+                )
+            })
+            .collect();
+
+        let slice = (RESOLUTION * RESOLUTION) as usize;
+        let mut distances = vec![f32::MAX; Self::sample_count()];
+        distances
+            .par_chunks_mut(slice)
+            .enumerate()
+            .for_each(|(z, slice)| {
+                let z = z as u32;
+                for (triangle, (low, high)) in triangles.iter().zip(&bounds) {
+                    if z < low.z || z > high.z {
+                        continue;
+                    }
+                    for y in low.y..=high.y {
+                        for x in low.x..=high.x {
+                            let point = Self::sample_position(x, y, z);
+                            let distance = point_triangle_distance(point, triangle);
+                            let sample = &mut slice[(x + RESOLUTION * y) as usize];
+                            *sample = sample.min(distance);
+                        }
+                    }
+                }
+            });
+        distances
+    }
+
+    /// Fills in the distances away from the band with a forward and a
+    /// backward chamfer sweep, close to euclidean this far from the surface
+    fn propagate(distances: &mut [f32]) {
+        let cell_size = Self::cell_size();
+        let mut offsets = Vec::new();
+        for z in -1..=1i32 {
+            for y in -1..=1i32 {
+                for x in -1..=1i32 {
+                    // Neighbors that come earlier in memory order
+                    if (z, y, x) < (0, 0, 0) {
+                        let length = ((x * x + y * y + z * z) as f32).sqrt() * cell_size;
+                        offsets.push((x, y, z, length));
+                    }
+                }
+            }
+        }
+
+        let size = RESOLUTION as i32;
+        let mut sweep = |coords: &mut dyn Iterator<Item = (i32, i32, i32)>, sign: i32| {
+            for (x, y, z) in coords {
+                let mut best = distances[Self::index(x as u32, y as u32, z as u32)];
+                for &(dx, dy, dz, length) in &offsets {
+                    let (nx, ny, nz) = (x + dx * sign, y + dy * sign, z + dz * sign);
+                    if nx < 0 || ny < 0 || nz < 0 || nx >= size || ny >= size || nz >= size {
+                        continue;
+                    }
+                    let neighbor = distances[Self::index(nx as u32, ny as u32, nz as u32)];
+                    best = best.min(neighbor + length);
+                }
+                distances[Self::index(x as u32, y as u32, z as u32)] = best;
+            }
+        };
+        let forward =
+            (0..size).flat_map(|z| (0..size).flat_map(move |y| (0..size).map(move |x| (x, y, z))));
+        sweep(&mut forward.clone(), 1);
+        sweep(
+            &mut forward.map(|(x, y, z)| (size - 1 - x, size - 1 - y, size - 1 - z)),
+            -1,
+        );
+    }
+
+    /// Negates the samples inside, counting surface crossings along +x
+    fn apply_sign(distances: &mut [f32], triangles: &[[Vec3; 3]]) {
+        distances
+            .par_chunks_mut(RESOLUTION as usize)
+            .enumerate()
+            .for_each(|(row, samples)| {
+                let y = row as u32 % RESOLUTION;
+                let z = row as u32 / RESOLUTION;
+                // Nudged off the grid so the ray doesn't run exactly through
+                // an edge shared by two triangles and count it twice
+                let origin = Self::sample_position(0, y, z) + Vec3::new(0.0, 1.37e-4, 2.91e-4);
+                let mut crossings: Vec<f32> = triangles
+                    .iter()
+                    .filter_map(|triangle| ray_x_crossing(origin.y, origin.z, triangle))
+                    .collect();
+                crossings.sort_by(f32::total_cmp);
+
+                let mut inside = false;
+                let mut next = 0;
+                for (x, sample) in samples.iter_mut().enumerate() {
+                    let position = Self::sample_position(x as u32, y, z).x;
+                    while next < crossings.len() && crossings[next] < position {
+                        inside = !inside;
+                        next += 1;
+                    }
+                    if inside {
+                        *sample = -*sample;
+                    }
+                }
+            });
+    }
+
+    /// Trilinear distance at `position` in grid space, growing with the
+    /// distance to the grid outside of it
+    // Keep in sync with `mesh_distance` in obstacles.wgsl
+    pub fn sample(&self, position: Vec3) -> f32 {
+        let inside = position.clamp(Vec3::splat(-1.0), Vec3::ONE);
+        let last = (RESOLUTION - 1) as f32;
+        let grid = ((inside + 1.0) / Self::cell_size()).clamp(Vec3::ZERO, Vec3::splat(last));
+        let base = grid.floor().min(Vec3::splat(last - 1.0));
+        let t = grid - base;
+        let base = base.as_uvec3();
+
+        let at = |dx: u32, dy: u32, dz: u32| {
+            self.distances[Self::index(base.x + dx, base.y + dy, base.z + dz)]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let x00 = lerp(at(0, 0, 0), at(1, 0, 0), t.x);
+        let x10 = lerp(at(0, 1, 0), at(1, 1, 0), t.x);
+        let x01 = lerp(at(0, 0, 1), at(1, 0, 1), t.x);
+        let x11 = lerp(at(0, 1, 1), at(1, 1, 1), t.x);
+        let distance = lerp(lerp(x00, x10, t.y), lerp(x01, x11, t.y), t.z);
+        distance + (position - inside).length()
+    }
+}
+
+/// Triangles of the faces in a Wavefront OBJ, polygons split into fans
+fn parse_obj(text: &str) -> Result<Vec<[Vec3; 3]>, String> {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let error = |what: &str| format!("line {}: {what}", number + 1);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let mut coords = words.take(3).map(str::parse::<f32>);
+                let mut next = || {
+                    coords
+                        .next()
+                        .and_then(Result::ok)
+                        .ok_or_else(|| error("bad vertex"))
+                };
+                vertices.push(Vec3::new(next()?, next()?, next()?));
+            }
+            Some("f") => {
+                let corners = words
+                    .map(|word| {
+                        // Only the position of `v/vt/vn`, negative counts from the end
+                        let index: i64 = word
+                            .split('/')
+                            .next()
+                            .and_then(|index| index.parse().ok())
+                            .ok_or_else(|| error("bad face"))?;
+                        let index = if index < 0 {
+                            vertices.len() as i64 + index
+                        } else {
+                            index - 1
+                        };
+                        vertices
+                            .get(index as usize)
+                            .copied()
+                            .ok_or_else(|| error("face refers to a missing vertex"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for i in 1..corners.len().saturating_sub(1) {
+                    triangles.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+/// Where the ray along +x through (y, z) crosses `triangle`, if it does
+fn ray_x_crossing(y: f32, z: f32, [a, b, c]: &[Vec3; 3]) -> Option<f32> {
+    // Barycentric coordinates of (y, z) in the triangle projected onto yz
+    let edge = |p: Vec3, q: Vec3| (q.y - p.y) * (z - p.z) - (q.z - p.z) * (y - p.y);
+    let (u, v, w) = (edge(*b, *c), edge(*c, *a), edge(*a, *b));
+    let inside = (u >= 0.0 && v >= 0.0 && w >= 0.0) || (u <= 0.0 && v <= 0.0 && w <= 0.0);
+    let area = u + v + w;
+    if !inside || area == 0.0 {
+        return None;
+    }
+    Some((u * a.x + v * b.x + w * c.x) / area)
+}
+
+/// Closest point on a triangle, from Ericson's Real-Time Collision Detection
+fn point_triangle_distance(p: Vec3, &[a, b, c]: &[Vec3; 3]) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return p.distance(a);
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return p.distance(b);
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return p.distance(a + ab * (d1 / (d1 - d3)));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return p.distance(c);
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return p.distance(a + ac * (d2 / (d2 - d6)));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return p.distance(b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6))));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    p.distance(a + ab * (vb * denom) + ac * (vc * denom))
+}
@@ -1,32 +1,656 @@
-use bytemuck::{Pod, Zeroable};
 use glam::{Vec3, Vec4};
 use rand::{Rng, SeedableRng};
 use wgpu::{CommandEncoder, Device, Queue};
 
+pub mod analysis;
+pub mod attractors;
+pub mod background_grid;
+pub mod barnes_hut;
+pub mod chemistry;
+pub mod collisions;
 pub mod compute;
 pub mod cpu;
+pub mod dispatch;
+pub mod electrostatics;
+pub mod field_graph;
+pub mod flip;
+pub mod flocking;
+pub mod flow_field;
+pub mod forces;
+pub mod frame_graph;
+pub mod gpu_buffer;
+pub mod gpu_grid;
+pub mod gpu_scan;
+pub mod gpu_sort;
+#[cfg(test)]
+mod gpu_test;
+pub mod grid;
+pub mod health;
+pub mod heat;
+mod layout;
+pub mod lennard_jones;
+pub mod magnetism;
+pub mod mesh_sdf;
+pub mod mpm;
+pub mod noise;
+pub mod obstacles;
+pub mod particle_life;
+pub mod readback;
+pub mod springs;
+pub mod strange;
+pub mod surface;
+
+use attractors::PointAttractor;
+use chemistry::ReactionRule;
+use collisions::CONTACT_IMPULSE;
+use field_graph::FieldGraph;
+use flow_field::FlowField;
+use forces::{Force, ForceStack};
+use mesh_sdf::MeshSdf;
+use mpm::MATERIAL_SNOW;
+use obstacles::Obstacle;
+use particle_life::InteractionMatrix;
+use std::sync::Arc;
+use strange::STRANGE_NONE;
+use surface::SURFACE_NONE;
+
+pub const MAX_SPECIES: u32 = 8;
+
+// Keep in sync with `species_color` in colors.wgsl
+pub const SPECIES_COLORS: [[f32; 4]; MAX_SPECIES as usize] = [
+    [0.95, 0.30, 0.25, 1.0],
+    [0.25, 0.60, 0.95, 1.0],
+    [0.35, 0.90, 0.40, 1.0],
+    [0.95, 0.85, 0.25, 1.0],
+    [0.75, 0.40, 0.95, 1.0],
+    [0.25, 0.90, 0.85, 1.0],
+    [0.95, 0.55, 0.15, 1.0],
+    [0.90, 0.90, 0.90, 1.0],
+];
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SimulationMethod {
     Cpu,
     ComputeShader,
+    /// The CPU backend with mutual gravity between the particles
+    BarnesHut,
+    /// Flocking on the compute shader backend, or on the CPU without one
+    Boids,
+    /// The compute shader backend with a FLIP/PIC liquid filling the box
+    Liquid,
+    /// The compute shader backend with the particles as snow or sand, the
+    /// material point method
+    Mpm,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl SimulationMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            SimulationMethod::Cpu => "CPU",
+            SimulationMethod::ComputeShader => "Compute Shader",
+            SimulationMethod::BarnesHut => "Barnes-Hut N-body",
+            SimulationMethod::Boids => "Boids",
+            SimulationMethod::Liquid => "FLIP Liquid",
+            SimulationMethod::Mpm => "MPM Snow & Sand",
+        }
+    }
+}
+
+/// Optional features a backend may or may not implement, so the UI can tell
+/// the user instead of silently ignoring the matching parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Reactions,
+    HeatConduction,
+    DipoleForces,
+    MolecularDynamics,
+    RadialDistribution,
+    LayerCoupling,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 6] = [
+        Capability::Reactions,
+        Capability::HeatConduction,
+        Capability::DipoleForces,
+        Capability::MolecularDynamics,
+        Capability::RadialDistribution,
+        Capability::LayerCoupling,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Reactions => "Reactions",
+            Capability::HeatConduction => "Heat conduction",
+            Capability::DipoleForces => "Dipole forces",
+            Capability::MolecularDynamics => "Molecular dynamics",
+            Capability::RadialDistribution => "g(r) measurement",
+            Capability::LayerCoupling => "Layer coupling",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum SphereGeneration {
     Hollow,
     Filled,
+    /// Flat ring in the XZ plane launched tangentially, for orbit experiments
+    OrbitRing,
+    /// Horizontal square sheet held together by springs, to drape over things
+    ClothGrid,
+    /// Thin two-armed disc in the XZ plane with every particle on a circular
+    /// orbit around a central mass
+    Galaxy,
+}
+
+pub const SPAWN_HOLLOW: u32 = 0;
+pub const SPAWN_FILLED: u32 = 1;
+
+impl SphereGeneration {
+    /// Value of `SimParams::spawn_mode` for this shape, `None` if particles
+    /// can't be re-seeded from it
+    pub fn spawn_mode(self) -> Option<u32> {
+        match self {
+            SphereGeneration::Hollow => Some(SPAWN_HOLLOW),
+            SphereGeneration::Filled => Some(SPAWN_FILLED),
+            SphereGeneration::OrbitRing
+            | SphereGeneration::ClothGrid
+            | SphereGeneration::Galaxy => None,
+        }
+    }
+}
+
+/// How the masses of generated particles are spread
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MassDistribution {
+    /// Every particle has mass 1
+    Equal,
+    /// Log-uniform between 1 and the mass ratio, as many light as heavy
+    /// particles per doubling
+    Random,
+    /// Evenly spaced on a log scale from the first species at 1 to the last
+    /// at the mass ratio
+    BySpecies,
+}
+
+impl MassDistribution {
+    pub const ALL: [MassDistribution; 3] = [
+        MassDistribution::Equal,
+        MassDistribution::Random,
+        MassDistribution::BySpecies,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MassDistribution::Equal => "Equal",
+            MassDistribution::Random => "Random",
+            MassDistribution::BySpecies => "By Species",
+        }
+    }
+}
+
+/// Radius of the sphere particles are generated in
+pub const SPAWN_RADIUS: f32 = 50.0;
+/// Height cloths are generated at
+const CLOTH_HEIGHT: f32 = 20.0;
+/// Fraction of a galaxy's radius left empty around the central mass, where
+/// the orbits would be too fast to follow
+const GALAXY_CORE: f32 = 0.1;
+/// Radians the galaxy arms wind through per e-fold of radius
+const GALAXY_WINDING: f32 = 2.5;
+
+/// Where particle `index` out of `count` (re)spawns. Hollow spheres put every
+/// particle back on its own spot of the golden-angle spiral, filled spheres
+/// draw a fresh random point for every `step`.
+// Keep in sync with `spawn_position` in the compute shader
+pub fn spawn_position(index: u32, count: u32, spawn_mode: u32, step: u32) -> Vec3 {
+    if spawn_mode == SPAWN_FILLED {
+        let r = SPAWN_RADIUS * random_unit(index, step, 1).cbrt();
+        let theta = random_unit(index, step, 2) * 2.0 * std::f32::consts::PI;
+        let phi = (random_unit(index, step, 3) * 2.0 - 1.0).acos();
+        return Vec3::new(
+            r * phi.sin() * theta.cos(),
+            r * phi.cos(),
+            r * phi.sin() * theta.sin(),
+        );
+    }
+
+    let golden_angle = std::f32::consts::PI * (3.0 - (5.0_f32).sqrt());
+    let y = 1.0 - (index as f32 / (count.max(2) - 1) as f32) * 2.0; // y goes from 1 to -1
+    let radius_at_y = (1.0 - y * y).max(0.0).sqrt(); // radius at y
+    let theta = golden_angle * index as f32; // golden angle increment
+    Vec3::new(theta.cos() * radius_at_y, y, theta.sin() * radius_at_y) * SPAWN_RADIUS
+}
+
+/// Original color of a particle spawned at `position`
+// Keep in sync with `spawn_color` in the compute shader
+pub fn spawn_color(position: Vec3) -> Vec4 {
+    let norm_pos = (position / SPAWN_RADIUS + Vec3::ONE) * 0.5;
+    norm_pos.extend(1.0)
+}
+
+/// Cheap stateless hash mapped to [0, 1), so parallel workers don't have to
+/// share an RNG
+// Keep in sync with `random_unit` in random.wgsl
+pub fn random_unit(index: u32, step: u32, salt: u32) -> f32 {
+    let mut h = index
+        .wrapping_mul(0x9E37_79B9)
+        .wrapping_add(step.wrapping_mul(0x85EB_CA6B))
+        .wrapping_add(salt.wrapping_mul(0xC2B2_AE35));
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Salt for the per-step respawn roll, distinct from the spawn position ones
+pub const RESPAWN_SALT: u32 = 4;
+/// Salts for the lifetime rolls and the direction and speed of re-emission
+pub const LIFETIME_SALT: u32 = 5;
+pub const AGE_SALT: u32 = 6;
+pub const EMIT_SALT: u32 = 7;
+/// Salt for rounding the dead revived per step at the emission rate
+pub const EMISSION_SALT: u32 = 10;
+
+/// `emitter_mode` re-seeding expired particles from the spawn shape at rest
+pub const EMIT_SPAWN_SHAPE: u32 = 0;
+/// `emitter_mode` launching expired particles from the emitter in a cone
+/// around straight up
+pub const EMIT_FOUNTAIN: u32 = 1;
+/// `emitter_mode` launching expired particles from the emitter in every
+/// direction. Lifetimes aren't staggered, so everything goes off at once.
+pub const EMIT_BURST: u32 = 2;
+
+/// Shortest lifetime rolled, so particles live for at least a step or so
+const MIN_LIFETIME: f32 = 0.05;
+
+/// `Particle::lifetime` of a dead particle, undrawn and left out of every
+/// pass until the emission rate revives it
+// Keep in sync with `DEAD` in the compute shader
+pub const DEAD: f32 = -1.0;
+
+/// Lifetime of particle `index` re-emitted at `step`, the mean
+/// `params.lifetime` varied by up to `params.lifetime_variation` of it
+// Keep in sync with `roll_lifetime` in the compute shader
+pub fn roll_lifetime(index: u32, step: u32, params: &SimParams) -> f32 {
+    let variation =
+        params.lifetime_variation * (random_unit(index, step, LIFETIME_SALT) * 2.0 - 1.0);
+    (params.lifetime * (1.0 + variation)).max(MIN_LIFETIME)
+}
+
+/// Dead particles revived this step, `params.emission_rate` over the step
+/// rounded up or down at random so the rate holds on average
+// Keep in sync with `emission_budget` in the compute shader
+pub fn emission_budget(params: &SimParams) -> u32 {
+    let expected = params.emission_rate * params.delta_time;
+    (expected + random_unit(0, params.step, EMISSION_SALT)) as u32
+}
+
+/// Sends particle `index` off from the emitter again, with a fresh lifetime
+/// if they're limited
+// Keep in sync with `reemit` in the compute shader
+pub fn reemit(particle: &mut Particle, index: u32, params: &SimParams) {
+    let (position, velocity, color) = emit(index, params);
+    particle.position = position.into();
+    particle.velocity = velocity.into();
+    particle.temperature = 0.0;
+    particle.initial_color = color.into();
+    particle.age = 0.0;
+    particle.lifetime = if params.lifetime > 0.0 {
+        roll_lifetime(index, params.step, params)
+    } else {
+        0.0
+    };
+}
+
+/// Position, velocity and color particle `index` is re-emitted with once its
+/// lifetime is up
+// Keep in sync with `emit` in the compute shader
+pub fn emit(index: u32, params: &SimParams) -> (Vec3, Vec3, Vec4) {
+    let step = params.step;
+    if params.emitter_mode == EMIT_SPAWN_SHAPE {
+        let position = spawn_position(index, params.particle_count, params.spawn_mode, step);
+        return (position, Vec3::ZERO, spawn_color(position));
+    }
+
+    // Uniform over the cap of the sphere within the spread of straight up,
+    // or over the whole sphere for bursts
+    let min_cos = if params.emitter_mode == EMIT_BURST {
+        -1.0
+    } else {
+        params.emitter_spread.cos()
+    };
+    let cos_theta = 1.0 - random_unit(index, step, EMIT_SALT) * (1.0 - min_cos);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = random_unit(index, step, EMIT_SALT + 1) * 2.0 * std::f32::consts::PI;
+    let direction = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+    let speed = params.emitter_speed * (0.8 + 0.4 * random_unit(index, step, EMIT_SALT + 2));
+    let position = Vec3::from(params.emitter_position);
+    (
+        position,
+        direction * speed,
+        spawn_color(direction * SPAWN_RADIUS),
+    )
+}
+
+/// Black → red → yellow → white ramp over `temperature` in [0, 1].
+// Keep in sync with `temperature_color` in colors.wgsl
+pub fn temperature_color(temperature: f32) -> [f32; 4] {
+    let t = temperature.clamp(0.0, 1.0);
+    [
+        (t * 3.0).min(1.0),
+        (t * 3.0 - 1.0).clamp(0.0, 1.0),
+        (t * 3.0 - 2.0).clamp(0.0, 1.0),
+        1.0,
+    ]
+}
+
+/// Lift of a particle at `temperature`, pointing against gravity when it is
+/// warmer than the surroundings and with it when colder. Straight up without
+/// gravity.
+// Keep in sync with `buoyancy` in the compute shader
+pub fn buoyancy(temperature: f32, params: &SimParams) -> Vec3 {
+    let up = (-Vec3::from(params.gravity))
+        .try_normalize()
+        .unwrap_or(Vec3::Y);
+    up * (params.buoyancy * (temperature - params.ambient_temperature))
+}
+
+/// `color_mode` coloring particles by how many neighbors they have
+pub const COLOR_DENSITY: u32 = 7;
+/// `color_mode` coloring particles by how far through their life they are
+pub const COLOR_AGE: u32 = 8;
+/// `color_mode` coloring particles by how far they moved since the
+/// reference positions were taken
+pub const COLOR_DISPLACEMENT: u32 = 9;
+/// Age at which the age ramp saturates for particles that live forever
+pub const AGE_FULL: f32 = 10.0;
+
+/// White-hot → yellow → deep red ramp over the fraction of the lifetime
+/// lived, or over `AGE_FULL` seconds without a lifetime.
+// Keep in sync with `age_color` in colors.wgsl
+pub fn age_color(age: f32, lifetime: f32) -> [f32; 4] {
+    let span = if lifetime > 0.0 { lifetime } else { AGE_FULL };
+    let t = (age / span).clamp(0.0, 1.0);
+    [
+        1.0 - 0.5 * t,
+        (1.0 - t * 1.5).max(0.0) * 0.9 + 0.1 * (1.0 - t),
+        (1.0 - t * 3.0).max(0.0) * 0.8,
+        1.0,
+    ]
+}
+/// Dark purple → teal → bright yellow ramp over `distance` moved, saturating
+/// at `full`, so stagnant particles stay dark and active ones light up
+// Keep in sync with `displacement_color` in colors.wgsl
+pub fn displacement_color(distance: f32, full: f32) -> [f32; 4] {
+    let t = (distance / full.max(0.01)).clamp(0.0, 1.0);
+    [
+        0.25 * (1.0 - t) + t * t,
+        0.05 + 0.85 * t,
+        0.35 + 0.3 * t - 0.55 * t * t,
+        1.0,
+    ]
+}
+
+/// Neighbors within the contact radius at which the density ramp saturates
+pub const DENSITY_FULL: f32 = 24.0;
+
+/// Dark blue → orange → pale yellow ramp over the neighbor count.
+// Keep in sync with `density_color` in colors.wgsl
+pub fn density_color(neighbors: u32) -> [f32; 4] {
+    let d = (neighbors as f32 / DENSITY_FULL).min(1.0);
+    [
+        d,
+        0.15 + 0.75 * d * d,
+        0.45 * (1.0 - d) + 0.6 * d * d * d,
+        1.0,
+    ]
+}
+
+/// Boris push of `velocity` through uniform E and B fields, which keeps
+/// gyration stable where a plain Euler step would spiral outwards.
+// Keep in sync with `lorentz_push` in the compute shader
+pub fn lorentz_push(
+    velocity: Vec3,
+    charge_per_mass: f32,
+    electric_field: Vec3,
+    magnetic_field: Vec3,
+    delta_time: f32,
+) -> Vec3 {
+    let half_kick = electric_field * (charge_per_mass * delta_time * 0.5);
+    let v_minus = velocity + half_kick;
+    let t = magnetic_field * (charge_per_mass * delta_time * 0.5);
+    let s = 2.0 * t / (1.0 + t.length_squared());
+    let v_prime = v_minus + v_minus.cross(t);
+    let v_plus = v_minus + v_prime.cross(s);
+    v_plus + half_kick
+}
+
+/// How positions and velocities are advanced each step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Integrator {
+    /// Semi-implicit Euler: kick by the full step, then drift
+    #[default]
+    Euler,
+    /// Half kick, drift, half kick with the forces at the new position, which
+    /// keeps orbits and strong mouse pulls from gaining energy
+    VelocityVerlet,
+}
+
+pub const INTEGRATOR_EULER: u32 = 0;
+pub const INTEGRATOR_VERLET: u32 = 1;
+
+impl Integrator {
+    pub const ALL: [Integrator; 2] = [Integrator::Euler, Integrator::VelocityVerlet];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::VelocityVerlet => "Velocity Verlet",
+        }
+    }
+
+    /// Value of `SimParams::integrator`
+    pub fn mode(self) -> u32 {
+        match self {
+            Integrator::Euler => INTEGRATOR_EULER,
+            Integrator::VelocityVerlet => INTEGRATOR_VERLET,
+        }
+    }
+}
+
+/// Gravity pulls everything along `SimParams::gravity`
+pub const GRAVITY_UNIFORM: u32 = 0;
+/// Gravity pulls towards `SimParams::gravity_center`, weakening with distance
+pub const GRAVITY_CENTRAL_LINEAR: u32 = 1;
+/// Like [`GRAVITY_CENTRAL_LINEAR`], weakening with the square of distance
+pub const GRAVITY_CENTRAL_INVERSE_SQUARE: u32 = 2;
+
+pub const BOUNDARY_OPEN: u32 = 0;
+pub const BOUNDARY_PERIODIC: u32 = 1;
+pub const BOUNDARY_CONTAINER: u32 = 2;
+
+impl SimParams {
+    /// Half extents of the simulation box when it wraps around
+    pub fn periodic_box(&self) -> Option<Vec3> {
+        (self.boundary_mode == BOUNDARY_PERIODIC).then(|| Vec3::from(self.box_half_extents))
+    }
+
+    /// Half extents of the simulation box when particles bounce off its walls
+    pub fn container_box(&self) -> Option<Vec3> {
+        (self.boundary_mode == BOUNDARY_CONTAINER).then(|| Vec3::from(self.box_half_extents))
+    }
+}
+
+/// Puts a particle that left the box of `half_extents` back on the wall it
+/// crossed, reversing its velocity into that wall and keeping `restitution`
+/// of it
+// Keep in sync with the container boundary in the compute shader
+pub fn reflect_walls(
+    mut position: Vec3,
+    mut velocity: Vec3,
+    half_extents: Vec3,
+    restitution: f32,
+) -> (Vec3, Vec3) {
+    for axis in 0..3 {
+        if position[axis].abs() > half_extents[axis] {
+            if velocity[axis] * position[axis] > 0.0 {
+                velocity[axis] *= -restitution;
+            }
+            position[axis] = position[axis].clamp(-half_extents[axis], half_extents[axis]);
+        }
+    }
+    (position, velocity)
+}
+
+/// Lands a particle that sank below the plane y = `height` back on it,
+/// bouncing it up with `restitution` and slowing its slide by `friction`
+// Keep in sync with the ground plane in the compute shader
+pub fn land_on_ground(
+    mut position: Vec3,
+    mut velocity: Vec3,
+    height: f32,
+    restitution: f32,
+    friction: f32,
+) -> (Vec3, Vec3) {
+    if position.y < height {
+        position.y = height;
+        if velocity.y < 0.0 {
+            velocity.y *= -restitution;
+        }
+        velocity.x *= 1.0 - friction;
+        velocity.z *= 1.0 - friction;
+    }
+    (position, velocity)
+}
+
+/// Softening length of the attractor, keeps close passes from blowing up
+pub const ATTRACTOR_SOFTENING: f32 = 0.5;
+
+/// Plummer-softened pull towards a point mass, `mass` being G·M
+// Keep in sync with `attractor_acceleration` in the compute shader
+pub fn attractor_acceleration(position: Vec3, attractor: Vec3, mass: f32) -> Vec3 {
+    let offset = attractor - position;
+    let dist_sq = offset.length_squared() + ATTRACTOR_SOFTENING * ATTRACTOR_SOFTENING;
+    offset * (mass / (dist_sq * dist_sq.sqrt()))
+}
+
+/// Velocity for a circular orbit at `position` around the attractor at the
+/// origin, sqrt(GM/r) softened like [`attractor_acceleration`], turning
+/// around the y axis the same way as the galaxy
+pub fn circular_orbit_velocity(position: Vec3, mass: f32) -> Vec3 {
+    let r = position.length();
+    let pull = attractor_acceleration(position, Vec3::ZERO, mass).length();
+    // Particles right above or below the center orbit around the x axis
+    let tangent = Vec3::Y
+        .cross(position)
+        .try_normalize()
+        .or_else(|| Vec3::X.cross(position).try_normalize())
+        .unwrap_or(Vec3::ZERO);
+    -tangent * (pull * r).sqrt()
+}
+
+/// Distance from the center within which central gravity pulls at its full
+/// strength, capping the pull on particles passing through the middle
+pub const CENTRAL_GRAVITY_RADIUS: f32 = 10.0;
+
+/// Pull of central gravity in `mode` towards `center`, `strength` up to
+/// [`CENTRAL_GRAVITY_RADIUS`] away and falling off past it
+// Keep in sync with `central_gravity_acceleration` in the compute shader
+pub fn central_gravity(position: Vec3, center: Vec3, strength: f32, mode: u32) -> Vec3 {
+    let offset = center - position;
+    let dist = offset.length();
+    if dist == 0.0 {
+        return Vec3::ZERO;
+    }
+    let ratio = CENTRAL_GRAVITY_RADIUS / dist.max(CENTRAL_GRAVITY_RADIUS);
+    let falloff = if mode == GRAVITY_CENTRAL_INVERSE_SQUARE {
+        ratio * ratio
+    } else {
+        ratio
+    };
+    offset * (strength * falloff / dist)
 }
 
-pub trait ParticleSimulation {
+/// Wraps `position` back into the box centered at the origin
+// Keep in sync with `wrap_periodic` in the compute shader
+pub fn wrap_periodic(position: Vec3, half_extents: Vec3) -> Vec3 {
+    (position + half_extents).rem_euclid(half_extents * 2.0) - half_extents
+}
+
+/// `offset` to the nearest of the periodic images of its end in the box of
+/// `half_extents`
+// Keep in sync with `nearest_image` in springs.wgsl and displacement.wgsl
+pub fn nearest_image(offset: Vec3, half_extents: Vec3) -> Vec3 {
+    let box_size = half_extents * 2.0;
+    offset - box_size * (offset / box_size).round()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GenerationSettings {
+    pub mode: SphereGeneration,
+    /// Particles are assigned species round-robin in `0..species_count`
+    pub species_count: u32,
+    /// Radius and tangential launch speed of `SphereGeneration::OrbitRing`,
+    /// or the disc radius and the orbital speed at its rim for
+    /// `SphereGeneration::Galaxy`
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub mass_distribution: MassDistribution,
+    /// Heaviest over lightest mass, the lightest being 1
+    pub mass_ratio: f32,
+    /// Distance within which generated particles are tied to their nearest
+    /// neighbors by springs, 0 for none. Cloths always get their grid.
+    pub spring_radius: f32,
+    /// Launch the particles of the spheres on circular orbits around the
+    /// central attractor instead of at rest
+    pub orbit_insertion: bool,
+    /// Spread of the launch speeds around the circular one, as a fraction,
+    /// making the orbits eccentric
+    pub orbit_jitter: f32,
+    /// Mixed into every random choice of the generation, the same seed
+    /// always gives the same particles
+    pub seed: u64,
+    /// G·M of the central attractor the orbits are inserted around, 0 while
+    /// it's off. Kept up to date by the app rather than saved.
+    #[serde(skip)]
+    pub orbit_mass: f32,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            mode: SphereGeneration::Hollow,
+            species_count: 1,
+            orbit_radius: 30.0,
+            orbit_speed: 0.0,
+            mass_distribution: MassDistribution::Equal,
+            mass_ratio: 10.0,
+            spring_radius: 0.0,
+            orbit_insertion: false,
+            orbit_jitter: 0.1,
+            seed: 0,
+            orbit_mass: 0.0,
+        }
+    }
+}
+
+/// A particle backend. Sendable on native, so the layers running in the
+/// background can be encoded on the rayon pool.
+pub trait ParticleSimulation: wgpu::WasmNotSend {
     fn new(
         device: &Device,
         initial_particle_count: u32,
         surface_format: wgpu::TextureFormat,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     ) -> Self
     where
         Self: Sized;
+    /// Records a step into `encoder`, which the caller submits. Nothing
+    /// besides buffer writes may go to the queue directly.
     fn update(
         &mut self,
         device: &Device,
@@ -39,38 +663,292 @@ pub trait ParticleSimulation {
         device: &Device,
         queue: &Queue,
         new_count: u32,
-        generation_mode: SphereGeneration,
+        generation: GenerationSettings,
     );
     fn get_particle_buffer(&self) -> &wgpu::Buffer;
     fn get_method(&self) -> SimulationMethod;
+    fn capabilities(&self) -> &'static [Capability];
+    fn supports(&self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
     fn get_particle_count(&self) -> u32;
-    fn reset(&mut self, device: &Device, queue: &Queue, generation_mode: SphereGeneration);
+    /// The passes the last step ran, for backends that declare them
+    fn frame_plan(&self) -> Option<&frame_graph::FramePlan> {
+        None
+    }
+    fn reset(&mut self, device: &Device, queue: &Queue, generation: GenerationSettings);
     fn is_paused(&self) -> bool;
     fn set_paused(&mut self, paused: bool);
+    /// Reactions need neighbor queries, backends without them ignore the rules
+    fn set_reaction_rules(&mut self, _rules: &[ReactionRule]) {}
+    /// Solid obstacles the particles bounce off, past
+    /// [`obstacles::MAX_OBSTACLES`] they're ignored
+    fn set_obstacles(&mut self, obstacles: &[Obstacle]);
+    /// Shape of the [`obstacles::ObstacleShape::Mesh`] obstacles
+    fn set_obstacle_mesh(&mut self, mesh: Arc<MeshSdf>);
+    /// Points pulling or pushing the particles around them, past
+    /// [`attractors::MAX_POINT_ATTRACTORS`] they're ignored
+    fn set_point_attractors(&mut self, attractors: &[PointAttractor]);
+    /// Order and choice of the field forces, past [`forces::MAX_FORCES`]
+    /// enabled ones they're ignored
+    fn set_forces(&mut self, forces: &ForceStack);
+    /// Force field wired in the node editor, applied on top of the stack
+    /// while enabled
+    fn set_field_graph(&mut self, graph: &FieldGraph);
+    /// Takes the particles where they are now as the reference
+    /// [`COLOR_DISPLACEMENT`] measures from. Also taken on creation, resets
+    /// and resizes.
+    fn mark_reference(&mut self, device: &Device, queue: &Queue);
+    /// Puts the particles where `particles` has them, as recordings play
+    /// back. Past the backend's particle count they're ignored.
+    fn set_particles(&mut self, device: &Device, queue: &Queue, particles: &[Particle]);
+    /// Attractions between the species for particle life
+    fn set_species_interactions(&mut self, matrix: &InteractionMatrix);
+    /// Flow of another layer to drag the particles along at `strength`,
+    /// backends without [`Capability::LayerCoupling`] ignore it
+    fn set_flow_field(&mut self, _field: Option<Arc<FlowField>>, _strength: f32) {}
+    /// Current state of the particles at `indices`, for overlays. GPU backends
+    /// answer with a copy requested on an earlier call, so this is `None`
+    /// until one has come back for the same indices.
+    fn sample_particles(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        indices: &[u32],
+    ) -> Option<Vec<Particle>>;
+    /// Extremes of the particle state for the watchdog. GPU backends answer
+    /// with a reduction requested on an earlier call, like
+    /// [`Self::sample_particles`].
+    fn check_health(&mut self, device: &Device, queue: &Queue) -> Option<health::Health>;
+    /// Particles that aren't dead, as counted by the last step with an
+    /// emission rate. GPU backends answer a call or so late, like
+    /// [`Self::check_health`].
+    fn alive_count(&mut self, device: &Device, queue: &Queue) -> Option<u32>;
+    /// Metrics of every species present. GPU backends measure a sample read
+    /// back like [`Self::sample_particles`], answering a call or so late.
+    fn group_statistics(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+    ) -> Option<Vec<analysis::GroupStats>>;
+    /// g(r) over `bins` shells up to `max_radius`, `None` if the backend
+    /// can't measure it
+    fn radial_distribution(
+        &mut self,
+        _max_radius: f32,
+        _bins: usize,
+        _periodic_box: Option<Vec3>,
+    ) -> Option<Vec<f32>> {
+        None
+    }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct SimParams {
-    pub delta_time: f32,
-    pub gravity: f32,
-    pub color_mode: u32,
-    pub mouse_force: f32,
+layout::gpu_struct! {
+    pub struct SimParams (version 28) {
+        pub delta_time: f32 => "f32",
+        /// Number of entries in the force buffer that are in use
+        pub force_count: u32 => "u32",
+        pub color_mode: u32 => "u32",
+        pub mouse_force: f32 => "f32",
+
+        pub mouse_radius: f32 => "f32",
+        pub is_mouse_dragging: u32 => "u32",
+        pub damping: f32 => "f32",
+        pub max_dist_for_color: f32 => "f32",
+
+        pub mouse_position: [f32; 3] => "vec3<f32>",
+        /// Number of entries in the point attractor buffer that are in use
+        pub point_attractor_count: u32 => "u32",
+
+        pub contact_radius: f32 => "f32",
+        pub conduction: f32 => "f32",
+        pub mouse_heat: f32 => "f32",
+        /// Radians off straight up fountains launch particles within
+        pub emitter_spread: f32 => "f32",
 
-    pub mouse_radius: f32,
-    pub is_mouse_dragging: u32,
-    pub damping: f32,
-    pub max_dist_for_color: f32,
+        pub dipole_strength: f32 => "f32",
+        pub dipole_radius: f32 => "f32",
+        /// Mean lifetime in seconds, 0 lets particles live forever
+        pub lifetime: f32 => "f32",
+        /// Fraction of the mean lifetimes are varied by
+        pub lifetime_variation: f32 => "f32",
 
-    pub mouse_position: [f32; 3],
-    pub _padding2: u32,
+        pub electric_field: [f32; 3] => "vec3<f32>",
+        pub lorentz_enabled: u32 => "u32",
+
+        pub magnetic_field: [f32; 3] => "vec3<f32>",
+        /// One of the `EMIT_*` constants
+        pub emitter_mode: u32 => "u32",
+
+        /// Lennard-Jones well depth, 0 disables the pair force
+        pub lj_epsilon: f32 => "f32",
+        pub lj_sigma: f32 => "f32",
+        /// Interaction range in multiples of `lj_sigma`
+        pub lj_cutoff: f32 => "f32",
+        pub thermostat_target: f32 => "f32",
+
+        /// Berendsen relaxation time, 0 disables the thermostat
+        pub thermostat_tau: f32 => "f32",
+        /// Strength of the curl noise acceleration, 0 disables it
+        pub turbulence_amplitude: f32 => "f32",
+        /// Noise features per unit of distance
+        pub turbulence_frequency: f32 => "f32",
+        /// How far the noise has scrolled up since the start
+        pub turbulence_scroll: f32 => "f32",
+
+        /// Wind acceleration, gusts scale it up and down
+        pub wind: [f32; 3] => "vec3<f32>",
+        /// How much the gusts vary the wind, 0 keeps it steady
+        pub wind_gustiness: f32 => "f32",
+
+        pub box_half_extents: [f32; 3] => "vec3<f32>",
+        /// 0 = open, 1 = periodic, 2 = container
+        pub boundary_mode: u32 => "u32",
+
+        /// Fraction of particles re-seeded from the spawn shape per second
+        pub respawn_rate: f32 => "f32",
+        /// `SPAWN_HOLLOW` or `SPAWN_FILLED`
+        pub spawn_mode: u32 => "u32",
+        /// Frame counter, seeds the per-particle random rolls
+        pub step: u32 => "u32",
+        pub particle_count: u32 => "u32",
+
+        pub attractor_position: [f32; 3] => "vec3<f32>",
+        /// G·M of the central attractor, 0 disables it
+        pub attractor_mass: f32 => "f32",
+
+        /// G·M of all particles together for mutual gravity, 0 disables it
+        pub nbody_mass: f32 => "f32",
+        /// Barnes–Hut opening angle, larger is faster and less accurate
+        pub nbody_theta: f32 => "f32",
+        pub nbody_softening: f32 => "f32",
+        /// Launch speed of fountains and bursts
+        pub emitter_speed: f32 => "f32",
+
+        pub emitter_position: [f32; 3] => "vec3<f32>",
+        /// Coulomb constant between the charges, negative makes like charges
+        /// attract, 0 disables it
+        pub coulomb_strength: f32 => "f32",
+
+        /// How far a boid sees its flockmates
+        pub boid_radius: f32 => "f32",
+        pub boid_separation: f32 => "f32",
+        pub boid_alignment: f32 => "f32",
+        pub boid_cohesion: f32 => "f32",
+
+        /// Speed boids are capped at, 0 leaves them uncapped
+        pub boid_max_speed: f32 => "f32",
+        /// Radius of every particle for collisions, 0 lets them pass through
+        /// each other
+        pub collision_radius: f32 => "f32",
+        /// Fraction of the approach speed kept after a collision
+        pub restitution: f32 => "f32",
+        /// Fraction of the speed into a container wall kept after bouncing
+        pub wall_restitution: f32 => "f32",
+
+        /// 1 = particles land on the plane y = `ground_height`
+        pub ground_enabled: u32 => "u32",
+        pub ground_height: f32 => "f32",
+        pub ground_restitution: f32 => "f32",
+        /// Fraction of the sliding velocity lost on every contact
+        pub ground_friction: f32 => "f32",
+
+        /// Number of entries in the obstacle buffer that are in use
+        pub obstacle_count: u32 => "u32",
+        /// Fraction of the speed into an obstacle kept after bouncing
+        pub obstacle_restitution: f32 => "f32",
+        /// One of the `INTEGRATOR_*` constants
+        pub integrator: u32 => "u32",
+        /// Simulated seconds, for fields that change on their own
+        pub time: f32 => "f32",
+
+        /// Fraction of their error springs are relaxed by per iteration, 0
+        /// leaves them slack
+        pub spring_stiffness: f32 => "f32",
+        /// Relaxation passes over the springs per step
+        pub spring_iterations: u32 => "u32",
+        /// Upward acceleration per degree above the ambient temperature
+        pub buoyancy: f32 => "f32",
+        /// Temperature at which particles neither rise nor sink
+        pub ambient_temperature: f32 => "f32",
+
+        /// One of the `STRANGE_*` flows particles are carried along
+        pub strange_mode: u32 => "u32",
+        /// How quickly particles take on the flow's velocity, per second
+        pub strange_follow: f32 => "f32",
+        /// World units per attractor unit
+        pub strange_scale: f32 => "f32",
+        /// Attractor time per simulated second
+        pub strange_speed: f32 => "f32",
+
+        /// The flow's coefficients, e.g. σ, ρ and β of the Lorenz system
+        pub strange_coefficients: [f32; 3] => "vec3<f32>",
+        /// Distance past which charges stop feeling each other
+        pub coulomb_cutoff: f32 => "f32",
+
+        /// Scale of the particle life forces between species, 0 disables them
+        pub particle_life_strength: f32 => "f32",
+        /// Distance past which species stop feeling each other
+        pub particle_life_radius: f32 => "f32",
+        /// One of the `GRAVITY_*` modes
+        pub gravity_mode: u32 => "u32",
+        /// Pull towards `gravity_center` in the central modes
+        pub central_gravity: f32 => "f32",
+
+        /// Acceleration everything falls with in [`GRAVITY_UNIFORM`],
+        /// straight down in most scenes, zero in the central modes
+        pub gravity: [f32; 3] => "vec3<f32>",
+        pub _padding20: u32 => "u32",
+
+        /// Point the central modes pull towards
+        pub gravity_center: [f32; 3] => "vec3<f32>",
+        /// How far each particle's damping and gravity stray from the rest,
+        /// as a fraction
+        pub variation: f32 => "f32",
+
+        /// How much of the liquid's velocity change particles keep as their
+        /// own (FLIP) instead of taking the grid's (PIC)
+        pub flip_ratio: f32 => "f32",
+        /// Cells along the longest side of the box in the liquid grid
+        pub liquid_resolution: u32 => "u32",
+        /// Red-black sweeps of the liquid's pressure solve per step
+        pub pressure_iterations: u32 => "u32",
+        /// One of the `MATERIAL_*` modes of the material point method
+        pub mpm_material: u32 => "u32",
+
+        /// How much snow stiffens as it packs
+        pub hardening: f32 => "f32",
+        /// Steepest slope sand piles up to, in radians
+        pub friction_angle: f32 => "f32",
+        /// Cells along the longest side of the box in the material point grid
+        pub mpm_resolution: u32 => "u32",
+        /// One of the `CONTACT_*` models of collisions
+        pub contact_model: u32 => "u32",
+
+        /// Grip of granular contacts, as a share of the push between the pair
+        pub contact_friction: f32 => "f32",
+        /// How hard granular contacts brake the pair rolling over each other
+        pub rolling_resistance: f32 => "f32",
+        /// One of the `SURFACE_*` shapes the particles are held on
+        pub surface_mode: u32 => "u32",
+        /// Radius of the sphere shell, or of the torus's ring
+        pub surface_radius: f32 => "f32",
+
+        /// Radius of the torus's tube
+        pub surface_tube_radius: f32 => "f32",
+        /// Dead particles revived per second, 0 re-emits them as soon as
+        /// they die instead
+        pub emission_rate: f32 => "f32",
+        pub _padding22: u32 => "u32",
+        pub _padding23: u32 => "u32",
+    }
 }
 
 impl Default for SimParams {
     fn default() -> Self {
         Self {
             delta_time: 0.016,
-            gravity: 0.0,
+            force_count: Force::BUILT_IN.len() as u32,
             color_mode: 0,
             mouse_force: 5.0,
             mouse_radius: 10.0,
@@ -78,36 +956,148 @@ impl Default for SimParams {
             damping: 0.99,
             max_dist_for_color: 50.0,
             mouse_position: [0.0, 0.0, 0.0],
-            _padding2: 0,
+            point_attractor_count: 0,
+            contact_radius: 1.0,
+            conduction: 0.0,
+            mouse_heat: 0.0,
+            emitter_spread: 0.3,
+            dipole_strength: 0.0,
+            dipole_radius: 3.0,
+            lifetime: 0.0,
+            lifetime_variation: 0.3,
+            electric_field: [0.0, 0.0, 0.0],
+            lorentz_enabled: 0,
+            magnetic_field: [0.0, 1.0, 0.0],
+            emitter_mode: EMIT_SPAWN_SHAPE,
+            lj_epsilon: 0.0,
+            lj_sigma: 1.5,
+            lj_cutoff: 2.5,
+            thermostat_target: 0.5,
+            thermostat_tau: 0.0,
+            turbulence_amplitude: 0.0,
+            turbulence_frequency: 0.05,
+            turbulence_scroll: 0.0,
+            wind: [0.0; 3],
+            wind_gustiness: 0.0,
+            box_half_extents: [50.0, 50.0, 50.0],
+            boundary_mode: 0,
+            respawn_rate: 0.0,
+            spawn_mode: SPAWN_HOLLOW,
+            step: 0,
+            particle_count: 0,
+            attractor_position: [0.0, 0.0, 0.0],
+            attractor_mass: 0.0,
+            nbody_mass: 0.0,
+            nbody_theta: 0.7,
+            nbody_softening: 1.0,
+            emitter_speed: 20.0,
+            emitter_position: [0.0, 0.0, 0.0],
+            coulomb_strength: 0.0,
+            boid_radius: 4.0,
+            boid_separation: 8.0,
+            boid_alignment: 1.0,
+            boid_cohesion: 0.5,
+            boid_max_speed: 8.0,
+            collision_radius: 0.0,
+            restitution: 0.8,
+            wall_restitution: 0.8,
+            ground_enabled: 0,
+            ground_height: -30.0,
+            ground_restitution: 0.3,
+            ground_friction: 0.2,
+            obstacle_count: 0,
+            obstacle_restitution: 0.5,
+            integrator: INTEGRATOR_EULER,
+            time: 0.0,
+            spring_stiffness: 0.0,
+            spring_iterations: 8,
+            buoyancy: 0.0,
+            ambient_temperature: 0.0,
+            strange_mode: STRANGE_NONE,
+            strange_follow: 10.0,
+            strange_scale: 1.0,
+            strange_speed: 1.0,
+            strange_coefficients: [0.0; 3],
+            coulomb_cutoff: 5.0,
+            particle_life_strength: 0.0,
+            particle_life_radius: 5.0,
+            gravity_mode: GRAVITY_UNIFORM,
+            central_gravity: 0.0,
+            gravity: [0.0; 3],
+            _padding20: 0,
+            gravity_center: [0.0; 3],
+            variation: 0.0,
+            flip_ratio: 0.95,
+            liquid_resolution: 32,
+            pressure_iterations: 40,
+            mpm_material: MATERIAL_SNOW,
+            hardening: 10.0,
+            friction_angle: 30f32.to_radians(),
+            mpm_resolution: 48,
+            contact_model: CONTACT_IMPULSE,
+            contact_friction: 0.6,
+            rolling_resistance: 0.1,
+            surface_mode: SURFACE_NONE,
+            surface_radius: 30.0,
+            surface_tube_radius: 10.0,
+            emission_rate: 0.0,
+            _padding22: 0,
+            _padding23: 0,
         }
     }
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
-pub struct Particle {
-    pub position: [f32; 3],
-    pub padding1: f32,
+layout::gpu_struct! {
+    pub struct Particle (version 4) {
+        pub position: [f32; 3] => "vec3<f32>",
+        pub species: u32 => "u32",
+
+        pub velocity: [f32; 3] => "vec3<f32>",
+        pub temperature: f32 => "f32",
 
-    pub velocity: [f32; 3],
-    pub padding2: f32,
+        pub color: [f32; 4] => "vec4<f32>",
 
-    pub color: [f32; 4],
+        pub initial_color: [f32; 4] => "vec4<f32>",
 
-    pub initial_color: [f32; 4],
+        /// Unit magnetic moment orientation
+        pub dipole: [f32; 3] => "vec3<f32>",
+        pub charge: f32 => "f32",
+
+        /// Inertia against forces, gravity accelerates every mass alike
+        pub mass: f32 => "f32",
+        /// Seconds since the particle was (re-)emitted
+        pub age: f32 => "f32",
+        /// Age the particle is re-emitted or dies at, 0 until one is rolled
+        /// and [`DEAD`] once it died
+        pub lifetime: f32 => "f32",
+        /// Two 16-bit rolls scaling the damping and gravity the particle
+        /// feels, see [`variation_scales`]
+        pub variation: u32 => "u32",
+    }
 }
 
 impl Particle {
-    fn new(position: Vec3, velocity: Vec3, initial_color: Vec4) -> Self {
+    fn new(position: Vec3, velocity: Vec3, initial_color: Vec4, species: u32) -> Self {
         Self {
             position: position.into(),
-            padding1: 0.0,
+            species,
             velocity: velocity.into(),
-            padding2: 0.0,
+            temperature: 0.0,
             color: initial_color.into(),
             initial_color: initial_color.into(),
+            dipole: [0.0, 1.0, 0.0],
+            charge: 0.0,
+            mass: 1.0,
+            age: 0.0,
+            lifetime: 0.0,
+            variation: 0,
         }
     }
+
+    /// Whether it died and waits on the emitter
+    pub fn is_dead(&self) -> bool {
+        self.lifetime < 0.0
+    }
 }
 
 // pub fn generate_initial_particles(count: u32, mode:) -> Vec<Particle> {
@@ -142,33 +1132,25 @@ impl Particle {
 
 //     particles
 // }
-pub fn generate_initial_particles(count: u32, mode: SphereGeneration) -> Vec<Particle> {
+pub fn generate_initial_particles(count: u32, generation: GenerationSettings) -> Vec<Particle> {
     let mut particles = Vec::with_capacity(count as usize);
-    let sphere_radius = 50.0; // Initial radius of the sphere
+    let sphere_radius = SPAWN_RADIUS; // Initial radius of the sphere
+    let species_count = generation.species_count.clamp(1, MAX_SPECIES);
 
-    match mode {
+    match generation.mode {
         SphereGeneration::Hollow => {
-            let golden_angle = std::f32::consts::PI * (3.0 - (5.0_f32).sqrt());
             for i in 0..count {
-                let y = 1.0 - (i as f32 / (count.max(1) - 1) as f32) * 2.0; // y goes from 1 to -1
-                let radius_at_y = (1.0 - y * y).sqrt(); // radius at y
-                let theta = golden_angle * i as f32; // golden angle increment
-
-                let x = theta.cos() * radius_at_y;
-                let z = theta.sin() * radius_at_y;
-
-                let pos = Vec3::new(x, y, z) * sphere_radius;
+                let pos = spawn_position(i, count, SPAWN_HOLLOW, 0);
                 let vel = Vec3::ZERO;
-                let norm_pos = (pos / sphere_radius + Vec3::ONE) * 0.5;
-                let initial_color = Vec4::new(norm_pos.x, norm_pos.y, norm_pos.z, 1.0);
+                let initial_color = spawn_color(pos);
 
-                particles.push(Particle::new(pos, vel, initial_color));
+                particles.push(Particle::new(pos, vel, initial_color, i % species_count));
             }
         }
         SphereGeneration::Filled => {
             // Use RNG for filled sphere
-            let mut rng = rand::rngs::SmallRng::seed_from_u64(69); // Use a fixed seed for reproducibility
-            for _ in 0..count {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 69); // The same particles for the same seed
+            for i in 0..count {
                 // Uniform distribution within a sphere volume
                 let r = sphere_radius * rng.random::<f32>().cbrt(); // Cube root for uniform volume
                 let theta = rng.random::<f32>() * 2.0 * std::f32::consts::PI;
@@ -183,10 +1165,176 @@ pub fn generate_initial_particles(count: u32, mode: SphereGeneration) -> Vec<Par
                 let norm_pos = (pos / sphere_radius + Vec3::ONE) * 0.5; // Color based on normalized position
                 let initial_color = Vec4::new(norm_pos.x, norm_pos.y, norm_pos.z, 1.0);
 
-                particles.push(Particle::new(pos, vel, initial_color));
+                particles.push(Particle::new(pos, vel, initial_color, i % species_count));
+            }
+        }
+        SphereGeneration::OrbitRing => {
+            // Slightly jittered so the ring spreads out instead of orbiting in lockstep
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 7);
+            for i in 0..count {
+                let angle = i as f32 / count as f32 * 2.0 * std::f32::consts::PI;
+                let radial = Vec3::new(angle.cos(), 0.0, angle.sin());
+                let tangent = Vec3::new(-angle.sin(), 0.0, angle.cos());
+                let r = generation.orbit_radius * (1.0 + (rng.random::<f32>() - 0.5) * 0.05);
+                let y = (rng.random::<f32>() - 0.5) * 0.5;
+
+                let pos = radial * r + Vec3::Y * y;
+                let vel = tangent * generation.orbit_speed;
+                let initial_color = spawn_color(pos);
+
+                particles.push(Particle::new(pos, vel, initial_color, i % species_count));
+            }
+        }
+        SphereGeneration::Galaxy => {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 42);
+            let radius = generation.orbit_radius;
+            let inner = radius * GALAXY_CORE;
+            for i in 0..count {
+                // Exponential surface density with a scale length of a quarter
+                // of the radius, cut off at the radius
+                let u = rng.random::<f32>() * (1.0 - (-4.0f32).exp());
+                let r = inner + (radius - inner) * -(1.0 - u).ln() / 4.0;
+                // Along one of two logarithmic spirals, scattered less near the
+                // core so it stays round
+                let arm = (i % 2) as f32 * std::f32::consts::PI;
+                let scatter =
+                    (rng.random::<f32>() - 0.5) * std::f32::consts::PI * (1.0 - r / radius);
+                let angle = arm + GALAXY_WINDING * (r / inner).ln() + scatter;
+                let radial = Vec3::new(angle.cos(), 0.0, angle.sin());
+                let tangent = Vec3::new(-angle.sin(), 0.0, angle.cos());
+                // Thicker towards the bulge
+                let y = (rng.random::<f32>() - 0.5) * 0.1 * radius * (1.0 - 0.5 * r / radius);
+
+                let pos = radial * r + Vec3::Y * y;
+                // Keplerian around a point mass, so the speed at the rim
+                // sets the mass
+                let vel = tangent * generation.orbit_speed * (radius / r).sqrt();
+                // Yellow-white bulge fading to a blue rim
+                let t = r / radius;
+                let initial_color = Vec4::new(1.0 - 0.6 * t, 0.9 - 0.4 * t, 0.7 + 0.3 * t, 1.0);
+
+                particles.push(Particle::new(pos, vel, initial_color, i % species_count));
+            }
+        }
+        SphereGeneration::ClothGrid => {
+            // Rows along Z, as wide as the spawn sphere and a little above it
+            let side = springs::cloth_side(count);
+            let spacing = 2.0 * sphere_radius / (side.max(2) - 1) as f32;
+            for i in 0..count {
+                let (column, row) = (i % side, i / side);
+                let pos = Vec3::new(
+                    column as f32 * spacing - sphere_radius,
+                    CLOTH_HEIGHT,
+                    row as f32 * spacing - sphere_radius,
+                );
+                let initial_color = spawn_color(pos);
+
+                particles.push(Particle::new(
+                    pos,
+                    Vec3::ZERO,
+                    initial_color,
+                    i % species_count,
+                ));
             }
         }
     }
 
+    let spheres = matches!(
+        generation.mode,
+        SphereGeneration::Hollow | SphereGeneration::Filled
+    );
+    if generation.orbit_insertion && generation.orbit_mass > 0.0 && spheres {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 5);
+        for particle in &mut particles {
+            let jitter = 1.0 + generation.orbit_jitter * (rng.random::<f32>() * 2.0 - 1.0);
+            let velocity =
+                circular_orbit_velocity(Vec3::from(particle.position), generation.orbit_mass);
+            particle.velocity = (velocity * jitter).into();
+        }
+    }
+
+    // Alternating charges and random dipole orientations, uniform on the unit sphere
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 420);
+    for (i, particle) in particles.iter_mut().enumerate() {
+        particle.charge = if i % 2 == 0 { 1.0 } else { -1.0 };
+
+        let z = rng.random::<f32>() * 2.0 - 1.0;
+        let theta = rng.random::<f32>() * 2.0 * std::f32::consts::PI;
+        let r = (1.0 - z * z).sqrt();
+        particle.dipole = [r * theta.cos(), r * theta.sin(), z];
+    }
+
+    let mass_ratio = generation.mass_ratio.max(1.0);
+    match generation.mass_distribution {
+        MassDistribution::Equal => {}
+        MassDistribution::Random => {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 1337);
+            for particle in &mut particles {
+                particle.mass = mass_ratio.powf(rng.random::<f32>());
+            }
+        }
+        MassDistribution::BySpecies => {
+            let steps = (species_count - 1).max(1) as f32;
+            for particle in &mut particles {
+                particle.mass = mass_ratio.powf(particle.species as f32 / steps);
+            }
+        }
+    }
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(generation.seed ^ 7);
+    for particle in &mut particles {
+        particle.variation = rng.random();
+    }
+
     particles
 }
+
+/// Factors a particle scales damping's loss and gravity by, from its
+/// [`Particle::variation`] rolls and up to `amount` either way
+// Keep in sync with `variation_scales` in the compute shader
+pub fn variation_scales(variation: u32, amount: f32) -> (f32, f32) {
+    let roll = |bits: u32| (bits & 0xffff) as f32 / 65535.0 * 2.0 - 1.0;
+    (
+        1.0 + amount * roll(variation),
+        1.0 + amount * roll(variation >> 16),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATIONS: [SphereGeneration; 5] = [
+        SphereGeneration::Hollow,
+        SphereGeneration::Filled,
+        SphereGeneration::OrbitRing,
+        SphereGeneration::ClothGrid,
+        SphereGeneration::Galaxy,
+    ];
+
+    #[test]
+    fn generates_empty_and_single_particle_scenes() {
+        for mode in GENERATIONS {
+            let generation = GenerationSettings {
+                mode,
+                ..Default::default()
+            };
+            assert!(generate_initial_particles(0, generation).is_empty());
+            let single = generate_initial_particles(1, generation);
+            assert_eq!(single.len(), 1, "{mode:?}");
+            assert!(Vec3::from(single[0].position).is_finite(), "{mode:?}");
+            assert!(Vec3::from(single[0].velocity).is_finite(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn spawns_in_empty_and_single_particle_scenes() {
+        for spawn_mode in [SPAWN_HOLLOW, SPAWN_FILLED] {
+            for (index, count) in [(0, 0), (0, 1)] {
+                let position = spawn_position(index, count, spawn_mode, 0);
+                assert!(position.is_finite(), "mode {spawn_mode}, {count} particles");
+                assert!(position.length() <= SPAWN_RADIUS * 1.0001);
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use super::background_grid::BackgroundGrid;
+use super::dispatch::{self, WORKGROUP_SIZE, dispatch_linear};
+use super::gpu_buffer::{GpuBuffer, TrackedBindGroup, storage_entry, uniform_entry};
+use super::{Particle, SimParams, layout};
+use crate::wgsl;
+
+/// Fewest and most cells along the longest side of the box the material
+/// point grid is made of
+pub const MIN_MPM_RESOLUTION: u32 = 8;
+pub const MAX_MPM_RESOLUTION: u32 = 128;
+
+// Keep in sync with mpm.wgsl
+pub const MATERIAL_SNOW: u32 = 0;
+pub const MATERIAL_SAND: u32 = 1;
+
+/// How the material points yield once deformed past their elastic range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum MpmMaterial {
+    /// Fixed corotated elasticity whose stretch and squash are clamped, the
+    /// lost volume hardening it as it packs
+    #[default]
+    Snow,
+    /// Drucker-Prager plasticity, holding together only under compression
+    /// and sliding once the shear exceeds its friction
+    Sand,
+}
+
+impl MpmMaterial {
+    pub const ALL: [MpmMaterial; 2] = [MpmMaterial::Snow, MpmMaterial::Sand];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MpmMaterial::Snow => "Snow",
+            MpmMaterial::Sand => "Sand",
+        }
+    }
+
+    /// Value of `SimParams::mpm_material`
+    pub fn mode(self) -> u32 {
+        match self {
+            MpmMaterial::Snow => MATERIAL_SNOW,
+            MpmMaterial::Sand => MATERIAL_SAND,
+        }
+    }
+}
+
+layout::gpu_struct! {
+    /// What the material point method keeps of every particle on top of its
+    /// [`Particle`]
+    pub struct MaterialPoint (version 1) {
+        /// Elastic part of the deformation gradient
+        pub deformation: [[f32; 4]; 3] => "mat3x3<f32>",
+        /// Velocity gradient around the particle the grid handed back (APIC)
+        pub affine: [[f32; 4]; 3] => "mat3x3<f32>",
+        /// Kirchhoff stress of the deformation times the particle's volume,
+        /// for the next transfer to the grid
+        pub stress: [[f32; 4]; 3] => "mat3x3<f32>",
+
+        /// Volume the plastic flow took away or added, snow hardens as it
+        /// packs below 1
+        pub plastic_volume: f32 => "f32",
+        pub _padding0: u32 => "u32",
+        pub _padding1: u32 => "u32",
+        pub _padding2: u32 => "u32",
+    }
+}
+
+impl MaterialPoint {
+    const IDENTITY: [[f32; 4]; 3] = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+    ];
+
+    /// Undeformed and unstressed
+    pub const REST: Self = Self {
+        deformation: Self::IDENTITY,
+        affine: [[0.0; 4]; 3],
+        stress: [[0.0; 4]; 3],
+        plastic_volume: 1.0,
+        _padding0: 0,
+        _padding1: 0,
+        _padding2: 0,
+    };
+}
+
+/// MLS-MPM snow and sand on a [`BackgroundGrid`] over the container box, the
+/// [`super::SimulationMethod::Mpm`] backend's part of every step.
+///
+/// The particles splat their momentum and stress onto the nodes with
+/// quadratic B-spline weights, the nodes turn it into velocities that walls
+/// can't push out of, and the particles take those back along with their
+/// gradient, which deforms them. The deformation beyond what the material
+/// holds elastically flows away plastically, and what's left is the stress
+/// of the next step. Integration moves the particles afterwards, forces
+/// included.
+pub struct MpmSolver {
+    grid_buffer: GpuBuffer<BackgroundGrid>,
+    points: GpuBuffer<MaterialPoint>,
+    /// Particles `points` currently hold the state of, zero once they have to
+    /// start over from rest
+    point_count: u32,
+    /// Momentum and mass of every node, as fixed point
+    accumulators: GpuBuffer<i32>,
+    /// Velocity of every node
+    velocities: GpuBuffer<[f32; 4]>,
+    particle_to_grid_pipeline: wgpu::ComputePipeline,
+    update_grid_pipeline: wgpu::ComputePipeline,
+    grid_to_particle_pipeline: wgpu::ComputePipeline,
+    bind_group: TrackedBindGroup,
+}
+
+impl MpmSolver {
+    pub fn new(
+        device: &wgpu::Device,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+    ) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+        let grid_buffer =
+            GpuBuffer::with_capacity(device, "MPM Grid Buffer", wgpu::BufferUsages::UNIFORM, 1);
+        let points = GpuBuffer::with_capacity(device, "MPM Material Points", storage, 1);
+        let accumulators = GpuBuffer::with_capacity(device, "MPM Accumulators", storage, 1);
+        let velocities = GpuBuffer::with_capacity(device, "MPM Node Velocities", storage, 1);
+
+        let source = wgsl::compose(&[
+            Particle::WGSL,
+            SimParams::WGSL,
+            BackgroundGrid::WGSL,
+            MaterialPoint::WGSL,
+            BackgroundGrid::HELPERS_WGSL,
+            dispatch::WGSL,
+            include_str!("../shaders/mpm.wgsl"),
+        ]);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("MPM Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        // Particles, params, grid, points, accumulators, velocities
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("MPM Bind Group Layout"),
+            entries: &[
+                storage_entry(0, false),
+                uniform_entry(1),
+                uniform_entry(2),
+                storage_entry(3, false),
+                storage_entry(4, false),
+                storage_entry(5, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("MPM Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let bind_group = TrackedBindGroup::new(
+            device,
+            "MPM Bind Group",
+            layout,
+            &[
+                particles,
+                sim_params,
+                &grid_buffer,
+                &points,
+                &accumulators,
+                &velocities,
+            ],
+        );
+
+        Self {
+            grid_buffer,
+            points,
+            point_count: 0,
+            accumulators,
+            velocities,
+            particle_to_grid_pipeline: pipeline("particle_to_grid"),
+            update_grid_pipeline: pipeline("update_grid"),
+            grid_to_particle_pipeline: pipeline("grid_to_particle"),
+            bind_group,
+        }
+    }
+
+    /// Puts every particle back at rest, for when they were replaced
+    pub fn reset_points(&mut self) {
+        self.point_count = 0;
+    }
+
+    /// Records the passes carrying the first `grid.particle_count` particles
+    /// through the grid. `particles` and `sim_params` are the buffers it was
+    /// created with.
+    pub fn record(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        particles: &GpuBuffer<Particle>,
+        sim_params: &GpuBuffer<SimParams>,
+        grid: BackgroundGrid,
+    ) {
+        if self.point_count != grid.particle_count {
+            let rest = vec![MaterialPoint::REST; grid.particle_count as usize];
+            self.points.write(device, queue, &rest);
+            self.point_count = grid.particle_count;
+        }
+        let node_count = grid.node_count as usize;
+        self.grid_buffer.write(device, queue, &[grid]);
+        self.accumulators.reserve(device, node_count * 4);
+        self.velocities.reserve(device, node_count);
+        encoder.clear_buffer(self.accumulators.buffer(), 0, None);
+
+        let bind_group = self.bind_group.get(
+            device,
+            &[
+                particles,
+                sim_params,
+                &self.grid_buffer,
+                &self.points,
+                &self.accumulators,
+                &self.velocities,
+            ],
+        );
+
+        let particle_groups = grid.particle_count.div_ceil(WORKGROUP_SIZE);
+        let node_groups = grid.node_count.div_ceil(WORKGROUP_SIZE);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("MPM Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        let mut run = |pipeline: &wgpu::ComputePipeline, workgroups: u32| {
+            pass.set_pipeline(pipeline);
+            dispatch_linear(&mut pass, workgroups);
+        };
+        run(&self.particle_to_grid_pipeline, particle_groups);
+        run(&self.update_grid_pipeline, node_groups);
+        run(&self.grid_to_particle_pipeline, particle_groups);
+    }
+}
@@ -0,0 +1,104 @@
+use glam::{IVec3, Vec3, Vec3Swizzles, Vec4};
+
+/// The noise helpers, for shaders using them
+pub const WGSL: &str = concat!(include_str!("../shaders/noise.wgsl"), "\n");
+
+/// Brings [`simplex_noise`] to roughly [-1, 1]
+// Keep in sync with `SIMPLEX_SCALE` in noise.wgsl
+const SIMPLEX_SCALE: f32 = 70.0;
+
+/// Offsets of the second and third noise in [`curl_noise`], far enough
+/// apart that they look unrelated
+const CURL_OFFSETS: [Vec3; 2] = [Vec3::new(31.4, -17.2, 5.9), Vec3::new(-8.3, 23.7, -41.1)];
+
+// Keep in sync with `noise_lattice_hash` in noise.wgsl
+fn lattice_hash(cell: IVec3) -> u32 {
+    let mut h = (cell.x as u32)
+        .wrapping_mul(0x9E37_79B9)
+        .wrapping_add((cell.y as u32).wrapping_mul(0x85EB_CA6B))
+        .wrapping_add((cell.z as u32).wrapping_mul(0xC2B2_AE35));
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    h
+}
+
+/// One of the 12 edge midpoints of a cube
+// Keep in sync with `noise_lattice_gradient` in noise.wgsl
+fn lattice_gradient(cell: IVec3) -> Vec3 {
+    const GRADIENTS: [Vec3; 12] = [
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(-1.0, 1.0, 0.0),
+        Vec3::new(1.0, -1.0, 0.0),
+        Vec3::new(-1.0, -1.0, 0.0),
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(-1.0, 0.0, 1.0),
+        Vec3::new(1.0, 0.0, -1.0),
+        Vec3::new(-1.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 1.0),
+        Vec3::new(0.0, -1.0, 1.0),
+        Vec3::new(0.0, 1.0, -1.0),
+        Vec3::new(0.0, -1.0, -1.0),
+    ];
+    GRADIENTS[(lattice_hash(cell) % 12) as usize]
+}
+
+/// 3D simplex noise at `position`, xyz = its gradient and w = its value
+// Keep in sync with `simplex_noise` in noise.wgsl
+pub fn simplex_noise(position: Vec3) -> Vec4 {
+    let skew = position.element_sum() / 3.0;
+    let cell = (position + skew).floor();
+    let unskew = cell.element_sum() / 6.0;
+    let x0 = position - cell + unskew;
+
+    // The simplex is picked by the order of the offset's components
+    let g = Vec3::select(x0.cmpge(x0.yzx()), Vec3::ONE, Vec3::ZERO);
+    let l = Vec3::ONE - g;
+    let i1 = g.min(l.zxy());
+    let i2 = g.max(l.zxy());
+
+    let mut result = Vec4::ZERO;
+    for (corner, step) in [Vec3::ZERO, i1, i2, Vec3::ONE].into_iter().enumerate() {
+        let offset = x0 - step + corner as f32 / 6.0;
+        let falloff = 0.5 - offset.length_squared();
+        if falloff <= 0.0 {
+            continue;
+        }
+        let gradient = lattice_gradient((cell + step).as_ivec3());
+        let ramp = gradient.dot(offset);
+        let f2 = falloff * falloff;
+        let f4 = f2 * f2;
+        result += (f4 * gradient - 8.0 * f2 * falloff * ramp * offset).extend(f4 * ramp);
+    }
+    result * SIMPLEX_SCALE
+}
+
+/// Curl of three decorrelated noises, a divergence-free field that stirs
+/// particles around without bunching them up
+// Keep in sync with `curl_noise` in noise.wgsl
+pub fn curl_noise(position: Vec3) -> Vec3 {
+    let x = simplex_noise(position).truncate();
+    let y = simplex_noise(position + CURL_OFFSETS[0]).truncate();
+    let z = simplex_noise(position + CURL_OFFSETS[1]).truncate();
+    Vec3::new(z.y - y.z, x.z - z.x, y.x - x.y)
+}
+
+/// Noise features per unit of distance in wind gusts
+const GUST_FREQUENCY: f32 = 0.02;
+
+/// How strong the wind is at `position` after `time` seconds, 1 on average.
+/// Gusts drift downwind at the wind's magnitude in units per second.
+// Keep in sync with `gust_factor` in the compute shader
+pub fn gust_factor(position: Vec3, wind: Vec3, gustiness: f32, time: f32) -> f32 {
+    let noise = simplex_noise((position - wind * time) * GUST_FREQUENCY).w;
+    (1.0 + gustiness * noise).max(0.0)
+}
+
+/// Curl noise at `position` for noise `frequency` times as fine as world
+/// space, the pattern moved up by `scroll`
+// Keep in sync with `turbulence_acceleration` in the compute shader
+pub fn turbulence(position: Vec3, frequency: f32, scroll: f32) -> Vec3 {
+    curl_noise((position - Vec3::Y * scroll) * frequency)
+}
@@ -0,0 +1,150 @@
+use super::Particle;
+use super::layout;
+use super::mesh_sdf::MeshSdf;
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use rayon::prelude::*;
+
+/// Obstacles uploaded to the GPU at most, the rest are ignored
+pub const MAX_OBSTACLES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ObstacleShape {
+    Sphere,
+    Box,
+    /// Ring around the y axis
+    Torus,
+    /// Segment along the y axis with rounded ends
+    Capsule,
+    /// The loaded [`MeshSdf`]
+    Mesh,
+}
+
+impl ObstacleShape {
+    pub const ALL: [ObstacleShape; 5] = [
+        ObstacleShape::Sphere,
+        ObstacleShape::Box,
+        ObstacleShape::Torus,
+        ObstacleShape::Capsule,
+        ObstacleShape::Mesh,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ObstacleShape::Sphere => "Sphere",
+            ObstacleShape::Box => "Box",
+            ObstacleShape::Torus => "Torus",
+            ObstacleShape::Capsule => "Capsule",
+            ObstacleShape::Mesh => "Mesh",
+        }
+    }
+
+    /// What the components of [`Obstacle::size`] mean, unused ones are `None`
+    pub fn size_labels(self) -> [Option<&'static str>; 3] {
+        match self {
+            ObstacleShape::Sphere => [Some("Radius"), None, None],
+            ObstacleShape::Box => [Some("Half X"), Some("Half Y"), Some("Half Z")],
+            ObstacleShape::Torus => [Some("Ring Radius"), Some("Tube Radius"), None],
+            ObstacleShape::Capsule => [Some("Radius"), Some("Half Length"), None],
+            ObstacleShape::Mesh => [Some("Half Size"), None, None],
+        }
+    }
+}
+
+/// Solid shape the particles bounce off, described by its signed distance
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Obstacle {
+    pub shape: ObstacleShape,
+    pub center: Vec3,
+    /// Dimensions, see [`ObstacleShape::size_labels`]
+    pub size: Vec3,
+}
+
+impl Default for Obstacle {
+    fn default() -> Self {
+        Self {
+            shape: ObstacleShape::Sphere,
+            center: Vec3::ZERO,
+            size: Vec3::new(10.0, 2.0, 10.0),
+        }
+    }
+}
+
+layout::gpu_struct! {
+    pub struct GpuObstacle (version 1) {
+        pub center: [f32; 3] => "vec3<f32>",
+        /// 0 = sphere, 1 = box, 2 = torus, 3 = capsule, 4 = mesh
+        pub shape: u32 => "u32",
+        pub size: [f32; 3] => "vec3<f32>",
+        pub _padding: u32 => "u32",
+    }
+}
+
+impl Obstacle {
+    pub fn to_gpu(self) -> GpuObstacle {
+        GpuObstacle {
+            center: self.center.into(),
+            shape: self.shape as u32,
+            size: self.size.into(),
+            _padding: 0,
+        }
+    }
+
+    /// Distance from `position` to the surface, negative inside
+    // Keep in sync with `obstacle_distance` in obstacles.wgsl
+    pub fn distance(&self, position: Vec3, mesh: &MeshSdf) -> f32 {
+        let p = position - self.center;
+        let size = self.size;
+        match self.shape {
+            ObstacleShape::Sphere => p.length() - size.x,
+            ObstacleShape::Box => {
+                let q = p.abs() - size;
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+            }
+            ObstacleShape::Torus => Vec2::new(p.xz().length() - size.x, p.y).length() - size.y,
+            ObstacleShape::Capsule => (p - Vec3::Y * p.y.clamp(-size.y, size.y)).length() - size.x,
+            ObstacleShape::Mesh => mesh.sample(p / size.x) * size.x,
+        }
+    }
+
+    /// Outward surface normal near `position`, from the distance gradient
+    pub fn normal(&self, position: Vec3, mesh: &MeshSdf) -> Vec3 {
+        const EPSILON: f32 = 1e-3;
+        let distance = |offset: Vec3| self.distance(position + offset, mesh);
+        let gradient = Vec3::new(
+            distance(Vec3::X * EPSILON) - distance(-Vec3::X * EPSILON),
+            distance(Vec3::Y * EPSILON) - distance(-Vec3::Y * EPSILON),
+            distance(Vec3::Z * EPSILON) - distance(-Vec3::Z * EPSILON),
+        );
+        gradient.normalize_or(Vec3::Y)
+    }
+}
+
+/// Pushes particles that went inside an obstacle back onto its surface and
+/// reflects their velocity into it, keeping `restitution` of it
+// Keep in sync with obstacles.wgsl
+pub fn resolve_obstacles(
+    particles: &mut [Particle],
+    obstacles: &[Obstacle],
+    mesh: &MeshSdf,
+    restitution: f32,
+) {
+    particles.par_iter_mut().for_each(|particle| {
+        let mut position = Vec3::from(particle.position);
+        let mut velocity = Vec3::from(particle.velocity);
+        for obstacle in obstacles {
+            let distance = obstacle.distance(position, mesh);
+            if distance >= 0.0 {
+                continue;
+            }
+            let normal = obstacle.normal(position, mesh);
+            position -= normal * distance;
+            let into = velocity.dot(normal);
+            if into < 0.0 {
+                velocity -= normal * (into * (1.0 + restitution));
+            }
+        }
+        particle.position = position.into();
+        particle.velocity = velocity.into();
+    });
+}
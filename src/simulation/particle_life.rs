@@ -0,0 +1,88 @@
+use super::grid::SpatialGrid;
+use super::{MAX_SPECIES, Particle};
+use glam::Vec3;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+/// Fraction of the interaction radius within which every pair pushes apart,
+/// whatever the matrix says, so organisms don't collapse into a point
+pub const PARTICLE_LIFE_REPULSION: f32 = 0.3;
+
+/// How strongly each species (row) is pulled towards each other one
+/// (column), negative pushing away. Uploaded as is, rows one after another.
+pub type InteractionMatrix = [[f32; MAX_SPECIES as usize]; MAX_SPECIES as usize];
+
+/// Every entry uniform in -1..1, the same for the same `seed`
+pub fn random_matrix(seed: u64) -> InteractionMatrix {
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+    let mut matrix = InteractionMatrix::default();
+    for row in &mut matrix {
+        for attraction in row {
+            *attraction = rng.random::<f32>() * 2.0 - 1.0;
+        }
+    }
+    matrix
+}
+
+/// Force at `ratio` of the interaction radius: a linear push apart up close,
+/// then a tent of height `attraction` peaking halfway through the rest.
+// Keep in sync with particle_life.wgsl
+pub fn life_force(ratio: f32, attraction: f32) -> f32 {
+    if ratio < PARTICLE_LIFE_REPULSION {
+        ratio / PARTICLE_LIFE_REPULSION - 1.0
+    } else if ratio < 1.0 {
+        let tent =
+            (2.0 * ratio - 1.0 - PARTICLE_LIFE_REPULSION).abs() / (1.0 - PARTICLE_LIFE_REPULSION);
+        attraction * (1.0 - tent)
+    } else {
+        0.0
+    }
+}
+
+/// Pulls and pushes between the species within `radius` by the matrix, added
+/// to the velocity. Every particle reads the others from before the pass.
+// Keep in sync with particle_life.wgsl
+pub fn apply_particle_life(
+    particles: &mut [Particle],
+    grid: &SpatialGrid,
+    matrix: &InteractionMatrix,
+    strength: f32,
+    radius: f32,
+    delta_time: f32,
+) {
+    let radius_sq = radius * radius;
+
+    let impulses: Vec<Vec3> = particles
+        .par_iter()
+        .enumerate()
+        .map(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let row = &matrix[(particle.species % MAX_SPECIES) as usize];
+            let mut acceleration = Vec3::ZERO;
+
+            grid.for_each_neighbor(position, radius, |j| {
+                if j == i {
+                    return;
+                }
+                let other = &particles[j];
+                let offset = grid.separation(Vec3::from(other.position), position);
+                let dist_sq = offset.length_squared();
+                if dist_sq >= radius_sq || dist_sq == 0.0 {
+                    return;
+                }
+                let distance = dist_sq.sqrt();
+                let attraction = row[(other.species % MAX_SPECIES) as usize];
+                acceleration += offset / distance * life_force(distance / radius, attraction);
+            });
+
+            acceleration * (strength * radius * delta_time)
+        })
+        .collect();
+
+    particles
+        .par_iter_mut()
+        .zip(impulses)
+        .for_each(|(particle, impulse)| {
+            particle.velocity = (Vec3::from(particle.velocity) + impulse).into();
+        });
+}
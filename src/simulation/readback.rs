@@ -0,0 +1,282 @@
+use super::Particle;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+const PARTICLE_SIZE: wgpu::BufferAddress = std::mem::size_of::<Particle>() as wgpu::BufferAddress;
+
+/// Where a [`ReadMapping`] stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapState {
+    Pending,
+    Done,
+    /// The buffer couldn't be mapped, as when the device was lost
+    Failed,
+}
+
+/// Progress of a staging buffer's `map_async` for reading, checked from
+/// frame to frame since nothing can block on the web. The device still has
+/// to be polled for the mapping to finish.
+pub struct ReadMapping(Arc<AtomicU8>);
+
+impl ReadMapping {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU8::new(MAP_PENDING)))
+    }
+
+    /// Starts mapping `slice`, forgetting how the previous mapping went
+    pub fn start(&self, slice: wgpu::BufferSlice<'_>) {
+        self.0.store(MAP_PENDING, Ordering::Release);
+        let state = self.0.clone();
+        slice.map_async(wgpu::MapMode::Read, move |status| {
+            let done = if status.is_ok() { MAP_DONE } else { MAP_FAILED };
+            state.store(done, Ordering::Release);
+        });
+    }
+
+    pub fn state(&self) -> MapState {
+        match self.0.load(Ordering::Acquire) {
+            MAP_PENDING => MapState::Pending,
+            MAP_DONE => MapState::Done,
+            _ => MapState::Failed,
+        }
+    }
+}
+
+impl Default for ReadMapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Non-blocking copies of a few particles back from a GPU buffer. Each call
+/// to [`ParticleReadback::sample`] hands out the last finished copy and
+/// queues the next one, so results lag a frame or so behind.
+pub struct ParticleReadback {
+    staging: Option<wgpu::Buffer>,
+    indices: Vec<u32>,
+    mapping: ReadMapping,
+    in_flight: bool,
+}
+
+impl ParticleReadback {
+    pub fn new() -> Self {
+        Self {
+            staging: None,
+            indices: Vec::new(),
+            mapping: ReadMapping::new(),
+            in_flight: false,
+        }
+    }
+
+    pub fn sample(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::Buffer,
+        particle_count: u32,
+        indices: &[u32],
+    ) -> Option<Vec<Particle>> {
+        let _ = device.poll(wgpu::PollType::Poll);
+
+        let mut result = None;
+        if self.in_flight {
+            let staging = self.staging.as_ref()?;
+            match self.mapping.state() {
+                MapState::Pending => return None,
+                MapState::Done => {
+                    let size = self.indices.len() as wgpu::BufferAddress * PARTICLE_SIZE;
+                    {
+                        let view = staging.slice(..size).get_mapped_range();
+                        if self.indices == indices {
+                            result = Some(bytemuck::cast_slice(&view).to_vec());
+                        }
+                    }
+                    staging.unmap();
+                }
+                MapState::Failed => {}
+            }
+            self.in_flight = false;
+        }
+
+        let indices: Vec<u32> = indices
+            .iter()
+            .copied()
+            .filter(|&index| index < particle_count)
+            .collect();
+        if indices.is_empty() {
+            return result;
+        }
+
+        let size = indices.len() as wgpu::BufferAddress * PARTICLE_SIZE;
+        if self.staging.as_ref().is_none_or(|s| s.size() < size) {
+            self.staging = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Particle Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }));
+        }
+        let staging = self.staging.as_ref()?;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Particle Readback Encoder"),
+        });
+        for (slot, &index) in indices.iter().enumerate() {
+            encoder.copy_buffer_to_buffer(
+                source,
+                index as wgpu::BufferAddress * PARTICLE_SIZE,
+                staging,
+                slot as wgpu::BufferAddress * PARTICLE_SIZE,
+                PARTICLE_SIZE,
+            );
+        }
+        queue.submit(Some(encoder.finish()));
+
+        self.mapping.start(staging.slice(..size));
+        self.indices = indices;
+        self.in_flight = true;
+
+        result
+    }
+}
+
+impl Default for ParticleReadback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Non-blocking reads of one count from a GPU buffer, lagging a frame or so
+/// behind like [`ParticleReadback`]
+pub struct CountReadback {
+    staging: wgpu::Buffer,
+    mapping: ReadMapping,
+    in_flight: bool,
+}
+
+impl CountReadback {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            staging: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Count Readback Buffer"),
+                size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            mapping: ReadMapping::new(),
+            in_flight: false,
+        }
+    }
+
+    /// The last finished read, queueing one of the `u32` at `offset` in
+    /// `source`, which needs `COPY_SRC`
+    pub fn read(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::Buffer,
+        offset: wgpu::BufferAddress,
+    ) -> Option<u32> {
+        let _ = device.poll(wgpu::PollType::Poll);
+
+        let mut result = None;
+        if self.in_flight {
+            match self.mapping.state() {
+                MapState::Pending => return None,
+                MapState::Done => {
+                    result = Some(bytemuck::pod_read_unaligned(
+                        &self.staging.slice(..).get_mapped_range(),
+                    ));
+                    self.staging.unmap();
+                }
+                MapState::Failed => {}
+            }
+            self.in_flight = false;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Count Readback Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(source, offset, &self.staging, 0, self.staging.size());
+        queue.submit(Some(encoder.finish()));
+
+        self.mapping.start(self.staging.slice(..));
+        self.in_flight = true;
+
+        result
+    }
+}
+
+/// Where a [`ParticleCopy`] stands
+pub enum CopyState {
+    Pending,
+    Done(Vec<Particle>),
+    /// The copy couldn't be mapped, as when the device was lost
+    Failed,
+}
+
+/// One non-blocking copy of every particle in a GPU buffer, for carrying
+/// them over to another backend
+pub struct ParticleCopy {
+    staging: wgpu::Buffer,
+    particle_count: u32,
+    mapping: ReadMapping,
+}
+
+impl ParticleCopy {
+    /// Queues the copy of the first `particle_count` particles of `source`,
+    /// which needs `COPY_SRC`
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::Buffer,
+        particle_count: u32,
+    ) -> Self {
+        let size = particle_count as wgpu::BufferAddress * PARTICLE_SIZE;
+        // Empty buffers can't be mapped, keep room for one particle
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Copy Buffer"),
+            size: size.max(PARTICLE_SIZE),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Particle Copy Encoder"),
+        });
+        if size > 0 {
+            encoder.copy_buffer_to_buffer(source, 0, &staging, 0, size);
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let mapping = ReadMapping::new();
+        mapping.start(staging.slice(..));
+        Self {
+            staging,
+            particle_count,
+            mapping,
+        }
+    }
+
+    pub fn poll(&self, device: &wgpu::Device) -> CopyState {
+        let _ = device.poll(wgpu::PollType::Poll);
+        match self.mapping.state() {
+            MapState::Pending => CopyState::Pending,
+            MapState::Done => {
+                let size = self.particle_count as wgpu::BufferAddress * PARTICLE_SIZE;
+                let particles = if size == 0 {
+                    Vec::new()
+                } else {
+                    let view = self.staging.slice(..size).get_mapped_range();
+                    bytemuck::cast_slice(&view).to_vec()
+                };
+                self.staging.unmap();
+                CopyState::Done(particles)
+            }
+            MapState::Failed => CopyState::Failed,
+        }
+    }
+}
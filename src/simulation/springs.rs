@@ -0,0 +1,197 @@
+use super::grid::SpatialGrid;
+use super::{GenerationSettings, Particle, SphereGeneration, layout, nearest_image};
+use glam::Vec3;
+use rayon::prelude::*;
+
+/// Most springs a particle ties to its nearest neighbors with, on top of the
+/// ones its neighbors tie to it
+pub const MAX_NEIGHBOR_SPRINGS: usize = 12;
+/// Most relaxation passes over the springs per step
+pub const MAX_SPRING_ITERATIONS: u32 = 32;
+
+layout::gpu_struct! {
+    /// One spring as seen from the particle at the other end
+    pub struct SpringEnd (version 1) {
+        pub other: u32 => "u32",
+        pub rest_length: f32 => "f32",
+    }
+}
+
+/// Springs between particles, listed per particle so every particle can
+/// gather its own correction without writing to the others
+#[derive(Debug, Clone, Default)]
+pub struct SpringNetwork {
+    /// Where the springs of each particle start in `ends`, with the end of
+    /// the last particle's at the back
+    pub offsets: Vec<u32>,
+    pub ends: Vec<SpringEnd>,
+}
+
+impl SpringNetwork {
+    /// The springs of freshly generated `particles`: the grid of a cloth, or
+    /// ties to the neighbors within the generation's spring radius
+    pub fn for_generation(particles: &[Particle], generation: &GenerationSettings) -> Self {
+        let pairs = if generation.mode == SphereGeneration::ClothGrid {
+            cloth_pairs(particles.len() as u32)
+        } else if generation.spring_radius > 0.0 {
+            neighbor_pairs(particles, generation.spring_radius)
+        } else {
+            Vec::new()
+        };
+        Self::from_pairs(particles, &pairs)
+    }
+
+    /// Connects every pair both ways, at the distance they start at
+    fn from_pairs(particles: &[Particle], pairs: &[(u32, u32)]) -> Self {
+        if pairs.is_empty() {
+            return Self::default();
+        }
+        let mut counts = vec![0u32; particles.len() + 1];
+        for &(a, b) in pairs {
+            counts[a as usize] += 1;
+            counts[b as usize] += 1;
+        }
+        let mut offsets = Vec::with_capacity(counts.len());
+        let mut total = 0;
+        for count in counts {
+            offsets.push(total);
+            total += count;
+        }
+
+        let mut cursor = offsets.clone();
+        let mut ends = vec![
+            SpringEnd {
+                other: 0,
+                rest_length: 0.0,
+            };
+            total as usize
+        ];
+        for &(a, b) in pairs {
+            let rest_length = Vec3::from(particles[a as usize].position)
+                .distance(Vec3::from(particles[b as usize].position));
+            for (from, to) in [(a, b), (b, a)] {
+                ends[cursor[from as usize] as usize] = SpringEnd {
+                    other: to,
+                    rest_length,
+                };
+                cursor[from as usize] += 1;
+            }
+        }
+        Self { offsets, ends }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+}
+
+/// Structural springs to the right and below, and shear springs along both
+/// diagonals, of particles laid out row by row in a square grid
+fn cloth_pairs(count: u32) -> Vec<(u32, u32)> {
+    let side = cloth_side(count);
+    let mut pairs = Vec::with_capacity(count as usize * 4);
+    for i in 0..count {
+        let column = i % side;
+        let mut tie = |j: u32| {
+            if j < count {
+                pairs.push((i, j));
+            }
+        };
+        if column + 1 < side {
+            tie(i + 1);
+            tie(i + side + 1);
+        }
+        if column > 0 {
+            tie(i + side - 1);
+        }
+        tie(i + side);
+    }
+    pairs
+}
+
+/// Particles per row of a cloth of `count`, as square as it gets
+pub fn cloth_side(count: u32) -> u32 {
+    (count as f32).sqrt().ceil().max(1.0) as u32
+}
+
+/// Every particle tied to its nearest neighbors within `radius`, each pair
+/// once
+fn neighbor_pairs(particles: &[Particle], radius: f32) -> Vec<(u32, u32)> {
+    let mut grid = SpatialGrid::new();
+    grid.build(particles, radius, None);
+    let radius_sq = radius * radius;
+    particles
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, particle)| {
+            let position = Vec3::from(particle.position);
+            let mut neighbors = Vec::new();
+            grid.for_each_neighbor(position, radius, |j| {
+                let dist_sq = position.distance_squared(Vec3::from(particles[j].position));
+                if j > i && dist_sq < radius_sq {
+                    neighbors.push((dist_sq, j));
+                }
+            });
+            neighbors.sort_by(|a, b| a.0.total_cmp(&b.0));
+            neighbors.truncate(MAX_NEIGHBOR_SPRINGS);
+            neighbors
+                .into_iter()
+                .map(move |(_, j)| (i as u32, j as u32))
+        })
+        .collect()
+}
+
+/// Pulls the springs towards their rest lengths with `iterations` Jacobi
+/// passes. Every particle moves by `stiffness` of the average error of its
+/// springs, the lighter end of each taking the larger share, against the
+/// positions from before the pass. The velocity picks up the move so the
+/// next step doesn't undo it. In a `periodic_box` springs reach across its
+/// walls to the nearest image of the other end.
+// Keep in sync with springs.wgsl
+pub fn relax(
+    particles: &mut [Particle],
+    network: &SpringNetwork,
+    stiffness: f32,
+    iterations: u32,
+    delta_time: f32,
+    periodic_box: Option<Vec3>,
+) {
+    for _ in 0..iterations {
+        let snapshot: Vec<(Vec3, f32)> = particles
+            .par_iter()
+            .map(|particle| (Vec3::from(particle.position), particle.mass))
+            .collect();
+        particles
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, particle)| {
+                let (Some(&start), Some(&end)) =
+                    (network.offsets.get(i), network.offsets.get(i + 1))
+                else {
+                    return;
+                };
+                if start == end {
+                    return;
+                }
+                let (position, mass) = snapshot[i];
+                let mut correction = Vec3::ZERO;
+                for spring in &network.ends[start as usize..end as usize] {
+                    let (other, other_mass) = snapshot[spring.other as usize];
+                    let mut offset = other - position;
+                    if let Some(half_extents) = periodic_box {
+                        offset = nearest_image(offset, half_extents);
+                    }
+                    let dist = offset.length();
+                    if dist <= 0.0 {
+                        continue;
+                    }
+                    let share = other_mass / (mass + other_mass);
+                    correction += offset / dist * ((dist - spring.rest_length) * share);
+                }
+                correction *= stiffness / (end - start) as f32;
+                particle.position = (position + correction).into();
+                particle.velocity =
+                    (Vec3::from(particle.velocity) + correction / delta_time).into();
+            });
+    }
+}
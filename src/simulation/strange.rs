@@ -0,0 +1,107 @@
+use super::SimParams;
+use glam::Vec3;
+use std::ops::RangeInclusive;
+
+/// `SimParams::strange_mode` leaving the particles alone
+pub const STRANGE_NONE: u32 = 0;
+pub const STRANGE_LORENZ: u32 = 1;
+pub const STRANGE_AIZAWA: u32 = 2;
+pub const STRANGE_THOMAS: u32 = 3;
+
+/// One of the chaotic flows particles can be carried along
+pub struct StrangeAttractor {
+    pub mode: u32,
+    pub name: &'static str,
+    /// Name and sensible range of each of its coefficients, `None` for the
+    /// ones it doesn't use
+    pub coefficients: [Option<(&'static str, RangeInclusive<f32>)>; 3],
+    /// Coefficients of its classic shape
+    pub defaults: [f32; 3],
+    /// World units per attractor unit that fill the spawn sphere
+    pub scale: f32,
+    /// Time multiplier it looks good moving at
+    pub speed: f32,
+}
+
+pub const STRANGE_ATTRACTORS: [StrangeAttractor; 3] = [
+    StrangeAttractor {
+        mode: STRANGE_LORENZ,
+        name: "Lorenz",
+        coefficients: [
+            Some(("σ", 1.0..=30.0)),
+            Some(("ρ", 1.0..=60.0)),
+            Some(("β", 0.1..=8.0)),
+        ],
+        defaults: [10.0, 28.0, 8.0 / 3.0],
+        scale: 1.0,
+        speed: 0.5,
+    },
+    StrangeAttractor {
+        mode: STRANGE_AIZAWA,
+        name: "Aizawa",
+        coefficients: [
+            Some(("a", 0.0..=1.5)),
+            Some(("b", 0.0..=1.5)),
+            Some(("c", 0.0..=1.5)),
+        ],
+        defaults: [0.95, 0.7, 0.6],
+        scale: 20.0,
+        speed: 1.0,
+    },
+    StrangeAttractor {
+        mode: STRANGE_THOMAS,
+        name: "Thomas",
+        coefficients: [Some(("b", 0.05..=0.35)), None, None],
+        defaults: [0.208_186, 0.0, 0.0],
+        scale: 8.0,
+        speed: 4.0,
+    },
+];
+
+/// The attractor with `mode`, `None` for [`STRANGE_NONE`]
+pub fn strange_attractor(mode: u32) -> Option<&'static StrangeAttractor> {
+    STRANGE_ATTRACTORS
+        .iter()
+        .find(|attractor| attractor.mode == mode)
+}
+
+/// Velocity the chosen flow carries a particle at `position` with. The
+/// attractor's own z axis points up the world's y, and its middle sits at
+/// the origin.
+// Keep in sync with `strange_velocity` in the compute shader
+pub fn strange_velocity(position: Vec3, params: &SimParams) -> Vec3 {
+    let [a, b, c] = params.strange_coefficients;
+    let p = Vec3::new(position.x, position.z, position.y) / params.strange_scale;
+    let d = match params.strange_mode {
+        STRANGE_LORENZ => {
+            // Its wings circle z = ρ - 1
+            let p = p + Vec3::new(0.0, 0.0, b - 1.0);
+            Vec3::new(a * (p.y - p.x), p.x * (b - p.z) - p.y, p.x * p.y - c * p.z)
+        }
+        STRANGE_AIZAWA => {
+            let p = p + Vec3::new(0.0, 0.0, 0.7);
+            let spin = p.z - b;
+            Vec3::new(
+                spin * p.x - 3.5 * p.y,
+                3.5 * p.x + spin * p.y,
+                c + a * p.z - p.z * p.z * p.z / 3.0 - (p.x * p.x + p.y * p.y) * (1.0 + 0.25 * p.z)
+                    + 0.1 * p.z * p.x * p.x * p.x,
+            )
+        }
+        STRANGE_THOMAS => Vec3::new(
+            p.y.sin() - a * p.x,
+            p.z.sin() - a * p.y,
+            p.x.sin() - a * p.z,
+        ),
+        _ => return Vec3::ZERO,
+    };
+    Vec3::new(d.x, d.z, d.y) * params.strange_scale * params.strange_speed
+}
+
+/// Pulls `velocity` towards the flow's at `position`, `follow` per second, so
+/// a fast follow traces the attractor and a slow one lets other forces in
+// Keep in sync with the strange attractor step in the compute shader
+pub fn follow_strange_flow(velocity: Vec3, position: Vec3, params: &SimParams) -> Vec3 {
+    let blend = 1.0 - (-params.strange_follow * params.delta_time).exp();
+    velocity.lerp(strange_velocity(position, params), blend)
+}
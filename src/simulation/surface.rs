@@ -0,0 +1,90 @@
+use super::Particle;
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use rayon::prelude::*;
+
+// Keep in sync with surface.wgsl
+pub const SURFACE_NONE: u32 = 0;
+pub const SURFACE_SPHERE: u32 = 1;
+pub const SURFACE_PLANE: u32 = 2;
+pub const SURFACE_TORUS: u32 = 3;
+
+/// Shape the particles are held on, all centered on the box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum Surface {
+    /// The particles move freely
+    #[default]
+    None,
+    /// Shell of `surface_radius`
+    Sphere,
+    /// The horizontal plane through the center
+    Plane,
+    /// Ring of `surface_radius` around the y axis, its tube
+    /// `surface_tube_radius` thick
+    Torus,
+}
+
+impl Surface {
+    pub const ALL: [Surface; 4] = [
+        Surface::None,
+        Surface::Sphere,
+        Surface::Plane,
+        Surface::Torus,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Surface::None => "None",
+            Surface::Sphere => "Sphere Shell",
+            Surface::Plane => "Plane",
+            Surface::Torus => "Torus",
+        }
+    }
+
+    /// Value of `SimParams::surface_mode`
+    pub fn mode(self) -> u32 {
+        match self {
+            Surface::None => SURFACE_NONE,
+            Surface::Sphere => SURFACE_SPHERE,
+            Surface::Plane => SURFACE_PLANE,
+            Surface::Torus => SURFACE_TORUS,
+        }
+    }
+}
+
+/// The nearest point of the surface of `mode` to `position` and the
+/// surface's normal there
+// Keep in sync with `closest_on_surface` in surface.wgsl
+pub fn closest_on_surface(
+    position: Vec3,
+    mode: u32,
+    radius: f32,
+    tube_radius: f32,
+) -> (Vec3, Vec3) {
+    match mode {
+        SURFACE_SPHERE => {
+            let normal = position.try_normalize().unwrap_or(Vec3::Y);
+            (normal * radius, normal)
+        }
+        SURFACE_PLANE => (Vec3::new(position.x, 0.0, position.z), Vec3::Y),
+        SURFACE_TORUS => {
+            let around = position.xz().try_normalize().unwrap_or(Vec2::X) * radius;
+            let ring = Vec3::new(around.x, 0.0, around.y);
+            let normal = (position - ring).try_normalize().unwrap_or(Vec3::Y);
+            (ring + normal * tube_radius, normal)
+        }
+        _ => (position, Vec3::ZERO),
+    }
+}
+
+/// Puts every particle back on the surface of `mode` and takes the part of
+/// its velocity off it away, so it slides along
+// Keep in sync with surface.wgsl
+pub fn constrain_to_surface(particles: &mut [Particle], mode: u32, radius: f32, tube_radius: f32) {
+    particles.par_iter_mut().for_each(|particle| {
+        let (position, normal) =
+            closest_on_surface(Vec3::from(particle.position), mode, radius, tube_radius);
+        let velocity = Vec3::from(particle.velocity);
+        particle.position = position.into();
+        particle.velocity = (velocity - normal * velocity.dot(normal)).into();
+    });
+}
@@ -0,0 +1,47 @@
+/// Most steps taken in one frame. Past it the simulation slows down instead of
+/// falling further and further behind.
+pub const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Most sub-steps a step can be split into
+pub const MAX_SUBSTEPS: u32 = 8;
+
+/// Turns frame times into a whole number of steps of fixed length, so the
+/// physics plays out the same at 30 and at 240 FPS
+#[derive(Default)]
+pub struct FixedTimestep {
+    /// Real seconds not yet simulated
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Number of steps of `1 / rate` seconds due after a frame of `delta_time`
+    pub fn advance(&mut self, delta_time: f32, rate: f32) -> u32 {
+        let period = 1.0 / rate;
+        self.accumulator += delta_time;
+        let steps = (self.accumulator / period) as u32;
+        if steps > MAX_STEPS_PER_FRAME {
+            // Drop the backlog but keep the phase, for smooth interpolation
+            self.accumulator %= period;
+            return MAX_STEPS_PER_FRAME;
+        }
+        self.accumulator -= steps as f32 * period;
+        steps
+    }
+
+    /// How far into the next step the frame is, from 0 to 1
+    pub fn alpha(&self, rate: f32) -> f32 {
+        (self.accumulator * rate).clamp(0.0, 1.0)
+    }
+}
+
+/// Sub-steps a step of `step_delta` needs for nothing moving at up to
+/// `max_speed` to travel farther than `max_travel` in one, so fast particles
+/// can't tunnel through colliders. At least `substeps`, at most
+/// [`MAX_SUBSTEPS`].
+pub fn adaptive_substeps(substeps: u32, max_speed: f32, step_delta: f32, max_travel: f32) -> u32 {
+    let needed = (max_speed * step_delta / max_travel.max(f32::EPSILON)).ceil();
+    if needed.is_nan() {
+        return substeps.clamp(1, MAX_SUBSTEPS);
+    }
+    (needed.min(MAX_SUBSTEPS as f32) as u32).clamp(substeps.clamp(1, MAX_SUBSTEPS), MAX_SUBSTEPS)
+}
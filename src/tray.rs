@@ -0,0 +1,87 @@
+use crate::presets::PRESETS;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+const PAUSE_ID: &str = "pause";
+const QUIT_ID: &str = "quit";
+const PRESET_PREFIX: &str = "preset:";
+
+pub enum TrayAction {
+    TogglePause,
+    Preset(usize),
+    Quit,
+}
+
+/// System tray icon with pause/resume, preset selection and quit, so the
+/// window can be controlled without focusing it
+pub struct Tray {
+    // Dropping the icon removes it from the tray
+    _icon: Option<TrayIcon>,
+}
+
+impl Tray {
+    pub fn new() -> Self {
+        // GTK wants the tray on a thread running its own main loop, everywhere
+        // else it has to live on the main thread
+        #[cfg(target_os = "linux")]
+        {
+            std::thread::spawn(|| {
+                if gtk::init().is_err() {
+                    return;
+                }
+                let _icon = build_icon();
+                gtk::main();
+            });
+            Self { _icon: None }
+        }
+        #[cfg(not(target_os = "linux"))]
+        Self {
+            _icon: build_icon(),
+        }
+    }
+
+    /// Next menu entry the user clicked, if any
+    pub fn poll(&self) -> Option<TrayAction> {
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            let id: &str = event.id.as_ref();
+            let action = match id {
+                PAUSE_ID => Some(TrayAction::TogglePause),
+                QUIT_ID => Some(TrayAction::Quit),
+                _ => id
+                    .strip_prefix(PRESET_PREFIX)
+                    .and_then(|index| index.parse().ok())
+                    .map(TrayAction::Preset),
+            };
+            if action.is_some() {
+                return action;
+            }
+        }
+        None
+    }
+}
+
+fn build_icon() -> Option<TrayIcon> {
+    let menu = Menu::new();
+    let presets = Submenu::new("Presets", true);
+    for (index, preset) in PRESETS.iter().enumerate() {
+        let item = MenuItem::with_id(format!("{PRESET_PREFIX}{index}"), preset.name, true, None);
+        presets.append(&item).ok()?;
+    }
+    menu.append_items(&[
+        &MenuItem::with_id(PAUSE_ID, "Pause / Resume", true, None),
+        &presets,
+        &PredefinedMenuItem::separator(),
+        &MenuItem::with_id(QUIT_ID, "Quit", true, None),
+    ])
+    .ok()?;
+
+    let image = eframe::icon_data::from_png_bytes(include_bytes!("../assets/icon-256.png")).ok()?;
+    let icon = Icon::from_rgba(image.rgba, image.width, image.height).ok()?;
+
+    TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("Particle Simulation 3D")
+        .with_icon(icon)
+        .build()
+        .ok()
+}
@@ -0,0 +1,198 @@
+use crate::camera::Camera;
+use crate::simulation::{GenerationSettings, SphereGeneration};
+use glam::Vec3;
+
+/// Arrow length in world units per unit of launch speed
+const ARROW_SCALE: f32 = 2.0;
+const ORBIT_SEGMENTS: usize = 256;
+/// Open orbits are only drawn out to this multiple of the launch radius
+const MAX_OPEN_RADIUS: f32 = 4.0;
+
+/// Kepler orbit through a launch point, ignoring the attractor's softening
+pub struct OrbitPrediction {
+    pub points: Vec<Vec3>,
+    pub eccentricity: f32,
+    /// `None` for unbound (parabolic or hyperbolic) orbits
+    pub period: Option<f32>,
+}
+
+/// Predicts the orbit of a particle at `position` moving with `velocity`
+/// around a point mass at the origin with G·M = `mass`
+pub fn predict_orbit(position: Vec3, velocity: Vec3, mass: f32) -> Option<OrbitPrediction> {
+    let r = position.length();
+    let angular_momentum = position.cross(velocity);
+    let h = angular_momentum.length();
+    if mass <= 0.0 || r <= 0.0 || h <= 1e-6 {
+        // Radial plunge, there's no conic to draw
+        return None;
+    }
+
+    let eccentricity_vector = velocity.cross(angular_momentum) / mass - position / r;
+    let eccentricity = eccentricity_vector.length();
+    let semi_latus_rectum = h * h / mass;
+
+    // Periapsis direction, any in-plane axis will do for circles
+    let x_axis = if eccentricity > 1e-4 {
+        eccentricity_vector / eccentricity
+    } else {
+        position / r
+    };
+    let z_axis = angular_momentum / h;
+    let y_axis = z_axis.cross(x_axis);
+
+    let (max_angle, period) = if eccentricity < 1.0 {
+        let semi_major = semi_latus_rectum / (1.0 - eccentricity * eccentricity);
+        let period = 2.0 * std::f32::consts::PI * (semi_major.powi(3) / mass).sqrt();
+        (std::f32::consts::PI, Some(period))
+    } else {
+        // Stop short of the asymptotes
+        ((-1.0 / eccentricity).acos() * 0.99, None)
+    };
+
+    let max_radius = r * MAX_OPEN_RADIUS;
+    let points = (0..=ORBIT_SEGMENTS)
+        .map(|i| -max_angle + 2.0 * max_angle * i as f32 / ORBIT_SEGMENTS as f32)
+        .filter_map(|angle| {
+            let radius = semi_latus_rectum / (1.0 + eccentricity * angle.cos());
+            (period.is_some() || radius <= max_radius)
+                .then(|| (x_axis * angle.cos() + y_axis * angle.sin()) * radius)
+        })
+        .collect();
+
+    Some(OrbitPrediction {
+        points,
+        eccentricity,
+        period,
+    })
+}
+
+/// Interactive zero-gravity orbit scene: a ring of particles launched
+/// tangentially around a central attractor, with a draggable launch-speed
+/// arrow and the predicted orbit drawn on top
+pub struct OrbitTutorial {
+    pub active: bool,
+    pub radius: f32,
+    pub speed: f32,
+    pub dragging: bool,
+}
+
+impl Default for OrbitTutorial {
+    fn default() -> Self {
+        Self {
+            active: false,
+            radius: 30.0,
+            speed: 3.5,
+            dragging: false,
+        }
+    }
+}
+
+impl OrbitTutorial {
+    /// Launch point of the reference particle the prediction is drawn for
+    pub fn launch_position(&self) -> Vec3 {
+        Vec3::X * self.radius
+    }
+
+    pub fn launch_velocity(&self) -> Vec3 {
+        Vec3::Z * self.speed
+    }
+
+    /// Speeds for a circular and a barely unbound orbit around an attractor
+    /// with G·M = `mass`
+    pub fn circular_speed(&self, mass: f32) -> f32 {
+        (mass / self.radius).sqrt()
+    }
+
+    pub fn escape_speed(&self, mass: f32) -> f32 {
+        (2.0 * mass / self.radius).sqrt()
+    }
+
+    pub fn generation(&self, species_count: u32) -> GenerationSettings {
+        GenerationSettings {
+            mode: SphereGeneration::OrbitRing,
+            species_count,
+            orbit_radius: self.radius,
+            orbit_speed: self.speed,
+            ..Default::default()
+        }
+    }
+
+    pub fn prediction(&self, mass: f32) -> Option<OrbitPrediction> {
+        predict_orbit(self.launch_position(), self.launch_velocity(), mass)
+    }
+
+    /// Draws the attractor, the launch arrow and the predicted orbit over the
+    /// viewport, and lets the arrow tip be dragged to change the launch speed.
+    /// Returns `true` once a drag ends and the particles should be relaunched.
+    pub fn show_overlay(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        camera: &Camera,
+        mass: f32,
+    ) -> bool {
+        let painter = ui.painter_at(rect);
+        let launch = self.launch_position();
+        let direction = self.launch_velocity().normalize_or(Vec3::Z);
+
+        if let Some(center) = camera.world_to_screen(Vec3::ZERO, rect) {
+            painter.circle_filled(center, 6.0, egui::Color32::from_rgb(255, 200, 60));
+        }
+
+        if let Some(prediction) = self.prediction(mass) {
+            let stroke = egui::Stroke::new(1.5_f32, egui::Color32::from_rgb(120, 200, 255));
+            let screen: Vec<_> = prediction
+                .points
+                .iter()
+                .map(|&p| camera.world_to_screen(p, rect))
+                .collect();
+            for pair in screen.windows(2) {
+                if let [Some(a), Some(b)] = pair {
+                    painter.line_segment([*a, *b], stroke);
+                }
+            }
+        }
+
+        let (Some(start), Some(unit_tip)) = (
+            camera.world_to_screen(launch, rect),
+            camera.world_to_screen(launch + direction * ARROW_SCALE, rect),
+        ) else {
+            return false;
+        };
+        let tip = start + (unit_tip - start) * self.speed;
+
+        let handle = ui.interact(
+            egui::Rect::from_center_size(tip, egui::vec2(16.0, 16.0)),
+            ui.id().with("orbit_launch_handle"),
+            egui::Sense::drag(),
+        );
+        if handle.dragged()
+            && let Some(pointer) = handle.interact_pointer_pos()
+        {
+            // Project the pointer onto the arrow's screen direction
+            let per_speed = unit_tip - start;
+            if per_speed.length_sq() > 0.0 {
+                self.speed = ((pointer - start).dot(per_speed) / per_speed.length_sq())
+                    .clamp(0.0, self.escape_speed(mass) * 1.5);
+            }
+        }
+        self.dragging = handle.dragged();
+
+        let color = if handle.hovered() || self.dragging {
+            egui::Color32::WHITE
+        } else {
+            egui::Color32::from_rgb(255, 120, 80)
+        };
+        painter.arrow(start, tip - start, egui::Stroke::new(2.0_f32, color));
+        painter.circle_filled(tip, 5.0, color);
+        painter.text(
+            tip + egui::vec2(8.0, -8.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("v = {:.2}", self.speed),
+            egui::FontId::proportional(14.0),
+            color,
+        );
+
+        handle.drag_stopped()
+    }
+}
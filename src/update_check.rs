@@ -0,0 +1,161 @@
+use serde::Deserialize;
+use std::sync::mpsc::{Receiver, TryRecvError, channel};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/lucascompython/particle-simulation-3d/releases/latest";
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The fields used from GitHub's release JSON
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub body: String,
+    pub html_url: String,
+}
+
+enum State {
+    Idle,
+    Checking(Receiver<Result<Release, String>>),
+    UpToDate,
+    Available(Release),
+    Failed(String),
+}
+
+/// Looks up the latest GitHub release once, when enabled. The only request
+/// is to the GitHub API, carrying nothing but the app name and version.
+pub struct UpdateChecker {
+    pub enabled: bool,
+    state: State,
+    show_changelog: bool,
+    dismissed: bool,
+}
+
+impl Default for UpdateChecker {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            state: State::Idle,
+            show_changelog: false,
+            dismissed: false,
+        }
+    }
+}
+
+impl UpdateChecker {
+    /// Starts the check the first time it's enabled and picks up the answer
+    pub fn update(&mut self) {
+        match &self.state {
+            State::Idle if self.enabled => {
+                let (sender, receiver) = channel();
+                std::thread::spawn(move || sender.send(fetch_latest_release()).ok());
+                self.state = State::Checking(receiver);
+            }
+            State::Checking(receiver) => match receiver.try_recv() {
+                Ok(Ok(release)) if is_newer(&release.tag_name, CURRENT_VERSION) => {
+                    self.state = State::Available(release);
+                }
+                Ok(Ok(_)) => self.state = State::UpToDate,
+                Ok(Err(error)) => self.state = State::Failed(error),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    self.state = State::Failed("The update check stopped".to_owned());
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn status(&self) -> String {
+        match &self.state {
+            State::Idle => format!("Version {CURRENT_VERSION}"),
+            State::Checking(_) => "Checking for updates…".to_owned(),
+            State::UpToDate => format!("Version {CURRENT_VERSION} is the latest"),
+            State::Available(release) => format!("Version {} is available", release.tag_name),
+            State::Failed(error) => format!("Update check failed: {error}"),
+        }
+    }
+
+    /// "New version available" toast and the changelog window
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let State::Available(release) = &self.state else {
+            return;
+        };
+
+        if !self.dismissed {
+            egui::Area::new(egui::Id::new("update_toast"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(format!("Version {} is available", release.tag_name));
+                        ui.horizontal(|ui| {
+                            if ui.button("Changelog").clicked() {
+                                self.show_changelog = true;
+                            }
+                            ui.hyperlink_to("Download", &release.html_url);
+                            if ui.button("Dismiss").clicked() {
+                                self.dismissed = true;
+                            }
+                        });
+                    });
+                });
+        }
+
+        egui::Window::new(format!("What's new in {}", release.tag_name))
+            .open(&mut self.show_changelog)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        show_markdown(ui, &release.body);
+                    });
+                ui.separator();
+                ui.hyperlink_to("Download from GitHub", &release.html_url);
+            });
+    }
+}
+
+fn fetch_latest_release() -> Result<Release, String> {
+    ureq::get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        // GitHub rejects requests without one
+        .header(
+            "User-Agent",
+            concat!("particle-simulation-3d/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()
+        .and_then(|response| response.into_body().read_json())
+        .map_err(|e| e.to_string())
+}
+
+/// Compares `major.minor.patch`, ignoring a leading `v` and any suffix
+fn is_newer(tag: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u32> {
+        version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    parse(tag) > parse(current)
+}
+
+/// Just enough Markdown for release notes: headings, bullets and paragraphs
+fn show_markdown(ui: &mut egui::Ui, text: &str) {
+    for line in text.lines().map(str::trim_end) {
+        if let Some(heading) = line.strip_prefix('#') {
+            ui.strong(heading.trim_start_matches('#').trim());
+        } else if let Some(item) = line
+            .trim_start()
+            .strip_prefix("- ")
+            .or_else(|| line.trim_start().strip_prefix("* "))
+        {
+            ui.label(format!("• {item}"));
+        } else if line.is_empty() {
+            ui.add_space(4.0);
+        } else {
+            ui.label(line);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::format;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Key in eframe's storage
+pub const STORAGE_KEY: &str = "usage";
+
+/// Statistics about how this machine and this user run the app. They only
+/// ever live in eframe's local storage and are never sent anywhere.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    pub sessions: u32,
+    /// Seconds
+    pub total_runtime: f64,
+    pub max_particle_count: u32,
+    /// Best measured particles × frames per second, per backend
+    pub best_throughput: BTreeMap<String, f64>,
+    /// How many times each feature was turned on
+    pub feature_usage: BTreeMap<String, u32>,
+    #[serde(skip)]
+    enabled_features: HashSet<&'static str>,
+}
+
+impl UsageStats {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut stats: Self = storage
+            .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+            .unwrap_or_default();
+        stats.sessions += 1;
+        stats
+    }
+
+    /// Records one frame. `throughput` is `None` while paused, the frame
+    /// rate says nothing about the backend then.
+    pub fn record_frame(
+        &mut self,
+        delta_time: f32,
+        particle_count: u32,
+        backend: &str,
+        throughput: Option<f64>,
+    ) {
+        self.total_runtime += delta_time as f64;
+        self.max_particle_count = self.max_particle_count.max(particle_count);
+        if let Some(throughput) = throughput {
+            let best = self.best_throughput.entry(backend.to_owned()).or_default();
+            *best = best.max(throughput);
+        }
+    }
+
+    /// Counts the features in `features` that are on now but weren't on the
+    /// previous call
+    pub fn record_features(&mut self, features: &[(&'static str, bool)]) {
+        for &(name, enabled) in features {
+            if enabled && self.enabled_features.insert(name) {
+                *self.feature_usage.entry(name.to_owned()).or_default() += 1;
+            } else if !enabled {
+                self.enabled_features.remove(name);
+            }
+        }
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let hours = self.total_runtime / 3600.0;
+        ui.label(format!(
+            "Sessions: {}",
+            format::grouped(self.sessions as u64)
+        ));
+        ui.label(format!("Total runtime: {hours:.1} h"));
+        ui.label(format!(
+            "Most particles: {}",
+            format::count(self.max_particle_count as u64)
+        ));
+        if let Some((backend, throughput)) = self
+            .best_throughput
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+        {
+            ui.label(format!(
+                "Fastest backend: {backend} ({} particles/s)",
+                format::si(*throughput)
+            ))
+            .on_hover_text("Particle count × frames per second, at best");
+        }
+
+        if !self.feature_usage.is_empty() {
+            egui::CollapsingHeader::new("Feature Usage").show(ui, |ui| {
+                let mut usage: Vec<_> = self.feature_usage.iter().collect();
+                usage.sort_by(|a, b| b.1.cmp(a.1));
+                for (feature, count) in usage {
+                    ui.label(format!("{feature}: {count}"));
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::allocation::Checkpoint;
+use crate::settings::Settings;
+use crate::simulation::health::Health;
+use crate::timestep::MAX_SUBSTEPS;
+
+/// Seconds between health checks, GPU backends answer a frame or so late
+/// anyway
+const CHECK_INTERVAL: f32 = 0.5;
+/// Seconds a configuration has to run healthy before it's trusted enough to
+/// roll back to, so a change that takes a while to blow up isn't
+const SETTLE_TIME: f32 = 5.0;
+/// Longest sub-step, in units of the Lennard-Jones time σ·√(m/ε), that
+/// still integrates a collision without the particles passing through
+const LJ_MAX_STEP: f32 = 0.05;
+
+/// A parameter set past what the current sub-step can integrate stably
+pub struct Instability {
+    /// Key of the parameter in the registry
+    pub key: &'static str,
+    pub reason: String,
+    /// Closest value that's stable again
+    pub limit: f32,
+}
+
+/// Parameters known to blow up at sub-steps of `step_delta` over the sub-step
+/// count, with the values that would keep them stable. A rough model, just
+/// the combinations that reliably diverge.
+pub fn instabilities(settings: &Settings, step_delta: f32) -> Vec<Instability> {
+    let dt = step_delta / settings.substeps.clamp(1, MAX_SUBSTEPS) as f32;
+    let mut found = Vec::new();
+
+    if settings.damping > 1.0 {
+        found.push(Instability {
+            key: "damping",
+            reason: "Damping above 1 adds energy every step".to_owned(),
+            limit: 1.0,
+        });
+    }
+
+    // The mouse pulls up to twice its force right at the cursor, which
+    // mustn't carry a particle across the whole radius in one sub-step
+    let mouse_limit = settings.mouse_radius / (2.0 * dt * dt);
+    if settings.mouse_force > mouse_limit {
+        found.push(Instability {
+            key: "mouse_force",
+            reason: "The mouse flings particles through its radius in one step".to_owned(),
+            limit: mouse_limit,
+        });
+    }
+
+    if settings.springs_enabled && settings.spring_stiffness > 1.0 {
+        found.push(Instability {
+            key: "spring_stiffness",
+            reason: "Springs stiffer than 1 overshoot their rest length".to_owned(),
+            limit: 1.0,
+        });
+    }
+
+    if settings.lj_enabled && settings.lj_epsilon > 0.0 {
+        let lj_limit = (LJ_MAX_STEP * settings.lj_sigma / dt).powi(2);
+        if settings.lj_epsilon > lj_limit {
+            found.push(Instability {
+                key: "lj_epsilon",
+                reason: "Particles hit the Lennard-Jones wall too hard for the step".to_owned(),
+                limit: lj_limit,
+            });
+        }
+    }
+
+    // The Berendsen thermostat overcorrects past the target when it relaxes
+    // in less than a step
+    if settings.lj_enabled && settings.thermostat_enabled && settings.thermostat_tau < dt {
+        found.push(Instability {
+            key: "thermostat_tau",
+            reason: "The thermostat relaxes faster than a step".to_owned(),
+            limit: dt,
+        });
+    }
+
+    found
+}
+
+/// What the watchdog caught
+pub struct Divergence {
+    pub health: Health,
+    /// Settings fields that changed since the configuration rolled back to,
+    /// the likely culprits
+    pub changed: Vec<String>,
+    /// The last configuration that ran healthy, `None` if the simulation blew
+    /// up before one settled
+    pub checkpoint: Option<Checkpoint>,
+}
+
+/// Watches the particles for NaNs and runaway positions or speeds, which
+/// otherwise just blank the screen, keeping the last configuration that ran
+/// healthy to go back to
+pub struct Watchdog {
+    pub enabled: bool,
+    /// Skips the stability warnings and limits, for scenes that know better
+    pub expert_mode: bool,
+    /// Pulls unstable parameters back to their limits before every step
+    pub clamp_unstable: bool,
+    timer: f32,
+    /// Trusted configuration to roll back to
+    checkpoint: Option<Checkpoint>,
+    /// Configuration running healthy since the last change, and for how long
+    candidate: Option<(Checkpoint, serde_json::Value, f32)>,
+    pub tripped: Option<Divergence>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            expert_mode: false,
+            clamp_unstable: false,
+            timer: 0.0,
+            checkpoint: None,
+            candidate: None,
+            tripped: None,
+        }
+    }
+
+    /// Whether a check is due after another `delta_time` seconds of running,
+    /// never while tripped
+    pub fn due(&mut self, delta_time: f32) -> bool {
+        if !self.enabled || self.tripped.is_some() {
+            return false;
+        }
+        self.timer += delta_time;
+        if self.timer < CHECK_INTERVAL {
+            return false;
+        }
+        self.timer = 0.0;
+        true
+    }
+
+    /// Takes in a check of the simulation running `current`, returning
+    /// whether it just tripped
+    pub fn report(&mut self, health: Health, current: Checkpoint) -> bool {
+        let value = serde_json::to_value(&current.settings).expect("settings always serialize");
+
+        if health.diverged() {
+            let changed = match &self.checkpoint {
+                Some(checkpoint) => changed_fields(&checkpoint.settings, &value),
+                None => Vec::new(),
+            };
+            self.candidate = None;
+            self.tripped = Some(Divergence {
+                health,
+                changed,
+                checkpoint: self.checkpoint.clone(),
+            });
+            return true;
+        }
+
+        match &mut self.candidate {
+            Some((candidate, settled, healthy_time))
+                if *settled == value && candidate.method == current.method =>
+            {
+                *healthy_time += CHECK_INTERVAL;
+                if *healthy_time >= SETTLE_TIME {
+                    self.checkpoint = Some(candidate.clone());
+                }
+            }
+            _ => self.candidate = Some((current, value, 0.0)),
+        }
+        false
+    }
+
+    /// The last configuration that ran healthy long enough to trust
+    pub fn checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Forgets what tripped it, checking again from the next due check
+    pub fn dismiss(&mut self) {
+        self.tripped = None;
+        self.timer = 0.0;
+    }
+}
+
+/// Top-level fields of `now` that differ from `before`
+fn changed_fields(before: &Settings, now: &serde_json::Value) -> Vec<String> {
+    let before = serde_json::to_value(before).expect("settings always serialize");
+    let (Some(before), Some(now)) = (before.as_object(), now.as_object()) else {
+        return Vec::new();
+    };
+    now.iter()
+        .filter(|(key, value)| before.get(key.as_str()) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
@@ -0,0 +1,185 @@
+use crate::commands::{self, Command};
+use std::cell::RefCell;
+use web_sys::js_sys;
+use web_sys::wasm_bindgen::closure::{Closure, WasmClosure};
+use web_sys::wasm_bindgen::{JsCast as _, JsValue};
+
+/// Whether the page was opened with `?embed=1`
+pub fn embed_requested() -> bool {
+    query_param("embed").is_some_and(|value| value != "0" && value != "false")
+}
+
+/// `name` from the query string of the page URL
+pub fn query_param(name: &str) -> Option<String> {
+    web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get(name))
+}
+
+/// Queues the commands the host page sends with `postMessage`, either as
+/// objects or as JSON strings
+pub fn listen_for_messages() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let on_message = Closure::<dyn FnMut(_)>::new(|event: web_sys::MessageEvent| {
+        let data = event.data();
+        let Some(text) = data
+            .as_string()
+            .or_else(|| js_sys::JSON::stringify(&data).ok().map(String::from))
+        else {
+            return;
+        };
+        // Other scripts and extensions post messages too, those are ignored
+        match Command::from_json(&text) {
+            Ok(command) => commands::push(command),
+            #[cfg(feature = "logs")]
+            Err(error) => log::warn!("{error}"),
+            #[cfg(not(feature = "logs"))]
+            Err(_) => {}
+        }
+    });
+    window
+        .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+        .ok();
+    // Listens for as long as the page is open
+    on_message.forget();
+}
+
+/// Installs `window.particleSimulation`, for scripting the app from the page:
+///
+/// ```js
+/// particleSimulation.set_gravity(2.0);
+/// particleSimulation.set("ground_enabled", true); // any registered parameter
+/// particleSimulation.set_particle_count(50000);
+/// particleSimulation.apply_preset("Plasma");
+/// particleSimulation.get_stats(); // { particles, fps, paused, method, preset }
+/// ```
+///
+/// Calls are queued and run on the next frame.
+pub fn install_api() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let api = js_sys::Object::new();
+
+    add_function(
+        &api,
+        "set_gravity",
+        Closure::<dyn Fn(f32)>::new(|gravity: f32| set_params("gravity", gravity.into())),
+    );
+    add_function(
+        &api,
+        "set",
+        Closure::<dyn Fn(String, JsValue)>::new(|name: String, value: JsValue| {
+            let value = js_sys::JSON::stringify(&value)
+                .ok()
+                .and_then(|text| serde_json::from_str(&String::from(text)).ok());
+            if let Some(value) = value {
+                set_params(&name, value);
+            }
+        }),
+    );
+    add_function(
+        &api,
+        "set_particle_count",
+        Closure::<dyn Fn(u32)>::new(|count: u32| set_params("particle_count", count.into())),
+    );
+    add_function(
+        &api,
+        "apply_preset",
+        Closure::<dyn Fn(String)>::new(|name: String| {
+            commands::push(Command::ApplyPreset { name })
+        }),
+    );
+    add_function(
+        &api,
+        "pause",
+        Closure::<dyn Fn()>::new(|| commands::push(Command::Pause)),
+    );
+    add_function(
+        &api,
+        "resume",
+        Closure::<dyn Fn()>::new(|| commands::push(Command::Resume)),
+    );
+    add_function(
+        &api,
+        "reset",
+        Closure::<dyn Fn()>::new(|| commands::push(Command::Reset)),
+    );
+    add_function(
+        &api,
+        "get_stats",
+        Closure::<dyn Fn() -> JsValue>::new(|| {
+            let stats = serde_json::to_string(&commands::stats()).expect("stats always serialize");
+            js_sys::JSON::parse(&stats).unwrap_or(JsValue::NULL)
+        }),
+    );
+
+    js_sys::Reflect::set(&window, &"particleSimulation".into(), &api).ok();
+}
+
+fn set_params(name: &str, value: serde_json::Value) {
+    commands::push(Command::SetParams {
+        params: serde_json::json!({ name: value }),
+    });
+}
+
+fn add_function<T: WasmClosure + ?Sized>(api: &js_sys::Object, name: &str, function: Closure<T>) {
+    js_sys::Reflect::set(api, &name.into(), function.as_ref()).ok();
+    // Lives for as long as the page is open
+    function.forget();
+}
+
+/// Whether the browser runs on a phone or tablet, and the approximate device
+/// memory in GB where the browser tells (`navigator.deviceMemory`, Chromium only)
+pub fn device_hints() -> (bool, Option<f32>) {
+    let Some(navigator) = web_sys::window().map(|window| window.navigator()) else {
+        return (false, None);
+    };
+    let user_agent = navigator.user_agent().unwrap_or_default();
+    // iPads report a desktop user agent, but have a touch screen
+    let mobile = ["Mobi", "Android", "iPhone", "iPad"]
+        .iter()
+        .any(|hint| user_agent.contains(hint))
+        || (user_agent.contains("Macintosh") && navigator.max_touch_points() > 1);
+    let memory = js_sys::Reflect::get(&navigator, &"deviceMemory".into())
+        .ok()
+        .and_then(|memory| memory.as_f64())
+        .map(|memory| memory as f32);
+    (mobile, memory)
+}
+
+thread_local! {
+    static BATTERY: RefCell<Option<JsValue>> = const { RefCell::new(None) };
+}
+
+/// Starts looking up the battery with `navigator.getBattery()`, which only
+/// Chromium-based browsers have
+pub fn watch_battery() {
+    let Some(navigator) = web_sys::window().map(|window| window.navigator()) else {
+        return;
+    };
+    let Ok(promise) = js_sys::Reflect::get(&navigator, &"getBattery".into())
+        .and_then(|get_battery| get_battery.dyn_into::<js_sys::Function>())
+        .and_then(|get_battery| get_battery.call0(&navigator))
+        .and_then(|promise| promise.dyn_into::<js_sys::Promise>())
+    else {
+        return;
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Ok(battery) = wasm_bindgen_futures::JsFuture::from(promise).await {
+            BATTERY.with(|current| *current.borrow_mut() = Some(battery));
+        }
+    });
+}
+
+/// `None` until the battery is known, or if the browser doesn't tell
+pub fn on_battery() -> Option<bool> {
+    BATTERY.with(|battery| {
+        let battery = battery.borrow();
+        let charging = js_sys::Reflect::get(battery.as_ref()?, &"charging".into()).ok()?;
+        charging.as_bool().map(|charging| !charging)
+    })
+}
@@ -0,0 +1,25 @@
+/// `Camera` and its binding at group 0, for the render shaders
+pub const CAMERA: &str = concat!(include_str!("shaders/camera.wgsl"), "\n");
+/// The species, temperature, age and density color ramps
+pub const COLORS: &str = concat!(include_str!("shaders/colors.wgsl"), "\n");
+/// Distance falloffs of the forces pulling towards points
+pub const FALLOFFS: &str = concat!(include_str!("shaders/falloffs.wgsl"), "\n");
+/// Stateless per-particle random numbers
+pub const RANDOM: &str = concat!(include_str!("shaders/random.wgsl"), "\n");
+
+/// Joins the generated struct declarations, shared libraries and a shader's
+/// own source in `parts` into one module, in order. Parts already included
+/// are skipped, so a library pulled in from more than one place is only
+/// declared once.
+pub fn compose(parts: &[&str]) -> String {
+    let mut included: Vec<&str> = Vec::with_capacity(parts.len());
+    let mut source = String::with_capacity(parts.iter().map(|part| part.len()).sum());
+    for &part in parts {
+        if included.contains(&part) {
+            continue;
+        }
+        included.push(part);
+        source.push_str(part);
+    }
+    source
+}